@@ -6,6 +6,8 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+use chrono::{DateTime, Utc};
+
 use crate::domain::{AccountId, EmailId, LabelId, ThreadId};
 
 /// Domain events for cross-component communication.
@@ -129,9 +131,10 @@ pub enum AiResult {
     /// Summary result.
     Summary {
         thread_id: ThreadId,
-        text: String,
-        key_points: Vec<String>,
+        tldr: String,
         action_items: Vec<String>,
+        key_dates: Vec<(String, DateTime<Utc>)>,
+        participants: Vec<String>,
     },
     /// Draft reply result.
     DraftReply {
@@ -508,9 +511,10 @@ mod tests {
 
         let result = AiResult::Summary {
             thread_id: ThreadId::from("thread-1"),
-            text: "Summary text".to_string(),
-            key_points: vec!["Point 1".to_string()],
+            tldr: "Summary text".to_string(),
             action_items: vec!["Action 1".to_string()],
+            key_dates: vec![("Deadline".to_string(), Utc::now())],
+            participants: vec!["Ada".to_string()],
         };
         assert!(matches!(result, AiResult::Summary { .. }));
     }
@@ -11,14 +11,22 @@ pub mod state;
 
 pub use events::{AppEvent, EventBus};
 pub use state::{
-    AiStatus, AppState, ComposerMode, ComposerState, MessageListState, ReadingPaneState,
-    SyncStatus, ViewType,
+    AiStatus, AppState, ComposerMode, ComposerState, MarkReadSchedule, MarkReadTimer,
+    MessageListState, ReadingPaneState, SyncStatus, ViewType,
 };
 
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::Result;
-use gpui::{actions, AppContext, Application, KeyBinding, WindowOptions};
+use gpui::{actions, AppContext, Application, Global, KeyBinding, WindowOptions};
 
 use crate::ui::MainWindow;
+use crate::MarginClient;
+
+/// How long quitting waits to push queued changes and disconnect providers.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 // Define application actions
 actions!(
@@ -33,14 +41,23 @@ actions!(
         Forward,
         Archive,
         Trash,
+        ReportSpam,
         Star,
         Snooze,
+        FollowUp,
         ApplyLabel,
         MarkRead,
         MarkUnread,
         NextMessage,
         PreviousMessage,
         OpenThread,
+        ExpandAllMessages,
+        CollapseAllMessages,
+        ViewSource,
+        ToggleSelection,
+        SelectNext,
+        SelectPrevious,
+        SelectAll,
         GoToInbox,
         GoToStarred,
         GoToDrafts,
@@ -54,17 +71,66 @@ actions!(
         Search,
         ToggleTheme,
         OpenSettings,
+        NextMisspelling,
+        CorrectSpelling,
     ]
 );
 
+/// The client views read and write mail through, and the app shuts down
+/// through on quit. Absent when the app runs without one.
+pub struct ClientHandle {
+    /// The client.
+    pub client: Arc<MarginClient>,
+    /// Runtime the client's async work runs on.
+    pub runtime: tokio::runtime::Handle,
+}
+
+impl ClientHandle {
+    /// Runs `work` on the client's runtime. The returned future can be
+    /// awaited from a UI task without blocking the UI thread.
+    pub fn spawn<T, F>(&self, work: F) -> impl Future<Output = Result<T>>
+    where
+        F: Future<Output = Result<T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let task = self.runtime.spawn(work);
+        async move {
+            match task.await {
+                Ok(result) => result,
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Global for ClientHandle {}
+
 /// Main application entry point
 pub struct App;
 
 impl App {
     /// Run the application
     pub fn run() -> Result<()> {
-        Application::new().run(|cx: &mut gpui::App| {
+        Self::launch(None)
+    }
+
+    /// Run the application over a client, which is shut down on quit so
+    /// open drafts and queued changes aren't lost. `runtime` runs the
+    /// client's async work.
+    pub fn run_with_client(
+        client: Arc<MarginClient>,
+        runtime: tokio::runtime::Handle,
+    ) -> Result<()> {
+        Self::launch(Some(ClientHandle { client, runtime }))
+    }
+
+    fn launch(client: Option<ClientHandle>) -> Result<()> {
+        Application::new().run(move |cx: &mut gpui::App| {
+            if let Some(client) = client {
+                cx.set_global(client);
+            }
             Self::register_keybindings(cx);
+            cx.on_action(Self::quit);
 
             cx.open_window(WindowOptions::default(), |window, cx| {
                 cx.new(|cx| MainWindow::new(window, cx))
@@ -75,6 +141,21 @@ impl App {
         Ok(())
     }
 
+    /// Shuts the client down, waiting up to [`SHUTDOWN_TIMEOUT`] for the
+    /// network, then exits.
+    fn quit(_: &Quit, cx: &mut gpui::App) {
+        if let Some(handle) = cx.try_global::<ClientHandle>() {
+            match handle
+                .runtime
+                .block_on(handle.client.shutdown(SHUTDOWN_TIMEOUT))
+            {
+                Ok(report) => tracing::info!(?report, "Shut down"),
+                Err(e) => tracing::error!("Shutdown failed: {}", e),
+            }
+        }
+        cx.quit();
+    }
+
     /// Register global keybindings
     fn register_keybindings(cx: &mut gpui::App) {
         // Context for single-letter keybindings that should not fire during text input
@@ -92,14 +173,19 @@ impl App {
             KeyBinding::new("f", Forward, email_ctx),
             KeyBinding::new("e", Archive, email_ctx),
             KeyBinding::new("shift-3", Trash, email_ctx),
+            KeyBinding::new("shift-1", ReportSpam, email_ctx),
             KeyBinding::new("s", Star, email_ctx),
             KeyBinding::new("h", Snooze, email_ctx),
+            KeyBinding::new("shift-h", FollowUp, email_ctx),
             KeyBinding::new("l", ApplyLabel, email_ctx),
             KeyBinding::new("u", MarkRead, email_ctx),
             KeyBinding::new("shift-u", MarkUnread, email_ctx),
             KeyBinding::new("j", NextMessage, email_ctx),
             KeyBinding::new("k", PreviousMessage, email_ctx),
             KeyBinding::new("enter", OpenThread, email_ctx),
+            KeyBinding::new("n", ExpandAllMessages, email_ctx),
+            KeyBinding::new("shift-n", CollapseAllMessages, email_ctx),
+            KeyBinding::new("shift-v", ViewSource, email_ctx),
             KeyBinding::new("g i", GoToInbox, email_ctx),
             KeyBinding::new("g s", GoToStarred, email_ctx),
             KeyBinding::new("g d", GoToDrafts, email_ctx),
@@ -108,11 +194,18 @@ impl App {
             KeyBinding::new("g c", GoToScreener, email_ctx),
             KeyBinding::new("g p", GoToStats, email_ctx),
             KeyBinding::new("a", ScreenerApprove, email_ctx),
-            KeyBinding::new("x", ScreenerReject, email_ctx),
+            KeyBinding::new("x", ScreenerReject, Some("Screener")),
+            KeyBinding::new("x", ToggleSelection, Some("EmailActions && !Screener")),
+            KeyBinding::new("shift-j", SelectNext, email_ctx),
+            KeyBinding::new("shift-k", SelectPrevious, email_ctx),
             KeyBinding::new("/", Search, email_ctx),
             // Cmd-key bindings - global, always available
             KeyBinding::new("cmd-k", OpenCommandPalette, None),
             KeyBinding::new("cmd-,", OpenSettings, None),
+            KeyBinding::new("cmd-a", SelectAll, email_ctx),
+            // Spelling - only active in the composer
+            KeyBinding::new("cmd-;", NextMisspelling, Some("Composer")),
+            KeyBinding::new("cmd-shift-;", CorrectSpelling, Some("Composer")),
         ]);
     }
 }
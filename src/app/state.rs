@@ -4,6 +4,7 @@
 //! current view, selection state, and runtime status.
 
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
 use crate::config::Settings;
 use crate::domain::{AccountId, LabelId, ThreadId};
@@ -373,6 +374,77 @@ impl ReadingPaneState {
     }
 }
 
+/// Tracks the thread waiting to be marked read after it has been open long
+/// enough.
+///
+/// Time is passed in by the caller, so the view drives it from the real
+/// clock and tests from a fake one. Opening another thread or closing the
+/// reading pane before the deadline cancels the pending mark.
+#[derive(Debug, Clone, Default)]
+pub struct MarkReadTimer {
+    /// Dwell time before marking read; `None` disables marking on open.
+    delay: Option<Duration>,
+    /// Thread waiting to be marked read and when it becomes due.
+    pending: Option<(ThreadId, Instant)>,
+}
+
+impl MarkReadTimer {
+    /// Create a timer with the given dwell time.
+    pub fn new(delay: Option<Duration>) -> Self {
+        Self {
+            delay,
+            pending: None,
+        }
+    }
+
+    /// Change the dwell time, cancelling any pending mark.
+    pub fn set_delay(&mut self, delay: Option<Duration>) {
+        self.delay = delay;
+        self.pending = None;
+    }
+
+    /// Record that a thread was opened at `now`.
+    ///
+    /// Any previously opened thread that has not been marked yet stays
+    /// unread. With a zero delay the thread is due immediately; otherwise the
+    /// caller should wait the returned delay and then call [`Self::poll`].
+    pub fn open(&mut self, thread_id: ThreadId, now: Instant) -> MarkReadSchedule {
+        self.pending = None;
+        match self.delay {
+            None => MarkReadSchedule::Never,
+            Some(delay) if delay.is_zero() => MarkReadSchedule::Now(thread_id),
+            Some(delay) => {
+                self.pending = Some((thread_id, now + delay));
+                MarkReadSchedule::After(delay)
+            }
+        }
+    }
+
+    /// Cancel the pending mark, e.g. when the reading pane is closed.
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Returns the pending thread if its deadline has passed at `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<ThreadId> {
+        match &self.pending {
+            Some((_, deadline)) if now >= *deadline => self.pending.take().map(|(id, _)| id),
+            _ => None,
+        }
+    }
+}
+
+/// What to do after opening a thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkReadSchedule {
+    /// Marking read on open is disabled.
+    Never,
+    /// Mark the thread read immediately.
+    Now(ThreadId),
+    /// Poll the timer again after this delay.
+    After(Duration),
+}
+
 /// State for the composer.
 #[derive(Debug, Clone, Default)]
 pub struct ComposerState {
@@ -613,4 +685,54 @@ mod tests {
         assert!(state.ai_suggestion.is_none());
         assert!(state.is_dirty);
     }
+
+    #[test]
+    fn mark_read_after_dwell_time() {
+        let start = Instant::now();
+        let mut timer = MarkReadTimer::new(Some(Duration::from_millis(500)));
+        let id = ThreadId::from("t1");
+
+        assert_eq!(
+            timer.open(id.clone(), start),
+            MarkReadSchedule::After(Duration::from_millis(500))
+        );
+        assert_eq!(timer.poll(start + Duration::from_millis(499)), None);
+        assert_eq!(timer.poll(start + Duration::from_millis(500)), Some(id));
+        assert_eq!(timer.poll(start + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn switching_threads_cancels_pending_mark() {
+        let start = Instant::now();
+        let mut timer = MarkReadTimer::new(Some(Duration::from_millis(500)));
+        let first = ThreadId::from("t1");
+        let second = ThreadId::from("t2");
+
+        timer.open(first, start);
+        timer.open(second.clone(), start + Duration::from_millis(300));
+
+        // The first thread's deadline passes without it being marked.
+        assert_eq!(timer.poll(start + Duration::from_millis(600)), None);
+        assert_eq!(timer.poll(start + Duration::from_millis(800)), Some(second));
+
+        timer.open(ThreadId::from("t3"), start + Duration::from_secs(1));
+        timer.cancel();
+        assert_eq!(timer.poll(start + Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn mark_read_immediately_or_never() {
+        let now = Instant::now();
+        let id = ThreadId::from("t1");
+
+        let mut immediate = MarkReadTimer::new(Some(Duration::ZERO));
+        assert_eq!(
+            immediate.open(id.clone(), now),
+            MarkReadSchedule::Now(id.clone())
+        );
+
+        let mut never = MarkReadTimer::new(None);
+        assert_eq!(never.open(id, now), MarkReadSchedule::Never);
+        assert_eq!(never.poll(now + Duration::from_secs(60)), None);
+    }
 }
@@ -0,0 +1,89 @@
+//! Adapts the local embedding engine to the services' engine trait.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::domain::{Email, EmailId};
+use crate::embedding::{Embedding, EmbeddingEngine as LocalEngine};
+use crate::services::EmbeddingEngine;
+
+/// The local [`embedding`](crate::embedding) engine and its vector store,
+/// usable as the services' [`EmbeddingEngine`].
+pub struct LocalEmbeddings {
+    engine: RwLock<LocalEngine>,
+}
+
+impl LocalEmbeddings {
+    /// Wraps an engine, initialized or not.
+    pub fn new(engine: LocalEngine) -> Self {
+        Self {
+            engine: RwLock::new(engine),
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingEngine for LocalEmbeddings {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        Ok(self.engine.read().await.embed(text)?.values)
+    }
+
+    async fn search(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<(EmailId, f32)>> {
+        let query = Embedding::new(query_embedding.to_vec());
+        self.engine.read().await.search(&query, limit)
+    }
+
+    async fn index_email(&self, email: &Email) -> Result<()> {
+        self.engine.write().await.index_email(email)
+    }
+
+    async fn stored_embedding(&self, email_id: &EmailId) -> Option<Vec<f32>> {
+        let engine = self.engine.read().await;
+        engine
+            .vector_store()
+            .get(email_id)
+            .map(|embedding| embedding.values.clone())
+    }
+
+    async fn remove_emails(&self, email_ids: &[EmailId]) -> Result<usize> {
+        Ok(self
+            .engine
+            .write()
+            .await
+            .vector_store_mut()
+            .remove_all(email_ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::VectorStore;
+
+    #[tokio::test]
+    async fn removing_emails_drops_their_vectors() {
+        let mut store = VectorStore::new();
+        for id in ["a", "b"] {
+            store
+                .insert(&EmailId::from(id), Embedding::new(vec![1.0, 0.0]))
+                .unwrap();
+        }
+        let embeddings = LocalEmbeddings::new(LocalEngine::with_defaults(store));
+
+        let removed = embeddings
+            .remove_emails(&[EmailId::from("a"), EmailId::from("c")])
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(embeddings
+            .stored_embedding(&EmailId::from("a"))
+            .await
+            .is_none());
+        assert!(embeddings
+            .stored_embedding(&EmailId::from("b"))
+            .await
+            .is_some());
+    }
+}
@@ -0,0 +1,1441 @@
+//! Storage for the services backed by the local database.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, OptionalExtension};
+
+use crate::domain::{
+    AccountId, Address, Contact, Email, EmailId, ImportanceWeights, Label, LabelId, MessageId,
+    Thread, ThreadId, ThreadSort, ThreadSummary,
+};
+use crate::services::{
+    AttachmentStorage, BlockAction, BlockEntry, Draft, EmailMetadata, EmailStorage, EmailUpdates,
+    FolderCount, FolderCounts, FollowUp, FtsHit, ImportanceContext, Pagination, PendingChange,
+    SearchFolder, SearchQuery, SearchStorage, SendState, Summary, SummaryCache, SyncState,
+    SyncStorage, ThreadMetadataUpdate, UnsubscribeOutcome, ViewType,
+};
+use crate::storage::queries::{
+    accounts, attachments, blobs, contacts, emails, labels, summaries, threads,
+};
+use crate::storage::{BlobWriter, StorageLayer};
+
+/// Labels that keep a thread out of the archive view.
+const NOT_ARCHIVED: [&str; 3] = ["INBOX", "TRASH", "SPAM"];
+
+/// [`EmailStorage`] and [`SearchStorage`] over a [`StorageLayer`].
+pub struct LocalStore {
+    storage: Arc<StorageLayer>,
+    /// Weights [`ThreadSort::PriorityFirst`] scores importance with.
+    importance: RwLock<ImportanceWeights>,
+}
+
+impl LocalStore {
+    /// Creates a store over the given storage layer.
+    pub fn new(storage: Arc<StorageLayer>) -> Self {
+        Self {
+            storage,
+            importance: RwLock::new(ImportanceWeights::default()),
+        }
+    }
+
+    /// Sets the weights [`ThreadSort::PriorityFirst`] lists are ranked with.
+    pub fn set_importance_weights(&self, weights: ImportanceWeights) {
+        *self.importance.write().unwrap() = weights;
+    }
+
+    /// Collects what the database knows about an account's threads for
+    /// scoring their importance.
+    async fn importance_context(&self, account_id: &AccountId) -> Result<ImportanceContext> {
+        let db = self.storage.db();
+        let contacts = db
+            .with_reader(|conn| Ok(contacts::get_all_by_frequency(conn)?))
+            .await?;
+        let weights = *self.importance.read().unwrap();
+
+        let mut context = ImportanceContext::new(weights).with_contacts(&contacts);
+        context.addressed_to_me = threads::addressed_to_owner(db, account_id)
+            .await?
+            .into_iter()
+            .collect();
+        context.replied = threads::replied_by_owner(db, account_id)
+            .await?
+            .into_iter()
+            .collect();
+        Ok(context)
+    }
+
+    /// Saves a draft, replacing any earlier version with the same ID.
+    pub async fn save_draft(&self, draft: &Draft) -> Result<()> {
+        let id = draft.id.clone().context("Draft has no ID")?;
+        let draft = draft.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO drafts (
+                         id, account_id, reply_to_thread_id, reply_to_message_id,
+                         to_addresses, cc_addresses, bcc_addresses, subject,
+                         body_markdown, body_html, references_json, idempotency_key,
+                         request_read_receipt, created_at, updated_at, from_address,
+                         signature_html
+                     ) VALUES (
+                         ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         ?17
+                     )",
+                    params![
+                        id,
+                        draft.account_id.0,
+                        draft.reply_to_thread_id.as_ref().map(|t| &t.0),
+                        draft.reply_to_message_id,
+                        serde_json::to_string(&draft.to).unwrap_or_default(),
+                        serde_json::to_string(&draft.cc).unwrap_or_default(),
+                        serde_json::to_string(&draft.bcc).unwrap_or_default(),
+                        draft.subject,
+                        draft.body_markdown,
+                        draft.body_html,
+                        serde_json::to_string(&draft.references).unwrap_or_default(),
+                        draft.idempotency_key,
+                        draft.request_read_receipt as i32,
+                        draft.created_at.to_rfc3339(),
+                        draft.updated_at.to_rfc3339(),
+                        draft
+                            .from
+                            .as_ref()
+                            .and_then(|from| serde_json::to_string(from).ok()),
+                        draft.signature_html,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Loads a saved draft.
+    pub async fn load_draft(&self, id: &str) -> Result<Option<Draft>> {
+        let id = id.to_string();
+        let draft = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                let draft = conn
+                    .query_row(
+                        "SELECT id, account_id, reply_to_thread_id, reply_to_message_id,
+                             to_addresses, cc_addresses, bcc_addresses, subject,
+                             body_markdown, body_html, references_json, idempotency_key,
+                             request_read_receipt, created_at, updated_at, from_address,
+                             signature_html
+                         FROM drafts WHERE id = ?1",
+                        params![id],
+                        |row| {
+                            let json = |i: usize| -> rusqlite::Result<String> {
+                                Ok(row.get::<_, Option<String>>(i)?.unwrap_or_default())
+                            };
+                            Ok(Draft {
+                                id: Some(row.get(0)?),
+                                account_id: AccountId(row.get(1)?),
+                                from: row
+                                    .get::<_, Option<String>>(15)?
+                                    .and_then(|json| serde_json::from_str(&json).ok()),
+                                reply_to_thread_id: row.get::<_, Option<String>>(2)?.map(ThreadId),
+                                reply_to_message_id: row.get(3)?,
+                                to: serde_json::from_str(&json(4)?).unwrap_or_default(),
+                                cc: serde_json::from_str(&json(5)?).unwrap_or_default(),
+                                bcc: serde_json::from_str(&json(6)?).unwrap_or_default(),
+                                subject: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+                                body_markdown: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                                body_html: row.get(9)?,
+                                signature_html: row.get(16)?,
+                                references: serde_json::from_str(&json(10)?).unwrap_or_default(),
+                                idempotency_key: row
+                                    .get::<_, Option<String>>(11)?
+                                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                                request_read_receipt: row.get::<_, Option<i32>>(12)? == Some(1),
+                                created_at: parse_timestamp(&row.get::<_, String>(13)?),
+                                updated_at: parse_timestamp(&row.get::<_, String>(14)?),
+                            })
+                        },
+                    )
+                    .optional()?;
+                Ok(draft)
+            })
+            .await?;
+        Ok(draft)
+    }
+
+    /// Queues a change to push to the server later.
+    pub async fn queue_change(&self, change: &PendingChange) -> Result<()> {
+        let change = change.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                let payload = serde_json::to_string(&change.change_type).unwrap_or_default();
+                let change_type = change_type_name(&payload);
+                conn.execute(
+                    "INSERT INTO pending_changes (id, account_id, change_type, payload, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        change.id,
+                        change.account_id.0,
+                        change_type,
+                        payload,
+                        change.created_at.to_rfc3339(),
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Lists queued changes, oldest first. Changes that can't be read are
+    /// skipped.
+    pub async fn pending_changes(&self) -> Result<Vec<PendingChange>> {
+        let rows = self
+            .storage
+            .db()
+            .with_reader(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, account_id, payload, created_at
+                     FROM pending_changes ORDER BY created_at, rowid",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                })?;
+                let rows: std::result::Result<Vec<_>, _> = rows.collect();
+                Ok(rows?)
+            })
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, account_id, payload, created_at)| {
+                let change_type = match serde_json::from_str(&payload) {
+                    Ok(change_type) => change_type,
+                    Err(e) => {
+                        tracing::warn!(change = %id, "Skipping unreadable pending change: {}", e);
+                        return None;
+                    }
+                };
+                Some(PendingChange {
+                    id,
+                    account_id: AccountId(account_id),
+                    change_type,
+                    created_at: parse_timestamp(&created_at),
+                })
+            })
+            .collect())
+    }
+
+    /// Removes a queued change once it has been pushed.
+    pub async fn remove_change(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute("DELETE FROM pending_changes WHERE id = ?1", params![id])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Saves a blocked sender pattern, replacing an entry for the same
+    /// pattern.
+    pub async fn block_sender(&self, entry: &BlockEntry) -> Result<()> {
+        let entry = entry.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO blocked_senders
+                     (pattern, action, report_spam, created_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        entry.pattern,
+                        block_action_name(entry.action),
+                        entry.report_spam,
+                        entry.created_at.to_rfc3339(),
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Lists the blocked sender patterns, oldest first.
+    pub async fn blocked_senders(&self) -> Result<Vec<BlockEntry>> {
+        let rows: Vec<(String, String, bool, String)> = self
+            .storage
+            .db()
+            .with_reader(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT pattern, action, report_spam, created_at
+                     FROM blocked_senders ORDER BY created_at",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?;
+                Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+            })
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(pattern, action, report_spam, created_at)| BlockEntry {
+                pattern,
+                action: match action.as_str() {
+                    "archive" => BlockAction::Archive,
+                    _ => BlockAction::Trash,
+                },
+                report_spam,
+                created_at: parse_timestamp(&created_at),
+            })
+            .collect())
+    }
+}
+
+fn block_action_name(action: BlockAction) -> &'static str {
+    match action {
+        BlockAction::Trash => "trash",
+        BlockAction::Archive => "archive",
+    }
+}
+
+/// Returns the variant name of a serialized [`PendingChangeType`], e.g.
+/// `archive` for `{"archive":{...}}`.
+///
+/// [`PendingChangeType`]: crate::services::PendingChangeType
+fn change_type_name(payload: &str) -> String {
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(payload)
+        .ok()
+        .and_then(|map| map.keys().next().cloned())
+        .unwrap_or_default()
+}
+
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+/// Returns the label holding the threads of a view, or `None` for views
+/// that aren't a single label.
+fn label_for_view(view: &ViewType) -> Option<LabelId> {
+    let label = match view {
+        ViewType::Inbox => "INBOX",
+        ViewType::Sent => "SENT",
+        ViewType::Drafts => "DRAFT",
+        ViewType::Trash => "TRASH",
+        ViewType::Snoozed => "heap/Snoozed",
+        ViewType::Label(label) => return Some(label.clone()),
+        ViewType::Starred | ViewType::Archive | ViewType::All => return None,
+    };
+    Some(LabelId::from(label))
+}
+
+#[async_trait::async_trait]
+impl EmailStorage for LocalStore {
+    async fn get_threads(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        let db = self.storage.db();
+        let fetch = (pagination.offset + pagination.limit) as u32;
+
+        let summaries = match (&view, label_for_view(&view)) {
+            (_, Some(label)) => threads::get_by_label(db, account_id, &label, fetch).await?,
+            (ViewType::Starred, None) => threads::get_starred(db, account_id, fetch).await?,
+            (ViewType::Archive, None) => threads::get_by_account(db, account_id, u32::MAX, 0)
+                .await?
+                .into_iter()
+                .filter(|t| {
+                    !t.labels
+                        .iter()
+                        .any(|l| NOT_ARCHIVED.contains(&l.0.as_str()))
+                })
+                .collect(),
+            _ => threads::get_by_account(db, account_id, fetch, 0).await?,
+        };
+
+        Ok(summaries
+            .into_iter()
+            .skip(pagination.offset)
+            .take(pagination.limit)
+            .collect())
+    }
+
+    async fn get_threads_sorted(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        sort: ThreadSort,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        if sort == ThreadSort::PriorityFirst {
+            // Importance is scored here, so rank the whole view.
+            let everything = Pagination::with_limit(u32::MAX as usize);
+            let mut threads = self.get_threads(account_id, view, everything).await?;
+            let context = self.importance_context(account_id).await?;
+            sort.sort(&mut threads, |t| context.score(t));
+            return Ok(threads
+                .into_iter()
+                .skip(pagination.offset)
+                .take(pagination.limit)
+                .collect());
+        }
+
+        let db = self.storage.db();
+        let (limit, offset) = (pagination.limit as u32, pagination.offset as u32);
+
+        let label = match (&view, label_for_view(&view)) {
+            (_, Some(label)) => Some(label),
+            (ViewType::All, None) => None,
+            _ => {
+                // Views that aren't a label are filtered in memory, so sort
+                // everything and keep the view's threads.
+                let everything = Pagination::with_limit(u32::MAX as usize);
+                let in_view: HashSet<ThreadId> = self
+                    .get_threads(account_id, view, everything)
+                    .await?
+                    .into_iter()
+                    .map(|t| t.id)
+                    .collect();
+                return Ok(threads::list(db, account_id, None, sort, u32::MAX, 0)
+                    .await?
+                    .into_iter()
+                    .filter(|t| in_view.contains(&t.id))
+                    .skip(pagination.offset)
+                    .take(pagination.limit)
+                    .collect());
+            }
+        };
+        Ok(threads::list(db, account_id, label.as_ref(), sort, limit, offset).await?)
+    }
+
+    async fn get_thread(&self, thread_id: &ThreadId) -> Result<Option<Thread>> {
+        let db = self.storage.db();
+        let Some(summary) = threads::get_by_id(db, thread_id).await? else {
+            return Ok(None);
+        };
+        let messages = emails::get_by_thread(db, thread_id).await?;
+        let owner = accounts::get_by_id(db, &summary.account_id)
+            .await?
+            .map(|account| account.email);
+
+        Ok(Some(Thread {
+            id: summary.id,
+            account_id: summary.account_id,
+            subject: summary.subject,
+            snippet: summary.snippet,
+            participants: Thread::ordered_participants(&messages, owner.as_deref()),
+            messages,
+            last_message_date: summary.last_message_date,
+            unread_count: summary.unread_count,
+            is_starred: summary.is_starred,
+            labels: summary.labels,
+        }))
+    }
+
+    async fn store_thread(&self, thread: &Thread) -> Result<()> {
+        let db = self.storage.db();
+        let from = thread
+            .messages
+            .first()
+            .map(|email| email.from.clone())
+            .or_else(|| thread.participants.first().cloned())
+            .unwrap_or_else(|| Address::new("unknown@unknown.com"));
+
+        threads::upsert(
+            db,
+            &ThreadSummary {
+                id: thread.id.clone(),
+                account_id: thread.account_id.clone(),
+                subject: thread.subject.clone(),
+                snippet: thread.snippet.clone(),
+                from,
+                last_message_date: thread.last_message_date,
+                message_count: thread.messages.len() as u32,
+                unread_count: thread.unread_count,
+                is_starred: thread.is_starred,
+                labels: thread.labels.clone(),
+                muted: false,
+                watched: false,
+            },
+        )
+        .await?;
+
+        for email in &thread.messages {
+            if emails::get_by_id(db, &email.id).await?.is_none() {
+                emails::insert(db, email).await?;
+            }
+        }
+        threads::refresh_snippet(db, &thread.id).await?;
+        Ok(())
+    }
+
+    async fn thread_snippets(&self, thread_ids: &[ThreadId]) -> Result<HashMap<ThreadId, String>> {
+        Ok(threads::snippets(self.storage.db(), thread_ids).await?)
+    }
+
+    async fn update_thread_metadata(
+        &self,
+        thread_id: &ThreadId,
+        updates: ThreadMetadataUpdate,
+    ) -> Result<()> {
+        let db = self.storage.db();
+        let Some(summary) = threads::get_by_id(db, thread_id).await? else {
+            return Ok(());
+        };
+
+        if let Some(starred) = updates.is_starred {
+            threads::set_starred(db, thread_id, starred).await?;
+        }
+
+        if let Some(read) = updates.is_read {
+            let messages = emails::get_by_thread(db, thread_id).await?;
+            for email in &messages {
+                emails::set_read(db, &email.id, read).await?;
+            }
+            let unread = if read { 0 } else { messages.len() as u32 };
+            threads::set_unread_count(db, thread_id, unread).await?;
+            threads::refresh_snippet(db, thread_id).await?;
+        }
+
+        if !updates.add_labels.is_empty() || !updates.remove_labels.is_empty() {
+            let mut labels = summary.labels;
+            labels.retain(|l| !updates.remove_labels.contains(l));
+            for label in updates.add_labels {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+            threads::set_labels(db, thread_id, &labels).await?;
+        }
+
+        // Snooze times are kept by the snooze service.
+        Ok(())
+    }
+
+    async fn restore_body(&self, email: &Email) -> Result<()> {
+        Ok(emails::restore_body(
+            self.storage.db(),
+            &email.id,
+            email.body_text.clone(),
+            email.body_html.clone(),
+        )
+        .await?)
+    }
+
+    async fn threads_trashed_before(
+        &self,
+        label: &LabelId,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<ThreadSummary>> {
+        Ok(threads::get_trashed_before(self.storage.db(), label, before).await?)
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        Ok(threads::delete(self.storage.db(), thread_id).await?)
+    }
+
+    async fn thread_for_message(
+        &self,
+        account_id: &AccountId,
+        message_id: &MessageId,
+    ) -> Result<Option<ThreadId>> {
+        Ok(emails::thread_for_message(self.storage.db(), account_id, message_id).await?)
+    }
+
+    async fn folder_counts(&self, account_id: &AccountId) -> Result<FolderCounts> {
+        let counts = threads::label_counts(self.storage.db(), account_id).await?;
+        Ok(FolderCounts::from_labels(counts.into_iter().map(
+            |(label, unread, total)| (label, FolderCount { unread, total }),
+        )))
+    }
+
+    async fn get_labels(&self, account_id: &AccountId) -> Result<Vec<Label>> {
+        let account_id = account_id.clone();
+        Ok(self
+            .storage
+            .db()
+            .with_reader(move |conn| Ok(labels::get_by_account(conn, &account_id)?))
+            .await?)
+    }
+
+    async fn get_contacts(&self, account_id: &AccountId) -> Result<Vec<Contact>> {
+        let account_id = account_id.clone();
+        Ok(self
+            .storage
+            .db()
+            .with_reader(move |conn| Ok(contacts::get_by_account(conn, &account_id)?))
+            .await?)
+    }
+
+    async fn get_attachment_data(
+        &self,
+        email_id: &EmailId,
+        attachment_id: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let stored = attachments::get_for_email(self.storage.db(), email_id).await?;
+        let Some(hash) = stored
+            .into_iter()
+            .find(|attachment| attachment.id == attachment_id)
+            .and_then(|attachment| attachment.content_hash)
+        else {
+            return Ok(None);
+        };
+        Ok(self.storage.load_blob(&hash).await?)
+    }
+
+    async fn store_attachment_data(
+        &self,
+        _email_id: &EmailId,
+        attachment_id: &str,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        // The blob is kept alive by the attachment referencing its hash.
+        let hash = self.storage.store_blob(data).await?;
+        attachments::set_content_hash(self.storage.db(), attachment_id, &hash).await?;
+        Ok(())
+    }
+
+    async fn store_attachment_from(
+        &self,
+        _email_id: &EmailId,
+        attachment_id: &str,
+        writer: BlobWriter,
+    ) -> Result<Option<String>> {
+        let hash = self.storage.store_blob_from(writer).await?;
+        attachments::set_content_hash(self.storage.db(), attachment_id, &hash).await?;
+        Ok(Some(hash))
+    }
+
+    async fn send_state(&self, idempotency_key: &str) -> Result<Option<SendState>> {
+        let key = idempotency_key.to_string();
+        let state: Option<String> = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT state FROM send_states WHERE idempotency_key = ?1",
+                        params![key],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        state
+            .map(|state| serde_json::from_str(&state).context("Invalid send state"))
+            .transpose()
+    }
+
+    async fn set_send_state(&self, idempotency_key: &str, state: Option<SendState>) -> Result<()> {
+        let key = idempotency_key.to_string();
+        let state = state
+            .map(|state| serde_json::to_string(&state))
+            .transpose()?;
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                match state {
+                    Some(state) => conn.execute(
+                        "INSERT OR REPLACE INTO send_states (idempotency_key, state, updated_at)
+                         VALUES (?1, ?2, ?3)",
+                        params![key, state, Utc::now().to_rfc3339()],
+                    )?,
+                    None => conn.execute(
+                        "DELETE FROM send_states WHERE idempotency_key = ?1",
+                        params![key],
+                    )?,
+                };
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn get_followups(&self) -> Result<Vec<(ThreadId, FollowUp)>> {
+        let rows: Vec<(String, String, String)> = self
+            .storage
+            .db()
+            .with_reader(|conn| {
+                let mut stmt = conn.prepare("SELECT thread_id, set_at, due_at FROM followups")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+                Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+            })
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(thread_id, set_at, due_at)| {
+                let followup = FollowUp {
+                    set_at: parse_timestamp(&set_at),
+                    due_at: parse_timestamp(&due_at),
+                };
+                (ThreadId(thread_id), followup)
+            })
+            .collect())
+    }
+
+    async fn set_followup(&self, thread_id: &ThreadId, followup: Option<FollowUp>) -> Result<()> {
+        let thread_id = thread_id.0.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                match followup {
+                    Some(followup) => conn.execute(
+                        "INSERT OR REPLACE INTO followups (thread_id, set_at, due_at)
+                         VALUES (?1, ?2, ?3)",
+                        params![
+                            thread_id,
+                            followup.set_at.to_rfc3339(),
+                            followup.due_at.to_rfc3339()
+                        ],
+                    )?,
+                    None => conn.execute(
+                        "DELETE FROM followups WHERE thread_id = ?1",
+                        params![thread_id],
+                    )?,
+                };
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe_outcome(
+        &self,
+        account_id: &AccountId,
+        sender: &str,
+    ) -> Result<Option<UnsubscribeOutcome>> {
+        let (account_id, sender) = (account_id.0.clone(), sender.to_string());
+        let outcome: Option<String> = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT outcome FROM unsubscribes WHERE account_id = ?1 AND sender = ?2",
+                        params![account_id, sender],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        outcome
+            .map(|outcome| serde_json::from_str(&outcome).context("Invalid unsubscribe outcome"))
+            .transpose()
+    }
+
+    async fn set_unsubscribe_outcome(
+        &self,
+        account_id: &AccountId,
+        sender: &str,
+        outcome: &UnsubscribeOutcome,
+    ) -> Result<()> {
+        let (account_id, sender) = (account_id.0.clone(), sender.to_string());
+        let outcome = serde_json::to_string(outcome)?;
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO unsubscribes (account_id, sender, outcome, updated_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![account_id, sender, outcome, Utc::now().to_rfc3339()],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentStorage for LocalStore {
+    async fn load_blob(&self, content_hash: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.storage.load_blob(content_hash).await?)
+    }
+
+    async fn load_thumbnail(&self, content_hash: &str, size: u32) -> Result<Option<Vec<u8>>> {
+        Ok(blobs::load_thumbnail(self.storage.db(), content_hash, size).await?)
+    }
+
+    async fn store_thumbnail(&self, content_hash: &str, size: u32, png: &[u8]) -> Result<()> {
+        Ok(blobs::store_thumbnail(self.storage.db(), content_hash, size, png.to_vec()).await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl SummaryCache for LocalStore {
+    async fn get(&self, thread_id: &ThreadId, content_hash: &str) -> Result<Option<Summary>> {
+        let (thread_id, content_hash) = (thread_id.clone(), content_hash.to_string());
+        let summary = self
+            .storage
+            .db()
+            .with_reader(move |conn| Ok(summaries::get(conn, &thread_id, &content_hash)?))
+            .await?;
+        summary
+            .map(|summary| serde_json::from_str(&summary).context("Invalid cached summary"))
+            .transpose()
+    }
+
+    async fn put(&self, thread_id: &ThreadId, content_hash: &str, summary: &Summary) -> Result<()> {
+        let (thread_id, content_hash) = (thread_id.clone(), content_hash.to_string());
+        let summary = serde_json::to_string(summary)?;
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                Ok(summaries::upsert(
+                    conn,
+                    &thread_id,
+                    &content_hash,
+                    &summary,
+                )?)
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn invalidate(&self, thread_id: &ThreadId) -> Result<()> {
+        let thread_id = thread_id.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| Ok(summaries::delete(conn, &thread_id)?))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncStorage for LocalStore {
+    async fn get_sync_state(&self, account_id: &AccountId) -> Result<SyncState> {
+        let account_id = account_id.0.clone();
+        let state = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT last_sync, last_history_id, last_uid_validity, last_uid
+                         FROM sync_state WHERE account_id = ?1",
+                        params![account_id],
+                        |row| {
+                            Ok(SyncState {
+                                last_sync: row
+                                    .get::<_, Option<String>>(0)?
+                                    .map(|s| parse_timestamp(&s)),
+                                last_history_id: row.get(1)?,
+                                last_uid_validity: row.get(2)?,
+                                last_uid: row.get(3)?,
+                            })
+                        },
+                    )
+                    .optional()?)
+            })
+            .await?;
+        Ok(state.unwrap_or_default())
+    }
+
+    async fn update_sync_state(&self, account_id: &AccountId, state: SyncState) -> Result<()> {
+        let account_id = account_id.0.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO sync_state
+                     (account_id, last_sync, last_history_id, last_uid_validity, last_uid)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        account_id,
+                        state.last_sync.map(|t| t.to_rfc3339()),
+                        state.last_history_id,
+                        state.last_uid_validity,
+                        state.last_uid,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn get_pending_changes(&self, account_id: &AccountId) -> Result<Vec<PendingChange>> {
+        let mut changes = self.pending_changes().await?;
+        changes.retain(|change| change.account_id == *account_id);
+        Ok(changes)
+    }
+
+    async fn mark_change_synced(&self, change_id: &str) -> Result<()> {
+        self.remove_change(change_id).await
+    }
+
+    async fn insert_email(&self, email: &Email) -> Result<()> {
+        let db = self.storage.db();
+        if emails::get_by_id(db, &email.id).await?.is_some() {
+            return Ok(());
+        }
+
+        let mut summary = threads::get_by_id(db, &email.thread_id)
+            .await?
+            .unwrap_or_else(|| ThreadSummary {
+                id: email.thread_id.clone(),
+                account_id: email.account_id.clone(),
+                subject: email.subject.clone(),
+                snippet: email.snippet.clone(),
+                from: email.from.clone(),
+                last_message_date: email.date,
+                message_count: 0,
+                unread_count: 0,
+                is_starred: false,
+                labels: Vec::new(),
+                muted: false,
+                watched: false,
+            });
+        summary.message_count += 1;
+        summary.unread_count += u32::from(!email.is_read);
+        summary.is_starred |= email.is_starred;
+        summary.last_message_date = summary.last_message_date.max(email.date);
+        for label in &email.labels {
+            if !summary.labels.contains(label) {
+                summary.labels.push(label.clone());
+            }
+        }
+
+        threads::upsert(db, &summary).await?;
+        emails::insert(db, email).await?;
+        threads::refresh_snippet(db, &email.thread_id).await?;
+        Ok(())
+    }
+
+    async fn update_email(&self, email_id: &EmailId, updates: &EmailUpdates) -> Result<()> {
+        let db = self.storage.db();
+        let Some(email) = emails::get_by_id(db, email_id).await? else {
+            return Ok(());
+        };
+        let thread_id = &email.thread_id;
+
+        if let Some(read) = updates.is_read {
+            emails::set_read(db, email_id, read).await?;
+            let unread = emails::count_unread_in_thread(db, thread_id).await?;
+            threads::set_unread_count(db, thread_id, unread).await?;
+            threads::refresh_snippet(db, thread_id).await?;
+        }
+        if let Some(starred) = updates.is_starred {
+            emails::set_starred(db, email_id, starred).await?;
+            let starred = emails::get_by_thread(db, thread_id)
+                .await?
+                .iter()
+                .any(|m| m.is_starred);
+            threads::set_starred(db, thread_id, starred).await?;
+        }
+
+        let labels = |names: &[String]| names.iter().map(|l| LabelId::from(l.as_str())).collect();
+        self.update_thread_metadata(
+            thread_id,
+            ThreadMetadataUpdate {
+                add_labels: labels(&updates.add_labels),
+                remove_labels: labels(&updates.remove_labels),
+                ..ThreadMetadataUpdate::default()
+            },
+        )
+        .await
+    }
+
+    async fn delete_email(&self, email_id: &EmailId) -> Result<()> {
+        let db = self.storage.db();
+        let Some(email) = emails::get_by_id(db, email_id).await? else {
+            return Ok(());
+        };
+        emails::delete(db, email_id).await?;
+        if emails::count_in_thread(db, &email.thread_id).await? == 0 {
+            threads::delete(db, &email.thread_id).await?;
+        } else {
+            let unread = emails::count_unread_in_thread(db, &email.thread_id).await?;
+            threads::set_unread_count(db, &email.thread_id, unread).await?;
+            threads::refresh_snippet(db, &email.thread_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn is_thread_muted(&self, thread_id: &ThreadId) -> Result<bool> {
+        let thread = threads::get_by_id(self.storage.db(), thread_id).await?;
+        Ok(thread.is_some_and(|t| t.muted))
+    }
+
+    async fn is_thread_watched(&self, thread_id: &ThreadId) -> Result<bool> {
+        let thread = threads::get_by_id(self.storage.db(), thread_id).await?;
+        Ok(thread.is_some_and(|t| t.watched))
+    }
+
+    async fn thread_senders(&self, thread_id: &ThreadId) -> Result<Vec<Address>> {
+        let messages = emails::get_by_thread(self.storage.db(), thread_id).await?;
+        Ok(messages.into_iter().map(|m| m.from).collect())
+    }
+}
+
+/// Builds an FTS5 query matching every word of `text`, or `None` if there
+/// are no words. Words are quoted so punctuation isn't read as syntax.
+fn fts_pattern(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Appends the filters of `query` to an FTS query over emails `e` joined
+/// with their threads `t`.
+fn push_filters(query: &SearchQuery, sql: &mut String, params: &mut Vec<Value>) {
+    if !query.account_ids.is_empty() {
+        let placeholders = vec!["?"; query.account_ids.len()].join(", ");
+        sql.push_str(&format!(" AND e.account_id IN ({})", placeholders));
+        params.extend(query.account_ids.iter().map(|id| Value::Text(id.0.clone())));
+    }
+    if let Some(from) = &query.from {
+        sql.push_str(" AND (e.from_address LIKE ? OR e.from_name LIKE ?)");
+        params.push(Value::Text(format!("%{}%", from)));
+        params.push(Value::Text(format!("%{}%", from)));
+    }
+    if let Some(to) = &query.to {
+        sql.push_str(" AND e.to_addresses LIKE ?");
+        params.push(Value::Text(format!("%{}%", to)));
+    }
+    if let Some(unread) = query.is_unread {
+        sql.push_str(" AND e.is_read = ?");
+        params.push(Value::Integer(!unread as i64));
+    }
+    if let Some(starred) = query.is_starred {
+        sql.push_str(" AND (e.is_starred = ? OR t.is_starred = ?)");
+        params.push(Value::Integer(starred as i64));
+        params.push(Value::Integer(starred as i64));
+    }
+    if let Some(has_attachment) = query.has_attachment {
+        sql.push_str(if has_attachment {
+            " AND EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = e.id)"
+        } else {
+            " AND NOT EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = e.id)"
+        });
+    }
+    if let Some(range) = &query.date_range {
+        sql.push_str(" AND e.date >= ? AND e.date <= ?");
+        params.push(Value::Text(range.start.to_rfc3339()));
+        params.push(Value::Text(range.end.to_rfc3339()));
+    }
+
+    let name = match &query.folder {
+        None | Some(SearchFolder::All) => None,
+        Some(SearchFolder::Inbox) => Some("INBOX"),
+        Some(SearchFolder::Sent) => Some("SENT"),
+        Some(SearchFolder::Trash) => Some("TRASH"),
+        Some(SearchFolder::Label(name)) => Some(name.as_str()),
+        Some(SearchFolder::Drafts) => {
+            sql.push_str(" AND e.is_draft = 1");
+            None
+        }
+        Some(SearchFolder::Archive) => {
+            for name in NOT_ARCHIVED {
+                sql.push_str(" AND t.labels NOT LIKE ?");
+                params.push(Value::Text(format!("%\"{}\"%", name)));
+            }
+            None
+        }
+    };
+    if let Some(name) = name {
+        sql.push_str(" AND t.labels LIKE ?");
+        params.push(Value::Text(format!("%\"{}\"%", name)));
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchStorage for LocalStore {
+    async fn fts_search(&self, query: &SearchQuery) -> Result<Vec<FtsHit>> {
+        let Some(pattern) = fts_pattern(&query.text) else {
+            return Ok(Vec::new());
+        };
+
+        let mut sql = String::from(
+            "SELECT e.id, e.thread_id, bm25(emails_fts),
+                 snippet(emails_fts, 1, '', '', '…', 16)
+             FROM emails_fts
+             JOIN emails e ON e.rowid = emails_fts.rowid
+             LEFT JOIN threads t ON t.id = e.thread_id
+             WHERE emails_fts MATCH ?",
+        );
+        let mut params = vec![Value::Text(pattern)];
+        push_filters(query, &mut sql, &mut params);
+        sql.push_str(" ORDER BY bm25(emails_fts) LIMIT ?");
+        params.push(Value::Integer((query.offset + query.limit) as i64));
+
+        let rows = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                let mut stmt = conn.prepare(&sql)?;
+                let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+                    Ok((
+                        EmailId(row.get(0)?),
+                        ThreadId(row.get(1)?),
+                        row.get::<_, f64>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })?;
+                let rows: std::result::Result<Vec<_>, _> = rows.collect();
+                Ok(rows?)
+            })
+            .await?;
+
+        // BM25 scores are negative, lower being better. Scale them so the
+        // best match ranks 1.0.
+        let best = rows.first().map_or(0.0, |row| row.2);
+        Ok(rows
+            .into_iter()
+            .map(|(email_id, thread_id, score, snippet)| FtsHit {
+                email_id,
+                thread_id,
+                rank: if best < 0.0 {
+                    (score / best) as f32
+                } else {
+                    1.0
+                },
+                snippet: snippet.unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn get_email_metadata(&self, ids: &[EmailId]) -> Result<Vec<EmailMetadata>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT id, thread_id, subject, snippet, from_address, from_name, date, is_read
+             FROM emails WHERE id IN ({})",
+            vec!["?"; ids.len()].join(", ")
+        );
+        let params: Vec<Value> = ids.iter().map(|id| Value::Text(id.0.clone())).collect();
+
+        let metadata = self
+            .storage
+            .db()
+            .with_reader(move |conn| {
+                let mut stmt = conn.prepare(&sql)?;
+                let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+                    let from = Address {
+                        email: row.get(4)?,
+                        name: row.get(5)?,
+                    };
+                    let date: String = row.get(6)?;
+                    Ok(EmailMetadata {
+                        email_id: EmailId(row.get(0)?),
+                        thread_id: ThreadId(row.get(1)?),
+                        subject: row.get(2)?,
+                        snippet: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                        from: from.display(),
+                        date: DateTime::parse_from_rfc3339(&date)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                        is_read: row.get::<_, i32>(7)? != 0,
+                    })
+                })?;
+                let metadata: std::result::Result<Vec<_>, _> = rows.collect();
+                Ok(metadata?)
+            })
+            .await?;
+        Ok(metadata)
+    }
+
+    /// Rebuilds the whole index; it isn't partitioned by account.
+    async fn rebuild_fts_index(&self, _account_id: &AccountId) -> Result<()> {
+        self.storage
+            .db()
+            .with_conn(|conn| {
+                conn.execute("INSERT INTO emails_fts(emails_fts) VALUES ('rebuild')", [])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn get_thread_emails(&self, thread_id: &ThreadId) -> Result<Vec<Email>> {
+        Ok(emails::get_by_thread(self.storage.db(), thread_id).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fts_pattern_quotes_words() {
+        assert_eq!(
+            fts_pattern("weekly  \"digest\" c++").as_deref(),
+            Some("\"weekly\" \"\"\"digest\"\"\" \"c++\"")
+        );
+        assert_eq!(fts_pattern("   "), None);
+    }
+
+    #[test]
+    fn archive_view_has_no_single_label() {
+        assert_eq!(
+            label_for_view(&ViewType::Inbox),
+            Some(LabelId::from("INBOX"))
+        );
+        assert_eq!(label_for_view(&ViewType::Archive), None);
+        assert_eq!(
+            label_for_view(&ViewType::Label(LabelId::from("work"))),
+            Some(LabelId::from("work"))
+        );
+    }
+
+    #[test]
+    fn change_type_name_is_the_variant_key() {
+        let payload = serde_json::to_string(&crate::services::PendingChangeType::MarkRead {
+            thread_id: "thread-1".to_string(),
+            read: true,
+        })
+        .unwrap();
+        assert_eq!(change_type_name(&payload), "mark_read");
+        assert_eq!(change_type_name("not json"), "");
+    }
+
+    #[tokio::test]
+    async fn send_states_are_saved_and_forgotten() {
+        let store = LocalStore::new(StorageLayer::in_memory().await.unwrap().into_arc());
+        let sent = SendState::Sent(EmailId::from("sent-1"));
+
+        store
+            .set_send_state("key-1", Some(SendState::InFlight))
+            .await
+            .unwrap();
+        store
+            .set_send_state("key-1", Some(sent.clone()))
+            .await
+            .unwrap();
+        assert_eq!(store.send_state("key-1").await.unwrap(), Some(sent));
+
+        store.set_send_state("key-1", None).await.unwrap();
+        assert_eq!(store.send_state("key-1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_outcomes_are_saved_per_account() {
+        use crate::storage::queries::test_support::{self, account_id, Dataset};
+
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        test_support::seed(storage.db(), &Dataset::generate(2, 1))
+            .await
+            .unwrap();
+        let store = LocalStore::new(storage);
+        let mailto = UnsubscribeOutcome::Mailto("mailto:leave@example.com".to_string());
+
+        store
+            .set_unsubscribe_outcome(&account_id(1), "news@example.com", &mailto)
+            .await
+            .unwrap();
+        store
+            .set_unsubscribe_outcome(
+                &account_id(1),
+                "news@example.com",
+                &UnsubscribeOutcome::Completed,
+            )
+            .await
+            .unwrap();
+
+        let outcome = |n| {
+            let store = &store;
+            async move {
+                store
+                    .unsubscribe_outcome(&account_id(n), "news@example.com")
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(outcome(1).await, Some(UnsubscribeOutcome::Completed));
+        assert_eq!(outcome(2).await, None);
+    }
+
+    #[tokio::test]
+    async fn summaries_are_cached_by_content_hash() {
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        let store = LocalStore::new(storage);
+        let thread_id = ThreadId::from("thread-1");
+        let summary = Summary {
+            tldr: "Launch moved to Friday.".to_string(),
+            action_items: vec!["Update the release notes".to_string()],
+            ..Summary::default()
+        };
+
+        SummaryCache::put(&store, &thread_id, "hash-1", &summary)
+            .await
+            .unwrap();
+        let cached = SummaryCache::get(&store, &thread_id, "hash-1")
+            .await
+            .unwrap();
+        assert_eq!(cached, Some(summary));
+        let stale = SummaryCache::get(&store, &thread_id, "hash-2")
+            .await
+            .unwrap();
+        assert_eq!(stale, None);
+
+        SummaryCache::invalidate(&store, &thread_id).await.unwrap();
+        let cached = SummaryCache::get(&store, &thread_id, "hash-1")
+            .await
+            .unwrap();
+        assert_eq!(cached, None);
+    }
+
+    #[tokio::test]
+    async fn synced_mail_joins_its_thread() {
+        use crate::domain::test_email;
+        use crate::storage::queries::test_support::{self, Dataset};
+
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        test_support::seed(storage.db(), &Dataset::generate(1, 0))
+            .await
+            .unwrap();
+        let store = LocalStore::new(storage);
+        let thread_id = ThreadId::from("thread-1");
+        let counts = || async {
+            threads::get_by_id(store.storage.db(), &thread_id)
+                .await
+                .unwrap()
+                .map(|t| (t.message_count, t.unread_count))
+        };
+
+        for (id, is_read) in [("email-1", true), ("email-2", false), ("email-2", false)] {
+            let email = Email {
+                is_read,
+                ..test_email(id, "thread-1")
+            };
+            store.insert_email(&email).await.unwrap();
+        }
+        assert_eq!(counts().await, Some((2, 1)));
+
+        let read = EmailUpdates {
+            is_read: Some(true),
+            ..EmailUpdates::default()
+        };
+        store
+            .update_email(&EmailId::from("email-2"), &read)
+            .await
+            .unwrap();
+        assert_eq!(counts().await, Some((2, 0)));
+
+        for id in ["email-1", "email-2"] {
+            store.delete_email(&EmailId::from(id)).await.unwrap();
+        }
+        assert_eq!(counts().await, None);
+    }
+
+    #[tokio::test]
+    async fn sync_state_is_saved_per_account() {
+        use crate::storage::queries::test_support::{self, account_id, Dataset};
+
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        test_support::seed(storage.db(), &Dataset::generate(2, 0))
+            .await
+            .unwrap();
+        let store = LocalStore::new(storage);
+        assert!(store
+            .get_sync_state(&account_id(1))
+            .await
+            .unwrap()
+            .last_sync
+            .is_none());
+
+        let synced_at = Utc::now();
+        let state = SyncState {
+            last_sync: Some(synced_at),
+            last_history_id: Some("42".to_string()),
+            ..SyncState::default()
+        };
+        store
+            .update_sync_state(&account_id(1), state)
+            .await
+            .unwrap();
+
+        let saved = store.get_sync_state(&account_id(1)).await.unwrap();
+        assert_eq!(
+            saved.last_sync.map(|t| t.timestamp()),
+            Some(synced_at.timestamp())
+        );
+        assert_eq!(saved.last_history_id.as_deref(), Some("42"));
+        let other = store.get_sync_state(&account_id(2)).await.unwrap();
+        assert!(other.last_sync.is_none());
+    }
+
+    #[tokio::test]
+    async fn blocking_a_sender_again_replaces_the_entry() {
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        let store = LocalStore::new(storage);
+        let entry = BlockEntry {
+            pattern: "spam@example.com".to_string(),
+            action: BlockAction::Trash,
+            report_spam: false,
+            created_at: Utc::now(),
+        };
+        store.block_sender(&entry).await.unwrap();
+        let archived = BlockEntry {
+            action: BlockAction::Archive,
+            report_spam: true,
+            ..entry
+        };
+        store.block_sender(&archived).await.unwrap();
+
+        let blocked = store.blocked_senders().await.unwrap();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].pattern, "spam@example.com");
+        assert_eq!(blocked[0].action, BlockAction::Archive);
+        assert!(blocked[0].report_spam);
+    }
+
+    #[tokio::test]
+    async fn search_filters_by_label() {
+        use crate::storage::queries::test_support::{self, account_id, label_id, Dataset};
+
+        let storage = StorageLayer::in_memory().await.unwrap().into_arc();
+        test_support::seed(storage.db(), &Dataset::generate(2, 6))
+            .await
+            .unwrap();
+        let store = LocalStore::new(storage);
+        let account = account_id(1);
+        let search = |folder: Option<LabelId>| {
+            let mut query = SearchQuery::new("Project update").with_accounts(vec![account.clone()]);
+            if let Some(label) = folder {
+                query = query.with_folder(SearchFolder::Label(label.0));
+            }
+            let store = &store;
+            async move {
+                let hits = store.fts_search(&query).await.unwrap();
+                let mut ids: Vec<String> = hits.into_iter().map(|hit| hit.thread_id.0).collect();
+                ids.sort();
+                ids.dedup();
+                ids
+            }
+        };
+
+        assert_eq!(
+            search(None).await,
+            [
+                "account-1-thread-00",
+                "account-1-thread-02",
+                "account-1-thread-04"
+            ]
+        );
+        let receipts = label_id(&account, "receipts");
+        assert_eq!(search(Some(receipts)).await, ["account-1-thread-00"]);
+        let other_accounts_label = label_id(&account_id(2), "work");
+        assert!(search(Some(other_accounts_label)).await.is_empty());
+    }
+}
@@ -0,0 +1,731 @@
+//! Headless client for scripting and automation.
+//!
+//! [`MarginClient`] composes the storage layer and services without the
+//! gpui UI, so the crate can be driven from scripts (e.g. a nightly job that
+//! archives newsletters) and tested end to end without a window.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use heap::services::{Pagination, SearchQuery, ViewType};
+//! use heap::MarginClient;
+//!
+//! let client = MarginClient::open("heap.db").await?;
+//! let results = client.search(SearchQuery::new("newsletter")).await?;
+//! let thread_ids: Vec<_> = results.hits.into_iter().map(|hit| hit.thread_id).collect();
+//! client.archive(&thread_ids).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod embeddings;
+mod local_store;
+mod provider;
+mod server_search;
+mod thread_store;
+
+pub use embeddings::LocalEmbeddings;
+pub use local_store::LocalStore;
+pub use provider::ConnectedProvider;
+pub use server_search::ProviderSearch;
+pub use thread_store::ThreadStore;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::config::{NotificationSettings, PrivacySettings, Settings};
+use crate::domain::{
+    Account, AccountId, Address, Email, EmailId, ImportanceWeights, Label, LabelId, Thread,
+    ThreadId, ThreadSort, ThreadSummary,
+};
+use crate::providers::email::EmailProvider as RemoteProvider;
+use crate::providers::http;
+use crate::services::{
+    provider_for_account, AiService, AttachmentService, BlockAction, BlockEntry, Blocklist, Draft,
+    EmailProvider, EmailService, ImageBytes, NotificationService,
+    NotificationSettings as NotifierSettings, Pagination, PdfRenderer, PdftoppmRenderer,
+    PendingChange, PendingChangeType, SearchQuery, SearchResults, SearchService, SendHandle,
+    SendOptions, ServiceBus, Summary, SyncService, SyncSettings, ThreadService, UndoService,
+    UndoableAction, ViewType,
+};
+use crate::storage::queries::{accounts, labels};
+use crate::storage::StorageLayer;
+
+/// How often [`MarginClient::start_purging`] purges expired trash and spam.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often [`MarginClient::start_evicting`] evicts old message bodies.
+const EVICT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often due follow-up reminders are checked for.
+const FOLLOWUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What [`MarginClient::shutdown`] managed to do.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Open drafts saved to the database.
+    pub drafts_saved: usize,
+    /// Queued changes pushed to their providers.
+    pub changes_flushed: usize,
+    /// Queued changes left for the next start, because their provider was
+    /// unavailable, rejected them or the timeout ran out.
+    pub changes_remaining: usize,
+    /// Whether flushing and disconnecting were cut short by the timeout.
+    pub timed_out: bool,
+}
+
+/// Entry point for using the client as a library.
+///
+/// Reads and writes the local database. Changes are pushed to the server for
+/// accounts with a registered provider and kept locally otherwise.
+pub struct MarginClient {
+    storage: Arc<StorageLayer>,
+    store: Arc<LocalStore>,
+    email: Arc<EmailService<LocalStore>>,
+    /// Thread changes, recorded for [`undo`](Self::undo).
+    threads: ThreadService<ThreadStore>,
+    search: SearchService<LocalStore>,
+    attachments: AttachmentService<LocalStore>,
+    ai: Option<Arc<AiService>>,
+    /// Changes from the server, applied to the database and published to
+    /// `events`.
+    sync: Arc<SyncService<LocalStore>>,
+    /// Where new mail reaches the services that react to it.
+    events: ServiceBus,
+    /// Tasks delivering `events` and due follow-up reminders to the
+    /// services that react to them.
+    subscribers: Mutex<Vec<JoinHandle<()>>>,
+    /// Notifications raised for new mail and due follow-up reminders.
+    notifications: Arc<Mutex<NotificationService>>,
+    /// Drafts being composed, by ID, saved on shutdown.
+    open_drafts: Mutex<HashMap<String, Draft>>,
+    /// Bound on requests to mail servers by connected providers.
+    request_timeout: Duration,
+}
+
+impl MarginClient {
+    /// Opens the database at `db_path`, creating it if necessary.
+    pub async fn open(db_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(StorageLayer::new(db_path).await?))
+    }
+
+    /// Opens the database at `db_path` tuned by the user's settings, ranking
+    /// lists with their importance weights.
+    pub async fn open_with_settings(
+        db_path: impl AsRef<Path>,
+        settings: &Settings,
+    ) -> Result<Self> {
+        let storage = StorageLayer::with_options(db_path, settings.database.clone()).await?;
+        let client = Self::new(storage)
+            .with_importance_weights(settings.thread_list.importance)
+            .with_request_timeout(settings.sync.request_timeout());
+        client.apply_privacy(&settings.privacy);
+        client.apply_notifications(&settings.notifications).await;
+        Ok(match PdftoppmRenderer::detect() {
+            Some(renderer) => client.with_pdf_renderer(Arc::new(renderer)),
+            None => client,
+        })
+    }
+
+    /// Creates a client over an in-memory database, for tests.
+    pub async fn in_memory() -> Result<Self> {
+        Ok(Self::new(StorageLayer::in_memory().await?))
+    }
+
+    /// Creates a client over an existing storage layer.
+    pub fn new(storage: StorageLayer) -> Self {
+        let storage = storage.into_arc();
+        let store = Arc::new(LocalStore::new(storage.clone()));
+        let email = Arc::new(EmailService::new(store.clone()));
+        let undo = Arc::new(Mutex::new(UndoService::new()));
+        let search = SearchService::new(store.clone())
+            .with_server_search(Arc::new(ProviderSearch::new(email.clone())));
+        let events = ServiceBus::new();
+        let sync =
+            SyncService::new(store.clone(), SyncSettings::default()).with_event_bus(events.clone());
+
+        Self {
+            threads: ThreadService::new(ThreadStore::new(storage.clone(), email.clone()))
+                .with_undo(undo),
+            email,
+            search,
+            attachments: AttachmentService::new(store.clone()),
+            store,
+            storage,
+            ai: None,
+            sync: Arc::new(sync),
+            events,
+            subscribers: Mutex::new(Vec::new()),
+            notifications: Arc::new(Mutex::new(NotificationService::with_defaults())),
+            open_drafts: Mutex::new(HashMap::new()),
+            request_timeout: http::DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Ranks [`ThreadSort::PriorityFirst`] lists with `weights`.
+    pub fn with_importance_weights(self, weights: ImportanceWeights) -> Self {
+        self.store.set_importance_weights(weights);
+        self
+    }
+
+    /// Abandons requests to mail servers after `timeout` in the providers
+    /// [`connect_accounts`](Self::connect_accounts) connects.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Applies the privacy settings: whether lists may be contacted to
+    /// unsubscribe, and whether read receipts are offered.
+    pub fn apply_privacy(&self, privacy: &PrivacySettings) {
+        self.email
+            .set_external_requests_allowed(privacy.external_content_enabled);
+        self.email
+            .set_read_receipts_enabled(privacy.read_receipts_enabled);
+    }
+
+    /// Enables summaries and semantic search with the given AI service.
+    ///
+    /// Summaries are cached in the database, so a thread that hasn't
+    /// changed isn't summarized again after a restart.
+    pub async fn with_ai_service(mut self, ai_service: Arc<AiService>) -> Self {
+        ai_service.set_summary_cache(self.store.clone()).await;
+        self.search = self.search.with_ai_service(ai_service.clone());
+        self.ai = Some(ai_service);
+        self
+    }
+
+    /// Previews PDF attachments with `renderer`; without one only images
+    /// get thumbnails.
+    pub fn with_pdf_renderer(mut self, renderer: Arc<dyn PdfRenderer>) -> Self {
+        self.attachments = AttachmentService::new(self.store.clone()).with_pdf_renderer(renderer);
+        self
+    }
+
+    /// Returns the storage layer.
+    pub fn storage(&self) -> &StorageLayer {
+        &self.storage
+    }
+
+    /// Returns the email service, for operations not covered here.
+    pub fn email_service(&self) -> &EmailService<LocalStore> {
+        &self.email
+    }
+
+    /// Registers the provider that sends mail and receives changes for an
+    /// account.
+    pub async fn register_provider(&self, account_id: AccountId, provider: Arc<dyn EmailProvider>) {
+        self.email.register_provider(account_id, provider).await;
+    }
+
+    /// Signs `provider` in and registers it for `account`, so it is synced
+    /// and disconnected on [`shutdown`](Self::shutdown).
+    ///
+    /// Special folders and send-as aliases the provider discovers while
+    /// signing in are stored on the account.
+    pub async fn connect(
+        &self,
+        account: &Account,
+        mut provider: Box<dyn RemoteProvider>,
+    ) -> Result<()> {
+        provider.authenticate().await?;
+        if let Some(mapping) = provider.special_folders() {
+            if mapping != account.folder_mapping {
+                accounts::set_folder_mapping(self.storage.db(), &account.id, &mapping).await?;
+            }
+        }
+        if let Some(aliases) = provider.send_as_aliases() {
+            if aliases != account.aliases {
+                accounts::set_aliases(self.storage.db(), &account.id, &aliases).await?;
+            }
+        }
+        let provider = Arc::new(ConnectedProvider::new(provider));
+        self.register_provider(account.id.clone(), provider.clone())
+            .await;
+        self.sync
+            .register_provider(account.id.clone(), provider)
+            .await;
+        Ok(())
+    }
+
+    /// Connects every stored account that syncs, with the credentials in
+    /// the keychain, returning how many connected.
+    ///
+    /// An account that fails to connect is logged and left working
+    /// offline.
+    pub async fn connect_accounts(&self) -> Result<usize> {
+        let mut connected = 0;
+        for account in self.accounts().await? {
+            if !account.sync_enabled {
+                continue;
+            }
+            let provider = provider_for_account(&account, self.storage.db(), self.request_timeout);
+            match self.connect(&account, provider).await {
+                Ok(()) => connected += 1,
+                Err(e) => tracing::warn!(account = %account.id, "Failed to connect: {}", e),
+            }
+        }
+        Ok(connected)
+    }
+
+    /// Applies the notification settings: whether notifications are shown
+    /// at all, and whether they play a sound.
+    pub async fn apply_notifications(&self, settings: &NotificationSettings) {
+        self.notifications
+            .lock()
+            .await
+            .set_settings(NotifierSettings {
+                system_notifications_enabled: settings.enabled,
+                toasts_enabled: settings.enabled,
+                sounds_enabled: settings.sound_enabled,
+                ..NotifierSettings::default()
+            });
+    }
+
+    /// Syncs connected accounts every sync interval in `settings`, on the
+    /// current Tokio runtime, and starts the services that react to new
+    /// mail: notifications, follow-up reminders, which replies cancel, and,
+    /// with an AI service, semantic search indexing. Mail from blocked
+    /// senders skips the inbox.
+    ///
+    /// Call once. Accounts connected later are synced from the next round.
+    pub async fn start_syncing(&self, settings: &Settings) {
+        let mut subscribers = self.subscribers.lock().await;
+        subscribers.push(self.events.register(self.notifications.clone()));
+        subscribers.push(self.events.register(self.email.clone()));
+        if let Some(ai) = &self.ai {
+            subscribers.push(self.events.register(ai.clone()));
+        }
+        drop(subscribers);
+
+        if let Err(e) = self.load_blocklist().await {
+            tracing::warn!("Failed to load blocked senders: {}", e);
+        }
+        self.sync
+            .update_settings(SyncSettings {
+                background_sync_enabled: settings.sync.enabled,
+                sync_interval: Duration::from_secs(settings.sync.interval_seconds.into()),
+                ..SyncSettings::default()
+            })
+            .await;
+        self.sync.clone().start_background_sync();
+    }
+
+    /// Purges trash and spam kept longer than the retention in `settings`,
+    /// now and then every hour, on the current Tokio runtime.
+    pub async fn start_purging(&self, settings: &Settings) {
+        let retention = SyncSettings {
+            trash_retention_days: settings.sync.trash_retention_days,
+            spam_retention_days: settings.sync.spam_retention_days,
+            ..SyncSettings::default()
+        };
+        self.email.set_purge_retention(&retention).await;
+        if let Err(e) = self.email.purge_expired(Utc::now()).await {
+            tracing::warn!("Failed to purge expired threads: {}", e);
+        }
+        self.email.clone().start_purging(PURGE_INTERVAL);
+    }
+
+    /// Loads the follow-up reminders saved before the last shutdown, then
+    /// checks for due ones every minute on the current Tokio runtime.
+    ///
+    /// Due reminders are announced to
+    /// [`subscribe_followups`](EmailService::subscribe_followups) and
+    /// delivered as notifications.
+    pub async fn start_followup_checks(&self) {
+        match self.email.load_followups().await {
+            Ok(pending) => tracing::debug!(pending, "Loaded follow-up reminders"),
+            Err(e) => tracing::warn!("Failed to load follow-up reminders: {}", e),
+        }
+
+        let mut due = self.email.subscribe_followups();
+        let notifications = self.notifications.clone();
+        let delivering = tokio::spawn(async move {
+            loop {
+                match due.recv().await {
+                    // Each reminder is for a different thread, so none is
+                    // held back by the rate limit.
+                    Ok(due) => {
+                        let notification = due.notification.immediate();
+                        if let Err(e) = notifications.lock().await.notify(notification) {
+                            tracing::warn!("Failed to notify about a follow-up: {}", e);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "Follow-up reminders were missed");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        self.subscribers.lock().await.push(delivering);
+        self.email.clone().start_followup_checks(FOLLOWUP_INTERVAL);
+    }
+
+    /// Evicts message bodies older than the body retention in `settings`,
+    /// now and then every day, on the current Tokio runtime. Does nothing
+    /// when bodies are kept indefinitely.
+    ///
+    /// Evicted bodies are fetched again when their thread is opened.
+    pub fn start_evicting(&self, settings: &Settings) {
+        let Some(days) = settings.sync.body_retention_days else {
+            return;
+        };
+        let older_than = chrono::Duration::days(i64::from(days));
+        let storage = Arc::downgrade(&self.storage);
+        tokio::spawn(async move {
+            loop {
+                let Some(storage) = storage.upgrade() else {
+                    break;
+                };
+                match storage.evict_bodies(older_than).await {
+                    Ok(evicted) => tracing::debug!(evicted, "Evicted old message bodies"),
+                    Err(e) => tracing::warn!("Failed to evict message bodies: {}", e),
+                }
+                drop(storage);
+                tokio::time::sleep(EVICT_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Lists the threads of a view, newest first.
+    pub async fn list_threads(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        self.email.fetch_threads(account_id, view, pagination).await
+    }
+
+    /// Lists the threads of a view in the given order.
+    pub async fn list_threads_sorted(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        sort: ThreadSort,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        self.email
+            .fetch_threads_sorted(account_id, view, sort, pagination)
+            .await
+    }
+
+    /// Returns a thread with all its messages, fetching it from its
+    /// provider when it isn't stored.
+    pub async fn get_thread(&self, thread_id: &ThreadId) -> Result<Thread> {
+        self.email.get_thread(thread_id).await
+    }
+
+    /// Returns PNG thumbnails of an email's image and PDF attachments,
+    /// keyed by attachment ID, fitting in `size` by `size`.
+    ///
+    /// Attachments that haven't been downloaded are downloaded first, when
+    /// the account's provider is connected.
+    pub async fn thumbnails(&self, email: &Email, size: u32) -> HashMap<String, ImageBytes> {
+        let mut thumbnails = HashMap::new();
+        for attachment in email.attachments.iter().filter(|a| !a.is_inline) {
+            if !self.attachments.can_preview(&attachment.content_type) {
+                continue;
+            }
+            let mut attachment = attachment.clone();
+            if attachment.content_hash.is_none() {
+                match self.email.download_attachment(email, &attachment.id).await {
+                    Ok(hash) => attachment.content_hash = hash,
+                    Err(e) => {
+                        tracing::debug!("Failed to download attachment {}: {}", attachment.id, e);
+                        continue;
+                    }
+                }
+            }
+            if let Some(png) = self.attachments.thumbnail(&attachment, size).await {
+                thumbnails.insert(attachment.id.clone(), png);
+            }
+        }
+        thumbnails
+    }
+
+    /// Searches stored mail.
+    pub async fn search(&self, query: SearchQuery) -> Result<SearchResults> {
+        self.search.search(query).await
+    }
+
+    /// Returns the thread service. Its archiving, trashing and label changes
+    /// can be reversed with [`undo`](Self::undo).
+    pub fn threads(&self) -> &ThreadService<ThreadStore> {
+        &self.threads
+    }
+
+    /// Reverses the most recent thread change made through
+    /// [`threads`](Self::threads), locally and at the thread's provider.
+    ///
+    /// Returns the undone action, or `None` when there is nothing to undo.
+    pub async fn undo(&self) -> Result<Option<UndoableAction>> {
+        Ok(self.threads.undo().await?)
+    }
+
+    /// Archives threads.
+    pub async fn archive(&self, thread_ids: &[ThreadId]) -> Result<()> {
+        self.email.archive(thread_ids).await
+    }
+
+    /// Blocks `senders`, e.g. those of mail reported as spam. Their new mail
+    /// skips the inbox and is reported as spam as it syncs.
+    pub async fn block_senders(&self, senders: &[Address]) -> Result<()> {
+        for sender in senders {
+            let entry = BlockEntry {
+                pattern: sender.email.trim().to_lowercase(),
+                action: BlockAction::Archive,
+                report_spam: true,
+                created_at: Utc::now(),
+            };
+            self.store.block_sender(&entry).await?;
+        }
+        self.load_blocklist().await
+    }
+
+    /// Enforces the saved blocked senders on new mail.
+    async fn load_blocklist(&self) -> Result<()> {
+        let entries = self.store.blocked_senders().await?;
+        self.sync.set_blocklist(Blocklist::new(entries)).await;
+        Ok(())
+    }
+
+    /// Sends a draft through its account's registered provider.
+    pub async fn send(&self, draft: Draft) -> Result<EmailId> {
+        self.email.send_email(draft).await
+    }
+
+    /// Sends a draft after an undo window or at a scheduled time, returning
+    /// a handle to watch or cancel the send.
+    pub async fn send_with_options(
+        &self,
+        draft: Draft,
+        options: SendOptions,
+    ) -> Result<SendHandle> {
+        self.email.send_with_options(draft, options).await
+    }
+
+    /// Lists the configured accounts.
+    pub async fn accounts(&self) -> Result<Vec<Account>> {
+        Ok(accounts::get_all(self.storage.db()).await?)
+    }
+
+    /// Lists an account's user labels by name.
+    pub async fn labels(&self, account_id: &AccountId) -> Result<Vec<Label>> {
+        let account_id = account_id.clone();
+        let db = self.storage.db();
+        Ok(db
+            .with_reader(move |conn| Ok(labels::get_user_labels(conn, &account_id)?))
+            .await?)
+    }
+
+    /// Returns the account's user label called `name`, ignoring case,
+    /// creating it if the account has none.
+    ///
+    /// Labels belong to one account, so the same name gets a different ID
+    /// in each account.
+    pub async fn ensure_label(&self, account_id: &AccountId, name: &str) -> Result<Label> {
+        let name = name.trim();
+        if let Some(label) = self
+            .labels(account_id)
+            .await?
+            .into_iter()
+            .find(|label| label.name.eq_ignore_ascii_case(name))
+        {
+            return Ok(label);
+        }
+
+        let label = Label {
+            id: LabelId::from(format!("label-{}", uuid::Uuid::new_v4())),
+            account_id: account_id.clone(),
+            name: name.to_string(),
+            color: None,
+            is_system: false,
+            provider_id: None,
+        };
+        let row = label.clone();
+        self.storage
+            .db()
+            .with_conn(move |conn| Ok(labels::insert(conn, &row)?))
+            .await?;
+        Ok(label)
+    }
+
+    /// Removes an account with everything stored for it: its mail,
+    /// attachments, credentials and semantic search embeddings.
+    ///
+    /// Its provider is disconnected first so the server doesn't keep the
+    /// session open.
+    pub async fn remove_account(&self, account_id: &AccountId) -> Result<()> {
+        self.sync.unregister_provider(account_id).await;
+        self.email.unregister_provider(account_id).await;
+        let email_ids = self.storage.delete_account(account_id).await?;
+        if let Some(ai) = &self.ai {
+            ai.forget_emails(&email_ids).await?;
+        }
+        Ok(())
+    }
+
+    /// Summarizes a thread.
+    ///
+    /// Fails unless an AI service was configured with
+    /// [`with_ai_service`](Self::with_ai_service).
+    pub async fn summarize(&self, thread_id: &ThreadId) -> Result<Summary> {
+        let ai = self.ai.as_ref().context("No AI service configured")?;
+        let thread = self.email.get_thread(thread_id).await?;
+        ai.summarize_thread(&thread).await
+    }
+
+    /// Translates the body of an email in a thread into `to_lang`.
+    ///
+    /// Fails unless an AI service was configured with
+    /// [`with_ai_service`](Self::with_ai_service), or when it has translation
+    /// turned off.
+    pub async fn translate(
+        &self,
+        thread_id: &ThreadId,
+        email_id: &EmailId,
+        to_lang: &str,
+    ) -> Result<String> {
+        let ai = self.ai.as_ref().context("No AI service configured")?;
+        let thread = self.email.get_thread(thread_id).await?;
+        let email = thread
+            .messages
+            .iter()
+            .find(|email| &email.id == email_id)
+            .with_context(|| format!("Email not in thread: {}", email_id))?;
+        ai.translate(email, to_lang).await
+    }
+
+    /// Records the latest state of a draft being composed, giving it an ID
+    /// if it has none, and returns the ID.
+    ///
+    /// Open drafts are kept in memory and saved by
+    /// [`save_drafts`](Self::save_drafts) or on [`shutdown`](Self::shutdown).
+    pub async fn edit_draft(&self, mut draft: Draft) -> String {
+        let id = draft
+            .id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone();
+        draft.updated_at = Utc::now();
+        self.open_drafts.lock().await.insert(id.clone(), draft);
+        id
+    }
+
+    /// Stops tracking a draft, e.g. after sending or discarding it.
+    pub async fn close_draft(&self, id: &str) -> Option<Draft> {
+        self.open_drafts.lock().await.remove(id)
+    }
+
+    /// Returns a draft, preferring its open state over the saved one.
+    pub async fn load_draft(&self, id: &str) -> Result<Option<Draft>> {
+        if let Some(draft) = self.open_drafts.lock().await.get(id) {
+            return Ok(Some(draft.clone()));
+        }
+        self.store.load_draft(id).await
+    }
+
+    /// Saves every open draft, returning how many were saved.
+    pub async fn save_drafts(&self) -> Result<usize> {
+        let drafts: Vec<Draft> = self.open_drafts.lock().await.values().cloned().collect();
+        for draft in &drafts {
+            self.store.save_draft(draft).await?;
+        }
+        Ok(drafts.len())
+    }
+
+    /// Queues a change to push to the account's provider, returning its ID.
+    ///
+    /// The queue is kept in the database, so changes made offline survive a
+    /// restart. Push it with [`flush_pending_changes`](Self::flush_pending_changes).
+    pub async fn queue_change(
+        &self,
+        account_id: AccountId,
+        change_type: PendingChangeType,
+    ) -> Result<String> {
+        let change = PendingChange {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id,
+            change_type,
+            created_at: Utc::now(),
+        };
+        self.store.queue_change(&change).await?;
+        Ok(change.id)
+    }
+
+    /// Lists queued changes, oldest first.
+    pub async fn pending_changes(&self) -> Result<Vec<PendingChange>> {
+        self.store.pending_changes().await
+    }
+
+    /// Pushes queued changes in order, returning how many were pushed.
+    ///
+    /// A change that fails is logged and left queued; later changes for the
+    /// same account are left too, so they are never applied out of order.
+    pub async fn flush_pending_changes(&self) -> Result<usize> {
+        let mut blocked: Vec<AccountId> = Vec::new();
+        let mut flushed = 0;
+        for change in self.store.pending_changes().await? {
+            if blocked.contains(&change.account_id) {
+                continue;
+            }
+            match self
+                .email
+                .push_queued_change(&change.account_id, &change.change_type)
+                .await
+            {
+                Ok(()) => {
+                    self.store.remove_change(&change.id).await?;
+                    flushed += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(change = %change.id, "Failed to push queued change: {}", e);
+                    blocked.push(change.account_id);
+                }
+            }
+        }
+        Ok(flushed)
+    }
+
+    /// Shuts down cleanly before the app exits.
+    ///
+    /// Saves open drafts, then pushes queued changes and disconnects
+    /// providers within `timeout`, then checkpoints the database's
+    /// write-ahead log. Drafts and the checkpoint are local and always run;
+    /// changes not pushed in time stay queued for the next start.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<ShutdownReport> {
+        self.sync.stop_background_sync();
+        for subscriber in self.subscribers.lock().await.drain(..) {
+            subscriber.abort();
+        }
+        let mut report = ShutdownReport {
+            drafts_saved: self.save_drafts().await?,
+            ..Default::default()
+        };
+
+        let network = async {
+            let flushed = self.flush_pending_changes().await;
+            self.email.disconnect_providers().await;
+            flushed
+        };
+        match tokio::time::timeout(timeout, network).await {
+            Ok(flushed) => report.changes_flushed = flushed?,
+            Err(_) => {
+                tracing::warn!(?timeout, "Shutdown timed out pushing queued changes");
+                report.timed_out = true;
+            }
+        }
+
+        report.changes_remaining = self.store.pending_changes().await?.len();
+        self.storage.checkpoint().await?;
+        Ok(report)
+    }
+}
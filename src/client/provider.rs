@@ -0,0 +1,357 @@
+//! Adapts real providers to the services' provider trait.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::domain::{Thread, ThreadId, ThreadSummary};
+use crate::providers::email::{
+    Change as RemoteChange, EmailProvider as RemoteProvider, OutgoingEmail as RemoteEmail,
+    Pagination as RemotePagination, PendingChange, PendingChangeType, ProviderCapabilities,
+    SearchCriteria,
+};
+use crate::services::{
+    Change, EmailProvider, EmailUpdates, OutgoingEmail, Pagination, PendingChange as QueuedChange,
+    SyncProvider, SyncState,
+};
+
+/// A signed-in [`providers`](crate::providers) email provider, usable as
+/// the services' [`EmailProvider`].
+///
+/// [`disconnect`](EmailProvider::disconnect) ends the provider's session,
+/// so shutting the client down logs out of IMAP servers. It is also the
+/// [`SyncProvider`] that brings the account's changes into the database.
+pub struct ConnectedProvider<P: ?Sized + RemoteProvider> {
+    provider: RwLock<Box<P>>,
+    /// When the last successful fetch of changes started, which the next
+    /// sync fetches from.
+    fetched_at: std::sync::Mutex<Option<DateTime<Utc>>>,
+}
+
+impl<P: ?Sized + RemoteProvider> ConnectedProvider<P> {
+    /// Wraps a provider that has already authenticated.
+    pub fn new(provider: Box<P>) -> Self {
+        Self {
+            provider: RwLock::new(provider),
+            fetched_at: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Returns the wrapped provider.
+    pub async fn inner(&self) -> RwLockReadGuard<'_, Box<P>> {
+        self.provider.read().await
+    }
+
+    async fn push(&self, change_type: PendingChangeType) -> Result<()> {
+        let change = PendingChange {
+            id: uuid::Uuid::new_v4().to_string(),
+            change_type,
+            created_at: chrono::Utc::now(),
+        };
+        Ok(self.provider.read().await.push_change(&change).await?)
+    }
+}
+
+#[async_trait]
+impl<P: ?Sized + RemoteProvider> EmailProvider for ConnectedProvider<P> {
+    fn provider_type(&self) -> &str {
+        match self.provider.try_read() {
+            Ok(provider) => provider.provider_type().as_str(),
+            Err(_) => "disconnecting",
+        }
+    }
+
+    async fn fetch_threads(
+        &self,
+        folder: &str,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        // Providers page with cursors, so fetch through the end of the page
+        // and skip to its start.
+        let limit = (pagination.offset + pagination.limit) as u32;
+        let threads = self
+            .provider
+            .read()
+            .await
+            .fetch_threads(folder, RemotePagination::with_limit(limit))
+            .await?;
+        Ok(threads.into_iter().skip(pagination.offset).collect())
+    }
+
+    async fn fetch_thread(&self, thread_id: &str) -> Result<Thread> {
+        Ok(self.provider.read().await.fetch_thread(thread_id).await?)
+    }
+
+    async fn search(&self, folder: &str, criteria: &SearchCriteria) -> Result<Vec<ThreadSummary>> {
+        Ok(self.provider.read().await.search(folder, criteria).await?)
+    }
+
+    async fn fetch_raw(&self, email_id: &str) -> Result<Vec<u8>> {
+        Ok(self.provider.read().await.fetch_raw(email_id).await?)
+    }
+
+    async fn fetch_attachment(
+        &self,
+        email_id: &str,
+        attachment_id: &str,
+        out: &mut (dyn std::io::Write + Send),
+    ) -> Result<u64> {
+        Ok(self
+            .provider
+            .read()
+            .await
+            .fetch_attachment(email_id, attachment_id, out)
+            .await?)
+    }
+
+    async fn send_email(&self, email: &OutgoingEmail) -> Result<String> {
+        let email = RemoteEmail {
+            from: Some(email.from.clone()),
+            to: email.to.clone(),
+            cc: email.cc.clone(),
+            bcc: email.bcc.clone(),
+            subject: email.subject.clone(),
+            body_text: email.body_text.clone(),
+            body_html: email.body_html.clone(),
+            in_reply_to_thread: email.in_reply_to.clone(),
+            in_reply_to_message: email.reply_to_message_id.clone(),
+            references: email.references.clone(),
+            attachments: Vec::new(),
+            idempotency_key: Some(email.idempotency_key.clone()),
+            request_read_receipt: email.request_read_receipt,
+            read_receipt_for: email.read_receipt_for.clone(),
+        };
+        Ok(self.provider.read().await.send_email(&email).await?)
+    }
+
+    async fn archive(&self, thread_ids: &[String]) -> Result<()> {
+        Ok(self.provider.read().await.archive(thread_ids).await?)
+    }
+
+    async fn trash(&self, thread_ids: &[String]) -> Result<()> {
+        Ok(self.provider.read().await.trash(thread_ids).await?)
+    }
+
+    async fn delete_permanently(&self, thread_ids: &[String]) -> Result<()> {
+        Ok(self
+            .provider
+            .read()
+            .await
+            .delete_permanently(thread_ids)
+            .await?)
+    }
+
+    async fn report_spam(&self, thread_ids: &[String]) -> Result<()> {
+        Ok(self.provider.read().await.report_spam(thread_ids).await?)
+    }
+
+    async fn star(&self, thread_id: &str, starred: bool) -> Result<()> {
+        Ok(self.provider.read().await.star(thread_id, starred).await?)
+    }
+
+    async fn mark_read(&self, thread_id: &str, read: bool) -> Result<()> {
+        Ok(self
+            .provider
+            .read()
+            .await
+            .mark_read(thread_id, read)
+            .await?)
+    }
+
+    async fn apply_label(&self, thread_id: &str, label: &str) -> Result<()> {
+        Ok(self
+            .provider
+            .read()
+            .await
+            .apply_label(thread_id, label)
+            .await?)
+    }
+
+    async fn remove_label(&self, thread_id: &str, label: &str) -> Result<()> {
+        self.push(PendingChangeType::RemoveLabel {
+            thread_ids: vec![ThreadId::from(thread_id)],
+            label_id: label.to_string().into(),
+        })
+        .await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(self.provider.write().await.disconnect().await?)
+    }
+}
+
+#[async_trait]
+impl<P: ?Sized + RemoteProvider> SyncProvider for ConnectedProvider<P> {
+    /// Fetches the changes since the last sync. Mail that arrived before an
+    /// account's first sync is loaded with its folders instead.
+    ///
+    /// Providers report new mail by its headers, so each new message is
+    /// fetched in full with its thread. Label changes carry the message's
+    /// labels rather than what changed, so labels it lost stay until its
+    /// thread is fetched again.
+    async fn fetch_changes_since(&self, state: &SyncState) -> Result<Vec<Change>> {
+        let started = Utc::now();
+        let since = state.last_sync.unwrap_or(started);
+        let changes = self
+            .provider
+            .read()
+            .await
+            .fetch_changes_since(&since)
+            .await?;
+
+        let mut threads: HashMap<ThreadId, Thread> = HashMap::new();
+        let mut synced = Vec::with_capacity(changes.len());
+        for change in changes {
+            synced.push(match change {
+                RemoteChange::NewEmail(new) => {
+                    if !threads.contains_key(&new.thread_id) {
+                        let thread = EmailProvider::fetch_thread(self, &new.thread_id.0).await?;
+                        threads.insert(new.thread_id.clone(), thread);
+                    }
+                    let email = threads[&new.thread_id]
+                        .messages
+                        .iter()
+                        .find(|m| m.id == new.id)
+                        .cloned();
+                    match email {
+                        Some(email) => Change::NewEmail(Box::new(email)),
+                        None => {
+                            tracing::debug!(email_id = %new.id, "New message left its thread");
+                            continue;
+                        }
+                    }
+                }
+                RemoteChange::Updated(update) => Change::Updated(
+                    update.id,
+                    EmailUpdates {
+                        is_read: update.is_read,
+                        is_starred: update.is_starred,
+                        add_labels: update
+                            .labels
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|l| l.0)
+                            .collect(),
+                        remove_labels: Vec::new(),
+                    },
+                ),
+                RemoteChange::Deleted(email_id) => Change::Deleted(email_id),
+            });
+        }
+
+        *self.fetched_at.lock().unwrap() = Some(started);
+        Ok(synced)
+    }
+
+    async fn push_change(&self, change: &QueuedChange) -> Result<()> {
+        let change = PendingChange {
+            id: change.id.clone(),
+            change_type: change.change_type.to_provider()?,
+            created_at: change.created_at,
+        };
+        Ok(self.provider.read().await.push_change(&change).await?)
+    }
+
+    async fn get_current_state(&self) -> Result<SyncState> {
+        Ok(SyncState {
+            last_sync: Some(self.fetched_at.lock().unwrap().unwrap_or_else(Utc::now)),
+            ..SyncState::default()
+        })
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        match self.provider.try_read() {
+            Ok(provider) => provider.capabilities(),
+            Err(_) => ProviderCapabilities::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{test_email, Address, Email};
+    use crate::providers::email::FakeEmailProvider;
+
+    async fn connected() -> ConnectedProvider<FakeEmailProvider> {
+        let mut fake = FakeEmailProvider::new("account-1", "me@example.com");
+        fake.authenticate().await.unwrap();
+        ConnectedProvider::new(Box::new(fake))
+    }
+
+    #[tokio::test]
+    async fn disconnect_ends_the_provider_session() {
+        let provider = connected().await;
+        assert!(provider.inner().await.is_authenticated());
+
+        EmailProvider::disconnect(&provider).await.unwrap();
+
+        assert!(!provider.inner().await.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn sends_keep_their_idempotency_key() {
+        let provider = connected().await;
+        let email = OutgoingEmail {
+            from: Address::new("me@example.com"),
+            to: vec![Address::new("bob@example.com")],
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: "Hello".into(),
+            body_text: "Hi Bob".into(),
+            body_html: None,
+            in_reply_to: None,
+            reply_to_message_id: None,
+            references: Vec::new(),
+            idempotency_key: "key-1".into(),
+            request_read_receipt: false,
+            read_receipt_for: None,
+        };
+
+        provider.send_email(&email).await.unwrap();
+
+        let sent = provider.inner().await.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].idempotency_key.as_deref(), Some("key-1"));
+        assert_eq!(sent[0].subject, "Hello");
+    }
+
+    #[tokio::test]
+    async fn new_mail_is_synced_in_full() {
+        let provider = connected().await;
+        let state = SyncState {
+            last_sync: Some(Utc::now() - chrono::Duration::minutes(1)),
+            ..SyncState::default()
+        };
+        provider.inner().await.seed(Email {
+            body_text: Some("Are you free for lunch?".to_string()),
+            ..test_email("email-1", "thread-1")
+        });
+
+        let changes = provider.fetch_changes_since(&state).await.unwrap();
+
+        assert!(matches!(
+            &changes[..],
+            [Change::NewEmail(email)]
+                if email.body_text.as_deref() == Some("Are you free for lunch?")
+        ));
+        let synced = provider.get_current_state().await.unwrap();
+        assert!(synced.last_sync > state.last_sync);
+    }
+
+    #[tokio::test]
+    async fn removing_a_label_pushes_the_change() {
+        let provider = connected().await;
+
+        provider.remove_label("t1", "Work").await.unwrap();
+
+        let pushed = provider.inner().await.pushed();
+        assert!(matches!(
+            &pushed[0].change_type,
+            PendingChangeType::RemoveLabel { label_id, .. } if label_id.0 == "Work"
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+//! Server-side search through the registered providers.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::domain::{AccountId, EmailId};
+use crate::providers::email::SearchCriteria;
+use crate::services::{EmailService, SearchHit, SearchSource, ServerSearch};
+
+use super::LocalStore;
+
+/// Folder searched on the server.
+const SEARCH_FOLDER: &str = "INBOX";
+
+/// [`ServerSearch`] over the providers registered with the
+/// [`EmailService`].
+///
+/// The client doesn't keep a complete copy of a connected account's
+/// mailbox, since its listings come from the provider, so connected
+/// accounts are searched on the server. Accounts without a provider are
+/// searched locally only.
+pub struct ProviderSearch {
+    email: Arc<EmailService<LocalStore>>,
+}
+
+impl ProviderSearch {
+    /// Creates a server search through `email`'s providers.
+    pub fn new(email: Arc<EmailService<LocalStore>>) -> Self {
+        Self { email }
+    }
+}
+
+#[async_trait]
+impl ServerSearch for ProviderSearch {
+    async fn is_fully_synced(&self, account_id: &AccountId) -> bool {
+        !self.email.has_provider(account_id).await
+    }
+
+    async fn search(
+        &self,
+        account_id: &AccountId,
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<SearchHit>> {
+        let summaries = self
+            .email
+            .search_server(account_id, SEARCH_FOLDER, criteria)
+            .await?;
+        Ok(summaries
+            .into_iter()
+            .map(|summary| SearchHit {
+                // Server searches list each matching message on its own.
+                email_id: EmailId(summary.id.0.clone()),
+                thread_id: summary.id,
+                subject: summary.subject,
+                snippet: summary.snippet,
+                from: summary.from.display(),
+                date: summary.last_message_date,
+                is_read: summary.unread_count == 0,
+                score: 1.0,
+                source: SearchSource::Server,
+                highlights: vec![],
+            })
+            .collect())
+    }
+}
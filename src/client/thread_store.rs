@@ -0,0 +1,163 @@
+//! Thread storage for the thread service that pushes changes to providers.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::domain::{AccountId, LabelId, Thread, ThreadId, ThreadSort, ThreadSummary};
+use crate::services::{EmailService, ThreadError, ThreadFilter, ThreadResult, ThreadStorage};
+use crate::storage::queries::{accounts, threads};
+use crate::storage::StorageLayer;
+
+use super::LocalStore;
+
+/// [`ThreadStorage`] over the local database and the registered providers.
+///
+/// Archiving, trashing, label, read and star changes go through the
+/// [`EmailService`], so each one, including those applied to undo an
+/// earlier change, is pushed to the thread's provider. Muting, watching and
+/// deleting are local.
+pub struct ThreadStore {
+    storage: Arc<StorageLayer>,
+    email: Arc<EmailService<LocalStore>>,
+}
+
+impl ThreadStore {
+    /// Creates thread storage over `storage` that pushes through `email`.
+    pub fn new(storage: Arc<StorageLayer>, email: Arc<EmailService<LocalStore>>) -> Self {
+        Self { storage, email }
+    }
+
+    /// Lists the account IDs a filter covers.
+    async fn account_ids(&self, filter: &ThreadFilter) -> ThreadResult<Vec<AccountId>> {
+        if let Some(account_id) = &filter.account_id {
+            return Ok(vec![account_id.clone()]);
+        }
+        let accounts = accounts::get_all(self.storage.db())
+            .await
+            .map_err(storage_error)?;
+        Ok(accounts.into_iter().map(|account| account.id).collect())
+    }
+}
+
+fn storage_error(e: impl std::fmt::Display) -> ThreadError {
+    ThreadError::Storage(e.to_string())
+}
+
+#[async_trait]
+impl ThreadStorage for ThreadStore {
+    async fn get_thread(&self, id: &ThreadId) -> ThreadResult<Option<Thread>> {
+        if self.get_thread_summary(id).await?.is_none() {
+            return Ok(None);
+        }
+        let thread = self.email.get_thread(id).await.map_err(storage_error)?;
+        Ok(Some(thread))
+    }
+
+    async fn get_thread_summary(&self, id: &ThreadId) -> ThreadResult<Option<ThreadSummary>> {
+        threads::get_by_id(self.storage.db(), id)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn list_threads(
+        &self,
+        filter: &ThreadFilter,
+        sort: ThreadSort,
+    ) -> ThreadResult<Vec<ThreadSummary>> {
+        let db = self.storage.db();
+        let mut summaries = Vec::new();
+        for account_id in self.account_ids(filter).await? {
+            let label_id = filter.label_id.as_ref();
+            let page = threads::list(db, &account_id, label_id, sort, u32::MAX, 0)
+                .await
+                .map_err(storage_error)?;
+            summaries.extend(page);
+        }
+        summaries.retain(|t| {
+            (!filter.unread_only || t.has_unread())
+                && (!filter.starred_only || t.is_starred)
+                && filter.muted.map_or(true, |muted| t.muted == muted)
+        });
+        // Ranked by the thread service when sorting by priority.
+        sort.sort(&mut summaries, |_| 0.0);
+
+        let offset = filter.offset.unwrap_or(0) as usize;
+        let limit = filter.limit.map_or(usize::MAX, |limit| limit as usize);
+        Ok(summaries.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn set_starred(&self, id: &ThreadId, starred: bool) -> ThreadResult<()> {
+        self.email.star(id, starred).await.map_err(storage_error)
+    }
+
+    async fn set_muted(&self, id: &ThreadId, muted: bool) -> ThreadResult<()> {
+        threads::set_muted(self.storage.db(), id, muted)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn set_watched(&self, id: &ThreadId, watched: bool) -> ThreadResult<()> {
+        threads::set_watched(self.storage.db(), id, watched)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn set_unread_count(&self, id: &ThreadId, count: u32) -> ThreadResult<()> {
+        threads::set_unread_count(self.storage.db(), id, count)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn mark_read(&self, id: &ThreadId) -> ThreadResult<()> {
+        self.email.mark_read(id, true).await.map_err(storage_error)
+    }
+
+    async fn mark_unread(&self, id: &ThreadId) -> ThreadResult<()> {
+        self.email.mark_read(id, false).await.map_err(storage_error)
+    }
+
+    async fn add_label(&self, id: &ThreadId, label_id: &LabelId) -> ThreadResult<()> {
+        self.email
+            .apply_label(std::slice::from_ref(id), label_id)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn remove_label(&self, id: &ThreadId, label_id: &LabelId) -> ThreadResult<()> {
+        self.email
+            .remove_label(std::slice::from_ref(id), label_id)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn archive(&self, id: &ThreadId) -> ThreadResult<()> {
+        self.email
+            .archive(std::slice::from_ref(id))
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn trash(&self, id: &ThreadId) -> ThreadResult<()> {
+        self.email
+            .trash(std::slice::from_ref(id))
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn delete(&self, id: &ThreadId) -> ThreadResult<()> {
+        threads::delete(self.storage.db(), id)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn count_threads(&self, filter: &ThreadFilter) -> ThreadResult<u32> {
+        let filter = ThreadFilter {
+            limit: None,
+            offset: None,
+            ..filter.clone()
+        };
+        let summaries = self.list_threads(&filter, ThreadSort::DateDesc).await?;
+        Ok(summaries.len() as u32)
+    }
+}
@@ -4,9 +4,12 @@
 //! Settings are stored in the user's config directory as JSON.
 
 mod settings;
+mod transfer;
 
 pub use settings::{
-    AiSettings, AppearanceSettings, ComposeSettings, Density, KeybindingSettings,
-    NewEmailNotification, NotificationSettings, PrivacySettings, ProviderSettings, QuietHours,
-    SearchSettings, Settings, SummarySettings, SyncSettings, Theme, Tone,
+    AfterDone, AiSettings, AppearanceSettings, ComposeSettings, Density, DoneAction,
+    KeybindingSettings, KeyboardMacro, NewEmailNotification, NotificationSettings, Preset,
+    PrivacySettings, ProviderSettings, QuietHours, ReadingSettings, SearchSettings,
+    SendingSettings, Settings, SummarySettings, SyncSettings, Theme, ThreadListSettings, Tone,
 };
+pub use transfer::{ImportReport, SETTINGS_EXPORT_VERSION};
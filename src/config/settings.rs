@@ -3,8 +3,17 @@
 //! Settings are persisted to `~/.config/heap/settings.json` (or XDG equivalent)
 //! and loaded at application startup.
 
+use anyhow::Context;
+use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::domain::{ImportanceWeights, ThreadSort};
+use crate::logging::LogConfig;
+use crate::storage::DatabaseOptions;
 
 /// Top-level application settings.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -21,6 +30,43 @@ pub struct Settings {
     pub keybindings: KeybindingSettings,
     /// Privacy-related settings.
     pub privacy: PrivacySettings,
+    /// Reading behavior settings.
+    #[serde(default)]
+    pub reading: ReadingSettings,
+    /// Sending behavior settings.
+    #[serde(default)]
+    pub sending: SendingSettings,
+    /// Thread list settings.
+    #[serde(default)]
+    pub thread_list: ThreadListSettings,
+    /// Database connection tuning, for advanced users.
+    #[serde(default)]
+    pub database: DatabaseOptions,
+    /// Log level and format. Environment variables take precedence.
+    #[serde(default)]
+    pub logging: LogConfig,
+}
+
+impl Settings {
+    /// Returns where settings are saved in the user's config directory, or
+    /// `None` without a home directory.
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "panbanda", "heap")
+            .map(|dirs| dirs.config_dir().join("settings.json"))
+    }
+
+    /// Reads the settings saved at `path`, or the defaults if none have been
+    /// saved yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("{} is not valid settings", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read settings from {}", path.display()))
+            }
+        }
+    }
 }
 
 /// Visual appearance configuration.
@@ -101,12 +147,30 @@ pub struct ProviderSettings {
     pub api_key_keychain_id: String,
     /// Custom API endpoint (for self-hosted or compatible APIs).
     pub base_url: Option<String>,
+    /// Extra headers sent with every request, e.g. a gateway's routing
+    /// header.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
     /// Model identifier.
     pub model: String,
     /// Sampling temperature (0.0 to 1.0).
     pub temperature: f32,
     /// Maximum tokens in response.
     pub max_tokens: Option<usize>,
+    /// Seconds before a request to the provider is abandoned.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+impl ProviderSettings {
+    /// Returns the request timeout as a duration.
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+}
+
+fn default_request_timeout_secs() -> u64 {
+    60
 }
 
 /// Settings for email summarization.
@@ -120,6 +184,13 @@ pub struct SummarySettings {
     pub system_prompt: String,
     /// Maximum summary length in characters.
     pub max_length: usize,
+    /// Fewest messages a thread needs to be summarized automatically.
+    #[serde(default = "default_summary_min_messages")]
+    pub min_messages: usize,
+    /// Fewest characters of new content a thread needs to be summarized
+    /// automatically.
+    #[serde(default = "default_summary_min_chars")]
+    pub min_chars: usize,
 }
 
 impl Default for SummarySettings {
@@ -131,10 +202,20 @@ impl Default for SummarySettings {
                 "Summarize this email thread concisely, highlighting key points and action items."
                     .to_string(),
             max_length: 500,
+            min_messages: default_summary_min_messages(),
+            min_chars: default_summary_min_chars(),
         }
     }
 }
 
+fn default_summary_min_messages() -> usize {
+    3
+}
+
+fn default_summary_min_chars() -> usize {
+    1500
+}
+
 /// Settings for AI-assisted reply composition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComposeSettings {
@@ -148,6 +229,10 @@ pub struct ComposeSettings {
     pub tone: Tone,
     /// Whether to learn from user's sent emails.
     pub learn_from_sent: bool,
+    /// Whether new messages, replies and forwards start with the sending
+    /// account's signature.
+    #[serde(default = "default_include_signature")]
+    pub include_signature: bool,
 }
 
 impl Default for ComposeSettings {
@@ -158,10 +243,15 @@ impl Default for ComposeSettings {
             system_prompt: "Draft a reply matching the user's communication style.".to_string(),
             tone: Tone::Casual,
             learn_from_sent: false,
+            include_signature: default_include_signature(),
         }
     }
 }
 
+fn default_include_signature() -> bool {
+    true
+}
+
 /// Writing tone for AI-generated content.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -187,6 +277,13 @@ pub struct SearchSettings {
     pub max_results: usize,
     /// Minimum similarity score (0.0 to 1.0).
     pub min_similarity: f32,
+    /// Similarity above which two messages are near-duplicates (0.0 to 1.0).
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+}
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.9
 }
 
 impl Default for SearchSettings {
@@ -195,6 +292,7 @@ impl Default for SearchSettings {
             enabled: true,
             max_results: 50,
             min_similarity: 0.5,
+            near_duplicate_threshold: default_near_duplicate_threshold(),
         }
     }
 }
@@ -258,6 +356,32 @@ pub struct SyncSettings {
     pub sync_on_battery: bool,
     /// Whether to sync on metered connections.
     pub sync_on_metered: bool,
+    /// Days to keep message bodies and attachments locally before evicting
+    /// them, or `None` to keep them indefinitely.
+    #[serde(default)]
+    pub body_retention_days: Option<u32>,
+    /// Days to keep threads in the trash before deleting them for good, or
+    /// `None` to keep them until emptied by hand.
+    #[serde(default = "default_purge_retention_days")]
+    pub trash_retention_days: Option<u32>,
+    /// Days to keep threads in spam before deleting them for good, or
+    /// `None` to keep them until emptied by hand.
+    #[serde(default = "default_purge_retention_days")]
+    pub spam_retention_days: Option<u32>,
+    /// Seconds before a request to a mail server is abandoned.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+impl SyncSettings {
+    /// Returns the mail server request timeout as a duration.
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+}
+
+fn default_purge_retention_days() -> Option<u32> {
+    Some(30)
 }
 
 impl Default for SyncSettings {
@@ -267,21 +391,108 @@ impl Default for SyncSettings {
             interval_seconds: 300,
             sync_on_battery: true,
             sync_on_metered: false,
+            body_retention_days: None,
+            trash_retention_days: default_purge_retention_days(),
+            spam_retention_days: default_purge_retention_days(),
+            request_timeout_secs: default_request_timeout_secs(),
         }
     }
 }
 
+/// A complete set of bindings to start from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// The Heap's own bindings.
+    #[default]
+    Default,
+    /// Gmail's shortcuts.
+    Gmail,
+    /// Mutt and Vim style keys.
+    Mutt,
+}
+
+impl Preset {
+    /// All presets, in display order.
+    pub const ALL: [Preset; 3] = [Preset::Default, Preset::Gmail, Preset::Mutt];
+
+    /// Returns the key used in settings.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Gmail => "gmail",
+            Preset::Mutt => "mutt",
+        }
+    }
+
+    /// Returns the display name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Default => "Default",
+            Preset::Gmail => "Gmail",
+            Preset::Mutt => "Mutt/Vim",
+        }
+    }
+
+    /// Parses a settings key, as returned by [`key`](Self::key).
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.key() == key)
+    }
+}
+
+/// A named sequence of command ids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyboardMacro {
+    /// Name the macro is saved under.
+    pub name: String,
+    /// Commands in the order they were run.
+    pub commands: Vec<String>,
+}
+
 /// Custom keybinding overrides.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeybindingSettings {
+    /// Binding set the overrides apply to.
+    #[serde(default)]
+    pub preset: Preset,
     /// Map of action name to key sequence.
     pub overrides: HashMap<String, String>,
+    /// Time allowed between the keys of a sequence such as `g i`, in
+    /// milliseconds.
+    #[serde(default = "default_sequence_timeout_ms")]
+    pub sequence_timeout_ms: u64,
+    /// Saved keyboard macros.
+    #[serde(default)]
+    pub macros: Vec<KeyboardMacro>,
+}
+
+impl KeybindingSettings {
+    /// Returns the time allowed between the keys of a sequence.
+    pub fn sequence_timeout(&self) -> Duration {
+        Duration::from_millis(self.sequence_timeout_ms)
+    }
+}
+
+impl Default for KeybindingSettings {
+    fn default() -> Self {
+        Self {
+            preset: Preset::default(),
+            overrides: HashMap::new(),
+            sequence_timeout_ms: default_sequence_timeout_ms(),
+            macros: Vec::new(),
+        }
+    }
+}
+
+fn default_sequence_timeout_ms() -> u64 {
+    1000
 }
 
 /// Privacy-related settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrivacySettings {
-    /// Whether to send read receipts.
+    /// Whether to offer to send read receipts when a sender asks for one.
+    /// Receipts are never sent without confirmation.
     pub read_receipts_enabled: bool,
     /// Whether to load external content (images, etc.).
     pub external_content_enabled: bool,
@@ -299,6 +510,133 @@ impl Default for PrivacySettings {
     }
 }
 
+/// Reading behavior configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingSettings {
+    /// Whether opening a thread marks it as read.
+    pub mark_read_on_open: bool,
+    /// How long a thread must stay open before it is marked read, in
+    /// milliseconds. Zero marks it read immediately.
+    pub mark_read_delay_ms: u64,
+    /// What the archive key (`e`) does with the open thread.
+    #[serde(default)]
+    pub done_action: DoneAction,
+    /// Where focus goes after a thread is archived or trashed.
+    #[serde(default)]
+    pub after_done: AfterDone,
+}
+
+impl ReadingSettings {
+    /// Returns the dwell time before an opened thread is marked read, or
+    /// `None` if opening a thread never marks it read.
+    pub fn mark_read_delay(&self) -> Option<Duration> {
+        self.mark_read_on_open
+            .then(|| Duration::from_millis(self.mark_read_delay_ms))
+    }
+}
+
+impl Default for ReadingSettings {
+    fn default() -> Self {
+        Self {
+            mark_read_on_open: true,
+            mark_read_delay_ms: 500,
+            done_action: DoneAction::default(),
+            after_done: AfterDone::default(),
+        }
+    }
+}
+
+/// Sending behavior configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SendingSettings {
+    /// Whether sent messages wait out an undo window before going out.
+    pub undo_send: bool,
+    /// Length of the undo window, in seconds.
+    pub undo_send_seconds: u64,
+}
+
+impl SendingSettings {
+    /// Returns how long a send can be undone, zero when undo is off.
+    pub fn undo_send_delay(&self) -> Duration {
+        if self.undo_send {
+            Duration::from_secs(self.undo_send_seconds)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+impl Default for SendingSettings {
+    fn default() -> Self {
+        Self {
+            undo_send: true,
+            undo_send_seconds: 10,
+        }
+    }
+}
+
+/// Thread list configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThreadListSettings {
+    /// Sort order per view, keyed by `ViewType::key`. Views not listed are
+    /// sorted newest first.
+    #[serde(default)]
+    pub sort: HashMap<String, ThreadSort>,
+    /// Weights for scoring thread importance.
+    #[serde(default)]
+    pub importance: ImportanceWeights,
+    /// Whether the inbox groups mail into bundles such as Promotions and
+    /// Social.
+    #[serde(default)]
+    pub bundles: bool,
+    /// Whether the inbox shows near-duplicate messages, such as the same
+    /// newsletter from two lists, as one entry.
+    #[serde(default)]
+    pub collapse_near_duplicates: bool,
+}
+
+impl ThreadListSettings {
+    /// Returns the sort order chosen for a view.
+    pub fn sort_for(&self, view_key: &str) -> ThreadSort {
+        self.sort.get(view_key).copied().unwrap_or_default()
+    }
+
+    /// Chooses the sort order for a view.
+    pub fn set_sort(&mut self, view_key: impl Into<String>, sort: ThreadSort) {
+        let view_key = view_key.into();
+        if sort == ThreadSort::default() {
+            self.sort.remove(&view_key);
+        } else {
+            self.sort.insert(view_key, sort);
+        }
+    }
+}
+
+/// What the archive key (`e`) does with the open thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DoneAction {
+    /// Archive the thread.
+    #[default]
+    Archive,
+    /// Move the thread to the trash.
+    Trash,
+    /// Move on to the next thread, leaving this one in place.
+    Skip,
+}
+
+/// Where focus goes after the open thread leaves the list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterDone {
+    /// Open the thread that takes its place.
+    #[default]
+    Advance,
+    /// Close the reading pane and stay on the list.
+    Stay,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +649,65 @@ mod tests {
         assert_eq!(settings.appearance.font_size, 14);
     }
 
+    #[test]
+    fn provider_request_timeout_defaults_when_missing() {
+        let json = r#"{
+            "api_key_keychain_id": "openai_api_key",
+            "base_url": null,
+            "model": "gpt-4o",
+            "temperature": 0.7,
+            "max_tokens": null
+        }"#;
+        let provider: ProviderSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(provider.request_timeout(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn undo_send_defaults_to_ten_seconds_when_missing() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("sending");
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.sending.undo_send_delay(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn undo_send_delay_is_zero_when_off() {
+        let off = SendingSettings {
+            undo_send: false,
+            ..Default::default()
+        };
+        assert_eq!(off.undo_send_delay(), Duration::ZERO);
+    }
+
+    #[test]
+    fn reading_settings_default_when_missing() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("reading");
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            settings.reading.mark_read_delay(),
+            Some(Duration::from_millis(500))
+        );
+
+        let never = ReadingSettings {
+            mark_read_on_open: false,
+            ..Default::default()
+        };
+        assert_eq!(never.mark_read_delay(), None);
+    }
+
+    #[test]
+    fn database_options_fill_in_missing_fields() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json["database"] = serde_json::json!({ "busy_timeout_ms": 10000 });
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.database.busy_timeout_ms, 10_000);
+        assert_eq!(
+            settings.database.readers,
+            DatabaseOptions::default().readers
+        );
+    }
+
     #[test]
     fn theme_serialization() {
         let theme = Theme::Dark;
@@ -349,9 +746,11 @@ mod tests {
             ProviderSettings {
                 api_key_keychain_id: "anthropic_api_key".to_string(),
                 base_url: None,
+                headers: HashMap::new(),
                 model: "claude-3-5-sonnet".to_string(),
                 temperature: 0.7,
                 max_tokens: Some(4096),
+                request_timeout_secs: 30,
             },
         );
 
@@ -408,4 +807,56 @@ mod tests {
             Some(&"a".to_string())
         );
     }
+
+    #[test]
+    fn thread_sort_is_persisted_per_view() {
+        let mut settings = Settings::default();
+        settings
+            .thread_list
+            .set_sort("inbox", ThreadSort::PriorityFirst);
+        settings
+            .thread_list
+            .set_sort("label:work", ThreadSort::UnreadFirst);
+
+        let json = serde_json::to_string(&settings).unwrap();
+        let mut restored: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.thread_list.sort_for("inbox"),
+            ThreadSort::PriorityFirst
+        );
+        assert_eq!(
+            restored.thread_list.sort_for("label:work"),
+            ThreadSort::UnreadFirst
+        );
+        assert_eq!(restored.thread_list.sort_for("sent"), ThreadSort::DateDesc);
+
+        restored.thread_list.set_sort("inbox", ThreadSort::DateDesc);
+        assert!(!restored.thread_list.sort.contains_key("inbox"));
+    }
+
+    #[test]
+    fn keybinding_sequence_timeout_defaults_when_missing() {
+        let keybindings: KeybindingSettings = serde_json::from_str(r#"{"overrides": {}}"#).unwrap();
+        assert_eq!(keybindings.sequence_timeout(), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn loading_without_a_settings_file_gives_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::load(&dir.path().join("settings.json")).unwrap();
+        assert_eq!(settings.logging, LogConfig::default());
+    }
+
+    #[test]
+    fn loading_reads_the_saved_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut saved = Settings::default();
+        saved.logging.level = "debug".into();
+        std::fs::write(&path, serde_json::to_string(&saved).unwrap()).unwrap();
+
+        let settings = Settings::load(&path).unwrap();
+
+        assert_eq!(settings.logging.level, "debug");
+    }
 }
@@ -0,0 +1,222 @@
+//! Settings export and import.
+//!
+//! An export is a JSON file holding a format version and the full settings.
+//! Secrets never appear in it: API keys live in the system keychain and
+//! settings only reference them by keychain identifier.
+//!
+//! Importing merges field by field into the current settings. Fields the
+//! running version doesn't know, or whose values don't validate, are left
+//! untouched and reported back, so a file written by a newer version still
+//! applies everything this version understands.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::settings::Settings;
+
+/// Format version written by [`Settings::export_to`].
+pub const SETTINGS_EXPORT_VERSION: u32 = 1;
+
+/// Outcome of [`Settings::import_from`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Format version of the imported file.
+    pub version: u32,
+    /// Dotted paths of fields that were not applied, e.g.
+    /// `appearance.font_size`.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SettingsExport<'a> {
+    version: u32,
+    settings: &'a Settings,
+}
+
+impl Settings {
+    /// Writes these settings to `path` as a portable export.
+    pub fn export_to(&self, path: &Path) -> Result<()> {
+        let export = SettingsExport {
+            version: SETTINGS_EXPORT_VERSION,
+            settings: self,
+        };
+        let json = serde_json::to_string_pretty(&export)?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write settings to {}", path.display()))
+    }
+
+    /// Merges the settings exported at `path` into these settings.
+    ///
+    /// Fields missing from the file keep their current values. Unknown
+    /// fields and values that fail validation are skipped and listed in the
+    /// returned report. Fails only if the file can't be read or isn't a
+    /// settings export at all.
+    pub fn import_from(&mut self, path: &Path) -> Result<ImportReport> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings from {}", path.display()))?;
+        let file: Value = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+
+        let Some(version) = file.get("version").and_then(Value::as_u64) else {
+            bail!(
+                "{} is not a settings export: missing version",
+                path.display()
+            );
+        };
+        let Some(incoming) = file.get("settings").filter(|s| s.is_object()) else {
+            bail!(
+                "{} is not a settings export: missing settings",
+                path.display()
+            );
+        };
+
+        let mut merged = serde_json::to_value(&*self)?;
+        let mut skipped = Vec::new();
+        merge_field(&mut merged, &mut Vec::new(), incoming, &mut skipped)?;
+
+        *self = serde_json::from_value(merged)?;
+        Ok(ImportReport {
+            version: u32::try_from(version).unwrap_or(u32::MAX),
+            skipped,
+        })
+    }
+}
+
+/// Merges `incoming` into `settings` at `path`.
+///
+/// Objects are merged key by key. Any other value is applied on its own and
+/// kept only if the result still deserializes and the field survives a
+/// round trip; fields the settings types don't have are dropped by serde.
+fn merge_field(
+    settings: &mut Value,
+    path: &mut Vec<String>,
+    incoming: &Value,
+    skipped: &mut Vec<String>,
+) -> Result<()> {
+    let pointer = json_pointer(path);
+
+    if let (Some(Value::Object(_)), Value::Object(fields)) = (settings.pointer(&pointer), incoming)
+    {
+        for (key, value) in fields {
+            path.push(key.clone());
+            merge_field(settings, path, value, skipped)?;
+            path.pop();
+        }
+        return Ok(());
+    }
+
+    let mut candidate = settings.clone();
+    let (key, parent) = path.split_last().expect("the root is always an object");
+    if let Some(Value::Object(fields)) = candidate.pointer_mut(&json_pointer(parent)) {
+        fields.insert(key.clone(), incoming.clone());
+    }
+
+    match serde_json::from_value::<Settings>(candidate) {
+        Ok(parsed) => {
+            let normalized = serde_json::to_value(parsed)?;
+            if normalized.pointer(&pointer).is_some() {
+                *settings = normalized;
+            } else {
+                skipped.push(path.join("."));
+            }
+        }
+        Err(_) => skipped.push(path.join(".")),
+    }
+    Ok(())
+}
+
+/// Builds an RFC 6901 JSON pointer from path segments.
+fn json_pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Theme;
+
+    #[test]
+    fn export_import_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        let mut original = Settings::default();
+        original.appearance.theme = Theme::Dark;
+        original.appearance.font_size = 16;
+        original
+            .keybindings
+            .overrides
+            .insert("archive".to_string(), "a".to_string());
+        original.export_to(&path).unwrap();
+
+        let mut imported = Settings::default();
+        let report = imported.import_from(&path).unwrap();
+
+        assert_eq!(report.version, SETTINGS_EXPORT_VERSION);
+        assert!(report.skipped.is_empty());
+        assert_eq!(imported.appearance.theme, Theme::Dark);
+        assert_eq!(imported.appearance.font_size, 16);
+        assert_eq!(
+            imported.keybindings.overrides.get("archive"),
+            Some(&"a".to_string())
+        );
+    }
+
+    #[test]
+    fn import_from_future_version_skips_unknown_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            r#"{
+                "version": 7,
+                "exported_by": "heap 9.0",
+                "settings": {
+                    "appearance": {
+                        "theme": "light",
+                        "font_size": "huge",
+                        "corner_radius": 12
+                    },
+                    "sync": { "interval_seconds": 60 },
+                    "plugins": { "enabled": ["calendar"] }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        let mut report = settings.import_from(&path).unwrap();
+        report.skipped.sort();
+
+        assert_eq!(report.version, 7);
+        assert_eq!(
+            report.skipped,
+            vec![
+                "appearance.corner_radius".to_string(),
+                "appearance.font_size".to_string(),
+                "plugins".to_string(),
+            ]
+        );
+        // Valid fields are applied; the rest keep their current values.
+        assert_eq!(settings.appearance.theme, Theme::Light);
+        assert_eq!(settings.appearance.font_size, 14);
+        assert_eq!(settings.sync.interval_seconds, 60);
+        assert!(settings.sync.enabled);
+    }
+
+    #[test]
+    fn import_rejects_files_that_are_not_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"theme": "dark"}"#).unwrap();
+
+        let mut settings = Settings::default();
+        assert!(settings.import_from(&path).is_err());
+    }
+}
@@ -2,11 +2,13 @@
 //!
 //! Represents email accounts and their provider-specific configurations.
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use super::AccountId;
+use super::{AccountId, Address};
 
 /// An email account configured in the application.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,8 +28,48 @@ pub struct Account {
     /// Interval between sync operations.
     #[serde(with = "duration_serde")]
     pub sync_interval: Duration,
-    /// Email signature for this account.
+    /// Plain-text email signature for this account.
     pub signature: Option<String>,
+    /// HTML variant of the signature, used in HTML bodies.
+    #[serde(default)]
+    pub signature_html: Option<String>,
+    /// Where the account's special folders live on the server.
+    #[serde(default)]
+    pub folder_mapping: FolderMapping,
+    /// Other verified addresses the account can send as, such as Gmail
+    /// send-as aliases.
+    #[serde(default)]
+    pub aliases: Vec<Address>,
+}
+
+impl Account {
+    /// Returns the account's own address, with its display name.
+    pub fn address(&self) -> Address {
+        Address {
+            email: self.email.clone(),
+            name: self.display_name.clone(),
+        }
+    }
+
+    /// Returns the addresses mail can be sent from: the account's own
+    /// address, then its aliases.
+    pub fn from_addresses(&self) -> Vec<Address> {
+        let mut addresses = vec![self.address()];
+        addresses.extend(self.aliases.iter().cloned());
+        addresses
+    }
+
+    /// Returns the address to send from: the account's own when `email` is
+    /// `None`, or the matching alias. Returns `None` when `email` isn't one
+    /// of the account's addresses.
+    pub fn sender(&self, email: Option<&str>) -> Option<Address> {
+        let Some(email) = email else {
+            return Some(self.address());
+        };
+        self.from_addresses()
+            .into_iter()
+            .find(|address| address.email.eq_ignore_ascii_case(email.trim()))
+    }
 }
 
 /// Type of email provider.
@@ -40,6 +82,16 @@ pub enum ProviderType {
     Imap,
 }
 
+impl ProviderType {
+    /// Returns the lowercase identifier used in serialization and credential keys.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gmail => "gmail",
+            Self::Imap => "imap",
+        }
+    }
+}
+
 /// Provider-specific configuration.
 ///
 /// OAuth tokens and passwords are stored in the system keychain,
@@ -63,9 +115,159 @@ pub enum ProviderConfig {
         smtp_port: u16,
         /// Whether to use TLS.
         use_tls: bool,
+        /// Skip TLS entirely, for local test servers.
+        #[serde(default)]
+        allow_plaintext: bool,
+        /// PEM file of extra root certificates, for servers signed by a
+        /// private CA.
+        #[serde(default)]
+        tls_root_cert: Option<PathBuf>,
+        /// Accept any server certificate, e.g. a self-signed one.
+        #[serde(default)]
+        accept_invalid_certs: bool,
     },
 }
 
+/// A folder with a special role, as flagged by RFC 6154 special-use
+/// attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialFolder {
+    /// The inbox.
+    Inbox,
+    /// Sent messages (`\Sent`).
+    Sent,
+    /// Drafts (`\Drafts`).
+    Drafts,
+    /// Deleted messages (`\Trash`).
+    Trash,
+    /// Spam (`\Junk`).
+    Junk,
+    /// Archived messages (`\Archive`).
+    Archive,
+    /// Every message (`\All`).
+    All,
+    /// Starred messages (`\Flagged`).
+    Flagged,
+}
+
+impl SpecialFolder {
+    /// Returns the role of one of the app's folder names, such as `SENT`
+    /// or `spam`.
+    pub fn from_folder_name(folder: &str) -> Option<Self> {
+        match folder.to_uppercase().as_str() {
+            "INBOX" => Some(Self::Inbox),
+            "SENT" => Some(Self::Sent),
+            "DRAFTS" => Some(Self::Drafts),
+            "TRASH" => Some(Self::Trash),
+            "SPAM" | "JUNK" => Some(Self::Junk),
+            "ARCHIVE" => Some(Self::Archive),
+            "ALL" => Some(Self::All),
+            "STARRED" => Some(Self::Flagged),
+            _ => None,
+        }
+    }
+
+    /// Returns the role named by a mailbox attribute such as `\Sent`.
+    ///
+    /// Also accepts the older XLIST names (`\Spam`, `\AllMail`,
+    /// `\Starred`, `\Inbox`) some servers still send.
+    pub fn from_attribute(attribute: &str) -> Option<Self> {
+        let name = attribute.strip_prefix('\\').unwrap_or(attribute);
+        match name.to_ascii_lowercase().as_str() {
+            "inbox" => Some(Self::Inbox),
+            "sent" => Some(Self::Sent),
+            "drafts" => Some(Self::Drafts),
+            "trash" => Some(Self::Trash),
+            "junk" | "spam" => Some(Self::Junk),
+            "archive" => Some(Self::Archive),
+            "all" | "allmail" => Some(Self::All),
+            "flagged" | "starred" => Some(Self::Flagged),
+            _ => None,
+        }
+    }
+}
+
+/// Where an account's special folders are on the server.
+///
+/// Folders are discovered from the server's special-use attributes when
+/// it advertises them, and can be overridden by the user, for servers that
+/// use names like `INBOX.Sent` without flagging them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderMapping {
+    /// Folders found from special-use attributes.
+    #[serde(default)]
+    discovered: BTreeMap<SpecialFolder, String>,
+    /// Folders set by the user, taking precedence over discovered ones.
+    #[serde(default)]
+    overrides: BTreeMap<SpecialFolder, String>,
+}
+
+impl FolderMapping {
+    /// Creates an empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a mapping from listed mailboxes and their attributes.
+    ///
+    /// When several mailboxes claim a role, the first is used.
+    pub fn discover<'a, A>(mailboxes: impl IntoIterator<Item = (&'a str, A)>) -> Self
+    where
+        A: IntoIterator<Item = &'a str>,
+    {
+        let mut mapping = Self::new();
+        mapping.set_discovered(mailboxes.into_iter().flat_map(|(path, attributes)| {
+            attributes
+                .into_iter()
+                .filter_map(SpecialFolder::from_attribute)
+                .map(move |role| (role, path.to_string()))
+        }));
+        mapping
+    }
+
+    /// Replaces the discovered folders, keeping the user's overrides.
+    ///
+    /// When several folders claim a role, the first is used.
+    pub fn set_discovered(&mut self, folders: impl IntoIterator<Item = (SpecialFolder, String)>) {
+        self.discovered.clear();
+        for (role, path) in folders {
+            self.discovered.entry(role).or_insert(path);
+        }
+    }
+
+    /// Sets the folder for a role, or with `None` goes back to the
+    /// discovered one.
+    pub fn set_override(&mut self, role: SpecialFolder, path: Option<String>) {
+        match path {
+            Some(path) => self.overrides.insert(role, path),
+            None => self.overrides.remove(&role),
+        };
+    }
+
+    /// Returns the folder path for a role, if known.
+    pub fn path(&self, role: SpecialFolder) -> Option<&str> {
+        self.overrides
+            .get(&role)
+            .or_else(|| self.discovered.get(&role))
+            .map(String::as_str)
+    }
+
+    /// Returns the role of a folder path, if it has one.
+    pub fn role_of(&self, path: &str) -> Option<SpecialFolder> {
+        self.overrides
+            .keys()
+            .chain(self.discovered.keys())
+            .copied()
+            .find(|role| self.path(*role) == Some(path))
+    }
+
+    /// Returns whether nothing is mapped.
+    pub fn is_empty(&self) -> bool {
+        self.discovered.is_empty() && self.overrides.is_empty()
+    }
+}
+
 mod duration_serde {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
     use std::time::Duration;
@@ -101,6 +303,9 @@ mod tests {
             sync_enabled: true,
             sync_interval: Duration::from_secs(300),
             signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+            aliases: vec![],
         };
 
         let json = serde_json::to_string(&account).unwrap();
@@ -110,6 +315,42 @@ mod tests {
         assert_eq!(deserialized.sync_interval, Duration::from_secs(300));
     }
 
+    #[test]
+    fn sender_is_the_account_address_or_a_verified_alias() {
+        let mut account: Account = serde_json::from_value(serde_json::json!({
+            "id": "account-1",
+            "email": "me@work.example",
+            "display_name": "Me",
+            "provider_type": "gmail",
+            "provider_config": {"type": "gmail"},
+            "sync_enabled": true,
+            "sync_interval": 300,
+            "signature": null
+        }))
+        .unwrap();
+        assert!(account.aliases.is_empty());
+        account.aliases = vec![Address::with_name("billing@work.example", "Billing")];
+
+        assert_eq!(
+            account.sender(None),
+            Some(Address::with_name("me@work.example", "Me"))
+        );
+        assert_eq!(
+            account.sender(Some("Billing@Work.example")),
+            Some(Address::with_name("billing@work.example", "Billing"))
+        );
+        assert_eq!(account.sender(Some("someone@else.example")), None);
+        assert_eq!(account.from_addresses().len(), 2);
+    }
+
+    #[test]
+    fn provider_type_as_str_matches_serde() {
+        for provider in [ProviderType::Gmail, ProviderType::Imap] {
+            let json = serde_json::to_string(&provider).unwrap();
+            assert_eq!(json, format!("\"{}\"", provider.as_str()));
+        }
+    }
+
     #[test]
     fn imap_config_serialization() {
         let config = ProviderConfig::Imap {
@@ -118,6 +359,9 @@ mod tests {
             smtp_host: "smtp.example.com".to_string(),
             smtp_port: 587,
             use_tls: true,
+            allow_plaintext: false,
+            tls_root_cert: None,
+            accept_invalid_certs: false,
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -131,6 +375,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn imap_config_without_tls_options_uses_the_defaults() {
+        let json = r#"{"type":"imap","imap_host":"imap.example.com","imap_port":993,
+            "smtp_host":"smtp.example.com","smtp_port":587,"use_tls":true}"#;
+
+        let config: ProviderConfig = serde_json::from_str(json).unwrap();
+
+        let ProviderConfig::Imap {
+            allow_plaintext,
+            tls_root_cert,
+            accept_invalid_certs,
+            ..
+        } = config
+        else {
+            panic!("Expected Imap config");
+        };
+        assert!(!allow_plaintext);
+        assert_eq!(tls_root_cert, None);
+        assert!(!accept_invalid_certs);
+    }
+
+    #[test]
+    fn folder_mapping_discovers_special_use_folders() {
+        let mapping = FolderMapping::discover([
+            ("INBOX", vec!["\\HasNoChildren"]),
+            ("INBOX.Sent Items", vec!["\\HasNoChildren", "\\Sent"]),
+            ("[Gmail]/All Mail", vec!["\\All"]),
+            ("[Gmail]/Spam", vec!["\\Spam"]),
+            ("Old Sent", vec!["\\Sent"]),
+            ("Work", vec![]),
+        ]);
+
+        assert_eq!(mapping.path(SpecialFolder::Sent), Some("INBOX.Sent Items"));
+        assert_eq!(mapping.path(SpecialFolder::All), Some("[Gmail]/All Mail"));
+        assert_eq!(mapping.path(SpecialFolder::Junk), Some("[Gmail]/Spam"));
+        assert_eq!(mapping.path(SpecialFolder::Trash), None);
+        assert_eq!(mapping.role_of("[Gmail]/Spam"), Some(SpecialFolder::Junk));
+        assert_eq!(mapping.role_of("Old Sent"), None);
+        assert_eq!(mapping.role_of("Work"), None);
+    }
+
+    #[test]
+    fn folder_mapping_overrides_take_precedence() {
+        let mut mapping = FolderMapping::discover([("Sent", ["\\Sent"])]);
+        mapping.set_override(SpecialFolder::Sent, Some("INBOX.Sent".to_string()));
+        assert_eq!(mapping.path(SpecialFolder::Sent), Some("INBOX.Sent"));
+        assert_eq!(mapping.role_of("INBOX.Sent"), Some(SpecialFolder::Sent));
+        assert_eq!(mapping.role_of("Sent"), None);
+
+        // Rediscovery keeps the override.
+        mapping.set_discovered([(SpecialFolder::Sent, "Sent Messages".to_string())]);
+        assert_eq!(mapping.path(SpecialFolder::Sent), Some("INBOX.Sent"));
+
+        mapping.set_override(SpecialFolder::Sent, None);
+        assert_eq!(mapping.path(SpecialFolder::Sent), Some("Sent Messages"));
+
+        let json = serde_json::to_string(&mapping).unwrap();
+        assert_eq!(
+            serde_json::from_str::<FolderMapping>(&json).unwrap(),
+            mapping
+        );
+    }
+
+    #[test]
+    fn folder_names_map_to_roles() {
+        assert_eq!(
+            SpecialFolder::from_folder_name("spam"),
+            Some(SpecialFolder::Junk)
+        );
+        assert_eq!(
+            SpecialFolder::from_folder_name("STARRED"),
+            Some(SpecialFolder::Flagged)
+        );
+        assert_eq!(SpecialFolder::from_folder_name("Work"), None);
+        assert_eq!(
+            SpecialFolder::from_attribute("\\AllMail"),
+            Some(SpecialFolder::All)
+        );
+        assert_eq!(SpecialFolder::from_attribute("\\HasChildren"), None);
+    }
+
     #[test]
     fn provider_type_equality() {
         assert_eq!(ProviderType::Gmail, ProviderType::Gmail);
@@ -25,6 +25,10 @@ pub struct Contact {
     pub is_vip: bool,
     /// User notes about this contact.
     pub notes: Option<String>,
+    /// Other addresses of the same person, from contacts merged into this
+    /// one.
+    #[serde(default)]
+    pub aliases: Vec<String>,
 }
 
 impl Contact {
@@ -38,6 +42,7 @@ impl Contact {
             last_contacted: None,
             is_vip: false,
             notes: None,
+            aliases: Vec::new(),
         }
     }
 
@@ -51,6 +56,7 @@ impl Contact {
             last_contacted: None,
             is_vip: false,
             notes: None,
+            aliases: Vec::new(),
         }
     }
 
@@ -59,6 +65,11 @@ impl Contact {
         self.name.as_deref().unwrap_or(&self.email)
     }
 
+    /// Returns the contact's email followed by its aliases.
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.email.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
     /// Increments the contact frequency and updates last contacted time.
     pub fn record_interaction(&mut self) {
         self.frequency = self.frequency.saturating_add(1);
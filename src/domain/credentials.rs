@@ -0,0 +1,82 @@
+//! Credential storage shared by account management and email providers.
+//!
+//! Credentials for an account live under a single key per provider,
+//! `{provider}-{account_id}` (see [`credentials_key`]), holding the JSON
+//! document the provider reads back on authentication.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use super::{AccountId, ProviderType};
+
+/// Error reported by a credential store.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct CredentialError(pub String);
+
+/// Result type for credential store operations.
+pub type CredentialResult<T> = std::result::Result<T, CredentialError>;
+
+/// Storage abstraction for credentials.
+///
+/// Both the account service and the email providers go through this trait
+/// so credentials written by one are visible to the other.
+#[async_trait]
+pub trait CredentialStore: Send + Sync {
+    /// Stores a credential.
+    async fn store(&self, key: &str, value: &str) -> CredentialResult<()>;
+
+    /// Retrieves a credential.
+    async fn retrieve(&self, key: &str) -> CredentialResult<Option<String>>;
+
+    /// Deletes a credential.
+    async fn delete(&self, key: &str) -> CredentialResult<()>;
+
+    /// Deletes every credential stored for an account.
+    ///
+    /// The default implementation removes the per-provider credential keys;
+    /// stores that can enumerate their keys should override it.
+    async fn delete_all_for_account(&self, account_id: &AccountId) -> CredentialResult<()> {
+        for provider in [ProviderType::Gmail, ProviderType::Imap] {
+            let _ = self.delete(&credentials_key(provider, account_id)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the credential key for an account's provider credentials.
+pub fn credentials_key(provider: ProviderType, account_id: &AccountId) -> String {
+    format!("{}-{}", provider.as_str(), account_id.0)
+}
+
+#[async_trait]
+impl<T: CredentialStore + ?Sized> CredentialStore for Arc<T> {
+    async fn store(&self, key: &str, value: &str) -> CredentialResult<()> {
+        (**self).store(key, value).await
+    }
+
+    async fn retrieve(&self, key: &str) -> CredentialResult<Option<String>> {
+        (**self).retrieve(key).await
+    }
+
+    async fn delete(&self, key: &str) -> CredentialResult<()> {
+        (**self).delete(key).await
+    }
+
+    async fn delete_all_for_account(&self, account_id: &AccountId) -> CredentialResult<()> {
+        (**self).delete_all_for_account(account_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_joins_provider_and_account() {
+        let key = credentials_key(ProviderType::Gmail, &AccountId::from("account-123"));
+        assert_eq!(key, "gmail-account-123");
+    }
+}
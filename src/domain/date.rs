@@ -0,0 +1,106 @@
+//! Relative timestamp formatting for thread lists and message headers.
+//!
+//! Dates are stored in UTC and shown in the viewer's timezone, so which day a
+//! message falls on is decided by the timezone of `now`.
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use std::fmt::Display;
+
+/// Formats `date` relative to `now` for compact display.
+///
+/// - Today: the time of day, e.g. `9:42 AM`
+/// - The previous calendar day: `Yesterday`
+/// - Two to six days ago: the weekday, e.g. `Monday`
+/// - Older (or in the future): the month and day, e.g. `Mar 3`, with the year
+///   appended when it differs from the current one
+pub fn format_relative<Tz>(date: DateTime<Utc>, now: DateTime<Tz>) -> String
+where
+    Tz: TimeZone,
+    Tz::Offset: Display,
+{
+    let local = date.with_timezone(&now.timezone());
+    let days_ago = (now.date_naive() - local.date_naive()).num_days();
+
+    match days_ago {
+        0 => local.format("%-I:%M %p").to_string(),
+        1 => "Yesterday".to_string(),
+        2..=6 => local.format("%A").to_string(),
+        _ if local.year() == now.year() => local.format("%b %-d").to_string(),
+        _ => local.format("%b %-d, %Y").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, FixedOffset};
+
+    /// Wednesday 2024-03-13 in UTC-5.
+    fn now_at(hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        FixedOffset::west_opt(5 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 13, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn utc(now: DateTime<FixedOffset>) -> DateTime<Utc> {
+        now.with_timezone(&Utc)
+    }
+
+    #[test]
+    fn today_shows_time() {
+        let now = now_at(15, 0);
+        assert_eq!(format_relative(utc(now_at(9, 42)), now), "9:42 AM");
+        assert_eq!(format_relative(utc(now_at(13, 5)), now), "1:05 PM");
+    }
+
+    #[test]
+    fn just_past_midnight() {
+        let now = now_at(0, 1);
+        assert_eq!(format_relative(utc(now_at(0, 0)), now), "12:00 AM");
+        assert_eq!(
+            format_relative(utc(now_at(0, 0)) - Duration::minutes(1), now),
+            "Yesterday"
+        );
+    }
+
+    #[test]
+    fn day_is_decided_in_viewer_timezone() {
+        // 02:00 UTC on the 13th is still the evening of the 12th in UTC-5.
+        let date = Utc.with_ymd_and_hms(2024, 3, 13, 2, 0, 0).unwrap();
+        assert_eq!(format_relative(date, now_at(12, 0)), "Yesterday");
+    }
+
+    #[test]
+    fn this_week_shows_weekday() {
+        let now = now_at(12, 0);
+        let two_days = utc(now) - Duration::days(2);
+        let six_days = utc(now) - Duration::days(6);
+        assert_eq!(format_relative(two_days, now), "Monday");
+        assert_eq!(format_relative(six_days, now), "Thursday");
+    }
+
+    #[test]
+    fn exactly_seven_days_ago_shows_date() {
+        let now = now_at(12, 0);
+        let date = utc(now) - Duration::days(7);
+        assert_eq!(format_relative(date, now), "Mar 6");
+    }
+
+    #[test]
+    fn older_dates_show_month_and_day() {
+        let now = now_at(12, 0);
+        let date = Utc.with_ymd_and_hms(2024, 1, 3, 17, 0, 0).unwrap();
+        assert_eq!(format_relative(date, now), "Jan 3");
+
+        let last_year = Utc.with_ymd_and_hms(2023, 12, 25, 17, 0, 0).unwrap();
+        assert_eq!(format_relative(last_year, now), "Dec 25, 2023");
+    }
+
+    #[test]
+    fn future_dates_show_date() {
+        let now = now_at(12, 0);
+        let tomorrow = utc(now) + Duration::days(1);
+        assert_eq!(format_relative(tomorrow, now), "Mar 14");
+    }
+}
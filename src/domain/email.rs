@@ -2,9 +2,12 @@
 //!
 //! Represents individual email messages and related structures.
 
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::quote::strip_quotes;
 use super::{AccountId, EmailId, LabelId, MessageId, ThreadId};
 
 /// An individual email message.
@@ -50,6 +53,51 @@ pub struct Email {
     pub labels: Vec<LabelId>,
     /// File attachments.
     pub attachments: Vec<Attachment>,
+    /// Unsubscribe options from the `List-Unsubscribe` headers.
+    ///
+    /// Populated when the message is fetched from the provider.
+    #[serde(default)]
+    pub unsubscribe: Option<UnsubscribeInfo>,
+    /// Where the sender asked for a read receipt to be sent, from the
+    /// `Disposition-Notification-To` header (RFC 8098).
+    #[serde(default)]
+    pub read_receipt_to: Option<Address>,
+}
+
+impl Email {
+    /// Returns the new content of the plain text body, without quoted
+    /// history or signature.
+    ///
+    /// Falls back to the snippet when there is no text body. The stored body
+    /// is unchanged.
+    pub fn new_content(&self) -> String {
+        match &self.body_text {
+            Some(body) => strip_quotes(body),
+            None => self.snippet.clone(),
+        }
+    }
+
+    /// Returns whether the message body is stored locally.
+    ///
+    /// Bodies of old messages may have been evicted from the local cache, in
+    /// which case only the headers and snippet remain.
+    pub fn has_body(&self) -> bool {
+        self.body_text.is_some() || self.body_html.is_some()
+    }
+
+    /// Returns whether the sender requested a read receipt.
+    pub fn requests_read_receipt(&self) -> bool {
+        self.read_receipt_to.is_some()
+    }
+
+    /// Returns the attachments that have a Content-ID, keyed by it, for
+    /// resolving `cid:` references in the HTML body.
+    pub fn attachments_by_content_id(&self) -> HashMap<&str, &Attachment> {
+        self.attachments
+            .iter()
+            .filter_map(|attachment| Some((attachment.content_id.as_deref()?, attachment)))
+            .collect()
+    }
 }
 
 /// An email address with optional display name.
@@ -78,6 +126,27 @@ impl Address {
         }
     }
 
+    /// Parses a single mailbox like `"Name" <email@example.com>` or a bare
+    /// `email@example.com`. Returns `None` if there is no address.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (email, name) = match (value.rfind('<'), value.rfind('>')) {
+            (Some(start), Some(end)) if start < end => {
+                let name = value[..start].trim().trim_matches('"').trim();
+                (&value[start + 1..end], (!name.is_empty()).then_some(name))
+            }
+            _ => (value, None),
+        };
+        let email = email.trim();
+        if !email.contains('@') {
+            return None;
+        }
+        Some(Self {
+            email: email.to_string(),
+            name: name.map(str::to_string),
+        })
+    }
+
     /// Returns the display representation of this address.
     ///
     /// If a name is present, returns `"Name <email>"`, otherwise just the email.
@@ -102,6 +171,114 @@ pub struct Attachment {
     pub size_bytes: u64,
     /// Whether this is an inline attachment (e.g., embedded image).
     pub is_inline: bool,
+    /// SHA-256 of the content in the blob store, once downloaded.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Content-ID without angle brackets, by which the HTML body refers to
+    /// an inline part as `cid:...`.
+    #[serde(default)]
+    pub content_id: Option<String>,
+}
+
+/// Unsubscribe options advertised by a mailing list (RFC 2369, RFC 8058).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsubscribeInfo {
+    /// First HTTP(S) unsubscribe URL.
+    pub url: Option<String>,
+    /// First `mailto:` unsubscribe URI, including any subject or body.
+    pub mailto: Option<String>,
+    /// Whether `url` accepts an RFC 8058 one-click POST.
+    pub one_click: bool,
+}
+
+impl UnsubscribeInfo {
+    /// Parses `List-Unsubscribe` and, if present, `List-Unsubscribe-Post`.
+    ///
+    /// The header is a comma-separated list of angle-bracketed URIs, e.g.
+    /// `<mailto:leave@example.com>, <https://example.com/u/123>`. One-click
+    /// unsubscribe requires an HTTPS URL and a `List-Unsubscribe=One-Click`
+    /// post header. Returns `None` if no usable URI is found.
+    pub fn parse(list_unsubscribe: &str, list_unsubscribe_post: Option<&str>) -> Option<Self> {
+        let mut url = None;
+        let mut mailto = None;
+
+        for part in list_unsubscribe.split(',') {
+            let uri: String = part
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .chars()
+                // Folded headers may leave whitespace inside the brackets.
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            let scheme = uri
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            match scheme.as_str() {
+                "https" | "http" if url.is_none() => url = Some(uri),
+                "mailto" if mailto.is_none() => mailto = Some(uri),
+                _ => {}
+            }
+        }
+
+        if url.is_none() && mailto.is_none() {
+            return None;
+        }
+
+        let one_click = list_unsubscribe_post.is_some_and(|post| {
+            post.trim()
+                .eq_ignore_ascii_case("List-Unsubscribe=One-Click")
+        }) && url.as_deref().is_some_and(|u| {
+            u.get(..8)
+                .is_some_and(|s| s.eq_ignore_ascii_case("https://"))
+        });
+
+        Some(Self {
+            url,
+            mailto,
+            one_click,
+        })
+    }
+
+    /// Returns the recipient address of the `mailto:` URI, if any.
+    pub fn mailto_address(&self) -> Option<&str> {
+        let uri = self.mailto.as_deref()?;
+        let address = uri.get(7..)?.split('?').next()?;
+        (!address.is_empty()).then_some(address)
+    }
+}
+
+/// Returns an unread email `id` in thread `thread_id` of `account-1`, from
+/// sender@example.com to me@example.com, for tests to adjust with struct
+/// update syntax.
+#[cfg(test)]
+pub(crate) fn test_email(id: &str, thread_id: &str) -> Email {
+    Email {
+        id: EmailId::from(id),
+        account_id: AccountId::from("account-1"),
+        thread_id: ThreadId::from(thread_id),
+        message_id: MessageId::from(format!("<{}@example.com>", id)),
+        in_reply_to: None,
+        references: vec![],
+        from: Address::new("sender@example.com"),
+        to: vec![Address::new("me@example.com")],
+        cc: vec![],
+        bcc: vec![],
+        subject: Some("Hello".to_string()),
+        body_text: Some("Hello".to_string()),
+        body_html: None,
+        snippet: "Hello".to_string(),
+        date: Utc::now(),
+        is_read: false,
+        is_starred: false,
+        is_draft: false,
+        labels: vec![],
+        attachments: vec![],
+        unsubscribe: None,
+        read_receipt_to: None,
+    }
 }
 
 #[cfg(test)]
@@ -127,6 +304,67 @@ mod tests {
         assert_eq!(addr1, addr2);
     }
 
+    #[test]
+    fn address_parse() {
+        assert_eq!(
+            Address::parse("\"Jane Doe\" <jane@example.com>"),
+            Some(Address::with_name("jane@example.com", "Jane Doe"))
+        );
+        assert_eq!(
+            Address::parse(" <jane@example.com>\r\n"),
+            Some(Address::new("jane@example.com"))
+        );
+        assert_eq!(
+            Address::parse("jane@example.com"),
+            Some(Address::new("jane@example.com"))
+        );
+        assert_eq!(Address::parse("undisclosed-recipients"), None);
+    }
+
+    #[test]
+    fn unsubscribe_parses_url_and_mailto() {
+        let info = UnsubscribeInfo::parse(
+            "<mailto:leave@lists.example.com?subject=unsubscribe>,\r\n <https://example.com/u/abc>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+
+        assert_eq!(info.url.as_deref(), Some("https://example.com/u/abc"));
+        assert_eq!(
+            info.mailto.as_deref(),
+            Some("mailto:leave@lists.example.com?subject=unsubscribe")
+        );
+        assert_eq!(info.mailto_address(), Some("leave@lists.example.com"));
+        assert!(info.one_click);
+    }
+
+    #[test]
+    fn unsubscribe_mailto_only() {
+        let info = UnsubscribeInfo::parse("<mailto:leave@example.com>", None).unwrap();
+        assert_eq!(info.url, None);
+        assert_eq!(info.mailto_address(), Some("leave@example.com"));
+        assert!(!info.one_click);
+    }
+
+    #[test]
+    fn unsubscribe_one_click_requires_https_and_post_header() {
+        let http = UnsubscribeInfo::parse(
+            "<http://example.com/u/abc>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert!(!http.one_click);
+
+        let no_post = UnsubscribeInfo::parse("<https://example.com/u/abc>", None).unwrap();
+        assert!(!no_post.one_click);
+    }
+
+    #[test]
+    fn unsubscribe_without_usable_uri() {
+        assert!(UnsubscribeInfo::parse("", None).is_none());
+        assert!(UnsubscribeInfo::parse("<ftp://example.com/leave>", None).is_none());
+    }
+
     #[test]
     fn attachment_serialization() {
         let attachment = Attachment {
@@ -135,6 +373,8 @@ mod tests {
             content_type: "application/pdf".to_string(),
             size_bytes: 1024,
             is_inline: false,
+            content_hash: None,
+            content_id: None,
         };
 
         let json = serde_json::to_string(&attachment).unwrap();
@@ -170,6 +410,8 @@ mod tests {
             is_draft: false,
             labels: vec![LabelId::from("INBOX")],
             attachments: vec![],
+            unsubscribe: None,
+            read_receipt_to: None,
         };
 
         assert_eq!(email.references.len(), 2);
@@ -0,0 +1,190 @@
+//! Image references in HTML bodies.
+//!
+//! Inline images are parts of the message referenced from the HTML as
+//! `cid:` URLs (RFC 2392). They are local, so they are always shown; remote
+//! images are only loaded when the user allows external images.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+
+/// Normalizes a Content-ID header value or `cid:` URL body for lookups,
+/// removing angle brackets and percent-encoding.
+pub fn normalize_content_id(id: &str) -> String {
+    let id = percent_decode(id.trim());
+    let id = id.strip_prefix('<').unwrap_or(&id);
+    id.strip_suffix('>').unwrap_or(id).to_string()
+}
+
+/// Returns the Content-IDs referenced by `cid:` image sources, in order of
+/// first appearance.
+pub fn referenced_content_ids(html: &str) -> Vec<String> {
+    let mut ids: Vec<String> = vec![];
+    for source in image_sources(html) {
+        if let Some(id) = cid(&html[source]) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Rewrites image sources for display.
+///
+/// `cid:` sources are replaced by the matching URL in `inline`, keyed by
+/// normalized Content-ID, typically a [`data_url`]. Remote sources are
+/// removed unless `allow_remote` is set.
+pub fn resolve_image_sources(
+    html: &str,
+    inline: &HashMap<String, String>,
+    allow_remote: bool,
+) -> String {
+    let mut resolved = String::with_capacity(html.len());
+    let mut copied = 0;
+    for source in image_sources(html) {
+        let value = &html[source.clone()];
+        let replacement = match cid(value) {
+            Some(id) => inline.get(&id).map(String::as_str),
+            None if !allow_remote && is_remote(value) => Some(""),
+            None => None,
+        };
+        if let Some(replacement) = replacement {
+            resolved.push_str(&html[copied..source.start]);
+            resolved.push_str(replacement);
+            copied = source.end;
+        }
+    }
+    resolved.push_str(&html[copied..]);
+    resolved
+}
+
+/// Encodes content as a `data:` URL.
+pub fn data_url(content_type: &str, bytes: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        content_type,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// Byte ranges of the values of `src` attributes, without quotes.
+fn image_sources(html: &str) -> Vec<std::ops::Range<usize>> {
+    let lower = html.to_ascii_lowercase();
+    let mut sources = vec![];
+    let mut from = 0;
+    while let Some(found) = lower[from..].find("src") {
+        let start = from + found;
+        from = start + 3;
+        // Skip names that merely end in "src", like data-src.
+        if lower[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            continue;
+        }
+        let rest = lower[from..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value_start = html.len() - rest.trim_start().len();
+        let value = &html[value_start..];
+        let range = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+                Some(end) => value_start + 1..value_start + 1 + end,
+                None => continue,
+            },
+            Some(_) => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(value.len());
+                value_start..value_start + end
+            }
+            None => continue,
+        };
+        from = range.end;
+        sources.push(range);
+    }
+    sources
+}
+
+fn cid(source: &str) -> Option<String> {
+    let source = source.trim();
+    source
+        .get(..4)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("cid:"))
+        .map(|_| normalize_content_id(&source[4..]))
+}
+
+fn is_remote(source: &str) -> bool {
+    let source = source.trim().to_ascii_lowercase();
+    source.starts_with("http:") || source.starts_with("https:") || source.starts_with("//")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"<p>Hi</p><img src="cid:logo@example.com" alt="logo">
+<img data-src="cid:ignored"><IMG SRC='https://tracker.example.com/p.gif'>
+<img src=cid:%3Cchart@example.com%3E>"#;
+
+    #[test]
+    fn finds_referenced_content_ids() {
+        assert_eq!(
+            referenced_content_ids(HTML),
+            ["logo@example.com", "chart@example.com"]
+        );
+    }
+
+    #[test]
+    fn resolves_inline_and_blocks_remote_images() {
+        let inline = HashMap::from([(
+            "logo@example.com".to_string(),
+            data_url("image/png", b"png"),
+        )]);
+
+        let blocked = resolve_image_sources(HTML, &inline, false);
+        assert!(blocked.contains(r#"<img src="data:image/png;base64,cG5n" alt="logo">"#));
+        assert!(blocked.contains("<IMG SRC=''>"));
+        // Unknown parts are left alone.
+        assert!(blocked.contains("src=cid:%3Cchart@example.com%3E"));
+
+        let allowed = resolve_image_sources(HTML, &inline, true);
+        assert!(allowed.contains("https://tracker.example.com/p.gif"));
+        assert!(allowed.contains("data:image/png;base64,cG5n"));
+    }
+
+    #[test]
+    fn normalizes_content_ids() {
+        assert_eq!(
+            normalize_content_id(" <part1@example.com> "),
+            "part1@example.com"
+        );
+        assert_eq!(normalize_content_id("part%401"), "part@1");
+    }
+}
@@ -0,0 +1,210 @@
+//! Thread importance scoring.
+//!
+//! Combines what is known about a thread into a single score between 0 and
+//! 1, used to rank threads, pick notification priority and fill the
+//! Important smart view.
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ThreadSummary, IMPORTANT_LABEL};
+
+/// What is known about a thread when scoring its importance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportanceSignals {
+    /// The sender is a VIP contact.
+    pub vip_sender: bool,
+    /// The sender is someone the user corresponds with often.
+    pub frequent_sender: bool,
+    /// The user is in the To of a message, not just Cc or Bcc.
+    pub addressed_to_me: bool,
+    /// The user has replied in the thread before.
+    pub replied: bool,
+    /// Time since the latest message.
+    pub age: Duration,
+    /// The thread was classified as needing a reply.
+    pub needs_reply: bool,
+    /// The thread was classified as a newsletter.
+    pub newsletter: bool,
+    /// The thread has the [`IMPORTANT_LABEL`], e.g. from Gmail.
+    pub marked_important: bool,
+}
+
+impl Default for ImportanceSignals {
+    fn default() -> Self {
+        Self {
+            vip_sender: false,
+            frequent_sender: false,
+            addressed_to_me: false,
+            replied: false,
+            age: Duration::zero(),
+            needs_reply: false,
+            newsletter: false,
+            marked_important: false,
+        }
+    }
+}
+
+impl ImportanceSignals {
+    /// Returns the signals a summary carries on its own, without knowing
+    /// the user's contacts, replies or the thread's category.
+    pub fn of(summary: &ThreadSummary) -> Self {
+        Self {
+            age: Utc::now() - summary.last_message_date,
+            marked_important: summary.is_important(),
+            ..Self::default()
+        }
+    }
+}
+
+/// How much each signal contributes to a thread's importance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportanceWeights {
+    /// Added when the sender is a VIP contact.
+    pub vip_sender: f32,
+    /// Added when the sender is a frequent contact.
+    pub frequent_sender: f32,
+    /// Added when the user is in To.
+    pub addressed_to_me: f32,
+    /// Added when the user has replied in the thread.
+    pub replied: f32,
+    /// Added for a brand new thread, halving every
+    /// [`recency_half_life_hours`](Self::recency_half_life_hours).
+    pub recency: f32,
+    /// Hours for the recency bonus to halve.
+    pub recency_half_life_hours: u32,
+    /// Added when the thread needs a reply.
+    pub needs_reply: f32,
+    /// Added when the thread is a newsletter; negative to demote them.
+    pub newsletter: f32,
+    /// Added when the thread is marked important.
+    pub marked_important: f32,
+}
+
+impl Default for ImportanceWeights {
+    fn default() -> Self {
+        Self {
+            vip_sender: 0.35,
+            frequent_sender: 0.15,
+            addressed_to_me: 0.2,
+            replied: 0.15,
+            recency: 0.15,
+            recency_half_life_hours: 24,
+            needs_reply: 0.1,
+            newsletter: -0.4,
+            marked_important: 0.1,
+        }
+    }
+}
+
+impl ImportanceWeights {
+    /// Scores a thread, from 0 (unimportant) to 1.
+    pub fn score(&self, signals: &ImportanceSignals) -> f32 {
+        let flags = [
+            (signals.vip_sender, self.vip_sender),
+            (signals.frequent_sender, self.frequent_sender),
+            (signals.addressed_to_me, self.addressed_to_me),
+            (signals.replied, self.replied),
+            (signals.needs_reply, self.needs_reply),
+            (signals.newsletter, self.newsletter),
+            (signals.marked_important, self.marked_important),
+        ];
+        let mut score: f32 = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, weight)| weight)
+            .sum();
+
+        let age_hours = signals.age.num_minutes().max(0) as f32 / 60.0;
+        let half_life = self.recency_half_life_hours.max(1) as f32;
+        score += self.recency * 0.5_f32.powf(age_hours / half_life);
+
+        score.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direct_mail_from_frequent_contact_outranks_cc_newsletter() {
+        let weights = ImportanceWeights::default();
+        let direct = ImportanceSignals {
+            frequent_sender: true,
+            addressed_to_me: true,
+            age: Duration::hours(3),
+            ..Default::default()
+        };
+        let newsletter = ImportanceSignals {
+            newsletter: true,
+            age: Duration::minutes(5),
+            ..Default::default()
+        };
+
+        assert!(weights.score(&direct) > weights.score(&newsletter));
+        assert_eq!(weights.score(&newsletter), 0.0);
+    }
+
+    #[test]
+    fn recency_bonus_halves_each_half_life() {
+        let weights = ImportanceWeights {
+            recency: 0.4,
+            ..Default::default()
+        };
+        let at = |hours| {
+            weights.score(&ImportanceSignals {
+                age: Duration::hours(hours),
+                ..Default::default()
+            })
+        };
+
+        assert!((at(0) - 0.4).abs() < 1e-6);
+        assert!((at(24) - 0.2).abs() < 1e-6);
+        assert!((at(48) - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn score_is_clamped_to_unit_range() {
+        let weights = ImportanceWeights::default();
+        let everything = ImportanceSignals {
+            vip_sender: true,
+            frequent_sender: true,
+            addressed_to_me: true,
+            replied: true,
+            needs_reply: true,
+            ..Default::default()
+        };
+        assert_eq!(weights.score(&everything), 1.0);
+    }
+
+    #[test]
+    fn weights_fill_in_missing_fields() {
+        let weights: ImportanceWeights = serde_json::from_str(r#"{"vip_sender": 0.9}"#).unwrap();
+        assert_eq!(weights.vip_sender, 0.9);
+        assert_eq!(weights.recency_half_life_hours, 24);
+    }
+
+    #[test]
+    fn summary_signals_count_the_important_label() {
+        let summary = ThreadSummary {
+            id: crate::domain::ThreadId::from("thread-1"),
+            account_id: crate::domain::AccountId::from("account-1"),
+            subject: None,
+            snippet: String::new(),
+            from: crate::domain::Address::new("sender@example.com"),
+            last_message_date: Utc::now() - Duration::days(30),
+            message_count: 1,
+            unread_count: 0,
+            is_starred: false,
+            labels: vec![crate::domain::LabelId::from(IMPORTANT_LABEL)],
+            muted: false,
+            watched: false,
+        };
+        let weights = ImportanceWeights::default();
+
+        assert!(ImportanceSignals::of(&summary).marked_important);
+        assert!((weights.score(&ImportanceSignals::of(&summary)) - 0.1).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,273 @@
+//! Lightweight language detection for incoming mail.
+//!
+//! Detection runs locally so it can decide whether to offer a translation
+//! without sending anything to an AI provider. Text in a non-Latin script is
+//! identified by its script; Latin-script text is scored against short lists
+//! of common words and distinctive letters for each supported language.
+
+/// Language code returned when detection is inconclusive (BCP 47
+/// "undetermined").
+pub const UNDETERMINED_LANGUAGE: &str = "und";
+
+/// Common words per Latin-script language, by ISO 639-1 code.
+const COMMON_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "you", "that", "this", "with", "for", "have", "not", "will",
+            "of", "to", "your", "we", "hello", "hi", "thanks",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "que", "de", "y", "es", "por", "para", "con", "una", "del",
+            "está", "pero", "muy", "hola", "gracias", "saludos",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le",
+            "la",
+            "les",
+            "et",
+            "est",
+            "je",
+            "vous",
+            "nous",
+            "pour",
+            "dans",
+            "une",
+            "des",
+            "pas",
+            "que",
+            "avec",
+            "sur",
+            "bonjour",
+            "merci",
+            "cordialement",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "ich", "sie", "nicht", "mit", "ein", "eine", "für",
+            "wir", "zu", "auf", "den", "dem", "hallo", "danke",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "gli", "che", "di", "è", "per", "non", "sono", "una", "con", "della",
+            "questo", "ciao", "grazie", "saluti",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "que", "de", "é", "não", "para", "com", "uma", "um", "você", "em",
+            "muito", "olá", "obrigado", "obrigada",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "ik", "je", "niet", "van", "met", "voor", "wij", "dat",
+            "op", "zijn", "ook", "bedankt", "groeten",
+        ],
+    ),
+];
+
+/// Letters that only appear in one of the supported Latin-script languages.
+const DISTINCTIVE_LETTERS: &[(&str, &[char])] = &[
+    ("es", &['ñ', '¿', '¡']),
+    ("pt", &['ã', 'õ']),
+    ("de", &['ß', 'ä', 'ö', 'ü']),
+];
+
+/// Detects the language of `text`, returning its ISO 639-1 code.
+///
+/// Returns `None` when the text is too short or too mixed to tell.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut letters = 0;
+    let mut scripts: Vec<(&'static str, usize)> = Vec::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if let Some(language) = script_language(c) {
+            match scripts.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, count)) => *count += 1,
+                None => scripts.push((language, 1)),
+            }
+        }
+    }
+
+    let non_latin: usize = scripts.iter().map(|(_, count)| count).sum();
+    if non_latin * 2 > letters {
+        // Japanese mixes kana with Chinese characters; any kana settles it.
+        if scripts.iter().any(|(l, _)| *l == "ja") {
+            return Some("ja");
+        }
+        return scripts
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(l, _)| *l);
+    }
+
+    detect_latin_language(text)
+}
+
+/// Returns the English name of a language code such as `fr` or `pt-BR`.
+pub fn language_name(code: &str) -> Option<&'static str> {
+    let primary = code.split(['-', '_']).next()?.to_lowercase();
+    let name = match primary.as_str() {
+        "en" => "English",
+        "es" => "Spanish",
+        "fr" => "French",
+        "de" => "German",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "nl" => "Dutch",
+        "ru" => "Russian",
+        "el" => "Greek",
+        "ar" => "Arabic",
+        "he" => "Hebrew",
+        "hi" => "Hindi",
+        "th" => "Thai",
+        "zh" => "Chinese",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Returns the language a character's script identifies, for scripts that
+/// are written by essentially one language here.
+fn script_language(c: char) -> Option<&'static str> {
+    let language = match c as u32 {
+        0x0370..=0x03FF => "el",
+        0x0400..=0x04FF => "ru",
+        0x0590..=0x05FF => "he",
+        0x0600..=0x06FF => "ar",
+        0x0900..=0x097F => "hi",
+        0x0E00..=0x0E7F => "th",
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => "ko",
+        0x3040..=0x30FF => "ja",
+        0x4E00..=0x9FFF => "zh",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Scores Latin-script text against each language's common words and
+/// distinctive letters.
+fn detect_latin_language(text: &str) -> Option<&'static str> {
+    let lowercase = text.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut scores: Vec<(&'static str, usize)> = COMMON_WORDS
+        .iter()
+        .map(|(language, common)| {
+            let hits = words.iter().filter(|w| common.contains(w)).count();
+            (*language, hits)
+        })
+        .collect();
+    for (language, letters) in DISTINCTIVE_LETTERS {
+        if lowercase.contains(*letters) {
+            if let Some((_, score)) = scores.iter_mut().find(|(l, _)| l == language) {
+                *score += 2;
+            }
+        }
+    }
+
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    match scores.as_slice() {
+        [(language, best), (_, second), ..] if *best >= 2 && best > second => Some(*language),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_latin_languages() {
+        let samples = [
+            (
+                "en",
+                "Hi Sam, thanks for the notes. I will have the report ready for you.",
+            ),
+            (
+                "es",
+                "Hola Ana, gracias por la información. ¿Podemos hablar mañana?",
+            ),
+            (
+                "fr",
+                "Bonjour, merci pour votre message. Je vous envoie les documents.",
+            ),
+            (
+                "de",
+                "Hallo Jonas, danke für die Nachricht. Ich schicke dir das Angebot.",
+            ),
+            (
+                "it",
+                "Ciao Marco, grazie per il messaggio. Questo è il documento.",
+            ),
+            (
+                "pt",
+                "Olá João, obrigado pela mensagem. Você recebeu o contrato?",
+            ),
+            (
+                "nl",
+                "Hallo Pieter, bedankt voor je bericht. Ik stuur het contract ook.",
+            ),
+        ];
+        for (expected, text) in samples {
+            assert_eq!(detect_language(text), Some(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn detects_scripts() {
+        assert_eq!(
+            detect_language("Здравствуйте, спасибо за письмо."),
+            Some("ru")
+        );
+        assert_eq!(detect_language("会议改到明天下午三点。"), Some("zh"));
+        assert_eq!(detect_language("明日の会議は三時からです。"), Some("ja"));
+        assert_eq!(
+            detect_language("내일 회의는 세 시에 시작합니다."),
+            Some("ko")
+        );
+        assert_eq!(
+            detect_language("Καλημέρα, ευχαριστώ για το μήνυμα."),
+            Some("el")
+        );
+        assert_eq!(detect_language("شكرا على رسالتك"), Some("ar"));
+    }
+
+    #[test]
+    fn inconclusive_text() {
+        assert_eq!(detect_language(""), None);
+        assert_eq!(detect_language("12:30 / 42"), None);
+        assert_eq!(detect_language("Invoice INV-2041"), None);
+        // A Cyrillic name in English text doesn't make it Russian.
+        assert_eq!(
+            detect_language("Thanks to Дмитрий for the review, you have the final version."),
+            Some("en")
+        );
+    }
+
+    #[test]
+    fn language_names() {
+        assert_eq!(language_name("en"), Some("English"));
+        assert_eq!(language_name("pt-BR"), Some("Portuguese"));
+        assert_eq!(language_name("de_DE"), Some("German"));
+        assert_eq!(language_name("tlh"), None);
+    }
+}
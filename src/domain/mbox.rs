@@ -0,0 +1,258 @@
+//! Reading and writing mbox files.
+//!
+//! Messages are written in the mboxrd variant: each message starts with a
+//! `From ` separator line, and body lines that would look like a separator
+//! (`From `, `>From `, ...) gain one more `>`. Reading reverses the
+//! quoting, so message bodies round-trip unchanged.
+
+use std::io::{self, BufRead, Write};
+
+use base64::Engine;
+
+use super::{Address, Email, MessageId};
+
+/// Writes `email` to `out` as one mbox entry, separator line included.
+pub fn write_mbox_message(out: &mut impl Write, email: &Email) -> io::Result<()> {
+    writeln!(
+        out,
+        "From {} {}",
+        if email.from.email.is_empty() {
+            "MAILER-DAEMON"
+        } else {
+            &email.from.email
+        },
+        email.date.format("%a %b %e %H:%M:%S %Y")
+    )?;
+    for line in message_lines(email) {
+        if is_separator(line.trim_start_matches('>')) {
+            out.write_all(b">")?;
+        }
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out)
+}
+
+/// Formats `email` as an RFC 5322 message, one line per item.
+fn message_lines(email: &Email) -> Vec<String> {
+    let mut lines = vec![
+        format!("Message-ID: {}", bracketed(&email.message_id)),
+        format!("Date: {}", email.date.to_rfc2822()),
+        format!("From: {}", encode_address(&email.from)),
+    ];
+    for (name, addresses) in [("To", &email.to), ("Cc", &email.cc)] {
+        if !addresses.is_empty() {
+            let list: Vec<String> = addresses.iter().map(encode_address).collect();
+            lines.push(format!("{}: {}", name, list.join(", ")));
+        }
+    }
+    if let Some(subject) = &email.subject {
+        lines.push(format!("Subject: {}", encode_word(subject)));
+    }
+    if let Some(in_reply_to) = &email.in_reply_to {
+        lines.push(format!("In-Reply-To: {}", bracketed(in_reply_to)));
+    }
+    if !email.references.is_empty() {
+        let references: Vec<String> = email.references.iter().map(bracketed).collect();
+        lines.push(format!("References: {}", references.join(" ")));
+    }
+    lines.push("MIME-Version: 1.0".to_string());
+
+    let text = email.body_text.as_deref();
+    let html = email.body_html.as_deref();
+    match (text, html) {
+        (Some(text), Some(html)) => {
+            let boundary = format!("=_{}", uuid::Uuid::new_v4().simple());
+            lines.push(format!(
+                "Content-Type: multipart/alternative; boundary=\"{}\"",
+                boundary
+            ));
+            lines.push(String::new());
+            for (subtype, body) in [("plain", text), ("html", html)] {
+                lines.push(format!("--{}", boundary));
+                push_part(&mut lines, subtype, body);
+            }
+            lines.push(format!("--{}--", boundary));
+        }
+        (None, Some(html)) => push_part(&mut lines, "html", html),
+        (text, None) => push_part(&mut lines, "plain", text.unwrap_or(&email.snippet)),
+    }
+    lines
+}
+
+/// Appends the headers and body of one text part.
+fn push_part(lines: &mut Vec<String>, subtype: &str, body: &str) {
+    lines.push(format!("Content-Type: text/{}; charset=utf-8", subtype));
+    let encoding = if body.is_ascii() { "7bit" } else { "8bit" };
+    lines.push(format!("Content-Transfer-Encoding: {}", encoding));
+    lines.push(String::new());
+    lines.extend(body.lines().map(str::to_string));
+}
+
+/// Returns whether a line would be read as a message separator.
+fn is_separator(line: &str) -> bool {
+    line.starts_with("From ")
+}
+
+/// Formats a Message-ID in angle brackets.
+fn bracketed(id: &MessageId) -> String {
+    format!(
+        "<{}>",
+        id.0.trim().trim_start_matches('<').trim_end_matches('>')
+    )
+}
+
+/// Formats an address for a header, encoding a non-ASCII display name.
+fn encode_address(address: &Address) -> String {
+    match &address.name {
+        Some(name) if name.is_ascii() => {
+            let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("\"{}\" <{}>", name, address.email)
+        }
+        Some(name) => format!("{} <{}>", encode_word(name), address.email),
+        None => format!("<{}>", address.email),
+    }
+}
+
+/// Encodes a header value as an RFC 2047 encoded word when it isn't plain
+/// ASCII.
+fn encode_word(value: &str) -> String {
+    if value.is_ascii() && !value.contains(['\r', '\n']) {
+        return value.to_string();
+    }
+    format!(
+        "=?utf-8?b?{}?=",
+        base64::engine::general_purpose::STANDARD.encode(value)
+    )
+}
+
+/// Iterates over the raw messages in an mbox file.
+///
+/// Separator lines are dropped and `>From ` quoting is undone, so each item
+/// is an RFC 5322 message ready for a MIME parser.
+pub struct MboxReader<R> {
+    reader: R,
+    /// Whether the separator of the message being read has been consumed.
+    started: bool,
+    done: bool,
+}
+
+impl<R: BufRead> MboxReader<R> {
+    /// Creates a reader over mbox data.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MboxReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut message = Vec::new();
+        let mut line = Vec::new();
+        while !self.done {
+            line.clear();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => self.done = true,
+                Ok(_) if line.starts_with(b"From ") => {
+                    if self.started {
+                        // The blank line before a separator belongs to it.
+                        if message.ends_with(b"\n\n") {
+                            message.pop();
+                        } else if message.ends_with(b"\r\n\r\n") {
+                            message.truncate(message.len() - 2);
+                        }
+                        return Some(Ok(message));
+                    }
+                    self.started = true;
+                }
+                Ok(_) if !self.started => {}
+                Ok(_) => {
+                    let quotes = line.iter().take_while(|&&b| b == b'>').count();
+                    if quotes > 0 && line[quotes..].starts_with(b"From ") {
+                        message.extend_from_slice(&line[1..]);
+                    } else {
+                        message.extend_from_slice(&line);
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        if !self.started || message.is_empty() {
+            return None;
+        }
+        if message.ends_with(b"\n\n") {
+            message.pop();
+        }
+        self.started = false;
+        Some(Ok(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::test_email;
+    use chrono::{TimeZone, Utc};
+
+    fn email(id: &str, body: &str) -> Email {
+        Email {
+            from: Address::with_name("ada@example.com", "Ada"),
+            to: vec![Address::new("grace@example.com")],
+            subject: Some("Notes".to_string()),
+            body_text: Some(body.to_string()),
+            snippet: String::new(),
+            date: Utc.with_ymd_and_hms(2024, 3, 13, 9, 30, 0).unwrap(),
+            is_read: true,
+            ..test_email(id, "thread-1")
+        }
+    }
+
+    fn body_of(raw: &[u8]) -> String {
+        let raw = String::from_utf8(raw.to_vec()).unwrap();
+        raw.split_once("\n\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn separator_lines_in_bodies_are_quoted() {
+        let body = "Hi,\nFrom the notes:\n>From before\nbye";
+        let mut out = Vec::new();
+        write_mbox_message(&mut out, &email("one", body)).unwrap();
+        write_mbox_message(&mut out, &email("two", "Second")).unwrap();
+
+        let mbox = String::from_utf8(out.clone()).unwrap();
+        assert!(mbox.starts_with("From ada@example.com Wed Mar 13 09:30:00 2024\n"));
+        assert!(mbox.contains("\n>From the notes:\n>>From before\n"));
+
+        let messages: Vec<Vec<u8>> = MboxReader::new(out.as_slice())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(body_of(&messages[0]), format!("{}\n", body));
+        assert_eq!(body_of(&messages[1]), "Second\n");
+    }
+
+    #[test]
+    fn headers_are_encoded() {
+        let mut message = email("one", "Body");
+        message.subject = Some("Café plans".to_string());
+        message.from = Address::with_name("jose@example.com", "José");
+        message.in_reply_to = Some(MessageId::from("parent@example.com"));
+        message.references = vec![MessageId::from("<root@example.com>")];
+
+        let mut out = Vec::new();
+        write_mbox_message(&mut out, &message).unwrap();
+        let mbox = String::from_utf8(out).unwrap();
+        assert!(mbox.contains("\nMessage-ID: <one@example.com>\n"));
+        assert!(mbox.contains("\nDate: Wed, 13 Mar 2024 09:30:00 +0000\n"));
+        assert!(mbox.contains("\nFrom: =?utf-8?b?Sm9zw6k=?= <jose@example.com>\n"));
+        assert!(mbox.contains("\nTo: <grace@example.com>\n"));
+        assert!(mbox.contains("\nSubject: =?utf-8?b?Q2Fmw6kgcGxhbnM=?=\n"));
+        assert!(mbox.contains("\nIn-Reply-To: <parent@example.com>\n"));
+        assert!(mbox.contains("\nReferences: <root@example.com>\n"));
+    }
+}
@@ -5,19 +5,40 @@
 
 mod account;
 mod contact;
+mod credentials;
+mod date;
 mod email;
+mod html;
+mod importance;
 mod label;
+mod language;
+mod mbox;
+mod quote;
 mod screener;
+mod template;
+mod text;
 mod thread;
 mod types;
 
-pub use account::{Account, ProviderConfig, ProviderType};
+pub use account::{Account, FolderMapping, ProviderConfig, ProviderType, SpecialFolder};
 pub use contact::Contact;
-pub use email::{Address, Attachment, Email};
+pub use credentials::{credentials_key, CredentialError, CredentialResult, CredentialStore};
+pub use date::format_relative;
+pub use email::{Address, Attachment, Email, UnsubscribeInfo};
+pub use html::{data_url, normalize_content_id, referenced_content_ids, resolve_image_sources};
+pub use importance::{ImportanceSignals, ImportanceWeights};
 pub use label::{system_labels, Label};
+pub use language::{detect_language, language_name, UNDETERMINED_LANGUAGE};
+pub use mbox::{write_mbox_message, MboxReader};
+pub use quote::{snippet_from_body, strip_quotes};
 pub use screener::{
     RuleType, ScreenerAction, ScreenerEntry, ScreenerRule, ScreenerStatus, SenderAnalysis,
     SenderType,
 };
-pub use thread::{Thread, ThreadSummary};
+pub use template::Template;
+pub use text::{char_prefix, truncate_chars, ELLIPSIS};
+pub use thread::{Thread, ThreadSort, ThreadSummary, IMPORTANT_LABEL};
 pub use types::{AccountId, EmailId, LabelId, MessageId, ThreadId};
+
+#[cfg(test)]
+pub(crate) use email::test_email;
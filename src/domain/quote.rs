@@ -0,0 +1,265 @@
+//! Quoted-history and signature stripping.
+//!
+//! Replies carry the conversation they answer, and most messages end in a
+//! signature. For derived views (list snippets, AI summarization) only the
+//! new content matters. The original body is never modified; these functions
+//! produce a separate cleaned copy.
+//!
+//! Recognized markers:
+//! - `>`-prefixed quoted lines, anywhere in the body (top- or bottom-posting)
+//! - Attribution lines such as `On Mon, 1 Jan 2024, Alice <a@x.com> wrote:`,
+//!   including ones wrapped over two lines
+//! - Outlook reply headers (`-----Original Message-----`, an underscore rule,
+//!   or a `From:` line followed by `Sent:`/`Date:`), which end the new content
+//! - The `-- ` signature delimiter (RFC 3676), which ends the new content
+//! - Forwarded-message markers, which end the new content unless there is no
+//!   note above them, in which case the forwarded body is kept
+
+use super::text::char_prefix;
+
+/// Header lines that follow forwarded-message and Outlook reply markers.
+const QUOTED_HEADER_PREFIXES: &[&str] = &["From:", "Sent:", "Date:", "To:", "Cc:", "Subject:"];
+
+/// Returns only the new content of a message body.
+///
+/// Quoted lines, attribution lines, reply headers and the signature are
+/// removed, and runs of blank lines are collapsed. Returns an empty string if
+/// the body consists entirely of quoted text.
+pub fn strip_quotes(body: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut kept: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if is_signature_delimiter(line) || is_reply_header(&lines[i..]) {
+            break;
+        }
+
+        if is_forward_marker(trimmed) {
+            if kept.iter().any(|l| !l.trim().is_empty()) {
+                break;
+            }
+            // Nothing new above the forward: keep the forwarded body itself.
+            i += 1;
+            while i < lines.len() {
+                let next = lines[i].trim();
+                if !next.is_empty() && !is_quoted_header(next) {
+                    break;
+                }
+                i += 1;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            i += 1;
+            continue;
+        }
+
+        if let Some(len) = attribution_len(&lines[i..]) {
+            i += len;
+            continue;
+        }
+
+        kept.push(line.trim_end());
+        i += 1;
+    }
+
+    collapse_blank_lines(&kept)
+}
+
+/// Builds a single-line preview of at most `max_chars` characters.
+///
+/// Uses the new content of the body, falling back to the full body when the
+/// message is entirely quoted.
+pub fn snippet_from_body(body: &str, max_chars: usize) -> String {
+    let stripped = strip_quotes(body);
+    let source = if stripped.is_empty() { body } else { &stripped };
+    let collapsed = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    char_prefix(&collapsed, max_chars).to_string()
+}
+
+/// Returns whether a line is a signature delimiter (`-- `).
+fn is_signature_delimiter(line: &str) -> bool {
+    // Many clients drop the trailing space.
+    line.trim_end() == "--"
+}
+
+/// Returns whether the lines start an Outlook-style reply header.
+fn is_reply_header(lines: &[&str]) -> bool {
+    let first = lines[0].trim();
+
+    if first.starts_with("-----Original Message-----") {
+        return true;
+    }
+
+    let header_start = if first.len() >= 10 && first.chars().all(|c| c == '_') {
+        1
+    } else {
+        0
+    };
+    let Some(from) = lines.get(header_start).map(|l| l.trim()) else {
+        return false;
+    };
+    if !from.starts_with("From:") {
+        return false;
+    }
+    lines
+        .iter()
+        .skip(header_start + 1)
+        .take(3)
+        .any(|l| l.trim().starts_with("Sent:") || l.trim().starts_with("Date:"))
+}
+
+/// Returns whether a line marks the start of a forwarded message.
+fn is_forward_marker(line: &str) -> bool {
+    let lower = line.to_ascii_lowercase();
+    (lower.starts_with("----") && lower.contains("forwarded message"))
+        || lower == "begin forwarded message:"
+}
+
+/// Returns whether a line is a header of a forwarded or quoted message.
+fn is_quoted_header(line: &str) -> bool {
+    QUOTED_HEADER_PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
+/// Returns how many lines an attribution (`On ... wrote:`) spans, if any.
+fn attribution_len(lines: &[&str]) -> Option<usize> {
+    let first = lines[0].trim();
+    if !first.starts_with("On ") {
+        return None;
+    }
+    if first.ends_with("wrote:") {
+        return Some(1);
+    }
+    // Gmail wraps long attributions before the address.
+    match lines.get(1) {
+        Some(next) if next.trim().ends_with("wrote:") => Some(2),
+        _ => None,
+    }
+}
+
+/// Joins lines, trimming blank lines at the ends and collapsing runs of them.
+fn collapse_blank_lines(lines: &[&str]) -> String {
+    let mut out = String::new();
+    let mut pending_blank = false;
+
+    for line in lines {
+        if line.trim().is_empty() {
+            pending_blank = !out.is_empty();
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+            if pending_blank {
+                out.push('\n');
+            }
+        }
+        out.push_str(line);
+        pending_blank = false;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gmail_top_posted_reply() {
+        let body = "Sounds good, see you then.\n\n\
+                    On Mon, Jan 1, 2024 at 10:00 AM Alice <alice@example.com> wrote:\n\
+                    > Lunch on Friday?\n\
+                    >\n\
+                    > Alice\n";
+        assert_eq!(strip_quotes(body), "Sounds good, see you then.");
+    }
+
+    #[test]
+    fn gmail_wrapped_attribution() {
+        let body = "Yes.\n\nOn Mon, Jan 1, 2024 at 10:00 AM Alice Example <\n\
+                    alice@example.com> wrote:\n> Coming?\n";
+        assert_eq!(strip_quotes(body), "Yes.");
+    }
+
+    #[test]
+    fn bottom_posted_reply_keeps_interleaved_answers() {
+        let body = "On Tue, Bob wrote:\n\
+                    > First question?\n\
+                    First answer.\n\
+                    \n\
+                    > Second question?\n\
+                    Second answer.\n";
+        assert_eq!(strip_quotes(body), "First answer.\n\nSecond answer.");
+    }
+
+    #[test]
+    fn outlook_original_message_header() {
+        let body = "Approved.\r\n\r\n-----Original Message-----\r\n\
+                    From: Carol <carol@example.com>\r\n\
+                    Sent: Monday, January 1, 2024 9:00 AM\r\n\
+                    Subject: Budget\r\n\r\nPlease approve.\r\n";
+        assert_eq!(strip_quotes(body), "Approved.");
+    }
+
+    #[test]
+    fn outlook_underscore_header() {
+        let body = "Thanks!\n\n________________________________\n\
+                    From: Carol <carol@example.com>\n\
+                    Sent: Monday, January 1, 2024 9:00 AM\n\
+                    To: Dave\n\nOriginal text\n";
+        assert_eq!(strip_quotes(body), "Thanks!");
+    }
+
+    #[test]
+    fn signature_is_removed() {
+        let body = "See attached.\n\n-- \nDave\nACME Corp\n";
+        assert_eq!(strip_quotes(body), "See attached.");
+    }
+
+    #[test]
+    fn forward_with_note_keeps_note() {
+        let body = "FYI, see below.\n\n\
+                    ---------- Forwarded message ---------\n\
+                    From: Erin <erin@example.com>\n\
+                    Date: Mon, Jan 1, 2024\n\
+                    Subject: Report\n\nThe report is ready.\n";
+        assert_eq!(strip_quotes(body), "FYI, see below.");
+    }
+
+    #[test]
+    fn forward_without_note_keeps_forwarded_body() {
+        let body = "Begin forwarded message:\n\n\
+                    From: Erin <erin@example.com>\n\
+                    Subject: Report\n\nThe report is ready.\n";
+        assert_eq!(strip_quotes(body), "The report is ready.");
+    }
+
+    #[test]
+    fn fully_quoted_body_is_empty() {
+        assert_eq!(strip_quotes("> only quoted\n> text"), "");
+    }
+
+    #[test]
+    fn unquoted_body_is_unchanged() {
+        let body = "Line one\nLine two";
+        assert_eq!(strip_quotes(body), body);
+    }
+
+    #[test]
+    fn snippet_uses_new_content() {
+        let body = "Short   reply\nhere.\n\nOn Mon, Alice wrote:\n> long quoted history";
+        assert_eq!(snippet_from_body(body, 200), "Short reply here.");
+        assert_eq!(snippet_from_body(body, 5), "Short");
+        assert_eq!(snippet_from_body("> all quoted", 200), "> all quoted");
+    }
+
+    #[test]
+    fn snippet_does_not_split_emoji_sequences() {
+        assert_eq!(snippet_from_body("Hi 👋🏽 there", 4), "Hi 👋🏽");
+    }
+}
@@ -0,0 +1,36 @@
+//! Template domain types.
+//!
+//! Canned responses the user inserts into the composer. Bodies may contain
+//! `{{placeholder}}` markers that are filled in when the template is used.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A named canned response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    /// Unique identifier for this template.
+    pub id: String,
+    /// Name the user picks the template by (unique).
+    pub name: String,
+    /// Body text, possibly containing `{{placeholder}}` markers.
+    pub body: String,
+    /// When the template was created.
+    pub created_at: DateTime<Utc>,
+    /// When the template was last modified.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Template {
+    /// Creates a new template.
+    pub fn new(name: impl Into<String>, body: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.into(),
+            body: body.into(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Unicode-safe text truncation for previews and snippets.
+//!
+//! Byte slicing panics when the cut lands inside a multi-byte character, and
+//! cutting between a character and the marks that modify it leaves a broken
+//! glyph behind. These helpers count characters instead of bytes and keep
+//! combining marks, variation selectors, skin-tone modifiers, flag pairs and
+//! zero-width-joined emoji sequences attached to their base character.
+
+/// Appended to text that was cut short.
+pub const ELLIPSIS: &str = "...";
+
+/// Zero width joiner, which glues emoji into a single sequence.
+const ZWJ: char = '\u{200D}';
+
+/// Returns the longest prefix of `text` holding at most `max_chars`
+/// characters.
+///
+/// A character and anything that extends it count as one, so the prefix never
+/// ends between an emoji and its modifier or a letter and its accent.
+pub fn char_prefix(text: &str, max_chars: usize) -> &str {
+    if max_chars == 0 {
+        return "";
+    }
+
+    let mut count = 0;
+    let mut after_joiner = false;
+    let mut open_flag = false;
+
+    for (idx, c) in text.char_indices() {
+        let flag_half = is_regional_indicator(c);
+        let extends = after_joiner || is_extending(c) || (flag_half && open_flag);
+
+        after_joiner = c == ZWJ;
+        if flag_half {
+            open_flag = !extends;
+        } else if !extends {
+            open_flag = false;
+        }
+
+        if !extends {
+            if count == max_chars {
+                return &text[..idx];
+            }
+            count += 1;
+        }
+    }
+
+    text
+}
+
+/// Truncates `text` to at most `max_chars` characters, appending
+/// [`ELLIPSIS`] when anything was cut.
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let prefix = char_prefix(text, max_chars);
+    if prefix.len() == text.len() {
+        text.to_string()
+    } else {
+        format!("{prefix}{ELLIPSIS}")
+    }
+}
+
+/// Returns whether a character modifies the one before it rather than
+/// starting a new one.
+fn is_extending(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'       // combining diacritical marks
+            | '\u{1AB0}'..='\u{1AFF}' // combining diacritical marks extended
+            | '\u{1DC0}'..='\u{1DFF}' // combining diacritical marks supplement
+            | '\u{200D}'              // zero width joiner
+            | '\u{20D0}'..='\u{20FF}' // combining marks for symbols (keycaps)
+            | '\u{FE00}'..='\u{FE0F}' // variation selectors
+            | '\u{FE20}'..='\u{FE2F}' // combining half marks
+            | '\u{1F3FB}'..='\u{1F3FF}' // skin tone modifiers
+            | '\u{E0020}'..='\u{E007F}' // tag sequences (subdivision flags)
+    )
+}
+
+/// Returns whether a character is one half of a flag pair.
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_unchanged() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("exactly10!", 10), "exactly10!");
+    }
+
+    #[test]
+    fn long_ascii_text_gets_ellipsis() {
+        assert_eq!(truncate_chars("this is a long text", 10), "this is a ...");
+    }
+
+    #[test]
+    fn emoji_at_boundary_does_not_panic() {
+        // The rocket starts at byte 9, so a byte cut at 10 would land inside it.
+        let text = "Launch in 🚀 soon";
+        assert_eq!(truncate_chars(text, 10), "Launch in ...");
+        assert_eq!(truncate_chars(text, 11), "Launch in 🚀...");
+        assert_eq!(truncate_chars("🎉🎉🎉", 2), "🎉🎉...");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        // "e" followed by a combining acute accent.
+        let text = "cafe\u{301} au lait";
+        assert_eq!(char_prefix(text, 4), "cafe\u{301}");
+        assert_eq!(truncate_chars(text, 4), "cafe\u{301}...");
+    }
+
+    #[test]
+    fn emoji_sequences_are_not_split() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        assert_eq!(char_prefix(&format!("{family} hi"), 1), family);
+
+        let thumbs = "👍🏽";
+        assert_eq!(char_prefix(&format!("{thumbs}!"), 1), thumbs);
+
+        let flags = "🇫🇷🇩🇪";
+        assert_eq!(char_prefix(flags, 1), "🇫🇷");
+        assert_eq!(char_prefix(flags, 2), flags);
+    }
+
+    #[test]
+    fn zero_length_prefix_is_empty() {
+        assert_eq!(char_prefix("\u{301}abc", 0), "");
+        assert_eq!(truncate_chars("abc", 0), "...");
+    }
+}
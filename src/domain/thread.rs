@@ -2,6 +2,8 @@
 //!
 //! Represents email threads (conversations) which group related messages.
 
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -16,9 +18,12 @@ pub struct Thread {
     pub account_id: AccountId,
     /// Thread subject (from first message).
     pub subject: Option<String>,
-    /// Short preview of the latest message.
+    /// Short preview of the newest unread message, or of the latest message
+    /// when all are read. See [`Thread::preview_snippet`].
     pub snippet: String,
-    /// All participants in the thread.
+    /// Participants ordered by most recent message, with the account owner last.
+    ///
+    /// See [`Thread::ordered_participants`].
     pub participants: Vec<Address>,
     /// All messages in the thread, ordered by date.
     pub messages: Vec<Email>,
@@ -44,7 +49,8 @@ pub struct ThreadSummary {
     pub account_id: AccountId,
     /// Thread subject.
     pub subject: Option<String>,
-    /// Short preview of the latest message.
+    /// Short preview of the newest unread message, or of the latest message
+    /// when all are read. See [`Thread::preview_snippet`].
     pub snippet: String,
     /// Primary sender for display.
     pub from: Address,
@@ -58,6 +64,72 @@ pub struct ThreadSummary {
     pub is_starred: bool,
     /// Labels applied to this thread.
     pub labels: Vec<LabelId>,
+    /// Whether the thread is muted, so new replies skip the inbox.
+    #[serde(default)]
+    pub muted: bool,
+    /// Whether the thread is watched, so every new message notifies.
+    #[serde(default)]
+    pub watched: bool,
+}
+
+impl Thread {
+    /// Collects a thread's participants for display.
+    ///
+    /// Senders and recipients of newer messages come first, addresses that
+    /// differ only by case are collapsed, and `owner` (the account's own
+    /// address) is moved to the end so the UI can render "Alice, Bob, you".
+    pub fn ordered_participants(messages: &[Email], owner: Option<&str>) -> Vec<Address> {
+        let mut by_recency: Vec<&Email> = messages.iter().collect();
+        // Reverse first so the stable sort puts later messages first on date ties.
+        by_recency.reverse();
+        by_recency.sort_by(|a, b| b.date.cmp(&a.date));
+
+        let mut participants: Vec<Address> = Vec::new();
+        let addresses = by_recency
+            .iter()
+            .flat_map(|msg| std::iter::once(&msg.from).chain(&msg.to).chain(&msg.cc));
+        for address in addresses {
+            match participants
+                .iter_mut()
+                .find(|p| p.email.eq_ignore_ascii_case(&address.email))
+            {
+                Some(existing) => {
+                    if existing.name.is_none() {
+                        existing.name.clone_from(&address.name);
+                    }
+                }
+                None => participants.push(address.clone()),
+            }
+        }
+
+        if let Some(owner) = owner {
+            if let Some(pos) = participants
+                .iter()
+                .position(|p| p.email.eq_ignore_ascii_case(owner))
+            {
+                let me = participants.remove(pos);
+                participants.push(me);
+            }
+        }
+
+        participants
+    }
+
+    /// Picks a thread's preview: the snippet of the newest unread message,
+    /// so the list shows what hasn't been read yet, or of the newest message
+    /// when all are read.
+    pub fn preview_snippet(messages: &[Email]) -> String {
+        let newest = |unread_only: bool| {
+            messages
+                .iter()
+                .filter(|msg| !unread_only || !msg.is_read)
+                .max_by_key(|msg| msg.date)
+        };
+        newest(true)
+            .or_else(|| newest(false))
+            .map(|msg| msg.snippet.clone())
+            .unwrap_or_default()
+    }
 }
 
 impl ThreadSummary {
@@ -65,6 +137,63 @@ impl ThreadSummary {
     pub fn has_unread(&self) -> bool {
         self.unread_count > 0
     }
+
+    /// Returns true if the thread is marked important.
+    pub fn is_important(&self) -> bool {
+        self.labels.iter().any(|l| l.0 == IMPORTANT_LABEL)
+    }
+}
+
+/// Label marking a thread as important, set by the priority smart view and
+/// by Gmail's priority inbox.
+pub const IMPORTANT_LABEL: &str = "IMPORTANT";
+
+/// Order of a thread list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadSort {
+    /// Sort by date, newest first.
+    #[default]
+    DateDesc,
+    /// Sort by date, oldest first.
+    DateAsc,
+    /// Unread threads first, then by date.
+    UnreadFirst,
+    /// Most important first, as scored by
+    /// [`ImportanceWeights::score`](super::ImportanceWeights::score), then by
+    /// date.
+    PriorityFirst,
+}
+
+impl ThreadSort {
+    /// Sorts summaries in place.
+    ///
+    /// `importance` is only consulted for [`ThreadSort::PriorityFirst`].
+    /// Ties are broken newest first, except for [`ThreadSort::DateAsc`].
+    pub fn sort(self, threads: &mut [ThreadSummary], importance: impl Fn(&ThreadSummary) -> f32) {
+        let newest_first =
+            |a: &ThreadSummary, b: &ThreadSummary| b.last_message_date.cmp(&a.last_message_date);
+        match self {
+            ThreadSort::DateDesc => threads.sort_by(newest_first),
+            ThreadSort::DateAsc => threads.sort_by_key(|t| t.last_message_date),
+            ThreadSort::UnreadFirst => threads.sort_by(|a, b| {
+                b.has_unread()
+                    .cmp(&a.has_unread())
+                    .then_with(|| newest_first(a, b))
+            }),
+            ThreadSort::PriorityFirst => {
+                let scores: HashMap<ThreadId, f32> = threads
+                    .iter()
+                    .map(|t| (t.id.clone(), importance(t)))
+                    .collect();
+                threads.sort_by(|a, b| {
+                    scores[&b.id]
+                        .total_cmp(&scores[&a.id])
+                        .then_with(|| newest_first(a, b))
+                });
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -83,6 +212,8 @@ mod tests {
             unread_count: 1,
             is_starred: false,
             labels: vec![LabelId::from("INBOX")],
+            muted: false,
+            watched: false,
         }
     }
 
@@ -99,6 +230,60 @@ mod tests {
         assert!(!summary.has_unread());
     }
 
+    /// Threads numbered by age, thread-1 being the newest.
+    fn sortable_summaries() -> Vec<ThreadSummary> {
+        let now = Utc::now();
+        (1..=4)
+            .map(|i| {
+                let mut summary = make_summary();
+                summary.id = ThreadId::from(format!("thread-{}", i));
+                summary.last_message_date = now - chrono::Duration::hours(i);
+                summary.unread_count = (i % 2 == 0) as u32;
+                summary
+            })
+            .collect()
+    }
+
+    fn sorted_ids(sort: ThreadSort, importance: impl Fn(&ThreadSummary) -> f32) -> Vec<String> {
+        let mut threads = sortable_summaries();
+        threads.reverse();
+        sort.sort(&mut threads, importance);
+        threads.into_iter().map(|t| t.id.0).collect()
+    }
+
+    #[test]
+    fn thread_sorts_order_summaries() {
+        let none = |_: &ThreadSummary| 0.0;
+        assert_eq!(
+            sorted_ids(ThreadSort::DateDesc, none),
+            ["thread-1", "thread-2", "thread-3", "thread-4"]
+        );
+        assert_eq!(
+            sorted_ids(ThreadSort::DateAsc, none),
+            ["thread-4", "thread-3", "thread-2", "thread-1"]
+        );
+        assert_eq!(
+            sorted_ids(ThreadSort::UnreadFirst, none),
+            ["thread-2", "thread-4", "thread-1", "thread-3"]
+        );
+        assert_eq!(
+            sorted_ids(ThreadSort::PriorityFirst, |t| match t.id.0.as_str() {
+                "thread-4" => 0.9,
+                "thread-3" => 0.5,
+                _ => 0.1,
+            }),
+            ["thread-4", "thread-3", "thread-1", "thread-2"]
+        );
+    }
+
+    #[test]
+    fn thread_sort_serializes_as_snake_case() {
+        let json = serde_json::to_string(&ThreadSort::PriorityFirst).unwrap();
+        assert_eq!(json, "\"priority_first\"");
+        let sort: ThreadSort = serde_json::from_str("\"unread_first\"").unwrap();
+        assert_eq!(sort, ThreadSort::UnreadFirst);
+    }
+
     #[test]
     fn thread_summary_serialization() {
         let summary = make_summary();
@@ -109,6 +294,85 @@ mod tests {
         assert_eq!(deserialized.subject, Some("Test Subject".to_string()));
     }
 
+    fn message(id: &str, from: &str, to: &[&str], minutes: i64) -> Email {
+        Email {
+            from: Address::new(from),
+            to: to.iter().map(|a| Address::new(*a)).collect(),
+            subject: None,
+            body_text: None,
+            snippet: String::new(),
+            date: DateTime::from_timestamp(minutes * 60, 0).unwrap(),
+            is_read: true,
+            ..super::super::test_email(id, "thread-1")
+        }
+    }
+
+    #[test]
+    fn participants_ordered_by_recency_with_owner_last() {
+        let messages = vec![
+            message("1", "alice@example.com", &["me@example.com"], 1),
+            message(
+                "2",
+                "me@example.com",
+                &["alice@example.com", "carol@example.com"],
+                2,
+            ),
+            message(
+                "3",
+                "Bob@Example.com",
+                &["ME@example.com", "Alice@Example.com"],
+                3,
+            ),
+        ];
+
+        let participants = Thread::ordered_participants(&messages, Some("me@example.com"));
+        let emails: Vec<&str> = participants.iter().map(|p| p.email.as_str()).collect();
+
+        assert_eq!(
+            emails,
+            [
+                "Bob@Example.com",
+                "Alice@Example.com",
+                "carol@example.com",
+                "ME@example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn participants_without_owner_keep_recency_order() {
+        let messages = vec![
+            message("1", "alice@example.com", &["bob@example.com"], 5),
+            message("2", "bob@example.com", &["alice@example.com"], 1),
+        ];
+
+        let participants = Thread::ordered_participants(&messages, None);
+        let emails: Vec<&str> = participants.iter().map(|p| p.email.as_str()).collect();
+
+        assert_eq!(emails, ["alice@example.com", "bob@example.com"]);
+    }
+
+    #[test]
+    fn preview_prefers_the_newest_unread_message() {
+        let mut messages: Vec<Email> = (1..=8)
+            .map(|n| {
+                let mut msg = message(&n.to_string(), "alice@example.com", &[], n);
+                msg.snippet = format!("Message {}", n);
+                msg
+            })
+            .collect();
+        assert_eq!(Thread::preview_snippet(&messages), "Message 8");
+
+        // Only an older message is unread: it is what the user hasn't seen.
+        messages[2].is_read = false;
+        assert_eq!(Thread::preview_snippet(&messages), "Message 3");
+
+        messages[5].is_read = false;
+        assert_eq!(Thread::preview_snippet(&messages), "Message 6");
+
+        assert_eq!(Thread::preview_snippet(&[]), "");
+    }
+
     #[test]
     fn thread_with_messages() {
         use super::super::{EmailId, MessageId};
@@ -143,6 +407,8 @@ mod tests {
                 is_draft: false,
                 labels: vec![],
                 attachments: vec![],
+                unsubscribe: None,
+                read_receipt_to: None,
             }],
             last_message_date: Utc::now(),
             unread_count: 0,
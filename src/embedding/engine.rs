@@ -12,7 +12,7 @@ use std::path::PathBuf;
 use tokenizers::Tokenizer;
 
 use crate::domain::{Email, EmailId};
-use crate::embedding::VectorStore;
+use crate::embedding::{ModelInfo, ModelSource, VectorStore};
 
 /// A vector embedding representing text semantics.
 ///
@@ -80,6 +80,12 @@ pub struct EmbeddingConfig {
     pub model_id: String,
     /// Maximum sequence length for tokenization.
     pub max_seq_length: usize,
+    /// Dimension of the embeddings the model produces.
+    pub embedding_dim: usize,
+    /// Number of texts passed through the model in one forward pass.
+    pub batch_size: usize,
+    /// Tokens shared between consecutive windows when a long email is split.
+    pub chunk_overlap: usize,
     /// Whether to use GPU acceleration if available.
     pub use_gpu: bool,
     /// Whether to use fallback (hash-based) embeddings when model unavailable.
@@ -92,12 +98,52 @@ impl Default for EmbeddingConfig {
             model_path: None,
             model_id: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
             max_seq_length: 256,
+            embedding_dim: 384,
+            batch_size: 32,
+            chunk_overlap: 32,
             use_gpu: false,
             use_fallback: true,
         }
     }
 }
 
+impl EmbeddingConfig {
+    /// Creates a configuration for a registered model.
+    ///
+    /// `model_path` is the directory returned by [`ModelRegistry::download`];
+    /// when absent, Hugging Face models are fetched through the hub cache.
+    ///
+    /// [`ModelRegistry::download`]: crate::embedding::ModelRegistry::download
+    pub fn for_model(info: &ModelInfo, model_path: Option<PathBuf>) -> Self {
+        let (model_id, model_path) = match &info.source {
+            ModelSource::HuggingFace { repo, .. } => (repo.clone(), model_path),
+            ModelSource::LocalPath(dir) => (info.name.clone(), Some(dir.clone())),
+        };
+
+        Self {
+            model_path,
+            model_id,
+            max_seq_length: info.max_seq_length,
+            embedding_dim: info.embedding_dim,
+            ..Self::default()
+        }
+    }
+}
+
+/// Stored vectors whose dimension does not match the selected model.
+///
+/// Vectors from different models are not comparable, so the affected emails
+/// must be re-indexed with the new model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionMismatch {
+    /// Dimension the selected model produces.
+    pub expected: usize,
+    /// Dimensions found among the stored vectors, ascending.
+    pub stored: Vec<usize>,
+    /// Emails whose vectors are incompatible.
+    pub affected: Vec<EmailId>,
+}
+
 /// Engine for generating text embeddings using local ML models.
 ///
 /// The engine uses Candle for inference, avoiding external API calls
@@ -170,22 +216,33 @@ impl EmbeddingEngine {
         Ok(())
     }
 
-    /// Loads the model and tokenizer from HuggingFace Hub.
+    /// Loads the model and tokenizer from the configured directory, or from
+    /// HuggingFace Hub when no directory is set.
     fn load_model(&mut self) -> Result<()> {
-        let api = Api::new().context("Failed to create HuggingFace API client")?;
-        let repo = api.repo(Repo::new(self.config.model_id.clone(), RepoType::Model));
-
-        // Download model files
-        let config_path = repo
-            .get("config.json")
-            .context("Failed to get config.json")?;
-        let tokenizer_path = repo
-            .get("tokenizer.json")
-            .context("Failed to get tokenizer.json")?;
-        let weights_path = repo
-            .get("model.safetensors")
-            .or_else(|_| repo.get("pytorch_model.bin"))
-            .context("Failed to get model weights")?;
+        let (config_path, tokenizer_path, weights_path) = match &self.config.model_path {
+            Some(dir) => (
+                dir.join("config.json"),
+                dir.join("tokenizer.json"),
+                dir.join("model.safetensors"),
+            ),
+            None => {
+                let api = Api::new().context("Failed to create HuggingFace API client")?;
+                let repo = api.repo(Repo::new(self.config.model_id.clone(), RepoType::Model));
+
+                // Download model files
+                let config_path = repo
+                    .get("config.json")
+                    .context("Failed to get config.json")?;
+                let tokenizer_path = repo
+                    .get("tokenizer.json")
+                    .context("Failed to get tokenizer.json")?;
+                let weights_path = repo
+                    .get("model.safetensors")
+                    .or_else(|_| repo.get("pytorch_model.bin"))
+                    .context("Failed to get model weights")?;
+                (config_path, tokenizer_path, weights_path)
+            }
+        };
 
         // Load configuration
         let config_str =
@@ -193,9 +250,14 @@ impl EmbeddingEngine {
         let bert_config: BertConfig =
             serde_json::from_str(&config_str).context("Failed to parse config.json")?;
 
-        // Load tokenizer
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        // Load tokenizer. Truncation and padding are handled here so long
+        // emails can be chunked and batches padded to a common length.
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
             .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
+        tokenizer
+            .with_truncation(None)
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer: {}", e))?;
+        tokenizer.with_padding(None);
 
         // Load model weights
         let vb = if weights_path
@@ -225,37 +287,64 @@ impl EmbeddingEngine {
     /// The text is tokenized, passed through the model, and the output
     /// is pooled to produce a fixed-size embedding vector.
     pub fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_batch(&[text])?
+            .pop()
+            .context("Model returned no embedding")
+    }
+
+    /// Generates embeddings for several texts in one forward pass.
+    ///
+    /// Texts are padded to the longest one in the batch; the attention mask
+    /// keeps padding out of the pooled result.
+    pub fn embed_batch<S: AsRef<str>>(&self, texts: &[S]) -> Result<Vec<Embedding>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
         if let (Some(model), Some(tokenizer)) = (&self.model, &self.tokenizer) {
-            self.embed_with_model(model, tokenizer, text)
+            self.embed_with_model(model, tokenizer, texts)
         } else {
             // Fallback to hash-based pseudo-embeddings
-            self.embed_fallback(text)
+            texts
+                .iter()
+                .map(|text| self.embed_fallback(text.as_ref()))
+                .collect()
         }
     }
 
-    /// Generates an embedding using the loaded model.
-    fn embed_with_model(
+    /// Generates embeddings for a batch using the loaded model.
+    fn embed_with_model<S: AsRef<str>>(
         &self,
         model: &BertModel,
         tokenizer: &Tokenizer,
-        text: &str,
-    ) -> Result<Embedding> {
-        // Tokenize the input
-        let encoding = tokenizer
-            .encode(text, true)
+        texts: &[S],
+    ) -> Result<Vec<Embedding>> {
+        // Tokenize the inputs
+        let inputs: Vec<&str> = texts.iter().map(|t| t.as_ref()).collect();
+        let encodings = tokenizer
+            .encode_batch(inputs, true)
             .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
 
-        let token_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
-
-        // Truncate to max sequence length
-        let seq_len = token_ids.len().min(self.config.max_seq_length);
-        let token_ids: Vec<u32> = token_ids[..seq_len].to_vec();
-        let attention_mask: Vec<u32> = attention_mask[..seq_len].to_vec();
+        // Truncate to max sequence length and pad to the longest input
+        let seq_len = encodings
+            .iter()
+            .map(|e| e.get_ids().len().min(self.config.max_seq_length))
+            .max()
+            .unwrap_or(0);
+        let mut token_ids: Vec<u32> = Vec::with_capacity(encodings.len() * seq_len);
+        let mut attention_mask: Vec<u32> = Vec::with_capacity(encodings.len() * seq_len);
+        for encoding in &encodings {
+            let len = encoding.get_ids().len().min(seq_len);
+            token_ids.extend_from_slice(&encoding.get_ids()[..len]);
+            token_ids.extend(std::iter::repeat(0).take(seq_len - len));
+            attention_mask.extend_from_slice(&encoding.get_attention_mask()[..len]);
+            attention_mask.extend(std::iter::repeat(0).take(seq_len - len));
+        }
 
         // Create tensors
-        let token_ids_tensor = Tensor::new(&token_ids[..], &self.device)?.unsqueeze(0)?;
-        let attention_mask_tensor = Tensor::new(&attention_mask[..], &self.device)?.unsqueeze(0)?;
+        let shape = (encodings.len(), seq_len);
+        let token_ids_tensor = Tensor::from_vec(token_ids, shape, &self.device)?;
+        let attention_mask_tensor = Tensor::from_vec(attention_mask, shape, &self.device)?;
         let token_type_ids = Tensor::zeros_like(&token_ids_tensor)?;
 
         // Forward pass
@@ -276,18 +365,58 @@ impl EmbeddingEngine {
         let mean_pooled = sum_embeddings.broadcast_div(&sum_mask)?;
 
         // Extract values
-        let values: Vec<f32> = mean_pooled.squeeze(0)?.to_vec1()?;
+        let rows: Vec<Vec<f32>> = mean_pooled.to_vec2()?;
+
+        Ok(rows
+            .into_iter()
+            .map(|values| {
+                let mut embedding = Embedding::new(values);
+                embedding.normalize();
+                embedding
+            })
+            .collect())
+    }
 
-        let mut embedding = Embedding::new(values);
-        embedding.normalize();
+    /// Splits text into overlapping windows that each fit the model.
+    ///
+    /// Without a tokenizer the text is returned whole, since the fallback
+    /// embeddings have no length limit.
+    fn chunk_text(&self, text: &str) -> Vec<String> {
+        let Some(tokenizer) = &self.tokenizer else {
+            return vec![text.to_string()];
+        };
+        let Ok(encoding) = tokenizer.encode(text, false) else {
+            return vec![text.to_string()];
+        };
 
-        Ok(embedding)
+        // Leave room for the special tokens added when embedding.
+        let window = self.config.max_seq_length.saturating_sub(2).max(1);
+        let offsets = encoding.get_offsets();
+        if offsets.len() <= window {
+            return vec![text.to_string()];
+        }
+
+        let step = window.saturating_sub(self.config.chunk_overlap).max(1);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        loop {
+            let end = (start + window).min(offsets.len());
+            if let Some(chunk) = text.get(offsets[start].0..offsets[end - 1].1) {
+                chunks.push(chunk.to_string());
+            }
+            if end == offsets.len() {
+                break;
+            }
+            start += step;
+        }
+
+        chunks
     }
 
     /// Generates a fallback hash-based pseudo-embedding.
     fn embed_fallback(&self, text: &str) -> Result<Embedding> {
         let hash = Self::simple_hash(text);
-        let dimension = 384; // MiniLM dimension
+        let dimension = self.config.embedding_dim;
         let values: Vec<f32> = (0..dimension)
             .map(|i| {
                 let seed = hash.wrapping_add(i as u64);
@@ -311,12 +440,127 @@ impl EmbeddingEngine {
     ///
     /// Combines subject and body text for a comprehensive representation.
     pub fn index_email(&mut self, email: &Email) -> Result<()> {
-        let text = Self::email_to_text(email);
-        let embedding = self.embed(&text)?;
-        self.vector_store.insert(&email.id, embedding)?;
+        self.index_emails(std::slice::from_ref(email))
+    }
+
+    /// Indexes several emails, batching them through the model.
+    ///
+    /// Emails longer than the model's sequence length are split into
+    /// overlapping windows whose embeddings are averaged into one vector.
+    pub fn index_emails(&mut self, emails: &[Email]) -> Result<()> {
+        let mut texts = Vec::new();
+        let mut owners = Vec::new();
+        for (i, email) in emails.iter().enumerate() {
+            for chunk in self.chunk_text(&Self::email_to_text(email)) {
+                texts.push(chunk);
+                owners.push(i);
+            }
+        }
+
+        let mut grouped: Vec<Vec<Embedding>> = vec![Vec::new(); emails.len()];
+        let mut owners = owners.into_iter();
+        for batch in texts.chunks(self.config.batch_size.max(1)) {
+            for (embedding, owner) in self.embed_batch(batch)?.into_iter().zip(&mut owners) {
+                grouped[owner].push(embedding);
+            }
+        }
+
+        for (email, chunks) in emails.iter().zip(grouped) {
+            if let Some(embedding) = Self::average(chunks) {
+                self.vector_store.insert(&email.id, embedding)?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Averages chunk embeddings into a single normalized vector.
+    fn average(mut chunks: Vec<Embedding>) -> Option<Embedding> {
+        if chunks.len() <= 1 {
+            return chunks.pop();
+        }
+
+        let dimension = chunks[0].dimension();
+        let mut values = vec![0.0; dimension];
+        for chunk in &chunks {
+            for (sum, v) in values.iter_mut().zip(&chunk.values) {
+                *sum += v;
+            }
+        }
+
+        let mut embedding = Embedding::new(values);
+        embedding.normalize();
+        Some(embedding)
+    }
+
+    /// Switches to a different model.
+    ///
+    /// The new model is loaded immediately. If stored vectors have a different
+    /// dimension than the new model produces, they are removed and reported so
+    /// the caller can re-index those emails.
+    pub async fn switch_model(
+        &mut self,
+        config: EmbeddingConfig,
+    ) -> Result<Option<DimensionMismatch>> {
+        tracing::info!(
+            from = %self.config.model_id,
+            to = %config.model_id,
+            "Switching embedding model"
+        );
+
+        self.config = config;
+        self.model = None;
+        self.tokenizer = None;
+        self.initialized = false;
+        self.initialize().await?;
+
+        let mismatch = self.dimension_mismatch(self.config.embedding_dim);
+        if let Some(mismatch) = &mismatch {
+            tracing::warn!(
+                expected = mismatch.expected,
+                stored = ?mismatch.stored,
+                count = mismatch.affected.len(),
+                "Stored embeddings are incompatible with the new model and need re-indexing"
+            );
+            for id in &mismatch.affected {
+                self.vector_store.remove(id);
+            }
+        }
+
+        Ok(mismatch)
+    }
+
+    /// Checks stored vectors against the dimension a model produces.
+    ///
+    /// Returns `None` when every stored vector is compatible.
+    pub fn dimension_mismatch(&self, expected: usize) -> Option<DimensionMismatch> {
+        let mut stored = Vec::new();
+        let mut affected = Vec::new();
+
+        for id in self.vector_store.email_ids() {
+            let Some(dim) = self.vector_store.get(id).map(Embedding::dimension) else {
+                continue;
+            };
+            if dim != expected {
+                if !stored.contains(&dim) {
+                    stored.push(dim);
+                }
+                affected.push(id.clone());
+            }
+        }
+
+        if affected.is_empty() {
+            return None;
+        }
+
+        stored.sort_unstable();
+        Some(DimensionMismatch {
+            expected,
+            stored,
+            affected,
+        })
+    }
+
     /// Returns whether the model is loaded (vs using fallback).
     pub fn is_model_loaded(&self) -> bool {
         self.model.is_some() && self.tokenizer.is_some()
@@ -385,6 +629,8 @@ mod tests {
             is_draft: false,
             labels: vec![],
             attachments: vec![],
+            unsubscribe: None,
+            read_receipt_to: None,
         }
     }
 
@@ -486,6 +732,52 @@ mod tests {
         assert_eq!(results[0].0, EmailId::from("email-1"));
     }
 
+    #[test]
+    fn batch_indexing_matches_single_indexing() {
+        let emails: Vec<Email> = (0..50)
+            .map(|i| {
+                make_test_email(
+                    &format!("email-{}", i),
+                    &format!("Subject {}", i),
+                    &format!("Body of message number {}", i),
+                )
+            })
+            .collect();
+
+        let mut single = EmbeddingEngine::with_defaults(VectorStore::new());
+        for email in &emails {
+            single.index_email(email).unwrap();
+        }
+
+        let config = EmbeddingConfig {
+            batch_size: 8,
+            ..EmbeddingConfig::default()
+        };
+        let mut batched = EmbeddingEngine::new(config, VectorStore::new());
+        batched.index_emails(&emails).unwrap();
+
+        assert_eq!(batched.vector_store().len(), emails.len());
+        for email in &emails {
+            let a = single.vector_store().get(&email.id).unwrap();
+            let b = batched.vector_store().get(&email.id).unwrap();
+            assert!((a.cosine_similarity(b) - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn average_of_chunks_is_normalized() {
+        let averaged = EmbeddingEngine::average(vec![
+            Embedding::new(vec![1.0, 0.0]),
+            Embedding::new(vec![0.0, 1.0]),
+        ])
+        .unwrap();
+
+        let norm: f32 = averaged.values.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 0.0001);
+        assert!((averaged.values[0] - averaged.values[1]).abs() < 0.0001);
+        assert!(EmbeddingEngine::average(Vec::new()).is_none());
+    }
+
     #[test]
     fn email_to_text_combines_subject_and_body() {
         let email = make_test_email("email-1", "Subject here", "Body content here");
@@ -503,6 +795,46 @@ mod tests {
         assert!(config.use_fallback);
     }
 
+    #[test]
+    fn dimension_mismatch_detected_for_new_model() {
+        let mut store = VectorStore::new();
+        store
+            .insert(&EmailId::from("email-1"), Embedding::new(vec![0.1; 384]))
+            .unwrap();
+        store
+            .insert(&EmailId::from("email-2"), Embedding::new(vec![0.1; 384]))
+            .unwrap();
+        let engine = EmbeddingEngine::with_defaults(store);
+
+        assert!(engine.dimension_mismatch(384).is_none());
+
+        let mismatch = engine.dimension_mismatch(768).unwrap();
+        assert_eq!(mismatch.expected, 768);
+        assert_eq!(mismatch.stored, vec![384]);
+        assert_eq!(mismatch.affected.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn switch_model_reports_and_drops_incompatible_vectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = VectorStore::new();
+        store
+            .insert(&EmailId::from("email-1"), Embedding::new(vec![0.1; 384]))
+            .unwrap();
+        let mut engine = EmbeddingEngine::with_defaults(store);
+
+        // An empty local directory fails to load, so the fallback is used at
+        // the new model's dimension.
+        let info = ModelInfo::custom("wide", ModelSource::LocalPath(dir.path().into()), 768);
+        let config = EmbeddingConfig::for_model(&info, None);
+        let mismatch = engine.switch_model(config).await.unwrap().unwrap();
+
+        assert_eq!(mismatch.stored, vec![384]);
+        assert_eq!(mismatch.affected, vec![EmailId::from("email-1")]);
+        assert!(engine.vector_store().is_empty());
+        assert_eq!(engine.embed("test").unwrap().dimension(), 768);
+    }
+
     #[test]
     fn engine_without_model_uses_fallback() {
         let store = VectorStore::new();
@@ -29,6 +29,6 @@ mod engine;
 mod models;
 mod vector_store;
 
-pub use engine::{Embedding, EmbeddingConfig, EmbeddingEngine};
-pub use models::{DownloadStatus, ModelInfo, ModelRegistry, ModelType};
+pub use engine::{DimensionMismatch, Embedding, EmbeddingConfig, EmbeddingEngine};
+pub use models::{DownloadStatus, ModelInfo, ModelRegistry, ModelSource, ModelType};
 pub use vector_store::VectorStore;
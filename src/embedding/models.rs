@@ -1,9 +1,22 @@
 //! Embedding model definitions and configuration.
 //!
 //! This module defines the available embedding models and their configurations
-//! for generating text embeddings used in semantic search.
+//! for generating text embeddings used in semantic search. Besides the
+//! built-in models, users can register their own from a Hugging Face
+//! repository or a local directory.
 
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use ring::digest;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Files that make up a model, in download order. The weights come last.
+const MODEL_FILES: &[&str] = &["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Base URL for downloading files from Hugging Face.
+const HF_BASE_URL: &str = "https://huggingface.co";
 
 /// Available embedding model types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
@@ -18,39 +31,57 @@ pub enum ModelType {
     BgeSmall,
     /// E5-Small - good for asymmetric search.
     E5Small,
+    /// A user-registered model, identified by its registry index.
+    Custom(u32),
 }
 
 impl ModelType {
-    /// Returns the Hugging Face model ID.
-    pub fn hf_model_id(&self) -> &'static str {
+    /// Returns the Hugging Face model ID of a built-in model.
+    ///
+    /// Custom models carry their source on [`ModelInfo`] instead.
+    pub fn hf_model_id(&self) -> Option<&'static str> {
         match self {
-            Self::MiniLm => "sentence-transformers/paraphrase-MiniLM-L6-v2",
-            Self::AllMiniLmL6V2 => "sentence-transformers/all-MiniLM-L6-v2",
-            Self::BgeSmall => "BAAI/bge-small-en-v1.5",
-            Self::E5Small => "intfloat/e5-small-v2",
+            Self::MiniLm => Some("sentence-transformers/paraphrase-MiniLM-L6-v2"),
+            Self::AllMiniLmL6V2 => Some("sentence-transformers/all-MiniLM-L6-v2"),
+            Self::BgeSmall => Some("BAAI/bge-small-en-v1.5"),
+            Self::E5Small => Some("intfloat/e5-small-v2"),
+            Self::Custom(_) => None,
         }
     }
 
     /// Returns the expected embedding dimension.
+    ///
+    /// For custom models this is the MiniLM default; the registered
+    /// [`ModelInfo::embedding_dim`] is authoritative.
     pub fn embedding_dim(&self) -> usize {
         match self {
             Self::MiniLm => 384,
             Self::AllMiniLmL6V2 => 384,
             Self::BgeSmall => 384,
             Self::E5Small => 384,
+            Self::Custom(_) => 384,
         }
     }
 
     /// Returns the maximum sequence length.
+    ///
+    /// For custom models this is the MiniLM default; the registered
+    /// [`ModelInfo::max_seq_length`] is authoritative.
     pub fn max_seq_length(&self) -> usize {
         match self {
             Self::MiniLm => 256,
             Self::AllMiniLmL6V2 => 256,
             Self::BgeSmall => 512,
             Self::E5Small => 512,
+            Self::Custom(_) => 256,
         }
     }
 
+    /// Returns whether this is a user-registered model.
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::Custom(_))
+    }
+
     /// Returns whether this model requires a query prefix.
     pub fn requires_query_prefix(&self) -> bool {
         matches!(self, Self::E5Small)
@@ -78,14 +109,46 @@ impl ModelType {
 pub enum DownloadStatus {
     /// Model is not downloaded.
     NotDownloaded,
-    /// Model is being downloaded.
-    Downloading,
+    /// Model weights are being downloaded.
+    Downloading {
+        /// Bytes received so far.
+        downloaded: u64,
+        /// Total size, if the server reported it.
+        total: Option<u64>,
+    },
+    /// Download finished and the checksum is being verified.
+    Verifying,
     /// Model is downloaded and ready.
     Ready,
-    /// Download failed.
+    /// Download or verification failed.
     Failed,
 }
 
+/// Where a model's files come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelSource {
+    /// A Hugging Face model repository.
+    HuggingFace {
+        /// Repository ID, e.g. `BAAI/bge-small-en-v1.5`.
+        repo: String,
+        /// Branch, tag or commit. Defaults to `main`.
+        revision: Option<String>,
+    },
+    /// A local directory containing `config.json`, `tokenizer.json` and
+    /// `model.safetensors`.
+    LocalPath(PathBuf),
+}
+
+impl ModelSource {
+    /// Creates a Hugging Face source for the default revision.
+    pub fn hugging_face(repo: impl Into<String>) -> Self {
+        Self::HuggingFace {
+            repo: repo.into(),
+            revision: None,
+        }
+    }
+}
+
 /// Information about a model.
 #[derive(Debug, Clone)]
 pub struct ModelInfo {
@@ -99,6 +162,14 @@ pub struct ModelInfo {
     pub size_bytes: u64,
     /// Download status.
     pub status: DownloadStatus,
+    /// Where the model files come from.
+    pub source: ModelSource,
+    /// Dimension of the embeddings the model produces.
+    pub embedding_dim: usize,
+    /// Maximum sequence length for tokenization.
+    pub max_seq_length: usize,
+    /// Expected SHA-256 of the weights file, as lowercase hex.
+    pub sha256: Option<String>,
 }
 
 impl ModelInfo {
@@ -125,6 +196,7 @@ impl ModelInfo {
                 "Good for asymmetric search (query vs document)",
                 130_000_000,
             ),
+            ModelType::Custom(_) => ("Custom model", "User-registered embedding model", 0),
         };
 
         Self {
@@ -133,9 +205,49 @@ impl ModelInfo {
             description: description.to_string(),
             size_bytes,
             status: DownloadStatus::NotDownloaded,
+            source: ModelSource::hugging_face(model_type.hf_model_id().unwrap_or_default()),
+            embedding_dim: model_type.embedding_dim(),
+            max_seq_length: model_type.max_seq_length(),
+            sha256: None,
+        }
+    }
+
+    /// Creates info for a user-supplied model.
+    ///
+    /// The model type is assigned when the info is passed to
+    /// [`ModelRegistry::register_custom`].
+    pub fn custom(name: impl Into<String>, source: ModelSource, embedding_dim: usize) -> Self {
+        Self {
+            model_type: ModelType::Custom(0),
+            name: name.into(),
+            description: String::new(),
+            size_bytes: 0,
+            status: DownloadStatus::NotDownloaded,
+            source,
+            embedding_dim,
+            max_seq_length: ModelType::Custom(0).max_seq_length(),
+            sha256: None,
         }
     }
 
+    /// Sets the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Sets the maximum sequence length.
+    pub fn with_max_seq_length(mut self, max_seq_length: usize) -> Self {
+        self.max_seq_length = max_seq_length;
+        self
+    }
+
+    /// Sets the expected SHA-256 of the weights file.
+    pub fn with_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.sha256 = Some(sha256.into().to_ascii_lowercase());
+        self
+    }
+
     /// Returns the size as a human-readable string.
     pub fn size_human(&self) -> String {
         let mb = self.size_bytes as f64 / 1_000_000.0;
@@ -196,6 +308,182 @@ impl ModelRegistry {
         self.get_model(ModelType::default())
             .expect("default model should always exist")
     }
+
+    /// Registers a user-supplied model and returns the type assigned to it.
+    pub fn register_custom(&mut self, mut info: ModelInfo) -> ModelType {
+        let next_id = self
+            .models
+            .iter()
+            .filter_map(|m| match m.model_type {
+                ModelType::Custom(id) => Some(id + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let model_type = ModelType::Custom(next_id);
+        info.model_type = model_type;
+        info.status = DownloadStatus::NotDownloaded;
+        self.models.push(info);
+        model_type
+    }
+
+    /// Downloads a model into `models_dir` and verifies its checksum.
+    ///
+    /// Progress is reported through `on_progress` and recorded as the
+    /// model's status. Local models are not copied; their weights are only
+    /// verified. Returns the directory holding the model files.
+    pub async fn download<F>(
+        &mut self,
+        model_type: ModelType,
+        models_dir: &Path,
+        mut on_progress: F,
+    ) -> Result<PathBuf>
+    where
+        F: FnMut(DownloadStatus),
+    {
+        let info = self
+            .get_model(model_type)
+            .cloned()
+            .with_context(|| format!("Unknown model: {:?}", model_type))?;
+
+        let mut report = |registry: &mut Self, status: DownloadStatus| {
+            registry.set_status(model_type, status);
+            on_progress(status);
+        };
+
+        let result = match &info.source {
+            ModelSource::LocalPath(dir) => {
+                report(&mut *self, DownloadStatus::Verifying);
+                verify_local(dir, info.sha256.as_deref())
+                    .await
+                    .map(|_| dir.clone())
+            }
+            ModelSource::HuggingFace { repo, revision } => {
+                let dir = models_dir.join(repo.replace('/', "--"));
+                let revision = revision.as_deref().unwrap_or("main");
+                download_hf(repo, revision, &dir, info.sha256.as_deref(), |status| {
+                    report(&mut *self, status)
+                })
+                .await
+                .map(|_| dir)
+            }
+        };
+
+        let status = if result.is_ok() {
+            DownloadStatus::Ready
+        } else {
+            DownloadStatus::Failed
+        };
+        if let Err(e) = &result {
+            tracing::warn!(model = %info.name, error = %e, "Model download failed");
+        }
+        report(&mut *self, status);
+        result
+    }
+}
+
+/// Checks that a local model directory is complete and its weights match.
+async fn verify_local(dir: &Path, sha256: Option<&str>) -> Result<()> {
+    for file in MODEL_FILES {
+        if !dir.join(file).is_file() {
+            bail!("Model directory {} is missing {}", dir.display(), file);
+        }
+    }
+
+    if let Some(expected) = sha256 {
+        let weights = tokio::fs::read(dir.join("model.safetensors"))
+            .await
+            .context("Failed to read model weights")?;
+        check_sha256(&weights_digest(&weights), expected)?;
+    }
+
+    Ok(())
+}
+
+/// Downloads the model files from Hugging Face, streaming the weights.
+async fn download_hf<F>(
+    repo: &str,
+    revision: &str,
+    dir: &Path,
+    sha256: Option<&str>,
+    mut on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(DownloadStatus),
+{
+    tokio::fs::create_dir_all(dir)
+        .await
+        .context("Failed to create model directory")?;
+    let client = reqwest::Client::new();
+
+    for file in MODEL_FILES {
+        let url = format!("{}/{}/resolve/{}/{}", HF_BASE_URL, repo, revision, file);
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to download {}", file))?;
+
+        let total = response.content_length();
+        let is_weights = *file == "model.safetensors";
+        let part_path = dir.join(format!("{}.part", file));
+        let mut out = tokio::fs::File::create(&part_path)
+            .await
+            .context("Failed to create model file")?;
+        let mut hasher = digest::Context::new(&digest::SHA256);
+        let mut downloaded = 0u64;
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.with_context(|| format!("Failed to download {}", file))?;
+            out.write_all(&chunk)
+                .await
+                .context("Failed to write model file")?;
+            downloaded += chunk.len() as u64;
+            if is_weights {
+                hasher.update(&chunk);
+                on_progress(DownloadStatus::Downloading { downloaded, total });
+            }
+        }
+        out.flush().await.context("Failed to write model file")?;
+
+        if is_weights {
+            on_progress(DownloadStatus::Verifying);
+            if let Some(expected) = sha256 {
+                let actual = to_hex(hasher.finish().as_ref());
+                if let Err(e) = check_sha256(&actual, expected) {
+                    let _ = tokio::fs::remove_file(&part_path).await;
+                    return Err(e);
+                }
+            }
+        }
+
+        tokio::fs::rename(&part_path, dir.join(file))
+            .await
+            .context("Failed to move model file into place")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the lowercase hex SHA-256 of the given bytes.
+fn weights_digest(bytes: &[u8]) -> String {
+    to_hex(digest::digest(&digest::SHA256, bytes).as_ref())
+}
+
+/// Compares a computed digest with the expected one.
+fn check_sha256(actual: &str, expected: &str) -> Result<()> {
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("Checksum mismatch: expected {}, got {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// Encodes bytes as lowercase hex.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 #[cfg(test)]
@@ -247,6 +535,82 @@ mod tests {
         assert_eq!(ready.len(), 1);
     }
 
+    #[test]
+    fn register_custom_assigns_distinct_types() {
+        let mut registry = ModelRegistry::new();
+
+        let first = registry.register_custom(ModelInfo::custom(
+            "GTE-Base",
+            ModelSource::hugging_face("thenlper/gte-base"),
+            768,
+        ));
+        let second = registry.register_custom(ModelInfo::custom(
+            "Local",
+            ModelSource::LocalPath(PathBuf::from("/models/local")),
+            384,
+        ));
+
+        assert_eq!(first, ModelType::Custom(0));
+        assert_eq!(second, ModelType::Custom(1));
+        assert_eq!(registry.all_models().len(), 6);
+        assert_eq!(registry.get_model(first).unwrap().embedding_dim, 768);
+        assert_eq!(
+            registry.default_model().model_type,
+            ModelType::AllMiniLmL6V2
+        );
+    }
+
+    #[tokio::test]
+    async fn local_model_checksum_mismatch_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in MODEL_FILES {
+            std::fs::write(dir.path().join(file), b"data").unwrap();
+        }
+
+        let mut registry = ModelRegistry::new();
+        let model = registry.register_custom(
+            ModelInfo::custom("Local", ModelSource::LocalPath(dir.path().into()), 384)
+                .with_sha256("00"),
+        );
+
+        let mut statuses = Vec::new();
+        let result = registry
+            .download(model, dir.path(), |s| statuses.push(s))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            statuses,
+            vec![DownloadStatus::Verifying, DownloadStatus::Failed]
+        );
+        assert_eq!(
+            registry.get_model(model).unwrap().status,
+            DownloadStatus::Failed
+        );
+    }
+
+    #[tokio::test]
+    async fn local_model_with_matching_checksum_is_ready() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in MODEL_FILES {
+            std::fs::write(dir.path().join(file), b"data").unwrap();
+        }
+
+        let mut registry = ModelRegistry::new();
+        let model = registry.register_custom(
+            ModelInfo::custom("Local", ModelSource::LocalPath(dir.path().into()), 384)
+                .with_sha256(weights_digest(b"data")),
+        );
+
+        let path = registry.download(model, dir.path(), |_| {}).await.unwrap();
+
+        assert_eq!(path, dir.path());
+        assert_eq!(
+            registry.get_model(model).unwrap().status,
+            DownloadStatus::Ready
+        );
+    }
+
     #[test]
     fn model_serialization() {
         let model = ModelType::BgeSmall;
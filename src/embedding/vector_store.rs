@@ -43,6 +43,15 @@ impl VectorStore {
         self.embeddings.remove(email_id)
     }
 
+    /// Removes the embeddings for several emails, such as those of a deleted
+    /// account, returning how many were stored.
+    pub fn remove_all<'a>(&mut self, email_ids: impl IntoIterator<Item = &'a EmailId>) -> usize {
+        email_ids
+            .into_iter()
+            .filter(|email_id| self.embeddings.remove(email_id).is_some())
+            .count()
+    }
+
     /// Returns whether an embedding exists for the given email.
     pub fn contains(&self, email_id: &EmailId) -> bool {
         self.embeddings.contains_key(email_id)
@@ -129,6 +138,22 @@ mod tests {
         assert_eq!(retrieved.values, vec![1.0, 0.0, 0.0]);
     }
 
+    #[test]
+    fn remove_all_drops_listed_emails() {
+        let mut store = VectorStore::new();
+        for id in ["email-1", "email-2", "email-3"] {
+            store
+                .insert(&EmailId::from(id), make_embedding(&[1.0]))
+                .unwrap();
+        }
+
+        let removed = store.remove_all(&[EmailId::from("email-1"), EmailId::from("missing")]);
+
+        assert_eq!(removed, 1);
+        assert_eq!(store.len(), 2);
+        assert!(!store.contains(&EmailId::from("email-1")));
+    }
+
     #[test]
     fn insert_updates_existing() {
         let mut store = VectorStore::new();
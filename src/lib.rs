@@ -4,12 +4,16 @@
 //! including email protocol handling, AI services, and storage management.
 
 pub mod app;
+pub mod client;
 pub mod config;
 pub mod domain;
 pub mod embedding;
+pub mod logging;
 pub mod providers;
 pub mod services;
 pub mod storage;
 pub mod ui;
 
 pub use app::App;
+pub use client::{MarginClient, ShutdownReport};
+pub use logging::{init_logging, LogConfig, LogFormat};
@@ -0,0 +1,234 @@
+//! Logging setup and provider call spans.
+//!
+//! [`init_logging`] installs a `tracing` subscriber configured by a
+//! [`LogConfig`], read from the settings file or the environment. The app
+//! calls it on startup; library users, including [`MarginClient`] scripts,
+//! call it themselves if they want the crate's logs.
+//!
+//! Sync cycles run in a `sync` span and provider requests in a
+//! `provider_call` span, both with the account ID, and each logs its
+//! duration when it finishes.
+//!
+//! [`MarginClient`]: crate::MarginClient
+
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::Instrument;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+use crate::domain::AccountId;
+
+/// Environment variable overriding the log filter. `RUST_LOG` is used when
+/// it is unset.
+pub const LOG_LEVEL_ENV: &str = "HEAP_LOG";
+
+/// Environment variable overriding the log format, `pretty` or `json`.
+pub const LOG_FORMAT_ENV: &str = "HEAP_LOG_FORMAT";
+
+/// Errors from setting up logging.
+#[derive(Debug, Error)]
+pub enum LoggingError {
+    /// The filter is not valid `RUST_LOG` syntax.
+    #[error("invalid log filter: {0}")]
+    InvalidFilter(String),
+
+    /// A global subscriber was already installed.
+    #[error("logging is already initialized")]
+    AlreadyInitialized,
+}
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the fields of the enclosing spans.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pretty" | "text" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown log format: {}", other)),
+        }
+    }
+}
+
+/// Log level and format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Filter in `RUST_LOG` syntax, e.g. `info` or `warn,heap=debug`.
+    pub level: String,
+    /// Output format.
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Returns the defaults with any environment overrides applied.
+    pub fn from_env() -> Self {
+        Self::default().with_env_overrides()
+    }
+
+    /// Applies [`LOG_LEVEL_ENV`] (or `RUST_LOG`) and [`LOG_FORMAT_ENV`] on
+    /// top of this config. An unknown format is ignored.
+    pub fn with_env_overrides(mut self) -> Self {
+        let level = std::env::var(LOG_LEVEL_ENV).or_else(|_| std::env::var("RUST_LOG"));
+        if let Ok(level) = level {
+            if !level.trim().is_empty() {
+                self.level = level;
+            }
+        }
+        if let Some(format) = std::env::var(LOG_FORMAT_ENV)
+            .ok()
+            .and_then(|format| format.parse().ok())
+        {
+            self.format = format;
+        }
+        self
+    }
+}
+
+/// Installs the global subscriber, writing to stdout.
+pub fn init_logging(config: &LogConfig) -> Result<(), LoggingError> {
+    init_logging_with_writer(config, std::io::stdout)
+}
+
+/// Installs the global subscriber, writing to `writer`.
+pub fn init_logging_with_writer<W>(config: &LogConfig, writer: W) -> Result<(), LoggingError>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter = EnvFilter::try_new(&config.level)
+        .map_err(|e| LoggingError::InvalidFilter(e.to_string()))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    let result = match config.format {
+        LogFormat::Pretty => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+    result.map_err(|_| LoggingError::AlreadyInitialized)
+}
+
+/// Runs a request to an account's provider in a `provider_call` span and
+/// logs how long it took.
+pub(crate) async fn provider_call<T, E, F>(
+    operation: &'static str,
+    account_id: &AccountId,
+    call: F,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    let span = tracing::info_span!("provider_call", operation, account_id = %account_id);
+    async move {
+        let start = Instant::now();
+        let result = call.await;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        match &result {
+            Ok(_) => tracing::debug!(duration_ms, "provider call finished"),
+            Err(e) => tracing::debug!(duration_ms, error = %e, "provider call failed"),
+        }
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything written to it.
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn json_logs_are_one_object_per_line() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let writer = {
+            let output = output.clone();
+            move || Capture(output.clone())
+        };
+        let config = LogConfig {
+            level: "debug".to_string(),
+            format: LogFormat::Json,
+        };
+        init_logging_with_writer(&config, writer).unwrap();
+
+        let account_id = AccountId::from("account-1");
+        let result: Result<(), String> = provider_call("fetch_threads", &account_id, async {
+            Err("timed out".into())
+        })
+        .await;
+        assert!(result.is_err());
+        tracing::trace!("below the filter");
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is JSON"))
+            .collect();
+        let failed = lines
+            .iter()
+            .find(|line| line["fields"]["message"] == "provider call failed")
+            .expect("provider call is logged");
+        assert_eq!(failed["fields"]["error"], "timed out");
+        assert!(failed["fields"]["duration_ms"].is_u64());
+        assert_eq!(failed["span"]["name"], "provider_call");
+        assert_eq!(failed["span"]["account_id"], "account-1");
+        assert_eq!(failed["span"]["operation"], "fetch_threads");
+        assert!(lines.iter().all(|line| line["level"] != "TRACE"));
+
+        assert!(matches!(
+            init_logging(&config),
+            Err(LoggingError::AlreadyInitialized)
+        ));
+    }
+
+    #[test]
+    fn log_formats_parse_case_insensitively() {
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!(" pretty ".parse::<LogFormat>(), Ok(LogFormat::Pretty));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
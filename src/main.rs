@@ -1,21 +1,91 @@
 //! The Heap - Entry point for the desktop email client
 
-use heap::App;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use directories::ProjectDirs;
+use heap::config::Settings;
+use heap::{App, LogConfig, MarginClient};
 
 fn main() {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+    let (settings, settings_error) = match load_settings() {
+        Ok(settings) => (settings, None),
+        Err(e) => (Settings::default(), Some(e)),
+    };
+
+    // Initialize logging, letting the environment override the settings,
+    // and fall back to the default logging when they're invalid
+    let log_config = settings.logging.clone().with_env_overrides();
+    let logging_error = match heap::init_logging(&log_config) {
+        Ok(()) => None,
+        Err(e) => {
+            let _ = heap::init_logging(&LogConfig::default());
+            Some(e)
+        }
+    };
 
     tracing::info!("Starting The Heap");
+    if let Some(e) = logging_error {
+        tracing::warn!("Invalid logging settings, using the defaults: {}", e);
+    }
+    if let Some(e) = settings_error {
+        tracing::warn!("Failed to load settings, using the defaults: {:#}", e);
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            tracing::error!("Failed to start the async runtime: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let client = match runtime.block_on(open_client(&settings)) {
+        Ok(client) => Arc::new(client),
+        Err(e) => {
+            tracing::error!("Failed to open the database: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Connect in the background so the window opens offline at once, then
+    // sync, and purge expired trash and spam, now that providers can be
+    // reached
+    let connecting = client.clone();
+    let background_settings = settings.clone();
+    runtime.spawn(async move {
+        connecting.start_evicting(&background_settings);
+        connecting.start_followup_checks().await;
+        match connecting.connect_accounts().await {
+            Ok(connected) => tracing::info!(connected, "Connected accounts"),
+            Err(e) => tracing::error!("Failed to connect accounts: {}", e),
+        }
+        connecting.start_syncing(&background_settings).await;
+        connecting.start_purging(&background_settings).await;
+    });
 
     // Run the gpui application
-    if let Err(e) = App::run() {
+    if let Err(e) = App::run_with_client(client, runtime.handle().clone()) {
         tracing::error!("Application error: {}", e);
         std::process::exit(1);
     }
 }
+
+/// Reads the settings saved in the user's config directory.
+fn load_settings() -> anyhow::Result<Settings> {
+    let path = Settings::default_path().context("No home directory")?;
+    Settings::load(&path)
+}
+
+/// Opens the client over the database in the user's data directory.
+async fn open_client(settings: &Settings) -> anyhow::Result<MarginClient> {
+    MarginClient::open_with_settings(database_path()?, settings).await
+}
+
+/// Returns where the database lives, creating its directory.
+fn database_path() -> anyhow::Result<PathBuf> {
+    let dirs = ProjectDirs::from("com", "panbanda", "heap").context("No home directory")?;
+    std::fs::create_dir_all(dirs.data_dir())
+        .with_context(|| format!("Failed to create {}", dirs.data_dir().display()))?;
+    Ok(dirs.data_dir().join("heap.db"))
+}
@@ -11,6 +11,7 @@ use super::traits::{
     CompletionRequest, CompletionResponse, CompletionStream, FinishReason, LlmError, LlmProvider,
     LlmResult, Message, Role, StreamChunk, TokenUsage,
 };
+use crate::providers::http;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
@@ -170,7 +171,7 @@ impl AnthropicProvider {
         let context_length = model_context_length(&model);
 
         Self {
-            client: reqwest::Client::new(),
+            client: http::client(http::DEFAULT_REQUEST_TIMEOUT),
             api_key: api_key.into(),
             model,
             context_length,
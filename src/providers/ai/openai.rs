@@ -4,7 +4,8 @@
 
 use async_trait::async_trait;
 use futures::Stream;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -13,6 +14,7 @@ use super::traits::{
     CompletionRequest, CompletionResponse, CompletionStream, FinishReason, LlmError, LlmProvider,
     LlmResult, Message, Role, StreamChunk, TokenUsage,
 };
+use crate::providers::http;
 
 /// Default base URL for OpenAI API.
 const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
@@ -136,12 +138,23 @@ struct OpenAiErrorDetail {
 /// - vLLM
 /// - LM Studio
 /// - Any other OpenAI-compatible endpoint
+///
+/// Point it at any endpoint at runtime with the builder:
+///
+/// ```rust,no_run
+/// # use heap::providers::ai::OpenAiCompatibleProvider;
+/// let provider = OpenAiCompatibleProvider::with_base_url("https://openrouter.ai/api/v1")
+///     .with_model("meta-llama/llama-3-70b-instruct")
+///     .with_header("HTTP-Referer", "https://example.com")
+///     .with_api_key("sk-or-...");
+/// ```
 pub struct OpenAiCompatibleProvider {
     client: reqwest::Client,
     base_url: String,
     api_key: Option<String>,
     model: String,
     context_length: usize,
+    headers: Vec<(String, String)>,
 }
 
 impl OpenAiCompatibleProvider {
@@ -151,11 +164,12 @@ impl OpenAiCompatibleProvider {
         let context_length = model_context_length(&model);
 
         Self {
-            client: reqwest::Client::new(),
+            client: http::client(http::DEFAULT_REQUEST_TIMEOUT),
             base_url: OPENAI_BASE_URL.to_string(),
             api_key: Some(api_key.into()),
             model,
             context_length,
+            headers: Vec::new(),
         }
     }
 
@@ -169,12 +183,53 @@ impl OpenAiCompatibleProvider {
         let context_length = model_context_length(&model);
 
         Self {
-            client: reqwest::Client::new(),
+            client: http::client(http::DEFAULT_REQUEST_TIMEOUT),
             base_url: base_url.into().trim_end_matches('/').to_string(),
             api_key,
             model,
             context_length,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Starts building a provider for the endpoint at `base_url`, e.g.
+    /// `http://localhost:1234/v1` for LM Studio.
+    ///
+    /// The URL is checked by [`validate`](Self::validate) and before every
+    /// request; set the model with [`with_model`](Self::with_model).
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self::custom(base_url, None, "")
+    }
+
+    /// Sets the model, and the context length known for it.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self.context_length = model_context_length(&self.model);
+        self
+    }
+
+    /// Sets the API key sent as a bearer token.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Adds a header sent with every request, e.g. an organization ID or a
+    /// gateway's routing header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Checks the base URL, model, and custom headers without making a
+    /// request.
+    pub fn validate(&self) -> LlmResult<()> {
+        self.endpoint("chat/completions")?;
+        if self.model.is_empty() {
+            return Err(LlmError::InvalidConfig("No model set".to_string()));
         }
+        self.build_headers()?;
+        Ok(())
     }
 
     /// Overrides the context length (useful for local models with custom context).
@@ -189,7 +244,24 @@ impl OpenAiCompatibleProvider {
         self
     }
 
-    fn build_headers(&self) -> HeaderMap {
+    /// Returns the URL for an API path under the base URL.
+    fn endpoint(&self, path: &str) -> LlmResult<Url> {
+        let invalid = |reason: &str| {
+            LlmError::InvalidConfig(format!("Invalid base URL {:?}: {}", self.base_url, reason))
+        };
+
+        let url = Url::parse(&format!("{}/{}", self.base_url, path))
+            .map_err(|e| invalid(&e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid("scheme must be http or https"));
+        }
+        if url.host_str().map_or(true, str::is_empty) {
+            return Err(invalid("missing host"));
+        }
+        Ok(url)
+    }
+
+    fn build_headers(&self) -> LlmResult<HeaderMap> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
@@ -199,7 +271,41 @@ impl OpenAiCompatibleProvider {
             }
         }
 
-        headers
+        for (name, value) in &self.headers {
+            let invalid = || LlmError::InvalidConfig(format!("Invalid header {:?}", name));
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+            let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+            headers.insert(name, value);
+        }
+
+        Ok(headers)
+    }
+
+    /// Posts a chat completion request.
+    ///
+    /// Failing to reach the server at all is reported as
+    /// [`LlmError::ConnectionFailed`] naming the base URL, since a wrong
+    /// endpoint in settings is the usual cause.
+    async fn post_completion(&self, body: &OpenAiRequest) -> LlmResult<reqwest::Response> {
+        let url = self.endpoint("chat/completions")?;
+        let headers = self.build_headers()?;
+
+        self.client
+            .post(url)
+            .headers(headers)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() {
+                    LlmError::ConnectionFailed {
+                        url: self.base_url.clone(),
+                        message: e.to_string(),
+                    }
+                } else {
+                    LlmError::HttpError(e)
+                }
+            })
     }
 
     fn build_request(&self, request: &CompletionRequest, stream: bool) -> OpenAiRequest {
@@ -281,16 +387,8 @@ impl LlmProvider for OpenAiCompatibleProvider {
     }
 
     async fn complete(&self, request: &CompletionRequest) -> LlmResult<CompletionResponse> {
-        let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request(request, false);
-
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.build_headers())
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.post_completion(&body).await?;
 
         if !response.status().is_success() {
             return Err(self.handle_error_response(response).await);
@@ -326,16 +424,8 @@ impl LlmProvider for OpenAiCompatibleProvider {
     }
 
     async fn stream_complete(&self, request: &CompletionRequest) -> LlmResult<CompletionStream> {
-        let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request(request, true);
-
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.build_headers())
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.post_completion(&body).await?;
 
         if !response.status().is_success() {
             return Err(self.handle_error_response(response).await);
@@ -580,6 +670,125 @@ mod tests {
         assert!(chunk.choices[0].finish_reason.is_none());
     }
 
+    /// Serves one chat completion on a local port, returning the base URL
+    /// and the raw request received.
+    async fn serve_completion() -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await.unwrap();
+            request.push_str(&String::from_utf8_lossy(&body));
+
+            let response_body = r#"{"choices":[{"message":{"content":"Hi from the mock"},"finish_reason":"stop"}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            );
+            reader
+                .get_mut()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+            request
+        });
+
+        (base_url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_builder_against_mock_server() {
+        let (base_url, server) = serve_completion().await;
+        let provider = OpenAiCompatibleProvider::with_base_url(format!("{}/", base_url))
+            .with_model("local-model")
+            .with_header("X-Gateway-Route", "primary")
+            .with_api_key("secret");
+        provider.validate().unwrap();
+
+        let response = provider
+            .complete(&CompletionRequest::new(vec![Message::user("Hello")]))
+            .await
+            .unwrap();
+        assert_eq!(response.text, "Hi from the mock");
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /v1/chat/completions "));
+        assert!(request.contains("authorization: bearer secret"));
+        assert!(request.contains("x-gateway-route: primary"));
+        assert!(request.contains("\"model\":\"local-model\""));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_base_url_is_rejected() {
+        for url in ["localhost:1234/v1", "ftp://example.com/v1", "not a url"] {
+            let provider = OpenAiCompatibleProvider::with_base_url(url).with_model("m");
+            assert!(
+                matches!(provider.validate(), Err(LlmError::InvalidConfig(_))),
+                "{} should be rejected",
+                url
+            );
+        }
+
+        let provider = OpenAiCompatibleProvider::with_base_url("not a url").with_model("m");
+        let result = provider
+            .complete(&CompletionRequest::new(vec![Message::user("Hi")]))
+            .await;
+        assert!(matches!(result, Err(LlmError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_invalid_header_and_missing_model_are_rejected() {
+        let provider = OpenAiCompatibleProvider::with_base_url("http://localhost:1234/v1")
+            .with_model("m")
+            .with_header("Bad Header", "x");
+        assert!(matches!(
+            provider.validate(),
+            Err(LlmError::InvalidConfig(_))
+        ));
+
+        let provider = OpenAiCompatibleProvider::with_base_url("http://localhost:1234/v1");
+        assert!(matches!(
+            provider.validate(),
+            Err(LlmError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connection_failure_names_endpoint() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        drop(listener);
+
+        let provider = OpenAiCompatibleProvider::with_base_url(&base_url).with_model("m");
+        let result = provider
+            .complete(&CompletionRequest::new(vec![Message::user("Hi")]))
+            .await;
+        match result {
+            Err(LlmError::ConnectionFailed { url, .. }) => assert_eq!(url, base_url),
+            other => panic!("expected a connection failure, got {:?}", other.err()),
+        }
+    }
+
     #[test]
     fn test_trailing_slash_removal() {
         let provider =
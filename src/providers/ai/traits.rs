@@ -32,6 +32,12 @@ pub enum LlmError {
 
     #[error("Provider not available: {0}")]
     Unavailable(String),
+
+    #[error("Could not connect to {url}: {message}")]
+    ConnectionFailed { url: String, message: String },
+
+    #[error("Invalid provider configuration: {0}")]
+    InvalidConfig(String),
 }
 
 /// Result type for LLM operations.
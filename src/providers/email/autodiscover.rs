@@ -0,0 +1,256 @@
+//! IMAP/SMTP server autodiscovery.
+//!
+//! Derives server settings from an email address so most users never have
+//! to type a hostname. Discovery tries, in order:
+//!
+//! 1. A built-in table of common providers, keyed by domain.
+//! 2. Mozilla's ISP database (the Thunderbird autoconfig service), which
+//!    covers several thousand domains.
+//!
+//! [`ImapConfig`] carries a single TLS mode for both protocols, so providers
+//! that mix implicit TLS and STARTTLS (e.g. iCloud: IMAP on 993, SMTP on 587)
+//! are not discoverable and fall back to manual entry.
+
+use std::time::Duration;
+
+use super::ImapConfig;
+
+/// Mozilla ISP database endpoint; the domain is appended.
+const ISPDB_URL: &str = "https://autoconfig.thunderbird.net/v1.1/";
+
+/// Timeout for the ISP database lookup.
+const ISPDB_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Known provider settings: (domains, imap host, smtp host, use implicit TLS).
+const KNOWN_PROVIDERS: &[(&[&str], &str, &str, bool)] = &[
+    (
+        &["gmail.com", "googlemail.com"],
+        "imap.gmail.com",
+        "smtp.gmail.com",
+        true,
+    ),
+    (
+        &["outlook.com", "hotmail.com", "live.com", "msn.com"],
+        "outlook.office365.com",
+        "smtp.office365.com",
+        false,
+    ),
+    (
+        &["yahoo.com", "ymail.com", "rocketmail.com"],
+        "imap.mail.yahoo.com",
+        "smtp.mail.yahoo.com",
+        true,
+    ),
+    (&["aol.com"], "imap.aol.com", "smtp.aol.com", true),
+    (
+        &["fastmail.com", "fastmail.fm"],
+        "imap.fastmail.com",
+        "smtp.fastmail.com",
+        true,
+    ),
+    (&["zoho.com"], "imap.zoho.com", "smtp.zoho.com", true),
+    (
+        &["gmx.com", "gmx.net", "gmx.de"],
+        "imap.gmx.net",
+        "mail.gmx.net",
+        true,
+    ),
+    (
+        &["yandex.com", "yandex.ru"],
+        "imap.yandex.com",
+        "smtp.yandex.com",
+        true,
+    ),
+    (&["mail.ru"], "imap.mail.ru", "smtp.mail.ru", true),
+];
+
+/// Returns the lowercase domain part of an email address.
+pub(crate) fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().rsplit_once('@')?;
+    if local.is_empty() || domain.is_empty() || !domain.contains('.') {
+        return None;
+    }
+    Some(domain.to_ascii_lowercase())
+}
+
+/// Looks up a domain in the built-in provider table.
+pub(crate) fn lookup_known(domain: &str) -> Option<ImapConfig> {
+    KNOWN_PROVIDERS
+        .iter()
+        .find(|(domains, ..)| domains.contains(&domain))
+        .map(|&(_, imap_host, smtp_host, use_tls)| {
+            if use_tls {
+                ImapConfig::tls(imap_host, smtp_host)
+            } else {
+                ImapConfig::starttls(imap_host, smtp_host)
+            }
+        })
+}
+
+/// Queries Mozilla's ISP database for a domain.
+pub(crate) async fn lookup_ispdb(client: &reqwest::Client, domain: &str) -> Option<ImapConfig> {
+    let response = client
+        .get(format!("{}{}", ISPDB_URL, domain))
+        .timeout(ISPDB_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    parse_ispdb(&body)
+}
+
+/// Extracts IMAP and SMTP settings from an ISP database document.
+///
+/// Uses the first IMAP incoming server and the first SMTP outgoing server.
+/// Returns `None` if either is missing or their socket types disagree.
+pub(crate) fn parse_ispdb(xml: &str) -> Option<ImapConfig> {
+    let incoming = find_server(xml, "incomingServer", "imap")?;
+    let outgoing = find_server(xml, "outgoingServer", "smtp")?;
+
+    let use_tls = match (incoming.socket_type.as_str(), outgoing.socket_type.as_str()) {
+        ("SSL", "SSL") => true,
+        ("STARTTLS", "STARTTLS") => false,
+        _ => return None,
+    };
+
+    Some(ImapConfig {
+        imap_host: incoming.hostname,
+        imap_port: incoming.port,
+        smtp_host: outgoing.hostname,
+        smtp_port: outgoing.port,
+        use_tls,
+        allow_plaintext: false,
+        tls_root_cert: None,
+        accept_invalid_certs: false,
+        preview_bytes: ImapConfig::DEFAULT_PREVIEW_BYTES,
+    })
+}
+
+/// A server entry from an ISP database document.
+struct ServerEntry {
+    hostname: String,
+    port: u16,
+    socket_type: String,
+}
+
+/// Finds the first `<{element} type="{kind}">` block and reads its fields.
+fn find_server(xml: &str, element: &str, kind: &str) -> Option<ServerEntry> {
+    let open = format!("<{} type=\"{}\">", element, kind);
+    let close = format!("</{}>", element);
+
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let block = &xml[start..end];
+
+    let hostname = tag_text(block, "hostname")?;
+    // Templated hostnames depend on the user's address; leave those manual.
+    if hostname.contains('%') {
+        return None;
+    }
+
+    Some(ServerEntry {
+        hostname: hostname.to_string(),
+        port: tag_text(block, "port")?.parse().ok()?,
+        socket_type: tag_text(block, "socketType")?.to_string(),
+    })
+}
+
+/// Returns the trimmed text of the first `<tag>...</tag>` in a block.
+fn tag_text<'a>(block: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = block.find(&open)? + open.len();
+    let end = start + block[start..].find(&close)?;
+    Some(block[start..end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISPDB_SAMPLE: &str = r#"<?xml version="1.0"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.org">
+    <domain>example.org</domain>
+    <incomingServer type="pop3">
+      <hostname>pop.example.org</hostname>
+      <port>995</port>
+      <socketType>SSL</socketType>
+    </incomingServer>
+    <incomingServer type="imap">
+      <hostname>imap.example.org</hostname>
+      <port>993</port>
+      <socketType>SSL</socketType>
+      <username>%EMAILADDRESS%</username>
+    </incomingServer>
+    <outgoingServer type="smtp">
+      <hostname>smtp.example.org</hostname>
+      <port>465</port>
+      <socketType>SSL</socketType>
+    </outgoingServer>
+  </emailProvider>
+</clientConfig>"#;
+
+    #[test]
+    fn email_domain_extraction() {
+        assert_eq!(email_domain("User@Example.COM"), Some("example.com".into()));
+        assert_eq!(email_domain("no-at-sign"), None);
+        assert_eq!(email_domain("@example.com"), None);
+        assert_eq!(email_domain("user@localhost"), None);
+    }
+
+    #[test]
+    fn known_tls_provider() {
+        let config = lookup_known("gmail.com").unwrap();
+        assert_eq!(config.imap_host, "imap.gmail.com");
+        assert_eq!(config.imap_port, 993);
+        assert_eq!(config.smtp_port, 465);
+        assert!(config.use_tls);
+    }
+
+    #[test]
+    fn known_starttls_provider() {
+        let config = lookup_known("hotmail.com").unwrap();
+        assert_eq!(config.imap_host, "outlook.office365.com");
+        assert_eq!(config.smtp_port, 587);
+        assert!(!config.use_tls);
+    }
+
+    #[test]
+    fn unknown_domain() {
+        assert!(lookup_known("unknown-domain.example").is_none());
+    }
+
+    #[test]
+    fn parse_ispdb_prefers_imap_over_pop() {
+        let config = parse_ispdb(ISPDB_SAMPLE).unwrap();
+        assert_eq!(config.imap_host, "imap.example.org");
+        assert_eq!(config.imap_port, 993);
+        assert_eq!(config.smtp_host, "smtp.example.org");
+        assert_eq!(config.smtp_port, 465);
+        assert!(config.use_tls);
+    }
+
+    #[test]
+    fn parse_ispdb_rejects_mixed_socket_types() {
+        let xml = ISPDB_SAMPLE.replace(
+            "<port>465</port>\n      <socketType>SSL</socketType>",
+            "<port>587</port>\n      <socketType>STARTTLS</socketType>",
+        );
+        assert!(parse_ispdb(&xml).is_none());
+    }
+
+    #[test]
+    fn parse_ispdb_rejects_templated_hostname() {
+        let xml = ISPDB_SAMPLE.replace("imap.example.org", "%EMAILDOMAIN%");
+        assert!(parse_ispdb(&xml).is_none());
+    }
+
+    #[test]
+    fn parse_ispdb_garbage() {
+        assert!(parse_ispdb("not xml").is_none());
+    }
+}
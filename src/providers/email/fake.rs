@@ -0,0 +1,736 @@
+//! In-memory email provider for tests.
+//!
+//! [`FakeEmailProvider`] keeps a mailbox in memory that tests seed with
+//! messages. Label operations, sends and pushed changes modify it the way a
+//! server would, and every modification is recorded so the sync pipeline
+//! sees it as a change. Available in unit tests and, for integration tests
+//! and tools, with the `fake-provider` feature.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+use super::traits::{
+    Change, EmailProvider, EmailUpdate, NewEmailData, OutgoingEmail, Pagination, PendingChange,
+    PendingChangeType, ProviderCapabilities, ProviderError, Result,
+};
+use crate::domain::{
+    Account, AccountId, Address, Email, EmailId, Label, LabelId, MessageId, ProviderType, Thread,
+    ThreadId, ThreadSummary,
+};
+
+/// Labels reported by [`EmailProvider::fetch_labels`] even when no message
+/// carries them.
+const SYSTEM_LABELS: [&str; 6] = ["INBOX", "SENT", "DRAFTS", "TRASH", "SPAM", "STARRED"];
+
+/// A modification of the fake mailbox.
+#[derive(Debug, Clone)]
+pub enum MailboxChange {
+    /// A message was added, by seeding or sending.
+    Added(Box<Email>),
+    /// A message's flags or labels changed.
+    Updated {
+        /// The message that changed.
+        email_id: EmailId,
+        /// New read state, if it changed.
+        is_read: Option<bool>,
+        /// New starred state, if it changed.
+        is_starred: Option<bool>,
+        /// Labels added.
+        added: Vec<LabelId>,
+        /// Labels removed.
+        removed: Vec<LabelId>,
+    },
+    /// A message was deleted.
+    Deleted(EmailId),
+}
+
+#[derive(Default)]
+struct Mailbox {
+    emails: Vec<Email>,
+    sent: Vec<OutgoingEmail>,
+    pushed: Vec<PendingChange>,
+    changes: Vec<(DateTime<Utc>, MailboxChange)>,
+    authenticated: bool,
+}
+
+impl Mailbox {
+    /// Records a change, timestamped strictly after the previous one so
+    /// changes made in quick succession stay ordered.
+    fn record(&mut self, change: MailboxChange) {
+        let now = Utc::now();
+        let at = match self.changes.last() {
+            Some((last, _)) if *last >= now => *last + Duration::microseconds(1),
+            _ => now,
+        };
+        self.changes.push((at, change));
+    }
+
+    /// Adds and removes labels on every message of the given threads,
+    /// recording a change for each message that changed.
+    fn modify(&mut self, thread_ids: &[String], add: &[&str], remove: &[&str]) {
+        let mut changes = Vec::new();
+        for email in self
+            .emails
+            .iter_mut()
+            .filter(|e| thread_ids.contains(&e.thread_id.0))
+        {
+            let removed: Vec<LabelId> = email
+                .labels
+                .iter()
+                .filter(|l| remove.contains(&l.0.as_str()))
+                .cloned()
+                .collect();
+            email.labels.retain(|l| !removed.contains(l));
+
+            let added: Vec<LabelId> = add
+                .iter()
+                .map(|l| LabelId::from(*l))
+                .filter(|l| !email.labels.contains(l))
+                .collect();
+            email.labels.extend(added.iter().cloned());
+
+            if !added.is_empty() || !removed.is_empty() {
+                changes.push(MailboxChange::Updated {
+                    email_id: email.id.clone(),
+                    is_read: None,
+                    is_starred: None,
+                    added,
+                    removed,
+                });
+            }
+        }
+        for change in changes {
+            self.record(change);
+        }
+    }
+
+    /// Sets a flag on every message of a thread, recording a change for each
+    /// message whose flag changed.
+    fn set_flag(
+        &mut self,
+        thread_id: &str,
+        flag: impl Fn(&mut Email) -> &mut bool,
+        value: bool,
+        change: impl Fn(EmailId) -> MailboxChange,
+    ) {
+        let mut changes = Vec::new();
+        for email in self
+            .emails
+            .iter_mut()
+            .filter(|e| e.thread_id.0 == thread_id)
+        {
+            let current = flag(email);
+            if *current != value {
+                *current = value;
+                changes.push(change(email.id.clone()));
+            }
+        }
+        for change in changes {
+            self.record(change);
+        }
+    }
+
+    fn thread_messages(&self, thread_id: &str) -> Vec<Email> {
+        let mut messages: Vec<Email> = self
+            .emails
+            .iter()
+            .filter(|e| e.thread_id.0 == thread_id)
+            .cloned()
+            .collect();
+        messages.sort_by_key(|e| e.date);
+        messages
+    }
+}
+
+/// Email provider backed by an in-memory mailbox.
+///
+/// Threads have native labels like Gmail: archiving removes `INBOX`,
+/// trashing and reporting spam also add `TRASH` or `SPAM`.
+pub struct FakeEmailProvider {
+    account_id: AccountId,
+    address: String,
+    capabilities: ProviderCapabilities,
+    mailbox: Mutex<Mailbox>,
+}
+
+impl FakeEmailProvider {
+    /// Creates a provider with an empty mailbox for the account with the
+    /// given address.
+    pub fn new(account_id: impl Into<AccountId>, address: impl Into<String>) -> Self {
+        Self {
+            account_id: account_id.into(),
+            address: address.into(),
+            capabilities: ProviderCapabilities {
+                native_labels: true,
+                server_search: false,
+                push: false,
+                threads: true,
+                move_supported: true,
+            },
+            mailbox: Mutex::new(Mailbox::default()),
+        }
+    }
+
+    /// Creates a provider with an empty mailbox for an account.
+    pub fn for_account(account: &Account) -> Self {
+        Self::new(account.id.clone(), account.email.clone())
+    }
+
+    /// Overrides the capabilities the provider reports.
+    pub fn with_capabilities(mut self, capabilities: ProviderCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Adds a message to the mailbox as if it had just arrived.
+    pub fn seed(&self, email: Email) {
+        let mut mailbox = self.lock();
+        mailbox.emails.push(email.clone());
+        mailbox.record(MailboxChange::Added(Box::new(email)));
+    }
+
+    /// Deletes a message from the mailbox.
+    pub fn delete(&self, email_id: &EmailId) {
+        let mut mailbox = self.lock();
+        let before = mailbox.emails.len();
+        mailbox.emails.retain(|e| e.id != *email_id);
+        if mailbox.emails.len() != before {
+            mailbox.record(MailboxChange::Deleted(email_id.clone()));
+        }
+    }
+
+    /// Returns a message as currently stored in the mailbox.
+    pub fn email(&self, email_id: &EmailId) -> Option<Email> {
+        self.lock()
+            .emails
+            .iter()
+            .find(|e| e.id == *email_id)
+            .cloned()
+    }
+
+    /// Returns the emails sent through the provider, oldest first.
+    pub fn sent(&self) -> Vec<OutgoingEmail> {
+        self.lock().sent.clone()
+    }
+
+    /// Returns the changes pushed to the provider, oldest first.
+    pub fn pushed(&self) -> Vec<PendingChange> {
+        self.lock().pushed.clone()
+    }
+
+    /// Returns whether the provider is signed in.
+    pub fn is_authenticated(&self) -> bool {
+        self.lock().authenticated
+    }
+
+    /// Returns the changes made after `since`, or all changes when `None`,
+    /// oldest first.
+    pub fn changes_since(&self, since: Option<DateTime<Utc>>) -> Vec<MailboxChange> {
+        self.lock()
+            .changes
+            .iter()
+            .filter(|(at, _)| since.map_or(true, |since| *at > since))
+            .map(|(_, change)| change.clone())
+            .collect()
+    }
+
+    /// Returns when the mailbox last changed, if it ever did.
+    pub fn last_change(&self) -> Option<DateTime<Utc>> {
+        self.lock().changes.last().map(|(at, _)| *at)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Mailbox> {
+        self.mailbox.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn summarize(&self, messages: &[Email]) -> Option<ThreadSummary> {
+        let first = messages.first()?;
+        let latest = messages.last()?;
+        Some(ThreadSummary {
+            id: first.thread_id.clone(),
+            account_id: self.account_id.clone(),
+            subject: first.subject.clone(),
+            snippet: Thread::preview_snippet(messages),
+            from: latest.from.clone(),
+            last_message_date: latest.date,
+            message_count: messages.len() as u32,
+            unread_count: messages.iter().filter(|e| !e.is_read).count() as u32,
+            is_starred: messages.iter().any(|e| e.is_starred),
+            labels: thread_labels(messages),
+            muted: false,
+            watched: false,
+        })
+    }
+}
+
+/// Returns the labels carried by any message of a thread.
+fn thread_labels(messages: &[Email]) -> Vec<LabelId> {
+    let mut labels: Vec<LabelId> = Vec::new();
+    for label in messages.iter().flat_map(|e| &e.labels) {
+        if !labels.contains(label) {
+            labels.push(label.clone());
+        }
+    }
+    labels
+}
+
+#[async_trait]
+impl EmailProvider for FakeEmailProvider {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Gmail
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.capabilities
+    }
+
+    async fn authenticate(&mut self) -> Result<()> {
+        self.lock().authenticated = true;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.lock().authenticated = false;
+        Ok(())
+    }
+
+    async fn fetch_threads(
+        &self,
+        folder: &str,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        let offset = match pagination.page_token {
+            Some(token) => token
+                .parse()
+                .map_err(|_| ProviderError::InvalidRequest(format!("bad page token: {}", token)))?,
+            None => 0,
+        };
+
+        let mailbox = self.lock();
+        let mut by_thread: HashMap<&ThreadId, Vec<Email>> = HashMap::new();
+        for email in &mailbox.emails {
+            by_thread
+                .entry(&email.thread_id)
+                .or_default()
+                .push(email.clone());
+        }
+        let mut threads: Vec<ThreadSummary> = by_thread
+            .into_values()
+            .filter(|messages| {
+                messages
+                    .iter()
+                    .any(|e| e.labels.iter().any(|l| l.0 == folder))
+            })
+            .filter_map(|mut messages| {
+                messages.sort_by_key(|e| e.date);
+                self.summarize(&messages)
+            })
+            .collect();
+        threads.sort_by(|a, b| b.last_message_date.cmp(&a.last_message_date));
+
+        let limit = pagination.limit.map_or(usize::MAX, |l| l as usize);
+        Ok(threads.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn fetch_thread(&self, thread_id: &str) -> Result<Thread> {
+        let messages = self.lock().thread_messages(thread_id);
+        let summary = self
+            .summarize(&messages)
+            .ok_or_else(|| ProviderError::NotFound(format!("thread {}", thread_id)))?;
+
+        Ok(Thread {
+            id: summary.id,
+            account_id: summary.account_id,
+            subject: summary.subject,
+            snippet: summary.snippet,
+            participants: Thread::ordered_participants(&messages, Some(&self.address)),
+            last_message_date: summary.last_message_date,
+            unread_count: summary.unread_count,
+            is_starred: summary.is_starred,
+            labels: summary.labels,
+            messages,
+        })
+    }
+
+    async fn fetch_raw(&self, email_id: &str) -> Result<Vec<u8>> {
+        let email = self
+            .email(&EmailId::from(email_id))
+            .ok_or_else(|| ProviderError::NotFound(format!("email {}", email_id)))?;
+
+        let to: Vec<&str> = email.to.iter().map(|a| a.email.as_str()).collect();
+        Ok(format!(
+            "Message-ID: {}\r\nDate: {}\r\nFrom: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}",
+            email.message_id.0,
+            email.date.to_rfc2822(),
+            email.from.email,
+            to.join(", "),
+            email.subject.as_deref().unwrap_or_default(),
+            email.body_text.as_deref().unwrap_or_default(),
+        )
+        .into_bytes())
+    }
+
+    async fn fetch_changes_since(&self, since: &DateTime<Utc>) -> Result<Vec<Change>> {
+        let changes = self.changes_since(Some(*since));
+        let mailbox = self.lock();
+        Ok(changes
+            .into_iter()
+            .map(|change| match change {
+                MailboxChange::Added(email) => Change::NewEmail(NewEmailData {
+                    id: email.id,
+                    thread_id: email.thread_id,
+                    from: email.from,
+                    to: email.to,
+                    cc: email.cc,
+                    subject: email.subject,
+                    snippet: email.snippet,
+                    date: email.date,
+                    labels: email.labels,
+                    is_read: email.is_read,
+                    is_starred: email.is_starred,
+                    raw: None,
+                }),
+                MailboxChange::Updated {
+                    email_id,
+                    is_read,
+                    is_starred,
+                    added,
+                    removed,
+                } => {
+                    // Provider updates carry the full label set.
+                    let labels = (!added.is_empty() || !removed.is_empty()).then(|| {
+                        mailbox
+                            .emails
+                            .iter()
+                            .find(|e| e.id == email_id)
+                            .map(|e| e.labels.clone())
+                            .unwrap_or_default()
+                    });
+                    Change::Updated(EmailUpdate {
+                        id: email_id,
+                        labels,
+                        is_read,
+                        is_starred,
+                    })
+                }
+                MailboxChange::Deleted(email_id) => Change::Deleted(email_id),
+            })
+            .collect())
+    }
+
+    async fn send_email(&self, email: &OutgoingEmail) -> Result<String> {
+        let mut mailbox = self.lock();
+        let n = mailbox.sent.len() + 1;
+        let id = format!("sent-{}", n);
+        let message_id = email
+            .message_id()
+            .unwrap_or_else(|| format!("<{}@fake.local>", id));
+
+        let sent = Email {
+            id: EmailId::from(id.clone()),
+            account_id: self.account_id.clone(),
+            thread_id: email
+                .in_reply_to_thread
+                .clone()
+                .unwrap_or_else(|| ThreadId::from(format!("thread-{}", id))),
+            message_id: MessageId::from(message_id),
+            in_reply_to: email.in_reply_to_message.clone().map(MessageId::from),
+            references: email
+                .references
+                .iter()
+                .cloned()
+                .map(MessageId::from)
+                .collect(),
+            from: email
+                .from
+                .clone()
+                .unwrap_or_else(|| Address::new(self.address.clone())),
+            to: email.to.clone(),
+            cc: email.cc.clone(),
+            bcc: email.bcc.clone(),
+            subject: Some(email.subject.clone()),
+            body_text: Some(email.body_text.clone()),
+            body_html: email.body_html.clone(),
+            snippet: email.body_text.chars().take(100).collect(),
+            date: Utc::now(),
+            is_read: true,
+            is_starred: false,
+            is_draft: false,
+            labels: vec![LabelId::from("SENT")],
+            attachments: vec![],
+            unsubscribe: None,
+            read_receipt_to: None,
+        };
+        mailbox.sent.push(email.clone());
+        mailbox.emails.push(sent.clone());
+        mailbox.record(MailboxChange::Added(Box::new(sent)));
+        Ok(id)
+    }
+
+    async fn archive(&self, thread_ids: &[String]) -> Result<()> {
+        self.lock().modify(thread_ids, &[], &["INBOX"]);
+        Ok(())
+    }
+
+    async fn trash(&self, thread_ids: &[String]) -> Result<()> {
+        self.lock().modify(thread_ids, &["TRASH"], &["INBOX"]);
+        Ok(())
+    }
+
+    async fn delete_permanently(&self, thread_ids: &[String]) -> Result<()> {
+        let mut mailbox = self.lock();
+        let deleted: Vec<EmailId> = mailbox
+            .emails
+            .iter()
+            .filter(|e| thread_ids.contains(&e.thread_id.0))
+            .map(|e| e.id.clone())
+            .collect();
+        mailbox
+            .emails
+            .retain(|e| !thread_ids.contains(&e.thread_id.0));
+        for email_id in deleted {
+            mailbox.record(MailboxChange::Deleted(email_id));
+        }
+        Ok(())
+    }
+
+    async fn report_spam(&self, thread_ids: &[String]) -> Result<()> {
+        self.lock().modify(thread_ids, &["SPAM"], &["INBOX"]);
+        Ok(())
+    }
+
+    async fn star(&self, thread_id: &str, starred: bool) -> Result<()> {
+        self.lock().set_flag(
+            thread_id,
+            |e| &mut e.is_starred,
+            starred,
+            |email_id| MailboxChange::Updated {
+                email_id,
+                is_read: None,
+                is_starred: Some(starred),
+                added: vec![],
+                removed: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    async fn mark_read(&self, thread_id: &str, read: bool) -> Result<()> {
+        self.lock().set_flag(
+            thread_id,
+            |e| &mut e.is_read,
+            read,
+            |email_id| MailboxChange::Updated {
+                email_id,
+                is_read: Some(read),
+                is_starred: None,
+                added: vec![],
+                removed: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    async fn apply_label(&self, thread_id: &str, label: &str) -> Result<()> {
+        self.lock().modify(&[thread_id.to_string()], &[label], &[]);
+        Ok(())
+    }
+
+    async fn fetch_labels(&self) -> Result<Vec<Label>> {
+        let mailbox = self.lock();
+        let mut names: Vec<String> = SYSTEM_LABELS.iter().map(|l| l.to_string()).collect();
+        for label in mailbox.emails.iter().flat_map(|e| &e.labels) {
+            if !names.contains(&label.0) {
+                names.push(label.0.clone());
+            }
+        }
+
+        Ok(names
+            .into_iter()
+            .map(|name| Label {
+                id: LabelId::from(name.clone()),
+                account_id: self.account_id.clone(),
+                is_system: SYSTEM_LABELS.contains(&name.as_str()),
+                provider_id: Some(name.clone()),
+                name,
+                color: None,
+            })
+            .collect())
+    }
+
+    async fn push_change(&self, change: &PendingChange) -> Result<()> {
+        let ids = |thread_ids: &[ThreadId]| -> Vec<String> {
+            thread_ids.iter().map(|id| id.0.clone()).collect()
+        };
+        match &change.change_type {
+            PendingChangeType::MarkRead { thread_ids, read } => {
+                self.mark_read_many(&ids(thread_ids), *read).await?
+            }
+            PendingChangeType::Star { thread_id, starred } => {
+                self.star(&thread_id.0, *starred).await?
+            }
+            PendingChangeType::Archive { thread_ids } => self.archive(&ids(thread_ids)).await?,
+            PendingChangeType::Trash { thread_ids } => self.trash(&ids(thread_ids)).await?,
+            PendingChangeType::ReportSpam { thread_ids } => {
+                self.report_spam(&ids(thread_ids)).await?
+            }
+            PendingChangeType::ApplyLabel {
+                thread_ids,
+                label_id,
+            } => self.apply_label_many(&ids(thread_ids), &label_id.0).await?,
+            PendingChangeType::RemoveLabel {
+                thread_ids,
+                label_id,
+            } => {
+                let removed = [label_id.0.as_str()];
+                self.lock().modify(&ids(thread_ids), &[], &removed);
+            }
+            PendingChangeType::Send { email } => {
+                self.send_email(email).await?;
+            }
+        }
+        self.lock().pushed.push(change.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::test_email;
+
+    fn email(id: &str, thread: &str, minutes_ago: i64) -> Email {
+        Email {
+            from: Address::new("alice@example.com"),
+            subject: Some(format!("Subject {}", thread)),
+            date: Utc::now() - Duration::minutes(minutes_ago),
+            labels: vec![LabelId::from("INBOX")],
+            ..test_email(id, thread)
+        }
+    }
+
+    fn provider() -> FakeEmailProvider {
+        let provider = FakeEmailProvider::new("account-1", "me@example.com");
+        provider.seed(email("email-1", "thread-1", 30));
+        provider.seed(email("email-2", "thread-1", 20));
+        provider.seed(email("email-3", "thread-2", 10));
+        provider
+    }
+
+    #[tokio::test]
+    async fn seeded_threads_are_listed_newest_first() {
+        let provider = provider();
+
+        let threads = provider
+            .fetch_threads("INBOX", Pagination::default())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = threads.iter().map(|t| t.id.0.as_str()).collect();
+        assert_eq!(ids, vec!["thread-2", "thread-1"]);
+        assert_eq!(threads[1].message_count, 2);
+        assert_eq!(threads[1].unread_count, 2);
+
+        let next = provider
+            .fetch_threads(
+                "INBOX",
+                Pagination {
+                    limit: Some(1),
+                    page_token: Some("1".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(next[0].id, ThreadId::from("thread-1"));
+
+        let thread = provider.fetch_thread("thread-1").await.unwrap();
+        assert_eq!(thread.messages.len(), 2);
+        assert!(matches!(
+            provider.fetch_thread("missing").await,
+            Err(ProviderError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn label_operations_are_recorded_as_changes() {
+        let provider = provider();
+        let since = provider.last_change().unwrap();
+
+        provider.archive(&["thread-1".to_string()]).await.unwrap();
+        provider.mark_read("thread-2", true).await.unwrap();
+        // Already read, so nothing changes.
+        provider.mark_read("thread-2", true).await.unwrap();
+
+        assert!(provider
+            .fetch_threads("INBOX", Pagination::default())
+            .await
+            .unwrap()
+            .iter()
+            .all(|t| t.id == ThreadId::from("thread-2")));
+
+        let changes = provider.fetch_changes_since(&since).await.unwrap();
+        assert_eq!(changes.len(), 3);
+        match &changes[0] {
+            Change::Updated(update) => {
+                assert_eq!(update.id, EmailId::from("email-1"));
+                assert_eq!(update.labels, Some(vec![]));
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+        match &changes[2] {
+            Change::Updated(update) => {
+                assert_eq!(update.id, EmailId::from("email-3"));
+                assert_eq!(update.is_read, Some(true));
+                assert_eq!(update.labels, None);
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+    }
+
+    #[tokio::test]
+    async fn sent_mail_lands_in_sent() {
+        let provider = provider();
+        let outgoing = OutgoingEmail {
+            from: None,
+            to: vec![Address::new("alice@example.com")],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Re: Subject thread-1".to_string(),
+            body_text: "Sounds good".to_string(),
+            body_html: None,
+            in_reply_to_thread: Some(ThreadId::from("thread-1")),
+            in_reply_to_message: Some("<email-2@example.com>".to_string()),
+            references: vec![],
+            attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: false,
+            read_receipt_for: None,
+        };
+
+        provider
+            .push_change(&PendingChange {
+                id: "change-1".to_string(),
+                change_type: PendingChangeType::Send { email: outgoing },
+                created_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(provider.sent().len(), 1);
+
+        let thread = provider.fetch_thread("thread-1").await.unwrap();
+        let reply = thread.messages.last().unwrap();
+        assert_eq!(reply.labels, vec![LabelId::from("SENT")]);
+        assert_eq!(reply.from, Address::new("me@example.com"));
+        assert_eq!(provider.pushed().len(), 1);
+        assert_eq!(
+            provider
+                .fetch_threads("SENT", Pagination::default())
+                .await
+                .unwrap()[0]
+                .id,
+            ThreadId::from("thread-1")
+        );
+    }
+}
@@ -7,9 +7,14 @@
 //! # Authentication
 //!
 //! Gmail uses OAuth 2.0 for authentication. Access tokens and refresh tokens are
-//! stored in the system keychain, referenced by account ID. The provider handles
+//! stored in the system keychain under `gmail-{account_id}`. The provider handles
 //! token refresh automatically when tokens expire.
 //!
+//! New accounts obtain a refresh token through the installed-app loopback
+//! flow: [`GmailProvider::begin_oauth`] returns the consent URL,
+//! [`GmailProvider::wait_for_oauth_code`] receives the browser redirect, and
+//! [`GmailProvider::complete_oauth`] exchanges the code and stores the tokens.
+//!
 //! # API Usage
 //!
 //! This provider uses the Gmail API v1:
@@ -18,24 +23,63 @@
 //! - `users.history.list` for incremental sync
 //! - `users.messages.send` for sending emails
 //! - `users.labels.list` for fetching labels
+//! - `users.watch` / `users.stop` for Cloud Pub/Sub push notifications
+//!
+//! # Push Notifications
+//!
+//! Instead of polling history, Gmail can publish a notification to a Cloud
+//! Pub/Sub topic whenever the mailbox changes. [`GmailProvider::watch`]
+//! registers the topic; the webhook that receives Pub/Sub deliveries decodes
+//! the message data and hands it to
+//! [`GmailProvider::process_push_notification`], which fetches the history
+//! delta. Watches expire after seven days and must be renewed with
+//! [`GmailProvider::renew_watch_if_needed`].
 
 use async_trait::async_trait;
 use base64::prelude::*;
 use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
 
+use super::headers::{decode_header, parse_email_date};
+use super::oauth::{AuthUrl, PendingOAuth};
 use super::{
     Change, EmailProvider, EmailUpdate, NewEmailData, OutgoingEmail, Pagination, PendingChange,
-    PendingChangeType, ProviderError, Result,
+    PendingChangeType, ProviderCapabilities, ProviderError, Result,
 };
 use crate::domain::{
-    AccountId, Address, Email, EmailId, Label, LabelId, MessageId, ProviderType, Thread, ThreadId,
-    ThreadSummary,
+    credentials_key, snippet_from_body, AccountId, Address, CredentialStore, Email, EmailId,
+    FolderMapping, Label, LabelId, MessageId, ProviderType, SpecialFolder, Thread, ThreadId,
+    ThreadSummary, UnsubscribeInfo,
 };
+use crate::providers::http;
+use crate::storage::KeychainAccess;
 
 const GMAIL_API_BASE: &str = "https://gmail.googleapis.com/gmail/v1/users/me";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GMAIL_OAUTH_SCOPES: &[&str] = &["https://mail.google.com/"];
+
+/// Gmail batch endpoint, which bundles several API calls into one request.
+const GMAIL_BATCH_URL: &str = "https://gmail.googleapis.com/batch/gmail/v1";
+
+/// Path prefix of the API calls inside a batch request.
+const GMAIL_BATCH_PATH: &str = "/gmail/v1/users/me";
+
+/// Boundary separating the parts of a batch request.
+const GMAIL_BATCH_BOUNDARY: &str = "heap_batch";
+
+/// Maximum number of calls Gmail accepts in one batch request.
+const GMAIL_BATCH_LIMIT: usize = 100;
+
+/// Maximum length of generated snippets, in characters.
+const SNIPPET_LENGTH: usize = 200;
+
+/// Default number of concurrent message fetches during incremental sync.
+const DEFAULT_FETCH_CONCURRENCY: usize = 8;
 
 /// Gmail API thread list response.
 #[derive(Debug, Deserialize)]
@@ -73,6 +117,13 @@ struct GmailMessage {
     size_estimate: Option<u32>,
 }
 
+/// Gmail API message fetched with `format=raw`.
+#[derive(Debug, Deserialize)]
+struct GmailRawMessage {
+    /// The RFC 822 message, base64url encoded.
+    raw: String,
+}
+
 /// Gmail message payload (headers and body parts).
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -140,7 +191,6 @@ struct HistoryListResponse {
     history: Option<Vec<GmailHistory>>,
     #[allow(dead_code)]
     next_page_token: Option<String>,
-    #[allow(dead_code)]
     history_id: Option<String>,
 }
 
@@ -177,6 +227,109 @@ struct GmailHistoryLabelChange {
     label_ids: Vec<String>,
 }
 
+/// Gmail user profile.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GmailProfile {
+    email_address: String,
+}
+
+/// Gmail send-as settings list response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendAsListResponse {
+    send_as: Option<Vec<GmailSendAs>>,
+}
+
+/// An address the Gmail account can send as.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GmailSendAs {
+    send_as_email: String,
+    display_name: Option<String>,
+    #[serde(default)]
+    is_primary: bool,
+    /// `accepted` once the alias's owner has verified it; the primary
+    /// address has none.
+    verification_status: Option<String>,
+}
+
+impl SendAsListResponse {
+    /// Returns the verified aliases, without the account's own address.
+    fn verified_aliases(self) -> Vec<Address> {
+        self.send_as
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|send_as| {
+                !send_as.is_primary && send_as.verification_status.as_deref() == Some("accepted")
+            })
+            .map(|send_as| Address {
+                email: send_as.send_as_email,
+                name: send_as.display_name.filter(|name| !name.is_empty()),
+            })
+            .collect()
+    }
+}
+
+/// Gmail watch request body.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchRequest<'a> {
+    topic_name: &'a str,
+}
+
+/// Gmail watch response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchResponse {
+    history_id: String,
+    /// Expiration as epoch milliseconds.
+    expiration: String,
+}
+
+/// Gmail history ID, an opaque increasing mailbox version.
+pub type HistoryId = String;
+
+/// How long before expiry a watch is renewed.
+const WATCH_RENEWAL_MARGIN_HOURS: i64 = 24;
+
+/// An active Pub/Sub watch on the mailbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchState {
+    /// Fully qualified Pub/Sub topic, e.g. `projects/p/topics/t`.
+    pub topic: String,
+    /// When Gmail stops publishing unless the watch is renewed.
+    pub expiration: DateTime<Utc>,
+}
+
+impl WatchState {
+    /// Returns whether the watch expires within the renewal margin.
+    pub fn needs_renewal(&self, now: DateTime<Utc>) -> bool {
+        self.expiration - chrono::Duration::hours(WATCH_RENEWAL_MARGIN_HOURS) <= now
+    }
+}
+
+/// Payload of a Gmail Pub/Sub push notification.
+///
+/// This is the JSON carried in the Pub/Sub message's `data` field, after
+/// base64 decoding.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushNotification {
+    /// Mailbox the notification is for.
+    pub email_address: String,
+    /// Mailbox history ID at the time of the change.
+    pub history_id: u64,
+}
+
+impl PushNotification {
+    /// Parses the decoded `data` of a Pub/Sub message.
+    pub fn from_pubsub_data(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data)
+            .map_err(|e| ProviderError::InvalidRequest(format!("invalid push payload: {}", e)))
+    }
+}
+
 /// Gmail modify request body.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -191,6 +344,9 @@ struct ModifyRequest {
 #[derive(Debug, Deserialize)]
 struct TokenResponse {
     access_token: String,
+    /// Only present on authorization code exchange.
+    #[serde(default)]
+    refresh_token: Option<String>,
     #[allow(dead_code)]
     expires_in: u64,
     #[allow(dead_code)]
@@ -198,13 +354,18 @@ struct TokenResponse {
 }
 
 /// OAuth credentials stored in keychain.
+///
+/// The client fields default to empty so tokens stored by
+/// `AccountService::store_oauth_tokens` deserialize before a client is set.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GmailCredentials {
     /// OAuth refresh token.
     pub refresh_token: String,
     /// OAuth client ID.
+    #[serde(default)]
     pub client_id: String,
     /// OAuth client secret.
+    #[serde(default)]
     pub client_secret: String,
 }
 
@@ -223,8 +384,10 @@ pub struct GmailCredentials {
 /// let threads = provider.fetch_threads("INBOX", Pagination::with_limit(50)).await?;
 /// ```
 pub struct GmailProvider {
-    /// Account ID for keychain credential lookup.
+    /// Account ID for credential lookup.
     account_id: AccountId,
+    /// Store holding the account's credentials.
+    credential_store: Arc<dyn CredentialStore>,
     /// HTTP client for API requests.
     client: reqwest::Client,
     /// OAuth credentials.
@@ -233,8 +396,22 @@ pub struct GmailProvider {
     access_token: Option<String>,
     /// Whether the provider is authenticated.
     authenticated: bool,
+    /// The account's own address, from the Gmail profile.
+    email_address: Option<String>,
+    /// Verified send-as aliases, from the Gmail settings.
+    aliases: Option<Vec<Address>>,
     /// Last known history ID for incremental sync.
     last_history_id: Option<String>,
+    /// OAuth flow in progress, if any.
+    pending_oauth: Option<PendingOAuth>,
+    /// Active Pub/Sub watch, if registered.
+    watch: Option<WatchState>,
+    /// Maximum concurrent message fetches during incremental sync.
+    fetch_concurrency: usize,
+    /// Batch endpoint URL.
+    batch_url: String,
+    /// Labels to use for special folders instead of the system ones.
+    folder_mapping: FolderMapping,
 }
 
 impl GmailProvider {
@@ -248,11 +425,19 @@ impl GmailProvider {
     pub fn new(account_id: AccountId) -> Self {
         Self {
             account_id,
-            client: reqwest::Client::new(),
+            credential_store: Arc::new(KeychainAccess::new()),
+            client: http::client(http::DEFAULT_REQUEST_TIMEOUT),
             credentials: None,
             access_token: None,
             authenticated: false,
+            email_address: None,
+            aliases: None,
             last_history_id: None,
+            pending_oauth: None,
+            watch: None,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            batch_url: GMAIL_BATCH_URL.to_string(),
+            folder_mapping: FolderMapping::default(),
         }
     }
 
@@ -260,11 +445,19 @@ impl GmailProvider {
     pub fn with_credentials(account_id: AccountId, credentials: GmailCredentials) -> Self {
         Self {
             account_id,
-            client: reqwest::Client::new(),
+            credential_store: Arc::new(KeychainAccess::new()),
+            client: http::client(http::DEFAULT_REQUEST_TIMEOUT),
             credentials: Some(credentials),
             access_token: None,
             authenticated: false,
+            email_address: None,
+            aliases: None,
             last_history_id: None,
+            pending_oauth: None,
+            watch: None,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            batch_url: GMAIL_BATCH_URL.to_string(),
+            folder_mapping: FolderMapping::default(),
         }
     }
 
@@ -278,31 +471,157 @@ impl GmailProvider {
         &self.account_id
     }
 
-    /// Loads credentials from the system keychain.
-    fn load_credentials_from_keychain(&self) -> Result<GmailCredentials> {
-        let entry = keyring::Entry::new("heap", &format!("gmail-{}", self.account_id.0))
-            .map_err(|e| ProviderError::Authentication(format!("keyring error: {}", e)))?;
+    /// Replaces the credential store used to load and save credentials.
+    ///
+    /// Defaults to the system keychain.
+    pub fn with_credential_store(mut self, store: Arc<dyn CredentialStore>) -> Self {
+        self.credential_store = store;
+        self
+    }
+
+    /// Sets labels to use for special folders, typically the mapping stored
+    /// on the account. Folders it doesn't map use Gmail's system labels.
+    pub fn with_folder_mapping(mut self, folder_mapping: FolderMapping) -> Self {
+        self.folder_mapping = folder_mapping;
+        self
+    }
+
+    /// Sets the bound on each API request, replacing the default of 60 seconds.
+    ///
+    /// A request that exceeds it fails with [`ProviderError::Connection`].
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http::client(timeout);
+        self
+    }
+
+    /// Sets how many messages are fetched concurrently during incremental sync.
+    ///
+    /// Defaults to 8, which keeps a large history delta fast while staying
+    /// well within Gmail's per-user request quota.
+    pub fn with_fetch_concurrency(mut self, concurrency: usize) -> Self {
+        self.fetch_concurrency = concurrency.max(1);
+        self
+    }
 
-        let creds_json = entry
-            .get_password()
-            .map_err(|e| ProviderError::Authentication(format!("no credentials found: {}", e)))?;
+    /// Loads credentials from the credential store.
+    pub(crate) async fn load_credentials(&self) -> Result<GmailCredentials> {
+        let key = credentials_key(ProviderType::Gmail, &self.account_id);
+        let creds_json = self
+            .credential_store
+            .retrieve(&key)
+            .await
+            .map_err(|e| ProviderError::Authentication(format!("credential store error: {}", e)))?
+            .ok_or_else(|| {
+                ProviderError::Authentication(format!("no credentials found: {}", key))
+            })?;
 
         serde_json::from_str(&creds_json)
             .map_err(|e| ProviderError::Authentication(format!("invalid credentials: {}", e)))
     }
 
-    /// Saves credentials to the system keychain.
-    pub fn save_credentials_to_keychain(&self, credentials: &GmailCredentials) -> Result<()> {
-        let entry = keyring::Entry::new("heap", &format!("gmail-{}", self.account_id.0))
-            .map_err(|e| ProviderError::Authentication(format!("keyring error: {}", e)))?;
-
+    /// Saves credentials to the credential store.
+    pub async fn save_credentials(&self, credentials: &GmailCredentials) -> Result<()> {
+        let key = credentials_key(ProviderType::Gmail, &self.account_id);
         let creds_json = serde_json::to_string(credentials)
             .map_err(|e| ProviderError::Authentication(format!("serialize error: {}", e)))?;
 
-        entry
-            .set_password(&creds_json)
-            .map_err(|e| ProviderError::Authentication(format!("keyring error: {}", e)))?;
+        self.credential_store
+            .store(&key, &creds_json)
+            .await
+            .map_err(|e| ProviderError::Authentication(format!("credential store error: {}", e)))
+    }
+
+    /// Starts the OAuth loopback flow for this account.
+    ///
+    /// Binds a redirect listener on `127.0.0.1` and returns the consent URL,
+    /// which the caller should open in the user's browser (see
+    /// [`open_in_browser`](super::open_in_browser)). Starting a new flow
+    /// discards any flow already in progress.
+    pub async fn begin_oauth(
+        &mut self,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Result<AuthUrl> {
+        let (pending, auth_url) = PendingOAuth::start(
+            GOOGLE_AUTH_URL,
+            client_id.into(),
+            client_secret.into(),
+            GMAIL_OAUTH_SCOPES,
+        )
+        .await?;
+        self.pending_oauth = Some(pending);
+        Ok(auth_url)
+    }
+
+    /// Waits for the browser to hit the loopback redirect.
+    ///
+    /// Returns the authorization code to pass to
+    /// [`complete_oauth`](Self::complete_oauth), or an authentication error
+    /// if the user denied consent.
+    pub async fn wait_for_oauth_code(&mut self) -> Result<String> {
+        let pending = self
+            .pending_oauth
+            .as_mut()
+            .ok_or_else(|| ProviderError::InvalidRequest("no oauth flow in progress".into()))?;
+        pending.wait_for_code().await
+    }
+
+    /// Exchanges an authorization code for tokens and stores the credentials.
+    ///
+    /// On success the provider is authenticated and ready for API calls.
+    pub async fn complete_oauth(&mut self, code: &str) -> Result<()> {
+        let pending = self
+            .pending_oauth
+            .take()
+            .ok_or_else(|| ProviderError::InvalidRequest("no oauth flow in progress".into()))?;
+
+        let params = [
+            ("client_id", pending.client_id.as_str()),
+            ("client_secret", pending.client_secret.as_str()),
+            ("code", code),
+            ("code_verifier", pending.pkce_verifier.secret().as_str()),
+            ("redirect_uri", pending.redirect_uri.as_str()),
+            ("grant_type", "authorization_code"),
+        ];
+
+        let response = self
+            .client
+            .post(GOOGLE_TOKEN_URL)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Connection(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::Authentication(format!(
+                "code exchange failed ({}): {}",
+                status, body
+            )));
+        }
+
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Internal(format!("parse token response: {}", e)))?;
+
+        let refresh_token = token_response.refresh_token.ok_or_else(|| {
+            ProviderError::Authentication("token response missing refresh token".to_string())
+        })?;
+
+        let credentials = GmailCredentials {
+            refresh_token,
+            client_id: pending.client_id,
+            client_secret: pending.client_secret,
+        };
+        self.save_credentials(&credentials).await?;
+
+        self.credentials = Some(credentials);
+        self.access_token = Some(token_response.access_token);
+        self.authenticated = true;
 
+        tracing::info!(account_id = %self.account_id, "Gmail OAuth flow completed");
         Ok(())
     }
 
@@ -346,6 +665,241 @@ impl GmailProvider {
         Ok(token_response.access_token)
     }
 
+    /// Returns the last history ID this provider synced to.
+    pub fn last_history_id(&self) -> Option<&str> {
+        self.last_history_id.as_deref()
+    }
+
+    /// Sets the history ID to sync from, e.g. after restoring persisted state.
+    pub fn set_last_history_id(&mut self, history_id: impl Into<HistoryId>) {
+        self.last_history_id = Some(history_id.into());
+    }
+
+    /// Returns the active Pub/Sub watch, if any.
+    pub fn watch_state(&self) -> Option<&WatchState> {
+        self.watch.as_ref()
+    }
+
+    /// Registers a Pub/Sub watch so Gmail publishes mailbox changes to `topic`.
+    ///
+    /// The topic must grant publish rights to
+    /// `gmail-api-push@system.gserviceaccount.com`. Returns the mailbox's
+    /// current history ID, which also becomes the sync starting point if none
+    /// is known yet.
+    pub async fn watch(&mut self, topic: &str) -> Result<HistoryId> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        let request = WatchRequest { topic_name: topic };
+        let response: WatchResponse = self.post("/watch", &request).await?;
+
+        let expiration = response
+            .expiration
+            .parse::<i64>()
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| {
+                ProviderError::Internal(format!(
+                    "invalid watch expiration: {}",
+                    response.expiration
+                ))
+            })?;
+
+        self.watch = Some(WatchState {
+            topic: topic.to_string(),
+            expiration,
+        });
+        if self.last_history_id.is_none() {
+            self.last_history_id = Some(response.history_id.clone());
+        }
+
+        tracing::info!(account_id = %self.account_id, %expiration, "Gmail watch registered");
+        Ok(response.history_id)
+    }
+
+    /// Re-registers the watch if it expires within a day.
+    ///
+    /// Returns whether a renewal was performed. Call this periodically, e.g.
+    /// from the background sync loop.
+    pub async fn renew_watch_if_needed(&mut self) -> Result<bool> {
+        let topic = match &self.watch {
+            Some(watch) if watch.needs_renewal(Utc::now()) => watch.topic.clone(),
+            _ => return Ok(false),
+        };
+        self.watch(&topic).await?;
+        Ok(true)
+    }
+
+    /// Stops push notifications for the mailbox.
+    pub async fn stop_watch(&mut self) -> Result<()> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        self.post_no_response("/stop", &serde_json::json!({}))
+            .await?;
+        self.watch = None;
+        Ok(())
+    }
+
+    /// Processes a push notification by fetching the history delta since the
+    /// last known history ID.
+    ///
+    /// Notifications at or below the last known history ID (Pub/Sub may
+    /// redeliver or reorder) yield no changes. If no history ID
+    /// is known yet, the notification's ID becomes the starting point and a
+    /// full sync is expected to fill the gap.
+    pub async fn process_push_notification(
+        &mut self,
+        notification: &PushNotification,
+    ) -> Result<Vec<Change>> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        let Some(start) = self.last_history_id.clone() else {
+            self.last_history_id = Some(notification.history_id.to_string());
+            return Ok(vec![]);
+        };
+
+        if !is_newer_history(&start, notification.history_id) {
+            return Ok(vec![]);
+        }
+
+        let (changes, latest) = self.history_changes(&start).await?;
+        self.last_history_id = Some(latest.unwrap_or_else(|| notification.history_id.to_string()));
+
+        Ok(changes)
+    }
+
+    /// Fetches the history delta since `start_history_id` and converts it to changes.
+    ///
+    /// Returns the changes along with the mailbox's current history ID.
+    async fn history_changes(
+        &self,
+        start_history_id: &str,
+    ) -> Result<(Vec<Change>, Option<String>)> {
+        let endpoint = format!(
+            "/history?startHistoryId={}&historyTypes=messageAdded,messageDeleted,labelAdded,labelRemoved",
+            start_history_id
+        );
+
+        let response: HistoryListResponse = self.get(&endpoint).await?;
+        let latest_history_id = response.history_id.clone();
+
+        // Changes in history order; new messages are placeholders until fetched.
+        let mut slots: Vec<Option<Change>> = Vec::new();
+        let mut added_ids = Vec::new();
+        let mut added_slots = Vec::new();
+
+        if let Some(history) = response.history {
+            for record in history {
+                // Handle new messages
+                if let Some(added) = record.messages_added {
+                    for item in added {
+                        added_slots.push(slots.len());
+                        added_ids.push(item.message.id);
+                        slots.push(None);
+                    }
+                }
+
+                // Handle deleted messages
+                if let Some(deleted) = record.messages_deleted {
+                    for item in deleted {
+                        slots.push(Some(Change::Deleted(EmailId::from(item.message.id))));
+                    }
+                }
+
+                // Handle label changes (read/unread, starred, etc.)
+                if let Some(label_added) = record.labels_added {
+                    for item in label_added {
+                        let mut is_starred = None;
+                        let mut is_read = None;
+                        let mut labels = Vec::new();
+                        for label in &item.label_ids {
+                            match label.as_str() {
+                                "STARRED" => is_starred = Some(true),
+                                "UNREAD" => is_read = Some(false),
+                                _ => labels.push(LabelId::from(label.clone())),
+                            }
+                        }
+                        let update = EmailUpdate {
+                            id: EmailId::from(item.message.id),
+                            labels: if labels.is_empty() {
+                                None
+                            } else {
+                                Some(labels)
+                            },
+                            is_read,
+                            is_starred,
+                        };
+                        slots.push(Some(Change::Updated(update)));
+                    }
+                }
+
+                if let Some(label_removed) = record.labels_removed {
+                    for item in label_removed {
+                        let mut is_starred = None;
+                        let mut is_read = None;
+                        for label in &item.label_ids {
+                            match label.as_str() {
+                                "STARRED" => is_starred = Some(false),
+                                "UNREAD" => is_read = Some(true),
+                                _ => {}
+                            }
+                        }
+                        let update = EmailUpdate {
+                            id: EmailId::from(item.message.id),
+                            labels: None, // Can't express "remove these labels" with this schema
+                            is_read,
+                            is_starred,
+                        };
+                        slots.push(Some(Change::Updated(update)));
+                    }
+                }
+            }
+        }
+
+        // Fetch the full messages in parallel, keeping history order.
+        let fetched = fetch_bounded(&added_ids, self.fetch_concurrency, |id| {
+            let endpoint = format!("/messages/{}?format=full", id);
+            async move { self.get::<GmailMessage>(&endpoint).await }
+        })
+        .await;
+        for (slot, msg) in added_slots.into_iter().zip(fetched) {
+            slots[slot] = msg.map(|msg| Change::NewEmail(self.gmail_message_to_new_email(&msg)));
+        }
+
+        let changes = slots.into_iter().flatten().collect();
+        Ok((changes, latest_history_id))
+    }
+
+    /// Converts a Gmail message to sync change data.
+    fn gmail_message_to_new_email(&self, msg: &GmailMessage) -> NewEmailData {
+        let email = self.gmail_message_to_email(msg);
+        NewEmailData {
+            id: email.id,
+            thread_id: email.thread_id,
+            from: email.from,
+            to: email.to,
+            cc: email.cc,
+            subject: email.subject,
+            snippet: email.snippet,
+            date: email.date,
+            labels: email.labels,
+            is_read: email.is_read,
+            is_starred: email.is_starred,
+            raw: None,
+        }
+    }
+
     /// Builds authorization headers for API requests.
     fn auth_headers(&self) -> Result<HeaderMap> {
         let token = self
@@ -421,22 +975,118 @@ impl GmailProvider {
         Ok(())
     }
 
-    /// Handles API response, checking for errors.
-    async fn handle_response<T: for<'de> Deserialize<'de>>(
-        &self,
-        response: reqwest::Response,
-    ) -> Result<T> {
+    /// Makes an authenticated DELETE request to the Gmail API.
+    async fn delete(&self, endpoint: &str) -> Result<()> {
+        let url = format!("{}{}", GMAIL_API_BASE, endpoint);
+        let headers = self.auth_headers()?;
+
+        let response = self
+            .client
+            .delete(&url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Connection(e.to_string()))?;
+
         if !response.status().is_success() {
             return Err(self.handle_error(response).await);
         }
-
-        response
-            .json()
-            .await
-            .map_err(|e| ProviderError::Internal(format!("parse response: {}", e)))
+        Ok(())
     }
 
-    /// Handles API error responses.
+    /// Sends POST calls through the batch endpoint, up to
+    /// [`GMAIL_BATCH_LIMIT`] per request.
+    ///
+    /// Each call is an endpoint relative to the user and an optional JSON
+    /// body. Fails if the batch request or any call within it fails; the
+    /// error names each failed call so per-thread failures stay visible.
+    async fn post_batch(&self, calls: &[(String, Option<String>)]) -> Result<()> {
+        for chunk in calls.chunks(GMAIL_BATCH_LIMIT) {
+            let mut headers = self.auth_headers()?;
+            let content_type = format!("multipart/mixed; boundary={}", GMAIL_BATCH_BOUNDARY);
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_str(&content_type)
+                    .map_err(|e| ProviderError::Internal(e.to_string()))?,
+            );
+
+            let response = self
+                .client
+                .post(&self.batch_url)
+                .headers(headers)
+                .body(batch_request_body(chunk))
+                .send()
+                .await
+                .map_err(|e| ProviderError::Connection(e.to_string()))?;
+
+            if !response.status().is_success() {
+                return Err(self.handle_error(response).await);
+            }
+
+            let body = response
+                .text()
+                .await
+                .map_err(|e| ProviderError::Connection(e.to_string()))?;
+            let failed: Vec<String> = failed_batch_items(&body)
+                .into_iter()
+                .map(|(index, status)| {
+                    let endpoint = index
+                        .and_then(|i| chunk.get(i))
+                        .map_or("unknown call", |(endpoint, _)| endpoint.as_str());
+                    format!("{} ({})", endpoint, status)
+                })
+                .collect();
+            if !failed.is_empty() {
+                return Err(ProviderError::Provider(format!(
+                    "{} of {} batched calls failed: {}",
+                    failed.len(),
+                    chunk.len(),
+                    failed.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds and removes labels on several threads in one batch request.
+    ///
+    /// `messages.batchModify` only accepts message IDs, so thread modify
+    /// calls are bundled through the batch endpoint instead.
+    async fn modify_threads(
+        &self,
+        thread_ids: &[String],
+        add_label_ids: Vec<String>,
+        remove_label_ids: Vec<String>,
+    ) -> Result<()> {
+        let body = serde_json::to_string(&ModifyRequest {
+            add_label_ids,
+            remove_label_ids,
+        })
+        .map_err(|e| ProviderError::Internal(e.to_string()))?;
+
+        let calls: Vec<_> = thread_ids
+            .iter()
+            .map(|id| (format!("/threads/{}/modify", id), Some(body.clone())))
+            .collect();
+        self.post_batch(&calls).await
+    }
+
+    /// Handles API response, checking for errors.
+    async fn handle_response<T: for<'de> Deserialize<'de>>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<T> {
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ProviderError::Internal(format!("parse response: {}", e)))
+    }
+
+    /// Handles API error responses.
     async fn handle_error(&self, response: reqwest::Response) -> ProviderError {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
@@ -451,8 +1101,13 @@ impl GmailProvider {
         }
     }
 
-    /// Converts a Gmail label ID to a folder name for querying.
-    fn label_id_for_folder(folder: &str) -> &str {
+    /// Converts a folder name to the Gmail label ID to query.
+    fn label_id_for_folder<'a>(&'a self, folder: &'a str) -> &'a str {
+        let mapped =
+            SpecialFolder::from_folder_name(folder).and_then(|role| self.folder_mapping.path(role));
+        if let Some(label_id) = mapped {
+            return label_id;
+        }
         match folder.to_uppercase().as_str() {
             "INBOX" => "INBOX",
             "STARRED" => "STARRED",
@@ -466,12 +1121,16 @@ impl GmailProvider {
     }
 
     /// Parses an email address from a header value like "Name <email@example.com>".
+    ///
+    /// Encoded-words in the name are decoded.
     fn parse_address(value: &str) -> Address {
         let value = value.trim();
         if let Some(start) = value.find('<') {
             if let Some(end) = value.find('>') {
                 let email = value[start + 1..end].trim().to_string();
-                let name = value[..start].trim().trim_matches('"').to_string();
+                let name = decode_header(value[..start].trim().trim_matches('"'))
+                    .trim()
+                    .to_string();
                 return Address {
                     email,
                     name: if name.is_empty() { None } else { Some(name) },
@@ -579,7 +1238,12 @@ impl GmailProvider {
             .map(|v| Self::parse_addresses(&v))
             .unwrap_or_default();
 
-        let subject = get_header("Subject");
+        let subject = get_header("Subject").map(|v| decode_header(&v));
+        let unsubscribe = get_header("List-Unsubscribe").and_then(|value| {
+            UnsubscribeInfo::parse(&value, get_header("List-Unsubscribe-Post").as_deref())
+        });
+        let read_receipt_to =
+            get_header("Disposition-Notification-To").and_then(|v| Address::parse(&v));
         let message_id = get_header("Message-ID")
             .map(MessageId::from)
             .unwrap_or_else(|| MessageId::from(format!("<{}>", msg.id)));
@@ -598,6 +1262,7 @@ impl GmailProvider {
             .as_ref()
             .and_then(|d| d.parse::<i64>().ok())
             .and_then(DateTime::from_timestamp_millis)
+            .or_else(|| get_header("Date").and_then(|v| parse_email_date(&v)))
             .unwrap_or_else(Utc::now);
 
         let label_strings = msg.label_ids.clone().unwrap_or_default();
@@ -608,7 +1273,13 @@ impl GmailProvider {
 
         let (body_text, body_html) = payload.map(Self::extract_body).unwrap_or((None, None));
 
-        let snippet = msg.snippet.clone().unwrap_or_default();
+        // Gmail's own snippet includes quoted history; prefer the new content.
+        let snippet = body_text
+            .as_deref()
+            .map(|body| snippet_from_body(body, SNIPPET_LENGTH))
+            .filter(|snippet| !snippet.is_empty())
+            .or_else(|| msg.snippet.clone())
+            .unwrap_or_default();
 
         Email {
             id: EmailId::from(msg.id.clone()),
@@ -631,18 +1302,63 @@ impl GmailProvider {
             is_draft,
             labels,
             attachments: vec![], // TODO: parse attachments
+            unsubscribe,
+            read_receipt_to,
+        }
+    }
+
+    /// Finds a sent message by its Message-ID header, returning its Gmail ID.
+    pub async fn find_sent_message(&self, message_id: &str) -> Result<Option<String>> {
+        #[derive(Deserialize)]
+        struct MessageRef {
+            id: String,
+        }
+
+        #[derive(Deserialize)]
+        struct MessageListResponse {
+            messages: Option<Vec<MessageRef>>,
         }
+
+        let query = format!(
+            "rfc822msgid:{} in:sent",
+            message_id.trim_start_matches('<').trim_end_matches('>')
+        );
+        let endpoint = format!(
+            "/messages?maxResults=1&q={}",
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+        );
+        let response: MessageListResponse = self.get(&endpoint).await?;
+        Ok(response
+            .messages
+            .and_then(|messages| messages.into_iter().next())
+            .map(|m| m.id))
+    }
+
+    /// Returns the From header for an outgoing email: the chosen send-as
+    /// alias, or else the account's own address from its profile.
+    ///
+    /// Returns `None` when neither is known, leaving Gmail to fill in the
+    /// authenticated account's address. Gmail only sends as that address
+    /// and the account's verified aliases.
+    fn from_address(&self, email: &OutgoingEmail) -> Option<String> {
+        email
+            .from
+            .as_ref()
+            .map(Address::display)
+            .or_else(|| self.email_address.clone())
     }
 
     /// Builds an RFC 5322 message from OutgoingEmail for sending.
     ///
-    /// Note: The `from` address is not part of OutgoingEmail since Gmail's API
-    /// uses the authenticated account's address automatically.
-    fn build_raw_message(&self, email: &OutgoingEmail, from_address: &str) -> String {
+    /// Without a `from_address` the message has no From header and Gmail
+    /// adds the authenticated account's address.
+    fn build_raw_message(&self, email: &OutgoingEmail, from_address: Option<&str>) -> String {
         let mut message = String::new();
 
         // Headers
-        message.push_str(&format!("From: {}\r\n", from_address));
+        if let Some(from_address) = from_address {
+            message.push_str(&format!("From: {}\r\n", from_address));
+        }
 
         let to_addrs: Vec<String> = email.to.iter().map(|a| a.email.clone()).collect();
         message.push_str(&format!("To: {}\r\n", to_addrs.join(", ")));
@@ -659,11 +1375,53 @@ impl GmailProvider {
 
         message.push_str(&format!("Subject: {}\r\n", email.subject));
 
+        if let Some(message_id) = email.message_id() {
+            message.push_str(&format!("Message-ID: {}\r\n", message_id));
+        }
+
         if let Some(in_reply_to) = &email.in_reply_to_message {
             message.push_str(&format!("In-Reply-To: {}\r\n", in_reply_to));
         }
 
+        if let Some(references) = email.references_header() {
+            message.push_str(&format!("References: {}\r\n", references));
+        }
+
+        if let Some(from_address) = from_address.filter(|_| email.request_read_receipt) {
+            message.push_str(&format!(
+                "Disposition-Notification-To: {}\r\n",
+                from_address
+            ));
+        }
+
         message.push_str("MIME-Version: 1.0\r\n");
+
+        let final_recipient = email
+            .from
+            .as_ref()
+            .map(|from| from.email.clone())
+            .or_else(|| self.email_address.clone())
+            .unwrap_or_default();
+        if let Some(report) = email.disposition_notification(&final_recipient) {
+            // A read receipt (RFC 8098): the text, then the notification.
+            let boundary = uuid::Uuid::new_v4().simple().to_string();
+            message.push_str(&format!(
+                "Content-Type: multipart/report; report-type=disposition-notification; \
+                 boundary=\"{}\"\r\n\r\n",
+                boundary
+            ));
+            message.push_str(&format!(
+                "--{}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+                boundary, email.body_text
+            ));
+            message.push_str(&format!(
+                "--{}\r\nContent-Type: message/disposition-notification\r\n\r\n{}",
+                boundary, report
+            ));
+            message.push_str(&format!("--{}--\r\n", boundary));
+            return message;
+        }
+
         message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
         message.push_str("\r\n");
 
@@ -680,20 +1438,54 @@ impl EmailProvider for GmailProvider {
         ProviderType::Gmail
     }
 
+    fn send_as_aliases(&self) -> Option<Vec<Address>> {
+        self.aliases.clone()
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_labels: true,
+            server_search: true,
+            push: true,
+            threads: true,
+            move_supported: true,
+        }
+    }
+
     async fn authenticate(&mut self) -> Result<()> {
         // Load credentials from keychain if not already set
         if self.credentials.is_none() {
-            self.credentials = Some(self.load_credentials_from_keychain()?);
+            self.credentials = Some(self.load_credentials().await?);
         }
 
         // Refresh the access token
         self.refresh_access_token().await?;
         self.authenticated = true;
 
+        // The account's own address orders thread participants; not fatal if missing.
+        match self.get::<GmailProfile>("/profile").await {
+            Ok(profile) => self.email_address = Some(profile.email_address),
+            Err(e) => tracing::warn!("Failed to fetch Gmail profile: {}", e),
+        }
+        // Gmail refuses to send as an unverified alias, so only verified
+        // ones are offered.
+        match self.get::<SendAsListResponse>("/settings/sendAs").await {
+            Ok(list) => self.aliases = Some(list.verified_aliases()),
+            Err(e) => tracing::warn!("Failed to fetch Gmail send-as aliases: {}", e),
+        }
+
         tracing::info!(account_id = %self.account_id, "Gmail provider authenticated");
         Ok(())
     }
 
+    async fn disconnect(&mut self) -> Result<()> {
+        // The REST API has no session to close; forgetting the access token
+        // is enough. The refresh token stays stored for the next sign-in.
+        self.access_token = None;
+        self.authenticated = false;
+        Ok(())
+    }
+
     async fn fetch_threads(
         &self,
         folder: &str,
@@ -705,7 +1497,7 @@ impl EmailProvider for GmailProvider {
             ));
         }
 
-        let label_id = Self::label_id_for_folder(folder);
+        let label_id = self.label_id_for_folder(folder);
 
         // Build query parameters
         let limit = pagination.limit.unwrap_or(50);
@@ -738,7 +1530,7 @@ impl EmailProvider for GmailProvider {
                     h.iter()
                         .find(|hdr| hdr.name.eq_ignore_ascii_case("Subject"))
                 })
-                .map(|h| h.value.clone());
+                .map(|h| decode_header(&h.value));
 
             let date = first_message
                 .and_then(|m| m.internal_date.as_ref())
@@ -766,12 +1558,19 @@ impl EmailProvider for GmailProvider {
                 account_id: self.account_id.clone(),
                 from,
                 subject,
-                snippet: thread.snippet.unwrap_or_default(),
+                snippet: thread
+                    .messages
+                    .as_deref()
+                    .and_then(preview_snippet)
+                    .or(thread.snippet)
+                    .unwrap_or_default(),
                 last_message_date: date,
                 message_count: thread.messages.map(|m| m.len() as u32).unwrap_or(1),
                 unread_count,
                 is_starred,
                 labels: label_ids,
+                muted: false,
+                watched: false,
             });
         }
 
@@ -796,29 +1595,12 @@ impl EmailProvider for GmailProvider {
             .collect();
 
         let subject = messages.first().and_then(|m| m.subject.clone());
-        let snippet = messages
-            .last()
-            .map(|m| m.snippet.clone())
-            .unwrap_or_default();
+        let snippet = Thread::preview_snippet(&messages);
         let last_message_date = messages.last().map(|m| m.date).unwrap_or_else(Utc::now);
         let unread_count = messages.iter().filter(|m| !m.is_read).count() as u32;
         let is_starred = messages.iter().any(|m| m.is_starred);
 
-        // Collect unique participants from all messages
-        let mut participants = Vec::new();
-        for msg in &messages {
-            if !participants
-                .iter()
-                .any(|p: &Address| p.email == msg.from.email)
-            {
-                participants.push(msg.from.clone());
-            }
-            for recipient in &msg.to {
-                if !participants.iter().any(|p| p.email == recipient.email) {
-                    participants.push(recipient.clone());
-                }
-            }
-        }
+        let participants = Thread::ordered_participants(&messages, self.email_address.as_deref());
 
         // Collect unique labels from all messages
         let mut label_set = std::collections::HashSet::new();
@@ -843,6 +1625,18 @@ impl EmailProvider for GmailProvider {
         })
     }
 
+    async fn fetch_raw(&self, email_id: &str) -> Result<Vec<u8>> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        let endpoint = format!("/messages/{}?format=raw", email_id);
+        let message: GmailRawMessage = self.get(&endpoint).await?;
+        decode_raw_message(&message.raw)
+    }
+
     async fn fetch_changes_since(&self, _since: &DateTime<Utc>) -> Result<Vec<Change>> {
         if !self.authenticated {
             return Err(ProviderError::Authentication(
@@ -859,100 +1653,7 @@ impl EmailProvider for GmailProvider {
             }
         };
 
-        let endpoint = format!(
-            "/history?startHistoryId={}&historyTypes=messageAdded,messageDeleted,labelAdded,labelRemoved",
-            history_id
-        );
-
-        let response: HistoryListResponse = self.get(&endpoint).await?;
-
-        let mut changes = Vec::new();
-
-        if let Some(history) = response.history {
-            for record in history {
-                // Handle new messages
-                if let Some(added) = record.messages_added {
-                    for item in added {
-                        // Fetch the full message
-                        let msg_endpoint = format!("/messages/{}?format=full", item.message.id);
-                        if let Ok(msg) = self.get::<GmailMessage>(&msg_endpoint).await {
-                            let email = self.gmail_message_to_email(&msg);
-                            let new_email = NewEmailData {
-                                id: email.id,
-                                thread_id: email.thread_id,
-                                from: email.from,
-                                to: email.to,
-                                cc: email.cc,
-                                subject: email.subject,
-                                snippet: email.snippet,
-                                date: email.date,
-                                labels: email.labels,
-                                is_read: email.is_read,
-                                is_starred: email.is_starred,
-                                raw: None,
-                            };
-                            changes.push(Change::NewEmail(new_email));
-                        }
-                    }
-                }
-
-                // Handle deleted messages
-                if let Some(deleted) = record.messages_deleted {
-                    for item in deleted {
-                        changes.push(Change::Deleted(EmailId::from(item.message.id)));
-                    }
-                }
-
-                // Handle label changes (read/unread, starred, etc.)
-                if let Some(label_added) = record.labels_added {
-                    for item in label_added {
-                        let mut is_starred = None;
-                        let mut is_read = None;
-                        let mut labels = Vec::new();
-                        for label in &item.label_ids {
-                            match label.as_str() {
-                                "STARRED" => is_starred = Some(true),
-                                "UNREAD" => is_read = Some(false),
-                                _ => labels.push(LabelId::from(label.clone())),
-                            }
-                        }
-                        let update = EmailUpdate {
-                            id: EmailId::from(item.message.id),
-                            labels: if labels.is_empty() {
-                                None
-                            } else {
-                                Some(labels)
-                            },
-                            is_read,
-                            is_starred,
-                        };
-                        changes.push(Change::Updated(update));
-                    }
-                }
-
-                if let Some(label_removed) = record.labels_removed {
-                    for item in label_removed {
-                        let mut is_starred = None;
-                        let mut is_read = None;
-                        for label in &item.label_ids {
-                            match label.as_str() {
-                                "STARRED" => is_starred = Some(false),
-                                "UNREAD" => is_read = Some(true),
-                                _ => {}
-                            }
-                        }
-                        let update = EmailUpdate {
-                            id: EmailId::from(item.message.id),
-                            labels: None, // Can't express "remove these labels" with this schema
-                            is_read,
-                            is_starred,
-                        };
-                        changes.push(Change::Updated(update));
-                    }
-                }
-            }
-        }
-
+        let (changes, _) = self.history_changes(&history_id).await?;
         Ok(changes)
     }
 
@@ -963,9 +1664,21 @@ impl EmailProvider for GmailProvider {
             ));
         }
 
-        // Gmail API sends from the authenticated user; "me" is a special alias
-        let from_address = format!("{}@gmail.com", self.account_id.0);
-        let raw_message = self.build_raw_message(email, &from_address);
+        // A retried send whose first attempt went through is already in Sent.
+        // If the search fails, sending is better than not sending at all.
+        if let Some(message_id) = email.message_id() {
+            match self.find_sent_message(&message_id).await {
+                Ok(Some(id)) => {
+                    tracing::info!(message_id = %id, "Email already sent, not sending again");
+                    return Ok(id);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to check for an earlier send, sending: {}", e),
+            }
+        }
+
+        let from_address = self.from_address(email);
+        let raw_message = self.build_raw_message(email, from_address.as_deref());
         let encoded = BASE64_URL_SAFE_NO_PAD.encode(raw_message.as_bytes());
 
         #[derive(Serialize)]
@@ -993,16 +1706,8 @@ impl EmailProvider for GmailProvider {
             ));
         }
 
-        for thread_id in thread_ids {
-            let endpoint = format!("/threads/{}/modify", thread_id);
-            let body = ModifyRequest {
-                add_label_ids: vec![],
-                remove_label_ids: vec!["INBOX".to_string()],
-            };
-            self.post_no_response(&endpoint, &body).await?;
-        }
-
-        Ok(())
+        self.modify_threads(thread_ids, vec![], vec!["INBOX".to_string()])
+            .await
     }
 
     async fn trash(&self, thread_ids: &[String]) -> Result<()> {
@@ -1012,28 +1717,42 @@ impl EmailProvider for GmailProvider {
             ));
         }
 
-        for thread_id in thread_ids {
-            let endpoint = format!("/threads/{}/trash", thread_id);
-            // Gmail trash endpoint expects POST with empty body
-            let url = format!("{}{}", GMAIL_API_BASE, endpoint);
-            let headers = self.auth_headers()?;
-
-            let response = self
-                .client
-                .post(&url)
-                .headers(headers)
-                .send()
-                .await
-                .map_err(|e| ProviderError::Connection(e.to_string()))?;
+        // Gmail trash endpoint expects POST with empty body
+        let calls: Vec<_> = thread_ids
+            .iter()
+            .map(|id| (format!("/threads/{}/trash", id), None))
+            .collect();
+        self.post_batch(&calls).await
+    }
 
-            if !response.status().is_success() {
-                return Err(self.handle_error(response).await);
-            }
+    async fn delete_permanently(&self, thread_ids: &[String]) -> Result<()> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
         }
 
+        for id in thread_ids {
+            self.delete(&format!("/threads/{}", id)).await?;
+        }
         Ok(())
     }
 
+    async fn report_spam(&self, thread_ids: &[String]) -> Result<()> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        self.modify_threads(
+            thread_ids,
+            vec!["SPAM".to_string()],
+            vec!["INBOX".to_string()],
+        )
+        .await
+    }
+
     async fn star(&self, thread_id: &str, starred: bool) -> Result<()> {
         if !self.authenticated {
             return Err(ProviderError::Authentication(
@@ -1096,6 +1815,32 @@ impl EmailProvider for GmailProvider {
         self.post_no_response(&endpoint, &body).await
     }
 
+    async fn mark_read_many(&self, thread_ids: &[String], read: bool) -> Result<()> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        let unread = vec!["UNREAD".to_string()];
+        if read {
+            self.modify_threads(thread_ids, vec![], unread).await
+        } else {
+            self.modify_threads(thread_ids, unread, vec![]).await
+        }
+    }
+
+    async fn apply_label_many(&self, thread_ids: &[String], label: &str) -> Result<()> {
+        if !self.authenticated {
+            return Err(ProviderError::Authentication(
+                "not authenticated".to_string(),
+            ));
+        }
+
+        self.modify_threads(thread_ids, vec![label.to_string()], vec![])
+            .await
+    }
+
     async fn fetch_labels(&self) -> Result<Vec<Label>> {
         if !self.authenticated {
             return Err(ProviderError::Authentication(
@@ -1150,38 +1895,31 @@ impl EmailProvider for GmailProvider {
                 let ids: Vec<String> = thread_ids.iter().map(|t| t.0.clone()).collect();
                 self.trash(&ids).await
             }
+            PendingChangeType::ReportSpam { thread_ids } => {
+                let ids: Vec<String> = thread_ids.iter().map(|t| t.0.clone()).collect();
+                self.report_spam(&ids).await
+            }
             PendingChangeType::Star { thread_id, starred } => {
                 self.star(&thread_id.0, *starred).await
             }
             PendingChangeType::MarkRead { thread_ids, read } => {
-                // Apply read status to all thread IDs
-                for thread_id in thread_ids {
-                    self.mark_read(&thread_id.0, *read).await?;
-                }
-                Ok(())
+                let ids: Vec<String> = thread_ids.iter().map(|t| t.0.clone()).collect();
+                self.mark_read_many(&ids, *read).await
             }
             PendingChangeType::ApplyLabel {
                 thread_ids,
                 label_id,
             } => {
-                for thread_id in thread_ids {
-                    self.apply_label(&thread_id.0, &label_id.0).await?;
-                }
-                Ok(())
+                let ids: Vec<String> = thread_ids.iter().map(|t| t.0.clone()).collect();
+                self.apply_label_many(&ids, &label_id.0).await
             }
             PendingChangeType::RemoveLabel {
                 thread_ids,
                 label_id,
             } => {
-                for thread_id in thread_ids {
-                    let endpoint = format!("/threads/{}/modify", thread_id.0);
-                    let body = ModifyRequest {
-                        add_label_ids: vec![],
-                        remove_label_ids: vec![label_id.0.clone()],
-                    };
-                    self.post_no_response(&endpoint, &body).await?;
-                }
-                Ok(())
+                let ids: Vec<String> = thread_ids.iter().map(|t| t.0.clone()).collect();
+                self.modify_threads(&ids, vec![], vec![label_id.0.clone()])
+                    .await
             }
             PendingChangeType::Send { email } => {
                 self.send_email(email).await?;
@@ -1191,9 +1929,339 @@ impl EmailProvider for GmailProvider {
     }
 }
 
+/// Fetches each ID with at most `concurrency` requests in flight.
+///
+/// Results are returned in input order. Failed fetches are logged and yield
+/// `None` so one bad message does not abort the batch.
+async fn fetch_bounded<'a, T, F, Fut>(
+    ids: &'a [String],
+    concurrency: usize,
+    fetch: F,
+) -> Vec<Option<T>>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut results: Vec<(usize, Option<T>)> = stream::iter(ids.iter().enumerate())
+        .map(|(index, id)| {
+            let fut = fetch(id.as_str());
+            async move {
+                let result = match fut.await {
+                    Ok(value) => Some(value),
+                    Err(e) => {
+                        tracing::warn!(
+                            message_id = %id,
+                            error = %e,
+                            "Skipping message that failed to fetch"
+                        );
+                        None
+                    }
+                };
+                (index, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Builds a `multipart/mixed` batch request body with one POST per call.
+fn batch_request_body(calls: &[(String, Option<String>)]) -> String {
+    let mut body = String::new();
+    for (i, (endpoint, json)) in calls.iter().enumerate() {
+        body.push_str(&format!(
+            "--{}\r\nContent-Type: application/http\r\nContent-ID: <item-{}>\r\n\r\n",
+            GMAIL_BATCH_BOUNDARY, i
+        ));
+        body.push_str(&format!("POST {}{}\r\n", GMAIL_BATCH_PATH, endpoint));
+        match json {
+            Some(json) => body.push_str(&format!(
+                "Content-Type: application/json\r\n\r\n{}\r\n",
+                json
+            )),
+            None => body.push_str("\r\n"),
+        }
+    }
+    body.push_str(&format!("--{}--\r\n", GMAIL_BATCH_BOUNDARY));
+    body
+}
+
+/// Returns the calls in a batch response that failed, with their status lines.
+///
+/// Parts may arrive in any order, so each is matched back to its call through
+/// the `response-item-N` Content-ID. The index is `None` if a part has none.
+fn failed_batch_items(response: &str) -> Vec<(Option<usize>, &str)> {
+    let mut failed = Vec::new();
+    let mut index = None;
+
+    for line in response.lines().map(str::trim) {
+        if line.starts_with("--") {
+            index = None;
+        } else if let Some(id) = line.strip_prefix("Content-ID:") {
+            index = id
+                .trim()
+                .trim_start_matches("<response-item-")
+                .trim_end_matches('>')
+                .parse()
+                .ok();
+        } else if line.starts_with("HTTP/") {
+            let ok = line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse::<u16>().ok())
+                .is_some_and(|code| (200..300).contains(&code));
+            if !ok {
+                failed.push((index, line));
+            }
+        }
+    }
+
+    failed
+}
+
+/// Decodes the base64url `raw` field of a message fetched with
+/// `format=raw`, with or without padding.
+fn decode_raw_message(raw: &str) -> Result<Vec<u8>> {
+    BASE64_URL_SAFE_NO_PAD
+        .decode(raw.trim_end_matches('='))
+        .map_err(|e| ProviderError::Provider(format!("invalid raw message: {}", e)))
+}
+
+/// Picks a listed thread's preview like [`Thread::preview_snippet`]: the
+/// snippet of the newest unread message, or of the newest message when all
+/// are read. Returns `None` when no message carries a snippet.
+fn preview_snippet(messages: &[GmailMessage]) -> Option<String> {
+    let is_unread = |msg: &&GmailMessage| {
+        msg.label_ids
+            .as_ref()
+            .is_some_and(|labels| labels.iter().any(|l| l == "UNREAD"))
+    };
+    let date = |msg: &&GmailMessage| {
+        msg.internal_date
+            .as_deref()
+            .and_then(|d| d.parse::<i64>().ok())
+            .unwrap_or_default()
+    };
+    let with_snippet = messages.iter().filter(|msg| msg.snippet.is_some());
+    with_snippet
+        .clone()
+        .filter(is_unread)
+        .max_by_key(date)
+        .or_else(|| with_snippet.max_by_key(date))
+        .and_then(|msg| msg.snippet.clone())
+}
+
+/// Returns whether a pushed history ID is newer than the last known one.
+///
+/// Unparseable stored IDs are treated as stale so a delta is fetched.
+fn is_newer_history(last_known: &str, pushed: u64) -> bool {
+    last_known
+        .parse::<u64>()
+        .map_or(true, |known| pushed > known)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn raw_reply_carries_threading_headers() {
+        let provider = GmailProvider::new(AccountId::from("test-account"));
+        let email = OutgoingEmail {
+            from: None,
+            to: vec![Address::new("alice@example.com")],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Re: Plans".to_string(),
+            body_text: "Sounds good.".to_string(),
+            body_html: None,
+            in_reply_to_thread: None,
+            in_reply_to_message: Some("<b@example.com>".to_string()),
+            references: vec!["<a@example.com>".to_string(), "<b@example.com>".to_string()],
+            attachments: vec![],
+            idempotency_key: Some("send-1".to_string()),
+            request_read_receipt: false,
+            read_receipt_for: None,
+        };
+
+        let raw = provider.build_raw_message(&email, Some("me@example.com"));
+
+        assert!(raw.contains("In-Reply-To: <b@example.com>\r\n"));
+        assert!(raw.contains("References: <a@example.com> <b@example.com>\r\n"));
+        assert!(raw.contains("Message-ID: <send-1@heap.local>\r\n"));
+        assert!(!raw.contains("Disposition-Notification-To"));
+    }
+
+    #[test]
+    fn raw_message_is_sent_from_the_chosen_alias() {
+        let mut provider = GmailProvider::new(AccountId::from("account-1"));
+        let mut email = OutgoingEmail {
+            from: None,
+            to: vec![Address::new("alice@example.com")],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Invoice".to_string(),
+            body_text: "Attached.".to_string(),
+            body_html: None,
+            in_reply_to_thread: None,
+            in_reply_to_message: None,
+            references: vec![],
+            attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: false,
+            read_receipt_for: None,
+        };
+
+        // Before the profile is known, Gmail fills in the From header.
+        assert_eq!(provider.from_address(&email), None);
+        let raw = provider.build_raw_message(&email, None);
+        assert!(!raw.contains("From:"));
+
+        // The account's own address, which need not be an @gmail.com one.
+        provider.email_address = Some("me@work.example".to_string());
+        let from = provider.from_address(&email);
+        assert_eq!(from.as_deref(), Some("me@work.example"));
+
+        email.from = Some(Address::with_name("billing@work.example", "Billing"));
+        let from = provider.from_address(&email);
+        let raw = provider.build_raw_message(&email, from.as_deref());
+        assert!(raw.starts_with("From: Billing <billing@work.example>\r\n"));
+    }
+
+    #[test]
+    fn raw_message_requests_read_receipt() {
+        let provider = GmailProvider::new(AccountId::from("test-account"));
+        let email = OutgoingEmail {
+            from: None,
+            to: vec![Address::new("alice@example.com")],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Contract".to_string(),
+            body_text: "Please confirm.".to_string(),
+            body_html: None,
+            in_reply_to_thread: None,
+            in_reply_to_message: None,
+            references: vec![],
+            attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: true,
+            read_receipt_for: None,
+        };
+
+        let raw = provider.build_raw_message(&email, Some("me@example.com"));
+
+        assert!(raw.contains("Disposition-Notification-To: me@example.com\r\n"));
+    }
+
+    #[test]
+    fn raw_read_receipt_is_a_disposition_notification() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        provider.email_address = Some("me@example.com".to_string());
+        let email = OutgoingEmail {
+            from: None,
+            to: vec![Address::new("alice@example.com")],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Read: Contract".to_string(),
+            body_text: "The message was displayed.".to_string(),
+            body_html: None,
+            in_reply_to_thread: None,
+            in_reply_to_message: Some("<contract@example.com>".to_string()),
+            references: vec![],
+            attachments: vec![],
+            idempotency_key: Some("mdn.contract.example.com".to_string()),
+            request_read_receipt: false,
+            read_receipt_for: Some("<contract@example.com>".to_string()),
+        };
+
+        let from = provider.from_address(&email);
+        let raw = provider.build_raw_message(&email, from.as_deref());
+
+        assert!(raw.contains(
+            "Content-Type: multipart/report; report-type=disposition-notification; boundary="
+        ));
+        assert!(raw.contains("Content-Type: message/disposition-notification\r\n"));
+        assert!(raw.contains("Final-Recipient: rfc822;me@example.com\r\n"));
+        assert!(raw.contains("Original-Message-ID: <contract@example.com>\r\n"));
+        assert!(raw.contains("Message-ID: <mdn.contract.example.com@heap.local>\r\n"));
+        let parsed = mail_parser::MessageParser::default()
+            .parse(raw.as_bytes())
+            .unwrap();
+        assert_eq!(parsed.parts.len(), 3);
+    }
+
+    #[test]
+    fn encoded_headers_are_decoded() {
+        let provider = GmailProvider::new(AccountId::from("test-account"));
+        let from = "=?UTF-8?Q?Andr=C3=A9_M=C3=BCller?= <andre@example.com>";
+        let to = "\"=?ISO-8859-1?Q?Fran=E7ois?=\" <f@example.com>, bob@example.com";
+        let subject = "=?UTF-8?B?UsOpdW5pb24gZGUgbOKA?=\r\n =?UTF-8?B?mcOpcXVpcGU=?=";
+        let msg: GmailMessage = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "threadId": "t1",
+            "payload": {
+                "headers": [
+                    {"name": "From", "value": from},
+                    {"name": "To", "value": to},
+                    {"name": "Subject", "value": subject}
+                ]
+            }
+        }))
+        .unwrap();
+
+        let email = provider.gmail_message_to_email(&msg);
+
+        assert_eq!(email.from.name.as_deref(), Some("André Müller"));
+        assert_eq!(email.from.email, "andre@example.com");
+        assert_eq!(email.to[0].name.as_deref(), Some("François"));
+        assert_eq!(email.to[1].email, "bob@example.com");
+        assert_eq!(email.subject.as_deref(), Some("Réunion de l’équipe"));
+    }
+
+    #[test]
+    fn raw_messages_decode_to_rfc822() {
+        let source = "Message-ID: <raw-1@example.com>\r\n\
+                      From: Alice <alice@example.com>\r\n\
+                      To: bob@example.com\r\n\
+                      Subject: Delivery report\r\n\
+                      Content-Type: text/plain; charset=utf-8\r\n\
+                      \r\n\
+                      Did this arrive? \u{2713}\r\n";
+        // Gmail may send the raw field padded or not.
+        for encoded in [
+            BASE64_URL_SAFE.encode(source),
+            BASE64_URL_SAFE_NO_PAD.encode(source),
+        ] {
+            let raw = decode_raw_message(&encoded).unwrap();
+            assert_eq!(raw, source.as_bytes());
+
+            let parsed = mail_parser::MessageParser::default().parse(&raw).unwrap();
+            assert_eq!(parsed.subject(), Some("Delivery report"));
+            assert_eq!(parsed.message_id(), Some("raw-1@example.com"));
+            assert_eq!(
+                parsed.body_text(0).as_deref(),
+                Some("Did this arrive? \u{2713}\r\n")
+            );
+        }
+
+        // 8-bit sources that aren't UTF-8 come back unchanged.
+        let latin1 = b"Subject: caf\xe9\r\nContent-Type: text/plain; charset=iso-8859-1\r\n\r\n";
+        assert_eq!(
+            decode_raw_message(&BASE64_URL_SAFE.encode(latin1)).unwrap(),
+            latin1
+        );
+
+        assert!(matches!(
+            decode_raw_message("not base64!"),
+            Err(ProviderError::Provider(_))
+        ));
+    }
 
     #[test]
     fn gmail_provider_creation() {
@@ -1202,12 +2270,77 @@ mod tests {
         assert!(!provider.is_authenticated());
     }
 
+    #[test]
+    fn folders_use_system_labels_unless_mapped() {
+        let provider = GmailProvider::new(AccountId::from("test-account"));
+        assert_eq!(provider.label_id_for_folder("drafts"), "DRAFT");
+        assert_eq!(provider.label_id_for_folder("ARCHIVE"), "all");
+        assert_eq!(provider.label_id_for_folder("Label_7"), "Label_7");
+
+        let mut mapping = FolderMapping::new();
+        mapping.set_override(SpecialFolder::Archive, Some("Label_42".to_string()));
+        let provider = provider.with_folder_mapping(mapping);
+        assert_eq!(provider.label_id_for_folder("ARCHIVE"), "Label_42");
+        assert_eq!(provider.label_id_for_folder("SENT"), "SENT");
+    }
+
+    #[test]
+    fn only_verified_send_as_aliases_are_offered() {
+        let list: SendAsListResponse = serde_json::from_value(serde_json::json!({
+            "sendAs": [
+                { "sendAsEmail": "me@gmail.com", "displayName": "", "isPrimary": true },
+                {
+                    "sendAsEmail": "billing@work.example",
+                    "displayName": "Billing",
+                    "verificationStatus": "accepted"
+                },
+                { "sendAsEmail": "support@work.example", "verificationStatus": "pending" },
+                {
+                    "sendAsEmail": "me@work.example",
+                    "displayName": "",
+                    "verificationStatus": "accepted"
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            list.verified_aliases(),
+            vec![
+                Address::with_name("billing@work.example", "Billing"),
+                Address::new("me@work.example"),
+            ]
+        );
+    }
+
     #[test]
     fn gmail_provider_type() {
         let provider = GmailProvider::new(AccountId::from("test-account"));
         assert_eq!(provider.provider_type(), ProviderType::Gmail);
     }
 
+    #[tokio::test]
+    async fn begin_oauth_returns_google_consent_url() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        let auth_url = provider
+            .begin_oauth("client-id", "client-secret")
+            .await
+            .unwrap();
+
+        assert!(auth_url.url.starts_with(GOOGLE_AUTH_URL));
+        assert!(auth_url.url.contains("client_id=client-id"));
+        assert!(auth_url.url.contains("code_challenge_method=S256"));
+        assert!(auth_url.redirect_uri.starts_with("http://127.0.0.1:"));
+    }
+
+    #[tokio::test]
+    async fn complete_oauth_without_begin_fails() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        let result = provider.complete_oauth("code").await;
+        assert!(matches!(result, Err(ProviderError::InvalidRequest(_))));
+        assert!(!provider.is_authenticated());
+    }
+
     #[tokio::test]
     #[ignore = "requires OAuth credentials in keychain"]
     async fn gmail_provider_authenticate() {
@@ -1219,6 +2352,15 @@ mod tests {
         assert!(provider.is_authenticated());
     }
 
+    #[test]
+    fn gmail_capabilities() {
+        let provider = GmailProvider::new(AccountId::from("test-account"));
+        let caps = provider.capabilities();
+        assert!(caps.native_labels);
+        assert!(caps.threads);
+        assert!(caps.push);
+    }
+
     #[tokio::test]
     async fn gmail_provider_requires_auth() {
         let provider = GmailProvider::new(AccountId::from("test-account"));
@@ -1227,6 +2369,307 @@ mod tests {
         assert!(matches!(result, Err(ProviderError::Authentication(_))));
     }
 
+    #[test]
+    fn push_notification_parses_pubsub_data() {
+        let data = br#"{"emailAddress": "user@example.com", "historyId": 9876543210}"#;
+        let notification = PushNotification::from_pubsub_data(data).unwrap();
+        assert_eq!(notification.email_address, "user@example.com");
+        assert_eq!(notification.history_id, 9_876_543_210);
+
+        assert!(matches!(
+            PushNotification::from_pubsub_data(b"not json"),
+            Err(ProviderError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn watch_renewal_window() {
+        let now = Utc::now();
+        let fresh = WatchState {
+            topic: "projects/p/topics/t".to_string(),
+            expiration: now + chrono::Duration::days(7),
+        };
+        let expiring = WatchState {
+            expiration: now + chrono::Duration::hours(12),
+            ..fresh.clone()
+        };
+
+        assert!(!fresh.needs_renewal(now));
+        assert!(expiring.needs_renewal(now));
+    }
+
+    #[tokio::test]
+    async fn push_notification_first_sets_history_id() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        provider.authenticated = true;
+
+        let notification = PushNotification {
+            email_address: "user@example.com".to_string(),
+            history_id: 500,
+        };
+        let changes = provider
+            .process_push_notification(&notification)
+            .await
+            .unwrap();
+
+        assert!(changes.is_empty());
+        assert_eq!(provider.last_history_id(), Some("500"));
+    }
+
+    #[tokio::test]
+    async fn push_notification_skips_stale_history() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        provider.authenticated = true;
+        provider.set_last_history_id("500");
+
+        for history_id in [400, 500] {
+            let notification = PushNotification {
+                email_address: "user@example.com".to_string(),
+                history_id,
+            };
+            let changes = provider
+                .process_push_notification(&notification)
+                .await
+                .unwrap();
+            assert!(changes.is_empty());
+        }
+        assert_eq!(provider.last_history_id(), Some("500"));
+    }
+
+    #[tokio::test]
+    async fn fetch_bounded_limits_in_flight_and_keeps_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let ids: Vec<String> = (0..20).map(|i| format!("msg-{}", i)).collect();
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let results = fetch_bounded(&ids, 4, |id| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                // Finish in reverse-ish order to exercise reordering.
+                let n: u64 = id.trim_start_matches("msg-").parse().unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(20 - n)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if n == 7 {
+                    Err(ProviderError::NotFound(id.to_string()))
+                } else {
+                    Ok(id.to_string())
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 4);
+        assert_eq!(results.len(), 20);
+        assert!(results[7].is_none());
+        for (i, result) in results.iter().enumerate().filter(|(i, _)| *i != 7) {
+            assert_eq!(result.as_deref(), Some(format!("msg-{}", i).as_str()));
+        }
+    }
+
+    #[test]
+    fn fetch_concurrency_is_at_least_one() {
+        let provider =
+            GmailProvider::new(AccountId::from("test-account")).with_fetch_concurrency(0);
+        assert_eq!(provider.fetch_concurrency, 1);
+    }
+
+    #[tokio::test]
+    async fn watch_requires_auth() {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        let result = provider.watch("projects/p/topics/t").await;
+        assert!(matches!(result, Err(ProviderError::Authentication(_))));
+    }
+
+    #[test]
+    fn batch_request_has_one_part_per_call() {
+        let calls: Vec<_> = (0..3)
+            .map(|i| {
+                (
+                    format!("/threads/t{}/modify", i),
+                    Some(r#"{"removeLabelIds":["INBOX"]}"#.to_string()),
+                )
+            })
+            .collect();
+
+        let body = batch_request_body(&calls);
+
+        assert_eq!(body.matches("Content-Type: application/http").count(), 3);
+        assert!(body.contains("POST /gmail/v1/users/me/threads/t2/modify\r\n"));
+        assert!(body.ends_with("--heap_batch--\r\n"));
+    }
+
+    #[test]
+    fn failed_batch_items_match_parts_to_calls() {
+        let response = "--batch_x\r\nContent-Type: application/http\r\n\
+                        Content-ID: <response-item-1>\r\n\r\n\
+                        HTTP/1.1 404 Not Found\r\n\r\n{}\r\n\
+                        --batch_x\r\nContent-Type: application/http\r\n\
+                        Content-ID: <response-item-0>\r\n\r\n\
+                        HTTP/1.1 200 OK\r\n\r\n{}\r\n--batch_x--";
+
+        assert_eq!(
+            failed_batch_items(response),
+            vec![(Some(1), "HTTP/1.1 404 Not Found")]
+        );
+    }
+
+    /// Request bodies received by [`serve_batches`].
+    type BatchBodies = Arc<std::sync::Mutex<Vec<String>>>;
+
+    /// Serves batch requests on a local port, answering every call with
+    /// `status` and recording the requests received.
+    async fn serve_batches(status: &'static str) -> (String, Arc<AtomicUsize>, BatchBodies) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/batch", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let bodies = BatchBodies::default();
+        let (counter, received) = (requests.clone(), bodies.clone());
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (counter, received) = (counter.clone(), received.clone());
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    loop {
+                        let mut content_length = 0;
+                        let mut line = String::new();
+                        loop {
+                            line.clear();
+                            if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                                return;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            if let Some(len) =
+                                line.to_ascii_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = len.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        reader.read_exact(&mut body).await.unwrap();
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        let body = String::from_utf8_lossy(&body).into_owned();
+                        let calls = body.matches("Content-Type: application/http").count();
+                        received.lock().unwrap().push(body);
+
+                        let mut reply = String::new();
+                        for i in 0..calls {
+                            reply.push_str(&format!(
+                                "--r\r\nContent-Type: application/http\r\n\
+                                 Content-ID: <response-item-{}>\r\n\r\n{}\r\n\r\n{{}}\r\n",
+                                i, status
+                            ));
+                        }
+                        reply.push_str("--r--\r\n");
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: multipart/mixed; boundary=r\r\n\
+                             Content-Length: {}\r\n\r\n{}",
+                            reply.len(),
+                            reply
+                        );
+                        reader
+                            .get_mut()
+                            .write_all(response.as_bytes())
+                            .await
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        (url, requests, bodies)
+    }
+
+    fn batch_test_provider(batch_url: String) -> GmailProvider {
+        let mut provider = GmailProvider::new(AccountId::from("test-account"));
+        provider.authenticated = true;
+        provider.access_token = Some("token".to_string());
+        provider.batch_url = batch_url;
+        provider
+    }
+
+    #[tokio::test]
+    async fn disconnect_drops_access_token() {
+        let mut provider = batch_test_provider(String::new());
+        assert!(provider.is_authenticated());
+
+        provider.disconnect().await.unwrap();
+
+        assert!(!provider.is_authenticated());
+        assert!(provider.access_token.is_none());
+        assert!(matches!(
+            provider.archive(&["thread-1".to_string()]).await,
+            Err(ProviderError::Authentication(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn archive_of_150_threads_sends_two_batch_requests() {
+        let (url, requests, _) = serve_batches("HTTP/1.1 200 OK").await;
+        let provider = batch_test_provider(url);
+        let ids: Vec<String> = (0..150).map(|i| format!("thread-{}", i)).collect();
+
+        provider.archive(&ids).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn report_spam_labels_spam_and_removes_inbox() {
+        let (url, _, bodies) = serve_batches("HTTP/1.1 200 OK").await;
+        let provider = batch_test_provider(url);
+
+        provider
+            .report_spam(&["thread-a".to_string(), "thread-b".to_string()])
+            .await
+            .unwrap();
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        assert!(bodies[0].contains("POST /gmail/v1/users/me/threads/thread-a/modify\r\n"));
+        assert!(bodies[0].contains("POST /gmail/v1/users/me/threads/thread-b/modify\r\n"));
+        assert_eq!(
+            bodies[0]
+                .matches(r#"{"addLabelIds":["SPAM"],"removeLabelIds":["INBOX"]}"#)
+                .count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn request_timeout_maps_to_connection_error() {
+        let timeout = Duration::from_secs(1);
+        let provider = batch_test_provider("http://10.255.255.1/batch".to_string())
+            .with_request_timeout(timeout);
+        let start = std::time::Instant::now();
+
+        let result = provider.archive(&["thread-1".to_string()]).await;
+
+        assert!(matches!(result, Err(ProviderError::Connection(_))));
+        assert!(start.elapsed() < timeout + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn failed_batch_calls_are_reported_per_thread() {
+        let (url, _, _) = serve_batches("HTTP/1.1 404 Not Found").await;
+        let provider = batch_test_provider(url);
+        let ids = vec!["thread-a".to_string(), "thread-b".to_string()];
+
+        let err = provider.mark_read_many(&ids, true).await.unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("2 of 2"));
+        assert!(message.contains("/threads/thread-a/modify"));
+        assert!(message.contains("/threads/thread-b/modify"));
+    }
+
     #[tokio::test]
     #[ignore = "requires OAuth credentials in keychain"]
     async fn gmail_provider_fetch_threads_empty() {
@@ -1254,4 +2697,29 @@ mod tests {
         assert!(provider.apply_label("thread-1", "Work").await.is_ok());
         assert!(provider.fetch_labels().await.is_ok());
     }
+
+    #[test]
+    fn listed_threads_preview_their_newest_unread_message() {
+        let thread: GmailThread = serde_json::from_value(serde_json::json!({
+            "id": "t1",
+            "snippet": "Sounds good",
+            "messages": [
+                {"id": "m1", "threadId": "t1", "labelIds": ["INBOX", "UNREAD"],
+                 "snippet": "Lunch?", "internalDate": "1000"},
+                {"id": "m2", "threadId": "t1", "labelIds": ["INBOX", "UNREAD"],
+                 "snippet": "Noon works", "internalDate": "2000"},
+                {"id": "m3", "threadId": "t1", "labelIds": ["SENT"],
+                 "snippet": "Sounds good", "internalDate": "3000"}
+            ]
+        }))
+        .unwrap();
+        let messages = thread.messages.unwrap();
+
+        assert_eq!(preview_snippet(&messages).as_deref(), Some("Noon works"));
+        assert_eq!(
+            preview_snippet(&messages[2..]).as_deref(),
+            Some("Sounds good")
+        );
+        assert_eq!(preview_snippet(&[]), None);
+    }
 }
@@ -0,0 +1,378 @@
+//! Decoding of header values.
+//!
+//! Non-ASCII display names and subjects travel as RFC 2047 encoded-words
+//! such as `=?UTF-8?Q?Andr=C3=A9?=`. Gmail returns headers undecoded, so
+//! both providers run header values through [`decode_header`] before they
+//! reach the domain types. `Date` headers are often not quite RFC 2822;
+//! [`parse_email_date`] accepts the common variants.
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+/// Base64 that accepts encoded-words with or without padding.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Windows-1252 characters for bytes 0x80 to 0x9F, where it differs from
+/// ISO-8859-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Layouts of dates that aren't RFC 2822, once the day of the week and the
+/// zone are removed.
+const DATE_FORMATS: &[&str] = &[
+    "%d %b %Y %H:%M:%S",
+    "%d %b %Y %H:%M",
+    "%d %B %Y %H:%M:%S",
+    "%d %B %Y %H:%M",
+    "%b %d %H:%M:%S %Y",
+    "%b %d %Y %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Zone names seen in the wild, with their offsets in hours.
+const ZONE_NAMES: &[(&str, i32)] = &[
+    ("UT", 0),
+    ("UTC", 0),
+    ("GMT", 0),
+    ("Z", 0),
+    ("EST", -5),
+    ("EDT", -4),
+    ("CST", -6),
+    ("CDT", -5),
+    ("MST", -7),
+    ("MDT", -6),
+    ("PST", -8),
+    ("PDT", -7),
+    ("BST", 1),
+    ("CET", 1),
+    ("CEST", 2),
+    ("EET", 2),
+    ("EEST", 3),
+    ("JST", 9),
+    ("AEST", 10),
+    ("AEDT", 11),
+];
+
+/// Decodes the encoded-words in a header value and unfolds it.
+///
+/// Both B (base64) and Q (quoted-printable) encodings are handled. Adjacent
+/// encoded-words are joined without the whitespace between them, and their
+/// bytes are decoded together so a character split across words survives.
+/// Text that isn't a valid encoded-word is kept as is.
+pub fn decode_header(value: &str) -> String {
+    let value: String = value.chars().filter(|c| *c != '\r' && *c != '\n').collect();
+    let mut decoded = String::with_capacity(value.len());
+    // Bytes of the encoded-words seen since the last plain text.
+    let mut pending: Option<(String, Vec<u8>)> = None;
+    let mut rest = value.as_str();
+
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        let Some((word, len)) = EncodedWord::parse(candidate) else {
+            flush(&mut decoded, &mut pending);
+            decoded.push_str(before);
+            decoded.push_str("=?");
+            rest = &candidate[2..];
+            continue;
+        };
+
+        let adjacent = pending.is_some() && before.chars().all(char::is_whitespace);
+        match &mut pending {
+            Some((charset, bytes)) if adjacent && *charset == word.charset => {
+                bytes.extend(word.bytes);
+            }
+            _ => {
+                flush(&mut decoded, &mut pending);
+                if !adjacent {
+                    decoded.push_str(before);
+                }
+                pending = Some((word.charset, word.bytes));
+            }
+        }
+        rest = &candidate[len..];
+    }
+
+    flush(&mut decoded, &mut pending);
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Parses a `Date` header.
+///
+/// Tries RFC 2822 first, then common malformed variants: a missing or
+/// misspelled day of the week, zone names like `CEST`, `+01:00` offsets,
+/// two-digit years, asctime and ISO 8601 layouts. A missing zone is taken
+/// as UTC. Returns `None` rather than guessing when nothing matches.
+pub fn parse_email_date(raw: &str) -> Option<DateTime<Utc>> {
+    let cleaned = clean_date(raw);
+    if let Ok(date) = DateTime::parse_from_rfc2822(&cleaned) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(&cleaned) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    let mut tokens: Vec<&str> = cleaned
+        .split([' ', ','])
+        .filter(|token| !token.is_empty())
+        .collect();
+    // The day of the week is redundant and often wrong.
+    if tokens.first().is_some_and(|token| is_weekday(token)) {
+        tokens.remove(0);
+    }
+    let offset = match tokens.last().and_then(|token| zone_offset(token)) {
+        Some(offset) => {
+            tokens.pop();
+            offset
+        }
+        None => 0,
+    };
+
+    let rest = tokens.join(" ");
+    let mut naive = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&rest, format).ok())?;
+    if naive.year() < 100 {
+        let century = if naive.year() < 50 { 2000 } else { 1900 };
+        naive = naive.with_year(naive.year() + century)?;
+    }
+    FixedOffset::east_opt(offset)?
+        .from_local_datetime(&naive)
+        .single()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Unfolds a date and removes comments such as `(PST)`.
+fn clean_date(raw: &str) -> String {
+    let mut cleaned = String::with_capacity(raw.len());
+    let mut depth = 0usize;
+    for c in raw.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => cleaned.push(c),
+            _ => {}
+        }
+    }
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_weekday(token: &str) -> bool {
+    let token = token.to_ascii_lowercase();
+    token.len() >= 3
+        && token.chars().all(|c| c.is_ascii_alphabetic())
+        && ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+            .iter()
+            .any(|day| token.starts_with(day))
+}
+
+/// Offset in seconds of a zone like `-0800`, `+01:00` or `CEST`.
+fn zone_offset(token: &str) -> Option<i32> {
+    if let Some((_, hours)) = ZONE_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(token))
+    {
+        return Some(hours * 3600);
+    }
+
+    let sign = match token.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = token[1..].replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// A parsed `=?charset?encoding?text?=` word.
+struct EncodedWord {
+    charset: String,
+    bytes: Vec<u8>,
+}
+
+impl EncodedWord {
+    /// Parses the encoded-word at the start of `s`, returning it and its
+    /// length.
+    fn parse(s: &str) -> Option<(Self, usize)> {
+        let body = s.strip_prefix("=?")?;
+        let (charset, body) = body.split_once('?')?;
+        let (encoding, body) = body.split_once('?')?;
+        let end = body.find("?=")?;
+        let text = &body[..end];
+        if charset.is_empty() || text.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let bytes = match encoding {
+            "B" | "b" => BASE64.decode(text).ok()?,
+            "Q" | "q" => decode_q(text)?,
+            _ => return None,
+        };
+        // RFC 2231 allows a language after the charset, as in `UTF-8*en`.
+        let charset = charset.split('*').next().unwrap_or_default();
+        let len = s.len() - body.len() + end + 2;
+        Some((
+            Self {
+                charset: charset.to_ascii_lowercase(),
+                bytes,
+            },
+            len,
+        ))
+    }
+}
+
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [input.next()?, input.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    Some(bytes)
+}
+
+fn flush(decoded: &mut String, pending: &mut Option<(String, Vec<u8>)>) {
+    if let Some((charset, bytes)) = pending.take() {
+        decoded.push_str(&decode_charset(&charset, &bytes));
+    }
+}
+
+/// Decodes bytes in a charset. Unknown charsets are read as UTF-8.
+pub(crate) fn decode_charset(charset: &str, bytes: &[u8]) -> String {
+    match charset {
+        "iso-8859-1" | "latin1" | "l1" => bytes.iter().map(|&b| char::from(b)).collect(),
+        "windows-1252" | "cp1252" => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+                _ => char::from(b),
+            })
+            .collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_q_and_b_words() {
+        assert_eq!(decode_header("=?UTF-8?Q?Andr=C3=A9?="), "André");
+        assert_eq!(
+            decode_header("=?utf-8?B?w4lxdWlwZSBkZSB2ZW50ZQ==?="),
+            "Équipe de vente"
+        );
+        assert_eq!(
+            decode_header("=?ISO-8859-1?Q?Fran=E7ois_Dupont?= <francois@example.com>"),
+            "François Dupont <francois@example.com>"
+        );
+        assert_eq!(decode_header("=?windows-1252?Q?=93Hi=94?="), "“Hi”");
+    }
+
+    #[test]
+    fn joins_adjacent_words_across_folded_lines() {
+        // A subject folded across lines, with "’" split between the first
+        // two words by the sender's mail client.
+        let folded = "=?UTF-8?B?UsOpdW5pb24gZGUgbOKA?=\r\n =?UTF-8?B?mcOpcXVpcGUg4oCUIG9y?=\r\n\t\
+            =?UTF-8?B?ZHJlIGR1IGpvdXI=?=";
+        assert_eq!(decode_header(folded), "Réunion de l’équipe — ordre du jour");
+
+        // Whitespace around plain text is kept.
+        assert_eq!(
+            decode_header("Re: =?UTF-8?Q?caf=C3=A9?= =?UTF-8?Q?_tonight?= at 8"),
+            "Re: café tonight at 8"
+        );
+    }
+
+    #[test]
+    fn leaves_plain_and_malformed_text_alone() {
+        assert_eq!(decode_header("Plain subject"), "Plain subject");
+        assert_eq!(decode_header("Is 2+2 =?"), "Is 2+2 =?");
+        assert_eq!(decode_header("=?UTF-8?X?abc?="), "=?UTF-8?X?abc?=");
+        assert_eq!(decode_header("=?UTF-8?Q?a b?="), "=?UTF-8?Q?a b?=");
+        assert_eq!(decode_header("=?UTF-8*en?Q?Hello?="), "Hello");
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_well_formed_dates() {
+        assert_eq!(
+            parse_email_date("Tue, 1 Jul 2003 10:52:37 +0200"),
+            Some(utc("2003-07-01T08:52:37Z"))
+        );
+        assert_eq!(
+            parse_email_date("Mon, 2 Jan 2006 15:04:05 -0700 (MST)"),
+            Some(utc("2006-01-02T22:04:05Z"))
+        );
+        assert_eq!(
+            parse_email_date("Tue, 5 Mar 2024\r\n 10:00:00 GMT"),
+            Some(utc("2024-03-05T10:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn parses_malformed_dates() {
+        let cases = [
+            // Wrong day of the week.
+            ("Mon, 5 Mar 2024 10:00:00 +0000", "2024-03-05T10:00:00Z"),
+            // Zone names RFC 2822 doesn't know.
+            ("Wed, 17 Jan 2024 09:30:00 UTC", "2024-01-17T09:30:00Z"),
+            ("Fri, 15 Mar 2024 14:00:00 CEST", "2024-03-15T12:00:00Z"),
+            // Missing zone.
+            ("Mon, 4 Mar 2024 08:15:00", "2024-03-04T08:15:00Z"),
+            // Missing day of the week and seconds.
+            ("4 Mar 2024 08:15 -0800", "2024-03-04T16:15:00Z"),
+            // Offset with a colon.
+            ("Thu, 07 Mar 2024 16:20:11 +01:00", "2024-03-07T15:20:11Z"),
+            // Spelled-out names.
+            (
+                "Sunday, 10 March 2024 12:00:00 -0500",
+                "2024-03-10T17:00:00Z",
+            ),
+            // asctime.
+            ("Tue Mar  5 10:00:00 2024", "2024-03-05T10:00:00Z"),
+            // ISO 8601.
+            ("2024-03-05 10:00:00 +0000", "2024-03-05T10:00:00Z"),
+            ("2024-03-05T10:00:00+02:00", "2024-03-05T08:00:00Z"),
+            // Two-digit year.
+            ("Tue, 5 Mar 24 10:00:00 GMT", "2024-03-05T10:00:00Z"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_email_date(raw), Some(utc(expected)), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn rejects_unparseable_dates() {
+        assert_eq!(parse_email_date(""), None);
+        assert_eq!(parse_email_date("sometime last week"), None);
+        assert_eq!(parse_email_date("Thu, 29 Feb 2023 10:00:00 +0000"), None);
+        assert_eq!(parse_email_date("Tue, 5 Mar 2024 25:00:00 +0000"), None);
+    }
+}
@@ -0,0 +1,806 @@
+//! Streaming MIME parsing.
+//!
+//! Large messages are fetched from the server in slices and fed to a
+//! [`MessageStream`] as they arrive. The header block and text bodies are
+//! kept; other parts are only measured, or decoded into a writer when asked
+//! for, so the memory held is bounded by the limits below rather than by
+//! the size of the message.
+
+use std::io::{self, Write};
+
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+
+use super::headers::{decode_charset, decode_header};
+use crate::domain::normalize_content_id;
+
+/// Most header bytes kept for the message or a part; the rest are dropped.
+pub const MAX_HEADER_BYTES: usize = 256 * 1024;
+
+/// Most decoded bytes kept for each text body; the rest are dropped.
+pub const MAX_TEXT_BYTES: usize = 1024 * 1024;
+
+/// Most bytes of a line held before they are passed on without waiting for
+/// the line to end.
+pub const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Base64 as found in the wild: padding is optional.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// A body part other than the text bodies, such as an attachment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamedPart {
+    /// IMAP section number, such as `2` or `1.3`.
+    pub section: String,
+    /// Lowercase MIME type, such as `application/pdf`.
+    pub content_type: String,
+    pub filename: Option<String>,
+    /// Content-ID without angle brackets.
+    pub content_id: Option<String>,
+    pub is_inline: bool,
+    /// Size once decoded.
+    pub size_bytes: u64,
+}
+
+/// What a [`MessageStream`] keeps of a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamedMessage {
+    /// The message's header block, for parsing with `mail_parser`.
+    pub headers: Vec<u8>,
+    /// The first text/plain part that isn't an attachment.
+    pub body_text: Option<String>,
+    /// The first text/html part that isn't an attachment.
+    pub body_html: Option<String>,
+    /// The other leaf parts, in order.
+    pub parts: Vec<StreamedPart>,
+}
+
+/// Parses a message fed to it in slices of any size.
+pub struct MessageStream<'a> {
+    message: StreamedMessage,
+    state: State,
+    /// Open multiparts, outermost first.
+    multiparts: Vec<Multipart>,
+    /// The current line, up to [`MAX_LINE_BYTES`].
+    line: Vec<u8>,
+    /// Whether the start of the current line was already passed on.
+    mid_line: bool,
+    /// Decoded bytes of the current line.
+    decoded: Vec<u8>,
+    /// The part to decode into a writer, by section.
+    extract: Option<(String, &'a mut (dyn Write + Send))>,
+    error: Option<io::Error>,
+    peak: usize,
+}
+
+enum State {
+    /// Reading the headers of the part at `section`, empty for the message.
+    Headers { section: String, block: Vec<u8> },
+    /// Reading the content of a leaf part.
+    Body(Leaf),
+    /// In a multipart's preamble or epilogue, which are ignored.
+    Between,
+}
+
+struct Multipart {
+    /// `--` followed by the boundary.
+    delimiter: Vec<u8>,
+    section: String,
+    parts: usize,
+}
+
+struct Leaf {
+    target: Target,
+    decoder: Decoder,
+    /// The last line's break, which belongs to a following delimiter if
+    /// one comes next.
+    held_break: Vec<u8>,
+}
+
+enum Target {
+    Text {
+        html: bool,
+        charset: String,
+        bytes: Vec<u8>,
+    },
+    /// Index into the message's parts.
+    Part(usize),
+}
+
+impl<'a> MessageStream<'a> {
+    /// Creates a stream that keeps the message's headers and text bodies.
+    pub fn new() -> Self {
+        Self {
+            message: StreamedMessage::default(),
+            state: State::Headers {
+                section: String::new(),
+                block: vec![],
+            },
+            multiparts: vec![],
+            line: vec![],
+            mid_line: false,
+            decoded: vec![],
+            extract: None,
+            error: None,
+            peak: 0,
+        }
+    }
+
+    /// Creates a stream that also decodes the part at `section` into `out`
+    /// as it arrives.
+    pub fn extracting(section: impl Into<String>, out: &'a mut (dyn Write + Send)) -> Self {
+        Self {
+            extract: Some((section.into(), out)),
+            ..Self::new()
+        }
+    }
+
+    /// Parses the next slice of the message.
+    pub fn feed(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let end = bytes
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |i| i + 1);
+            let take = end.min(MAX_LINE_BYTES - self.line.len());
+            self.line.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            self.peak = self.peak.max(self.buffered());
+
+            if self.line.ends_with(b"\n") || self.line.len() == MAX_LINE_BYTES {
+                // A full line ending in "\r" keeps it for the "\n" that may
+                // follow, so the line break isn't split.
+                let keep = usize::from(!self.line.ends_with(b"\n") && self.line.ends_with(b"\r"));
+                let mut line = std::mem::take(&mut self.line);
+                self.line = line.split_off(line.len() - keep);
+                self.process(&line);
+            }
+        }
+    }
+
+    /// Finishes parsing, returning what was kept of the message or the
+    /// first error writing the extracted part.
+    pub fn finish(mut self) -> io::Result<StreamedMessage> {
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.process(&line);
+        }
+        self.end_part();
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.message),
+        }
+    }
+
+    /// Returns the most bytes held at once while parsing.
+    pub fn peak_buffered(&self) -> usize {
+        self.peak
+    }
+
+    fn buffered(&self) -> usize {
+        let state = match &self.state {
+            State::Headers { block, .. } => block.len(),
+            State::Body(leaf) => match &leaf.target {
+                Target::Text { bytes, .. } => bytes.len(),
+                Target::Part(_) => 0,
+            },
+            State::Between => 0,
+        };
+        self.line.len() + self.decoded.len() + self.message.headers.len() + state
+    }
+
+    fn process(&mut self, line: &[u8]) {
+        let starts_line = !self.mid_line;
+        let (data, line_break) = split_line_break(line);
+        self.mid_line = line_break.is_empty();
+
+        if starts_line {
+            if let Some((depth, closing)) = self.delimiter(data) {
+                self.end_part();
+                self.multiparts.truncate(depth + 1);
+                self.state = if closing {
+                    self.multiparts.pop();
+                    State::Between
+                } else {
+                    let multipart = &mut self.multiparts[depth];
+                    multipart.parts += 1;
+                    State::Headers {
+                        section: child_section(&multipart.section, multipart.parts),
+                        block: vec![],
+                    }
+                };
+                return;
+            }
+        }
+
+        match &mut self.state {
+            State::Headers { block, .. } => {
+                if starts_line && data.is_empty() && !line_break.is_empty() {
+                    self.end_headers();
+                } else {
+                    let room = MAX_HEADER_BYTES.saturating_sub(block.len());
+                    block.extend_from_slice(&line[..line.len().min(room)]);
+                }
+            }
+            State::Body(leaf) => {
+                leaf.push(data, line_break, &mut self.decoded);
+                deliver(
+                    &mut leaf.target,
+                    &self.decoded,
+                    &mut self.message,
+                    &mut self.extract,
+                    &mut self.error,
+                );
+                self.peak = self.peak.max(line.len() + self.buffered());
+                self.decoded.clear();
+            }
+            State::Between => {}
+        }
+    }
+
+    /// Returns the depth of the multipart whose delimiter `data` is, and
+    /// whether it closes the multipart.
+    fn delimiter(&self, data: &[u8]) -> Option<(usize, bool)> {
+        if !data.starts_with(b"--") {
+            return None;
+        }
+        let end = data
+            .iter()
+            .rposition(|b| !b" \t".contains(b))
+            .map_or(0, |i| i + 1);
+        let data = &data[..end];
+        self.multiparts
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, multipart)| {
+                match data.strip_prefix(multipart.delimiter.as_slice())? {
+                    b"" => Some((depth, false)),
+                    b"--" => Some((depth, true)),
+                    _ => None,
+                }
+            })
+    }
+
+    /// Starts reading the content of the part whose headers just ended.
+    fn end_headers(&mut self) {
+        let State::Headers { section, block } = std::mem::replace(&mut self.state, State::Between)
+        else {
+            return;
+        };
+        let headers = PartHeaders::parse(&block);
+        if section.is_empty() {
+            self.message.headers = block;
+        }
+
+        if headers.content_type.starts_with("multipart/") {
+            if let Some(boundary) = headers.param("boundary") {
+                self.multiparts.push(Multipart {
+                    delimiter: [b"--", boundary.as_bytes()].concat(),
+                    section,
+                    parts: 0,
+                });
+                return;
+            }
+        }
+
+        let section = if section.is_empty() {
+            "1".to_string()
+        } else {
+            section
+        };
+        let is_attachment = headers.disposition == "attachment" || headers.filename().is_some();
+        let text = match headers.content_type.as_str() {
+            "text/plain" if !is_attachment && self.message.body_text.is_none() => Some(false),
+            "text/html" if !is_attachment && self.message.body_html.is_none() => Some(true),
+            _ => None,
+        };
+        let target = match text {
+            Some(html) => Target::Text {
+                html,
+                charset: headers.param("charset").unwrap_or("utf-8").to_lowercase(),
+                bytes: vec![],
+            },
+            None => {
+                self.message.parts.push(StreamedPart {
+                    section,
+                    filename: headers.filename(),
+                    content_id: headers.content_id.as_deref().map(normalize_content_id),
+                    is_inline: headers.disposition == "inline"
+                        || (headers.content_id.is_some() && !is_attachment),
+                    content_type: headers.content_type,
+                    size_bytes: 0,
+                });
+                Target::Part(self.message.parts.len() - 1)
+            }
+        };
+        self.state = State::Body(Leaf {
+            target,
+            decoder: Decoder::new(&headers.encoding),
+            held_break: vec![],
+        });
+    }
+
+    /// Finishes the current part at a delimiter or the end of the message.
+    fn end_part(&mut self) {
+        if let State::Headers { .. } = self.state {
+            // A part with no content.
+            self.end_headers();
+        }
+        let State::Body(mut leaf) = std::mem::replace(&mut self.state, State::Between) else {
+            return;
+        };
+        leaf.decoder.finish(&mut self.decoded);
+        deliver(
+            &mut leaf.target,
+            &self.decoded,
+            &mut self.message,
+            &mut self.extract,
+            &mut self.error,
+        );
+        self.decoded.clear();
+        if let Target::Text {
+            html,
+            charset,
+            bytes,
+        } = leaf.target
+        {
+            let text = decode_charset(&charset, &bytes);
+            if html {
+                self.message.body_html = Some(text);
+            } else {
+                self.message.body_text = Some(text);
+            }
+        }
+    }
+}
+
+impl Default for MessageStream<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Passes decoded content to where its part goes.
+fn deliver(
+    target: &mut Target,
+    decoded: &[u8],
+    message: &mut StreamedMessage,
+    extract: &mut Option<(String, &mut (dyn Write + Send))>,
+    error: &mut Option<io::Error>,
+) {
+    if decoded.is_empty() {
+        return;
+    }
+    match target {
+        Target::Text { bytes, .. } => {
+            let room = MAX_TEXT_BYTES.saturating_sub(bytes.len());
+            bytes.extend_from_slice(&decoded[..decoded.len().min(room)]);
+        }
+        Target::Part(index) => {
+            let part = &mut message.parts[*index];
+            part.size_bytes += decoded.len() as u64;
+            if let Some((section, out)) = extract {
+                if *section == part.section && error.is_none() {
+                    if let Err(e) = out.write_all(decoded) {
+                        *error = Some(e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Leaf {
+    fn push(&mut self, data: &[u8], line_break: &[u8], out: &mut Vec<u8>) {
+        if !self.held_break.is_empty() {
+            self.decoder.line_break(&self.held_break, out);
+            self.held_break.clear();
+        }
+        self.decoder.data(data, out);
+        self.held_break.extend_from_slice(line_break);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Identity,
+    Base64,
+    QuotedPrintable,
+}
+
+/// Decodes a Content-Transfer-Encoding a line at a time.
+struct Decoder {
+    encoding: Encoding,
+    /// Input held until it can be decoded: up to three base64 characters,
+    /// or the start of a quoted-printable escape.
+    carry: Vec<u8>,
+}
+
+impl Decoder {
+    fn new(encoding: &str) -> Self {
+        let encoding = match encoding {
+            "base64" => Encoding::Base64,
+            "quoted-printable" => Encoding::QuotedPrintable,
+            _ => Encoding::Identity,
+        };
+        Self {
+            encoding,
+            carry: vec![],
+        }
+    }
+
+    /// Decodes content from within a line.
+    fn data(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        match self.encoding {
+            Encoding::Identity => out.extend_from_slice(data),
+            Encoding::Base64 => {
+                self.carry.extend(
+                    data.iter()
+                        .filter(|&&b| b.is_ascii_alphanumeric() || b"+/=".contains(&b)),
+                );
+                let whole = self.carry.len() / 4 * 4;
+                // Undecodable input is skipped rather than failing the part.
+                let _ = BASE64.decode_vec(&self.carry[..whole], out);
+                self.carry.drain(..whole);
+            }
+            Encoding::QuotedPrintable => {
+                self.carry.extend_from_slice(data);
+                let mut i = 0;
+                while i < self.carry.len() {
+                    if self.carry[i] != b'=' {
+                        out.push(self.carry[i]);
+                        i += 1;
+                    } else if self.carry.len() - i < 3 {
+                        break;
+                    } else {
+                        match std::str::from_utf8(&self.carry[i + 1..i + 3])
+                            .ok()
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        {
+                            Some(byte) => {
+                                out.push(byte);
+                                i += 3;
+                            }
+                            None => {
+                                out.push(b'=');
+                                i += 1;
+                            }
+                        }
+                    }
+                }
+                self.carry.drain(..i);
+            }
+        }
+    }
+
+    /// Decodes the break between two lines.
+    fn line_break(&mut self, line_break: &[u8], out: &mut Vec<u8>) {
+        match self.encoding {
+            Encoding::Identity => out.extend_from_slice(line_break),
+            Encoding::Base64 => {}
+            Encoding::QuotedPrintable => {
+                // A line ending in "=" is a soft break, joined to the next.
+                if self.carry != b"=" {
+                    out.append(&mut self.carry);
+                    out.extend_from_slice(line_break);
+                }
+                self.carry.clear();
+            }
+        }
+    }
+
+    /// Decodes any input held at the end of the part.
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        match self.encoding {
+            Encoding::Identity => {}
+            Encoding::Base64 => {
+                let _ = BASE64.decode_vec(&self.carry, out);
+            }
+            Encoding::QuotedPrintable => {
+                if self.carry != b"=" {
+                    out.extend_from_slice(&self.carry);
+                }
+            }
+        }
+        self.carry.clear();
+    }
+}
+
+/// The MIME headers of a part.
+#[derive(Debug, Default)]
+struct PartHeaders {
+    /// Lowercase MIME type, `text/plain` when missing.
+    content_type: String,
+    /// Parameters of Content-Type and Content-Disposition, names lowercase.
+    params: Vec<(String, String)>,
+    /// Lowercase disposition type, empty when missing.
+    disposition: String,
+    /// Lowercase Content-Transfer-Encoding.
+    encoding: String,
+    content_id: Option<String>,
+}
+
+impl PartHeaders {
+    fn parse(block: &[u8]) -> Self {
+        let mut headers = Self::default();
+        for (name, value) in header_fields(block) {
+            match name.as_str() {
+                "content-type" => {
+                    let (value, params) = split_params(&value);
+                    headers.content_type = value;
+                    headers.params.extend(params);
+                }
+                "content-disposition" => {
+                    let (value, params) = split_params(&value);
+                    headers.disposition = value;
+                    headers.params.extend(params);
+                }
+                "content-transfer-encoding" => headers.encoding = value.to_lowercase(),
+                "content-id" => headers.content_id = Some(value),
+                _ => {}
+            }
+        }
+        if !headers.content_type.contains('/') {
+            headers.content_type = "text/plain".to_string();
+        }
+        headers
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The part's filename, from Content-Disposition or, failing that, the
+    /// Content-Type `name`.
+    fn filename(&self) -> Option<String> {
+        if let Some(extended) = self.param("filename*") {
+            return Some(decode_extended_value(extended));
+        }
+        self.param("filename")
+            .or_else(|| self.param("name"))
+            .map(decode_header)
+    }
+}
+
+/// Unfolds a header block into lowercase names and trimmed values.
+fn header_fields(block: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(block);
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    fields
+}
+
+/// Splits a value like `text/plain; charset="utf-8"` into its lowercase
+/// value and its parameters, unquoted.
+fn split_params(value: &str) -> (String, Vec<(String, String)>) {
+    let mut pieces = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => pieces.last_mut().unwrap().extend(chars.next()),
+            ';' if !quoted => pieces.push(String::new()),
+            c => pieces.last_mut().unwrap().push(c),
+        }
+    }
+    let value = pieces[0].trim().to_lowercase();
+    let params = pieces[1..]
+        .iter()
+        .filter_map(|piece| piece.split_once('='))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    (value, params)
+}
+
+/// Decodes an RFC 2231 value like `utf-8''r%C3%A9sum%C3%A9.pdf`.
+fn decode_extended_value(value: &str) -> String {
+    let mut fields = value.splitn(3, '\'');
+    let (Some(charset), Some(_language), Some(encoded)) =
+        (fields.next(), fields.next(), fields.next())
+    else {
+        return value.to_string();
+    };
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decode_charset(&charset.to_lowercase(), &decoded)
+}
+
+fn split_line_break(line: &[u8]) -> (&[u8], &[u8]) {
+    let content = line
+        .strip_suffix(b"\r\n")
+        .or_else(|| line.strip_suffix(b"\n"))
+        .unwrap_or(line);
+    line.split_at(content.len())
+}
+
+/// Section number of the `n`th part of the multipart at `section`.
+fn child_section(section: &str, n: usize) -> String {
+    if section.is_empty() {
+        n.to_string()
+    } else {
+        format!("{}.{}", section, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "From: Alice <alice@example.com>\r\n\
+        Subject: Report\r\n\
+        Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+        \r\n\
+        This is a multi-part message.\r\n\
+        --outer\r\n\
+        Content-Type: multipart/alternative; boundary=inner\r\n\
+        \r\n\
+        --inner\r\n\
+        Content-Type: text/plain; charset=iso-8859-1\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        Caf=E9 report, see the long line that wraps here =\r\n\
+        and continues.\r\n\
+        --inner\r\n\
+        Content-Type: text/html\r\n\
+        \r\n\
+        <p>Caf\u{e9} <img src=\"cid:chart@example.com\"></p>\r\n\
+        --inner--\r\n\
+        --outer\r\n\
+        Content-Type: image/png\r\n\
+        Content-ID: <chart@example.com>\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        iVBORw0K\r\n\
+        --outer\r\n\
+        Content-Type: application/pdf; name=\"ignored.pdf\"\r\n\
+        Content-Disposition: attachment;\r\n\
+        \tfilename*=utf-8''r%C3%A9sum%C3%A9.pdf\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        JVBERi0x\r\n\
+        LjQK\r\n\
+        --outer--\r\n";
+
+    fn parse_in_slices(message: &[u8], slice: usize) -> StreamedMessage {
+        let mut stream = MessageStream::new();
+        for chunk in message.chunks(slice) {
+            stream.feed(chunk);
+        }
+        stream.finish().unwrap()
+    }
+
+    #[test]
+    fn keeps_text_bodies_and_measures_other_parts() {
+        let message = parse_in_slices(MESSAGE.as_bytes(), 7);
+
+        assert!(message.headers.starts_with(b"From: Alice"));
+        assert_eq!(
+            message.body_text.as_deref(),
+            Some("Café report, see the long line that wraps here and continues.")
+        );
+        assert_eq!(
+            message.body_html.as_deref(),
+            Some("<p>Café <img src=\"cid:chart@example.com\"></p>")
+        );
+        assert_eq!(
+            message.parts,
+            [
+                StreamedPart {
+                    section: "2".to_string(),
+                    content_type: "image/png".to_string(),
+                    filename: None,
+                    content_id: Some("chart@example.com".to_string()),
+                    is_inline: true,
+                    size_bytes: 6,
+                },
+                StreamedPart {
+                    section: "3".to_string(),
+                    content_type: "application/pdf".to_string(),
+                    filename: Some("résumé.pdf".to_string()),
+                    content_id: None,
+                    is_inline: false,
+                    size_bytes: 9,
+                },
+            ]
+        );
+        // Slicing doesn't change the result.
+        assert_eq!(parse_in_slices(MESSAGE.as_bytes(), 4096), message);
+    }
+
+    #[test]
+    fn extracts_one_part_into_a_writer() {
+        let mut pdf = vec![];
+        let mut stream = MessageStream::extracting("3", &mut pdf);
+        stream.feed(MESSAGE.as_bytes());
+        stream.finish().unwrap();
+
+        assert_eq!(pdf, b"%PDF-1.4\n");
+    }
+
+    #[test]
+    fn single_part_messages_are_section_one() {
+        let message = parse_in_slices(b"Subject: Hi\r\nContent-Type: image/gif\r\n\r\nGIF89a", 3);
+        assert_eq!(message.parts[0].section, "1");
+        assert_eq!(message.parts[0].size_bytes, 6);
+
+        let message = parse_in_slices(b"Subject: Hi\r\n\r\nHello\r\nthere", 5);
+        assert_eq!(message.body_text.as_deref(), Some("Hello\r\nthere"));
+        assert!(message.parts.is_empty());
+    }
+
+    #[test]
+    fn large_messages_are_parsed_in_bounded_memory() {
+        // A 25MB attachment as base64 in 76-column lines, then a binary
+        // part without line breaks, fed in IMAP-sized slices.
+        let attachment: Vec<u8> = (0..25 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&attachment);
+        let mut raw = b"Subject: Big\r\n\
+            Content-Type: multipart/mixed; boundary=b\r\n\r\n\
+            --b\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            See attached.\r\n\
+            --b\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Disposition: attachment; filename=data.bin\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n"
+            .to_vec();
+        for line in encoded.as_bytes().chunks(76) {
+            raw.extend_from_slice(line);
+            raw.extend_from_slice(b"\r\n");
+        }
+        raw.extend_from_slice(b"--b\r\nContent-Type: application/octet-stream\r\n\r\n");
+        raw.extend(std::iter::repeat(b'x').take(4 * 1024 * 1024));
+        raw.extend_from_slice(b"\r\n--b--\r\n");
+
+        let mut extracted = vec![];
+        let mut stream = MessageStream::extracting("2", &mut extracted);
+        for chunk in raw.chunks(256 * 1024) {
+            stream.feed(chunk);
+        }
+        let peak = stream.peak_buffered();
+        let message = stream.finish().unwrap();
+
+        assert!(peak < 256 * 1024, "peak buffer was {} bytes", peak);
+        assert_eq!(message.body_text.as_deref(), Some("See attached."));
+        assert_eq!(message.parts[0].filename.as_deref(), Some("data.bin"));
+        assert_eq!(message.parts[0].size_bytes, attachment.len() as u64);
+        assert_eq!(message.parts[1].size_bytes, 4 * 1024 * 1024);
+        assert!(extracted == attachment);
+    }
+}
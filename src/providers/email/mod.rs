@@ -5,6 +5,7 @@
 //!
 //! - [`GmailProvider`] - Gmail API with OAuth 2.0
 //! - [`ImapProvider`] - Standard IMAP/SMTP
+//! - `FakeEmailProvider` - In-memory mailbox for tests (`fake-provider` feature)
 //!
 //! # Architecture
 //!
@@ -34,13 +35,25 @@
 //! }
 //! ```
 
+mod autodiscover;
+#[cfg(any(test, feature = "fake-provider"))]
+mod fake;
 mod gmail;
+mod headers;
 mod imap;
+mod mime_stream;
+mod oauth;
+mod pool;
 mod traits;
 
-pub use gmail::GmailProvider;
-pub use imap::{ImapConfig, ImapProvider};
+#[cfg(any(test, feature = "fake-provider"))]
+pub use fake::{FakeEmailProvider, MailboxChange};
+pub use gmail::{GmailCredentials, GmailProvider, HistoryId, PushNotification, WatchState};
+pub use headers::{decode_header, parse_email_date};
+pub use imap::{ImapConfig, ImapCredentials, ImapProvider, LocationStore};
+pub use oauth::{open_in_browser, AuthUrl};
 pub use traits::{
-    Change, EmailProvider, EmailUpdate, NewEmailData, OutgoingAttachment, OutgoingEmail,
-    Pagination, PendingChange, PendingChangeType, ProviderError, Result,
+    idempotent_message_id, Change, EmailProvider, EmailUpdate, NewEmailData, OutgoingAttachment,
+    OutgoingEmail, Pagination, PendingChange, PendingChangeType, ProviderCapabilities,
+    ProviderError, Result, SearchCriteria,
 };
@@ -0,0 +1,344 @@
+//! OAuth 2.0 installed-app loopback flow.
+//!
+//! Desktop clients cannot keep a client secret confidential, so Google's
+//! installed-app flow (RFC 8252) redirects the browser to a short-lived HTTP
+//! listener on `127.0.0.1` and protects the authorization code with PKCE
+//! (RFC 7636). This module owns the provider-agnostic parts of that flow:
+//! building the consent URL, running the loopback listener, and validating
+//! the redirect. Token exchange stays with the provider.
+
+use std::time::Duration;
+
+use oauth2::{CsrfToken, PkceCodeChallenge, PkceCodeVerifier};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use url::Url;
+
+use super::{ProviderError, Result};
+
+/// How long to wait for the browser redirect before giving up, so a closed
+/// browser window doesn't leave the flow hanging.
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Authorization URL returned when an OAuth flow is started.
+#[derive(Debug, Clone)]
+pub struct AuthUrl {
+    /// Consent page the user must open in a browser.
+    pub url: String,
+    /// Loopback redirect URI registered for this flow.
+    pub redirect_uri: String,
+}
+
+/// State held between starting an OAuth flow and exchanging the code.
+pub(crate) struct PendingOAuth {
+    /// OAuth client ID.
+    pub client_id: String,
+    /// OAuth client secret.
+    pub client_secret: String,
+    /// Redirect URI sent with the authorization request.
+    pub redirect_uri: String,
+    /// PKCE verifier matching the challenge in the consent URL.
+    pub pkce_verifier: PkceCodeVerifier,
+    /// CSRF state echoed back by the authorization server.
+    csrf_state: String,
+    /// Loopback listener, taken when waiting for the redirect.
+    listener: Option<TcpListener>,
+}
+
+impl PendingOAuth {
+    /// Binds a loopback listener on an ephemeral port and builds the consent URL.
+    pub async fn start(
+        auth_endpoint: &str,
+        client_id: String,
+        client_secret: String,
+        scopes: &[&str],
+    ) -> Result<(Self, AuthUrl)> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| ProviderError::Connection(format!("bind loopback listener: {}", e)))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| ProviderError::Connection(e.to_string()))?
+            .port();
+        let redirect_uri = format!("http://127.0.0.1:{}", port);
+
+        let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+        let csrf_state = CsrfToken::new_random().secret().clone();
+
+        let url = build_auth_url(
+            auth_endpoint,
+            &client_id,
+            &redirect_uri,
+            scopes,
+            &csrf_state,
+            &challenge,
+        )?;
+
+        let pending = Self {
+            client_id,
+            client_secret,
+            redirect_uri: redirect_uri.clone(),
+            pkce_verifier: verifier,
+            csrf_state,
+            listener: Some(listener),
+        };
+
+        Ok((pending, AuthUrl { url, redirect_uri }))
+    }
+
+    /// Waits for the browser redirect and returns the authorization code.
+    ///
+    /// Answers the browser with a short page telling the user to return to
+    /// the app. Fails if the user denied consent, the state does not match,
+    /// or no redirect arrives within [`REDIRECT_TIMEOUT`].
+    pub async fn wait_for_code(&mut self) -> Result<String> {
+        self.wait_for_code_within(REDIRECT_TIMEOUT).await
+    }
+
+    /// Waits for the browser redirect for at most `timeout`.
+    async fn wait_for_code_within(&mut self, timeout: Duration) -> Result<String> {
+        let listener = self
+            .listener
+            .take()
+            .ok_or_else(|| ProviderError::InvalidRequest("redirect already received".into()))?;
+
+        let (mut stream, _) = tokio::time::timeout(timeout, listener.accept())
+            .await
+            .map_err(|_| ProviderError::Authentication("timed out waiting for sign-in".into()))?
+            .map_err(|e| ProviderError::Connection(format!("accept redirect: {}", e)))?;
+
+        let mut request_line = String::new();
+        BufReader::new(&mut stream)
+            .read_line(&mut request_line)
+            .await
+            .map_err(|e| ProviderError::Connection(format!("read redirect: {}", e)))?;
+
+        let result = parse_redirect(&request_line, &self.csrf_state);
+        let body = match &result {
+            Ok(_) => "Authorization complete. You can close this window and return to The Heap.",
+            Err(_) => "Authorization failed. You can close this window and try again.",
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        // The browser may have gone away; the code is still valid.
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+
+        result
+    }
+}
+
+/// Builds the authorization URL with PKCE and offline access.
+fn build_auth_url(
+    auth_endpoint: &str,
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &[&str],
+    state: &str,
+    challenge: &PkceCodeChallenge,
+) -> Result<String> {
+    let url = Url::parse_with_params(
+        auth_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", &scopes.join(" ")),
+            ("state", state),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", challenge.method().as_str()),
+            // Ask for a refresh token, and re-prompt so one is always issued.
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+        ],
+    )
+    .map_err(|e| ProviderError::Internal(format!("invalid auth endpoint: {}", e)))?;
+
+    Ok(url.into())
+}
+
+/// Extracts the authorization code from the redirect's HTTP request line.
+fn parse_redirect(request_line: &str, expected_state: &str) -> Result<String> {
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| ProviderError::InvalidRequest("malformed redirect request".into()))?;
+    let url = Url::parse(&format!("http://127.0.0.1{}", target))
+        .map_err(|e| ProviderError::InvalidRequest(format!("malformed redirect: {}", e)))?;
+
+    let mut code = None;
+    let mut state = None;
+    let mut error = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    if let Some(error) = error {
+        return Err(if error == "access_denied" {
+            ProviderError::Authentication("user denied consent".into())
+        } else {
+            ProviderError::Authentication(format!("authorization failed: {}", error))
+        });
+    }
+
+    if state.as_deref() != Some(expected_state) {
+        return Err(ProviderError::Authentication("oauth state mismatch".into()));
+    }
+
+    code.ok_or_else(|| ProviderError::Authentication("redirect missing authorization code".into()))
+}
+
+/// Opens a URL in the user's default browser.
+pub fn open_in_browser(url: &str) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        use std::os::windows::process::CommandExt;
+
+        // cmd splits an unquoted URL at `&`, dropping every query parameter
+        // after the first, so pass it through quoted and untouched.
+        let mut command = std::process::Command::new("cmd");
+        command
+            .args(["/C", "start", ""])
+            .raw_arg(format!("\"{}\"", url));
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = {
+        #[cfg(target_os = "macos")]
+        let mut command = std::process::Command::new("open");
+        #[cfg(not(target_os = "macos"))]
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(url);
+        command
+    };
+
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| ProviderError::Internal(format!("open browser: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpStream;
+
+    #[test]
+    fn parse_redirect_returns_code() {
+        let line = "GET /?state=abc&code=4%2Fxyz&scope=mail HTTP/1.1\r\n";
+        assert_eq!(parse_redirect(line, "abc").unwrap(), "4/xyz");
+    }
+
+    #[test]
+    fn parse_redirect_denied_consent() {
+        let line = "GET /?error=access_denied&state=abc HTTP/1.1";
+        let err = parse_redirect(line, "abc").unwrap_err();
+        assert!(matches!(err, ProviderError::Authentication(msg) if msg.contains("denied")));
+    }
+
+    #[test]
+    fn parse_redirect_state_mismatch() {
+        let line = "GET /?code=xyz&state=other HTTP/1.1";
+        let err = parse_redirect(line, "abc").unwrap_err();
+        assert!(matches!(err, ProviderError::Authentication(msg) if msg.contains("state")));
+    }
+
+    #[test]
+    fn parse_redirect_missing_code() {
+        let line = "GET /?state=abc HTTP/1.1";
+        assert!(parse_redirect(line, "abc").is_err());
+    }
+
+    #[test]
+    fn parse_redirect_malformed() {
+        assert!(matches!(
+            parse_redirect("", "abc"),
+            Err(ProviderError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn auth_url_includes_pkce_and_offline_access() {
+        let (challenge, _) = PkceCodeChallenge::new_random_sha256();
+        let url = build_auth_url(
+            "https://accounts.example.com/auth",
+            "client-1",
+            "http://127.0.0.1:8080",
+            &["scope-a", "scope-b"],
+            "state-1",
+            &challenge,
+        )
+        .unwrap();
+
+        let parsed = Url::parse(&url).unwrap();
+        let params: std::collections::HashMap<_, _> = parsed.query_pairs().into_owned().collect();
+        assert_eq!(params["client_id"], "client-1");
+        assert_eq!(params["redirect_uri"], "http://127.0.0.1:8080");
+        assert_eq!(params["scope"], "scope-a scope-b");
+        assert_eq!(params["state"], "state-1");
+        assert_eq!(params["code_challenge"], challenge.as_str());
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["access_type"], "offline");
+    }
+
+    #[tokio::test]
+    async fn loopback_listener_receives_code() {
+        let (mut pending, auth_url) = PendingOAuth::start(
+            "https://accounts.example.com/auth",
+            "client-1".into(),
+            "secret".into(),
+            &["scope"],
+        )
+        .await
+        .unwrap();
+
+        let state = pending.csrf_state.clone();
+        let addr = auth_url
+            .redirect_uri
+            .trim_start_matches("http://")
+            .to_string();
+        let browser = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET /?code=abc123&state={} HTTP/1.1\r\n\r\n", state);
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+
+        let code = pending.wait_for_code().await.unwrap();
+        assert_eq!(code, "abc123");
+
+        let response = browser.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(pending.wait_for_code().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wait_for_code_times_out_without_redirect() {
+        let (mut pending, _) = PendingOAuth::start(
+            "https://accounts.example.com/auth",
+            "client-1".into(),
+            "secret".into(),
+            &["scope"],
+        )
+        .await
+        .unwrap();
+
+        let err = pending
+            .wait_for_code_within(Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Authentication(msg) if msg.contains("timed out")));
+    }
+}
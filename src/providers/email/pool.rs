@@ -0,0 +1,241 @@
+//! Connection pooling with transparent reconnection.
+//!
+//! Long-lived protocol sessions (IMAP in particular) are dropped by servers
+//! after a period of inactivity. [`ConnectionPool`] keeps a small set of idle
+//! connections, health-checks each one before handing it out, and replaces
+//! dead ones by reconnecting through a [`Connector`]. The pool size bounds the
+//! number of concurrent connections so parallel folder operations do not
+//! serialize on a single session, without exceeding server connection limits.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use super::{ProviderError, Result};
+
+/// A connection that can report whether it is still usable.
+#[async_trait]
+pub(crate) trait PooledConnection: Send {
+    /// Returns whether the connection is alive (e.g. answers a NOOP).
+    async fn is_alive(&mut self) -> bool;
+}
+
+/// Establishes new authenticated connections for a pool.
+#[async_trait]
+pub(crate) trait Connector: Send + Sync {
+    /// Connection type produced by this connector.
+    type Conn: PooledConnection;
+
+    /// Opens and authenticates a new connection.
+    async fn connect(&self) -> Result<Self::Conn>;
+}
+
+/// A bounded pool of connections with health checks on checkout.
+pub(crate) struct ConnectionPool<C: Connector> {
+    connector: C,
+    idle: Mutex<Vec<C::Conn>>,
+    permits: Semaphore,
+}
+
+impl<C: Connector> ConnectionPool<C> {
+    /// Creates an empty pool allowing up to `max_size` concurrent connections.
+    pub fn new(connector: C, max_size: usize) -> Self {
+        Self {
+            connector,
+            idle: Mutex::new(Vec::new()),
+            permits: Semaphore::new(max_size.max(1)),
+        }
+    }
+
+    /// Adds an already established connection to the idle set.
+    pub fn put(&self, conn: C::Conn) {
+        self.idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(conn);
+    }
+
+    /// Returns the connector used to open new connections.
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    /// Checks out a live connection, reconnecting if the idle one is dead.
+    ///
+    /// Waits if all connections are in use. The connection is returned to
+    /// the pool when the guard is dropped.
+    pub async fn get(&self) -> Result<PooledGuard<'_, C>> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| ProviderError::Connection("connection pool closed".to_string()))?;
+
+        loop {
+            let candidate = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            let Some(mut conn) = candidate else {
+                break;
+            };
+            if conn.is_alive().await {
+                return Ok(PooledGuard::new(self, conn, permit));
+            }
+            tracing::debug!("Discarding dead pooled connection");
+        }
+
+        let conn = self.connector.connect().await?;
+        Ok(PooledGuard::new(self, conn, permit))
+    }
+
+    /// Removes and returns all idle connections, e.g. to close them cleanly.
+    pub fn drain(&self) -> Vec<C::Conn> {
+        std::mem::take(&mut *self.idle.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Returns the number of idle connections.
+    #[cfg(test)]
+    fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+/// A connection checked out of a [`ConnectionPool`].
+pub(crate) struct PooledGuard<'a, C: Connector> {
+    pool: &'a ConnectionPool<C>,
+    conn: Option<C::Conn>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<'a, C: Connector> PooledGuard<'a, C> {
+    fn new(pool: &'a ConnectionPool<C>, conn: C::Conn, permit: SemaphorePermit<'a>) -> Self {
+        Self {
+            pool,
+            conn: Some(conn),
+            _permit: permit,
+        }
+    }
+}
+
+impl<C: Connector> Deref for PooledGuard<'_, C> {
+    type Target = C::Conn;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.as_ref().expect("connection present until drop")
+    }
+}
+
+impl<C: Connector> DerefMut for PooledGuard<'_, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn.as_mut().expect("connection present until drop")
+    }
+}
+
+impl<C: Connector> Drop for PooledGuard<'_, C> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.put(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockConn {
+        id: usize,
+        alive: bool,
+    }
+
+    #[async_trait]
+    impl PooledConnection for MockConn {
+        async fn is_alive(&mut self) -> bool {
+            self.alive
+        }
+    }
+
+    struct MockConnector {
+        connects: AtomicUsize,
+        fail_first: bool,
+    }
+
+    impl MockConnector {
+        fn new(fail_first: bool) -> Self {
+            Self {
+                connects: AtomicUsize::new(0),
+                fail_first,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Connector for MockConnector {
+        type Conn = MockConn;
+
+        async fn connect(&self) -> Result<MockConn> {
+            let attempt = self.connects.fetch_add(1, Ordering::SeqCst);
+            if self.fail_first && attempt == 0 {
+                return Err(ProviderError::Connection("connection refused".to_string()));
+            }
+            Ok(MockConn {
+                id: attempt,
+                alive: true,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_live_connection() {
+        let pool = ConnectionPool::new(MockConnector::new(false), 2);
+
+        let first_id = pool.get().await.unwrap().id;
+        let second_id = pool.get().await.unwrap().id;
+
+        assert_eq!(first_id, second_id);
+        assert_eq!(pool.connector().connects.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.idle_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn reconnects_dead_connection() {
+        let pool = ConnectionPool::new(MockConnector::new(false), 2);
+        pool.put(MockConn {
+            id: 99,
+            alive: false,
+        });
+
+        let conn = pool.get().await.unwrap();
+
+        assert_ne!(conn.id, 99);
+        assert_eq!(pool.connector().connects.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn recovers_after_failed_connect() {
+        let pool = ConnectionPool::new(MockConnector::new(true), 1);
+
+        assert!(matches!(
+            pool.get().await,
+            Err(ProviderError::Connection(_))
+        ));
+
+        // The permit is released on failure, so the retry can proceed.
+        let conn = pool.get().await.unwrap();
+        assert_eq!(conn.id, 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_checkouts_use_separate_connections() {
+        let pool = ConnectionPool::new(MockConnector::new(false), 2);
+
+        let a = pool.get().await.unwrap();
+        let b = pool.get().await.unwrap();
+
+        assert_ne!(a.id, b.id);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 2);
+    }
+}
@@ -9,7 +9,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::domain::{
-    Address, EmailId, Label, LabelId, ProviderType, Thread, ThreadId, ThreadSummary,
+    Address, EmailId, FolderMapping, Label, LabelId, ProviderType, Thread, ThreadId, ThreadSummary,
 };
 
 /// Result type alias for email provider operations.
@@ -77,6 +77,65 @@ impl Pagination {
     }
 }
 
+/// Structured criteria for server-side search.
+///
+/// All set fields must match. An empty criteria matches every message.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchCriteria {
+    /// Substring of the sender address or name.
+    pub from: Option<String>,
+    /// Substring of the subject.
+    pub subject: Option<String>,
+    /// Only messages dated on or after this day.
+    pub since: Option<DateTime<Utc>>,
+    /// Only unread messages.
+    pub unseen: bool,
+    /// Substring of the headers or body.
+    pub text: Option<String>,
+    /// Maximum number of results.
+    pub limit: Option<u32>,
+}
+
+impl SearchCriteria {
+    /// Creates criteria matching `text` anywhere in the message.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the sender filter.
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Sets the subject filter.
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Restricts results to messages on or after `since`.
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Restricts results to unread messages.
+    pub fn unseen_only(mut self) -> Self {
+        self.unseen = true;
+        self
+    }
+
+    /// Sets the maximum number of results.
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
 /// A change detected during sync.
 ///
 /// Used by the sync service to apply incremental updates to local storage.
@@ -171,6 +230,11 @@ pub enum PendingChangeType {
         /// Thread IDs to trash.
         thread_ids: Vec<ThreadId>,
     },
+    /// Report thread(s) as spam.
+    ReportSpam {
+        /// Thread IDs to report.
+        thread_ids: Vec<ThreadId>,
+    },
     /// Apply a label to thread(s).
     ApplyLabel {
         /// Thread IDs to update.
@@ -195,6 +259,10 @@ pub enum PendingChangeType {
 /// An email to be sent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutgoingEmail {
+    /// Address to send as, one of the account's verified aliases. The
+    /// account's own address is used when `None`.
+    #[serde(default)]
+    pub from: Option<Address>,
     /// Recipient addresses.
     pub to: Vec<Address>,
     /// CC addresses.
@@ -211,8 +279,63 @@ pub struct OutgoingEmail {
     pub in_reply_to_thread: Option<ThreadId>,
     /// Message-ID of the email being replied to.
     pub in_reply_to_message: Option<String>,
+    /// Message-IDs of the conversation so far, oldest first, ending with the
+    /// message being replied to.
+    #[serde(default)]
+    pub references: Vec<String>,
     /// Attachment data.
     pub attachments: Vec<OutgoingAttachment>,
+    /// Client-generated key identifying this send across retries. When
+    /// set, the message's Message-ID is derived from it, so a copy that
+    /// already went out can be recognised.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// Whether to ask recipients for a read receipt with a
+    /// `Disposition-Notification-To` header addressed to the sender.
+    #[serde(default)]
+    pub request_read_receipt: bool,
+    /// Message-ID of the email this is a read receipt for. When set, the
+    /// message is sent as an RFC 8098 disposition notification.
+    #[serde(default)]
+    pub read_receipt_for: Option<String>,
+}
+
+impl OutgoingEmail {
+    /// Returns the Message-ID to send with, if the send has an idempotency
+    /// key. Otherwise the provider assigns one.
+    pub fn message_id(&self) -> Option<String> {
+        self.idempotency_key.as_deref().map(idempotent_message_id)
+    }
+
+    /// Returns the `References` header value, if this is a reply.
+    ///
+    /// Falls back to the `In-Reply-To` message when no chain is known, so
+    /// recipients can still thread the reply (RFC 5322 §3.6.4).
+    pub fn references_header(&self) -> Option<String> {
+        if !self.references.is_empty() {
+            Some(self.references.join(" "))
+        } else {
+            self.in_reply_to_message.clone()
+        }
+    }
+
+    /// Returns the `message/disposition-notification` part (RFC 8098 §3.2)
+    /// of a read receipt sent from `final_recipient`, if this is one.
+    pub fn disposition_notification(&self, final_recipient: &str) -> Option<String> {
+        let original_message_id = self.read_receipt_for.as_deref()?;
+        Some(format!(
+            "Reporting-UA: heap\r\n\
+             Final-Recipient: rfc822;{}\r\n\
+             Original-Message-ID: {}\r\n\
+             Disposition: manual-action/MDN-sent-manually; displayed\r\n",
+            final_recipient, original_message_id
+        ))
+    }
+}
+
+/// Returns the Message-ID of a send with the given idempotency key.
+pub fn idempotent_message_id(idempotency_key: &str) -> String {
+    format!("<{}@heap.local>", idempotency_key)
 }
 
 /// An attachment to be sent with an outgoing email.
@@ -227,6 +350,34 @@ pub struct OutgoingAttachment {
     pub data: Vec<u8>,
 }
 
+/// Features a provider supports, used to enable or disable UI affordances.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderCapabilities {
+    /// Labels are native (a message can carry many); otherwise labels map to folders.
+    pub native_labels: bool,
+    /// The server can search messages, so queries need not be local-only.
+    pub server_search: bool,
+    /// The server pushes changes (Pub/Sub, IMAP IDLE) instead of requiring polling.
+    pub push: bool,
+    /// The server groups messages into threads.
+    pub threads: bool,
+    /// Messages can be moved between folders atomically.
+    pub move_supported: bool,
+}
+
+impl ProviderCapabilities {
+    /// Returns whether a pending change can be applied faithfully.
+    ///
+    /// Removing a label only has meaning with native labels; folder-based
+    /// providers would have to move the message instead.
+    pub fn supports(&self, change: &PendingChangeType) -> bool {
+        match change {
+            PendingChangeType::RemoveLabel { .. } => self.native_labels,
+            _ => true,
+        }
+    }
+}
+
 mod base64_serde {
     use base64::{engine::general_purpose::STANDARD, Engine};
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -278,6 +429,27 @@ pub trait EmailProvider: Send + Sync {
     /// Returns the type of this provider.
     fn provider_type(&self) -> ProviderType;
 
+    /// Returns the features this provider supports.
+    ///
+    /// May change after [`authenticate`](Self::authenticate) for providers
+    /// that discover features from the server.
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    /// Returns where the special folders are, to store on the account once
+    /// [`authenticate`](Self::authenticate) has discovered them. The default
+    /// returns `None`, for providers without folders to map.
+    fn special_folders(&self) -> Option<FolderMapping> {
+        None
+    }
+
+    /// Returns the verified addresses the account can send as besides its
+    /// own, to store on the account once [`authenticate`](Self::authenticate)
+    /// has fetched them. The default returns `None`, for providers that
+    /// don't list aliases.
+    fn send_as_aliases(&self) -> Option<Vec<Address>> {
+        None
+    }
+
     /// Authenticates with the email provider.
     ///
     /// For OAuth-based providers (Gmail), this may refresh tokens if needed.
@@ -288,6 +460,15 @@ pub trait EmailProvider: Send + Sync {
     /// Returns [`ProviderError::Authentication`] if credentials are invalid or expired.
     async fn authenticate(&mut self) -> Result<()>;
 
+    /// Ends the session with the provider.
+    ///
+    /// IMAP providers log out of their open sessions; OAuth-based providers
+    /// drop their access token. The provider is unauthenticated afterwards
+    /// and can be signed in again with [`authenticate`](Self::authenticate).
+    /// Call this before dropping an authenticated provider so the server
+    /// doesn't keep the session open.
+    async fn disconnect(&mut self) -> Result<()>;
+
     /// Fetches thread summaries from a folder.
     ///
     /// # Arguments
@@ -315,6 +496,62 @@ pub trait EmailProvider: Send + Sync {
     /// Returns [`ProviderError::NotFound`] if the thread does not exist.
     async fn fetch_thread(&self, thread_id: &str) -> Result<Thread>;
 
+    /// Searches a folder on the server, so mail that hasn't been downloaded
+    /// can be found.
+    ///
+    /// The default fails with [`ProviderError::Provider`], for providers
+    /// without server search; callers fall back to local search.
+    async fn search(&self, folder: &str, _criteria: &SearchCriteria) -> Result<Vec<ThreadSummary>> {
+        Err(ProviderError::Provider(format!(
+            "server search is not supported: {}",
+            folder
+        )))
+    }
+
+    /// Fetches a message's RFC 822 source, headers and MIME parts as the
+    /// server has them, byte for byte. Decode it only for display: sources
+    /// need not be UTF-8.
+    ///
+    /// Nothing is stored; call it when the source is needed. Fetching the
+    /// source doesn't mark the message read.
+    ///
+    /// # Arguments
+    ///
+    /// * `email_id` - ID of the message, as in [`Email::id`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProviderError::NotFound`] if the message does not exist.
+    ///
+    /// [`Email::id`]: crate::domain::Email::id
+    async fn fetch_raw(&self, email_id: &str) -> Result<Vec<u8>>;
+
+    /// Downloads an attachment of a message into `out`, decoded, returning
+    /// its size.
+    ///
+    /// The content is written as it arrives, so a large attachment can go
+    /// straight to the blob store. The default fails, for providers that
+    /// can't download attachments.
+    ///
+    /// # Arguments
+    ///
+    /// * `email_id` - ID of the message, as in [`Email::id`]
+    /// * `attachment_id` - ID of the attachment, as in [`Attachment::id`]
+    ///
+    /// [`Email::id`]: crate::domain::Email::id
+    /// [`Attachment::id`]: crate::domain::Attachment::id
+    async fn fetch_attachment(
+        &self,
+        _email_id: &str,
+        attachment_id: &str,
+        _out: &mut (dyn std::io::Write + Send),
+    ) -> Result<u64> {
+        Err(ProviderError::InvalidRequest(format!(
+            "attachment downloads are not supported: {}",
+            attachment_id
+        )))
+    }
+
     /// Fetches changes since a given timestamp.
     ///
     /// Used for incremental sync to detect new emails, updates, and deletions.
@@ -355,6 +592,23 @@ pub trait EmailProvider: Send + Sync {
     /// * `thread_ids` - IDs of threads to trash
     async fn trash(&self, thread_ids: &[String]) -> Result<()>;
 
+    /// Permanently deletes the specified threads, bypassing the trash.
+    ///
+    /// Used to purge trash and spam once they pass their retention. The
+    /// threads can't be recovered afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - IDs of threads to delete
+    async fn delete_permanently(&self, thread_ids: &[String]) -> Result<()>;
+
+    /// Reports the specified threads as spam, moving them out of the inbox.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - IDs of threads to report
+    async fn report_spam(&self, thread_ids: &[String]) -> Result<()>;
+
     /// Stars or unstars a thread.
     ///
     /// # Arguments
@@ -379,6 +633,38 @@ pub trait EmailProvider: Send + Sync {
     /// * `label` - Label name or ID to apply
     async fn apply_label(&self, thread_id: &str, label: &str) -> Result<()>;
 
+    /// Marks several threads as read or unread.
+    ///
+    /// The default marks each thread in turn; providers that can batch the
+    /// change into fewer server calls override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - IDs of the threads
+    /// * `read` - `true` to mark as read, `false` to mark as unread
+    async fn mark_read_many(&self, thread_ids: &[String], read: bool) -> Result<()> {
+        for thread_id in thread_ids {
+            self.mark_read(thread_id, read).await?;
+        }
+        Ok(())
+    }
+
+    /// Applies a label to several threads.
+    ///
+    /// The default labels each thread in turn; providers that can batch the
+    /// change into fewer server calls override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - IDs of the threads
+    /// * `label` - Label name or ID to apply
+    async fn apply_label_many(&self, thread_ids: &[String], label: &str) -> Result<()> {
+        for thread_id in thread_ids {
+            self.apply_label(thread_id, label).await?;
+        }
+        Ok(())
+    }
+
     /// Fetches all labels for this account.
     ///
     /// # Returns
@@ -588,6 +874,7 @@ mod tests {
     #[test]
     fn outgoing_email_serialization() {
         let email = OutgoingEmail {
+            from: None,
             to: vec![Address::with_name("recipient@example.com", "Recipient")],
             cc: vec![],
             bcc: vec![],
@@ -596,7 +883,11 @@ mod tests {
             body_html: Some("<p>HTML body</p>".to_string()),
             in_reply_to_thread: None,
             in_reply_to_message: None,
+            references: vec![],
             attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: false,
+            read_receipt_for: None,
         };
 
         let json = serde_json::to_string(&email).unwrap();
@@ -610,6 +901,7 @@ mod tests {
     #[test]
     fn outgoing_email_with_reply_serialization() {
         let email = OutgoingEmail {
+            from: None,
             to: vec![Address::new("recipient@example.com")],
             cc: vec![],
             bcc: vec![],
@@ -618,7 +910,11 @@ mod tests {
             body_html: None,
             in_reply_to_thread: Some(ThreadId::from("thread-1")),
             in_reply_to_message: Some("<original@example.com>".to_string()),
+            references: vec![],
             attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: false,
+            read_receipt_for: None,
         };
 
         let json = serde_json::to_string(&email).unwrap();
@@ -626,6 +922,35 @@ mod tests {
 
         assert!(deserialized.in_reply_to_thread.is_some());
         assert!(deserialized.in_reply_to_message.is_some());
+        assert_eq!(
+            deserialized.references_header().as_deref(),
+            Some("<original@example.com>")
+        );
+    }
+
+    #[test]
+    fn references_header_joins_chain() {
+        let email = OutgoingEmail {
+            from: None,
+            to: vec![],
+            cc: vec![],
+            bcc: vec![],
+            subject: "Re: Plans".to_string(),
+            body_text: String::new(),
+            body_html: None,
+            in_reply_to_thread: None,
+            in_reply_to_message: Some("<b@example.com>".to_string()),
+            references: vec!["<a@example.com>".to_string(), "<b@example.com>".to_string()],
+            attachments: vec![],
+            idempotency_key: None,
+            request_read_receipt: false,
+            read_receipt_for: None,
+        };
+
+        assert_eq!(
+            email.references_header().as_deref(),
+            Some("<a@example.com> <b@example.com>")
+        );
     }
 
     #[test]
@@ -657,6 +982,27 @@ mod tests {
         assert!(not_found.to_string().contains("not found"));
     }
 
+    #[test]
+    fn capabilities_gate_remove_label() {
+        let remove = PendingChangeType::RemoveLabel {
+            thread_ids: vec![ThreadId::from("thread-1")],
+            label_id: LabelId::from("label-1"),
+        };
+        let archive = PendingChangeType::Archive {
+            thread_ids: vec![ThreadId::from("thread-1")],
+        };
+
+        let folders = ProviderCapabilities::default();
+        assert!(!folders.supports(&remove));
+        assert!(folders.supports(&archive));
+
+        let labels = ProviderCapabilities {
+            native_labels: true,
+            ..Default::default()
+        };
+        assert!(labels.supports(&remove));
+    }
+
     #[test]
     fn email_update_partial_fields() {
         let update = EmailUpdate {
@@ -0,0 +1,99 @@
+//! Shared HTTP client construction and connectivity checks.
+//!
+//! Every provider builds its `reqwest::Client` through [`client`] so a hung
+//! connection fails after a bounded time instead of blocking indefinitely.
+
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+
+/// Default bound on a whole request, including reading the response.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Upper bound on establishing a connection.
+const MAX_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host probed by [`is_online`]. Any reliably reachable host works; this one
+/// is what Android uses for its own connectivity check.
+const CONNECTIVITY_PROBE: &str = "connectivitycheck.gstatic.com:80";
+
+/// Timeout for the connectivity probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Builds an HTTP client whose requests fail after `timeout`.
+///
+/// Connecting is bounded separately by the smaller of `timeout` and 10
+/// seconds, so an unreachable host is reported quickly even when long
+/// requests are allowed.
+pub fn client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout(timeout))
+        .timeout(timeout)
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+/// Returns the bound on establishing a connection for requests bounded by
+/// `timeout`: the smaller of `timeout` and 10 seconds.
+pub fn connect_timeout(timeout: Duration) -> Duration {
+    timeout.min(MAX_CONNECT_TIMEOUT)
+}
+
+/// Returns whether the network appears to be reachable.
+///
+/// Opens a TCP connection to a well-known host. The sync scheduler uses this
+/// as a hint for entering offline mode; since the host is blocked on some
+/// networks, a provider that answers overrules it.
+pub async fn is_online() -> bool {
+    is_reachable(CONNECTIVITY_PROBE, PROBE_TIMEOUT).await
+}
+
+/// Returns whether a TCP connection to `addr` succeeds within `timeout`.
+pub async fn is_reachable(addr: &str, timeout: Duration) -> bool {
+    matches!(
+        tokio::time::timeout(timeout, TcpStream::connect(addr)).await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Non-routable address: connections neither succeed nor get refused.
+    const BLACKHOLE: &str = "10.255.255.1";
+
+    #[tokio::test]
+    async fn request_to_unroutable_address_fails_within_timeout() {
+        let timeout = Duration::from_secs(1);
+        let start = Instant::now();
+
+        let result = client(timeout)
+            .get(format!("http://{}/", BLACKHOLE))
+            .send()
+            .await;
+
+        assert!(result.is_err());
+        assert!(start.elapsed() < timeout + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn unroutable_address_is_not_reachable() {
+        let start = Instant::now();
+
+        let reachable =
+            is_reachable(&format!("{}:80", BLACKHOLE), Duration::from_millis(200)).await;
+
+        assert!(!reachable);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn local_listener_is_reachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        assert!(is_reachable(&addr, Duration::from_secs(1)).await);
+    }
+}
@@ -4,6 +4,8 @@
 //!
 //! - [`email`] - Email providers (Gmail API, IMAP/SMTP)
 //! - [`ai`] - AI/LLM providers (OpenAI, Anthropic, Ollama)
+//! - [`http`] - Shared HTTP client with timeouts, and connectivity checks
 
 pub mod ai;
 pub mod email;
+pub mod http;
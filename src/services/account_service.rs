@@ -5,13 +5,25 @@
 //! - Credential storage and retrieval
 //! - Account updates and deletion
 //! - Active account management
+//! - Connection tests before an account is saved
+//! - Tracking accounts whose credentials need to be re-entered
 
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use async_trait::async_trait;
 use thiserror::Error;
 
-use crate::domain::{Account, AccountId, ProviderConfig, ProviderType};
+use crate::domain::{
+    credentials_key, Account, AccountId, Address, CredentialError, CredentialStore, FolderMapping,
+    ProviderConfig, ProviderType,
+};
+use crate::providers::email::{
+    EmailProvider, GmailCredentials, GmailProvider, ImapConfig, ImapCredentials, ImapProvider,
+    ProviderError,
+};
+use crate::storage::Database;
 
 /// Errors that can occur during account operations.
 #[derive(Debug, Error)]
@@ -36,11 +48,42 @@ pub enum AccountError {
     #[error("credential storage error: {0}")]
     CredentialError(String),
 
+    /// The server could not be reached.
+    #[error("server unreachable: {0}")]
+    Unreachable(String),
+
+    /// The server refused the connection, usually because of a wrong port.
+    #[error("connection refused, check the port: {0}")]
+    ConnectionRefused(String),
+
+    /// The provider rejected a request.
+    #[error("provider error: {0}")]
+    Provider(String),
+
     /// Storage error.
     #[error("storage error: {0}")]
     Storage(String),
 }
 
+impl From<ProviderError> for AccountError {
+    fn from(error: ProviderError) -> Self {
+        match error {
+            ProviderError::Authentication(message) => Self::AuthenticationFailed(message),
+            ProviderError::Connection(message) if message.to_lowercase().contains("refused") => {
+                Self::ConnectionRefused(message)
+            }
+            ProviderError::Connection(message) => Self::Unreachable(message),
+            other => Self::Provider(other.to_string()),
+        }
+    }
+}
+
+impl From<CredentialError> for AccountError {
+    fn from(error: CredentialError) -> Self {
+        Self::CredentialError(error.0)
+    }
+}
+
 /// Result type for account operations.
 pub type AccountResult<T> = Result<T, AccountError>;
 
@@ -69,19 +112,62 @@ pub trait AccountStorage: Send + Sync {
     async fn count_accounts(&self) -> AccountResult<u32>;
 }
 
-/// Storage abstraction for credentials.
-#[async_trait]
-pub trait CredentialStore: Send + Sync {
-    /// Stores a credential.
-    async fn store(&self, key: &str, value: &str) -> AccountResult<()>;
+/// Whether an account's credentials are working.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AccountAuthState {
+    /// The provider accepted the account's credentials.
+    #[default]
+    Ok,
+    /// The provider rejected the credentials, e.g. because a refresh token
+    /// was revoked. The user must sign in again.
+    NeedsReauth,
+    /// The provider failed for another reason that won't clear up by
+    /// retrying.
+    Error(String),
+}
+
+impl AccountAuthState {
+    /// Returns whether the account should be badged for the user's
+    /// attention.
+    pub fn needs_attention(&self) -> bool {
+        !matches!(self, Self::Ok)
+    }
+}
 
-    /// Retrieves a credential.
-    async fn retrieve(&self, key: &str) -> AccountResult<Option<String>>;
+/// Records the outcome of provider calls made outside the account service,
+/// such as during sync, in the accounts' [`AccountAuthState`].
+pub trait AuthStateReporter: Send + Sync {
+    /// Records a provider failure for an account.
+    fn report_provider_error(&self, account_id: &AccountId, error: &ProviderError);
 
-    /// Deletes a credential.
-    async fn delete(&self, key: &str) -> AccountResult<()>;
+    /// Records that a provider accepted an account's credentials.
+    fn report_provider_success(&self, account_id: &AccountId);
 }
 
+/// Credentials entered for an account that hasn't been saved yet, or new
+/// credentials for one that needs to sign in again.
+#[derive(Clone)]
+pub enum AccountCredentials {
+    /// IMAP password or app-specific password.
+    Password(String),
+    /// Gmail OAuth client and refresh token.
+    OAuth {
+        /// OAuth refresh token.
+        refresh_token: String,
+        /// OAuth client ID.
+        client_id: String,
+        /// OAuth client secret.
+        client_secret: String,
+    },
+}
+
+/// Builds an unauthenticated provider for an account request.
+pub type ProviderFactory = Arc<
+    dyn Fn(&CreateAccountRequest, &AccountCredentials) -> AccountResult<Box<dyn EmailProvider>>
+        + Send
+        + Sync,
+>;
+
 /// Request to create a new account.
 #[derive(Debug, Clone)]
 pub struct CreateAccountRequest {
@@ -99,6 +185,10 @@ pub struct CreateAccountRequest {
     pub sync_interval: Duration,
     /// Email signature.
     pub signature: Option<String>,
+    /// HTML variant of the signature.
+    pub signature_html: Option<String>,
+    /// Where the special folders are, for servers that don't flag them.
+    pub folder_mapping: FolderMapping,
 }
 
 impl CreateAccountRequest {
@@ -112,6 +202,8 @@ impl CreateAccountRequest {
             sync_enabled: true,
             sync_interval: Duration::from_secs(300),
             signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
         }
     }
 
@@ -131,13 +223,48 @@ impl CreateAccountRequest {
                 smtp_host: smtp_host.into(),
                 smtp_port: 587,
                 use_tls: true,
+                allow_plaintext: false,
+                tls_root_cert: None,
+                accept_invalid_certs: false,
             },
             sync_enabled: true,
             sync_interval: Duration::from_secs(300),
             signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
         }
     }
 
+    /// Creates an IMAP account request with server settings discovered from
+    /// the email address.
+    ///
+    /// Returns `None` if the domain is unknown and settings must be entered
+    /// manually via [`imap`](Self::imap).
+    pub fn imap_auto(email: impl Into<String>) -> Option<Self> {
+        let email = email.into();
+        let config = ImapConfig::discover(&email)?;
+        Some(Self {
+            email,
+            display_name: None,
+            provider_type: ProviderType::Imap,
+            provider_config: ProviderConfig::Imap {
+                imap_host: config.imap_host,
+                imap_port: config.imap_port,
+                smtp_host: config.smtp_host,
+                smtp_port: config.smtp_port,
+                use_tls: config.use_tls,
+                allow_plaintext: config.allow_plaintext,
+                tls_root_cert: config.tls_root_cert,
+                accept_invalid_certs: config.accept_invalid_certs,
+            },
+            sync_enabled: true,
+            sync_interval: Duration::from_secs(300),
+            signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+        })
+    }
+
     /// Sets the display name.
     pub fn display_name(mut self, name: impl Into<String>) -> Self {
         self.display_name = Some(name.into());
@@ -161,6 +288,34 @@ impl CreateAccountRequest {
         self.signature = Some(sig.into());
         self
     }
+
+    /// Sets the HTML signature.
+    pub fn signature_html(mut self, sig: impl Into<String>) -> Self {
+        self.signature_html = Some(sig.into());
+        self
+    }
+
+    /// Sets where the special folders are.
+    pub fn folder_mapping(mut self, mapping: FolderMapping) -> Self {
+        self.folder_mapping = mapping;
+        self
+    }
+}
+
+impl From<&Account> for CreateAccountRequest {
+    fn from(account: &Account) -> Self {
+        Self {
+            email: account.email.clone(),
+            display_name: account.display_name.clone(),
+            provider_type: account.provider_type,
+            provider_config: account.provider_config.clone(),
+            sync_enabled: account.sync_enabled,
+            sync_interval: account.sync_interval,
+            signature: account.signature.clone(),
+            signature_html: account.signature_html.clone(),
+            folder_mapping: account.folder_mapping.clone(),
+        }
+    }
 }
 
 /// Updates to apply to an account.
@@ -174,6 +329,12 @@ pub struct AccountUpdate {
     pub sync_interval: Option<Duration>,
     /// New signature.
     pub signature: Option<String>,
+    /// New HTML signature.
+    pub signature_html: Option<String>,
+    /// New special folder locations.
+    pub folder_mapping: Option<FolderMapping>,
+    /// New send-as aliases.
+    pub aliases: Option<Vec<Address>>,
 }
 
 impl AccountUpdate {
@@ -206,12 +367,33 @@ impl AccountUpdate {
         self
     }
 
+    /// Sets the HTML signature.
+    pub fn signature_html(mut self, sig: impl Into<String>) -> Self {
+        self.signature_html = Some(sig.into());
+        self
+    }
+
+    /// Sets where the special folders are.
+    pub fn folder_mapping(mut self, mapping: FolderMapping) -> Self {
+        self.folder_mapping = Some(mapping);
+        self
+    }
+
+    /// Sets the verified addresses the account can send as.
+    pub fn aliases(mut self, aliases: Vec<Address>) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
     /// Returns true if this update has no changes.
     pub fn is_empty(&self) -> bool {
         self.display_name.is_none()
             && self.sync_enabled.is_none()
             && self.sync_interval.is_none()
             && self.signature.is_none()
+            && self.signature_html.is_none()
+            && self.folder_mapping.is_none()
+            && self.aliases.is_none()
     }
 }
 
@@ -233,6 +415,8 @@ pub struct AccountService<S: AccountStorage, C: CredentialStore> {
     storage: S,
     credentials: C,
     active_account_id: Option<AccountId>,
+    provider_factory: ProviderFactory,
+    auth_states: RwLock<HashMap<AccountId, AccountAuthState>>,
 }
 
 impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
@@ -242,9 +426,160 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
             storage,
             credentials,
             active_account_id: None,
+            provider_factory: Arc::new(connect_provider),
+            auth_states: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces how providers are built for connection tests.
+    pub fn with_provider_factory(mut self, factory: ProviderFactory) -> Self {
+        self.provider_factory = factory;
+        self
+    }
+
+    /// Checks that an account works before it is saved.
+    ///
+    /// Builds the request's provider with `credentials`, authenticates and
+    /// fetches the label list. Failures map to
+    /// [`AccountError::AuthenticationFailed`] for rejected credentials,
+    /// [`AccountError::ConnectionRefused`] for a wrong port and
+    /// [`AccountError::Unreachable`] for a host that can't be reached.
+    pub async fn test_connection(
+        &self,
+        request: &CreateAccountRequest,
+        credentials: &AccountCredentials,
+    ) -> AccountResult<()> {
+        if !is_valid_email(&request.email) {
+            return Err(AccountError::InvalidConfig(format!(
+                "invalid email address: {}",
+                request.email
+            )));
+        }
+        validate_provider_config(&request.provider_type, &request.provider_config)?;
+
+        let mut provider = (self.provider_factory)(request, credentials)?;
+        provider.authenticate().await?;
+        let result = provider.fetch_labels().await.map(|_| ());
+        disconnect(provider.as_mut()).await;
+        result?;
+        Ok(())
+    }
+
+    /// Returns whether an account's credentials are working.
+    pub fn auth_state(&self, account_id: &AccountId) -> AccountAuthState {
+        self.auth_states
+            .read()
+            .unwrap()
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the accounts whose credentials need the user's attention.
+    pub fn accounts_needing_attention(&self) -> Vec<AccountId> {
+        self.auth_states
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, state)| state.needs_attention())
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Records a provider failure for an account.
+    ///
+    /// Rejected credentials mark the account [`AccountAuthState::NeedsReauth`].
+    /// Connection problems and rate limits are transient and leave the
+    /// state unchanged.
+    pub fn report_provider_error(&self, account_id: &AccountId, error: &ProviderError) {
+        let state = match error {
+            ProviderError::Authentication(_) => AccountAuthState::NeedsReauth,
+            ProviderError::Connection(_) | ProviderError::RateLimited { .. } => return,
+            other => AccountAuthState::Error(other.to_string()),
+        };
+        if state == AccountAuthState::NeedsReauth {
+            tracing::warn!(account = %account_id, "Credentials rejected, sign-in required");
+        }
+        self.set_auth_state(account_id, state);
+    }
+
+    /// Records that a provider accepted an account's credentials.
+    pub fn report_provider_success(&self, account_id: &AccountId) {
+        self.set_auth_state(account_id, AccountAuthState::Ok);
+    }
+
+    fn set_auth_state(&self, account_id: &AccountId, state: AccountAuthState) {
+        let mut states = self.auth_states.write().unwrap();
+        if state == AccountAuthState::Ok {
+            states.remove(account_id);
+        } else {
+            states.insert(account_id.clone(), state);
         }
     }
 
+    /// Signs an account in again with new credentials.
+    ///
+    /// The UI obtains `credentials` by re-running the OAuth flow or
+    /// prompting for a new password. They are checked against the provider
+    /// and only stored once they work, which returns the account to
+    /// [`AccountAuthState::Ok`].
+    pub async fn reauthenticate(
+        &self,
+        account_id: &AccountId,
+        credentials: AccountCredentials,
+    ) -> AccountResult<()> {
+        let account = self.get_account(account_id).await?;
+        let request = CreateAccountRequest::from(&account);
+
+        let mut provider = (self.provider_factory)(&request, &credentials)?;
+        let result = match provider.authenticate().await {
+            Ok(()) => {
+                let result = provider.fetch_labels().await.map(|_| ());
+                disconnect(provider.as_mut()).await;
+                result
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.report_provider_error(account_id, &e);
+            return Err(e.into());
+        }
+        let mut discovered = account.clone();
+        if let Some(mapping) = provider.special_folders() {
+            discovered.folder_mapping = mapping;
+        }
+        if let Some(aliases) = provider.send_as_aliases() {
+            discovered.aliases = aliases;
+        }
+        if discovered.folder_mapping != account.folder_mapping
+            || discovered.aliases != account.aliases
+        {
+            self.storage.update_account(&discovered).await?;
+        }
+
+        match &credentials {
+            AccountCredentials::Password(password) => {
+                self.store_password(account_id, password).await?
+            }
+            AccountCredentials::OAuth {
+                refresh_token,
+                client_id,
+                client_secret,
+            } => {
+                let key = credentials_key(ProviderType::Gmail, account_id);
+                let value = serde_json::json!({
+                    "refresh_token": refresh_token,
+                    "client_id": client_id,
+                    "client_secret": client_secret,
+                });
+                self.credentials.store(&key, &value.to_string()).await?
+            }
+        }
+
+        self.report_provider_success(account_id);
+        Ok(())
+    }
+
     /// Creates a new account.
     pub async fn create_account(
         &mut self,
@@ -276,6 +611,9 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
             sync_enabled: request.sync_enabled,
             sync_interval: request.sync_interval,
             signature: request.signature,
+            signature_html: request.signature_html,
+            folder_mapping: request.folder_mapping,
+            aliases: vec![],
         };
 
         self.storage.insert_account(&account).await?;
@@ -333,6 +671,15 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
         if let Some(signature) = update.signature {
             account.signature = Some(signature);
         }
+        if let Some(signature_html) = update.signature_html {
+            account.signature_html = Some(signature_html);
+        }
+        if let Some(folder_mapping) = update.folder_mapping {
+            account.folder_mapping = folder_mapping;
+        }
+        if let Some(aliases) = update.aliases {
+            account.aliases = aliases;
+        }
 
         self.storage.update_account(&account).await?;
 
@@ -344,14 +691,15 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
         // Verify account exists
         self.get_account(id).await?;
 
-        // Delete associated credentials
-        let oauth_key = format!("oauth:{}", id);
-        let password_key = format!("password:{}", id);
-        let _ = self.credentials.delete(&oauth_key).await;
-        let _ = self.credentials.delete(&password_key).await;
+        // Delete associated credentials. A keychain that can't be reached
+        // shouldn't keep the account around.
+        if let Err(e) = self.credentials.delete_all_for_account(id).await {
+            tracing::warn!(account = %id, "Failed to delete account credentials: {}", e);
+        }
 
         // Delete the account
         self.storage.delete_account(id).await?;
+        self.auth_states.write().unwrap().remove(id);
 
         // Clear active account if it was deleted
         if self.active_account_id.as_ref() == Some(id) {
@@ -383,19 +731,31 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
     }
 
     /// Stores OAuth tokens for an account.
+    ///
+    /// Tokens are merged into the account's stored credentials so fields
+    /// written by the provider (such as the OAuth client) are preserved.
+    /// IMAP accounts then sign in with XOAUTH2 using the access token.
     pub async fn store_oauth_tokens(
         &self,
         account_id: &AccountId,
         access_token: &str,
         refresh_token: &str,
     ) -> AccountResult<()> {
-        let key = format!("oauth:{}", account_id);
-        let value = serde_json::json!({
-            "access_token": access_token,
-            "refresh_token": refresh_token,
-        });
+        let account = self.get_account(account_id).await?;
+        let key = credentials_key(account.provider_type, account_id);
+        let mut value = match self.credentials.retrieve(&key).await? {
+            Some(existing) => serde_json::from_str(&existing)
+                .map_err(|e| AccountError::CredentialError(e.to_string()))?,
+            None => serde_json::json!({}),
+        };
+        if account.provider_type == ProviderType::Imap && value.get("username").is_none() {
+            value["username"] = account.email.into();
+            value["display_name"] = account.display_name.into();
+        }
+        value["access_token"] = access_token.into();
+        value["refresh_token"] = refresh_token.into();
 
-        self.credentials.store(&key, &value.to_string()).await
+        Ok(self.credentials.store(&key, &value.to_string()).await?)
     }
 
     /// Retrieves OAuth tokens for an account.
@@ -403,7 +763,8 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
         &self,
         account_id: &AccountId,
     ) -> AccountResult<Option<(String, String)>> {
-        let key = format!("oauth:{}", account_id);
+        let account = self.get_account(account_id).await?;
+        let key = credentials_key(account.provider_type, account_id);
         match self.credentials.retrieve(&key).await? {
             Some(value) => {
                 let parsed: serde_json::Value = serde_json::from_str(&value)
@@ -423,19 +784,40 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
     }
 
     /// Stores a password for an account.
+    ///
+    /// The password is stored alongside the account's email as IMAP login
+    /// credentials.
     pub async fn store_password(
         &self,
         account_id: &AccountId,
         password: &str,
     ) -> AccountResult<()> {
-        let key = format!("password:{}", account_id);
-        self.credentials.store(&key, password).await
+        let account = self.get_account(account_id).await?;
+        let key = credentials_key(ProviderType::Imap, account_id);
+        let value = serde_json::json!({
+            "username": account.email,
+            "password": password,
+            "display_name": account.display_name,
+        });
+
+        Ok(self.credentials.store(&key, &value.to_string()).await?)
     }
 
     /// Retrieves a password for an account.
     pub async fn get_password(&self, account_id: &AccountId) -> AccountResult<Option<String>> {
-        let key = format!("password:{}", account_id);
-        self.credentials.retrieve(&key).await
+        let key = credentials_key(ProviderType::Imap, account_id);
+        match self.credentials.retrieve(&key).await? {
+            Some(value) => {
+                let parsed: serde_json::Value = serde_json::from_str(&value)
+                    .map_err(|e| AccountError::CredentialError(e.to_string()))?;
+                let password = parsed["password"]
+                    .as_str()
+                    .ok_or_else(|| AccountError::CredentialError("missing password".into()))?
+                    .to_string();
+                Ok(Some(password))
+            }
+            None => Ok(None),
+        }
     }
 
     /// Gets account statistics.
@@ -466,6 +848,117 @@ impl<S: AccountStorage, C: CredentialStore> AccountService<S, C> {
     }
 }
 
+impl<S: AccountStorage, C: CredentialStore> AuthStateReporter for AccountService<S, C> {
+    fn report_provider_error(&self, account_id: &AccountId, error: &ProviderError) {
+        AccountService::report_provider_error(self, account_id, error);
+    }
+
+    fn report_provider_success(&self, account_id: &AccountId) {
+        AccountService::report_provider_success(self, account_id);
+    }
+}
+
+/// Closes a provider's session once a check is done with it.
+async fn disconnect(provider: &mut dyn EmailProvider) {
+    if let Err(e) = provider.disconnect().await {
+        tracing::debug!("Failed to disconnect provider: {}", e);
+    }
+}
+
+/// Builds the provider for a saved account, which signs in with the
+/// credentials stored for it in the keychain.
+///
+/// IMAP providers keep where messages were last seen in `db`. Requests to
+/// the server are abandoned after `request_timeout`.
+pub fn provider_for_account(
+    account: &Account,
+    db: &Database,
+    request_timeout: Duration,
+) -> Box<dyn EmailProvider> {
+    match imap_config(&account.provider_config) {
+        Some(config) => Box::new(
+            ImapProvider::new(account.id.clone(), config)
+                .with_folder_mapping(account.folder_mapping.clone())
+                .with_location_store(Arc::new(db.clone()))
+                .with_request_timeout(request_timeout),
+        ),
+        None => Box::new(
+            GmailProvider::new(account.id.clone())
+                .with_folder_mapping(account.folder_mapping.clone())
+                .with_request_timeout(request_timeout),
+        ),
+    }
+}
+
+/// Returns the server settings of an IMAP account.
+fn imap_config(config: &ProviderConfig) -> Option<ImapConfig> {
+    let ProviderConfig::Imap {
+        imap_host,
+        imap_port,
+        smtp_host,
+        smtp_port,
+        use_tls,
+        allow_plaintext,
+        tls_root_cert,
+        accept_invalid_certs,
+    } = config
+    else {
+        return None;
+    };
+    Some(ImapConfig {
+        imap_host: imap_host.clone(),
+        imap_port: *imap_port,
+        smtp_host: smtp_host.clone(),
+        smtp_port: *smtp_port,
+        use_tls: *use_tls,
+        allow_plaintext: *allow_plaintext,
+        tls_root_cert: tls_root_cert.clone(),
+        accept_invalid_certs: *accept_invalid_certs,
+        preview_bytes: ImapConfig::DEFAULT_PREVIEW_BYTES,
+    })
+}
+
+/// Builds the real provider for an account request.
+fn connect_provider(
+    request: &CreateAccountRequest,
+    credentials: &AccountCredentials,
+) -> AccountResult<Box<dyn EmailProvider>> {
+    let account_id = AccountId::from("connection-test");
+    match (imap_config(&request.provider_config), credentials) {
+        (Some(config), AccountCredentials::Password(password)) => {
+            let credentials = ImapCredentials {
+                display_name: request.display_name.clone(),
+                ..ImapCredentials::password(request.email.clone(), password.clone())
+            };
+            Ok(Box::new(
+                ImapProvider::with_credentials(account_id, config, credentials)
+                    .with_folder_mapping(request.folder_mapping.clone()),
+            ))
+        }
+        (
+            None,
+            AccountCredentials::OAuth {
+                refresh_token,
+                client_id,
+                client_secret,
+            },
+        ) => {
+            let credentials = GmailCredentials {
+                refresh_token: refresh_token.clone(),
+                client_id: client_id.clone(),
+                client_secret: client_secret.clone(),
+            };
+            Ok(Box::new(
+                GmailProvider::with_credentials(account_id, credentials)
+                    .with_folder_mapping(request.folder_mapping.clone()),
+            ))
+        }
+        _ => Err(AccountError::InvalidConfig(
+            "credentials don't match the provider type".into(),
+        )),
+    }
+}
+
 /// Validates an email address format.
 fn is_valid_email(email: &str) -> bool {
     let parts: Vec<&str> = email.split('@').collect();
@@ -512,6 +1005,14 @@ mod tests {
     use std::collections::HashMap;
     use std::sync::Mutex;
 
+    use chrono::{DateTime, Utc};
+
+    use crate::domain::{CredentialResult, Label, SpecialFolder, Thread, ThreadSummary};
+    use crate::providers::email::{
+        Change, OutgoingEmail, Pagination, PendingChange, ProviderCapabilities,
+        Result as ProviderResult,
+    };
+
     struct MockStorage {
         accounts: Mutex<HashMap<AccountId, Account>>,
     }
@@ -567,34 +1068,47 @@ mod tests {
 
     struct MockCredentials {
         store: Mutex<HashMap<String, String>>,
+        /// Fails every request, like a locked keychain.
+        locked: bool,
     }
 
     impl MockCredentials {
         fn new() -> Self {
             Self {
                 store: Mutex::new(HashMap::new()),
+                locked: false,
             }
         }
     }
 
     #[async_trait]
     impl CredentialStore for MockCredentials {
-        async fn store(&self, key: &str, value: &str) -> AccountResult<()> {
+        async fn store(&self, key: &str, value: &str) -> CredentialResult<()> {
             let mut store = self.store.lock().unwrap();
             store.insert(key.to_string(), value.to_string());
             Ok(())
         }
 
-        async fn retrieve(&self, key: &str) -> AccountResult<Option<String>> {
+        async fn retrieve(&self, key: &str) -> CredentialResult<Option<String>> {
             let store = self.store.lock().unwrap();
             Ok(store.get(key).cloned())
         }
 
-        async fn delete(&self, key: &str) -> AccountResult<()> {
+        async fn delete(&self, key: &str) -> CredentialResult<()> {
             let mut store = self.store.lock().unwrap();
             store.remove(key);
             Ok(())
         }
+
+        async fn delete_all_for_account(&self, account_id: &AccountId) -> CredentialResult<()> {
+            if self.locked {
+                return Err(CredentialError("keychain is locked".to_string()));
+            }
+            for provider in [ProviderType::Gmail, ProviderType::Imap] {
+                self.delete(&credentials_key(provider, account_id)).await?;
+            }
+            Ok(())
+        }
     }
 
     fn create_service() -> AccountService<MockStorage, MockCredentials> {
@@ -629,6 +1143,18 @@ mod tests {
         assert_eq!(account.signature, Some("Best regards".to_string()));
     }
 
+    #[test]
+    fn imap_auto_known_and_unknown_domains() {
+        let request = CreateAccountRequest::imap_auto("someone@fastmail.com").unwrap();
+        assert_eq!(request.provider_type, ProviderType::Imap);
+        match request.provider_config {
+            ProviderConfig::Imap { imap_host, .. } => assert_eq!(imap_host, "imap.fastmail.com"),
+            _ => panic!("expected IMAP config"),
+        }
+
+        assert!(CreateAccountRequest::imap_auto("someone@unknown-domain.example").is_none());
+    }
+
     #[tokio::test]
     async fn create_account_duplicate() {
         let mut service = create_service();
@@ -700,6 +1226,39 @@ mod tests {
         assert!(matches!(result, Err(AccountError::NotFound(_))));
     }
 
+    #[tokio::test]
+    async fn delete_account_removes_credentials() {
+        let mut service = create_service();
+
+        let request = CreateAccountRequest::gmail("test@gmail.com");
+        let created = service.create_account(request).await.unwrap();
+        service
+            .store_oauth_tokens(&created.id, "access", "refresh")
+            .await
+            .unwrap();
+
+        service.delete_account(&created.id).await.unwrap();
+
+        assert!(service.credentials.store.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_account_survives_a_locked_keychain() {
+        let credentials = MockCredentials {
+            locked: true,
+            ..MockCredentials::new()
+        };
+        let mut service = AccountService::new(MockStorage::new(), credentials);
+
+        let request = CreateAccountRequest::gmail("test@gmail.com");
+        let created = service.create_account(request).await.unwrap();
+
+        service.delete_account(&created.id).await.unwrap();
+
+        let result = service.get_account(&created.id).await;
+        assert!(matches!(result, Err(AccountError::NotFound(_))));
+    }
+
     #[tokio::test]
     async fn active_account() {
         let mut service = create_service();
@@ -763,6 +1322,95 @@ mod tests {
         assert_eq!(password, Some("secret123".to_string()));
     }
 
+    #[tokio::test]
+    async fn password_is_readable_by_imap_provider() {
+        use crate::providers::email::ImapProvider;
+
+        let credentials = Arc::new(MockCredentials::new());
+        let mut service = AccountService::new(MockStorage::new(), credentials.clone());
+
+        let request =
+            CreateAccountRequest::imap("test@example.com", "imap.example.com", "smtp.example.com")
+                .display_name("Test User");
+        let account = service.create_account(request).await.unwrap();
+        service
+            .store_password(&account.id, "secret123")
+            .await
+            .unwrap();
+
+        let provider = ImapProvider::new(
+            account.id.clone(),
+            ImapConfig::tls("imap.example.com", "smtp.example.com"),
+        )
+        .with_credential_store(credentials);
+        let loaded = provider.load_credentials().await.unwrap();
+
+        assert_eq!(loaded.username, "test@example.com");
+        assert_eq!(loaded.password, "secret123");
+        assert_eq!(loaded.display_name, Some("Test User".to_string()));
+    }
+
+    #[tokio::test]
+    async fn oauth_tokens_are_readable_by_gmail_provider() {
+        use crate::providers::email::GmailProvider;
+
+        let credentials = Arc::new(MockCredentials::new());
+        let mut service = AccountService::new(MockStorage::new(), credentials.clone());
+
+        let request = CreateAccountRequest::gmail("test@gmail.com");
+        let account = service.create_account(request).await.unwrap();
+        service
+            .store_oauth_tokens(&account.id, "access123", "refresh456")
+            .await
+            .unwrap();
+
+        let provider = GmailProvider::new(account.id.clone()).with_credential_store(credentials);
+        let loaded = provider.load_credentials().await.unwrap();
+
+        assert_eq!(loaded.refresh_token, "refresh456");
+    }
+
+    #[tokio::test]
+    async fn oauth_tokens_are_readable_by_imap_provider() {
+        use crate::providers::email::ImapProvider;
+
+        let credentials = Arc::new(MockCredentials::new());
+        let mut service = AccountService::new(MockStorage::new(), credentials.clone());
+
+        let request = CreateAccountRequest::imap(
+            "me@outlook.com",
+            "outlook.office365.com",
+            "smtp.office365.com",
+        )
+        .display_name("Me");
+        let account = service.create_account(request).await.unwrap();
+        service
+            .store_oauth_tokens(&account.id, "access123", "refresh456")
+            .await
+            .unwrap();
+        // A refresh replaces the tokens and keeps the rest.
+        service
+            .store_oauth_tokens(&account.id, "access789", "refresh456")
+            .await
+            .unwrap();
+
+        let provider = ImapProvider::new(
+            account.id.clone(),
+            ImapConfig::tls("outlook.office365.com", "smtp.office365.com"),
+        )
+        .with_credential_store(credentials);
+        let loaded = provider.load_credentials().await.unwrap();
+
+        assert_eq!(loaded.username, "me@outlook.com");
+        assert_eq!(loaded.display_name, Some("Me".to_string()));
+        assert_eq!(loaded.access_token.as_deref(), Some("access789"));
+        assert_eq!(loaded.refresh_token.as_deref(), Some("refresh456"));
+        assert_eq!(
+            service.get_oauth_tokens(&account.id).await.unwrap(),
+            Some(("access789".to_string(), "refresh456".to_string()))
+        );
+    }
+
     #[tokio::test]
     async fn get_stats() {
         let mut service = create_service();
@@ -814,6 +1462,9 @@ mod tests {
                 smtp_host: "smtp.example.com".into(),
                 smtp_port: 587,
                 use_tls: true,
+                allow_plaintext: false,
+                tls_root_cert: None,
+                accept_invalid_certs: false,
             }
         )
         .is_ok());
@@ -827,6 +1478,9 @@ mod tests {
                 smtp_host: "smtp.example.com".into(),
                 smtp_port: 587,
                 use_tls: true,
+                allow_plaintext: false,
+                tls_root_cert: None,
+                accept_invalid_certs: false,
             }
         )
         .is_err());
@@ -840,8 +1494,310 @@ mod tests {
                 smtp_host: "smtp.example.com".into(),
                 smtp_port: 587,
                 use_tls: true,
+                allow_plaintext: false,
+                tls_root_cert: None,
+                accept_invalid_certs: false,
             }
         )
         .is_err());
     }
+
+    /// Provider whose authentication fails with a fixed error, or succeeds
+    /// when there is none.
+    struct MockProvider {
+        auth_error: Option<fn() -> ProviderError>,
+        special_folders: Option<FolderMapping>,
+    }
+
+    #[async_trait]
+    impl EmailProvider for MockProvider {
+        fn provider_type(&self) -> ProviderType {
+            ProviderType::Imap
+        }
+
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities::default()
+        }
+
+        fn special_folders(&self) -> Option<FolderMapping> {
+            self.special_folders.clone()
+        }
+
+        async fn authenticate(&mut self) -> ProviderResult<()> {
+            match self.auth_error {
+                Some(error) => Err(error()),
+                None => Ok(()),
+            }
+        }
+
+        async fn disconnect(&mut self) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn fetch_threads(
+            &self,
+            _folder: &str,
+            _pagination: Pagination,
+        ) -> ProviderResult<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn fetch_thread(&self, thread_id: &str) -> ProviderResult<Thread> {
+            Err(ProviderError::NotFound(thread_id.to_string()))
+        }
+
+        async fn fetch_raw(&self, email_id: &str) -> ProviderResult<Vec<u8>> {
+            Err(ProviderError::NotFound(email_id.to_string()))
+        }
+
+        async fn fetch_changes_since(&self, _since: &DateTime<Utc>) -> ProviderResult<Vec<Change>> {
+            Ok(vec![])
+        }
+
+        async fn send_email(&self, _email: &OutgoingEmail) -> ProviderResult<String> {
+            Ok(String::new())
+        }
+
+        async fn archive(&self, _thread_ids: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn trash(&self, _thread_ids: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn delete_permanently(&self, _thread_ids: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn report_spam(&self, _thread_ids: &[String]) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn star(&self, _thread_id: &str, _starred: bool) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn mark_read(&self, _thread_id: &str, _read: bool) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn apply_label(&self, _thread_id: &str, _label: &str) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn remove_label(&self, _thread_id: &str, _label: &str) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        async fn fetch_labels(&self) -> ProviderResult<Vec<Label>> {
+            Ok(vec![])
+        }
+
+        async fn push_change(&self, _change: &PendingChange) -> ProviderResult<()> {
+            Ok(())
+        }
+    }
+
+    fn service_with_auth_error(
+        auth_error: Option<fn() -> ProviderError>,
+    ) -> AccountService<MockStorage, MockCredentials> {
+        AccountService::new(MockStorage::new(), MockCredentials::new()).with_provider_factory(
+            Arc::new(move |_, _| {
+                Ok(Box::new(MockProvider {
+                    auth_error,
+                    special_folders: None,
+                }))
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_connection_maps_provider_errors() {
+        let request =
+            CreateAccountRequest::imap("user@example.com", "imap.example.com", "smtp.example.com");
+        let password = AccountCredentials::Password("secret".to_string());
+
+        let service = service_with_auth_error(None);
+        service.test_connection(&request, &password).await.unwrap();
+
+        let service = service_with_auth_error(Some(|| {
+            ProviderError::Authentication("IMAP login failed: bad password".to_string())
+        }));
+        assert!(matches!(
+            service.test_connection(&request, &password).await,
+            Err(AccountError::AuthenticationFailed(_))
+        ));
+
+        let service = service_with_auth_error(Some(|| {
+            ProviderError::Connection(
+                "TCP connect failed: Connection refused (os error 111)".to_string(),
+            )
+        }));
+        assert!(matches!(
+            service.test_connection(&request, &password).await,
+            Err(AccountError::ConnectionRefused(_))
+        ));
+
+        let service = service_with_auth_error(Some(|| {
+            ProviderError::Connection("TCP connect failed: failed to lookup address".to_string())
+        }));
+        assert!(matches!(
+            service.test_connection(&request, &password).await,
+            Err(AccountError::Unreachable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connection_does_not_save_account() {
+        let service = service_with_auth_error(None);
+        let request = CreateAccountRequest::gmail("user@gmail.com");
+        let oauth = AccountCredentials::OAuth {
+            refresh_token: "token".to_string(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+        };
+
+        service.test_connection(&request, &oauth).await.unwrap();
+        assert_eq!(service.count().await.unwrap(), 0);
+    }
+
+    #[test]
+    fn connect_provider_rejects_mismatched_credentials() {
+        let request = CreateAccountRequest::gmail("user@gmail.com");
+        let password = AccountCredentials::Password("secret".to_string());
+        assert!(matches!(
+            connect_provider(&request, &password),
+            Err(AccountError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn auth_failure_needs_reauth_until_signed_in_again() {
+        let mut service = AccountService::new(MockStorage::new(), MockCredentials::new())
+            .with_provider_factory(Arc::new(|_, credentials| {
+                // Only the new password is accepted.
+                let auth_error: Option<fn() -> ProviderError> = match credentials {
+                    AccountCredentials::Password(p) if p == "new-password" => None,
+                    _ => Some(|| ProviderError::Authentication("bad password".to_string())),
+                };
+                Ok(Box::new(MockProvider {
+                    auth_error,
+                    special_folders: None,
+                }))
+            }));
+        let account = service
+            .create_account(CreateAccountRequest::imap(
+                "user@example.com",
+                "imap.example.com",
+                "smtp.example.com",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(service.auth_state(&account.id), AccountAuthState::Ok);
+
+        // Transient failures don't need the user.
+        service.report_provider_error(&account.id, &ProviderError::Connection("offline".into()));
+        assert_eq!(service.auth_state(&account.id), AccountAuthState::Ok);
+
+        service.report_provider_error(
+            &account.id,
+            &ProviderError::Authentication("token revoked".into()),
+        );
+        assert_eq!(
+            service.auth_state(&account.id),
+            AccountAuthState::NeedsReauth
+        );
+        assert_eq!(
+            service.accounts_needing_attention(),
+            vec![account.id.clone()]
+        );
+
+        let result = service
+            .reauthenticate(&account.id, AccountCredentials::Password("wrong".into()))
+            .await;
+        assert!(matches!(result, Err(AccountError::AuthenticationFailed(_))));
+        assert_eq!(
+            service.auth_state(&account.id),
+            AccountAuthState::NeedsReauth
+        );
+        assert_eq!(service.get_password(&account.id).await.unwrap(), None);
+
+        service
+            .reauthenticate(
+                &account.id,
+                AccountCredentials::Password("new-password".into()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(service.auth_state(&account.id), AccountAuthState::Ok);
+        assert!(service.accounts_needing_attention().is_empty());
+        assert_eq!(
+            service.get_password(&account.id).await.unwrap().as_deref(),
+            Some("new-password")
+        );
+    }
+
+    #[test]
+    fn connect_provider_applies_the_folder_mapping() {
+        let mut mapping = FolderMapping::new();
+        mapping.set_override(SpecialFolder::Sent, Some("INBOX.Sent".into()));
+        let request =
+            CreateAccountRequest::imap("user@example.com", "imap.example.com", "smtp.example.com")
+                .folder_mapping(mapping.clone());
+        let password = AccountCredentials::Password("secret".to_string());
+
+        let provider = connect_provider(&request, &password).unwrap();
+
+        assert_eq!(provider.special_folders(), Some(mapping));
+    }
+
+    #[tokio::test]
+    async fn reauthenticating_stores_the_discovered_folders() {
+        let mut discovered = FolderMapping::new();
+        discovered.set_discovered([(SpecialFolder::Trash, "Deleted Items".to_string())]);
+        let found = discovered.clone();
+        let mut service = AccountService::new(MockStorage::new(), MockCredentials::new())
+            .with_provider_factory(Arc::new(move |_, _| {
+                Ok(Box::new(MockProvider {
+                    auth_error: None,
+                    special_folders: Some(found.clone()),
+                }))
+            }));
+        let account = service
+            .create_account(CreateAccountRequest::imap(
+                "user@example.com",
+                "imap.example.com",
+                "smtp.example.com",
+            ))
+            .await
+            .unwrap();
+
+        service
+            .reauthenticate(&account.id, AccountCredentials::Password("secret".into()))
+            .await
+            .unwrap();
+
+        let stored = service.get_account(&account.id).await.unwrap();
+        assert_eq!(stored.folder_mapping, discovered);
+    }
+
+    #[test]
+    fn imap_config_carries_the_tls_options() {
+        let config = imap_config(&ProviderConfig::Imap {
+            imap_host: "mail.lab.example".into(),
+            imap_port: 993,
+            smtp_host: "mail.lab.example".into(),
+            smtp_port: 465,
+            use_tls: true,
+            allow_plaintext: false,
+            tls_root_cert: Some("/etc/lab-ca.pem".into()),
+            accept_invalid_certs: true,
+        })
+        .unwrap();
+
+        assert_eq!(config.tls_root_cert, Some("/etc/lab-ca.pem".into()));
+        assert!(config.accept_invalid_certs);
+        assert!(!config.allow_plaintext);
+    }
 }
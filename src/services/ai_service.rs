@@ -7,14 +7,20 @@
 //! - Email categorization
 //! - Sender analysis
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::AddAssign;
 use std::sync::Arc;
 
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::domain::{AccountId, Email, EmailId, SenderAnalysis, Thread};
+use crate::domain::{
+    detect_language, language_name, AccountId, Email, EmailId, SenderAnalysis, Thread, ThreadId,
+    UNDETERMINED_LANGUAGE,
+};
+use crate::services::{AiStats, EventSubscriber, ServiceEvent, StatsTimeRange};
 
 /// LLM provider trait for abstracting over different AI backends.
 ///
@@ -24,6 +30,12 @@ pub trait LlmProvider: Send + Sync {
     /// Returns the provider name (e.g., "openai", "anthropic").
     fn name(&self) -> &str;
 
+    /// Returns the model completions are billed under, used for cost
+    /// estimates. Defaults to the provider name.
+    fn model(&self) -> &str {
+        self.name()
+    }
+
     /// Performs a completion request.
     async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse>;
 
@@ -42,6 +54,31 @@ pub trait EmbeddingEngine: Send + Sync {
 
     /// Indexes an email for future search.
     async fn index_email(&self, email: &Email) -> Result<()>;
+
+    /// Returns the stored embedding for an indexed email, if any.
+    async fn stored_embedding(&self, _email_id: &EmailId) -> Option<Vec<f32>> {
+        None
+    }
+
+    /// Drops the stored embeddings of emails, e.g. those of a deleted
+    /// account, returning how many were stored.
+    async fn remove_emails(&self, _email_ids: &[EmailId]) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+/// Cache of thread summaries, keyed by a hash of the thread's content.
+#[async_trait::async_trait]
+pub trait SummaryCache: Send + Sync {
+    /// Gets the summary for a thread if it was generated from content with
+    /// `content_hash`.
+    async fn get(&self, thread_id: &ThreadId, content_hash: &str) -> Result<Option<Summary>>;
+
+    /// Stores a thread's summary, replacing any previous one.
+    async fn put(&self, thread_id: &ThreadId, content_hash: &str, summary: &Summary) -> Result<()>;
+
+    /// Removes a thread's cached summary.
+    async fn invalidate(&self, thread_id: &ThreadId) -> Result<()>;
 }
 
 /// A request for LLM completion.
@@ -89,7 +126,7 @@ pub struct CompletionResponse {
 }
 
 /// Token usage statistics for a completion.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct TokenUsage {
     /// Tokens in the prompt.
     pub prompt_tokens: usize,
@@ -99,6 +136,105 @@ pub struct TokenUsage {
     pub total_tokens: usize,
 }
 
+impl AddAssign<&TokenUsage> for TokenUsage {
+    fn add_assign(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price per million prompt tokens.
+    pub prompt_per_million: f64,
+    /// Price per million completion tokens.
+    pub completion_per_million: f64,
+}
+
+impl ModelPricing {
+    /// Creates a price from USD per million prompt and completion tokens.
+    pub const fn new(prompt_per_million: f64, completion_per_million: f64) -> Self {
+        Self {
+            prompt_per_million,
+            completion_per_million,
+        }
+    }
+
+    /// Returns the cost of `usage` in USD.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt_per_million
+            + usage.completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Model prices keyed by model name or name prefix.
+///
+/// A model is priced by the longest key it starts with, so `gpt-4o` covers
+/// dated snapshots like `gpt-4o-2024-08-06`. Models without a price, such
+/// as local ones, cost nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TokenPricing(pub HashMap<String, ModelPricing>);
+
+impl Default for TokenPricing {
+    fn default() -> Self {
+        let known = [
+            ("gpt-4o", ModelPricing::new(2.50, 10.00)),
+            ("gpt-4o-mini", ModelPricing::new(0.15, 0.60)),
+            ("gpt-4-turbo", ModelPricing::new(10.00, 30.00)),
+            ("gpt-4", ModelPricing::new(30.00, 60.00)),
+            ("gpt-3.5-turbo", ModelPricing::new(0.50, 1.50)),
+            ("o1", ModelPricing::new(15.00, 60.00)),
+            ("o1-mini", ModelPricing::new(3.00, 12.00)),
+            ("claude-3-5-sonnet", ModelPricing::new(3.00, 15.00)),
+            ("claude-3-5-haiku", ModelPricing::new(0.80, 4.00)),
+            ("claude-3-opus", ModelPricing::new(15.00, 75.00)),
+            ("claude-3-sonnet", ModelPricing::new(3.00, 15.00)),
+            ("claude-3-haiku", ModelPricing::new(0.25, 1.25)),
+        ];
+        Self(
+            known
+                .into_iter()
+                .map(|(model, price)| (model.to_string(), price))
+                .collect(),
+        )
+    }
+}
+
+impl TokenPricing {
+    /// Returns the price for `model`, if known.
+    pub fn for_model(&self, model: &str) -> Option<&ModelPricing> {
+        self.0
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| price)
+    }
+
+    /// Returns the estimated cost of `usage` on `model` in USD.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> f64 {
+        self.for_model(model).map_or(0.0, |price| price.cost(usage))
+    }
+}
+
+/// What a completion was used for, for usage accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum UsageKind {
+    Summary,
+    Draft,
+    Other,
+}
+
+/// Token usage accumulated for one day, model and kind of completion.
+#[derive(Debug, Clone, Default)]
+struct UsageTally {
+    completions: u32,
+    tokens: TokenUsage,
+}
+
 /// Reason why generation finished.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FinishReason {
@@ -113,75 +249,125 @@ pub enum FinishReason {
 /// Summary of an email thread.
 ///
 /// Generated by AI to provide a quick overview of a conversation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Summary {
-    /// Concise summary text.
-    pub text: String,
-    /// Key points extracted from the thread.
-    pub key_points: Vec<String>,
+    /// One or two sentence overview.
+    pub tldr: String,
     /// Action items or tasks identified.
     pub action_items: Vec<String>,
+    /// Dates mentioned in the thread, with what happens on each.
+    pub key_dates: Vec<(String, DateTime<Utc>)>,
+    /// People taking part in the conversation.
+    pub participants: Vec<String>,
 }
 
 impl Summary {
     /// Parses a summary from LLM output.
     ///
-    /// Expects structured output with sections for summary, key points, and action items.
+    /// Expects a JSON object with `tldr`, `action_items`, `key_dates` and
+    /// `participants`, optionally wrapped in a markdown code fence or
+    /// surrounded by prose. Missing or malformed fields are left empty, and
+    /// output that isn't JSON at all becomes the `tldr`.
     pub fn parse(text: &str) -> Self {
-        // Simple parsing - in production this would use structured output
-        let mut summary_text = String::new();
-        let mut key_points = Vec::new();
-        let mut action_items = Vec::new();
+        Self::parse_json(text).unwrap_or_else(|| Self {
+            tldr: strip_code_fence(text).to_string(),
+            ..Self::default()
+        })
+    }
 
-        let mut current_section = "summary";
+    fn parse_json(text: &str) -> Option<Self> {
+        let text = strip_code_fence(text);
+        let start = text.find('{')?;
+        let end = text.rfind('}')?;
+        let value: serde_json::Value = serde_json::from_str(text.get(start..=end)?).ok()?;
+        let object = value.as_object()?;
 
-        for line in text.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+        let summary = Self {
+            tldr: ["tldr", "summary", "text"]
+                .iter()
+                .find_map(|key| object.get(*key).and_then(|v| v.as_str()))
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            action_items: string_list(object.get("action_items")),
+            key_dates: object
+                .get("key_dates")
+                .and_then(|v| v.as_array())
+                .map(|dates| dates.iter().filter_map(key_date).collect())
+                .unwrap_or_default(),
+            participants: string_list(object.get("participants")),
+        };
+        (summary != Self::default()).then_some(summary)
+    }
+}
 
-            if line.to_lowercase().starts_with("key points:") {
-                current_section = "key_points";
-                continue;
-            } else if line.to_lowercase().starts_with("action items:") {
-                current_section = "action_items";
-                continue;
-            }
+/// Returns the contents of a markdown code fence, or `text` trimmed if it
+/// has none.
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    // Skip the info string, e.g. "json".
+    let body = &text[start + 3..];
+    let body = body.find('\n').map_or(body, |newline| &body[newline + 1..]);
+    match body.find("```") {
+        Some(end) => body[..end].trim(),
+        None => body.trim(),
+    }
+}
 
-            match current_section {
-                "summary" => {
-                    if !summary_text.is_empty() {
-                        summary_text.push(' ');
-                    }
-                    summary_text.push_str(line);
-                }
-                "key_points" => {
-                    if let Some(point) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))
-                    {
-                        key_points.push(point.to_string());
-                    } else if !line.is_empty() {
-                        key_points.push(line.to_string());
-                    }
-                }
-                "action_items" => {
-                    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))
-                    {
-                        action_items.push(item.to_string());
-                    } else if !line.is_empty() {
-                        action_items.push(line.to_string());
-                    }
-                }
-                _ => {}
-            }
-        }
+/// Collects the non-empty strings in a JSON array.
+fn string_list(value: Option<&serde_json::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-        Self {
-            text: summary_text,
-            key_points,
-            action_items,
-        }
+/// Reads a key date given as `{"label": ..., "date": ...}` or
+/// `[label, date]`.
+fn key_date(value: &serde_json::Value) -> Option<(String, DateTime<Utc>)> {
+    let (label, date) = match value {
+        serde_json::Value::Object(object) => (
+            ["label", "description", "event"]
+                .iter()
+                .find_map(|key| object.get(*key).and_then(|v| v.as_str()))?,
+            object.get("date")?.as_str()?,
+        ),
+        serde_json::Value::Array(pair) => (pair.first()?.as_str()?, pair.get(1)?.as_str()?),
+        _ => return None,
+    };
+    Some((label.trim().to_string(), parse_key_date(date.trim())?))
+}
+
+/// Parses an RFC 3339 timestamp, or a date with optional time taken as UTC.
+fn parse_key_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(date) {
+        return Some(parsed.with_timezone(&Utc));
     }
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .and_then(|day| day.and_hms_opt(0, 0, 0))
+    })
+    .map(|naive| Utc.from_utc_datetime(&naive))
 }
 
 /// A suggested draft reply generated by AI.
@@ -198,8 +384,8 @@ pub struct DraftSuggestion {
 pub struct SearchResult {
     /// ID of the matching email.
     pub email_id: EmailId,
-    /// ID of the thread containing the email.
-    pub thread_id: crate::domain::ThreadId,
+    /// ID of the thread containing the email, when it has been looked up.
+    pub thread_id: Option<crate::domain::ThreadId>,
     /// Subject of the email.
     pub subject: Option<String>,
     /// Preview snippet.
@@ -281,12 +467,168 @@ pub struct AiSettings {
     pub enabled: bool,
     /// Default provider to use.
     pub default_provider: String,
+    /// Endpoint for an OpenAI-compatible provider such as vLLM, LM Studio or
+    /// OpenRouter, or `None` for the provider's own default.
+    #[serde(default)]
+    pub base_url: Option<String>,
     /// Settings for summarization.
     pub summary_settings: SummarySettings,
     /// Settings for draft generation.
     pub compose_settings: ComposeSettings,
     /// Settings for semantic search.
     pub search_settings: SearchSettings,
+    /// Settings for translating incoming mail.
+    #[serde(default)]
+    pub translation_settings: TranslationSettings,
+    /// Model prices used to estimate what AI usage costs.
+    #[serde(default)]
+    pub pricing: TokenPricing,
+    /// Prompt templates by feature. See [`PromptKind`] for the variables
+    /// each one accepts.
+    #[serde(default = "default_prompts")]
+    pub prompts: HashMap<PromptKind, String>,
+}
+
+fn default_prompts() -> HashMap<PromptKind, String> {
+    PromptKind::all()
+        .iter()
+        .map(|kind| (*kind, kind.default_template().to_string()))
+        .collect()
+}
+
+impl AiSettings {
+    /// Renders the prompt template for `kind`, substituting `{name}`
+    /// variables from `vars`.
+    ///
+    /// Variables missing from `vars` render as empty. A template that is
+    /// missing or uses a variable `kind` doesn't provide falls back to the
+    /// default template, with a warning.
+    pub fn render_prompt(&self, kind: PromptKind, vars: &[(&str, &str)]) -> String {
+        let template = match self.prompts.get(&kind) {
+            Some(template) => {
+                let unknown = kind.unknown_variables(template);
+                if unknown.is_empty() {
+                    template.as_str()
+                } else {
+                    tracing::warn!(
+                        ?kind,
+                        "Prompt template uses unknown variables {:?}, using the default",
+                        unknown
+                    );
+                    kind.default_template()
+                }
+            }
+            None => kind.default_template(),
+        };
+
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some((before, name, after)) = next_variable(rest) {
+            rendered.push_str(before);
+            let value = vars.iter().find(|(key, _)| *key == name);
+            rendered.push_str(value.map_or("", |(_, value)| value));
+            rest = after;
+        }
+        rendered.push_str(rest);
+        rendered.trim_end().to_string()
+    }
+}
+
+/// An AI feature with an editable prompt template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptKind {
+    /// Thread summaries. Variables: `{thread}`.
+    Summary,
+    /// Draft replies. Variables: `{thread}`, `{tone}`, `{instructions}`.
+    Draft,
+    /// Email categorization. Variables: `{email}`, `{categories}`.
+    Classification,
+    /// Screener sender analysis. Variables: `{sender}`.
+    SenderAnalysis,
+    /// Email translation. Variables: `{text}`, `{language}`.
+    Translation,
+}
+
+impl PromptKind {
+    /// Returns all prompt kinds.
+    pub fn all() -> &'static [PromptKind] {
+        &[
+            PromptKind::Summary,
+            PromptKind::Draft,
+            PromptKind::Classification,
+            PromptKind::SenderAnalysis,
+            PromptKind::Translation,
+        ]
+    }
+
+    /// Returns the variables this prompt's template may use.
+    pub fn variables(&self) -> &'static [&'static str] {
+        match self {
+            PromptKind::Summary => &["thread"],
+            PromptKind::Draft => &["thread", "tone", "instructions"],
+            PromptKind::Classification => &["email", "categories"],
+            PromptKind::SenderAnalysis => &["sender"],
+            PromptKind::Translation => &["text", "language"],
+        }
+    }
+
+    /// Returns the built-in template.
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            PromptKind::Summary => "{thread}",
+            PromptKind::Draft => "{thread}\n\nDraft a reply. {tone}\n\n{instructions}",
+            PromptKind::Classification => {
+                "Categorize the following email into one or more categories: {categories}. \
+                 Return only the category names, comma-separated, most relevant first.\n\n{email}"
+            }
+            PromptKind::SenderAnalysis => {
+                "Analyze the sender email address and determine their likely type. \
+                 Categories: known_contact, newsletter, marketing, recruiter, support, unknown. \
+                 Suggest whether to approve, reject, or review. \
+                 Respond in format: TYPE|REASONING|ACTION\n\nAnalyze sender: {sender}"
+            }
+            PromptKind::Translation => {
+                "Translate the following email into {language}. \
+                 Return only the translation.\n\n{text}"
+            }
+        }
+    }
+
+    /// Returns the variables in `template` this prompt doesn't provide.
+    pub fn unknown_variables(&self, template: &str) -> Vec<String> {
+        let mut unknown = Vec::new();
+        let mut rest = template;
+        while let Some((_, name, after)) = next_variable(rest) {
+            if !self.variables().contains(&name) && !unknown.iter().any(|u| u == name) {
+                unknown.push(name.to_string());
+            }
+            rest = after;
+        }
+        unknown
+    }
+}
+
+/// Finds the next `{name}` variable, returning the text before it, its
+/// name, and the text after it. Braces around anything other than a
+/// lowercase identifier are left as literal text.
+fn next_variable(text: &str) -> Option<(&str, &str, &str)> {
+    let mut offset = 0;
+    while let Some(open) = text[offset..].find('{') {
+        let start = offset + open;
+        if let Some(len) = text[start + 1..].find('}') {
+            let name = &text[start + 1..start + 1 + len];
+            let is_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if is_name {
+                return Some((&text[..start], name, &text[start + len + 2..]));
+            }
+        }
+        offset = start + 1;
+    }
+    None
 }
 
 impl Default for AiSettings {
@@ -294,9 +636,13 @@ impl Default for AiSettings {
         Self {
             enabled: true,
             default_provider: "anthropic".to_string(),
+            base_url: None,
             summary_settings: SummarySettings::default(),
             compose_settings: ComposeSettings::default(),
             search_settings: SearchSettings::default(),
+            translation_settings: TranslationSettings::default(),
+            pricing: TokenPricing::default(),
+            prompts: default_prompts(),
         }
     }
 }
@@ -312,6 +658,38 @@ pub struct SummarySettings {
     pub system_prompt: String,
     /// Maximum summary length in characters.
     pub max_length: usize,
+    /// Fewest messages a thread needs to be summarized automatically.
+    #[serde(default = "default_summary_min_messages")]
+    pub min_messages: usize,
+    /// Fewest characters of new content a thread needs to be summarized
+    /// automatically.
+    #[serde(default = "default_summary_min_chars")]
+    pub min_chars: usize,
+}
+
+impl SummarySettings {
+    /// Returns whether a thread is long enough to summarize without being
+    /// asked: it has at least `min_messages` messages and `min_chars`
+    /// characters of new content, not counting quoted replies.
+    pub fn qualifies_for_auto_summary(&self, thread: &Thread) -> bool {
+        if thread.messages.len() < self.min_messages {
+            return false;
+        }
+        let chars: usize = thread
+            .messages
+            .iter()
+            .map(|email| email.new_content().chars().count())
+            .sum();
+        chars >= self.min_chars
+    }
+}
+
+fn default_summary_min_messages() -> usize {
+    3
+}
+
+fn default_summary_min_chars() -> usize {
+    1500
 }
 
 impl Default for SummarySettings {
@@ -319,10 +697,14 @@ impl Default for SummarySettings {
         Self {
             enabled: true,
             provider: None,
-            system_prompt:
-                "Summarize this email thread concisely. Include key points and any action items."
-                    .to_string(),
+            system_prompt: "Summarize this email thread. Respond with only a JSON object with \
+                 the fields \"tldr\" (one or two sentences), \"action_items\" (a list of \
+                 tasks), \"key_dates\" (a list of {\"label\", \"date\"} objects with \
+                 ISO 8601 dates) and \"participants\" (a list of names)."
+                .to_string(),
             max_length: 500,
+            min_messages: default_summary_min_messages(),
+            min_chars: default_summary_min_chars(),
         }
     }
 }
@@ -394,6 +776,24 @@ impl Default for SearchSettings {
     }
 }
 
+/// Settings for translating incoming mail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationSettings {
+    /// Whether translation is offered for mail in other languages.
+    pub enabled: bool,
+    /// Provider override for translations.
+    pub provider: Option<String>,
+}
+
+impl Default for TranslationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            provider: None,
+        }
+    }
+}
+
 /// AI service for intelligent email features.
 ///
 /// Orchestrates AI providers for summarization, draft generation,
@@ -420,6 +820,12 @@ pub struct AiService {
     embedding_engine: RwLock<Option<Arc<dyn EmbeddingEngine>>>,
     /// AI settings.
     settings: RwLock<AiSettings>,
+    /// Token usage by day, model and kind of completion.
+    usage: RwLock<BTreeMap<(NaiveDate, String, UsageKind), UsageTally>>,
+    /// Cache of generated thread summaries.
+    summary_cache: RwLock<Option<Arc<dyn SummaryCache>>>,
+    /// Translated email bodies by email and target language.
+    translations: RwLock<HashMap<(EmailId, String), String>>,
 }
 
 impl AiService {
@@ -429,6 +835,9 @@ impl AiService {
             providers: RwLock::new(HashMap::new()),
             embedding_engine: RwLock::new(None),
             settings: RwLock::new(settings),
+            usage: RwLock::new(BTreeMap::new()),
+            summary_cache: RwLock::new(None),
+            translations: RwLock::new(HashMap::new()),
         }
     }
 
@@ -444,6 +853,29 @@ impl AiService {
         *embedding = Some(engine);
     }
 
+    /// Drops the embeddings of deleted emails from semantic search,
+    /// returning how many were stored.
+    pub async fn forget_emails(&self, email_ids: &[EmailId]) -> Result<usize> {
+        match self.embedding_engine.read().await.as_ref() {
+            Some(engine) => engine.remove_emails(email_ids).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Sets the cache summaries are stored in and served from.
+    pub async fn set_summary_cache(&self, cache: Arc<dyn SummaryCache>) {
+        let mut summary_cache = self.summary_cache.write().await;
+        *summary_cache = Some(cache);
+    }
+
+    /// Drops a thread's cached summary so the next summary is regenerated.
+    pub async fn invalidate_summary(&self, thread_id: &ThreadId) -> Result<()> {
+        match self.summary_cache.read().await.as_ref() {
+            Some(cache) => cache.invalidate(thread_id).await,
+            None => Ok(()),
+        }
+    }
+
     /// Updates the AI settings.
     pub async fn update_settings(&self, settings: AiSettings) {
         let mut current = self.settings.write().await;
@@ -455,6 +887,61 @@ impl AiService {
         self.settings.read().await.clone()
     }
 
+    /// Returns AI usage for a time range, with its estimated cost.
+    ///
+    /// Counts summaries and drafts generated through this service and the
+    /// tokens used by every completion, priced with the settings' pricing
+    /// table.
+    pub async fn usage_stats(&self, time_range: StatsTimeRange) -> AiStats {
+        let start = time_range.start_date();
+        let end = time_range.end_date();
+        let pricing = self.settings.read().await.pricing.clone();
+
+        let mut stats = AiStats::default();
+        let mut cost = 0.0;
+        for ((date, model, kind), tally) in self.usage.read().await.iter() {
+            if start.is_some_and(|start| *date < start) || *date > end {
+                continue;
+            }
+            match kind {
+                UsageKind::Summary => stats.summaries_generated += tally.completions,
+                UsageKind::Draft => stats.compose_assists += tally.completions,
+                UsageKind::Other => {}
+            }
+            stats.tokens_used += tally.tokens.total_tokens as u64;
+            cost += pricing.cost(model, &tally.tokens);
+        }
+        stats.estimated_cost_usd = cost as f32;
+        stats
+    }
+
+    /// Returns tokens used per day in a time range, oldest first.
+    pub async fn daily_token_usage(
+        &self,
+        time_range: StatsTimeRange,
+    ) -> Vec<(NaiveDate, TokenUsage)> {
+        let start = time_range.start_date();
+        let end = time_range.end_date();
+
+        let mut days: BTreeMap<NaiveDate, TokenUsage> = BTreeMap::new();
+        for ((date, _, _), tally) in self.usage.read().await.iter() {
+            if start.is_some_and(|start| *date < start) || *date > end {
+                continue;
+            }
+            *days.entry(*date).or_default() += &tally.tokens;
+        }
+        days.into_iter().collect()
+    }
+
+    /// Adds a completion's token usage to today's totals.
+    async fn record_usage(&self, kind: UsageKind, model: &str, tokens: &TokenUsage) {
+        let key = (Utc::now().date_naive(), model.to_string(), kind);
+        let mut usage = self.usage.write().await;
+        let tally = usage.entry(key).or_default();
+        tally.completions += 1;
+        tally.tokens += tokens;
+    }
+
     /// Gets a provider by name, falling back to the default.
     async fn get_provider(&self, name: Option<&str>) -> Result<Arc<dyn LlmProvider>> {
         let settings = self.settings.read().await;
@@ -478,31 +965,69 @@ impl AiService {
     /// # Returns
     ///
     /// A summary containing text, key points, and action items.
+    ///
+    /// With a summary cache set, a thread whose messages haven't changed
+    /// since it was last summarized is served from the cache.
     pub async fn summarize_thread(&self, thread: &Thread) -> Result<Summary> {
         let settings = self.settings.read().await;
         if !settings.enabled || !settings.summary_settings.enabled {
             anyhow::bail!("AI summarization is disabled");
         }
 
+        let cache = self.summary_cache.read().await.clone();
+        let content_hash = thread_content_hash(thread);
+        if let Some(cache) = &cache {
+            if let Some(summary) = cache.get(&thread.id, &content_hash).await? {
+                return Ok(summary);
+            }
+        }
+
         let provider = self
             .get_provider(settings.summary_settings.provider.as_deref())
             .await?;
 
         // Build the thread content for summarization
         let thread_content = self.format_thread_for_summary(thread);
+        let prompt = settings.render_prompt(PromptKind::Summary, &[("thread", &thread_content)]);
 
         let request = CompletionRequest {
             system_prompt: Some(settings.summary_settings.system_prompt.clone()),
             messages: vec![ChatMessage {
                 role: ChatRole::User,
-                content: thread_content,
+                content: prompt,
             }],
             temperature: 0.3,
             max_tokens: Some(settings.summary_settings.max_length),
         };
 
         let response = provider.complete(&request).await?;
-        Ok(Summary::parse(&response.text))
+        self.record_usage(UsageKind::Summary, provider.model(), &response.tokens_used)
+            .await;
+
+        let summary = Summary::parse(&response.text);
+        if let Some(cache) = &cache {
+            cache.put(&thread.id, &content_hash, &summary).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Summarizes a thread the user opened without asking for a summary.
+    ///
+    /// Returns `None` without calling a provider when summarization is
+    /// disabled or the thread is below the length thresholds in
+    /// [`SummarySettings`].
+    pub async fn auto_summarize(&self, thread: &Thread) -> Result<Option<Summary>> {
+        {
+            let settings = self.settings.read().await;
+            let summary_settings = &settings.summary_settings;
+            if !settings.enabled
+                || !summary_settings.enabled
+                || !summary_settings.qualifies_for_auto_summary(thread)
+            {
+                return Ok(None);
+            }
+        }
+        self.summarize_thread(thread).await.map(Some)
     }
 
     /// Generates a draft reply for a thread.
@@ -541,11 +1066,17 @@ impl AiService {
             Tone::Custom(prompt) => prompt,
         };
 
-        let mut user_content = format!("{}\n\nDraft a reply. {}", thread_content, tone_instruction);
-
-        if let Some(inst) = instructions {
-            user_content.push_str(&format!("\n\nAdditional instructions: {}", inst));
-        }
+        let instructions = instructions
+            .map(|inst| format!("Additional instructions: {}", inst))
+            .unwrap_or_default();
+        let user_content = settings.render_prompt(
+            PromptKind::Draft,
+            &[
+                ("thread", &thread_content),
+                ("tone", tone_instruction),
+                ("instructions", &instructions),
+            ],
+        );
 
         let request = CompletionRequest {
             system_prompt: Some(settings.compose_settings.system_prompt.clone()),
@@ -558,6 +1089,8 @@ impl AiService {
         };
 
         let response = provider.complete(&request).await?;
+        self.record_usage(UsageKind::Draft, provider.model(), &response.tokens_used)
+            .await;
 
         Ok(DraftSuggestion {
             content: response.text,
@@ -607,7 +1140,7 @@ impl AiService {
             .filter(|(_, score)| *score >= settings.search_settings.min_relevance)
             .map(|(email_id, relevance)| SearchResult {
                 email_id,
-                thread_id: crate::domain::ThreadId::from(""), // Would be filled from storage
+                thread_id: None,
                 subject: None,
                 snippet: String::new(),
                 relevance,
@@ -618,6 +1151,105 @@ impl AiService {
         Ok(search_results)
     }
 
+    /// Finds emails semantically similar to a set of messages.
+    ///
+    /// The messages' stored embeddings are averaged when all of them are
+    /// indexed; otherwise their combined new content is embedded. The
+    /// messages themselves are excluded from the results.
+    pub async fn similar_emails(
+        &self,
+        emails: &[Email],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let settings = self.settings.read().await;
+        if !settings.enabled || !settings.search_settings.enabled {
+            anyhow::bail!("Semantic search is disabled");
+        }
+
+        let engine_guard = self.embedding_engine.read().await;
+        let engine = engine_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No embedding engine configured"))?;
+
+        let mut stored = Vec::with_capacity(emails.len());
+        for email in emails {
+            match engine.stored_embedding(&email.id).await {
+                Some(embedding) => stored.push(embedding),
+                None => break,
+            }
+        }
+
+        let query_embedding = match average_embeddings(&stored) {
+            Some(embedding) if stored.len() == emails.len() => embedding,
+            _ => {
+                let text = emails
+                    .iter()
+                    .map(|e| format!("{} {}", e.subject.as_deref().unwrap_or(""), e.new_content()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                engine.embed(&text).await?
+            }
+        };
+
+        // Over-fetch so excluding the source messages still fills the limit.
+        let own_ids: Vec<&EmailId> = emails.iter().map(|e| &e.id).collect();
+        let results = engine
+            .search(&query_embedding, limit + emails.len())
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .filter(|(email_id, _)| !own_ids.contains(&email_id))
+            .filter(|(_, score)| *score >= settings.search_settings.min_relevance)
+            .take(limit)
+            .map(|(email_id, relevance)| SearchResult {
+                email_id,
+                thread_id: None,
+                subject: None,
+                snippet: String::new(),
+                relevance,
+                highlights: vec![],
+            })
+            .collect())
+    }
+
+    /// Finds emails whose body nearly repeats `email`'s, such as the same
+    /// newsletter forwarded or cross-posted to another list.
+    ///
+    /// Uses the email's stored embedding, or embeds its new content when it
+    /// isn't indexed, and returns up to `limit` other emails whose cosine
+    /// similarity is at least `threshold`, closest first.
+    pub async fn near_duplicates(
+        &self,
+        email: &Email,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<EmailId>> {
+        let settings = self.settings.read().await;
+        if !settings.enabled || !settings.search_settings.enabled {
+            anyhow::bail!("Semantic search is disabled");
+        }
+
+        let engine_guard = self.embedding_engine.read().await;
+        let engine = engine_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No embedding engine configured"))?;
+
+        let embedding = match engine.stored_embedding(&email.id).await {
+            Some(embedding) => embedding,
+            None => engine.embed(&email.new_content()).await?,
+        };
+
+        // One extra, since the email finds itself when it is indexed.
+        let results = engine.search(&embedding, limit + 1).await?;
+        Ok(results
+            .into_iter()
+            .filter(|(email_id, score)| email_id != &email.id && *score >= threshold)
+            .take(limit)
+            .map(|(email_id, _)| email_id)
+            .collect())
+    }
+
     /// Categorizes an email into one or more categories.
     ///
     /// Uses AI to determine the likely categories for an email based
@@ -651,21 +1283,24 @@ impl AiService {
             .collect::<Vec<_>>()
             .join(", ");
 
+        let prompt = settings.render_prompt(
+            PromptKind::Classification,
+            &[("email", &email_content), ("categories", &categories_list)],
+        );
+
         let request = CompletionRequest {
-            system_prompt: Some(format!(
-                "Categorize the following email into one or more categories: {}. \
-                 Return only the category names, comma-separated, most relevant first.",
-                categories_list
-            )),
+            system_prompt: None,
             messages: vec![ChatMessage {
                 role: ChatRole::User,
-                content: email_content,
+                content: prompt,
             }],
             temperature: 0.2,
             max_tokens: Some(100),
         };
 
         let response = provider.complete(&request).await?;
+        self.record_usage(UsageKind::Other, provider.model(), &response.tokens_used)
+            .await;
 
         // Parse categories from response
         let categories: Vec<Category> = response
@@ -693,6 +1328,84 @@ impl AiService {
         Ok(categories)
     }
 
+    /// Detects the language an email is written in.
+    ///
+    /// Runs locally without contacting a provider. Returns an ISO 639-1
+    /// code, or `"und"` when the language can't be determined.
+    pub fn detect_language(&self, email: &Email) -> String {
+        let text = format!(
+            "{}\n{}",
+            email.subject.as_deref().unwrap_or_default(),
+            email.new_content()
+        );
+        detect_language(&text)
+            .unwrap_or(UNDETERMINED_LANGUAGE)
+            .to_string()
+    }
+
+    /// Returns the detected language of an email when a translation into
+    /// `user_language` should be offered.
+    ///
+    /// Nothing is offered while AI or translation is turned off, or when the
+    /// email's language is unknown or already the user's.
+    pub async fn translation_offer(&self, email: &Email, user_language: &str) -> Option<String> {
+        let settings = self.settings.read().await;
+        if !settings.enabled || !settings.translation_settings.enabled {
+            return None;
+        }
+
+        let detected = self.detect_language(email);
+        let user_language = primary_language(user_language);
+        (detected != UNDETERMINED_LANGUAGE && detected != user_language).then_some(detected)
+    }
+
+    /// Translates an email's body into `to_lang`, an ISO 639-1 code such as
+    /// `en` or a locale such as `en-US`.
+    ///
+    /// Translations are cached per email and target language.
+    pub async fn translate(&self, email: &Email, to_lang: &str) -> Result<String> {
+        let settings = self.settings.read().await;
+        if !settings.enabled || !settings.translation_settings.enabled {
+            anyhow::bail!("AI translation is disabled");
+        }
+
+        let key = (email.id.clone(), primary_language(to_lang));
+        if let Some(translation) = self.translations.read().await.get(&key) {
+            return Ok(translation.clone());
+        }
+
+        let provider = self
+            .get_provider(settings.translation_settings.provider.as_deref())
+            .await?;
+
+        let language = language_name(to_lang).unwrap_or(to_lang);
+        let prompt = settings.render_prompt(
+            PromptKind::Translation,
+            &[("text", &email.new_content()), ("language", language)],
+        );
+
+        let request = CompletionRequest {
+            system_prompt: None,
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: prompt,
+            }],
+            temperature: 0.2,
+            max_tokens: None,
+        };
+
+        let response = provider.complete(&request).await?;
+        self.record_usage(UsageKind::Other, provider.model(), &response.tokens_used)
+            .await;
+
+        let translation = response.text.trim().to_string();
+        self.translations
+            .write()
+            .await
+            .insert(key, translation.clone());
+        Ok(translation)
+    }
+
     /// Analyzes a sender to determine their likely type.
     ///
     /// Uses AI and historical data to categorize senders for the screener.
@@ -712,23 +1425,21 @@ impl AiService {
 
         let provider = self.get_provider(None).await?;
 
+        let prompt = settings.render_prompt(PromptKind::SenderAnalysis, &[("sender", sender)]);
+
         let request = CompletionRequest {
-            system_prompt: Some(
-                "Analyze the sender email address and determine their likely type. \
-                 Categories: known_contact, newsletter, marketing, recruiter, support, unknown. \
-                 Suggest whether to approve, reject, or review. \
-                 Respond in format: TYPE|REASONING|ACTION"
-                    .to_string(),
-            ),
+            system_prompt: None,
             messages: vec![ChatMessage {
                 role: ChatRole::User,
-                content: format!("Analyze sender: {}", sender),
+                content: prompt,
             }],
             temperature: 0.2,
             max_tokens: Some(200),
         };
 
         let response = provider.complete(&request).await?;
+        self.record_usage(UsageKind::Other, provider.model(), &response.tokens_used)
+            .await;
 
         // Parse the response
         let parts: Vec<&str> = response.text.split('|').collect();
@@ -782,12 +1493,13 @@ impl AiService {
                 .join(", ")
         );
 
+        // Quoted history repeats earlier messages; send only what each one adds.
         for email in &thread.messages {
             content.push_str(&format!(
                 "---\nFrom: {}\nDate: {}\n\n{}\n",
                 email.from.display(),
                 email.date.format("%Y-%m-%d %H:%M"),
-                email.body_text.as_deref().unwrap_or(&email.snippet)
+                email.new_content()
             ));
         }
 
@@ -815,29 +1527,143 @@ impl AiService {
     }
 }
 
+/// Indexes new mail for semantic search as it arrives.
+#[async_trait::async_trait]
+impl EventSubscriber for AiService {
+    async fn handle(&self, event: &ServiceEvent) {
+        let ServiceEvent::NewEmail { email, .. } = event else {
+            return;
+        };
+        let Some(engine) = self.embedding_engine.read().await.clone() else {
+            return;
+        };
+        if let Err(e) = engine.index_email(email).await {
+            tracing::warn!(email_id = %email.id, "Failed to index email: {}", e);
+        }
+    }
+}
+
+/// Averages embedding vectors component-wise.
+///
+/// Returns `None` for an empty slice or mismatched dimensions.
+fn average_embeddings(embeddings: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let dimension = embeddings.first()?.len();
+    if embeddings.iter().any(|e| e.len() != dimension) {
+        return None;
+    }
+
+    let mut sum = vec![0.0; dimension];
+    for embedding in embeddings {
+        for (total, value) in sum.iter_mut().zip(embedding) {
+            *total += value;
+        }
+    }
+    let count = embeddings.len() as f32;
+    Some(sum.into_iter().map(|v| v / count).collect())
+}
+
+/// Returns the lowercased primary subtag of a language tag, e.g. `pt` for
+/// `pt-BR`.
+fn primary_language(tag: &str) -> String {
+    tag.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Hashes a thread's message ids and bodies, so any new or edited message
+/// changes the hash.
+fn thread_content_hash(thread: &Thread) -> String {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    for email in &thread.messages {
+        for part in [
+            email.id.0.as_str(),
+            email.body_text.as_deref().unwrap_or_default(),
+            email.body_html.as_deref().unwrap_or_default(),
+        ] {
+            // Length-prefix each part so boundaries can't shift between them.
+            context.update(&(part.len() as u64).to_le_bytes());
+            context.update(part.as_bytes());
+        }
+    }
+    context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
-    fn summary_parse_basic() {
-        let text =
-            "This is a summary.\n\nKey points:\n- Point 1\n- Point 2\n\nAction items:\n- Task 1";
+    fn summary_parse_json() {
+        let text = r#"{"tldr": "This is a summary.", "action_items": ["Task 1"],
+            "key_dates": [{"label": "Launch", "date": "2024-03-15T09:00:00Z"}],
+            "participants": ["Ada", "Grace"]}"#;
         let summary = Summary::parse(text);
 
-        assert_eq!(summary.text, "This is a summary.");
-        assert_eq!(summary.key_points, vec!["Point 1", "Point 2"]);
+        assert_eq!(summary.tldr, "This is a summary.");
         assert_eq!(summary.action_items, vec!["Task 1"]);
+        assert_eq!(
+            summary.key_dates,
+            vec![(
+                "Launch".to_string(),
+                Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap()
+            )]
+        );
+        assert_eq!(summary.participants, vec!["Ada", "Grace"]);
     }
 
     #[test]
-    fn summary_parse_no_sections() {
-        let text = "Just a simple summary without sections.";
+    fn summary_parse_messy_output() {
+        let text = r#"Sure! Here's the summary:
+
+```json
+{
+  "summary": "Budget review moved.",
+  "action_items": ["Send slides", 3, ""],
+  "key_dates": [
+    ["Review", "2024-04-02"],
+    {"event": "Offsite", "date": "2024-04-10 14:30"},
+    {"label": "Sometime", "date": "next week"}
+  ]
+}
+```
+Let me know!"#;
         let summary = Summary::parse(text);
 
-        assert_eq!(summary.text, "Just a simple summary without sections.");
-        assert!(summary.key_points.is_empty());
+        assert_eq!(summary.tldr, "Budget review moved.");
+        assert_eq!(summary.action_items, vec!["Send slides"]);
+        assert_eq!(
+            summary.key_dates,
+            vec![
+                (
+                    "Review".to_string(),
+                    Utc.with_ymd_and_hms(2024, 4, 2, 0, 0, 0).unwrap()
+                ),
+                (
+                    "Offsite".to_string(),
+                    Utc.with_ymd_and_hms(2024, 4, 10, 14, 30, 0).unwrap()
+                ),
+            ]
+        );
+        assert!(summary.participants.is_empty());
+    }
+
+    #[test]
+    fn summary_parse_malformed_falls_back_to_tldr() {
+        let text = "Just a simple summary without JSON.";
+        let summary = Summary::parse(text);
+        assert_eq!(summary.tldr, "Just a simple summary without JSON.");
         assert!(summary.action_items.is_empty());
+        assert!(summary.key_dates.is_empty());
+
+        let truncated = "```json\n{\"tldr\": \"Cut off";
+        assert_eq!(Summary::parse(truncated).tldr, "{\"tldr\": \"Cut off");
     }
 
     #[test]
@@ -865,6 +1691,362 @@ mod tests {
         assert_eq!(deserialized, Category::Finance);
     }
 
+    /// Provider returning the same response and usage for every request.
+    struct FixedProvider {
+        model: &'static str,
+        usage: TokenUsage,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn model(&self) -> &str {
+            self.model
+        }
+
+        async fn complete(&self, _request: &CompletionRequest) -> Result<CompletionResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse {
+                text: "Short summary.".to_string(),
+                tokens_used: self.usage,
+                finish_reason: FinishReason::Stop,
+            })
+        }
+
+        fn max_context_length(&self) -> usize {
+            8_192
+        }
+    }
+
+    fn empty_thread() -> Thread {
+        Thread {
+            id: crate::domain::ThreadId::from("thread-1"),
+            account_id: AccountId::from("account-1"),
+            subject: Some("Plans".to_string()),
+            snippet: String::new(),
+            participants: vec![],
+            messages: vec![],
+            last_message_date: Utc::now(),
+            unread_count: 0,
+            is_starred: false,
+            labels: vec![],
+        }
+    }
+
+    async fn service_with(model: &'static str, settings: AiSettings) -> AiService {
+        let default_provider = settings.default_provider.clone();
+        let service = AiService::new(settings);
+        let usage = TokenUsage {
+            prompt_tokens: 400_000,
+            completion_tokens: 100_000,
+            total_tokens: 500_000,
+        };
+        service
+            .register_provider(
+                default_provider,
+                Arc::new(FixedProvider {
+                    model,
+                    usage,
+                    calls: AtomicUsize::new(0),
+                }),
+            )
+            .await;
+        service
+    }
+
+    /// In-memory summary cache.
+    #[derive(Default)]
+    struct MemoryCache {
+        summaries: std::sync::Mutex<HashMap<ThreadId, (String, Summary)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SummaryCache for MemoryCache {
+        async fn get(&self, thread_id: &ThreadId, content_hash: &str) -> Result<Option<Summary>> {
+            let summaries = self.summaries.lock().unwrap();
+            Ok(summaries
+                .get(thread_id)
+                .filter(|(hash, _)| hash == content_hash)
+                .map(|(_, summary)| summary.clone()))
+        }
+
+        async fn put(
+            &self,
+            thread_id: &ThreadId,
+            content_hash: &str,
+            summary: &Summary,
+        ) -> Result<()> {
+            self.summaries.lock().unwrap().insert(
+                thread_id.clone(),
+                (content_hash.to_string(), summary.clone()),
+            );
+            Ok(())
+        }
+
+        async fn invalidate(&self, thread_id: &ThreadId) -> Result<()> {
+            self.summaries.lock().unwrap().remove(thread_id);
+            Ok(())
+        }
+    }
+
+    fn message(id: &str, body: &str) -> Email {
+        Email {
+            to: vec![],
+            subject: Some("Plans".to_string()),
+            body_text: Some(body.to_string()),
+            snippet: body.to_string(),
+            ..crate::domain::test_email(id, "thread-1")
+        }
+    }
+
+    #[tokio::test]
+    async fn summaries_are_cached_by_thread_content() {
+        let settings = AiSettings::default();
+        let service = AiService::new(settings.clone());
+        let provider = Arc::new(FixedProvider {
+            model: "fixed",
+            usage: TokenUsage::default(),
+            calls: AtomicUsize::new(0),
+        });
+        service
+            .register_provider(settings.default_provider, provider.clone())
+            .await;
+        service
+            .set_summary_cache(Arc::new(MemoryCache::default()))
+            .await;
+
+        let mut thread = empty_thread();
+        thread.messages.push(message("e1", "Lunch on Friday?"));
+
+        let first = service.summarize_thread(&thread).await.unwrap();
+        let second = service.summarize_thread(&thread).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first, second);
+
+        // A new message changes the content hash.
+        thread.messages.push(message("e2", "Friday works."));
+        service.summarize_thread(&thread).await.unwrap();
+        service.summarize_thread(&thread).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+        service.invalidate_summary(&thread.id).await.unwrap();
+        service.summarize_thread(&thread).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn only_long_threads_qualify_for_auto_summary() {
+        let settings = SummarySettings::default();
+
+        let mut short = empty_thread();
+        short.messages.push(message("e1", "Lunch on Friday?"));
+        short.messages.push(message("e2", "Friday works."));
+        assert!(!settings.qualifies_for_auto_summary(&short));
+
+        let paragraph = "We still need sign-off on the budget before the vendor call. ";
+        let mut long = empty_thread();
+        for i in 0..10 {
+            let body = paragraph.repeat(4);
+            long.messages.push(message(&format!("e{}", i), &body));
+        }
+        assert!(settings.qualifies_for_auto_summary(&long));
+
+        // Many short messages aren't enough on their own.
+        let mut chatty = empty_thread();
+        for i in 0..10 {
+            chatty
+                .messages
+                .push(message(&format!("e{}", i), "Sounds good."));
+        }
+        assert!(!settings.qualifies_for_auto_summary(&chatty));
+    }
+
+    #[tokio::test]
+    async fn translations_are_cached_per_language() {
+        let settings = AiSettings::default();
+        let service = AiService::new(settings.clone());
+        let provider = Arc::new(FixedProvider {
+            model: "fixed",
+            usage: TokenUsage::default(),
+            calls: AtomicUsize::new(0),
+        });
+        service
+            .register_provider(settings.default_provider, provider.clone())
+            .await;
+
+        let email = message("e1", "Hola, gracias por la información.");
+        assert_eq!(service.detect_language(&email), "es");
+        assert_eq!(
+            service.translation_offer(&email, "en-US").await.as_deref(),
+            Some("es")
+        );
+        assert_eq!(service.translation_offer(&email, "es").await, None);
+
+        service.translate(&email, "en").await.unwrap();
+        service.translate(&email, "EN-us").await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        service.translate(&email, "fr").await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn translation_respects_toggles() {
+        let mut settings = AiSettings::default();
+        settings.translation_settings.enabled = false;
+        let service = service_with("fixed", settings).await;
+
+        let email = message("e1", "Bonjour, merci pour votre message.");
+        assert_eq!(service.translation_offer(&email, "en").await, None);
+        assert!(service.translate(&email, "en").await.is_err());
+
+        let mut settings = AiSettings::default();
+        settings.enabled = false;
+        service.update_settings(settings).await;
+        assert!(service.translate(&email, "en").await.is_err());
+    }
+
+    #[test]
+    fn content_hash_covers_ids_and_bodies() {
+        let mut thread = empty_thread();
+        thread.messages.push(message("e1", "Hello"));
+        let hash = thread_content_hash(&thread);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, thread_content_hash(&thread));
+
+        let mut edited = thread.clone();
+        edited.messages[0].body_text = Some("Hello!".to_string());
+        assert_ne!(hash, thread_content_hash(&edited));
+
+        let mut renamed = thread.clone();
+        renamed.messages[0].id = EmailId::from("e2");
+        assert_ne!(hash, thread_content_hash(&renamed));
+    }
+
+    #[tokio::test]
+    async fn usage_is_summed_across_summaries_and_drafts() {
+        let service = service_with("gpt-4o-mini-2024-07-18", AiSettings::default()).await;
+        let thread = empty_thread();
+
+        service.summarize_thread(&thread).await.unwrap();
+        service.summarize_thread(&thread).await.unwrap();
+        service.draft_reply(&thread, None).await.unwrap();
+
+        let stats = service.usage_stats(StatsTimeRange::Month).await;
+        assert_eq!(stats.summaries_generated, 2);
+        assert_eq!(stats.compose_assists, 1);
+        assert_eq!(stats.tokens_used, 1_500_000);
+        // gpt-4o-mini: 3 x (0.4M x $0.15 + 0.1M x $0.60) per million.
+        assert!((stats.estimated_cost_usd - 0.36).abs() < 1e-4);
+        assert_eq!(stats.usage_summary(), "1.5M tokens (~$0.36)");
+
+        let daily = service.daily_token_usage(StatsTimeRange::Today).await;
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].1.prompt_tokens, 1_200_000);
+        assert_eq!(daily[0].1.completion_tokens, 300_000);
+    }
+
+    #[tokio::test]
+    async fn pricing_is_configurable() {
+        let mut settings = AiSettings::default();
+        settings
+            .pricing
+            .0
+            .insert("llama3".to_string(), ModelPricing::new(1.0, 2.0));
+        let service = service_with("llama3:8b", settings).await;
+        service.summarize_thread(&empty_thread()).await.unwrap();
+
+        let stats = service.usage_stats(StatsTimeRange::AllTime).await;
+        assert!((stats.estimated_cost_usd - 0.6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pricing_uses_longest_matching_prefix() {
+        let pricing = TokenPricing::default();
+        assert_eq!(
+            pricing.for_model("gpt-4o-mini-2024-07-18"),
+            Some(&ModelPricing::new(0.15, 0.60))
+        );
+        assert_eq!(
+            pricing.for_model("gpt-4o-2024-08-06"),
+            Some(&ModelPricing::new(2.50, 10.00))
+        );
+        assert_eq!(pricing.cost("mistral", &TokenUsage::default()), 0.0);
+        assert!(pricing.for_model("mistral").is_none());
+    }
+
+    #[test]
+    fn render_prompt_substitutes_variables() {
+        let mut settings = AiSettings::default();
+        settings.prompts.insert(
+            PromptKind::Summary,
+            "Summarize in one sentence:\n{thread}".to_string(),
+        );
+
+        assert_eq!(
+            settings.render_prompt(PromptKind::Summary, &[("thread", "Alice: Lunch?")]),
+            "Summarize in one sentence:\nAlice: Lunch?"
+        );
+        assert_eq!(
+            settings.render_prompt(
+                PromptKind::Draft,
+                &[("thread", "Alice: Lunch?"), ("tone", "Be brief.")]
+            ),
+            "Alice: Lunch?\n\nDraft a reply. Be brief."
+        );
+    }
+
+    #[test]
+    fn render_prompt_leaves_other_braces_alone() {
+        let mut settings = AiSettings::default();
+        settings.prompts.insert(
+            PromptKind::SenderAnalysis,
+            r#"Reply as {"type": "..."} for {sender}, not { sender }"#.to_string(),
+        );
+
+        assert_eq!(
+            settings.render_prompt(PromptKind::SenderAnalysis, &[("sender", "a@b.com")]),
+            r#"Reply as {"type": "..."} for a@b.com, not { sender }"#
+        );
+    }
+
+    #[test]
+    fn render_prompt_falls_back_on_unknown_variables() {
+        let mut settings = AiSettings::default();
+        settings.prompts.insert(
+            PromptKind::Summary,
+            "Summarize {thred} as {style}".to_string(),
+        );
+
+        assert_eq!(
+            PromptKind::Summary.unknown_variables("Summarize {thred} as {style}"),
+            vec!["thred".to_string(), "style".to_string()]
+        );
+        assert_eq!(
+            settings.render_prompt(PromptKind::Summary, &[("thread", "Alice: Lunch?")]),
+            "Alice: Lunch?"
+        );
+
+        settings.prompts.remove(&PromptKind::Summary);
+        assert_eq!(
+            settings.render_prompt(PromptKind::Summary, &[("thread", "Alice: Lunch?")]),
+            "Alice: Lunch?"
+        );
+    }
+
+    #[test]
+    fn default_prompts_use_known_variables() {
+        for kind in PromptKind::all() {
+            assert!(kind.unknown_variables(kind.default_template()).is_empty());
+        }
+
+        let json = serde_json::to_value(AiSettings::default()).unwrap();
+        assert!(json["prompts"]["sender_analysis"].is_string());
+    }
+
     #[test]
     fn ai_settings_default() {
         let settings = AiSettings::default();
@@ -888,7 +2070,7 @@ mod tests {
     fn search_result_serialization() {
         let result = SearchResult {
             email_id: EmailId::from("email-1"),
-            thread_id: crate::domain::ThreadId::from("thread-1"),
+            thread_id: Some(crate::domain::ThreadId::from("thread-1")),
             subject: Some("Test".to_string()),
             snippet: "Preview...".to_string(),
             relevance: 0.95,
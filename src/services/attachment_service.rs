@@ -0,0 +1,310 @@
+//! Attachment service for previews.
+//!
+//! The [`AttachmentService`] turns downloaded image and PDF attachments into
+//! small PNG thumbnails for the reading pane. Thumbnails are cached with the
+//! attachment's content in the blob store, keyed by its hash and the
+//! thumbnail size, so each is rendered once.
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::domain::Attachment;
+
+/// PNG-encoded image data.
+pub type ImageBytes = Vec<u8>;
+
+/// Image types thumbnails are made from, as decoded by the `image` crate.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Storage for attachment content and cached thumbnails.
+#[async_trait::async_trait]
+pub trait AttachmentStorage: Send + Sync {
+    /// Loads downloaded attachment content by its hash.
+    async fn load_blob(&self, content_hash: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Loads a cached thumbnail of the content with `content_hash`.
+    async fn load_thumbnail(&self, content_hash: &str, size: u32) -> Result<Option<ImageBytes>>;
+
+    /// Caches a thumbnail of the content with `content_hash`.
+    async fn store_thumbnail(&self, content_hash: &str, size: u32, png: &[u8]) -> Result<()>;
+}
+
+/// Renders the first page of a PDF.
+///
+/// PDF rendering needs a native library, so it is supplied by the
+/// application, e.g. [`PdftoppmRenderer`]. Without a renderer, PDFs get no
+/// thumbnail.
+pub trait PdfRenderer: Send + Sync {
+    /// Renders the first page of `pdf` as an image in any format the
+    /// `image` crate decodes, at least `size` pixels on its longer side.
+    fn render_first_page(&self, pdf: &[u8], size: u32) -> Result<Vec<u8>>;
+}
+
+/// Renders PDFs with Poppler's `pdftoppm` command.
+#[derive(Debug, Clone)]
+pub struct PdftoppmRenderer {
+    program: PathBuf,
+}
+
+impl PdftoppmRenderer {
+    /// Returns a renderer if `pdftoppm` is installed on the `PATH`.
+    pub fn detect() -> Option<Self> {
+        let renderer = Self {
+            program: PathBuf::from("pdftoppm"),
+        };
+        let installed = Command::new(&renderer.program)
+            .arg("-v")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        installed.then_some(renderer)
+    }
+}
+
+impl PdfRenderer for PdftoppmRenderer {
+    fn render_first_page(&self, pdf: &[u8], size: u32) -> Result<Vec<u8>> {
+        let dir = std::env::temp_dir().join(format!("heap-pdf-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir)?;
+        let rendered = (|| -> Result<Vec<u8>> {
+            let input = dir.join("input.pdf");
+            std::fs::write(&input, pdf)?;
+            let output = Command::new(&self.program)
+                .args(["-png", "-singlefile", "-f", "1", "-l", "1", "-scale-to"])
+                .arg(size.to_string())
+                .arg(&input)
+                .arg(dir.join("page"))
+                .output()
+                .context("Failed to run pdftoppm")?;
+            anyhow::ensure!(
+                output.status.success(),
+                "pdftoppm failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            Ok(std::fs::read(dir.join("page.png"))?)
+        })();
+        let _ = std::fs::remove_dir_all(&dir);
+        rendered
+    }
+}
+
+/// What a thumbnail is made from.
+enum PreviewKind {
+    Image,
+    Pdf(Arc<dyn PdfRenderer>),
+}
+
+/// Generates and caches attachment thumbnails.
+pub struct AttachmentService<S: AttachmentStorage> {
+    storage: Arc<S>,
+    pdf_renderer: Option<Arc<dyn PdfRenderer>>,
+}
+
+impl<S: AttachmentStorage> AttachmentService<S> {
+    /// Creates a service that previews images only.
+    pub fn new(storage: Arc<S>) -> Self {
+        Self {
+            storage,
+            pdf_renderer: None,
+        }
+    }
+
+    /// Previews PDFs with `renderer`.
+    pub fn with_pdf_renderer(mut self, renderer: Arc<dyn PdfRenderer>) -> Self {
+        self.pdf_renderer = Some(renderer);
+        self
+    }
+
+    /// Returns a PNG thumbnail of an attachment fitting in `size` by `size`
+    /// pixels, keeping its aspect ratio.
+    ///
+    /// Images are downscaled and PDFs show their first page. Returns `None`
+    /// for other types, attachments that haven't been downloaded and
+    /// content that can't be decoded, so the caller shows an icon instead.
+    pub async fn thumbnail(&self, attachment: &Attachment, size: u32) -> Option<ImageBytes> {
+        let kind = self.preview_kind(&attachment.content_type)?;
+        let content_hash = attachment.content_hash.as_deref()?;
+
+        match self.render(kind, content_hash, size).await {
+            Ok(thumbnail) => thumbnail,
+            Err(e) => {
+                tracing::warn!("Failed to preview attachment {}: {:#}", attachment.id, e);
+                None
+            }
+        }
+    }
+
+    /// Whether attachments of `content_type` get a thumbnail once their
+    /// content is downloaded.
+    pub fn can_preview(&self, content_type: &str) -> bool {
+        self.preview_kind(content_type).is_some()
+    }
+
+    fn preview_kind(&self, content_type: &str) -> Option<PreviewKind> {
+        let content_type = content_type.trim().to_ascii_lowercase();
+        if IMAGE_TYPES.contains(&content_type.as_str()) {
+            return Some(PreviewKind::Image);
+        }
+        match (content_type.as_str(), &self.pdf_renderer) {
+            ("application/pdf", Some(renderer)) => Some(PreviewKind::Pdf(renderer.clone())),
+            _ => None,
+        }
+    }
+
+    async fn render(
+        &self,
+        kind: PreviewKind,
+        content_hash: &str,
+        size: u32,
+    ) -> Result<Option<ImageBytes>> {
+        if let Some(cached) = self.storage.load_thumbnail(content_hash, size).await? {
+            return Ok(Some(cached));
+        }
+        let Some(content) = self.storage.load_blob(content_hash).await? else {
+            return Ok(None);
+        };
+
+        let png = tokio::task::spawn_blocking(move || {
+            let image = match kind {
+                PreviewKind::Image => content,
+                PreviewKind::Pdf(renderer) => renderer.render_first_page(&content, size)?,
+            };
+            downscale(&image, size)
+        })
+        .await??;
+
+        self.storage
+            .store_thumbnail(content_hash, size, &png)
+            .await?;
+        Ok(Some(png))
+    }
+}
+
+/// Decodes an image and encodes it as a PNG fitting in `size` by `size`.
+/// Images already that small are only re-encoded.
+fn downscale(image: &[u8], size: u32) -> Result<ImageBytes> {
+    let image = image::load_from_memory(image).context("Unreadable image")?;
+    let thumbnail = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size)
+    } else {
+        image
+    };
+
+    let mut png = Vec::new();
+    thumbnail.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        blobs: HashMap<String, Vec<u8>>,
+        thumbnails: Mutex<HashMap<(String, u32), Vec<u8>>>,
+        blob_loads: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl AttachmentStorage for MemoryStorage {
+        async fn load_blob(&self, content_hash: &str) -> Result<Option<Vec<u8>>> {
+            self.blob_loads.fetch_add(1, Ordering::SeqCst);
+            Ok(self.blobs.get(content_hash).cloned())
+        }
+
+        async fn load_thumbnail(&self, content_hash: &str, size: u32) -> Result<Option<Vec<u8>>> {
+            let thumbnails = self.thumbnails.lock().unwrap();
+            Ok(thumbnails.get(&(content_hash.to_string(), size)).cloned())
+        }
+
+        async fn store_thumbnail(&self, content_hash: &str, size: u32, png: &[u8]) -> Result<()> {
+            let mut thumbnails = self.thumbnails.lock().unwrap();
+            thumbnails.insert((content_hash.to_string(), size), png.to_vec());
+            Ok(())
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([200, 40, 40, 255]));
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    fn attachment(content_type: &str, content_hash: &str) -> Attachment {
+        Attachment {
+            id: "att-1".to_string(),
+            filename: "photo".to_string(),
+            content_type: content_type.to_string(),
+            size_bytes: 0,
+            is_inline: false,
+            content_hash: Some(content_hash.to_string()),
+            content_id: None,
+        }
+    }
+
+    fn service() -> (AttachmentService<MemoryStorage>, Arc<MemoryStorage>) {
+        let mut storage = MemoryStorage::default();
+        storage.blobs.insert("photo".to_string(), png(400, 200));
+        storage
+            .blobs
+            .insert("doc".to_string(), b"%PDF-1.7".to_vec());
+        let storage = Arc::new(storage);
+        (AttachmentService::new(storage.clone()), storage)
+    }
+
+    #[tokio::test]
+    async fn thumbnails_are_cached_by_content_and_size() {
+        let (service, storage) = service();
+        let photo = attachment("image/png", "photo");
+
+        let first = service.thumbnail(&photo, 64).await.unwrap();
+        let preview = image::load_from_memory(&first).unwrap();
+        assert_eq!((preview.width(), preview.height()), (64, 32));
+
+        let second = service.thumbnail(&photo, 64).await.unwrap();
+        assert_eq!(second, first);
+        assert_eq!(storage.blob_loads.load(Ordering::SeqCst), 1);
+
+        service.thumbnail(&photo, 128).await.unwrap();
+        assert_eq!(storage.blob_loads.load(Ordering::SeqCst), 2);
+    }
+
+    struct SolidPage;
+
+    impl PdfRenderer for SolidPage {
+        fn render_first_page(&self, pdf: &[u8], size: u32) -> Result<Vec<u8>> {
+            anyhow::ensure!(pdf.starts_with(b"%PDF"), "not a PDF");
+            Ok(png(size * 3 / 4, size))
+        }
+    }
+
+    #[tokio::test]
+    async fn unsupported_attachments_have_no_thumbnail() {
+        let (service, _) = service();
+        assert!(service
+            .thumbnail(&attachment("application/zip", "doc"), 64)
+            .await
+            .is_none());
+        assert!(service
+            .thumbnail(&attachment("image/png", "missing"), 64)
+            .await
+            .is_none());
+
+        // PDFs need a renderer.
+        let pdf = attachment("application/pdf", "doc");
+        assert!(service.thumbnail(&pdf, 64).await.is_none());
+        let service = service.with_pdf_renderer(Arc::new(SolidPage));
+        let page = service.thumbnail(&pdf, 64).await.unwrap();
+        let preview = image::load_from_memory(&page).unwrap();
+        assert_eq!((preview.width(), preview.height()), (48, 64));
+    }
+}
@@ -0,0 +1,339 @@
+//! Blocklist service for senders whose mail never reaches the inbox.
+//!
+//! Unlike the screener, which asks about unknown senders, the blocklist is
+//! enforced during sync regardless of screener state. Patterns are matched
+//! case-insensitively against the sender address:
+//! - `spammer@example.com` matches one address
+//! - `example.com` matches every address at that domain
+//! - `*@example.com`, `news*@*.example.com` use `*` as a wildcard
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::Address;
+
+/// Errors that can occur during blocklist operations.
+#[derive(Debug, Error)]
+pub enum BlocklistError {
+    #[error("Pattern not found: {0}")]
+    NotFound(String),
+
+    #[error("Pattern already blocked: {0}")]
+    AlreadyBlocked(String),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
+
+/// Result type for blocklist operations.
+pub type Result<T> = std::result::Result<T, BlocklistError>;
+
+/// What happens to mail from a blocked sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockAction {
+    /// Move to trash.
+    #[default]
+    Trash,
+    /// Archive out of the inbox.
+    Archive,
+}
+
+/// A blocked sender pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEntry {
+    /// Normalized pattern, e.g. `*@example.com`.
+    pub pattern: String,
+    /// What to do with matching mail.
+    pub action: BlockAction,
+    /// Whether matching mail is also reported to the provider as spam.
+    pub report_spam: bool,
+    /// When the pattern was added.
+    pub created_at: DateTime<Utc>,
+}
+
+impl BlockEntry {
+    /// Returns whether this entry matches `address`.
+    pub fn matches(&self, address: &Address) -> bool {
+        pattern_matches(&self.pattern, &address.email.to_lowercase())
+    }
+}
+
+/// A snapshot of blocked patterns, checked when new mail is applied.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    entries: Vec<BlockEntry>,
+}
+
+impl Blocklist {
+    /// Creates a blocklist from entries.
+    pub fn new(entries: Vec<BlockEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Returns the first entry matching `address`.
+    pub fn matching(&self, address: &Address) -> Option<&BlockEntry> {
+        self.entries.iter().find(|e| e.matches(address))
+    }
+
+    /// Returns whether mail from `address` is blocked.
+    pub fn is_blocked(&self, address: &Address) -> bool {
+        self.matching(address).is_some()
+    }
+
+    /// Returns whether the blocklist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Storage trait for blocklist persistence.
+pub trait BlocklistStorage: Send + Sync {
+    /// Gets all blocked patterns.
+    fn list(&self) -> Result<Vec<BlockEntry>>;
+
+    /// Stores or updates an entry.
+    fn save(&self, entry: &BlockEntry) -> Result<()>;
+
+    /// Deletes an entry by pattern.
+    fn delete(&self, pattern: &str) -> Result<()>;
+}
+
+/// Service for managing blocked senders.
+pub struct BlocklistService<S: BlocklistStorage> {
+    storage: S,
+}
+
+impl<S: BlocklistStorage> BlocklistService<S> {
+    /// Creates a new blocklist service.
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Blocks a pattern, trashing matching mail.
+    pub fn block(&self, pattern: &str) -> Result<BlockEntry> {
+        self.block_with(pattern, BlockAction::default(), false)
+    }
+
+    /// Blocks a pattern with the given action.
+    pub fn block_with(
+        &self,
+        pattern: &str,
+        action: BlockAction,
+        report_spam: bool,
+    ) -> Result<BlockEntry> {
+        let pattern = normalize_pattern(pattern)?;
+
+        if self.storage.list()?.iter().any(|e| e.pattern == pattern) {
+            return Err(BlocklistError::AlreadyBlocked(pattern));
+        }
+
+        let entry = BlockEntry {
+            pattern,
+            action,
+            report_spam,
+            created_at: Utc::now(),
+        };
+        self.storage.save(&entry)?;
+        Ok(entry)
+    }
+
+    /// Removes a blocked pattern.
+    pub fn unblock(&self, pattern: &str) -> Result<()> {
+        let pattern = normalize_pattern(pattern)?;
+        if !self.storage.list()?.iter().any(|e| e.pattern == pattern) {
+            return Err(BlocklistError::NotFound(pattern));
+        }
+        self.storage.delete(&pattern)
+    }
+
+    /// Gets all blocked patterns.
+    pub fn list(&self) -> Result<Vec<BlockEntry>> {
+        self.storage.list()
+    }
+
+    /// Returns whether mail from `address` is blocked.
+    pub fn is_blocked(&self, address: &Address) -> Result<bool> {
+        Ok(self.blocklist()?.is_blocked(address))
+    }
+
+    /// Returns a snapshot of the blocklist for enforcement during sync.
+    pub fn blocklist(&self) -> Result<Blocklist> {
+        Ok(Blocklist::new(self.storage.list()?))
+    }
+}
+
+/// Lowercases and validates a pattern. A leading `@` is shorthand for
+/// `*@`.
+fn normalize_pattern(pattern: &str) -> Result<String> {
+    let pattern = pattern.trim().to_lowercase();
+    let pattern = match pattern.strip_prefix('@') {
+        Some(domain) => format!("*@{}", domain),
+        None => pattern,
+    };
+
+    let invalid = pattern.is_empty()
+        || pattern.chars().any(char::is_whitespace)
+        || pattern.matches('@').count() > 1
+        || pattern.ends_with('@')
+        || pattern.chars().all(|c| c == '*' || c == '@' || c == '.');
+    if invalid {
+        return Err(BlocklistError::InvalidPattern(pattern));
+    }
+    Ok(pattern)
+}
+
+/// Matches a normalized pattern against a lowercased address. Patterns
+/// without `@` match the address's domain.
+fn pattern_matches(pattern: &str, address: &str) -> bool {
+    if pattern.contains('@') {
+        return glob_matches(pattern, address);
+    }
+    match address.rsplit_once('@') {
+        Some((_, domain)) => glob_matches(pattern, domain),
+        None => false,
+    }
+}
+
+/// Matches `text` against `pattern`, where `*` matches any run of
+/// characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::RwLock;
+
+    struct MockStorage {
+        entries: RwLock<Vec<BlockEntry>>,
+    }
+
+    impl MockStorage {
+        fn new() -> Self {
+            Self {
+                entries: RwLock::new(Vec::new()),
+            }
+        }
+    }
+
+    impl BlocklistStorage for MockStorage {
+        fn list(&self) -> Result<Vec<BlockEntry>> {
+            Ok(self.entries.read().unwrap().clone())
+        }
+
+        fn save(&self, entry: &BlockEntry) -> Result<()> {
+            let mut entries = self.entries.write().unwrap();
+            entries.retain(|e| e.pattern != entry.pattern);
+            entries.push(entry.clone());
+            Ok(())
+        }
+
+        fn delete(&self, pattern: &str) -> Result<()> {
+            self.entries
+                .write()
+                .unwrap()
+                .retain(|e| e.pattern != pattern);
+            Ok(())
+        }
+    }
+
+    fn blocked(patterns: &[&str], email: &str) -> bool {
+        let service = BlocklistService::new(MockStorage::new());
+        for pattern in patterns {
+            service.block(pattern).unwrap();
+        }
+        service.is_blocked(&Address::new(email)).unwrap()
+    }
+
+    #[test]
+    fn exact_address_match() {
+        assert!(blocked(&["spam@example.com"], "spam@example.com"));
+        assert!(blocked(&["spam@example.com"], "SPAM@Example.com"));
+        assert!(!blocked(&["spam@example.com"], "ham@example.com"));
+        assert!(!blocked(&["spam@example.com"], "spam@example.com.evil"));
+    }
+
+    #[test]
+    fn domain_match() {
+        assert!(blocked(&["spammer.com"], "anyone@spammer.com"));
+        assert!(blocked(&["@spammer.com"], "anyone@spammer.com"));
+        assert!(!blocked(&["spammer.com"], "anyone@notspammer.com"));
+        assert!(!blocked(&["spammer.com"], "anyone@mail.spammer.com"));
+    }
+
+    #[test]
+    fn wildcard_match() {
+        assert!(blocked(&["*@spammer.com"], "deals@spammer.com"));
+        assert!(!blocked(&["*@spammer.com"], "deals@mail.spammer.com"));
+        assert!(blocked(&["*.spammer.com"], "deals@mail.spammer.com"));
+        assert!(blocked(
+            &["news*@*.example.com"],
+            "newsletter@eu.example.com"
+        ));
+        assert!(!blocked(&["news*@*.example.com"], "alerts@eu.example.com"));
+    }
+
+    #[test]
+    fn block_rejects_invalid_and_duplicate_patterns() {
+        let service = BlocklistService::new(MockStorage::new());
+        for pattern in ["", "  ", "a@b@c", "spam@", "*", "*@*.*", "bad pattern.com"] {
+            assert!(
+                matches!(
+                    service.block(pattern),
+                    Err(BlocklistError::InvalidPattern(_))
+                ),
+                "{:?} should be rejected",
+                pattern
+            );
+        }
+
+        service.block("*@Spammer.com").unwrap();
+        assert!(matches!(
+            service.block("@spammer.com"),
+            Err(BlocklistError::AlreadyBlocked(_))
+        ));
+    }
+
+    #[test]
+    fn unblock_removes_pattern() {
+        let service = BlocklistService::new(MockStorage::new());
+        service
+            .block_with("spammer.com", BlockAction::Archive, true)
+            .unwrap();
+        assert!(service.is_blocked(&Address::new("x@spammer.com")).unwrap());
+
+        service.unblock("SPAMMER.com").unwrap();
+        assert!(!service.is_blocked(&Address::new("x@spammer.com")).unwrap());
+        assert!(matches!(
+            service.unblock("spammer.com"),
+            Err(BlocklistError::NotFound(_))
+        ));
+    }
+}
@@ -5,10 +5,17 @@
 //! - VIP status management
 //! - Frequency tracking
 //! - Search and filtering
+//! - Avatar resolution
+//! - Duplicate detection and merging
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{PoisonError, RwLock};
 
 use thiserror::Error;
 
 use crate::domain::{Address, Contact};
+use crate::services::{EventSubscriber, ServiceEvent};
 
 /// Errors that can occur during contact operations.
 #[derive(Debug, Error)]
@@ -105,7 +112,9 @@ impl ContactFilter {
                 .as_ref()
                 .map(|n| n.to_lowercase().contains(&query_lower))
                 .unwrap_or(false);
-            let email_match = contact.email.to_lowercase().contains(&query_lower);
+            let email_match = contact
+                .addresses()
+                .any(|email| email.to_lowercase().contains(&query_lower));
             if !name_match && !email_match {
                 return false;
             }
@@ -128,6 +137,45 @@ pub struct ContactStats {
     pub most_contacted: Option<String>,
 }
 
+/// Contacts as they were before a merge, to undo it with
+/// [`ContactService::undo_merge`].
+#[derive(Debug, Clone)]
+pub struct ContactMerge {
+    /// The merged contact.
+    pub contact: Contact,
+    /// The primary contact before the merge, then the contacts folded into
+    /// it.
+    previous: Vec<Contact>,
+}
+
+/// Size in pixels requested from Gravatar, enough for the largest avatar
+/// on a high-density display.
+const GRAVATAR_SIZE: u32 = 96;
+
+/// What to show as a contact's avatar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvatarSource {
+    /// Photo URL supplied by the account's provider.
+    Photo(String),
+    /// Gravatar image URL. The request fails with 404 when the address has
+    /// no Gravatar; report that with
+    /// [`ContactService::mark_gravatar_missing`] and show
+    /// [`ContactService::initials_for`] instead.
+    Gravatar(String),
+    /// Colored initials.
+    Initials(AvatarInitials),
+}
+
+/// Initials shown when no avatar image is available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvatarInitials {
+    /// One or two uppercase letters.
+    pub text: String,
+    /// Seed for the background color, derived from the email address so a
+    /// contact always gets the same color.
+    pub color_seed: u32,
+}
+
 /// Storage trait for contact persistence.
 pub trait ContactStorage: Send + Sync {
     /// Gets a contact by ID.
@@ -136,6 +184,20 @@ pub trait ContactStorage: Send + Sync {
     /// Gets a contact by email address.
     fn get_by_email(&self, email: &str) -> Result<Option<Contact>>;
 
+    /// Gets the contact with an address, either as its email or as an
+    /// alias merged into it. The default scans every contact for aliases;
+    /// database-backed storage should look the address up with
+    /// [`contacts::get_by_address`](crate::storage::queries::contacts::get_by_address).
+    fn get_by_address(&self, email: &str) -> Result<Option<Contact>> {
+        if let Some(contact) = self.get_by_email(email)? {
+            return Ok(Some(contact));
+        }
+        Ok(self
+            .get_all()?
+            .into_iter()
+            .find(|contact| contact.aliases.iter().any(|alias| alias == email)))
+    }
+
     /// Stores or updates a contact.
     fn save(&self, contact: &Contact) -> Result<()>;
 
@@ -152,12 +214,115 @@ pub trait ContactStorage: Send + Sync {
 /// Service for managing contacts.
 pub struct ContactService<S: ContactStorage> {
     storage: S,
+    /// Whether avatar images may be loaded (external images privacy setting).
+    external_images_allowed: AtomicBool,
+    /// Provider photo URLs by normalized email.
+    photos: RwLock<HashMap<String, String>>,
+    /// Gravatar URLs by normalized email, `None` when the address has none.
+    gravatars: RwLock<HashMap<String, Option<String>>>,
 }
 
 impl<S: ContactStorage> ContactService<S> {
     /// Creates a new contact service.
     pub fn new(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            external_images_allowed: AtomicBool::new(false),
+            photos: RwLock::new(HashMap::new()),
+            gravatars: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Sets whether avatar images may be loaded.
+    ///
+    /// Mirrors the privacy setting for external content. Off by default, in
+    /// which case avatars are always initials.
+    pub fn set_external_images_allowed(&self, allowed: bool) {
+        self.external_images_allowed
+            .store(allowed, Ordering::Relaxed);
+    }
+
+    /// Records a provider-supplied photo for an address, or clears it.
+    pub fn set_photo(&self, email: &str, url: Option<String>) {
+        let mut photos = self.photos.write().unwrap_or_else(PoisonError::into_inner);
+        match url {
+            Some(url) => photos.insert(normalize_email(email), url),
+            None => photos.remove(&normalize_email(email)),
+        };
+    }
+
+    /// Records that an address has no Gravatar, so it isn't requested again.
+    pub fn mark_gravatar_missing(&self, email: &str) {
+        self.gravatars
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(normalize_email(email), None);
+    }
+
+    /// Resolves the avatar to show for an address.
+    ///
+    /// Prefers the provider's photo, then Gravatar, then initials. Only
+    /// initials are returned while external images are blocked.
+    pub fn avatar_for(&self, address: &Address) -> AvatarSource {
+        if !self.external_images_allowed.load(Ordering::Relaxed) {
+            return AvatarSource::Initials(self.initials_for(address));
+        }
+
+        let email = normalize_email(&address.email);
+        if let Some(url) = self
+            .photos
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&email)
+        {
+            return AvatarSource::Photo(url.clone());
+        }
+
+        let gravatar = self
+            .gravatars
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(email)
+            .or_insert_with_key(|email| Some(gravatar_url(email)))
+            .clone();
+        match gravatar {
+            Some(url) => AvatarSource::Gravatar(url),
+            None => AvatarSource::Initials(self.initials_for(address)),
+        }
+    }
+
+    /// Returns the initials avatar for an address.
+    ///
+    /// Uses the display name when there is one, otherwise the first letter of
+    /// the email address. The color depends only on the address.
+    pub fn initials_for(&self, address: &Address) -> AvatarInitials {
+        let email = normalize_email(&address.email);
+        let name = address
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        let mut words = name.unwrap_or(&email).split_whitespace();
+        let first = words
+            .next()
+            .and_then(|w| w.chars().find(|c| c.is_alphanumeric()));
+        let last = words
+            .last()
+            .and_then(|w| w.chars().find(|c| c.is_alphanumeric()));
+        let text: String = first
+            .into_iter()
+            .chain(last)
+            .flat_map(char::to_uppercase)
+            .collect();
+
+        AvatarInitials {
+            text: if text.is_empty() {
+                "?".to_string()
+            } else {
+                text
+            },
+            color_seed: avatar_color_seed(&email),
+        }
     }
 
     /// Gets a contact by ID.
@@ -165,9 +330,10 @@ impl<S: ContactStorage> ContactService<S> {
         self.storage.get_by_id(id)
     }
 
-    /// Gets a contact by email address.
+    /// Gets a contact by email address, including addresses merged into
+    /// another contact.
     pub fn get_by_email(&self, email: &str) -> Result<Option<Contact>> {
-        self.storage.get_by_email(&normalize_email(email))
+        self.storage.get_by_address(&normalize_email(email))
     }
 
     /// Creates a new contact.
@@ -177,7 +343,7 @@ impl<S: ContactStorage> ContactService<S> {
             return Err(ContactError::InvalidEmail(email.to_string()));
         }
 
-        if self.storage.get_by_email(&normalized)?.is_some() {
+        if self.storage.get_by_address(&normalized)?.is_some() {
             return Err(ContactError::AlreadyExists(normalized));
         }
 
@@ -193,7 +359,7 @@ impl<S: ContactStorage> ContactService<S> {
     /// Gets or creates a contact from an email address.
     pub fn get_or_create(&self, address: &Address) -> Result<Contact> {
         let normalized = normalize_email(&address.email);
-        if let Some(existing) = self.storage.get_by_email(&normalized)? {
+        if let Some(existing) = self.storage.get_by_address(&normalized)? {
             return Ok(existing);
         }
 
@@ -247,7 +413,7 @@ impl<S: ContactStorage> ContactService<S> {
         let normalized = normalize_email(email);
         let mut contact = self
             .storage
-            .get_by_email(&normalized)?
+            .get_by_address(&normalized)?
             .ok_or_else(|| ContactError::NotFound(normalized.clone()))?;
 
         contact.record_interaction();
@@ -258,7 +424,7 @@ impl<S: ContactStorage> ContactService<S> {
     /// Records an interaction, creating the contact if needed.
     pub fn record_interaction_or_create(&self, address: &Address) -> Result<Contact> {
         let normalized = normalize_email(&address.email);
-        let mut contact = match self.storage.get_by_email(&normalized)? {
+        let mut contact = match self.storage.get_by_address(&normalized)? {
             Some(c) => c,
             None => {
                 let c = match &address.name {
@@ -283,6 +449,128 @@ impl<S: ContactStorage> ContactService<S> {
         self.storage.delete(id)
     }
 
+    /// Groups contacts that are likely the same person.
+    ///
+    /// Contacts are grouped when their names have the same words, ignoring
+    /// case, punctuation and order, or when they share an address, ignoring
+    /// `+tag` suffixes. Single-word names are too common to go on. Each
+    /// group is ordered most contacted first, a good candidate for the
+    /// primary of a [`merge`](Self::merge), and groups are ordered likewise.
+    pub fn find_duplicates(&self) -> Result<Vec<Vec<Contact>>> {
+        let mut contacts = self.storage.get_all()?;
+        contacts.sort_by(|a, b| {
+            b.frequency
+                .cmp(&a.frequency)
+                .then_with(|| a.email.cmp(&b.email))
+        });
+
+        // Union-find over contact indices, joined through shared keys.
+        let mut parent: Vec<usize> = (0..contacts.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (i, contact) in contacts.iter().enumerate() {
+            let name_key = contact
+                .name
+                .as_deref()
+                .and_then(duplicate_name_key)
+                .map(|name| format!("name:{}", name));
+            let address_keys = contact
+                .addresses()
+                .map(|email| format!("address:{}", duplicate_address_key(email)));
+            for key in name_key.into_iter().chain(address_keys) {
+                match seen.get(&key) {
+                    Some(&j) => {
+                        let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                        // Keep the most contacted member as the root.
+                        parent[a.max(b)] = a.min(b);
+                    }
+                    None => {
+                        seen.insert(key, i);
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<Contact>> = Vec::new();
+        let mut group_of_root: HashMap<usize, usize> = HashMap::new();
+        for (i, contact) in contacts.into_iter().enumerate() {
+            let r = root(&mut parent, i);
+            let group = *group_of_root.entry(r).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(contact);
+        }
+        groups.retain(|group| group.len() > 1);
+        Ok(groups)
+    }
+
+    /// Folds `others` into the `primary` contact and deletes them.
+    ///
+    /// The primary keeps its email and gains the others' addresses as
+    /// aliases. Interaction counts are summed and the latest interaction
+    /// kept; a missing name or notes are taken from the others, and the
+    /// contact is a VIP if any of them was. Returns what
+    /// [`undo_merge`](Self::undo_merge) needs to restore them.
+    pub fn merge(&self, primary: &str, others: &[String]) -> Result<ContactMerge> {
+        let before = self
+            .storage
+            .get_by_id(primary)?
+            .ok_or_else(|| ContactError::NotFound(primary.to_string()))?;
+        let mut previous = vec![before.clone()];
+        for id in others {
+            if id == primary || previous.iter().any(|c| &c.id == id) {
+                continue;
+            }
+            let other = self
+                .storage
+                .get_by_id(id)?
+                .ok_or_else(|| ContactError::NotFound(id.clone()))?;
+            previous.push(other);
+        }
+
+        let mut contact = before;
+        for other in &previous[1..] {
+            for address in other.addresses() {
+                if contact.addresses().all(|existing| existing != address) {
+                    contact.aliases.push(address.to_string());
+                }
+            }
+            contact.frequency = contact.frequency.saturating_add(other.frequency);
+            contact.last_contacted = contact.last_contacted.max(other.last_contacted);
+            contact.is_vip |= other.is_vip;
+            if contact.name.is_none() {
+                contact.name = other.name.clone();
+            }
+            contact.notes = match (contact.notes.take(), &other.notes) {
+                (Some(notes), Some(more)) => Some(format!("{}\n\n{}", notes, more)),
+                (notes, more) => notes.or_else(|| more.clone()),
+            };
+        }
+
+        self.storage.save(&contact)?;
+        for other in &previous[1..] {
+            self.storage.delete(&other.id)?;
+        }
+        Ok(ContactMerge { contact, previous })
+    }
+
+    /// Restores the contacts of a merge as they were before it.
+    ///
+    /// Interactions recorded on the merged contact since are lost.
+    pub fn undo_merge(&self, merge: &ContactMerge) -> Result<()> {
+        for contact in &merge.previous {
+            self.storage.save(contact)?;
+        }
+        Ok(())
+    }
+
     /// Queries contacts with filter and sort options.
     pub fn query(&self, filter: &ContactFilter, sort: ContactSort) -> Result<Vec<Contact>> {
         self.storage.query(filter, sort)
@@ -347,11 +635,95 @@ impl<S: ContactStorage> ContactService<S> {
     }
 }
 
+/// Extracts contacts from new mail: the sender of received mail and the
+/// recipients of mail the user sent.
+#[async_trait::async_trait]
+impl<S: ContactStorage> EventSubscriber for ContactService<S> {
+    async fn handle(&self, event: &ServiceEvent) {
+        let ServiceEvent::NewEmail { email, .. } = event else {
+            return;
+        };
+        if email.is_draft {
+            return;
+        }
+
+        let sent = email.labels.iter().any(|l| l.0 == "SENT");
+        let addresses: Vec<&Address> = if sent {
+            email.to.iter().chain(&email.cc).collect()
+        } else {
+            vec![&email.from]
+        };
+        for address in addresses {
+            if let Err(e) = self.record_interaction_or_create(address) {
+                tracing::warn!(email = %address.email, "Failed to record contact: {}", e);
+            }
+        }
+    }
+}
+
 /// Normalizes an email address (lowercase, trim).
 fn normalize_email(email: &str) -> String {
     email.trim().to_lowercase()
 }
 
+/// Returns the words of a name, lowercased and sorted, so "Chen, Alice"
+/// and "alice chen" match. `None` for names of fewer than two words.
+fn duplicate_name_key(name: &str) -> Option<String> {
+    let mut words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < 2 {
+        return None;
+    }
+    words.sort_unstable();
+    Some(words.join(" "))
+}
+
+/// Returns an address without its `+tag` suffix, so `alice+news@example.com`
+/// matches `alice@example.com`.
+fn duplicate_address_key(email: &str) -> String {
+    let email = normalize_email(email);
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let local = local.split_once('+').map_or(local, |(base, _)| base);
+            format!("{}@{}", local, domain)
+        }
+        None => email,
+    }
+}
+
+/// Returns the Gravatar hash of an address: the hex SHA-256 of the trimmed,
+/// lowercased email.
+fn gravatar_hash(email: &str) -> String {
+    ring::digest::digest(&ring::digest::SHA256, normalize_email(email).as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Returns the Gravatar image URL of an address. `d=404` makes Gravatar
+/// report missing images instead of serving a placeholder.
+fn gravatar_url(email: &str) -> String {
+    format!(
+        "https://gravatar.com/avatar/{}?s={}&d=404",
+        gravatar_hash(email),
+        GRAVATAR_SIZE
+    )
+}
+
+/// Derives a stable avatar color seed from an address.
+///
+/// Hashes the normalized address, so the seed is the same across runs and
+/// platforms, unlike `std`'s randomly keyed hasher.
+fn avatar_color_seed(email: &str) -> u32 {
+    let digest = ring::digest::digest(&ring::digest::SHA256, normalize_email(email).as_bytes());
+    let bytes = digest.as_ref();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
 /// Basic email validation.
 fn is_valid_email(email: &str) -> bool {
     let email = email.trim();
@@ -549,6 +921,47 @@ mod tests {
         assert_eq!(contact.name, Some("New Contact".to_string()));
     }
 
+    #[tokio::test]
+    async fn new_mail_creates_contacts() {
+        use crate::domain::{test_email, Email, LabelId};
+
+        let service = ContactService::new(MockStorage::new());
+        let email = |from: &str, to: &str, label: &str| Email {
+            from: Address::new(from),
+            to: vec![Address::new(to)],
+            subject: None,
+            body_text: None,
+            snippet: String::new(),
+            labels: vec![LabelId::from(label)],
+            ..test_email("email-1", "thread-1")
+        };
+
+        let received = email("alice@example.com", "me@example.com", "INBOX");
+        service
+            .handle(&ServiceEvent::NewEmail {
+                email: Box::new(received),
+                watched: false,
+            })
+            .await;
+        let sent = email("me@example.com", "bob@example.com", "SENT");
+        service
+            .handle(&ServiceEvent::NewEmail {
+                email: Box::new(sent),
+                watched: false,
+            })
+            .await;
+
+        let mut emails: Vec<String> = service
+            .storage
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.email)
+            .collect();
+        emails.sort();
+        assert_eq!(emails, vec!["alice@example.com", "bob@example.com"]);
+    }
+
     #[test]
     fn search_contacts() {
         let storage = MockStorage::new();
@@ -656,4 +1069,167 @@ mod tests {
             .unwrap();
         assert_eq!(updated.notes, Some("Important contact".to_string()));
     }
+
+    #[test]
+    fn gravatar_hash_is_sha256_of_normalized_email() {
+        let hash = gravatar_hash("test@example.com");
+        assert_eq!(
+            hash,
+            "973dfe463ec85785f5f95af5ba3906eedb2d931c24e69824a89ea65dba4e813b"
+        );
+        assert_eq!(gravatar_hash("  Test@Example.COM "), hash);
+        assert_eq!(
+            gravatar_url("test@example.com"),
+            format!("https://gravatar.com/avatar/{}?s=96&d=404", hash)
+        );
+    }
+
+    #[test]
+    fn avatar_color_is_deterministic() {
+        let alice = avatar_color_seed("alice@example.com");
+        assert_eq!(alice, avatar_color_seed("alice@example.com"));
+        assert_eq!(alice, avatar_color_seed("Alice@Example.com"));
+        assert_ne!(alice, avatar_color_seed("bob@example.com"));
+
+        let service = ContactService::new(MockStorage::new());
+        let named = service.initials_for(&Address::with_name("alice@example.com", "Alice Liddell"));
+        let bare = service.initials_for(&Address::new("alice@example.com"));
+        assert_eq!(named.text, "AL");
+        assert_eq!(bare.text, "A");
+        assert_eq!(named.color_seed, bare.color_seed);
+    }
+
+    #[test]
+    fn avatars_are_initials_when_images_are_blocked() {
+        let service = ContactService::new(MockStorage::new());
+        let alice = Address::with_name("alice@example.com", "Alice");
+        service.set_photo(
+            "alice@example.com",
+            Some("https://photos.example.com/a".to_string()),
+        );
+
+        assert!(matches!(
+            service.avatar_for(&alice),
+            AvatarSource::Initials(_)
+        ));
+
+        service.set_external_images_allowed(true);
+        assert_eq!(
+            service.avatar_for(&alice),
+            AvatarSource::Photo("https://photos.example.com/a".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_gravatar_falls_back_to_initials() {
+        let service = ContactService::new(MockStorage::new());
+        service.set_external_images_allowed(true);
+        let bob = Address::new("bob@example.com");
+
+        assert_eq!(
+            service.avatar_for(&bob),
+            AvatarSource::Gravatar(gravatar_url("bob@example.com"))
+        );
+
+        service.mark_gravatar_missing("Bob@example.com");
+        assert_eq!(
+            service.avatar_for(&bob),
+            AvatarSource::Initials(service.initials_for(&bob))
+        );
+    }
+
+    #[test]
+    fn find_duplicates_groups_name_variants_and_shared_addresses() {
+        let service = ContactService::new(MockStorage::new());
+        let alice = service
+            .create("alice@example.com", Some("Alice Chen"))
+            .unwrap();
+        let alice_work = service
+            .create("achen@work.example", Some("Chen, Alice"))
+            .unwrap();
+        let alice_news = service.create("alice+news@example.com", None).unwrap();
+        let bob = service.create("bob@example.com", Some("Bob Li")).unwrap();
+        service
+            .create("bob.li@example.org", Some("Robert Li"))
+            .unwrap();
+        // A first name alone isn't enough to go on.
+        service
+            .create("alice@other.example", Some("Alice"))
+            .unwrap();
+        for _ in 0..3 {
+            service.record_interaction("achen@work.example").unwrap();
+        }
+
+        let groups = service.find_duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<&str> = groups[0].iter().map(|c| c.id.as_str()).collect();
+        // Most contacted first.
+        assert_eq!(ids[0], alice_work.id);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&alice.id.as_str()));
+        assert!(ids.contains(&alice_news.id.as_str()));
+        assert!(groups.iter().flatten().all(|contact| contact.id != bob.id));
+    }
+
+    #[test]
+    fn merge_keeps_every_address_and_can_be_undone() {
+        let service = ContactService::new(MockStorage::new());
+        let primary = service
+            .create("alice@example.com", Some("Alice Chen"))
+            .unwrap();
+        let work = service.create("achen@work.example", None).unwrap();
+        let news = service.create("alice+news@example.com", None).unwrap();
+        service.set_vip(&news.id, true).unwrap();
+        for _ in 0..2 {
+            service.record_interaction("achen@work.example").unwrap();
+        }
+
+        let merge = service
+            .merge(&primary.id, &[work.id.clone(), news.id.clone()])
+            .unwrap();
+        let merged = service.get(&primary.id).unwrap().unwrap();
+        assert_eq!(merged.email, "alice@example.com");
+        assert_eq!(
+            merged.aliases,
+            ["achen@work.example", "alice+news@example.com"]
+        );
+        assert_eq!(merged.frequency, 1 + 3 + 1);
+        assert!(merged.is_vip);
+        assert_eq!(merge.contact.aliases, merged.aliases);
+        assert_eq!(service.count().unwrap(), 1);
+
+        // Merged addresses resolve to the merged contact rather than
+        // creating it again.
+        let address = Address::new("ACHEN@work.example");
+        let found = service.record_interaction_or_create(&address).unwrap();
+        assert_eq!(found.id, primary.id);
+        assert_eq!(service.search("work.example").unwrap().len(), 1);
+
+        service.undo_merge(&merge).unwrap();
+        assert_eq!(service.count().unwrap(), 3);
+        let restored = service.get(&primary.id).unwrap().unwrap();
+        assert!(restored.aliases.is_empty());
+        assert_eq!(
+            service
+                .get_by_email("achen@work.example")
+                .unwrap()
+                .unwrap()
+                .id,
+            work.id
+        );
+    }
+
+    #[test]
+    fn merge_fails_for_unknown_contacts() {
+        let service = ContactService::new(MockStorage::new());
+        let primary = service.create("alice@example.com", None).unwrap();
+        let other = service.create("bob@example.com", None).unwrap();
+
+        assert!(matches!(
+            service.merge(&primary.id, &[other.id.clone(), "missing".to_string()]),
+            Err(ContactError::NotFound(_))
+        ));
+        // Nothing changed.
+        assert_eq!(service.count().unwrap(), 2);
+    }
 }
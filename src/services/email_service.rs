@@ -3,15 +3,32 @@
 //! The [`EmailService`] coordinates between email providers and local storage,
 //! providing a unified interface for all email operations.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, watch, RwLock};
 
-use crate::domain::{AccountId, Address, EmailId, LabelId, Thread, ThreadId, ThreadSummary};
+use crate::domain::{
+    normalize_content_id, snippet_from_body, write_mbox_message, Account, AccountId, Address,
+    Attachment, Contact, Email, EmailId, ImportanceSignals, ImportanceWeights, Label, LabelId,
+    MboxReader, MessageId, Thread, ThreadId, ThreadSort, ThreadSummary, UnsubscribeInfo,
+};
+use crate::logging::provider_call;
+use crate::providers::email::{ProviderError, SearchCriteria};
+use crate::services::sync_service::{Change, PendingChangeType, SyncSettings};
+use crate::services::{
+    ActionState, ActionType, BundleCategory, Classification, ClassificationInput, EventSubscriber,
+    NotificationCategory, NotificationRequest, ServiceEvent, SmartViewType, UndoableAction,
+};
+use crate::storage::BlobWriter;
 
 /// Email provider trait for abstracting over different email backends.
 ///
@@ -31,6 +48,33 @@ pub trait EmailProvider: Send + Sync {
     /// Fetches a complete thread with all messages.
     async fn fetch_thread(&self, thread_id: &str) -> Result<Thread>;
 
+    /// Searches a folder on the server. The default fails, for providers
+    /// without server search.
+    async fn search(&self, folder: &str, _criteria: &SearchCriteria) -> Result<Vec<ThreadSummary>> {
+        anyhow::bail!("Server search is not supported: {}", folder)
+    }
+
+    /// Fetches a message's original source, headers and MIME body as
+    /// received. The default fails, for providers that can't.
+    async fn fetch_raw(&self, email_id: &str) -> Result<Vec<u8>> {
+        anyhow::bail!("Viewing the source is not supported: {}", email_id)
+    }
+
+    /// Downloads an attachment into `out` as it arrives, returning its
+    /// size. The default fails, for providers that can't.
+    async fn fetch_attachment(
+        &self,
+        email_id: &str,
+        attachment_id: &str,
+        _out: &mut (dyn std::io::Write + Send),
+    ) -> Result<u64> {
+        anyhow::bail!(
+            "Downloading attachments is not supported: {} {}",
+            email_id,
+            attachment_id
+        )
+    }
+
     /// Sends an email.
     async fn send_email(&self, email: &OutgoingEmail) -> Result<String>;
 
@@ -40,6 +84,15 @@ pub trait EmailProvider: Send + Sync {
     /// Moves threads to trash.
     async fn trash(&self, thread_ids: &[String]) -> Result<()>;
 
+    /// Permanently deletes threads, bypassing the trash. The default fails,
+    /// for providers that can't.
+    async fn delete_permanently(&self, thread_ids: &[String]) -> Result<()> {
+        anyhow::bail!("Permanent deletion is not supported: {:?}", thread_ids)
+    }
+
+    /// Reports threads as spam and moves them out of the inbox.
+    async fn report_spam(&self, thread_ids: &[String]) -> Result<()>;
+
     /// Stars or unstars a thread.
     async fn star(&self, thread_id: &str, starred: bool) -> Result<()>;
 
@@ -51,6 +104,21 @@ pub trait EmailProvider: Send + Sync {
 
     /// Removes a label from a thread.
     async fn remove_label(&self, thread_id: &str, label: &str) -> Result<()>;
+
+    /// Looks for a sent message by its Message-ID, returning its ID.
+    ///
+    /// Used to tell whether a send that failed with an unknown outcome went
+    /// through. The default finds nothing, so such sends are dispatched
+    /// again.
+    async fn find_sent(&self, _message_id: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Closes the provider's connections before the app exits. The default
+    /// does nothing, for providers without long-lived connections.
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Storage layer trait for local email persistence.
@@ -64,9 +132,34 @@ pub trait EmailStorage: Send + Sync {
         pagination: Pagination,
     ) -> Result<Vec<ThreadSummary>>;
 
+    /// Retrieves threads from local storage in the given order.
+    ///
+    /// The default sorts the page returned by
+    /// [`get_threads`](Self::get_threads), scoring importance with only the
+    /// signals a summary carries itself.
+    async fn get_threads_sorted(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        sort: ThreadSort,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        let mut threads = self.get_threads(account_id, view, pagination).await?;
+        let weights = ImportanceWeights::default();
+        sort.sort(&mut threads, |t| weights.score(&ImportanceSignals::of(t)));
+        Ok(threads)
+    }
+
     /// Retrieves a complete thread from local storage.
     async fn get_thread(&self, thread_id: &ThreadId) -> Result<Option<Thread>>;
 
+    /// Retrieves the previews of stored threads by id, as picked by
+    /// [`Thread::preview_snippet`] from their stored messages. The default
+    /// finds none.
+    async fn thread_snippets(&self, _thread_ids: &[ThreadId]) -> Result<HashMap<ThreadId, String>> {
+        Ok(HashMap::new())
+    }
+
     /// Stores a thread in local storage.
     async fn store_thread(&self, thread: &Thread) -> Result<()>;
 
@@ -76,6 +169,227 @@ pub trait EmailStorage: Send + Sync {
         thread_id: &ThreadId,
         updates: ThreadMetadataUpdate,
     ) -> Result<()>;
+
+    /// Computes unread and total counts per folder and label for an account.
+    ///
+    /// Implementations should aggregate over stored threads without loading
+    /// message bodies.
+    async fn folder_counts(&self, account_id: &AccountId) -> Result<FolderCounts>;
+
+    /// Stores the body of a message fetched again after it was evicted.
+    /// The default keeps nothing.
+    async fn restore_body(&self, _email: &Email) -> Result<()> {
+        Ok(())
+    }
+
+    /// Stores several threads, e.g. a batch of imported mail.
+    async fn store_threads(&self, threads: &[Thread]) -> Result<()> {
+        for thread in threads {
+            self.store_thread(thread).await?;
+        }
+        Ok(())
+    }
+
+    /// Finds the thread holding the message with `message_id`, if stored.
+    async fn thread_for_message(
+        &self,
+        _account_id: &AccountId,
+        _message_id: &MessageId,
+    ) -> Result<Option<ThreadId>> {
+        Ok(None)
+    }
+
+    /// Retrieves the threads of every account moved to the trash or spam
+    /// `label` before `before`. The default finds none.
+    async fn threads_trashed_before(
+        &self,
+        _label: &LabelId,
+        _before: DateTime<Utc>,
+    ) -> Result<Vec<ThreadSummary>> {
+        Ok(Vec::new())
+    }
+
+    /// Permanently deletes a thread and its messages.
+    async fn delete_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        anyhow::bail!("Deleting threads is not supported: {}", thread_id)
+    }
+
+    /// Retrieves the labels of an account.
+    async fn get_labels(&self, _account_id: &AccountId) -> Result<Vec<Label>> {
+        Ok(Vec::new())
+    }
+
+    /// Retrieves the contacts an account has exchanged mail with.
+    async fn get_contacts(&self, _account_id: &AccountId) -> Result<Vec<Contact>> {
+        Ok(Vec::new())
+    }
+
+    /// Retrieves the content of an attachment, if it has been downloaded.
+    async fn get_attachment_data(
+        &self,
+        _email_id: &EmailId,
+        _attachment_id: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Stores the downloaded content of a stored email's attachment. The
+    /// default keeps nothing.
+    async fn store_attachment_data(
+        &self,
+        _email_id: &EmailId,
+        _attachment_id: &str,
+        _data: Vec<u8>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Stores the content of a stored email's attachment spooled to
+    /// `writer`, returning its hash. The default keeps nothing.
+    async fn store_attachment_from(
+        &self,
+        _email_id: &EmailId,
+        _attachment_id: &str,
+        _writer: BlobWriter,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Retrieves the recorded state of the send with `idempotency_key`.
+    async fn send_state(&self, _idempotency_key: &str) -> Result<Option<SendState>> {
+        Ok(None)
+    }
+
+    /// Records the state of a send, or forgets it when `state` is `None`.
+    async fn set_send_state(
+        &self,
+        _idempotency_key: &str,
+        _state: Option<SendState>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Retrieves the pending follow-up reminders of every account.
+    async fn get_followups(&self) -> Result<Vec<(ThreadId, FollowUp)>> {
+        Ok(Vec::new())
+    }
+
+    /// Records the follow-up reminder on a thread, or forgets it when
+    /// `followup` is `None`. The default keeps nothing.
+    async fn set_followup(&self, _thread_id: &ThreadId, _followup: Option<FollowUp>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Retrieves the outcome of the last unsubscribe from `sender`'s list.
+    async fn unsubscribe_outcome(
+        &self,
+        _account_id: &AccountId,
+        _sender: &str,
+    ) -> Result<Option<UnsubscribeOutcome>> {
+        Ok(None)
+    }
+
+    /// Records the outcome of an unsubscribe from `sender`'s list. The
+    /// default keeps nothing.
+    async fn set_unsubscribe_outcome(
+        &self,
+        _account_id: &AccountId,
+        _sender: &str,
+        _outcome: &UnsubscribeOutcome,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Local record of a send, keyed by its draft's idempotency key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendState {
+    /// Handed to the provider without a confirmed outcome.
+    InFlight,
+    /// Confirmed by the provider, with the ID of the sent email.
+    Sent(EmailId),
+}
+
+/// When to send a draft with [`EmailService::send_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendOptions {
+    /// How long the send can be undone before it goes out. Zero sends at
+    /// once.
+    pub delay: Duration,
+    /// Time to send at instead, for a scheduled send. `delay` is ignored
+    /// when set.
+    pub schedule_at: Option<DateTime<Utc>>,
+}
+
+/// Progress of a send made with [`EmailService::send_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendStatus {
+    /// Waiting out the undo window, until `send_at`.
+    Queued { send_at: DateTime<Utc> },
+    /// Waiting for its scheduled time, `send_at`.
+    Scheduled { send_at: DateTime<Utc> },
+    /// Handed to the provider.
+    Sending,
+    /// Sent, with the ID of the sent email.
+    Sent(EmailId),
+    /// Canceled before it went out.
+    Canceled,
+    /// The provider failed to send it.
+    Failed(String),
+}
+
+impl SendStatus {
+    /// Returns whether the send hasn't started and can still be canceled.
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Queued { .. } | Self::Scheduled { .. })
+    }
+
+    /// Returns whether the send has reached its final state.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Sent(_) | Self::Canceled | Self::Failed(_))
+    }
+}
+
+/// A send in progress, to watch or cancel.
+#[derive(Debug, Clone)]
+pub struct SendHandle {
+    status: Arc<watch::Sender<SendStatus>>,
+}
+
+impl SendHandle {
+    /// Returns the current status.
+    pub fn status(&self) -> SendStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Subscribes to status changes, e.g. to replace "Sending... Undo" with
+    /// "Sent".
+    pub fn subscribe(&self) -> watch::Receiver<SendStatus> {
+        self.status.subscribe()
+    }
+
+    /// Cancels the send if it hasn't started, returning whether it was
+    /// canceled.
+    pub fn cancel(&self) -> bool {
+        self.status.send_if_modified(|status| {
+            let pending = status.is_pending();
+            if pending {
+                *status = SendStatus::Canceled;
+            }
+            pending
+        })
+    }
+
+    /// Waits until the send is sent, canceled or has failed.
+    pub async fn finished(&self) -> SendStatus {
+        let mut status = self.subscribe();
+        let finished = status
+            .wait_for(SendStatus::is_finished)
+            .await
+            .map(|status| status.clone());
+        finished.unwrap_or_else(|_| self.status())
+    }
 }
 
 /// Updates to thread metadata for local storage.
@@ -118,6 +432,22 @@ pub enum ViewType {
 }
 
 impl ViewType {
+    /// Returns a stable key for per-view settings, e.g. `inbox` or
+    /// `label:work`.
+    pub fn key(&self) -> String {
+        match self {
+            ViewType::Inbox => "inbox".to_string(),
+            ViewType::Starred => "starred".to_string(),
+            ViewType::Sent => "sent".to_string(),
+            ViewType::Drafts => "drafts".to_string(),
+            ViewType::Archive => "archive".to_string(),
+            ViewType::Trash => "trash".to_string(),
+            ViewType::All => "all".to_string(),
+            ViewType::Snoozed => "snoozed".to_string(),
+            ViewType::Label(label) => format!("label:{}", label.0),
+        }
+    }
+
     /// Returns the Gmail/IMAP folder name for this view type.
     pub fn folder_name(&self) -> &str {
         match self {
@@ -167,6 +497,182 @@ impl Pagination {
     }
 }
 
+/// Threads of one inbox bundle, newest first.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    /// What the threads have in common.
+    pub category: BundleCategory,
+    /// Threads in the bundle.
+    pub threads: Vec<ThreadSummary>,
+}
+
+/// Groups threads into bundles, in [`BundleCategory::all`] order and
+/// keeping the threads' order within each. Empty bundles are left out.
+fn bundle_threads(threads: Vec<ThreadSummary>) -> Vec<Bundle> {
+    let mut bundles: Vec<Bundle> = BundleCategory::all()
+        .iter()
+        .map(|&category| Bundle {
+            category,
+            threads: Vec::new(),
+        })
+        .collect();
+    for thread in threads {
+        let category = BundleCategory::classify(&ClassificationInput::from_summary(&thread));
+        if let Some(bundle) = bundles.iter_mut().find(|b| b.category == category) {
+            bundle.threads.push(thread);
+        }
+    }
+    bundles.retain(|bundle| !bundle.threads.is_empty());
+    bundles
+}
+
+/// Unread and total counts for one folder or label.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderCount {
+    /// Number of unread messages.
+    pub unread: u32,
+    /// Number of threads.
+    pub total: u32,
+}
+
+/// Unread and total counts for the folders and labels of an account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderCounts {
+    counts: HashMap<ViewType, FolderCount>,
+}
+
+impl FolderCounts {
+    /// Builds counts from per-label aggregates.
+    ///
+    /// System labels (`INBOX`, `SENT`, ...) map to their folder views; any
+    /// other label maps to [`ViewType::Label`].
+    pub fn from_labels(labels: impl IntoIterator<Item = (LabelId, FolderCount)>) -> Self {
+        let mut counts = Self::default();
+        for (label, count) in labels {
+            let entry = counts.counts.entry(view_for_label(&label)).or_default();
+            entry.unread += count.unread;
+            entry.total += count.total;
+        }
+        counts
+    }
+
+    /// Returns the counts for a view, zero if it has no threads.
+    pub fn get(&self, view: &ViewType) -> FolderCount {
+        self.counts.get(view).copied().unwrap_or_default()
+    }
+
+    /// Returns the unread count for a badge, or `None` when nothing is unread.
+    pub fn unread_badge(&self, view: &ViewType) -> Option<u32> {
+        Some(self.get(view).unread).filter(|&n| n > 0)
+    }
+
+    /// Adds or removes a thread from every view it appears in.
+    fn apply(&mut self, state: &ThreadCountState, add: bool) {
+        for view in state.views() {
+            let entry = self.counts.entry(view).or_default();
+            if add {
+                entry.unread += state.unread;
+                entry.total += 1;
+            } else {
+                entry.unread = entry.unread.saturating_sub(state.unread);
+                entry.total = entry.total.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// The parts of a thread that contribute to folder counts.
+#[derive(Debug, Clone)]
+struct ThreadCountState {
+    labels: Vec<LabelId>,
+    is_starred: bool,
+    unread: u32,
+}
+
+impl ThreadCountState {
+    fn of(thread: &Thread) -> Self {
+        Self {
+            labels: thread.labels.clone(),
+            is_starred: thread.is_starred,
+            unread: thread.unread_count,
+        }
+    }
+
+    /// Returns the state after applying a metadata update.
+    fn updated(&self, thread: &Thread, updates: &ThreadMetadataUpdate) -> Self {
+        let mut labels: Vec<LabelId> = self
+            .labels
+            .iter()
+            .filter(|l| !updates.remove_labels.contains(l))
+            .cloned()
+            .collect();
+        for label in &updates.add_labels {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+
+        let unread = match updates.is_read {
+            Some(true) => 0,
+            Some(false) => thread.messages.len() as u32,
+            None => self.unread,
+        };
+
+        Self {
+            labels,
+            is_starred: updates.is_starred.unwrap_or(self.is_starred),
+            unread,
+        }
+    }
+
+    /// Views this thread is counted in.
+    fn views(&self) -> Vec<ViewType> {
+        let mut views: Vec<ViewType> = self.labels.iter().map(view_for_label).collect();
+        if self.is_starred && !views.contains(&ViewType::Starred) {
+            views.push(ViewType::Starred);
+        }
+        views
+    }
+}
+
+/// Maps a label to the view that lists it.
+fn view_for_label(label: &LabelId) -> ViewType {
+    match label.0.as_str() {
+        "INBOX" => ViewType::Inbox,
+        "STARRED" => ViewType::Starred,
+        "SENT" => ViewType::Sent,
+        "DRAFT" => ViewType::Drafts,
+        "TRASH" => ViewType::Trash,
+        "heap/Snoozed" => ViewType::Snoozed,
+        _ => ViewType::Label(label.clone()),
+    }
+}
+
+/// Returns whether an email was received rather than sent by the user.
+fn is_inbound(email: &Email) -> bool {
+    !email.is_draft && !email.labels.iter().any(|l| l.0 == "SENT")
+}
+
+/// Identifies an optimistic change awaiting provider confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MutationToken(pub u64);
+
+/// Outcome of an optimistic change, emitted once the provider responds.
+#[derive(Debug, Clone)]
+pub enum MutationEvent {
+    /// The provider accepted the change.
+    Confirmed(MutationToken),
+    /// The provider rejected the change and local state was restored.
+    RolledBack {
+        /// The rejected change.
+        token: MutationToken,
+        /// Description of the reverted action, e.g. "1 conversation starred".
+        description: String,
+        /// Why the provider rejected it.
+        error: String,
+    },
+}
+
 /// An outgoing email to be sent.
 #[derive(Debug, Clone)]
 pub struct OutgoingEmail {
@@ -188,6 +694,16 @@ pub struct OutgoingEmail {
     pub in_reply_to: Option<ThreadId>,
     /// Message ID being replied to.
     pub reply_to_message_id: Option<String>,
+    /// Message-IDs of the conversation, ending with the one replied to.
+    pub references: Vec<String>,
+    /// Key identifying this send across retries. Providers derive the
+    /// Message-ID from it.
+    pub idempotency_key: String,
+    /// Whether to ask recipients for a read receipt.
+    pub request_read_receipt: bool,
+    /// Message-ID of the email this is a read receipt for, which makes the
+    /// message a disposition notification.
+    pub read_receipt_for: Option<String>,
 }
 
 /// A draft email being composed.
@@ -197,10 +713,16 @@ pub struct Draft {
     pub id: Option<String>,
     /// Account to send from.
     pub account_id: AccountId,
+    /// Alias of the account to send as; its own address when `None`.
+    #[serde(default)]
+    pub from: Option<Address>,
     /// Thread this is a reply to.
     pub reply_to_thread_id: Option<ThreadId>,
     /// Message this is a reply to.
     pub reply_to_message_id: Option<String>,
+    /// Message-IDs of the conversation, ending with the one replied to.
+    #[serde(default)]
+    pub references: Vec<String>,
     /// Recipient addresses.
     pub to: Vec<Address>,
     /// CC addresses.
@@ -211,12 +733,184 @@ pub struct Draft {
     pub subject: String,
     /// Markdown body content.
     pub body_markdown: String,
-    /// Rendered HTML body.
+    /// HTML body to send, for drafts without an HTML signature; see
+    /// [`render_html`](Self::render_html).
     pub body_html: Option<String>,
+    /// HTML signature of the sending account, put in place of the plain
+    /// one when the HTML body is rendered.
+    #[serde(default)]
+    pub signature_html: Option<String>,
     /// When the draft was created.
     pub created_at: DateTime<Utc>,
     /// When the draft was last modified.
     pub updated_at: DateTime<Utc>,
+    /// Key identifying the send of this draft, so retrying a send that
+    /// timed out doesn't deliver it twice.
+    #[serde(default = "new_idempotency_key")]
+    pub idempotency_key: String,
+    /// Whether to ask recipients for a read receipt.
+    #[serde(default)]
+    pub request_read_receipt: bool,
+}
+
+/// Generates a key for a new draft's send.
+fn new_idempotency_key() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl Draft {
+    /// Returns the HTML body to send.
+    ///
+    /// With an HTML signature, the body is rendered from the final Markdown
+    /// body, so it has everything typed since the signature was inserted,
+    /// with the HTML signature in place of the plain one. If the plain
+    /// signature was deleted, so is the HTML one.
+    pub fn render_html(&self) -> Option<String> {
+        let Some(signature) = &self.signature_html else {
+            return self.body_html.clone();
+        };
+        let body = &self.body_markdown;
+        let mut offset = 0;
+        let start = body.split_inclusive('\n').find_map(|line| {
+            let at = offset;
+            offset += line.len();
+            (line.trim_end_matches('\n') == "-- ").then_some(at)
+        });
+        let Some(start) = start else {
+            return Some(text_to_html(body));
+        };
+        // The plain signature runs to the next blank line
+        let end = body[start..]
+            .find("\n\n")
+            .map_or(body.len(), |end| start + end);
+        Some(format!(
+            "{}<div class=\"signature\">{}</div>{}",
+            text_to_html(&body[..start]),
+            signature,
+            text_to_html(&body[end..])
+        ))
+    }
+}
+
+/// Who a reply is addressed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyKind {
+    /// Only the author of the message being replied to.
+    Reply,
+    /// The author, plus everyone else on the message as CC.
+    ReplyAll,
+}
+
+/// Result of an unsubscribe request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsubscribeOutcome {
+    /// The list confirmed the one-click unsubscribe.
+    Completed,
+    /// The user must send an email to this `mailto:` URI.
+    Mailto(String),
+    /// The user must open this page in a browser to finish.
+    OpenUrl(String),
+}
+
+/// File format for [`EmailService::export_account`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Every message in one mbox file, readable by most mail clients.
+    Mbox,
+    /// A JSON document with the account's labels, contacts and threads.
+    Json,
+}
+
+impl ExportFormat {
+    /// Returns the name of the file the export is written to.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::Mbox => "mail.mbox",
+            ExportFormat::Json => "account.json",
+        }
+    }
+}
+
+/// What an account export wrote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// Number of threads exported.
+    pub threads: usize,
+    /// Number of messages exported.
+    pub emails: usize,
+    /// Number of attachment files written.
+    pub attachments: usize,
+}
+
+/// An exported attachment, as listed in the attachment index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedAttachment {
+    /// Email the attachment belongs to.
+    email_id: EmailId,
+    /// Message-ID of that email.
+    message_id: String,
+    /// Original filename.
+    filename: String,
+    /// MIME content type.
+    content_type: String,
+    /// Path of the written file, relative to the export directory.
+    path: String,
+}
+
+/// Directory attachments are exported to, within the export directory.
+const EXPORT_ATTACHMENTS_DIR: &str = "attachments";
+
+/// Number of threads loaded from storage at a time during an export.
+const EXPORT_PAGE_SIZE: usize = 100;
+
+/// Running totals of an mbox or maildir import.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportProgress {
+    /// Messages added to storage.
+    pub imported: usize,
+    /// Messages skipped because their Message-ID is already stored.
+    pub duplicates: usize,
+    /// Messages that couldn't be parsed.
+    pub failed: usize,
+}
+
+/// Number of imported messages stored per batch.
+const IMPORT_BATCH_SIZE: usize = 200;
+
+/// Length of generated snippets for imported messages.
+const IMPORT_SNIPPET_LENGTH: usize = 200;
+
+/// A follow-up reminder set with [`EmailService::set_followup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FollowUp {
+    /// When the reminder was set. Replies received after it cancel it.
+    pub set_at: DateTime<Utc>,
+    /// When the reminder is due.
+    pub due_at: DateTime<Utc>,
+}
+
+/// A follow-up reminder whose deadline passed without a reply.
+#[derive(Debug, Clone)]
+pub struct FollowUpDue {
+    /// The thread nobody replied to.
+    pub thread_id: ThreadId,
+    /// When the reminder was due.
+    pub due_at: DateTime<Utc>,
+    /// Notification nudging the user to follow up.
+    pub notification: NotificationRequest,
+    /// Places the thread in the follow-up smart view.
+    pub classification: Classification,
+}
+
+/// Outcome of [`EmailService::purge_expired`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PurgeReport {
+    /// Threads deleted for good, locally and at the provider.
+    pub purged: Vec<ThreadId>,
+    /// Expired threads kept because they are starred.
+    pub kept_starred: Vec<ThreadId>,
 }
 
 /// Orchestrates email operations across providers and storage.
@@ -243,6 +937,27 @@ pub struct EmailService<S: EmailStorage> {
     providers: RwLock<HashMap<AccountId, Arc<dyn EmailProvider>>>,
     /// Local storage layer.
     storage: Arc<S>,
+    /// HTTP client for one-click unsubscribe.
+    http: reqwest::Client,
+    /// Whether requests to third-party servers are allowed (privacy setting).
+    external_requests_allowed: AtomicBool,
+    /// Whether the user may be asked to send read receipts (privacy setting).
+    read_receipts_enabled: AtomicBool,
+    /// Folder counts by account, kept current as actions are applied.
+    folder_counts: RwLock<HashMap<AccountId, FolderCounts>>,
+    /// Optimistic changes awaiting provider confirmation, with the state
+    /// needed to revert them.
+    pending_mutations: RwLock<HashMap<MutationToken, UndoableAction>>,
+    /// Source of mutation tokens.
+    next_token: AtomicU64,
+    /// Event sender for mutation outcomes.
+    event_sender: broadcast::Sender<MutationEvent>,
+    /// Follow-up reminders by thread, mirroring storage.
+    followups: RwLock<HashMap<ThreadId, FollowUp>>,
+    /// Event sender for follow-up reminders that came due.
+    followup_sender: broadcast::Sender<FollowUpDue>,
+    /// How long threads stay in each purged folder, by folder label.
+    purge_after: RwLock<Vec<(LabelId, chrono::Duration)>>,
 }
 
 impl<S: EmailStorage> EmailService<S> {
@@ -251,9 +966,57 @@ impl<S: EmailStorage> EmailService<S> {
         Self {
             providers: RwLock::new(HashMap::new()),
             storage,
+            http: crate::providers::http::client(crate::providers::http::DEFAULT_REQUEST_TIMEOUT),
+            external_requests_allowed: AtomicBool::new(false),
+            read_receipts_enabled: AtomicBool::new(false),
+            folder_counts: RwLock::new(HashMap::new()),
+            pending_mutations: RwLock::new(HashMap::new()),
+            next_token: AtomicU64::new(0),
+            event_sender: broadcast::channel(100).0,
+            followups: RwLock::new(HashMap::new()),
+            followup_sender: broadcast::channel(100).0,
+            purge_after: RwLock::new(purge_after(&SyncSettings::default())),
         }
     }
 
+    /// Subscribes to the outcomes of optimistic changes.
+    ///
+    /// A [`MutationEvent::RolledBack`] should be surfaced to the user, since
+    /// the change they saw applied has been reverted.
+    pub fn subscribe(&self) -> broadcast::Receiver<MutationEvent> {
+        self.event_sender.subscribe()
+    }
+
+    /// Returns the number of optimistic changes awaiting confirmation.
+    pub async fn pending_mutation_count(&self) -> usize {
+        self.pending_mutations.read().await.len()
+    }
+
+    /// Sets whether the service may contact third-party servers.
+    ///
+    /// Mirrors the privacy setting for external content. Off by default.
+    pub fn set_external_requests_allowed(&self, allowed: bool) {
+        self.external_requests_allowed
+            .store(allowed, Ordering::Relaxed);
+    }
+
+    /// Sets whether read receipts may be sent.
+    ///
+    /// Mirrors the privacy setting for read receipts. Off by default, in
+    /// which case requests for receipts are ignored. Receipts are never sent
+    /// without the user's confirmation.
+    pub fn set_read_receipts_enabled(&self, enabled: bool) {
+        self.read_receipts_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether the user should be asked to send a read receipt for
+    /// an email they opened.
+    pub fn should_prompt_read_receipt(&self, email: &Email) -> bool {
+        self.read_receipts_enabled.load(Ordering::Relaxed)
+            && email.requests_read_receipt()
+            && !email.is_draft
+    }
+
     /// Registers an email provider for an account.
     ///
     /// If a provider is already registered for this account, it is replaced.
@@ -262,10 +1025,50 @@ impl<S: EmailStorage> EmailService<S> {
         providers.insert(account_id, provider);
     }
 
-    /// Unregisters the email provider for an account.
+    /// Disconnects and unregisters the email provider for an account, e.g.
+    /// when the account is removed.
     pub async fn unregister_provider(&self, account_id: &AccountId) {
-        let mut providers = self.providers.write().await;
-        providers.remove(account_id);
+        let removed = self.providers.write().await.remove(account_id);
+        if let Some(provider) = removed {
+            let call = provider.disconnect();
+            if let Err(e) = provider_call("disconnect", account_id, call).await {
+                tracing::warn!(account = %account_id, "Failed to disconnect provider: {}", e);
+            }
+        }
+    }
+
+    /// Disconnects and unregisters every provider.
+    ///
+    /// A provider that fails to disconnect is logged and dropped anyway.
+    pub async fn disconnect_providers(&self) {
+        let providers: Vec<_> = self.providers.write().await.drain().collect();
+        for (account_id, provider) in providers {
+            let call = provider.disconnect();
+            if let Err(e) = provider_call("disconnect", &account_id, call).await {
+                tracing::warn!(account = %account_id, "Failed to disconnect provider: {}", e);
+            }
+        }
+    }
+
+    /// Pushes a change queued while offline to the account's provider.
+    ///
+    /// Fails if the account has no registered provider, so the change stays
+    /// queued.
+    pub async fn push_queued_change(
+        &self,
+        account_id: &AccountId,
+        change: &PendingChangeType,
+    ) -> Result<()> {
+        let providers = self.providers.read().await;
+        let provider = providers
+            .get(account_id)
+            .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", account_id))?;
+        provider_call(
+            "push_change",
+            account_id,
+            push_change(provider.as_ref(), change),
+        )
+        .await
     }
 
     /// Fetches thread summaries for display in the message list.
@@ -291,8 +1094,12 @@ impl<S: EmailStorage> EmailService<S> {
         // Try to fetch from provider first
         let providers = self.providers.read().await;
         if let Some(provider) = providers.get(account_id) {
-            match provider.fetch_threads(view.folder_name(), pagination).await {
-                Ok(threads) => return Ok(threads),
+            let call = provider.fetch_threads(view.folder_name(), pagination);
+            match provider_call("fetch_threads", account_id, call).await {
+                Ok(mut threads) => {
+                    self.apply_stored_snippets(&mut threads).await;
+                    return Ok(threads);
+                }
                 Err(e) => {
                     // Log error and fall back to local storage
                     tracing::warn!("Failed to fetch threads from provider: {}", e);
@@ -304,6 +1111,58 @@ impl<S: EmailStorage> EmailService<S> {
         self.storage.get_threads(account_id, view, pagination).await
     }
 
+    /// Replaces the previews of provider summaries with those of the threads
+    /// stored locally, which follow the newest unread message as read state
+    /// changes. Providers preview with a snippet of their own choosing.
+    async fn apply_stored_snippets(&self, threads: &mut [ThreadSummary]) {
+        let ids: Vec<ThreadId> = threads.iter().map(|t| t.id.clone()).collect();
+        match self.storage.thread_snippets(&ids).await {
+            Ok(mut snippets) => {
+                for thread in threads {
+                    if let Some(snippet) = snippets.remove(&thread.id) {
+                        thread.snippet = snippet;
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to load stored thread previews: {}", e),
+        }
+    }
+
+    /// Fetches thread summaries in the given order.
+    ///
+    /// Newest first is [`fetch_threads`](Self::fetch_threads). Other orders
+    /// are read from local storage, which knows the VIP contacts and
+    /// recipients that [`ThreadSort::PriorityFirst`] ranks by.
+    pub async fn fetch_threads_sorted(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        sort: ThreadSort,
+        pagination: Pagination,
+    ) -> Result<Vec<ThreadSummary>> {
+        if sort == ThreadSort::DateDesc {
+            return self.fetch_threads(account_id, view, pagination).await;
+        }
+        self.storage
+            .get_threads_sorted(account_id, view, sort, pagination)
+            .await
+    }
+
+    /// Fetches thread summaries grouped into bundles such as Promotions and
+    /// Social, with everything else in Primary.
+    ///
+    /// Threads are fetched as by [`fetch_threads`](Self::fetch_threads), so
+    /// a page can spread over several bundles.
+    pub async fn list_bundled(
+        &self,
+        account_id: &AccountId,
+        view: ViewType,
+        pagination: Pagination,
+    ) -> Result<Vec<Bundle>> {
+        let threads = self.fetch_threads(account_id, view, pagination).await?;
+        Ok(bundle_threads(threads))
+    }
+
     /// Fetches a complete thread with all messages.
     ///
     /// Attempts to fetch from the provider first for the latest state,
@@ -319,7 +1178,7 @@ impl<S: EmailStorage> EmailService<S> {
     pub async fn get_thread(&self, thread_id: &ThreadId) -> Result<Thread> {
         // Check local storage first
         if let Some(thread) = self.storage.get_thread(thread_id).await? {
-            return Ok(thread);
+            return Ok(self.restore_evicted_bodies(thread).await);
         }
 
         // Try all providers
@@ -335,12 +1194,256 @@ impl<S: EmailStorage> EmailService<S> {
         anyhow::bail!("Thread not found: {}", thread_id)
     }
 
-    /// Sends an email.
-    ///
-    /// # Arguments
-    ///
-    /// * `draft` - The draft to send
-    ///
+    /// Returns whether a provider is registered for an account.
+    pub async fn has_provider(&self, account_id: &AccountId) -> bool {
+        self.providers.read().await.contains_key(account_id)
+    }
+
+    /// Searches a folder of an account on its provider's server, which
+    /// finds mail that hasn't been downloaded.
+    pub async fn search_server(
+        &self,
+        account_id: &AccountId,
+        folder: &str,
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<ThreadSummary>> {
+        let providers = self.providers.read().await;
+        let provider = providers
+            .get(account_id)
+            .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", account_id))?;
+        provider_call("search", account_id, provider.search(folder, criteria)).await
+    }
+
+    /// Fetches the original source of a message from its account's
+    /// provider.
+    ///
+    /// The source is not stored; it's fetched again each time it's viewed.
+    /// It is returned as received, which need not be valid UTF-8.
+    pub async fn fetch_raw(&self, account_id: &AccountId, email_id: &EmailId) -> Result<Vec<u8>> {
+        let providers = self.providers.read().await;
+        let provider = providers
+            .get(account_id)
+            .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", account_id))?;
+        provider_call("fetch_raw", account_id, provider.fetch_raw(&email_id.0)).await
+    }
+
+    /// Downloads an attachment of a stored email from its account's
+    /// provider, returning the hash of its content in the blob store.
+    ///
+    /// Attachments already downloaded aren't fetched again. The content is
+    /// spooled to disk as it arrives, so it is never held in memory whole.
+    pub async fn download_attachment(
+        &self,
+        email: &Email,
+        attachment_id: &str,
+    ) -> Result<Option<String>> {
+        let attachment = email
+            .attachments
+            .iter()
+            .find(|attachment| attachment.id == attachment_id)
+            .ok_or_else(|| anyhow::anyhow!("Attachment not found: {}", attachment_id))?;
+        if let Some(hash) = &attachment.content_hash {
+            return Ok(Some(hash.clone()));
+        }
+
+        let mut writer = BlobWriter::new()?;
+        {
+            let providers = self.providers.read().await;
+            let provider = providers
+                .get(&email.account_id)
+                .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", email.account_id))?;
+            let call = provider.fetch_attachment(&email.id.0, attachment_id, &mut writer);
+            provider_call("fetch_attachment", &email.account_id, call).await?;
+        }
+        self.storage
+            .store_attachment_from(&email.id, attachment_id, writer)
+            .await
+    }
+
+    /// Re-fetches message bodies evicted from the local cache.
+    ///
+    /// The restored bodies are stored again. When the provider can't be
+    /// reached the thread is returned as stored, with snippets only.
+    async fn restore_evicted_bodies(&self, mut thread: Thread) -> Thread {
+        if thread.messages.iter().all(|m| m.has_body() || m.is_draft) {
+            return thread;
+        }
+
+        let providers = self.providers.read().await;
+        let Some(provider) = providers.get(&thread.account_id) else {
+            return thread;
+        };
+        let call = provider.fetch_thread(&thread.id.0);
+        let fetched = match provider_call("fetch_thread", &thread.account_id, call).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                tracing::warn!("Failed to re-fetch evicted bodies of {}: {}", thread.id, e);
+                return thread;
+            }
+        };
+        drop(providers);
+
+        for message in thread.messages.iter_mut().filter(|m| !m.has_body()) {
+            let Some(source) = fetched
+                .messages
+                .iter()
+                .find(|f| f.message_id == message.message_id && f.has_body())
+            else {
+                continue;
+            };
+            message.body_text = source.body_text.clone();
+            message.body_html = source.body_html.clone();
+            if let Err(e) = self.storage.restore_body(message).await {
+                tracing::warn!(
+                    "Failed to store the re-fetched body of {}: {}",
+                    message.id,
+                    e
+                );
+            }
+        }
+        thread
+    }
+
+    /// Builds a reply to the latest message in a thread.
+    ///
+    /// The subject gets a single `Re:` prefix, the body quotes the message
+    /// being replied to, and `References` carries the full chain so the
+    /// recipient's client threads the reply. When the latest message was sent
+    /// by `own_address`, the reply goes to its recipients instead. Reply-all
+    /// copies everyone else on the message, never including `own_address`.
+    pub fn build_reply(&self, thread: &Thread, kind: ReplyKind, own_address: &str) -> Draft {
+        let mut draft = empty_draft(
+            thread.account_id.clone(),
+            prefixed_subject(thread, "Re", &["re:"]),
+        );
+        draft.reply_to_thread_id = Some(thread.id.clone());
+
+        let Some(latest) = thread.messages.iter().max_by_key(|m| m.date) else {
+            return draft;
+        };
+
+        let is_me = |a: &Address| a.email.eq_ignore_ascii_case(own_address);
+        draft.to = if is_me(&latest.from) {
+            latest.to.clone()
+        } else {
+            vec![latest.from.clone()]
+        };
+
+        if kind == ReplyKind::ReplyAll {
+            for addr in latest.to.iter().chain(&latest.cc) {
+                let seen = draft
+                    .to
+                    .iter()
+                    .chain(&draft.cc)
+                    .any(|a| a.email.eq_ignore_ascii_case(&addr.email));
+                if !seen && !is_me(addr) {
+                    draft.cc.push(addr.clone());
+                }
+            }
+        }
+
+        let mut references: Vec<String> = latest.references.iter().map(|r| r.0.clone()).collect();
+        if !references.contains(&latest.message_id.0) {
+            references.push(latest.message_id.0.clone());
+        }
+        draft.reply_to_message_id = Some(latest.message_id.0.clone());
+        draft.references = references;
+
+        let quoted: Vec<String> = message_text(latest)
+            .lines()
+            .map(|line| format!("> {}", line).trim_end().to_string())
+            .collect();
+        draft.body_markdown = format!(
+            "\n\nOn {}, {} wrote:\n{}\n",
+            latest.date.format("%a, %b %-d, %Y at %-I:%M %p"),
+            latest.from.display(),
+            quoted.join("\n")
+        );
+
+        draft
+    }
+
+    /// Builds a forward of the latest message in a thread.
+    ///
+    /// The subject gets a single `Fwd:` prefix and the body includes the
+    /// original headers above the forwarded text. Recipients are left empty.
+    pub fn build_forward(&self, thread: &Thread) -> Draft {
+        let mut draft = empty_draft(
+            thread.account_id.clone(),
+            prefixed_subject(thread, "Fwd", &["fwd:", "fw:"]),
+        );
+
+        let Some(latest) = thread.messages.iter().max_by_key(|m| m.date) else {
+            return draft;
+        };
+
+        let mut headers = vec![
+            format!("From: {}", latest.from.display()),
+            format!("Date: {}", latest.date.to_rfc2822()),
+            format!("Subject: {}", latest.subject.as_deref().unwrap_or_default()),
+            format!("To: {}", join_addresses(&latest.to)),
+        ];
+        if !latest.cc.is_empty() {
+            headers.push(format!("Cc: {}", join_addresses(&latest.cc)));
+        }
+        draft.body_markdown = format!(
+            "\n\n---------- Forwarded message ---------\n{}\n\n{}\n",
+            headers.join("\n"),
+            message_text(latest)
+        );
+
+        draft
+    }
+
+    /// Starts a new message from `account`, opening with its signature when
+    /// `include_signature` is set (see `ComposeSettings::include_signature`).
+    pub fn build_new(&self, account: &Account, include_signature: bool) -> Draft {
+        let mut draft = empty_draft(account.id.clone(), String::new());
+        self.insert_signature(&mut draft, account, include_signature);
+        draft
+    }
+
+    /// Makes `account` the sender of a draft, adding its signature when
+    /// `include_signature` is set.
+    ///
+    /// The signature goes above any quoted or forwarded text, after a blank
+    /// line to type into. The plain-text signature is added to the Markdown
+    /// body; the HTML one, if the account has one, is kept on the draft and
+    /// takes the plain one's place when the draft is sent. An account with
+    /// only an HTML signature gets a bare delimiter to mark its place.
+    pub fn insert_signature(&self, draft: &mut Draft, account: &Account, include_signature: bool) {
+        draft.account_id = account.id.clone();
+        // Another account's alias can't be sent as.
+        draft.from = draft
+            .from
+            .take()
+            .and_then(|from| account.sender(Some(&from.email)));
+        if !include_signature {
+            return;
+        }
+
+        draft.signature_html = account.signature_html.clone().filter(|s| !s.is_empty());
+        let delimited = match account.signature.as_deref().filter(|s| !s.is_empty()) {
+            Some(signature) if signature.starts_with("-- \n") => signature.to_string(),
+            Some(signature) => format!("-- \n{}", signature),
+            None if draft.signature_html.is_some() => "-- ".to_string(),
+            None => return,
+        };
+        draft.body_markdown = format!("\n\n{}{}", delimited, draft.body_markdown);
+    }
+
+    /// Sends an email.
+    ///
+    /// Sending is idempotent per draft: the send is recorded under the
+    /// draft's idempotency key, and retrying a draft that was already sent
+    /// returns the sent email instead of delivering it again. When an
+    /// earlier attempt failed with an unknown outcome (e.g. a timeout), the
+    /// provider is asked whether the message went out before sending again.
+    ///
+    /// # Arguments
+    ///
+    /// * `draft` - The draft to send
+    ///
     /// # Returns
     ///
     /// The ID of the sent email.
@@ -350,21 +1453,134 @@ impl<S: EmailStorage> EmailService<S> {
             .get(&draft.account_id)
             .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", draft.account_id))?;
 
+        let key = draft.idempotency_key.clone();
+        match self.storage.send_state(&key).await? {
+            Some(SendState::Sent(email_id)) => return Ok(email_id),
+            Some(SendState::InFlight) => {
+                let message_id = crate::providers::email::idempotent_message_id(&key);
+                if let Some(sent_id) = provider.find_sent(&message_id).await? {
+                    let email_id = EmailId::from(sent_id);
+                    self.storage
+                        .set_send_state(&key, Some(SendState::Sent(email_id.clone())))
+                        .await?;
+                    return Ok(email_id);
+                }
+            }
+            None => {}
+        }
+
         // Convert draft to outgoing email
+        let body_html = draft.render_html();
         let outgoing = OutgoingEmail {
-            from: Address::new(""), // Will be filled by provider from account
+            // An empty address is filled in by the provider from the account
+            from: draft.from.unwrap_or_else(|| Address::new("")),
             to: draft.to,
             cc: draft.cc,
             bcc: draft.bcc,
             subject: draft.subject,
             body_text: draft.body_markdown,
-            body_html: draft.body_html,
+            body_html,
             in_reply_to: draft.reply_to_thread_id,
             reply_to_message_id: draft.reply_to_message_id,
+            references: draft.references,
+            idempotency_key: key.clone(),
+            request_read_receipt: draft.request_read_receipt,
+            read_receipt_for: None,
+        };
+
+        self.storage
+            .set_send_state(&key, Some(SendState::InFlight))
+            .await?;
+        match provider.send_email(&outgoing).await {
+            Ok(sent_id) => {
+                let email_id = EmailId::from(sent_id);
+                self.storage
+                    .set_send_state(&key, Some(SendState::Sent(email_id.clone())))
+                    .await?;
+                Ok(email_id)
+            }
+            Err(e) => {
+                // A connection error may have struck after the server took
+                // the message, so the send stays in flight until checked.
+                if !matches!(
+                    e.downcast_ref::<ProviderError>(),
+                    Some(ProviderError::Connection(_))
+                ) {
+                    self.storage.set_send_state(&key, None).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Sends a draft after an undo window or at a scheduled time.
+    ///
+    /// The returned handle reports the send as queued (in its undo window)
+    /// or scheduled, then sending, and finally sent, canceled or failed. It
+    /// can be canceled until it starts sending. With default options the
+    /// draft is sent at once, as with [`send_email`](Self::send_email).
+    ///
+    /// Waiting sends are kept in memory, so they don't survive a restart.
+    pub async fn send_with_options(
+        self: &Arc<Self>,
+        draft: Draft,
+        options: SendOptions,
+    ) -> Result<SendHandle>
+    where
+        S: 'static,
+    {
+        if !self.providers.read().await.contains_key(&draft.account_id) {
+            anyhow::bail!("No provider for account: {}", draft.account_id);
+        }
+
+        let now = Utc::now();
+        let initial = match options.schedule_at {
+            Some(send_at) if send_at > now => SendStatus::Scheduled { send_at },
+            Some(_) => SendStatus::Sending,
+            None if options.delay.is_zero() => SendStatus::Sending,
+            None => SendStatus::Queued {
+                send_at: now + chrono::Duration::from_std(options.delay).unwrap_or_default(),
+            },
         };
+        let send_at = match initial {
+            SendStatus::Queued { send_at } | SendStatus::Scheduled { send_at } => Some(send_at),
+            _ => None,
+        };
+        let handle = SendHandle {
+            status: Arc::new(watch::channel(initial).0),
+        };
+
+        let status = handle.status.clone();
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Some(send_at) = send_at {
+                let wait = (send_at - Utc::now()).to_std().unwrap_or_default();
+                let mut changes = status.subscribe();
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = changes.wait_for(|s| *s == SendStatus::Canceled) => return,
+                }
+                // A cancel racing the timer may still win.
+                let started = status.send_if_modified(|s| {
+                    let pending = s.is_pending();
+                    if pending {
+                        *s = SendStatus::Sending;
+                    }
+                    pending
+                });
+                if !started {
+                    return;
+                }
+            }
 
-        let email_id = provider.send_email(&outgoing).await?;
-        Ok(EmailId::from(email_id))
+            let finished = match service.send_email(draft).await {
+                Ok(email_id) => SendStatus::Sent(email_id),
+                Err(e) => SendStatus::Failed(e.to_string()),
+            };
+            status.send_replace(finished);
+        });
+
+        Ok(handle)
     }
 
     /// Archives threads by removing them from the inbox.
@@ -379,27 +1595,19 @@ impl<S: EmailStorage> EmailService<S> {
             return Ok(());
         }
 
-        // Group by account
-        let ids: Vec<String> = thread_ids.iter().map(|id| id.0.clone()).collect();
-
-        // Update all providers (threads might span accounts in shared view)
-        let providers = self.providers.read().await;
-        for provider in providers.values() {
-            // Ignore errors for providers that don't have these threads
-            let _ = provider.archive(&ids).await;
-        }
-
-        // Update local storage
         for thread_id in thread_ids {
-            self.storage
-                .update_thread_metadata(
-                    thread_id,
-                    ThreadMetadataUpdate {
-                        remove_labels: vec![LabelId::from("INBOX")],
-                        ..Default::default()
-                    },
-                )
-                .await?;
+            self.mutate(
+                thread_id,
+                ActionType::Archive,
+                ThreadMetadataUpdate {
+                    remove_labels: vec![LabelId::from("INBOX")],
+                    ..Default::default()
+                },
+                PendingChangeType::Archive {
+                    thread_ids: vec![thread_id.0.clone()],
+                },
+            )
+            .await?;
         }
 
         Ok(())
@@ -417,25 +1625,99 @@ impl<S: EmailStorage> EmailService<S> {
             return Ok(());
         }
 
-        let ids: Vec<String> = thread_ids.iter().map(|id| id.0.clone()).collect();
+        for thread_id in thread_ids {
+            self.mutate(
+                thread_id,
+                ActionType::Delete,
+                ThreadMetadataUpdate {
+                    remove_labels: vec![LabelId::from("INBOX")],
+                    add_labels: vec![LabelId::from("TRASH")],
+                    ..Default::default()
+                },
+                PendingChangeType::Trash {
+                    thread_ids: vec![thread_id.0.clone()],
+                },
+            )
+            .await?;
+        }
 
-        let providers = self.providers.read().await;
-        for provider in providers.values() {
-            let _ = provider.trash(&ids).await;
+        Ok(())
+    }
+
+    /// Reports threads as spam, moving them out of the inbox.
+    ///
+    /// Returns the distinct senders of the reported messages so the caller
+    /// can offer to block them.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - The threads to report
+    pub async fn report_spam(&self, thread_ids: &[ThreadId]) -> Result<Vec<Address>> {
+        let mut senders: Vec<Address> = Vec::new();
+
+        for thread_id in thread_ids {
+            if let Some(thread) = self.storage.get_thread(thread_id).await? {
+                for email in thread.messages.iter().filter(|e| is_inbound(e)) {
+                    if !senders
+                        .iter()
+                        .any(|s| s.email.eq_ignore_ascii_case(&email.from.email))
+                    {
+                        senders.push(email.from.clone());
+                    }
+                }
+            }
+
+            self.mutate(
+                thread_id,
+                ActionType::ReportSpam,
+                ThreadMetadataUpdate {
+                    remove_labels: vec![LabelId::from("INBOX")],
+                    add_labels: vec![LabelId::from("SPAM")],
+                    ..Default::default()
+                },
+                PendingChangeType::ReportSpam {
+                    thread_ids: vec![thread_id.0.clone()],
+                },
+            )
+            .await?;
         }
 
-        // Update local storage
+        Ok(senders)
+    }
+
+    /// Moves threads reported as spam back to the inbox.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - The threads to move back
+    pub async fn not_spam(&self, thread_ids: &[ThreadId]) -> Result<()> {
         for thread_id in thread_ids {
-            self.storage
-                .update_thread_metadata(
-                    thread_id,
-                    ThreadMetadataUpdate {
-                        remove_labels: vec![LabelId::from("INBOX")],
-                        add_labels: vec![LabelId::from("TRASH")],
-                        ..Default::default()
-                    },
-                )
-                .await?;
+            self.mutate(
+                thread_id,
+                ActionType::NotSpam,
+                ThreadMetadataUpdate {
+                    remove_labels: vec![LabelId::from("SPAM")],
+                    ..Default::default()
+                },
+                PendingChangeType::RemoveLabel {
+                    thread_id: thread_id.0.clone(),
+                    label: "SPAM".to_string(),
+                },
+            )
+            .await?;
+            self.mutate(
+                thread_id,
+                ActionType::NotSpam,
+                ThreadMetadataUpdate {
+                    add_labels: vec![LabelId::from("INBOX")],
+                    ..Default::default()
+                },
+                PendingChangeType::ApplyLabel {
+                    thread_id: thread_id.0.clone(),
+                    label: "INBOX".to_string(),
+                },
+            )
+            .await?;
         }
 
         Ok(())
@@ -448,22 +1730,51 @@ impl<S: EmailStorage> EmailService<S> {
     /// * `thread_id` - The thread to star/unstar
     /// * `starred` - True to star, false to unstar
     pub async fn star(&self, thread_id: &ThreadId, starred: bool) -> Result<()> {
-        // Update provider
-        let providers = self.providers.read().await;
-        for provider in providers.values() {
-            let _ = provider.star(&thread_id.0, starred).await;
-        }
+        let action_type = if starred {
+            ActionType::Star
+        } else {
+            ActionType::Unstar
+        };
+        self.mutate(
+            thread_id,
+            action_type,
+            ThreadMetadataUpdate {
+                is_starred: Some(starred),
+                ..Default::default()
+            },
+            PendingChangeType::Star {
+                thread_id: thread_id.0.clone(),
+                starred,
+            },
+        )
+        .await
+    }
 
-        // Update local storage
-        self.storage
-            .update_thread_metadata(
-                thread_id,
-                ThreadMetadataUpdate {
-                    is_starred: Some(starred),
-                    ..Default::default()
-                },
-            )
-            .await
+    /// Marks all messages in a thread as read or unread.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_id` - The thread to update
+    /// * `read` - True to mark read, false to mark unread
+    pub async fn mark_read(&self, thread_id: &ThreadId, read: bool) -> Result<()> {
+        let action_type = if read {
+            ActionType::MarkRead
+        } else {
+            ActionType::MarkUnread
+        };
+        self.mutate(
+            thread_id,
+            action_type,
+            ThreadMetadataUpdate {
+                is_read: Some(read),
+                ..Default::default()
+            },
+            PendingChangeType::MarkRead {
+                thread_id: thread_id.0.clone(),
+                read,
+            },
+        )
+        .await
     }
 
     /// Applies a label to threads.
@@ -477,21 +1788,46 @@ impl<S: EmailStorage> EmailService<S> {
             return Ok(());
         }
 
-        let providers = self.providers.read().await;
         for thread_id in thread_ids {
-            for provider in providers.values() {
-                let _ = provider.apply_label(&thread_id.0, &label_id.0).await;
-            }
+            self.mutate(
+                thread_id,
+                ActionType::AddLabels,
+                ThreadMetadataUpdate {
+                    add_labels: vec![label_id.clone()],
+                    ..Default::default()
+                },
+                PendingChangeType::ApplyLabel {
+                    thread_id: thread_id.0.clone(),
+                    label: label_id.0.clone(),
+                },
+            )
+            .await?;
+        }
 
-            self.storage
-                .update_thread_metadata(
-                    thread_id,
-                    ThreadMetadataUpdate {
-                        add_labels: vec![label_id.clone()],
-                        ..Default::default()
-                    },
-                )
-                .await?;
+        Ok(())
+    }
+
+    /// Removes a label from threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `thread_ids` - The threads to unlabel
+    /// * `label_id` - The label to remove
+    pub async fn remove_label(&self, thread_ids: &[ThreadId], label_id: &LabelId) -> Result<()> {
+        for thread_id in thread_ids {
+            self.mutate(
+                thread_id,
+                ActionType::RemoveLabels,
+                ThreadMetadataUpdate {
+                    remove_labels: vec![label_id.clone()],
+                    ..Default::default()
+                },
+                PendingChangeType::RemoveLabel {
+                    thread_id: thread_id.0.clone(),
+                    label: label_id.0.clone(),
+                },
+            )
+            .await?;
         }
 
         Ok(())
@@ -507,17 +1843,16 @@ impl<S: EmailStorage> EmailService<S> {
     /// * `until` - When the thread should reappear
     pub async fn snooze(&self, thread_id: &ThreadId, until: DateTime<Utc>) -> Result<()> {
         // Update local storage
-        self.storage
-            .update_thread_metadata(
-                thread_id,
-                ThreadMetadataUpdate {
-                    snooze_until: Some(Some(until)),
-                    remove_labels: vec![LabelId::from("INBOX")],
-                    add_labels: vec![LabelId::from("heap/Snoozed")],
-                    ..Default::default()
-                },
-            )
-            .await
+        self.update_metadata(
+            thread_id,
+            ThreadMetadataUpdate {
+                snooze_until: Some(Some(until)),
+                remove_labels: vec![LabelId::from("INBOX")],
+                add_labels: vec![LabelId::from("heap/Snoozed")],
+                ..Default::default()
+            },
+        )
+        .await
     }
 
     /// Unsnoozes a thread, returning it to the inbox.
@@ -526,46 +1861,2882 @@ impl<S: EmailStorage> EmailService<S> {
     ///
     /// * `thread_id` - The thread to unsnooze
     pub async fn unsnooze(&self, thread_id: &ThreadId) -> Result<()> {
-        self.storage
-            .update_thread_metadata(
-                thread_id,
-                ThreadMetadataUpdate {
-                    snooze_until: Some(None),
-                    add_labels: vec![LabelId::from("INBOX")],
-                    remove_labels: vec![LabelId::from("heap/Snoozed")],
-                    ..Default::default()
-                },
-            )
-            .await
+        self.update_metadata(
+            thread_id,
+            ThreadMetadataUpdate {
+                snooze_until: Some(None),
+                add_labels: vec![LabelId::from("INBOX")],
+                remove_labels: vec![LabelId::from("heap/Snoozed")],
+                ..Default::default()
+            },
+        )
+        .await
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Reminds the user about a thread if nobody replies within `after`.
+    ///
+    /// Replaces any reminder already set on the thread, and is saved to
+    /// storage. The reminder is cancelled when a reply arrives (see
+    /// [`handle_change`](Self::handle_change)) and otherwise reported by
+    /// [`check_followups`](Self::check_followups). Returns when the reminder
+    /// is due.
+    pub async fn set_followup(
+        &self,
+        thread_id: &ThreadId,
+        after: Duration,
+    ) -> Result<DateTime<Utc>> {
+        let set_at = Utc::now();
+        let followup = FollowUp {
+            set_at,
+            due_at: set_at + chrono::Duration::from_std(after)?,
+        };
+        self.storage.set_followup(thread_id, Some(followup)).await?;
+        self.followups
+            .write()
+            .await
+            .insert(thread_id.clone(), followup);
+        Ok(followup.due_at)
+    }
 
-    #[test]
-    fn view_type_folder_names() {
-        assert_eq!(ViewType::Inbox.folder_name(), "INBOX");
-        assert_eq!(ViewType::Starred.folder_name(), "[Gmail]/Starred");
-        assert_eq!(ViewType::Sent.folder_name(), "[Gmail]/Sent Mail");
-        assert_eq!(ViewType::Drafts.folder_name(), "[Gmail]/Drafts");
-        assert_eq!(ViewType::Trash.folder_name(), "[Gmail]/Trash");
+    /// Loads the follow-up reminders saved in storage, e.g. at startup,
+    /// returning how many are pending.
+    pub async fn load_followups(&self) -> Result<usize> {
+        let saved = self.storage.get_followups().await?;
+        let mut followups = self.followups.write().await;
+        followups.extend(saved);
+        Ok(followups.len())
     }
 
-    #[test]
-    fn pagination_default() {
-        let p = Pagination::default();
-        assert_eq!(p.offset, 0);
-        assert_eq!(p.limit, 50);
+    /// Cancels the follow-up reminder on a thread, if any.
+    pub async fn cancel_followup(&self, thread_id: &ThreadId) -> bool {
+        let cancelled = self.followups.write().await.remove(thread_id).is_some();
+        if cancelled {
+            self.forget_followup(thread_id).await;
+        }
+        cancelled
     }
 
-    #[test]
-    fn pagination_next_page() {
-        let p = Pagination::with_limit(25);
-        let next = p.next_page();
-        assert_eq!(next.offset, 25);
-        assert_eq!(next.limit, 25);
+    /// Removes a reminder that was cancelled or reported from storage.
+    async fn forget_followup(&self, thread_id: &ThreadId) {
+        if let Err(e) = self.storage.set_followup(thread_id, None).await {
+            tracing::warn!(thread_id = %thread_id, "Failed to forget follow-up: {}", e);
+        }
+    }
+
+    /// Returns when the follow-up reminder on a thread is due, if one is set.
+    pub async fn followup_due_at(&self, thread_id: &ThreadId) -> Option<DateTime<Utc>> {
+        self.followups
+            .read()
+            .await
+            .get(thread_id)
+            .map(|followup| followup.due_at)
+    }
+
+    /// Applies a synced change to pending follow-up reminders.
+    ///
+    /// A new inbound message on a thread counts as a reply and cancels its
+    /// reminder. The user's own sent messages and drafts don't. Returns
+    /// whether a reminder was cancelled.
+    pub async fn handle_change(&self, change: &Change) -> bool {
+        match change {
+            Change::NewEmail(email) if is_inbound(email) => {
+                self.cancel_followup(&email.thread_id).await
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes and returns the follow-up reminders due at `now`.
+    ///
+    /// Each reminder is reported once. A reminder whose thread got a reply
+    /// since it was set, one that arrived without going through
+    /// [`handle_change`](Self::handle_change), is dropped instead.
+    pub async fn check_followups(&self, now: DateTime<Utc>) -> Vec<FollowUpDue> {
+        let due: Vec<(ThreadId, FollowUp)> = {
+            let mut followups = self.followups.write().await;
+            let due: Vec<_> = followups
+                .iter()
+                .filter(|(_, followup)| followup.due_at <= now)
+                .map(|(thread_id, followup)| (thread_id.clone(), *followup))
+                .collect();
+            for (thread_id, _) in &due {
+                followups.remove(thread_id);
+            }
+            due
+        };
+
+        let mut reminders = Vec::with_capacity(due.len());
+        for (thread_id, followup) in due {
+            self.forget_followup(&thread_id).await;
+            let thread = self.storage.get_thread(&thread_id).await.ok().flatten();
+            let replied = thread.as_ref().is_some_and(|thread| {
+                thread
+                    .messages
+                    .iter()
+                    .any(|email| is_inbound(email) && email.date > followup.set_at)
+            });
+            if replied {
+                continue;
+            }
+            let subject = thread
+                .and_then(|thread| thread.subject)
+                .unwrap_or_else(|| "(no subject)".to_string());
+            let due_at = followup.due_at;
+
+            reminders.push(FollowUpDue {
+                notification: NotificationRequest::new(
+                    NotificationCategory::Reminder,
+                    "No reply yet",
+                )
+                .body(subject)
+                .system()
+                .action(format!("thread:{}", thread_id)),
+                classification: Classification::manual(
+                    thread_id.clone(),
+                    SmartViewType::FollowUp,
+                    "No reply before the follow-up reminder",
+                ),
+                thread_id,
+                due_at,
+            });
+        }
+        reminders
+    }
+
+    /// Subscribes to follow-up reminders that came due.
+    ///
+    /// Events are emitted by the task started with
+    /// [`start_followup_checks`](Self::start_followup_checks).
+    pub fn subscribe_followups(&self) -> broadcast::Receiver<FollowUpDue> {
+        self.followup_sender.subscribe()
+    }
+
+    /// Starts checking for due follow-up reminders every `interval`.
+    ///
+    /// The task stops once the service is dropped.
+    pub fn start_followup_checks(self: Arc<Self>, interval: Duration)
+    where
+        S: 'static,
+    {
+        let service = Arc::downgrade(&self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(service) = service.upgrade() else {
+                    break;
+                };
+                for due in service.check_followups(Utc::now()).await {
+                    let _ = service.followup_sender.send(due);
+                }
+            }
+        });
+    }
+
+    /// Sets how long trash and spam are kept before
+    /// [`purge_expired`](Self::purge_expired) deletes them.
+    ///
+    /// Mirrors the retention sync settings, 30 days for both by default.
+    pub async fn set_purge_retention(&self, settings: &SyncSettings) {
+        *self.purge_after.write().await = purge_after(settings);
+    }
+
+    /// Permanently deletes trash and spam older than their retention, at
+    /// the provider and then locally.
+    ///
+    /// Age is measured from when a thread was trashed or spammed. Starred
+    /// threads are never purged; they are reported so the user can be
+    /// warned. Threads of an account without a reachable provider are left
+    /// for the next run, since sync would bring them back.
+    pub async fn purge_expired(&self, now: DateTime<Utc>) -> Result<PurgeReport> {
+        let purge_after = self.purge_after.read().await.clone();
+        let mut report = PurgeReport::default();
+        let mut expired: HashMap<AccountId, Vec<ThreadId>> = HashMap::new();
+
+        for (label, retention) in purge_after {
+            for thread in self
+                .storage
+                .threads_trashed_before(&label, now - retention)
+                .await?
+            {
+                if thread.is_starred {
+                    tracing::warn!("Keeping starred thread {} in {}", thread.id, label);
+                    report.kept_starred.push(thread.id);
+                } else {
+                    expired
+                        .entry(thread.account_id)
+                        .or_default()
+                        .push(thread.id);
+                }
+            }
+        }
+
+        for (account_id, thread_ids) in expired {
+            let ids: Vec<String> = thread_ids.iter().map(|id| id.0.clone()).collect();
+            let providers = self.providers.read().await;
+            let Some(provider) = providers.get(&account_id) else {
+                continue;
+            };
+            let call = provider.delete_permanently(&ids);
+            let result = provider_call("delete_permanently", &account_id, call).await;
+            drop(providers);
+            if let Err(e) = result {
+                tracing::warn!("Failed to purge expired threads of {}: {}", account_id, e);
+                continue;
+            }
+
+            for thread_id in thread_ids {
+                self.storage.delete_thread(&thread_id).await?;
+                report.purged.push(thread_id);
+            }
+            self.invalidate_folder_counts(&account_id).await;
+        }
+
+        Ok(report)
+    }
+
+    /// Starts purging expired trash and spam every `interval`.
+    ///
+    /// The task stops once the service is dropped.
+    pub fn start_purging(self: Arc<Self>, interval: Duration)
+    where
+        S: 'static,
+    {
+        let service = Arc::downgrade(&self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(service) = service.upgrade() else {
+                    break;
+                };
+                if let Err(e) = service.purge_expired(Utc::now()).await {
+                    tracing::warn!("Failed to purge expired threads: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Returns unread and total counts per folder and label for an account.
+    ///
+    /// Counts are computed by storage on first use and then kept current as
+    /// actions are applied through this service, without re-querying.
+    pub async fn folder_counts(&self, account_id: &AccountId) -> Result<FolderCounts> {
+        if let Some(counts) = self.folder_counts.read().await.get(account_id) {
+            return Ok(counts.clone());
+        }
+
+        let counts = self.storage.folder_counts(account_id).await?;
+        self.folder_counts
+            .write()
+            .await
+            .insert(account_id.clone(), counts.clone());
+        Ok(counts)
+    }
+
+    /// Discards cached folder counts so the next call recomputes them.
+    ///
+    /// Call after a sync changes threads outside this service.
+    pub async fn invalidate_folder_counts(&self, account_id: &AccountId) {
+        self.folder_counts.write().await.remove(account_id);
+    }
+
+    /// Applies a change locally, then confirms it with the thread's provider.
+    ///
+    /// The local update happens first so the UI reflects it immediately. The
+    /// pre-change state is kept as an [`UndoableAction`] until the provider
+    /// responds; if it rejects the change, the action's inverse is applied and
+    /// a [`MutationEvent::RolledBack`] is emitted. Connection failures and rate
+    /// limits are not rejections, so the local change is kept.
+    async fn mutate(
+        &self,
+        thread_id: &ThreadId,
+        action_type: ActionType,
+        update: ThreadMetadataUpdate,
+        change: PendingChangeType,
+    ) -> Result<()> {
+        let Some(thread) = self.storage.get_thread(thread_id).await? else {
+            // Not stored locally, so there is nothing to roll back. Try every
+            // provider and ignore those that don't have the thread.
+            let providers = self.providers.read().await;
+            for provider in providers.values() {
+                let _ = push_change(provider.as_ref(), &change).await;
+            }
+            return self.update_metadata(thread_id, update).await;
+        };
+
+        let before = ActionState {
+            thread_ids: vec![thread_id.clone()],
+            original_folder: update.remove_labels.first().cloned(),
+            target_folder: update.add_labels.first().cloned(),
+            original_labels: thread.labels.clone(),
+            affected_labels: match action_type {
+                ActionType::RemoveLabels => update.remove_labels.clone(),
+                _ => update.add_labels.clone(),
+            },
+            read_states: vec![(thread_id.clone(), thread.unread_count == 0)],
+            starred_states: vec![(thread_id.clone(), thread.is_starred)],
+            snooze_until: None,
+        };
+        let token = MutationToken(self.next_token.fetch_add(1, Ordering::Relaxed));
+        self.pending_mutations
+            .write()
+            .await
+            .insert(token, UndoableAction::new(action_type, before));
+
+        if let Err(e) = self.update_metadata(thread_id, update).await {
+            self.pending_mutations.write().await.remove(&token);
+            return Err(e);
+        }
+
+        let providers = self.providers.read().await;
+        let result = match providers.get(&thread.account_id) {
+            Some(provider) => {
+                let call = push_change(provider.as_ref(), &change);
+                provider_call("push_change", &thread.account_id, call).await
+            }
+            // No provider registered (e.g. offline): keep the local change.
+            None => Ok(()),
+        };
+        drop(providers);
+
+        self.finish_mutation(token, result).await
+    }
+
+    /// Resolves an optimistic change once the provider has responded.
+    async fn finish_mutation(&self, token: MutationToken, result: Result<()>) -> Result<()> {
+        let Some(action) = self.pending_mutations.write().await.remove(&token) else {
+            return result;
+        };
+
+        let error = match result {
+            Ok(()) => {
+                let _ = self.event_sender.send(MutationEvent::Confirmed(token));
+                return Ok(());
+            }
+            Err(e) if is_transient(&e) => {
+                tracing::warn!("Provider unavailable, keeping local change: {}", e);
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+
+        tracing::warn!("Provider rejected change, rolling back: {}", error);
+        for (thread_id, update) in action.inverse() {
+            self.update_metadata(&thread_id, update).await?;
+        }
+        let _ = self.event_sender.send(MutationEvent::RolledBack {
+            token,
+            description: action.description.clone(),
+            error: error.to_string(),
+        });
+
+        Err(error)
+    }
+
+    /// Updates thread metadata in storage and adjusts cached folder counts.
+    async fn update_metadata(
+        &self,
+        thread_id: &ThreadId,
+        updates: ThreadMetadataUpdate,
+    ) -> Result<()> {
+        let before = self.storage.get_thread(thread_id).await?;
+        self.storage
+            .update_thread_metadata(thread_id, updates.clone())
+            .await?;
+
+        let Some(thread) = before else {
+            return Ok(());
+        };
+        let mut cache = self.folder_counts.write().await;
+        if let Some(counts) = cache.get_mut(&thread.account_id) {
+            let old = ThreadCountState::of(&thread);
+            let new = old.updated(&thread, &updates);
+            counts.apply(&old, false);
+            counts.apply(&new, true);
+        }
+
+        Ok(())
+    }
+
+    /// Unsubscribes from the mailing list that sent an email.
+    ///
+    /// Performs an RFC 8058 one-click POST when the list supports it and
+    /// external requests are allowed. Otherwise returns the `mailto:` URI or
+    /// page the user must act on, so no request is made without consent.
+    ///
+    /// The outcome is recorded per sender, see
+    /// [`unsubscribe_outcome`](Self::unsubscribe_outcome). Once a one-click
+    /// unsubscribe has completed, the list isn't contacted again.
+    pub async fn unsubscribe(&self, email: &Email) -> Result<UnsubscribeOutcome> {
+        let info = email
+            .unsubscribe
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Email has no unsubscribe information: {}", email.id))?;
+
+        let sender = email.from.email.to_lowercase();
+        let previous = self
+            .storage
+            .unsubscribe_outcome(&email.account_id, &sender)
+            .await?;
+        if previous == Some(UnsubscribeOutcome::Completed) {
+            return Ok(UnsubscribeOutcome::Completed);
+        }
+
+        let outcome = self.request_unsubscribe(email, info).await?;
+        self.storage
+            .set_unsubscribe_outcome(&email.account_id, &sender, &outcome)
+            .await?;
+        Ok(outcome)
+    }
+
+    /// Returns the outcome of the last unsubscribe from the list that sent
+    /// an email, or `None` if the user never unsubscribed from it.
+    pub async fn unsubscribe_outcome(&self, email: &Email) -> Result<Option<UnsubscribeOutcome>> {
+        self.storage
+            .unsubscribe_outcome(&email.account_id, &email.from.email.to_lowercase())
+            .await
+    }
+
+    /// Unsubscribes with the best method `info` offers.
+    async fn request_unsubscribe(
+        &self,
+        email: &Email,
+        info: &UnsubscribeInfo,
+    ) -> Result<UnsubscribeOutcome> {
+        let allowed = self.external_requests_allowed.load(Ordering::Relaxed);
+        if let (true, true, Some(url)) = (info.one_click, allowed, &info.url) {
+            let response = self
+                .http
+                .post(url)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body("List-Unsubscribe=One-Click")
+                .send()
+                .await?;
+            if response.status().is_success() {
+                return Ok(UnsubscribeOutcome::Completed);
+            }
+            tracing::warn!(
+                status = %response.status(),
+                "One-click unsubscribe rejected, falling back"
+            );
+        }
+
+        if let Some(mailto) = &info.mailto {
+            return Ok(UnsubscribeOutcome::Mailto(mailto.clone()));
+        }
+        match &info.url {
+            Some(url) => Ok(UnsubscribeOutcome::OpenUrl(url.clone())),
+            None => anyhow::bail!("No usable unsubscribe method for email: {}", email.id),
+        }
+    }
+
+    /// Sends a read receipt (RFC 8098 message disposition notification) for
+    /// an email the user opened.
+    ///
+    /// Only call this once the user has agreed, after
+    /// [`should_prompt_read_receipt`](Self::should_prompt_read_receipt).
+    /// Fails if read receipts are disabled or the sender didn't ask for one.
+    pub async fn send_read_receipt(&self, email: &Email) -> Result<EmailId> {
+        if !self.read_receipts_enabled.load(Ordering::Relaxed) {
+            anyhow::bail!("Read receipts are disabled");
+        }
+        let recipient = email
+            .read_receipt_to
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Email did not request a read receipt: {}", email.id))?;
+
+        let providers = self.providers.read().await;
+        let provider = providers
+            .get(&email.account_id)
+            .ok_or_else(|| anyhow::anyhow!("No provider for account: {}", email.account_id))?;
+
+        let mut references: Vec<String> = email.references.iter().map(|r| r.0.clone()).collect();
+        references.push(email.message_id.0.clone());
+        let receipt = OutgoingEmail {
+            from: Address::new(""), // Will be filled by provider from account
+            to: vec![recipient],
+            cc: vec![],
+            bcc: vec![],
+            subject: format!("Read: {}", email.subject.as_deref().unwrap_or_default()),
+            body_text: read_receipt_body(email),
+            body_html: None,
+            in_reply_to: Some(email.thread_id.clone()),
+            reply_to_message_id: Some(email.message_id.0.clone()),
+            references,
+            idempotency_key: read_receipt_key(email),
+            request_read_receipt: false,
+            read_receipt_for: Some(email.message_id.0.clone()),
+        };
+
+        Ok(EmailId::from(provider.send_email(&receipt).await?))
+    }
+
+    /// Exports all mail of an account into `dir`.
+    ///
+    /// Messages are written to [`ExportFormat::file_name`]: an mbox file, or
+    /// a JSON document that also holds the account's labels and contacts.
+    /// Downloaded attachments are written as files under `attachments/`,
+    /// listed in `attachments/index.json`. Threads are loaded and written one
+    /// page at a time, so large accounts are never held in memory.
+    pub async fn export_account(
+        &self,
+        account_id: &AccountId,
+        format: ExportFormat,
+        dir: &Path,
+    ) -> Result<ExportSummary> {
+        std::fs::create_dir_all(dir.join(EXPORT_ATTACHMENTS_DIR))?;
+        let mut out = BufWriter::new(File::create(dir.join(format.file_name()))?);
+
+        if format == ExportFormat::Json {
+            out.write_all(b"{\"account_id\":")?;
+            serde_json::to_writer(&mut out, account_id)?;
+            out.write_all(b",\"labels\":")?;
+            serde_json::to_writer(&mut out, &self.storage.get_labels(account_id).await?)?;
+            out.write_all(b",\"contacts\":")?;
+            serde_json::to_writer(&mut out, &self.storage.get_contacts(account_id).await?)?;
+            out.write_all(b",\"threads\":[")?;
+        }
+
+        let mut summary = ExportSummary::default();
+        let mut attachments = Vec::new();
+        let mut page = Pagination::with_limit(EXPORT_PAGE_SIZE);
+        loop {
+            let threads = self
+                .storage
+                .get_threads(account_id, ViewType::All, page)
+                .await?;
+            for thread_summary in &threads {
+                let Some(thread) = self.storage.get_thread(&thread_summary.id).await? else {
+                    continue;
+                };
+
+                match format {
+                    ExportFormat::Mbox => {
+                        for email in &thread.messages {
+                            write_mbox_message(&mut out, email)?;
+                        }
+                    }
+                    ExportFormat::Json => {
+                        if summary.threads > 0 {
+                            out.write_all(b",")?;
+                        }
+                        serde_json::to_writer(&mut out, &thread)?;
+                    }
+                }
+                summary.threads += 1;
+                summary.emails += thread.messages.len();
+
+                for email in &thread.messages {
+                    for attachment in &email.attachments {
+                        if let Some(exported) =
+                            self.export_attachment(dir, email, attachment).await?
+                        {
+                            attachments.push(exported);
+                        }
+                    }
+                }
+            }
+
+            if threads.len() < page.limit {
+                break;
+            }
+            page = page.next_page();
+        }
+
+        if format == ExportFormat::Json {
+            out.write_all(b"]}")?;
+        }
+        out.flush()?;
+
+        summary.attachments = attachments.len();
+        let index = File::create(dir.join(EXPORT_ATTACHMENTS_DIR).join("index.json"))?;
+        serde_json::to_writer_pretty(BufWriter::new(index), &attachments)?;
+        Ok(summary)
+    }
+
+    /// Writes a downloaded attachment to the export directory. Returns `None`
+    /// when its content isn't stored locally.
+    async fn export_attachment(
+        &self,
+        dir: &Path,
+        email: &Email,
+        attachment: &Attachment,
+    ) -> Result<Option<ExportedAttachment>> {
+        let Some(data) = self
+            .storage
+            .get_attachment_data(&email.id, &attachment.id)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let email_dir = format!(
+            "{}/{}",
+            EXPORT_ATTACHMENTS_DIR,
+            file_name_component(&email.id.0)
+        );
+        std::fs::create_dir_all(dir.join(&email_dir))?;
+        let filename = file_name_component(&attachment.filename);
+        let mut path = format!("{}/{}", email_dir, filename);
+        if dir.join(&path).exists() {
+            // Two attachments of one email with the same name.
+            path = format!(
+                "{}/{}-{}",
+                email_dir,
+                file_name_component(&attachment.id),
+                filename
+            );
+        }
+        std::fs::write(dir.join(&path), data)?;
+
+        Ok(Some(ExportedAttachment {
+            email_id: email.id.clone(),
+            message_id: email.message_id.0.clone(),
+            filename: attachment.filename.clone(),
+            content_type: attachment.content_type.clone(),
+            path,
+        }))
+    }
+
+    /// Imports the messages of an mbox file into an account.
+    ///
+    /// Messages are marked read unless their `Status` header says otherwise.
+    /// See [`Self::import_maildir`] for how messages are threaded and stored.
+    pub async fn import_mbox<F>(
+        &self,
+        account_id: &AccountId,
+        path: &Path,
+        on_progress: F,
+    ) -> Result<ImportProgress>
+    where
+        F: FnMut(ImportProgress),
+    {
+        let mut importer = Importer::new(&*self.storage, account_id, on_progress);
+        for raw in MboxReader::new(BufReader::new(File::open(path)?)) {
+            importer.add(&raw?, None).await?;
+        }
+        importer.finish().await
+    }
+
+    /// Imports the messages of a maildir (its `cur` and `new` folders) into
+    /// an account.
+    ///
+    /// Each message gets a local id and is grouped into a thread using its
+    /// `References` and `In-Reply-To` headers, joining stored threads where
+    /// they match. Messages whose Message-ID is already stored, or appeared
+    /// earlier in the import, are skipped. Threads are stored in batches and
+    /// `on_progress` is called after each batch.
+    pub async fn import_maildir<F>(
+        &self,
+        account_id: &AccountId,
+        path: &Path,
+        on_progress: F,
+    ) -> Result<ImportProgress>
+    where
+        F: FnMut(ImportProgress),
+    {
+        let mut importer = Importer::new(&*self.storage, account_id, on_progress);
+        for folder in ["cur", "new"] {
+            let dir = path.join(folder);
+            if !dir.is_dir() {
+                continue;
+            }
+            let mut files: Vec<_> = std::fs::read_dir(&dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|file| file.is_file())
+                .collect();
+            files.sort();
+
+            for file in files {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let flags = MessageFlags::from_maildir(folder == "new", &name);
+                importer.add(&std::fs::read(&file)?, Some(flags)).await?;
+            }
+        }
+        importer.finish().await
+    }
+}
+
+/// Cancels follow-up reminders as replies arrive through sync.
+#[async_trait::async_trait]
+impl<S: EmailStorage> EventSubscriber for EmailService<S> {
+    async fn handle(&self, event: &ServiceEvent) {
+        if let ServiceEvent::NewEmail { email, .. } = event {
+            self.handle_change(&Change::NewEmail(email.clone())).await;
+        }
+    }
+}
+
+/// Read, starred and draft state of an imported message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MessageFlags {
+    is_read: bool,
+    is_starred: bool,
+    is_draft: bool,
+}
+
+impl MessageFlags {
+    /// Reads flags from mbox `Status` and `X-Status` headers. Messages
+    /// without a `Status` header count as read.
+    fn from_headers(status: Option<&str>, x_status: Option<&str>) -> Self {
+        let x_status = x_status.unwrap_or_default();
+        Self {
+            is_read: status.map_or(true, |s| s.contains('R')),
+            is_starred: x_status.contains('F'),
+            is_draft: x_status.contains('T'),
+        }
+    }
+
+    /// Reads flags from a maildir file name (`<unique>:2,<flags>`). Messages
+    /// in `new` haven't been seen by any client.
+    fn from_maildir(is_new: bool, file_name: &str) -> Self {
+        let flags = file_name
+            .rsplit_once(":2,")
+            .or_else(|| file_name.rsplit_once("!2,"))
+            .map(|(_, flags)| flags)
+            .unwrap_or_default();
+        Self {
+            is_read: !is_new && flags.contains('S'),
+            is_starred: flags.contains('F'),
+            is_draft: flags.contains('D'),
+        }
+    }
+}
+
+/// Threads imported messages and stores them in batches.
+struct Importer<'a, S, F> {
+    storage: &'a S,
+    account_id: &'a AccountId,
+    on_progress: F,
+    /// Thread of each Message-ID seen in the import, including referenced
+    /// messages that haven't been seen themselves.
+    threads_by_message: HashMap<String, ThreadId>,
+    /// Message-IDs of the messages imported so far.
+    imported: HashSet<String>,
+    /// Parsed messages awaiting storage, by thread.
+    pending: HashMap<ThreadId, Vec<Email>>,
+    /// Attachment contents of the pending messages, stored once they are.
+    pending_contents: Vec<(EmailId, String, Vec<u8>)>,
+    pending_count: usize,
+    progress: ImportProgress,
+}
+
+impl<'a, S: EmailStorage, F: FnMut(ImportProgress)> Importer<'a, S, F> {
+    fn new(storage: &'a S, account_id: &'a AccountId, on_progress: F) -> Self {
+        Self {
+            storage,
+            account_id,
+            on_progress,
+            threads_by_message: HashMap::new(),
+            imported: HashSet::new(),
+            pending: HashMap::new(),
+            pending_contents: Vec::new(),
+            pending_count: 0,
+            progress: ImportProgress::default(),
+        }
+    }
+
+    /// Parses and threads one raw message. Flags are read from its headers
+    /// when `flags` is `None`.
+    async fn add(&mut self, raw: &[u8], flags: Option<MessageFlags>) -> Result<()> {
+        let Some((mut email, contents)) = parse_imported_message(self.account_id, raw, flags)
+        else {
+            self.progress.failed += 1;
+            return Ok(());
+        };
+
+        let key = message_id_key(&email.message_id);
+        if self.imported.contains(&key)
+            || self
+                .storage
+                .thread_for_message(self.account_id, &email.message_id)
+                .await?
+                .is_some()
+        {
+            self.progress.duplicates += 1;
+            return Ok(());
+        }
+
+        let own = &email.message_id;
+        let related: Vec<&MessageId> = email.references.iter().chain(&email.in_reply_to).collect();
+        let mut thread_id = related
+            .iter()
+            .chain([&own])
+            .find_map(|id| self.threads_by_message.get(&message_id_key(id)))
+            .cloned();
+        if thread_id.is_none() {
+            for id in &related {
+                thread_id = self.storage.thread_for_message(self.account_id, id).await?;
+                if thread_id.is_some() {
+                    break;
+                }
+            }
+        }
+        let thread_id =
+            thread_id.unwrap_or_else(|| ThreadId::from(uuid::Uuid::new_v4().to_string()));
+
+        for id in related.into_iter().chain([own]) {
+            self.threads_by_message
+                .entry(message_id_key(id))
+                .or_insert_with(|| thread_id.clone());
+        }
+        self.imported.insert(key);
+        email.thread_id = thread_id.clone();
+        for (attachment, data) in email.attachments.iter().zip(contents) {
+            self.pending_contents
+                .push((email.id.clone(), attachment.id.clone(), data));
+        }
+        self.pending.entry(thread_id).or_default().push(email);
+        self.pending_count += 1;
+        self.progress.imported += 1;
+
+        if self.pending_count >= IMPORT_BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Stores pending messages, merging them into threads stored by earlier
+    /// batches.
+    async fn flush(&mut self) -> Result<()> {
+        let mut threads = Vec::with_capacity(self.pending.len());
+        for (thread_id, emails) in self.pending.drain() {
+            let mut messages = match self.storage.get_thread(&thread_id).await? {
+                Some(stored) => stored.messages,
+                None => Vec::new(),
+            };
+            messages.extend(emails);
+            threads.push(thread_from_messages(thread_id, self.account_id, messages));
+        }
+        self.pending_count = 0;
+
+        self.storage.store_threads(&threads).await?;
+        for (email_id, attachment_id, data) in self.pending_contents.drain(..) {
+            self.storage
+                .store_attachment_data(&email_id, &attachment_id, data)
+                .await?;
+        }
+        (self.on_progress)(self.progress);
+        Ok(())
+    }
+
+    /// Stores the last batch and returns the totals.
+    async fn finish(mut self) -> Result<ImportProgress> {
+        self.flush().await?;
+        Ok(self.progress)
+    }
+}
+
+/// Converts a raw RFC 5322 message into an email with a new local id.
+///
+/// Messages without a Message-ID get one derived from their content, so
+/// importing the same file twice still skips them. The decoded content of
+/// each attachment is returned alongside, in the same order.
+fn parse_imported_message(
+    account_id: &AccountId,
+    raw: &[u8],
+    flags: Option<MessageFlags>,
+) -> Option<(Email, Vec<Vec<u8>>)> {
+    use mail_parser::{MessageParser, MimeHeaders};
+
+    let message = MessageParser::default().parse(raw)?;
+    let addresses = |address: Option<&mail_parser::Address>| -> Vec<Address> {
+        address
+            .and_then(|a| a.as_list())
+            .map(|list| {
+                list.iter()
+                    .map(|addr| Address {
+                        email: addr.address().unwrap_or_default().to_string(),
+                        name: addr.name().map(str::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let message_id = match message.message_id() {
+        Some(id) => id.to_string(),
+        None => {
+            let digest = ring::digest::digest(&ring::digest::SHA256, raw);
+            let hex: String = digest.as_ref()[..16]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            format!("{}@import.invalid", hex)
+        }
+    };
+    let references = message
+        .references()
+        .as_text_list()
+        .map(|refs| {
+            refs.iter()
+                .map(|r| MessageId::from(r.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let flags = flags.unwrap_or_else(|| {
+        MessageFlags::from_headers(message.header_raw("Status"), message.header_raw("X-Status"))
+    });
+
+    let body_text = message.body_text(0).map(|s| s.to_string());
+    let body_html = message.body_html(0).map(|s| s.to_string());
+    let snippet = body_text
+        .as_deref()
+        .map(|body| snippet_from_body(body, IMPORT_SNIPPET_LENGTH))
+        .unwrap_or_default();
+    let contents = message
+        .attachments()
+        .map(|part| part.contents().to_vec())
+        .collect();
+    let attachments = message
+        .attachments()
+        .map(|part| Attachment {
+            id: uuid::Uuid::new_v4().to_string(),
+            filename: part.attachment_name().unwrap_or("attachment").to_string(),
+            content_type: part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+            size_bytes: part.contents().len() as u64,
+            is_inline: part.content_disposition().is_some_and(|d| d.is_inline()),
+            content_hash: None,
+            content_id: part.content_id().map(normalize_content_id),
+        })
+        .collect();
+
+    let email = Email {
+        id: EmailId::from(uuid::Uuid::new_v4().to_string()),
+        account_id: account_id.clone(),
+        thread_id: ThreadId::from(String::new()),
+        message_id: MessageId::from(message_id),
+        in_reply_to: message
+            .in_reply_to()
+            .as_text()
+            .map(|id| MessageId::from(id.to_string())),
+        references,
+        from: addresses(message.from())
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Address::new("unknown@unknown.invalid")),
+        to: addresses(message.to()),
+        cc: addresses(message.cc()),
+        bcc: addresses(message.bcc()),
+        subject: message.subject().map(str::to_string),
+        body_text,
+        body_html,
+        snippet,
+        date: message
+            .date()
+            .and_then(|d| DateTime::from_timestamp(d.to_timestamp(), 0))
+            .unwrap_or_else(Utc::now),
+        is_read: flags.is_read,
+        is_starred: flags.is_starred,
+        is_draft: flags.is_draft,
+        labels: vec![],
+        attachments,
+        unsubscribe: None,
+        read_receipt_to: None,
+    };
+    Some((email, contents))
+}
+
+/// Normalizes a Message-ID for comparison, without angle brackets.
+fn message_id_key(id: &MessageId) -> String {
+    id.0.trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+/// Builds a thread from its messages, ordering them by date.
+fn thread_from_messages(id: ThreadId, account_id: &AccountId, mut messages: Vec<Email>) -> Thread {
+    messages.sort_by_key(|m| m.date);
+    let mut labels: Vec<LabelId> = Vec::new();
+    for label in messages.iter().flat_map(|m| &m.labels) {
+        if !labels.contains(label) {
+            labels.push(label.clone());
+        }
+    }
+    Thread {
+        id,
+        account_id: account_id.clone(),
+        subject: messages.first().and_then(|m| m.subject.clone()),
+        snippet: Thread::preview_snippet(&messages),
+        participants: Thread::ordered_participants(&messages, None),
+        last_message_date: messages.last().map(|m| m.date).unwrap_or_else(Utc::now),
+        unread_count: messages.iter().filter(|m| !m.is_read).count() as u32,
+        is_starred: messages.iter().any(|m| m.is_starred),
+        labels,
+        messages,
+    }
+}
+
+/// Creates an unaddressed, empty draft.
+fn empty_draft(account_id: AccountId, subject: String) -> Draft {
+    let now = Utc::now();
+    Draft {
+        id: None,
+        account_id,
+        from: None,
+        reply_to_thread_id: None,
+        reply_to_message_id: None,
+        references: vec![],
+        to: vec![],
+        cc: vec![],
+        bcc: vec![],
+        subject,
+        body_markdown: String::new(),
+        body_html: None,
+        signature_html: None,
+        created_at: now,
+        updated_at: now,
+        idempotency_key: new_idempotency_key(),
+        request_read_receipt: false,
+    }
+}
+
+/// Returns the idempotency key of the read receipt for `email`.
+///
+/// It is derived from the email's Message-ID, so sending the receipt again
+/// reuses the Message-ID and can't deliver a second copy.
+fn read_receipt_key(email: &Email) -> String {
+    let id: String = email
+        .message_id
+        .0
+        .trim_matches(|c| c == '<' || c == '>')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!("mdn.{}", id)
+}
+
+/// Builds the human-readable part of a read receipt for `email`.
+fn read_receipt_body(email: &Email) -> String {
+    format!(
+        "This is a receipt for the mail you sent on {}.\n\n\
+         Subject: {}\n\
+         Message-ID: {}\n\n\
+         The message was displayed on the recipient's computer. There is no \
+         guarantee it was read or understood.\n",
+        email.date.format("%a, %d %b %Y %H:%M UTC"),
+        email.subject.as_deref().unwrap_or_default(),
+        email.message_id.0,
+    )
+}
+
+/// Prefixes a thread's subject with `prefix:`, removing existing `strip`
+/// prefixes (case-insensitive) so they don't pile up ("Re: Re: ...").
+fn prefixed_subject(thread: &Thread, prefix: &str, strip: &[&str]) -> String {
+    let mut subject = thread.subject.as_deref().unwrap_or_default().trim();
+    while let Some(rest) = strip.iter().find_map(|p| {
+        subject
+            .get(..p.len())
+            .filter(|head| head.eq_ignore_ascii_case(p))
+            .map(|_| subject[p.len()..].trim_start())
+    }) {
+        subject = rest;
+    }
+    format!("{}: {}", prefix, subject)
+}
+
+/// Makes `name` safe to use as a single path component.
+fn file_name_component(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let safe = safe.trim().trim_start_matches('.');
+    if safe.is_empty() {
+        "attachment".to_string()
+    } else {
+        safe.to_string()
+    }
+}
+
+/// Returns the text of a message for quoting, falling back to the snippet.
+fn message_text(email: &Email) -> &str {
+    email.body_text.as_deref().unwrap_or(&email.snippet)
+}
+
+/// Converts plain text to HTML, escaping markup and keeping line breaks.
+fn text_to_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+/// Formats addresses as a comma-separated header value.
+fn join_addresses(addresses: &[Address]) -> String {
+    addresses
+        .iter()
+        .map(Address::display)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Sends a pending change to the provider.
+async fn push_change(provider: &dyn EmailProvider, change: &PendingChangeType) -> Result<()> {
+    match change {
+        PendingChangeType::Archive { thread_ids } => provider.archive(thread_ids).await,
+        PendingChangeType::Trash { thread_ids } => provider.trash(thread_ids).await,
+        PendingChangeType::ReportSpam { thread_ids } => provider.report_spam(thread_ids).await,
+        PendingChangeType::Star { thread_id, starred } => provider.star(thread_id, *starred).await,
+        PendingChangeType::MarkRead { thread_id, read } => {
+            provider.mark_read(thread_id, *read).await
+        }
+        PendingChangeType::ApplyLabel { thread_id, label } => {
+            provider.apply_label(thread_id, label).await
+        }
+        PendingChangeType::RemoveLabel { thread_id, label } => {
+            provider.remove_label(thread_id, label).await
+        }
+        PendingChangeType::SendEmail { draft_id } => {
+            anyhow::bail!("Sending draft {} is not a metadata change", draft_id)
+        }
+    }
+}
+
+/// Returns the folders purged by [`EmailService::purge_expired`] with their
+/// retention.
+fn purge_after(settings: &SyncSettings) -> Vec<(LabelId, chrono::Duration)> {
+    [
+        ("TRASH", settings.trash_retention()),
+        ("SPAM", settings.spam_retention()),
+    ]
+    .into_iter()
+    .filter_map(|(label, retention)| Some((LabelId::from(label), retention?)))
+    .collect()
+}
+
+/// Returns whether an error is temporary rather than a rejection.
+fn is_transient(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ProviderError>(),
+        Some(ProviderError::Connection(_) | ProviderError::RateLimited { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FolderMapping, MessageId};
+    use chrono::TimeZone;
+
+    struct NoopStorage;
+
+    #[async_trait::async_trait]
+    impl EmailStorage for NoopStorage {
+        async fn get_threads(
+            &self,
+            _account_id: &AccountId,
+            _view: ViewType,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn get_thread(&self, _thread_id: &ThreadId) -> Result<Option<Thread>> {
+            Ok(None)
+        }
+
+        async fn store_thread(&self, _thread: &Thread) -> Result<()> {
+            Ok(())
+        }
+
+        async fn update_thread_metadata(
+            &self,
+            _thread_id: &ThreadId,
+            _updates: ThreadMetadataUpdate,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn folder_counts(&self, _account_id: &AccountId) -> Result<FolderCounts> {
+            Ok(FolderCounts::default())
+        }
+    }
+
+    /// Storage holding a single thread.
+    struct ThreadStorage {
+        thread: std::sync::Mutex<Thread>,
+        count_queries: std::sync::atomic::AtomicUsize,
+        sends: std::sync::Mutex<HashMap<String, SendState>>,
+        unsubscribes: std::sync::Mutex<HashMap<String, UnsubscribeOutcome>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailStorage for ThreadStorage {
+        async fn get_threads(
+            &self,
+            _account_id: &AccountId,
+            _view: ViewType,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn get_thread(&self, _thread_id: &ThreadId) -> Result<Option<Thread>> {
+            Ok(Some(self.thread.lock().unwrap().clone()))
+        }
+
+        async fn store_thread(&self, thread: &Thread) -> Result<()> {
+            *self.thread.lock().unwrap() = thread.clone();
+            Ok(())
+        }
+
+        async fn restore_body(&self, email: &Email) -> Result<()> {
+            let mut thread = self.thread.lock().unwrap();
+            if let Some(stored) = thread.messages.iter_mut().find(|m| m.id == email.id) {
+                stored.body_text = email.body_text.clone();
+                stored.body_html = email.body_html.clone();
+            }
+            Ok(())
+        }
+
+        async fn update_thread_metadata(
+            &self,
+            _thread_id: &ThreadId,
+            updates: ThreadMetadataUpdate,
+        ) -> Result<()> {
+            let mut thread = self.thread.lock().unwrap();
+            if updates.is_read == Some(true) {
+                thread.unread_count = 0;
+            }
+            if let Some(starred) = updates.is_starred {
+                thread.is_starred = starred;
+            }
+            thread.labels.retain(|l| !updates.remove_labels.contains(l));
+            thread.labels.extend(updates.add_labels);
+            Ok(())
+        }
+
+        async fn folder_counts(&self, _account_id: &AccountId) -> Result<FolderCounts> {
+            self.count_queries.fetch_add(1, Ordering::SeqCst);
+            let thread = self.thread.lock().unwrap();
+            Ok(FolderCounts::from_labels(thread.labels.iter().map(|l| {
+                let count = FolderCount {
+                    unread: thread.unread_count,
+                    total: 1,
+                };
+                (l.clone(), count)
+            })))
+        }
+
+        async fn send_state(&self, idempotency_key: &str) -> Result<Option<SendState>> {
+            Ok(self.sends.lock().unwrap().get(idempotency_key).cloned())
+        }
+
+        async fn set_send_state(
+            &self,
+            idempotency_key: &str,
+            state: Option<SendState>,
+        ) -> Result<()> {
+            let mut sends = self.sends.lock().unwrap();
+            match state {
+                Some(state) => sends.insert(idempotency_key.to_string(), state),
+                None => sends.remove(idempotency_key),
+            };
+            Ok(())
+        }
+
+        async fn unsubscribe_outcome(
+            &self,
+            _account_id: &AccountId,
+            sender: &str,
+        ) -> Result<Option<UnsubscribeOutcome>> {
+            Ok(self.unsubscribes.lock().unwrap().get(sender).cloned())
+        }
+
+        async fn set_unsubscribe_outcome(
+            &self,
+            _account_id: &AccountId,
+            sender: &str,
+            outcome: &UnsubscribeOutcome,
+        ) -> Result<()> {
+            self.unsubscribes
+                .lock()
+                .unwrap()
+                .insert(sender.to_string(), outcome.clone());
+            Ok(())
+        }
+    }
+
+    fn inbox_thread(unread: u32) -> Thread {
+        let messages: Vec<Email> = (0..3)
+            .map(|i| {
+                let mut email = newsletter(None);
+                email.id = EmailId::from(format!("email-{}", i));
+                email.message_id = MessageId::from(format!("<msg-{}@example.com>", i));
+                email.is_read = i >= unread;
+                email
+            })
+            .collect();
+        Thread {
+            id: ThreadId::from("thread-1"),
+            account_id: AccountId::from("account-1"),
+            subject: None,
+            snippet: String::new(),
+            participants: vec![],
+            messages,
+            last_message_date: Utc::now(),
+            unread_count: unread,
+            is_starred: false,
+            labels: vec![LabelId::from("INBOX"), LabelId::from("work")],
+        }
+    }
+
+    fn thread_service(unread: u32) -> (EmailService<ThreadStorage>, Arc<ThreadStorage>) {
+        let storage = Arc::new(ThreadStorage {
+            thread: std::sync::Mutex::new(inbox_thread(unread)),
+            count_queries: std::sync::atomic::AtomicUsize::new(0),
+            sends: std::sync::Mutex::new(HashMap::new()),
+            unsubscribes: std::sync::Mutex::new(HashMap::new()),
+        });
+        (EmailService::new(storage.clone()), storage)
+    }
+
+    #[tokio::test]
+    async fn mark_read_decrements_inbox_unread_by_thread_unread_count() {
+        let (service, storage) = thread_service(2);
+        let account = AccountId::from("account-1");
+
+        let before = service.folder_counts(&account).await.unwrap();
+        assert_eq!(before.get(&ViewType::Inbox).unread, 2);
+
+        service
+            .mark_read(&ThreadId::from("thread-1"), true)
+            .await
+            .unwrap();
+
+        let after = service.folder_counts(&account).await.unwrap();
+        assert_eq!(after.get(&ViewType::Inbox).unread, 0);
+        assert_eq!(after.get(&ViewType::Inbox).total, 1);
+        assert_eq!(after.get(&ViewType::Label(LabelId::from("work"))).unread, 0);
+        assert_eq!(storage.count_queries.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn archive_moves_thread_out_of_inbox_count() {
+        let (service, _) = thread_service(1);
+        let account = AccountId::from("account-1");
+        service.folder_counts(&account).await.unwrap();
+
+        service
+            .archive(&[ThreadId::from("thread-1")])
+            .await
+            .unwrap();
+
+        let counts = service.folder_counts(&account).await.unwrap();
+        assert_eq!(counts.get(&ViewType::Inbox), FolderCount::default());
+        assert_eq!(counts.unread_badge(&ViewType::Inbox), None);
+        assert_eq!(
+            counts.unread_badge(&ViewType::Label(LabelId::from("work"))),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn report_spam_moves_thread_and_offers_to_block_sender() {
+        let (service, storage) = thread_service(1);
+
+        let senders = service
+            .report_spam(&[ThreadId::from("thread-1")])
+            .await
+            .unwrap();
+
+        let sender = inbox_thread(1).messages[0].from.clone();
+        assert_eq!(senders, vec![sender]);
+        let labels = storage.thread.lock().unwrap().labels.clone();
+        assert!(labels.contains(&LabelId::from("SPAM")));
+        assert!(!labels.contains(&LabelId::from("INBOX")));
+    }
+
+    #[tokio::test]
+    async fn not_spam_moves_reported_thread_back_to_inbox() {
+        let (service, storage) = thread_service(1);
+        let thread_ids = [ThreadId::from("thread-1")];
+        service.report_spam(&thread_ids).await.unwrap();
+
+        service.not_spam(&thread_ids).await.unwrap();
+
+        let labels = storage.thread.lock().unwrap().labels.clone();
+        assert!(!labels.contains(&LabelId::from("SPAM")));
+        assert!(labels.contains(&LabelId::from("INBOX")));
+    }
+
+    /// Provider whose star requests fail with a fixed error.
+    struct StarFailingProvider {
+        error: fn() -> ProviderError,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailProvider for StarFailingProvider {
+        fn provider_type(&self) -> &str {
+            "mock"
+        }
+
+        async fn fetch_threads(
+            &self,
+            _folder: &str,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn fetch_thread(&self, thread_id: &str) -> Result<Thread> {
+            Err(ProviderError::NotFound(thread_id.to_string()).into())
+        }
+
+        async fn send_email(&self, _email: &OutgoingEmail) -> Result<String> {
+            Ok(String::new())
+        }
+
+        async fn archive(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn trash(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn report_spam(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn star(&self, _thread_id: &str, _starred: bool) -> Result<()> {
+            Err((self.error)().into())
+        }
+
+        async fn mark_read(&self, _thread_id: &str, _read: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn apply_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn remove_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn service_with_star_error(
+        error: fn() -> ProviderError,
+    ) -> (EmailService<ThreadStorage>, Arc<ThreadStorage>) {
+        let (service, storage) = thread_service(0);
+        service
+            .register_provider(
+                AccountId::from("account-1"),
+                Arc::new(StarFailingProvider { error }),
+            )
+            .await;
+        (service, storage)
+    }
+
+    #[tokio::test]
+    async fn rejected_star_rolls_back_to_unstarred() {
+        let (service, storage) =
+            service_with_star_error(|| ProviderError::InvalidRequest("no".to_string())).await;
+        let mut events = service.subscribe();
+
+        let result = service.star(&ThreadId::from("thread-1"), true).await;
+
+        assert!(result.is_err());
+        assert!(!storage.thread.lock().unwrap().is_starred);
+        assert_eq!(service.pending_mutation_count().await, 0);
+        match events.try_recv().unwrap() {
+            MutationEvent::RolledBack { description, .. } => {
+                assert!(description.contains("starred"))
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_failure_keeps_optimistic_star() {
+        let (service, storage) =
+            service_with_star_error(|| ProviderError::Connection("offline".to_string())).await;
+
+        service
+            .star(&ThreadId::from("thread-1"), true)
+            .await
+            .unwrap();
+
+        assert!(storage.thread.lock().unwrap().is_starred);
+    }
+
+    /// Provider that serves one thread with full bodies.
+    struct FullThreadProvider {
+        thread: Thread,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailProvider for FullThreadProvider {
+        fn provider_type(&self) -> &str {
+            "mock"
+        }
+
+        async fn fetch_threads(
+            &self,
+            _folder: &str,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn fetch_thread(&self, _thread_id: &str) -> Result<Thread> {
+            Ok(self.thread.clone())
+        }
+
+        async fn send_email(&self, _email: &OutgoingEmail) -> Result<String> {
+            Ok(String::new())
+        }
+
+        async fn archive(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn trash(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn report_spam(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn star(&self, _thread_id: &str, _starred: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn mark_read(&self, _thread_id: &str, _read: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn apply_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn remove_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Provider that delivers messages, optionally timing out after the
+    /// first delivery as if the response had been lost.
+    struct DeliveringProvider {
+        delivered: std::sync::Mutex<Vec<OutgoingEmail>>,
+        lose_response: AtomicBool,
+        reject: bool,
+    }
+
+    impl DeliveringProvider {
+        fn new() -> Self {
+            Self {
+                delivered: std::sync::Mutex::new(Vec::new()),
+                lose_response: AtomicBool::new(false),
+                reject: false,
+            }
+        }
+
+        fn delivered(&self) -> usize {
+            self.delivered.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EmailProvider for DeliveringProvider {
+        fn provider_type(&self) -> &str {
+            "mock"
+        }
+
+        async fn fetch_threads(
+            &self,
+            _folder: &str,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn fetch_thread(&self, thread_id: &str) -> Result<Thread> {
+            Err(ProviderError::NotFound(thread_id.to_string()).into())
+        }
+
+        async fn send_email(&self, email: &OutgoingEmail) -> Result<String> {
+            if self.reject {
+                return Err(ProviderError::InvalidRequest("bad recipient".to_string()).into());
+            }
+            let message_id = crate::providers::email::idempotent_message_id(&email.idempotency_key);
+            self.delivered.lock().unwrap().push(email.clone());
+            if self.lose_response.swap(false, Ordering::SeqCst) {
+                return Err(ProviderError::Connection("request timed out".to_string()).into());
+            }
+            Ok(message_id)
+        }
+
+        async fn archive(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn trash(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn report_spam(&self, _thread_ids: &[String]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn star(&self, _thread_id: &str, _starred: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn mark_read(&self, _thread_id: &str, _read: bool) -> Result<()> {
+            Ok(())
+        }
+
+        async fn apply_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn remove_label(&self, _thread_id: &str, _label: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn find_sent(&self, message_id: &str) -> Result<Option<String>> {
+            let delivered = self.delivered.lock().unwrap();
+            Ok(delivered
+                .iter()
+                .map(|e| crate::providers::email::idempotent_message_id(&e.idempotency_key))
+                .find(|id| id == message_id))
+        }
+    }
+
+    fn outgoing_draft() -> Draft {
+        let mut draft = empty_draft(AccountId::from("account-1"), "Plans".to_string());
+        draft.to = vec![Address::new("alice@example.com")];
+        draft.body_markdown = "See you at 3.".to_string();
+        draft
+    }
+
+    #[tokio::test]
+    async fn retry_after_timeout_sends_once() {
+        let (service, storage) = thread_service(0);
+        let provider = Arc::new(DeliveringProvider::new());
+        provider.lose_response.store(true, Ordering::SeqCst);
+        service
+            .register_provider(AccountId::from("account-1"), provider.clone())
+            .await;
+        let draft = outgoing_draft();
+        let key = draft.idempotency_key.clone();
+
+        assert!(service.send_email(draft.clone()).await.is_err());
+        assert_eq!(
+            storage.sends.lock().unwrap().get(&key),
+            Some(&SendState::InFlight)
+        );
+
+        let sent = service.send_email(draft.clone()).await.unwrap();
+        assert_eq!(provider.delivered(), 1);
+        assert_eq!(sent.0, format!("<{}@heap.local>", key));
+        assert_eq!(
+            storage.sends.lock().unwrap().get(&key),
+            Some(&SendState::Sent(sent.clone()))
+        );
+
+        // Sending the same draft again is a no-op.
+        assert_eq!(service.send_email(draft).await.unwrap(), sent);
+        assert_eq!(provider.delivered(), 1);
+
+        // A different draft is a different send.
+        service.send_email(outgoing_draft()).await.unwrap();
+        assert_eq!(provider.delivered(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_send_can_be_retried() {
+        let (service, storage) = thread_service(0);
+        let provider = Arc::new(DeliveringProvider {
+            reject: true,
+            ..DeliveringProvider::new()
+        });
+        service
+            .register_provider(AccountId::from("account-1"), provider)
+            .await;
+        let draft = outgoing_draft();
+
+        assert!(service.send_email(draft.clone()).await.is_err());
+        assert!(storage
+            .sends
+            .lock()
+            .unwrap()
+            .get(&draft.idempotency_key)
+            .is_none());
+    }
+
+    async fn send_pipeline() -> (Arc<EmailService<ThreadStorage>>, Arc<DeliveringProvider>) {
+        let (service, _storage) = thread_service(0);
+        let provider = Arc::new(DeliveringProvider::new());
+        service
+            .register_provider(AccountId::from("account-1"), provider.clone())
+            .await;
+        (Arc::new(service), provider)
+    }
+
+    #[tokio::test]
+    async fn immediate_send_goes_straight_to_sending() {
+        let (service, provider) = send_pipeline().await;
+
+        let handle = service
+            .send_with_options(outgoing_draft(), SendOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(handle.status(), SendStatus::Sending);
+        assert!(!handle.cancel());
+
+        assert!(matches!(handle.finished().await, SendStatus::Sent(_)));
+        assert_eq!(provider.delivered(), 1);
+    }
+
+    #[tokio::test]
+    async fn canceled_send_is_never_delivered() {
+        let (service, provider) = send_pipeline().await;
+        let options = SendOptions {
+            delay: Duration::from_secs(10),
+            ..Default::default()
+        };
+
+        let handle = service
+            .send_with_options(outgoing_draft(), options)
+            .await
+            .unwrap();
+        let mut updates = handle.subscribe();
+        assert!(matches!(handle.status(), SendStatus::Queued { .. }));
+
+        assert!(handle.cancel());
+        updates.changed().await.unwrap();
+        assert_eq!(*updates.borrow(), SendStatus::Canceled);
+        assert_eq!(handle.finished().await, SendStatus::Canceled);
+        assert!(!handle.cancel());
+
+        tokio::task::yield_now().await;
+        assert_eq!(provider.delivered(), 0);
+    }
+
+    #[tokio::test]
+    async fn delayed_and_scheduled_sends_go_out_when_due() {
+        let (service, provider) = send_pipeline().await;
+
+        let delayed = service
+            .send_with_options(
+                outgoing_draft(),
+                SendOptions {
+                    delay: Duration::from_millis(20),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let send_at = Utc::now() + chrono::Duration::milliseconds(40);
+        let scheduled = service
+            .send_with_options(
+                outgoing_draft(),
+                SendOptions {
+                    // The schedule wins over the undo window.
+                    delay: Duration::from_secs(60),
+                    schedule_at: Some(send_at),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(scheduled.status(), SendStatus::Scheduled { send_at });
+
+        let mut updates = scheduled.subscribe();
+        updates.changed().await.unwrap();
+        assert!(matches!(
+            *updates.borrow(),
+            SendStatus::Sending | SendStatus::Sent(_)
+        ));
+        assert!(Utc::now() >= send_at);
+
+        assert!(matches!(delayed.finished().await, SendStatus::Sent(_)));
+        assert!(matches!(scheduled.finished().await, SendStatus::Sent(_)));
+        assert_eq!(provider.delivered(), 2);
+    }
+
+    #[tokio::test]
+    async fn send_with_options_needs_a_provider() {
+        let (service, _storage) = thread_service(0);
+        let service = Arc::new(service);
+        assert!(service
+            .send_with_options(outgoing_draft(), SendOptions::default())
+            .await
+            .is_err());
+    }
+
+    fn receipt_requested() -> Email {
+        let mut email = newsletter(None);
+        email.from = Address::new("alice@example.com");
+        email.subject = Some("Contract".to_string());
+        email.read_receipt_to = Some(Address::with_name("receipts@example.com", "Alice"));
+        email
+    }
+
+    #[tokio::test]
+    async fn read_receipts_are_not_sent_by_default() {
+        let (service, _storage) = thread_service(0);
+        let provider = Arc::new(DeliveringProvider::new());
+        service
+            .register_provider(AccountId::from("account-1"), provider.clone())
+            .await;
+        let email = receipt_requested();
+
+        assert!(!service.should_prompt_read_receipt(&email));
+        assert!(service.send_read_receipt(&email).await.is_err());
+        assert_eq!(provider.delivered(), 0);
+    }
+
+    #[tokio::test]
+    async fn read_receipt_is_sent_to_requested_address() {
+        let (service, _storage) = thread_service(0);
+        let provider = Arc::new(DeliveringProvider::new());
+        service
+            .register_provider(AccountId::from("account-1"), provider.clone())
+            .await;
+        service.set_read_receipts_enabled(true);
+        let email = receipt_requested();
+
+        assert!(service.should_prompt_read_receipt(&email));
+        assert!(!service.should_prompt_read_receipt(&newsletter(None)));
+        service.send_read_receipt(&email).await.unwrap();
+
+        let delivered = provider.delivered.lock().unwrap();
+        let receipt = &delivered[0];
+        assert_eq!(
+            receipt.to,
+            vec![Address::with_name("receipts@example.com", "Alice")]
+        );
+        assert_eq!(receipt.subject, "Read: Contract");
+        assert_eq!(
+            receipt.reply_to_message_id.as_deref(),
+            Some("<msg-1@example.com>")
+        );
+        assert!(receipt.body_text.contains("<msg-1@example.com>"));
+        assert!(!receipt.request_read_receipt);
+        assert_eq!(
+            receipt.read_receipt_for.as_deref(),
+            Some("<msg-1@example.com>")
+        );
+        assert_eq!(receipt.idempotency_key, "mdn.msg-1.example.com");
+        drop(delivered);
+
+        // A retried receipt keeps its Message-ID.
+        service.send_read_receipt(&email).await.unwrap();
+        let delivered = provider.delivered.lock().unwrap();
+        assert_eq!(delivered[1].idempotency_key, delivered[0].idempotency_key);
+    }
+
+    #[tokio::test]
+    async fn opening_evicted_thread_refetches_bodies() {
+        let (service, storage) = thread_service(0);
+        let mut remote = inbox_thread(0);
+        for message in &mut remote.messages {
+            message.body_text = Some(format!("Body of {}", message.id));
+            message.body_html = Some(format!("<p>Body of {}</p>", message.id));
+        }
+        service
+            .register_provider(
+                AccountId::from("account-1"),
+                Arc::new(FullThreadProvider {
+                    thread: remote.clone(),
+                }),
+            )
+            .await;
+        assert!(!storage.thread.lock().unwrap().messages[0].has_body());
+
+        let thread = service
+            .get_thread(&ThreadId::from("thread-1"))
+            .await
+            .unwrap();
+
+        assert_eq!(thread.messages.len(), remote.messages.len());
+        for (opened, fetched) in thread.messages.iter().zip(&remote.messages) {
+            assert_eq!(opened.body_text, fetched.body_text);
+            assert_eq!(opened.body_html, fetched.body_html);
+        }
+        assert!(storage
+            .thread
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .all(Email::has_body));
+    }
+
+    #[tokio::test]
+    async fn evicted_thread_opens_with_snippets_when_offline() {
+        let (service, _) =
+            service_with_star_error(|| ProviderError::Connection("offline".to_string())).await;
+
+        let thread = service
+            .get_thread(&ThreadId::from("thread-1"))
+            .await
+            .unwrap();
+
+        assert!(thread.messages.iter().all(|m| !m.has_body()));
+    }
+
+    #[test]
+    fn folder_counts_map_system_labels_to_views() {
+        let counts = FolderCounts::from_labels(vec![
+            (
+                LabelId::from("INBOX"),
+                FolderCount {
+                    unread: 4,
+                    total: 10,
+                },
+            ),
+            (
+                LabelId::from("DRAFT"),
+                FolderCount {
+                    unread: 0,
+                    total: 2,
+                },
+            ),
+            (
+                LabelId::from("receipts"),
+                FolderCount {
+                    unread: 1,
+                    total: 3,
+                },
+            ),
+        ]);
+
+        assert_eq!(counts.unread_badge(&ViewType::Inbox), Some(4));
+        assert_eq!(counts.get(&ViewType::Drafts).total, 2);
+        assert_eq!(counts.unread_badge(&ViewType::Drafts), None);
+        assert_eq!(
+            counts
+                .get(&ViewType::Label(LabelId::from("receipts")))
+                .total,
+            3
+        );
+        assert_eq!(counts.get(&ViewType::Sent), FolderCount::default());
+    }
+
+    fn newsletter(unsubscribe: Option<UnsubscribeInfo>) -> Email {
+        Email {
+            message_id: MessageId::from("<msg-1@example.com>"),
+            from: Address::new("news@example.com"),
+            to: vec![],
+            subject: None,
+            body_text: None,
+            snippet: String::new(),
+            unsubscribe,
+            ..crate::domain::test_email("email-1", "thread-1")
+        }
+    }
+
+    /// A thread whose latest message is from Alice to me and Bob, CC Carol.
+    fn conversation() -> Thread {
+        let mut first = newsletter(None);
+        first.from = Address::new("me@example.com");
+        first.to = vec![Address::new("alice@example.com")];
+        first.date = Utc::now() - chrono::Duration::hours(1);
+
+        let mut latest = newsletter(None);
+        latest.id = EmailId::from("email-2");
+        latest.message_id = MessageId::from("<msg-2@example.com>");
+        latest.references = vec![MessageId::from("<msg-1@example.com>")];
+        latest.from = Address::with_name("alice@example.com", "Alice");
+        latest.to = vec![
+            Address::new("Me@Example.com"),
+            Address::new("bob@example.com"),
+        ];
+        latest.cc = vec![
+            Address::new("carol@example.com"),
+            Address::new("alice@example.com"),
+        ];
+        latest.subject = Some("RE: re: Lunch".to_string());
+        latest.body_text = Some("Friday?\nAlice".to_string());
+
+        Thread {
+            id: ThreadId::from("thread-1"),
+            account_id: AccountId::from("account-1"),
+            subject: Some("RE: re: Lunch".to_string()),
+            snippet: String::new(),
+            participants: vec![],
+            messages: vec![latest, first],
+            last_message_date: Utc::now(),
+            unread_count: 1,
+            is_starred: false,
+            labels: vec![LabelId::from("INBOX")],
+        }
+    }
+
+    fn emails(addresses: &[Address]) -> Vec<&str> {
+        addresses.iter().map(|a| a.email.as_str()).collect()
+    }
+
+    #[test]
+    fn reply_all_copies_everyone_but_me_and_the_sender() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+
+        let draft = service.build_reply(&conversation(), ReplyKind::ReplyAll, "me@example.com");
+
+        assert_eq!(emails(&draft.to), vec!["alice@example.com"]);
+        assert_eq!(
+            emails(&draft.cc),
+            vec!["bob@example.com", "carol@example.com"]
+        );
+    }
+
+    #[test]
+    fn reply_threads_onto_latest_message() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+
+        let draft = service.build_reply(&conversation(), ReplyKind::Reply, "me@example.com");
+
+        assert_eq!(emails(&draft.to), vec!["alice@example.com"]);
+        assert!(draft.cc.is_empty());
+        assert_eq!(draft.subject, "Re: Lunch");
+        assert_eq!(draft.reply_to_thread_id, Some(ThreadId::from("thread-1")));
+        assert_eq!(
+            draft.reply_to_message_id.as_deref(),
+            Some("<msg-2@example.com>")
+        );
+        assert_eq!(
+            draft.references,
+            vec!["<msg-1@example.com>", "<msg-2@example.com>"]
+        );
+        assert!(draft
+            .body_markdown
+            .contains("Alice <alice@example.com> wrote:\n> Friday?\n> Alice"));
+    }
+
+    #[test]
+    fn reply_to_own_message_goes_to_its_recipients() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let mut thread = conversation();
+        thread.messages.remove(0);
+
+        let draft = service.build_reply(&thread, ReplyKind::Reply, "me@example.com");
+
+        assert_eq!(emails(&draft.to), vec!["alice@example.com"]);
+        assert_eq!(draft.references, vec!["<msg-1@example.com>"]);
+    }
+
+    fn account(id: &str, signature: &str) -> Account {
+        Account {
+            id: AccountId::from(id),
+            email: format!("{}@example.com", id),
+            display_name: None,
+            provider_type: crate::domain::ProviderType::Gmail,
+            provider_config: crate::domain::ProviderConfig::Gmail {},
+            sync_enabled: true,
+            sync_interval: std::time::Duration::from_secs(300),
+            signature: Some(signature.to_string()),
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+            aliases: vec![],
+        }
+    }
+
+    #[test]
+    fn compose_uses_each_accounts_signature() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let work = account("work", "Jane Doe\nACME Corp");
+        let home = account("home", "-- \nJane");
+
+        let draft = service.build_new(&work, true);
+        assert_eq!(draft.account_id, work.id);
+        assert_eq!(draft.body_markdown, "\n\n-- \nJane Doe\nACME Corp");
+
+        let draft = service.build_new(&home, true);
+        assert_eq!(draft.account_id, home.id);
+        assert_eq!(draft.body_markdown, "\n\n-- \nJane");
+
+        let draft = service.build_new(&work, false);
+        assert!(draft.body_markdown.is_empty());
+    }
+
+    #[test]
+    fn reply_signature_goes_above_quoted_text() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let mut home = account("home", "Jane");
+        home.signature_html = Some("<b>Jane</b>".to_string());
+
+        let mut draft = service.build_reply(&conversation(), ReplyKind::Reply, "me@example.com");
+        service.insert_signature(&mut draft, &home, true);
+
+        assert_eq!(draft.account_id, home.id);
+        assert!(draft.body_markdown.starts_with("\n\n-- \nJane\n\nOn "));
+        let html = draft.render_html().unwrap();
+        assert!(html.starts_with("<br><br><div class=\"signature\"><b>Jane</b></div>"));
+        assert!(html.contains("Alice &lt;alice@example.com&gt; wrote:<br>&gt; Friday?"));
+    }
+
+    #[test]
+    fn html_body_is_rendered_from_the_final_text() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let mut home = account("home", "Jane");
+        home.signature_html = Some("<b>Jane</b>".to_string());
+        let mut draft = service.build_new(&home, true);
+
+        draft.body_markdown = format!("Lunch on Friday?{}", draft.body_markdown);
+
+        assert_eq!(
+            draft.render_html().unwrap(),
+            "Lunch on Friday?<br><br><div class=\"signature\"><b>Jane</b></div>"
+        );
+        draft.body_markdown = "No signature".to_string();
+        assert_eq!(draft.render_html().unwrap(), "No signature");
+
+        home.signature = None;
+        let mut draft = service.build_new(&home, true);
+        draft.body_markdown = format!("Hi{}", draft.body_markdown);
+        assert_eq!(draft.body_markdown, "Hi\n\n-- ");
+        assert_eq!(
+            draft.render_html().unwrap(),
+            "Hi<br><br><div class=\"signature\"><b>Jane</b></div>"
+        );
+    }
+
+    #[test]
+    fn forward_includes_original_headers() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let mut thread = conversation();
+        thread.subject = Some("Fw: Lunch".to_string());
+
+        let draft = service.build_forward(&thread);
+
+        assert_eq!(draft.subject, "Fwd: Lunch");
+        assert!(draft.to.is_empty());
+        assert!(draft.reply_to_message_id.is_none());
+        assert!(draft
+            .body_markdown
+            .contains("From: Alice <alice@example.com>\n"));
+        assert!(draft
+            .body_markdown
+            .contains("To: Me@Example.com, bob@example.com\n"));
+        assert!(draft.body_markdown.ends_with("Friday?\nAlice\n"));
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_without_external_requests_does_not_post() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let one_click = UnsubscribeInfo::parse(
+            "<https://example.com/u/abc>",
+            Some("List-Unsubscribe=One-Click"),
+        );
+
+        let outcome = service.unsubscribe(&newsletter(one_click)).await.unwrap();
+        assert_eq!(
+            outcome,
+            UnsubscribeOutcome::OpenUrl("https://example.com/u/abc".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_prefers_mailto_over_manual_url() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let info = UnsubscribeInfo::parse(
+            "<https://example.com/u/abc>, <mailto:leave@example.com>",
+            None,
+        );
+
+        let outcome = service.unsubscribe(&newsletter(info)).await.unwrap();
+        assert_eq!(
+            outcome,
+            UnsubscribeOutcome::Mailto("mailto:leave@example.com".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_outcomes_are_recorded_per_sender() {
+        let (service, storage) = thread_service(0);
+        let email = newsletter(UnsubscribeInfo::parse("<mailto:leave@example.com>", None));
+        assert_eq!(service.unsubscribe_outcome(&email).await.unwrap(), None);
+
+        service.unsubscribe(&email).await.unwrap();
+
+        assert_eq!(
+            service.unsubscribe_outcome(&email).await.unwrap(),
+            Some(UnsubscribeOutcome::Mailto(
+                "mailto:leave@example.com".to_string()
+            ))
+        );
+
+        // A completed unsubscribe doesn't contact the list again.
+        storage.unsubscribes.lock().unwrap().insert(
+            "news@example.com".to_string(),
+            UnsubscribeOutcome::Completed,
+        );
+        service.set_external_requests_allowed(true);
+        let one_click = UnsubscribeInfo::parse(
+            "<https://unreachable.invalid/u/abc>",
+            Some("List-Unsubscribe=One-Click"),
+        );
+        assert_eq!(
+            service.unsubscribe(&newsletter(one_click)).await.unwrap(),
+            UnsubscribeOutcome::Completed
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_requires_header() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        assert!(service.unsubscribe(&newsletter(None)).await.is_err());
+    }
+
+    fn reply_on(thread_id: &str) -> Change {
+        let mut email = newsletter(None);
+        email.thread_id = ThreadId::from(thread_id);
+        Change::NewEmail(Box::new(email))
+    }
+
+    #[tokio::test]
+    async fn followup_fires_once_after_deadline() {
+        let (service, _) = thread_service(0);
+        let thread_id = ThreadId::from("thread-1");
+        let due_at = service
+            .set_followup(&thread_id, Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        assert!(service
+            .check_followups(due_at - chrono::Duration::minutes(1))
+            .await
+            .is_empty());
+
+        let due = service.check_followups(due_at).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].thread_id, thread_id);
+        assert_eq!(due[0].notification.category, NotificationCategory::Reminder);
+        assert_eq!(due[0].classification.view_type, SmartViewType::FollowUp);
+        assert_eq!(due[0].classification.thread_id, thread_id);
+
+        assert!(service
+            .check_followups(due_at + chrono::Duration::hours(1))
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn reply_cancels_followup() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let thread_id = ThreadId::from("thread-1");
+        let due_at = service
+            .set_followup(&thread_id, Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        // A reply on another thread leaves the reminder in place.
+        assert!(!service.handle_change(&reply_on("thread-2")).await);
+        assert_eq!(service.followup_due_at(&thread_id).await, Some(due_at));
+
+        assert!(service.handle_change(&reply_on("thread-1")).await);
+        assert!(service.check_followups(due_at).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reply_published_by_sync_cancels_followup() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let thread_id = ThreadId::from("thread-1");
+        service
+            .set_followup(&thread_id, Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        let Change::NewEmail(email) = reply_on("thread-1") else {
+            unreachable!()
+        };
+        let event = ServiceEvent::NewEmail {
+            email,
+            watched: false,
+        };
+        service.handle(&event).await;
+
+        assert_eq!(service.followup_due_at(&thread_id).await, None);
+    }
+
+    #[tokio::test]
+    async fn reply_found_in_storage_cancels_followup() {
+        let (service, storage) = thread_service(0);
+        let thread_id = ThreadId::from("thread-1");
+        let due_at = service
+            .set_followup(&thread_id, Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        // The reply was stored without going through `handle_change`.
+        let mut reply = newsletter(None);
+        reply.id = EmailId::from("reply-1");
+        reply.date = Utc::now() + chrono::Duration::minutes(5);
+        storage.thread.lock().unwrap().messages.push(reply);
+
+        assert!(service.check_followups(due_at).await.is_empty());
+        assert_eq!(service.followup_due_at(&thread_id).await, None);
+    }
+
+    #[tokio::test]
+    async fn own_sent_message_keeps_followup() {
+        let service = EmailService::new(Arc::new(NoopStorage));
+        let thread_id = ThreadId::from("thread-1");
+        service
+            .set_followup(&thread_id, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let mut sent = newsletter(None);
+        sent.labels = vec![LabelId::from("SENT")];
+        assert!(
+            !service
+                .handle_change(&Change::NewEmail(Box::new(sent)))
+                .await
+        );
+        assert!(service.followup_due_at(&thread_id).await.is_some());
+    }
+
+    /// Storage holding complete threads and attachment contents.
+    struct ExportStorage {
+        threads: Vec<Thread>,
+        attachments: HashMap<(EmailId, String), Vec<u8>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailStorage for ExportStorage {
+        async fn get_threads(
+            &self,
+            _account_id: &AccountId,
+            _view: ViewType,
+            pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(self
+                .threads
+                .iter()
+                .skip(pagination.offset)
+                .take(pagination.limit)
+                .map(|t| ThreadSummary {
+                    id: t.id.clone(),
+                    account_id: t.account_id.clone(),
+                    subject: t.subject.clone(),
+                    snippet: t.snippet.clone(),
+                    from: t.messages[0].from.clone(),
+                    last_message_date: t.last_message_date,
+                    message_count: t.messages.len() as u32,
+                    unread_count: t.unread_count,
+                    is_starred: t.is_starred,
+                    labels: t.labels.clone(),
+                    muted: false,
+                    watched: false,
+                })
+                .collect())
+        }
+
+        async fn get_thread(&self, thread_id: &ThreadId) -> Result<Option<Thread>> {
+            Ok(self.threads.iter().find(|t| &t.id == thread_id).cloned())
+        }
+
+        async fn store_thread(&self, _thread: &Thread) -> Result<()> {
+            Ok(())
+        }
+
+        async fn update_thread_metadata(
+            &self,
+            _thread_id: &ThreadId,
+            _updates: ThreadMetadataUpdate,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn folder_counts(&self, _account_id: &AccountId) -> Result<FolderCounts> {
+            Ok(FolderCounts::default())
+        }
+
+        async fn get_labels(&self, account_id: &AccountId) -> Result<Vec<Label>> {
+            Ok(vec![Label {
+                id: LabelId::from("work"),
+                account_id: account_id.clone(),
+                name: "Work".to_string(),
+                color: None,
+                is_system: false,
+                provider_id: None,
+            }])
+        }
+
+        async fn get_attachment_data(
+            &self,
+            email_id: &EmailId,
+            attachment_id: &str,
+        ) -> Result<Option<Vec<u8>>> {
+            let key = (email_id.clone(), attachment_id.to_string());
+            Ok(self.attachments.get(&key).cloned())
+        }
+    }
+
+    /// Two threads: a multipart message with a reply, and a message with an
+    /// attachment.
+    fn export_service() -> EmailService<ExportStorage> {
+        let mut first = newsletter(None);
+        first.message_id = MessageId::from("<msg-1@example.com>");
+        first.from = Address::with_name("jose@example.com", "José");
+        first.to = vec![Address::new("me@example.com")];
+        first.subject = Some("Café plans".to_string());
+        first.body_text = Some("Lunch on Friday?".to_string());
+        first.body_html = Some("<p>Lunch on <b>Friday</b>?</p>".to_string());
+        first.date = Utc.with_ymd_and_hms(2024, 3, 13, 9, 30, 0).unwrap();
+
+        let mut reply = newsletter(None);
+        reply.id = EmailId::from("email-2");
+        reply.message_id = MessageId::from("<msg-2@example.com>");
+        reply.in_reply_to = Some(MessageId::from("<msg-1@example.com>"));
+        reply.references = vec![
+            MessageId::from("<msg-0@example.com>"),
+            MessageId::from("<msg-1@example.com>"),
+        ];
+        reply.from = Address::new("me@example.com");
+        reply.to = vec![Address::with_name("jose@example.com", "José")];
+        reply.cc = vec![Address::with_name("bob@example.com", "Bob")];
+        reply.subject = Some("Re: Café plans".to_string());
+        reply.body_text = Some("Sounds good.\nFrom the office at noon?".to_string());
+        reply.date = Utc.with_ymd_and_hms(2024, 3, 13, 10, 0, 0).unwrap();
+
+        let mut invoice = newsletter(None);
+        invoice.id = EmailId::from("INBOX:42");
+        invoice.thread_id = ThreadId::from("thread-2");
+        invoice.message_id = MessageId::from("<msg-3@example.com>");
+        invoice.subject = Some("Invoice".to_string());
+        invoice.body_text = Some("Attached.".to_string());
+        invoice.attachments = vec![Attachment {
+            id: "att-1".to_string(),
+            filename: "invoice.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 4,
+            is_inline: false,
+            content_hash: None,
+            content_id: None,
+        }];
+
+        let thread = |id: &str, messages: Vec<Email>| Thread {
+            id: ThreadId::from(id),
+            account_id: AccountId::from("account-1"),
+            subject: messages[0].subject.clone(),
+            snippet: String::new(),
+            participants: vec![],
+            last_message_date: messages[messages.len() - 1].date,
+            messages,
+            unread_count: 0,
+            is_starred: false,
+            labels: vec![],
+        };
+        let mut attachments = HashMap::new();
+        attachments.insert(
+            (EmailId::from("INBOX:42"), "att-1".to_string()),
+            b"%PDF".to_vec(),
+        );
+        EmailService::new(Arc::new(ExportStorage {
+            threads: vec![
+                thread("thread-1", vec![first, reply]),
+                thread("thread-2", vec![invoice]),
+            ],
+            attachments,
+        }))
+    }
+
+    #[tokio::test]
+    async fn mbox_export_round_trips_headers_and_bodies() {
+        use mail_parser::MessageParser;
+
+        let service = export_service();
+        let dir = tempfile::tempdir().unwrap();
+        let summary = service
+            .export_account(
+                &AccountId::from("account-1"),
+                ExportFormat::Mbox,
+                dir.path(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                threads: 2,
+                emails: 3,
+                attachments: 1,
+            }
+        );
+
+        let mbox = std::fs::File::open(dir.path().join("mail.mbox")).unwrap();
+        let raw: Vec<Vec<u8>> = crate::domain::MboxReader::new(std::io::BufReader::new(mbox))
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(raw.len(), 3);
+
+        let first = MessageParser::default().parse(&raw[0]).unwrap();
+        assert_eq!(first.message_id(), Some("msg-1@example.com"));
+        assert_eq!(first.subject(), Some("Café plans"));
+        let from = first.from().and_then(|a| a.first()).unwrap();
+        assert_eq!(from.name(), Some("José"));
+        assert_eq!(from.address(), Some("jose@example.com"));
+        assert_eq!(
+            first.date().unwrap().to_timestamp(),
+            Utc.with_ymd_and_hms(2024, 3, 13, 9, 30, 0)
+                .unwrap()
+                .timestamp()
+        );
+        assert_eq!(first.body_text(0).unwrap(), "Lunch on Friday?");
+        assert_eq!(
+            first.body_html(0).unwrap(),
+            "<p>Lunch on <b>Friday</b>?</p>"
+        );
+
+        let reply = MessageParser::default().parse(&raw[1]).unwrap();
+        assert_eq!(reply.in_reply_to().as_text(), Some("msg-1@example.com"));
+        let references: Vec<String> = reply
+            .references()
+            .as_text_list()
+            .unwrap()
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        assert_eq!(references, vec!["msg-0@example.com", "msg-1@example.com"]);
+        let cc = reply.cc().and_then(|a| a.first()).unwrap();
+        assert_eq!(cc.address(), Some("bob@example.com"));
+        assert_eq!(
+            reply.body_text(0).unwrap().trim_end(),
+            "Sounds good.\nFrom the office at noon?"
+        );
+
+        let index: Vec<ExportedAttachment> = serde_json::from_reader(
+            std::fs::File::open(dir.path().join("attachments/index.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].path, "attachments/INBOX_42/invoice.pdf");
+        assert_eq!(
+            std::fs::read(dir.path().join(&index[0].path)).unwrap(),
+            b"%PDF"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_export_bundles_labels_and_threads() {
+        let service = export_service();
+        let dir = tempfile::tempdir().unwrap();
+        service
+            .export_account(
+                &AccountId::from("account-1"),
+                ExportFormat::Json,
+                dir.path(),
+            )
+            .await
+            .unwrap();
+
+        let bundle: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(dir.path().join("account.json")).unwrap())
+                .unwrap();
+        assert_eq!(bundle["account_id"], "account-1");
+        assert_eq!(bundle["labels"][0]["name"], "Work");
+        assert_eq!(bundle["contacts"].as_array().unwrap().len(), 0);
+        let threads = bundle["threads"].as_array().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0]["messages"][1]["subject"], "Re: Café plans");
+    }
+
+    /// Storage keeping threads in memory, looked up by Message-ID.
+    #[derive(Default)]
+    struct ImportStorage {
+        threads: std::sync::Mutex<HashMap<ThreadId, Thread>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailStorage for ImportStorage {
+        async fn get_threads(
+            &self,
+            _account_id: &AccountId,
+            _view: ViewType,
+            _pagination: Pagination,
+        ) -> Result<Vec<ThreadSummary>> {
+            Ok(vec![])
+        }
+
+        async fn get_thread(&self, thread_id: &ThreadId) -> Result<Option<Thread>> {
+            Ok(self.threads.lock().unwrap().get(thread_id).cloned())
+        }
+
+        async fn store_thread(&self, thread: &Thread) -> Result<()> {
+            self.threads
+                .lock()
+                .unwrap()
+                .insert(thread.id.clone(), thread.clone());
+            Ok(())
+        }
+
+        async fn update_thread_metadata(
+            &self,
+            _thread_id: &ThreadId,
+            _updates: ThreadMetadataUpdate,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn folder_counts(&self, _account_id: &AccountId) -> Result<FolderCounts> {
+            Ok(FolderCounts::default())
+        }
+
+        async fn thread_for_message(
+            &self,
+            _account_id: &AccountId,
+            message_id: &MessageId,
+        ) -> Result<Option<ThreadId>> {
+            let threads = self.threads.lock().unwrap();
+            Ok(threads
+                .values()
+                .find(|t| t.messages.iter().any(|m| m.message_id == *message_id))
+                .map(|t| t.id.clone()))
+        }
+    }
+
+    /// Two conversations, an unrelated message and a duplicate. The second
+    /// conversation's reply comes before the message it replies to.
+    const FIXTURE_MBOX: &str = "\
+From ada@example.com Mon Mar 11 09:00:00 2024
+Message-ID: <plans@example.com>
+Date: Mon, 11 Mar 2024 09:00:00 +0000
+From: Ada <ada@example.com>
+To: grace@example.com
+Subject: Plans
+
+Lunch on Friday?
+
+From grace@example.com Mon Mar 11 10:00:00 2024
+Message-ID: <plans-re@example.com>
+Date: Mon, 11 Mar 2024 10:00:00 +0000
+From: Grace <grace@example.com>
+To: ada@example.com
+Subject: Re: Plans
+In-Reply-To: <plans@example.com>
+References: <plans@example.com>
+
+Sounds good.
+>From noon?
+
+From billing@example.com Tue Mar 12 08:00:00 2024
+Message-ID: <invoice@example.com>
+Date: Tue, 12 Mar 2024 08:00:00 +0000
+From: billing@example.com
+To: ada@example.com
+Subject: Invoice
+Status: O
+
+Your invoice is attached.
+
+From ada@example.com Tue Mar 12 11:00:00 2024
+Message-ID: <plans-re-re@example.com>
+Date: Tue, 12 Mar 2024 11:00:00 +0000
+From: Ada <ada@example.com>
+To: grace@example.com
+Subject: Re: Plans
+References: <plans@example.com> <plans-re@example.com>
+Status: RO
+X-Status: F
+
+See you there.
+
+From bob@example.com Wed Mar 13 10:00:00 2024
+Message-ID: <trip-re@example.com>
+Date: Wed, 13 Mar 2024 10:00:00 +0000
+From: bob@example.com
+To: ada@example.com
+Subject: Re: Trip
+In-Reply-To: <trip@example.com>
+References: <trip@example.com>
+
+Booked.
+
+From ada@example.com Wed Mar 13 09:00:00 2024
+Message-ID: <trip@example.com>
+Date: Wed, 13 Mar 2024 09:00:00 +0000
+From: ada@example.com
+To: bob@example.com
+Subject: Trip
+
+Can you book the train?
+
+From billing@example.com Tue Mar 12 08:00:00 2024
+Message-ID: <invoice@example.com>
+Date: Tue, 12 Mar 2024 08:00:00 +0000
+From: billing@example.com
+To: ada@example.com
+Subject: Invoice
+
+Your invoice is attached.
+";
+
+    #[tokio::test]
+    async fn mbox_import_groups_threads_by_references() {
+        let storage = Arc::new(ImportStorage::default());
+        let service = EmailService::new(storage.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixture.mbox");
+        std::fs::write(&path, FIXTURE_MBOX).unwrap();
+        let account_id = AccountId::from("account-1");
+
+        let mut reports = Vec::new();
+        let progress = service
+            .import_mbox(&account_id, &path, |p| reports.push(p))
+            .await
+            .unwrap();
+        assert_eq!(
+            progress,
+            ImportProgress {
+                imported: 6,
+                duplicates: 1,
+                failed: 0,
+            }
+        );
+        assert_eq!(reports.last(), Some(&progress));
+
+        let threads = storage.threads.lock().unwrap().clone();
+        assert_eq!(threads.len(), 3);
+        let thread_of = |message_id: &str| {
+            threads
+                .values()
+                .find(|t| t.messages.iter().any(|m| m.message_id.0 == message_id))
+                .unwrap()
+        };
+
+        let plans = thread_of("plans@example.com");
+        let ids: Vec<&str> = plans
+            .messages
+            .iter()
+            .map(|m| m.message_id.0.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "plans@example.com",
+                "plans-re@example.com",
+                "plans-re-re@example.com"
+            ]
+        );
+        assert_eq!(plans.subject.as_deref(), Some("Plans"));
+        assert!(plans.is_starred);
+        assert_eq!(
+            plans.messages[1].body_text.as_deref().map(str::trim_end),
+            Some("Sounds good.\nFrom noon?")
+        );
+        assert!(plans.messages.iter().all(|m| m.thread_id == plans.id));
+
+        let trip = thread_of("trip@example.com");
+        assert_eq!(trip.messages.len(), 2);
+        assert_eq!(trip.messages[0].message_id.0, "trip@example.com");
+
+        let invoice = thread_of("invoice@example.com");
+        assert_eq!(invoice.messages.len(), 1);
+        assert_eq!(invoice.unread_count, 1);
+
+        // Importing the same file again finds every message already stored.
+        let again = service
+            .import_mbox(&account_id, &path, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(again.imported, 0);
+        assert_eq!(again.duplicates, 7);
+        assert_eq!(storage.threads.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn parsed_cid_reference_links_to_its_inline_part() {
+        let raw = b"Message-ID: <newsletter@example.com>\r
+From: news@example.com\r
+To: ada@example.com\r
+Subject: News\r
+MIME-Version: 1.0\r
+Content-Type: multipart/related; boundary=\"rel\"\r
+\r
+--rel\r
+Content-Type: text/html; charset=utf-8\r
+\r
+<p>Hello</p><img src=\"cid:logo@example.com\">\r
+--rel\r
+Content-Type: image/png\r
+Content-Disposition: inline; filename=\"logo.png\"\r
+Content-ID: <logo@example.com>\r
+Content-Transfer-Encoding: base64\r
+\r
+iVBORw0KGgo=\r
+--rel--\r
+";
+        let (email, contents) =
+            parse_imported_message(&AccountId::from("account-1"), raw, None).unwrap();
+
+        let referenced = crate::domain::referenced_content_ids(email.body_html.as_deref().unwrap());
+        assert_eq!(referenced, ["logo@example.com"]);
+        let parts = email.attachments_by_content_id();
+        let logo = parts[referenced[0].as_str()];
+        assert_eq!(logo.filename, "logo.png");
+        assert_eq!(logo.content_type, "image/png");
+        assert!(logo.is_inline);
+        assert_eq!(contents, [b"\x89PNG\r\n\x1a\n".to_vec()]);
+    }
+
+    #[test]
+    fn maildir_flags_from_file_names() {
+        let flags = MessageFlags::from_maildir(false, "1710000000.M1P2.host:2,FS");
+        assert!(flags.is_read && flags.is_starred && !flags.is_draft);
+
+        let flags = MessageFlags::from_maildir(false, "1710000000.M1P2.host:2,D");
+        assert!(!flags.is_read && flags.is_draft);
+
+        // Messages in `new` haven't been seen, whatever their name says.
+        let flags = MessageFlags::from_maildir(true, "1710000000.M1P2.host");
+        assert!(!flags.is_read && !flags.is_starred);
+    }
+
+    #[test]
+    fn view_type_folder_names() {
+        assert_eq!(ViewType::Inbox.folder_name(), "INBOX");
+        assert_eq!(ViewType::Starred.folder_name(), "[Gmail]/Starred");
+        assert_eq!(ViewType::Sent.folder_name(), "[Gmail]/Sent Mail");
+        assert_eq!(ViewType::Drafts.folder_name(), "[Gmail]/Drafts");
+        assert_eq!(ViewType::Trash.folder_name(), "[Gmail]/Trash");
+    }
+
+    #[test]
+    fn view_type_keys() {
+        assert_eq!(ViewType::Inbox.key(), "inbox");
+        assert_eq!(ViewType::Snoozed.key(), "snoozed");
+        assert_eq!(ViewType::Label(LabelId::from("work")).key(), "label:work");
+    }
+
+    #[test]
+    fn pagination_default() {
+        let p = Pagination::default();
+        assert_eq!(p.offset, 0);
+        assert_eq!(p.limit, 50);
+    }
+
+    #[test]
+    fn pagination_next_page() {
+        let p = Pagination::with_limit(25);
+        let next = p.next_page();
+        assert_eq!(next.offset, 25);
+        assert_eq!(next.limit, 25);
     }
 
     #[test]
@@ -587,4 +4758,58 @@ mod tests {
         assert!(update.remove_labels.is_empty());
         assert!(update.snooze_until.is_none());
     }
+
+    #[test]
+    fn threads_are_grouped_into_bundles() {
+        let summary = |id: &str, from: &str, labels: &[&str]| ThreadSummary {
+            id: ThreadId::from(id),
+            account_id: AccountId::from("account-1"),
+            subject: None,
+            snippet: String::new(),
+            from: Address::new(from),
+            last_message_date: Utc::now(),
+            message_count: 1,
+            unread_count: 1,
+            is_starred: false,
+            labels: labels.iter().map(|&l| LabelId::from(l)).collect(),
+            muted: false,
+            watched: false,
+        };
+        let threads = vec![
+            summary("alice", "alice@example.com", &["INBOX"]),
+            summary("sale", "newsletter@shop.example", &["INBOX"]),
+            summary("mention", "messages-noreply@linkedin.com", &["INBOX"]),
+            summary("receipt", "receipts@store.example", &["INBOX"]),
+            summary("rust", "rust-users@googlegroups.com", &["INBOX"]),
+            summary("bob", "bob@example.com", &["INBOX"]),
+            // Gmail's own tab wins over the sender.
+            summary(
+                "promo",
+                "carol@example.com",
+                &["INBOX", "CATEGORY_PROMOTIONS"],
+            ),
+        ];
+
+        let bundles: Vec<(BundleCategory, Vec<String>)> = bundle_threads(threads)
+            .into_iter()
+            .map(|b| (b.category, b.threads.into_iter().map(|t| t.id.0).collect()))
+            .collect();
+        assert_eq!(
+            bundles,
+            [
+                (BundleCategory::Primary, vec!["alice".into(), "bob".into()]),
+                (
+                    BundleCategory::Promotions,
+                    vec!["sale".into(), "promo".into()]
+                ),
+                (BundleCategory::Social, vec!["mention".into()]),
+                (BundleCategory::Updates, vec!["receipt".into()]),
+                (BundleCategory::Forums, vec!["rust".into()]),
+            ]
+        );
+
+        let primary_only = bundle_threads(vec![summary("alice", "alice@example.com", &[])]);
+        assert_eq!(primary_only.len(), 1);
+        assert_eq!(primary_only[0].category, BundleCategory::Primary);
+    }
 }
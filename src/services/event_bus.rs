@@ -0,0 +1,173 @@
+//! Event bus for cross-service notifications.
+//!
+//! Services publish [`ServiceEvent`]s to a shared [`ServiceBus`] and other
+//! services react without depending on each other. The sync service
+//! publishes as changes arrive from the server; the indexer, notifier and
+//! contact extractor subscribe.
+//!
+//! This is separate from the UI's [`app::EventBus`](crate::app::EventBus),
+//! which carries window events on the UI thread.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::domain::{Email, EmailId, LabelId};
+
+/// Events buffered per subscriber before the slowest one starts missing
+/// events.
+const CAPACITY: usize = 256;
+
+/// Event published on the bus.
+#[derive(Debug, Clone)]
+pub enum ServiceEvent {
+    /// A new email arrived in a thread that isn't muted, or is watched.
+    NewEmail {
+        email: Box<Email>,
+        /// Whether the thread is watched, so the email notifies whatever
+        /// its priority.
+        watched: bool,
+    },
+    /// A message was marked read or unread.
+    ThreadRead { email_id: EmailId, is_read: bool },
+    /// Labels were added to or removed from a message.
+    LabelChanged {
+        email_id: EmailId,
+        added: Vec<LabelId>,
+        removed: Vec<LabelId>,
+    },
+}
+
+/// A service that reacts to events on the bus.
+#[async_trait]
+pub trait EventSubscriber: Send + Sync {
+    /// Handles an event.
+    ///
+    /// Failures are the subscriber's to report; they don't reach the
+    /// publisher.
+    async fn handle(&self, event: &ServiceEvent);
+}
+
+/// Broadcasts events from publishing services to every subscriber.
+///
+/// Cloning is cheap and clones share the same subscribers.
+#[derive(Clone)]
+pub struct ServiceBus {
+    sender: broadcast::Sender<ServiceEvent>,
+}
+
+impl ServiceBus {
+    /// Creates a bus with no subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes an event, returning the number of subscribers it reached.
+    pub fn publish(&self, event: ServiceEvent) -> usize {
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    /// Subscribes to events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ServiceEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Delivers events to `subscriber` on a background task until the task
+    /// is aborted.
+    ///
+    /// A subscriber that falls more than [`CAPACITY`] events behind skips
+    /// the oldest rather than holding up publishers.
+    pub fn register(&self, subscriber: Arc<dyn EventSubscriber>) -> JoinHandle<()> {
+        let mut receiver = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => subscriber.handle(&event).await,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "Event subscriber fell behind");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+impl Default for ServiceBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    use crate::domain::{test_email, Address};
+
+    fn email() -> Email {
+        Email {
+            from: Address::new("alice@example.com"),
+            subject: Some("Lunch?".to_string()),
+            body_text: None,
+            snippet: String::new(),
+            labels: vec![LabelId::from("INBOX")],
+            ..test_email("email-1", "thread-1")
+        }
+    }
+
+    struct Recorder {
+        name: &'static str,
+        seen: mpsc::UnboundedSender<(&'static str, EmailId)>,
+    }
+
+    #[async_trait]
+    impl EventSubscriber for Recorder {
+        async fn handle(&self, event: &ServiceEvent) {
+            if let ServiceEvent::NewEmail { email, .. } = event {
+                let _ = self.seen.send((self.name, email.id.clone()));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn publishing_new_email_triggers_registered_subscribers() {
+        let bus = ServiceBus::new();
+        let (seen, mut received) = mpsc::unbounded_channel();
+        for name in ["indexer", "notifier"] {
+            bus.register(Arc::new(Recorder {
+                name,
+                seen: seen.clone(),
+            }));
+        }
+
+        let event = ServiceEvent::NewEmail {
+            email: Box::new(email()),
+            watched: false,
+        };
+        assert_eq!(bus.publish(event), 2);
+
+        let mut names = vec![];
+        for _ in 0..2 {
+            let (name, email_id) = received.recv().await.unwrap();
+            assert_eq!(email_id, EmailId::from("email-1"));
+            names.push(name);
+        }
+        names.sort();
+        assert_eq!(names, vec!["indexer", "notifier"]);
+    }
+
+    #[test]
+    fn publishing_without_subscribers_is_a_no_op() {
+        let bus = ServiceBus::default();
+        let event = ServiceEvent::ThreadRead {
+            email_id: EmailId::from("email-1"),
+            is_read: true,
+        };
+        assert_eq!(bus.publish(event), 0);
+    }
+}
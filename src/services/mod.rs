@@ -20,6 +20,7 @@
 //! # Services Overview
 //!
 //! - [`EmailService`]: Orchestrates email operations across providers and storage
+//! - [`AttachmentService`]: Thumbnails of image and PDF attachments
 //! - [`AiService`]: Manages AI provider interactions for summarization, drafts, and search
 //! - [`SyncService`]: Handles synchronization between remote providers and local storage
 //! - [`SearchService`]: Combined full-text and semantic search across emails
@@ -28,39 +29,64 @@
 //! - [`SnoozeService`]: Temporarily hides emails until a scheduled time
 //! - [`TelemetryService`]: Local usage statistics and event tracking
 //! - [`ScreenerService`]: Manages unknown sender triage and screening
+//! - [`BlocklistService`]: Blocked senders whose mail is trashed or archived on arrival
 //! - [`NotificationService`]: In-app and system notifications
 //! - [`SmartViewService`]: AI-powered email classification into smart views
 //! - [`StatsService`]: Usage statistics and metrics aggregation
 //! - [`AccountService`]: Manages email account configuration and credentials
 //! - [`ThreadService`]: Thread operations and metadata management
+//! - [`TemplateService`]: Canned responses with placeholder substitution
+//! - [`SpellChecker`]: Dictionary-based spell checking for the composer
+//! - [`ServiceBus`]: Typed events published by one service and handled by others
 
 mod account_service;
 mod ai_service;
+mod attachment_service;
+mod blocklist_service;
 mod contact_service;
 mod email_service;
+mod event_bus;
 mod label_service;
 mod notification_service;
 mod screener_service;
 mod search_service;
 mod smart_view_service;
 mod snooze_service;
+mod spell_checker;
 mod stats_service;
 mod sync_service;
 mod telemetry_service;
+mod template_service;
 mod thread_service;
 mod undo_service;
 
 pub use account_service::{
-    AccountError, AccountService, AccountStats, AccountStorage, AccountUpdate,
-    CreateAccountRequest, CredentialStore,
+    provider_for_account, AccountAuthState, AccountCredentials, AccountError, AccountService,
+    AccountStats, AccountStorage, AccountUpdate, AuthStateReporter, CreateAccountRequest,
+    ProviderFactory,
 };
 pub use ai_service::{
-    AiService, AiSettings, Category, DraftSuggestion, SearchResult, Summary, SummarySettings,
+    AiService, AiSettings, Category, DraftSuggestion, EmbeddingEngine, ModelPricing, PromptKind,
+    SearchResult, Summary, SummaryCache, SummarySettings, TokenPricing, TokenUsage,
+    TranslationSettings,
+};
+pub use attachment_service::{
+    AttachmentService, AttachmentStorage, ImageBytes, PdfRenderer, PdftoppmRenderer,
+};
+pub use blocklist_service::{
+    BlockAction, BlockEntry, Blocklist, BlocklistError, BlocklistService, BlocklistStorage,
 };
 pub use contact_service::{
-    ContactError, ContactFilter, ContactService, ContactSort, ContactStats, ContactStorage,
+    AvatarInitials, AvatarSource, ContactError, ContactFilter, ContactMerge, ContactService,
+    ContactSort, ContactStats, ContactStorage,
+};
+pub use email_service::{
+    Bundle, Draft, EmailProvider, EmailService, EmailStorage, ExportFormat, ExportSummary,
+    FolderCount, FolderCounts, FollowUp, FollowUpDue, ImportProgress, MutationEvent, MutationToken,
+    OutgoingEmail, Pagination, PurgeReport, ReplyKind, SendHandle, SendOptions, SendState,
+    SendStatus, ThreadMetadataUpdate, UnsubscribeOutcome, ViewType,
 };
-pub use email_service::{Draft, EmailService, Pagination, ViewType};
+pub use event_bus::{EventSubscriber, ServiceBus, ServiceEvent};
 pub use label_service::{LabelError, LabelService, LabelSort, LabelStorage};
 pub use notification_service::{
     NotificationCategory, NotificationError, NotificationPriority, NotificationRequest,
@@ -71,24 +97,32 @@ pub use screener_service::{
 };
 pub use search_service::{
     DateRange, EmailMetadata, FtsHit, SearchFolder, SearchHit, SearchMode, SearchQuery,
-    SearchResults, SearchService, SearchSettings, SearchSource, SearchStorage,
+    SearchResults, SearchService, SearchSettings, SearchSource, SearchStorage, ServerSearch,
 };
 pub use smart_view_service::{
-    Classification, ClassificationCriteria, ClassificationInput, SmartViewError, SmartViewService,
-    SmartViewStorage, SmartViewType,
+    BundleCategory, Classification, ClassificationCriteria, ClassificationInput, SmartViewError,
+    SmartViewService, SmartViewStorage, SmartViewType, IMPORTANT_THRESHOLD,
 };
-pub use snooze_service::{SnoozeDuration, SnoozeError, SnoozeService, SnoozeStorage, SnoozedItem};
+pub use snooze_service::{
+    SnoozeDuration, SnoozeError, SnoozeService, SnoozeStorage, SnoozedItem, WakeCondition,
+};
+pub use spell_checker::{system_locale, Misspelling, SpellChecker};
 pub use stats_service::{
     AiStats, BusiestHour, DailyActivity, EmailStats, ProductivityStats, StatsError, StatsEvent,
     StatsReport, StatsService, StatsStorage, TopCorrespondent,
 };
-pub use sync_service::{SyncResult, SyncService, SyncSettings, SyncStatus};
+pub use sync_service::{
+    Change, EmailUpdates, PendingChange, PendingChangeType, SyncEvent, SyncProvider, SyncResult,
+    SyncService, SyncSettings, SyncState, SyncStatus, SyncStorage,
+};
 pub use telemetry_service::{
     AggregatedStats, DailyStats, EventPayload, EventType, StatsTimeRange, TelemetryError,
     TelemetryEvent, TelemetryService, TelemetryStorage,
 };
+pub use template_service::{TemplateContext, TemplateError, TemplateService, TemplateStorage};
 pub use thread_service::{
-    ThreadError, ThreadFilter, ThreadService, ThreadSort, ThreadStats, ThreadStorage,
+    ImportanceContext, ThreadError, ThreadFilter, ThreadResult, ThreadService, ThreadSort,
+    ThreadStats, ThreadStorage, FREQUENT_CONTACT_MIN,
 };
 pub use undo_service::{
     ActionBuilder, ActionResult, ActionState, ActionType, UndoService, UndoableAction,
@@ -10,6 +10,9 @@ use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::services::{EventSubscriber, ServiceEvent};
 
 /// Errors that can occur during notification operations.
 #[derive(Debug, Error)]
@@ -44,6 +47,21 @@ pub enum NotificationPriority {
     Critical,
 }
 
+impl NotificationPriority {
+    /// Picks the priority for mail of the given importance, from 0 to 1.
+    ///
+    /// Importance never makes a notification critical.
+    pub fn from_importance(importance: f32) -> Self {
+        if importance >= 0.6 {
+            NotificationPriority::High
+        } else if importance >= 0.25 {
+            NotificationPriority::Normal
+        } else {
+            NotificationPriority::Low
+        }
+    }
+}
+
 /// Type of notification for categorization.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum NotificationCategory {
@@ -92,6 +110,9 @@ pub struct NotificationRequest {
     pub auto_dismiss: Option<Duration>,
     /// Action URL or identifier.
     pub action: Option<String>,
+    /// Whether to send even if another notification of the same category
+    /// was just sent, instead of being rate limited.
+    pub immediate: bool,
 }
 
 impl NotificationRequest {
@@ -108,6 +129,7 @@ impl NotificationRequest {
             sound: false,
             auto_dismiss: Some(Duration::from_secs(5)),
             action: None,
+            immediate: false,
         }
     }
 
@@ -159,6 +181,12 @@ impl NotificationRequest {
         self
     }
 
+    /// Exempts the notification from rate limiting.
+    pub fn immediate(mut self) -> Self {
+        self.immediate = true;
+        self
+    }
+
     /// Creates a new email notification.
     pub fn new_email(sender: &str, subject: &str) -> Self {
         Self::new(
@@ -170,6 +198,14 @@ impl NotificationRequest {
         .with_sound()
     }
 
+    /// Creates a notification for new email in a watched thread, sent at
+    /// high priority and never rate limited.
+    pub fn watched_email(sender: &str, subject: &str) -> Self {
+        Self::new_email(sender, subject)
+            .priority(NotificationPriority::High)
+            .immediate()
+    }
+
     /// Creates an email sent notification.
     pub fn email_sent() -> Self {
         Self::new(NotificationCategory::EmailSent, "Email sent")
@@ -356,7 +392,7 @@ impl NotificationService {
 
         // Check rate limiting
         if let Some(last) = self.last_by_category.get(&request.category) {
-            if last.elapsed() < self.settings.rate_limit {
+            if !request.immediate && last.elapsed() < self.settings.rate_limit {
                 return Err(NotificationError::RateLimited);
             }
         }
@@ -443,6 +479,32 @@ impl Default for NotificationService {
     }
 }
 
+/// Notifies about unread mail received in the inbox, and about any unread
+/// mail in a watched thread at high priority.
+#[async_trait::async_trait]
+impl EventSubscriber for Mutex<NotificationService> {
+    async fn handle(&self, event: &ServiceEvent) {
+        let ServiceEvent::NewEmail { email, watched } = event else {
+            return;
+        };
+        if email.is_read || (!watched && !email.labels.iter().any(|l| l.0 == "INBOX")) {
+            return;
+        }
+
+        let sender = email.from.name.as_deref().unwrap_or(&email.from.email);
+        let subject = email.subject.as_deref().unwrap_or("(no subject)");
+        let request = if *watched {
+            NotificationRequest::watched_email(sender, subject)
+        } else {
+            NotificationRequest::new_email(sender, subject)
+        };
+        match self.lock().await.notify(request) {
+            Ok(()) | Err(NotificationError::RateLimited) => {}
+            Err(e) => tracing::warn!("Failed to notify about new email: {}", e),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -471,6 +533,100 @@ mod tests {
         assert!(notif.sound);
     }
 
+    #[test]
+    fn priority_from_importance() {
+        assert_eq!(
+            NotificationPriority::from_importance(0.9),
+            NotificationPriority::High
+        );
+        assert_eq!(
+            NotificationPriority::from_importance(0.4),
+            NotificationPriority::Normal
+        );
+        assert_eq!(
+            NotificationPriority::from_importance(0.0),
+            NotificationPriority::Low
+        );
+    }
+
+    #[tokio::test]
+    async fn unread_inbox_mail_notifies() {
+        use crate::domain::{test_email, Address, Email, LabelId};
+
+        let email = |is_read: bool| Email {
+            from: Address::with_name("alice@example.com", "Alice"),
+            subject: Some("Lunch?".to_string()),
+            body_text: None,
+            snippet: String::new(),
+            is_read,
+            labels: vec![LabelId::from("INBOX")],
+            ..test_email("email-1", "thread-1")
+        };
+        let mut settings = NotificationSettings::default();
+        settings.rate_limit = Duration::from_secs(0);
+        let service = Mutex::new(NotificationService::new(settings));
+
+        let event = |email| ServiceEvent::NewEmail {
+            email: Box::new(email),
+            watched: false,
+        };
+
+        service.handle(&event(email(true))).await;
+        assert_eq!(service.lock().await.active_count(), 0);
+
+        service.handle(&event(email(false))).await;
+        let service = service.lock().await;
+        let active = service.active_notifications();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].request.title, "New email from Alice");
+        assert_eq!(active[0].request.body.as_deref(), Some("Lunch?"));
+    }
+
+    #[tokio::test]
+    async fn watched_threads_notify_at_high_priority_without_rate_limiting() {
+        use crate::domain::{test_email, Address, Email, LabelId};
+
+        // Archived, so it only notifies when the thread is watched.
+        let email = |id: &str| Email {
+            from: Address::new("builds@example.com"),
+            subject: Some("Release 2.0".to_string()),
+            body_text: None,
+            snippet: String::new(),
+            labels: vec![LabelId::from("Builds")],
+            ..test_email(id, "thread-1")
+        };
+        let event = |id: &str, watched| ServiceEvent::NewEmail {
+            email: Box::new(email(id)),
+            watched,
+        };
+        // The default rate limit would drop a second notification.
+        let service = Mutex::new(NotificationService::with_defaults());
+
+        service.handle(&event("email-1", false)).await;
+        assert_eq!(service.lock().await.active_count(), 0);
+
+        service.handle(&event("email-2", true)).await;
+        service.handle(&event("email-3", true)).await;
+        {
+            let service = service.lock().await;
+            let active = service.active_notifications();
+            assert_eq!(active.len(), 2);
+            assert!(active
+                .iter()
+                .all(|n| n.request.priority == NotificationPriority::High));
+        }
+
+        // Normal mail in the inbox is still rate limited.
+        let mut inbox = email("email-4");
+        inbox.labels = vec![LabelId::from("INBOX")];
+        let normal = ServiceEvent::NewEmail {
+            email: Box::new(inbox),
+            watched: false,
+        };
+        service.handle(&normal).await;
+        assert_eq!(service.lock().await.active_count(), 2);
+    }
+
     #[test]
     fn service_notify_and_dismiss() {
         let mut settings = NotificationSettings::default();
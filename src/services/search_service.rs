@@ -4,8 +4,9 @@
 //! - FTS5-based full-text search for exact keyword matching
 //! - Semantic search via embeddings for conceptual similarity
 //! - Faceted filtering by folder, date range, sender, attachments
+//! - Server-side search for accounts whose mailbox is not fully synced
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -13,9 +14,16 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::domain::{AccountId, EmailId, ThreadId};
+use crate::domain::{AccountId, Email, EmailId, ThreadId};
+use crate::providers::email::SearchCriteria;
 use crate::services::{AiService, SearchResult};
 
+/// Number of related threads returned by [`SearchService::similar_to`].
+const SIMILAR_THREADS_LIMIT: usize = 10;
+
+/// Most near-duplicates returned by [`SearchService::find_near_duplicates`].
+const NEAR_DUPLICATES_LIMIT: usize = 20;
+
 /// Search query with filters and options.
 #[derive(Debug, Clone, Default)]
 pub struct SearchQuery {
@@ -108,6 +116,22 @@ impl SearchQuery {
         self.mode = mode;
         self
     }
+
+    /// Converts the query to criteria a provider can evaluate on the server.
+    ///
+    /// Recipient, attachment, starred and date-end filters have no portable
+    /// server equivalent and are left to local filtering.
+    pub fn to_criteria(&self) -> SearchCriteria {
+        let text = self.text.trim();
+        SearchCriteria {
+            from: self.from.clone(),
+            subject: None,
+            since: self.date_range.as_ref().map(|range| range.start),
+            unseen: self.is_unread == Some(true),
+            text: (!text.is_empty()).then(|| text.to_string()),
+            limit: Some((self.offset + self.limit) as u32),
+        }
+    }
 }
 
 /// Folder to filter search results.
@@ -184,6 +208,8 @@ pub enum SearchSource {
     Semantic,
     /// Appeared in both searches.
     Both,
+    /// From the provider's server-side search.
+    Server,
 }
 
 /// Search result page.
@@ -212,6 +238,26 @@ pub trait SearchStorage: Send + Sync {
 
     /// Rebuilds the FTS index for an account.
     async fn rebuild_fts_index(&self, account_id: &AccountId) -> Result<()>;
+
+    /// Loads the messages of a thread.
+    async fn get_thread_emails(&self, thread_id: &ThreadId) -> Result<Vec<Email>>;
+}
+
+/// Server-side search for accounts not yet fully synced locally.
+///
+/// Local FTS only sees what has been downloaded, so for a partially synced
+/// mailbox the service asks the provider instead (e.g. IMAP `UID SEARCH`).
+#[async_trait::async_trait]
+pub trait ServerSearch: Send + Sync {
+    /// Returns whether the account's mailbox is fully synced locally.
+    async fn is_fully_synced(&self, account_id: &AccountId) -> bool;
+
+    /// Searches the account's mailbox on the server.
+    async fn search(
+        &self,
+        account_id: &AccountId,
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<SearchHit>>;
 }
 
 /// Raw FTS hit from database.
@@ -259,6 +305,14 @@ pub struct SearchSettings {
     pub min_score: f32,
     /// Default result limit.
     pub default_limit: usize,
+    /// Cosine similarity above which two messages are near-duplicates
+    /// (0.0-1.0).
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+}
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.9
 }
 
 impl Default for SearchSettings {
@@ -269,6 +323,7 @@ impl Default for SearchSettings {
             semantic_weight: 0.4,
             min_score: 0.3,
             default_limit: 50,
+            near_duplicate_threshold: default_near_duplicate_threshold(),
         }
     }
 }
@@ -283,6 +338,8 @@ pub struct SearchService<S: SearchStorage> {
     storage: Arc<S>,
     /// AI service for semantic search.
     ai_service: Option<Arc<AiService>>,
+    /// Provider search for partially synced accounts.
+    server_search: Option<Arc<dyn ServerSearch>>,
     /// Search settings.
     settings: RwLock<SearchSettings>,
     /// Recent queries for suggestions.
@@ -295,6 +352,7 @@ impl<S: SearchStorage> SearchService<S> {
         Self {
             storage,
             ai_service: None,
+            server_search: None,
             settings: RwLock::new(SearchSettings::default()),
             recent_queries: RwLock::new(Vec::new()),
         }
@@ -306,6 +364,12 @@ impl<S: SearchStorage> SearchService<S> {
         self
     }
 
+    /// Sets the server search used for accounts that are not fully synced.
+    pub fn with_server_search(mut self, server_search: Arc<dyn ServerSearch>) -> Self {
+        self.server_search = Some(server_search);
+        self
+    }
+
     /// Updates search settings.
     pub async fn update_settings(&self, settings: SearchSettings) {
         let mut current = self.settings.write().await;
@@ -357,10 +421,20 @@ impl<S: SearchStorage> SearchService<S> {
         let used_semantic = !semantic_hits.is_empty();
 
         // Merge and rank results
-        let merged = self
+        let mut merged = self
             .merge_results(&fts_hits, &semantic_hits, &settings)
             .await?;
 
+        // Server results cover mail not downloaded yet; they rank first and
+        // replace local duplicates.
+        let server_hits = self.server_search(&query).await;
+        if !server_hits.is_empty() {
+            let server_ids: HashSet<EmailId> =
+                server_hits.iter().map(|hit| hit.email_id.clone()).collect();
+            merged.retain(|hit| !server_ids.contains(&hit.email_id));
+            merged.splice(0..0, server_hits);
+        }
+
         // Apply pagination
         let total = merged.len();
         let hits: Vec<SearchHit> = merged
@@ -380,6 +454,176 @@ impl<S: SearchStorage> SearchService<S> {
         })
     }
 
+    /// Finds threads semantically related to the given one.
+    ///
+    /// Returns at most one hit per related thread, its closest message,
+    /// ordered by similarity. The thread itself is never included. Returns
+    /// no hits when semantic search is unavailable.
+    pub async fn similar_to(&self, thread_id: &ThreadId) -> Result<SearchResults> {
+        let start = std::time::Instant::now();
+        let mut results = SearchResults {
+            hits: vec![],
+            total: 0,
+            query: String::new(),
+            took_ms: 0,
+            used_semantic: false,
+        };
+
+        let settings = self.settings.read().await;
+        let Some(ai_service) = self
+            .ai_service
+            .as_ref()
+            .filter(|_| settings.semantic_enabled)
+        else {
+            return Ok(results);
+        };
+
+        let emails = self.storage.get_thread_emails(thread_id).await?;
+        if emails.is_empty() {
+            return Ok(results);
+        }
+        results.query = emails
+            .iter()
+            .find_map(|e| e.subject.clone())
+            .unwrap_or_default();
+
+        // Fetch extra so collapsing messages into threads still fills the limit.
+        let similar = ai_service
+            .similar_emails(&emails, SIMILAR_THREADS_LIMIT * 3)
+            .await?;
+        let ids: Vec<EmailId> = similar.iter().map(|r| r.email_id.clone()).collect();
+        let metadata = self.storage.get_email_metadata(&ids).await?;
+
+        let mut seen_threads: HashSet<ThreadId> = HashSet::new();
+        seen_threads.insert(thread_id.clone());
+        for result in &similar {
+            let Some(meta) = metadata.iter().find(|m| m.email_id == result.email_id) else {
+                continue;
+            };
+            if !seen_threads.insert(meta.thread_id.clone()) {
+                continue;
+            }
+            results.hits.push(SearchHit {
+                email_id: meta.email_id.clone(),
+                thread_id: meta.thread_id.clone(),
+                subject: meta.subject.clone(),
+                snippet: meta.snippet.clone(),
+                from: meta.from.clone(),
+                date: meta.date,
+                is_read: meta.is_read,
+                score: result.relevance,
+                source: SearchSource::Semantic,
+                highlights: vec![],
+            });
+            if results.hits.len() == SIMILAR_THREADS_LIMIT {
+                break;
+            }
+        }
+
+        results.total = results.hits.len();
+        results.used_semantic = true;
+        results.took_ms = start.elapsed().as_millis() as u64;
+        Ok(results)
+    }
+
+    /// Finds emails whose body nearly repeats `email`'s, with a cosine
+    /// similarity of at least `threshold`, closest first.
+    ///
+    /// Returns nothing when semantic search is unavailable.
+    pub async fn find_near_duplicates(
+        &self,
+        email: &Email,
+        threshold: f32,
+    ) -> Result<Vec<EmailId>> {
+        let settings = self.settings.read().await;
+        let Some(ai_service) = self
+            .ai_service
+            .as_ref()
+            .filter(|_| settings.semantic_enabled)
+        else {
+            return Ok(vec![]);
+        };
+        ai_service
+            .near_duplicates(email, threshold, NEAR_DUPLICATES_LIMIT)
+            .await
+    }
+
+    /// Picks out threads that repeat another, so the inbox can collapse
+    /// them into one entry.
+    ///
+    /// Takes the newest message of each thread, in list order, and maps each
+    /// thread that repeats an earlier one to that thread. Messages with the
+    /// same Message-ID are always duplicates; others are near-duplicates at
+    /// the configured threshold. If the semantic lookup fails, e.g. because
+    /// AI is off, only Message-IDs are compared.
+    pub async fn duplicate_threads(&self, latest: &[Email]) -> HashMap<ThreadId, ThreadId> {
+        let threshold = self.settings.read().await.near_duplicate_threshold;
+        let mut duplicate_of: HashMap<ThreadId, ThreadId> = HashMap::new();
+        let mut by_message_id: HashMap<&str, &ThreadId> = HashMap::new();
+        let mut semantic = true;
+
+        for (i, email) in latest.iter().enumerate() {
+            if duplicate_of.contains_key(&email.thread_id) {
+                continue;
+            }
+            if let Some(&kept) = by_message_id.get(email.message_id.0.as_str()) {
+                duplicate_of.insert(email.thread_id.clone(), kept.clone());
+                continue;
+            }
+            by_message_id.insert(&email.message_id.0, &email.thread_id);
+            if !semantic {
+                continue;
+            }
+
+            let near: HashSet<EmailId> = match self.find_near_duplicates(email, threshold).await {
+                Ok(found) => found.into_iter().collect(),
+                Err(e) => {
+                    tracing::warn!(
+                        "Near-duplicate lookup failed, matching Message-IDs only: {}",
+                        e
+                    );
+                    semantic = false;
+                    continue;
+                }
+            };
+            for later in &latest[i + 1..] {
+                if near.contains(&later.id) && later.thread_id != email.thread_id {
+                    duplicate_of
+                        .entry(later.thread_id.clone())
+                        .or_insert_with(|| email.thread_id.clone());
+                }
+            }
+        }
+        duplicate_of
+    }
+
+    /// Searches on the server for queried accounts that are not fully synced.
+    ///
+    /// Failures are logged and yield no hits, leaving local results in place.
+    async fn server_search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let Some(server) = &self.server_search else {
+            return vec![];
+        };
+
+        let criteria = query.to_criteria();
+        let mut hits = Vec::new();
+        for account_id in &query.account_ids {
+            if server.is_fully_synced(account_id).await {
+                continue;
+            }
+            match server.search(account_id, &criteria).await {
+                Ok(found) => hits.extend(found.into_iter().map(|hit| SearchHit {
+                    source: SearchSource::Server,
+                    ..hit
+                })),
+                Err(e) => {
+                    tracing::warn!(%account_id, "Server search failed, using local results: {}", e)
+                }
+            }
+        }
+        hits
+    }
+
     /// Performs semantic search via AI service.
     async fn semantic_search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
         let ai_service = match &self.ai_service {
@@ -539,6 +783,394 @@ impl<S: SearchStorage> SearchService<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockStorage;
+
+    #[async_trait::async_trait]
+    impl SearchStorage for MockStorage {
+        async fn fts_search(&self, _query: &SearchQuery) -> Result<Vec<FtsHit>> {
+            Ok(vec![FtsHit {
+                email_id: EmailId::from("local-1"),
+                thread_id: ThreadId::from("thread-1"),
+                rank: 1.0,
+                snippet: "local".to_string(),
+            }])
+        }
+
+        async fn get_email_metadata(&self, ids: &[EmailId]) -> Result<Vec<EmailMetadata>> {
+            Ok(ids
+                .iter()
+                .map(|id| EmailMetadata {
+                    email_id: id.clone(),
+                    thread_id: ThreadId::from("thread-1"),
+                    subject: None,
+                    snippet: String::new(),
+                    from: "sender@example.com".to_string(),
+                    date: Utc::now(),
+                    is_read: true,
+                })
+                .collect())
+        }
+
+        async fn rebuild_fts_index(&self, _account_id: &AccountId) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_thread_emails(&self, _thread_id: &ThreadId) -> Result<Vec<Email>> {
+            Ok(vec![])
+        }
+    }
+
+    struct MockServerSearch {
+        synced: bool,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl ServerSearch for MockServerSearch {
+        async fn is_fully_synced(&self, _account_id: &AccountId) -> bool {
+            self.synced
+        }
+
+        async fn search(
+            &self,
+            _account_id: &AccountId,
+            criteria: &SearchCriteria,
+        ) -> Result<Vec<SearchHit>> {
+            if self.fail {
+                anyhow::bail!("SEARCH failed");
+            }
+            Ok(vec![SearchHit {
+                email_id: EmailId::from("remote-1"),
+                thread_id: ThreadId::from("thread-2"),
+                subject: criteria.text.clone(),
+                snippet: String::new(),
+                from: "sender@example.com".to_string(),
+                date: Utc::now(),
+                is_read: false,
+                score: 1.0,
+                source: SearchSource::FullText,
+                highlights: vec![],
+            }])
+        }
+    }
+
+    fn service_with(server: MockServerSearch) -> SearchService<MockStorage> {
+        SearchService::new(Arc::new(MockStorage)).with_server_search(Arc::new(server))
+    }
+
+    fn account_query() -> SearchQuery {
+        SearchQuery::new("invoice")
+            .with_accounts(vec![AccountId::from("account-1")])
+            .with_mode(SearchMode::FullText)
+    }
+
+    #[tokio::test]
+    async fn unsynced_account_prefers_server_results() {
+        let service = service_with(MockServerSearch {
+            synced: false,
+            fail: false,
+        });
+
+        let results = service.search(account_query()).await.unwrap();
+
+        assert_eq!(results.hits[0].email_id, EmailId::from("remote-1"));
+        assert_eq!(results.hits[0].source, SearchSource::Server);
+        assert_eq!(results.hits[0].subject.as_deref(), Some("invoice"));
+        assert_eq!(results.total, 2);
+    }
+
+    #[tokio::test]
+    async fn synced_account_uses_local_results() {
+        let service = service_with(MockServerSearch {
+            synced: true,
+            fail: false,
+        });
+
+        let results = service.search(account_query()).await.unwrap();
+
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].email_id, EmailId::from("local-1"));
+    }
+
+    #[tokio::test]
+    async fn server_search_failure_falls_back_to_local() {
+        let service = service_with(MockServerSearch {
+            synced: false,
+            fail: true,
+        });
+
+        let results = service.search(account_query()).await.unwrap();
+
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].source, SearchSource::FullText);
+    }
+
+    /// Storage holding a few threads, one email each.
+    struct ThreadStorage {
+        emails: Vec<Email>,
+    }
+
+    #[async_trait::async_trait]
+    impl SearchStorage for ThreadStorage {
+        async fn fts_search(&self, _query: &SearchQuery) -> Result<Vec<FtsHit>> {
+            Ok(vec![])
+        }
+
+        async fn get_email_metadata(&self, ids: &[EmailId]) -> Result<Vec<EmailMetadata>> {
+            Ok(self
+                .emails
+                .iter()
+                .filter(|e| ids.contains(&e.id))
+                .map(|e| EmailMetadata {
+                    email_id: e.id.clone(),
+                    thread_id: e.thread_id.clone(),
+                    subject: e.subject.clone(),
+                    snippet: e.snippet.clone(),
+                    from: e.from.email.clone(),
+                    date: e.date,
+                    is_read: e.is_read,
+                })
+                .collect())
+        }
+
+        async fn rebuild_fts_index(&self, _account_id: &AccountId) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_thread_emails(&self, thread_id: &ThreadId) -> Result<Vec<Email>> {
+            Ok(self
+                .emails
+                .iter()
+                .filter(|e| &e.thread_id == thread_id)
+                .cloned()
+                .collect())
+        }
+    }
+
+    /// Bag-of-words embeddings over a tiny vocabulary.
+    struct WordEmbeddings {
+        stored: HashMap<EmailId, Vec<f32>>,
+        embed_calls: AtomicUsize,
+    }
+
+    const VOCABULARY: &[&str] = &[
+        "budget",
+        "quarterly",
+        "forecast",
+        "lunch",
+        "friday",
+        "hiking",
+    ];
+
+    fn word_vector(text: &str) -> Vec<f32> {
+        let text = text.to_lowercase();
+        VOCABULARY
+            .iter()
+            .map(|word| text.matches(word).count() as f32)
+            .collect()
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm(a) * norm(b)).max(f32::EPSILON)
+    }
+
+    #[async_trait::async_trait]
+    impl crate::services::ai_service::EmbeddingEngine for WordEmbeddings {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            self.embed_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(word_vector(text))
+        }
+
+        async fn search(&self, query: &[f32], limit: usize) -> Result<Vec<(EmailId, f32)>> {
+            let mut scored: Vec<(EmailId, f32)> = self
+                .stored
+                .iter()
+                .map(|(id, v)| (id.clone(), cosine(query, v)))
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored.truncate(limit);
+            Ok(scored)
+        }
+
+        async fn index_email(&self, _email: &Email) -> Result<()> {
+            Ok(())
+        }
+
+        async fn stored_embedding(&self, email_id: &EmailId) -> Option<Vec<f32>> {
+            self.stored.get(email_id).cloned()
+        }
+    }
+
+    fn thread_email(id: &str, thread: &str, subject: &str, body: &str) -> Email {
+        Email {
+            to: vec![],
+            subject: Some(subject.to_string()),
+            body_text: Some(body.to_string()),
+            snippet: body.to_string(),
+            ..crate::domain::test_email(id, thread)
+        }
+    }
+
+    async fn related_service() -> (SearchService<ThreadStorage>, Arc<WordEmbeddings>) {
+        let emails = vec![
+            thread_email(
+                "e1",
+                "t1",
+                "Quarterly budget",
+                "Budget forecast for next quarter",
+            ),
+            thread_email(
+                "e2",
+                "t2",
+                "Budget forecast",
+                "Revised quarterly budget forecast",
+            ),
+            thread_email("e3", "t3", "Lunch friday", "Lunch on friday, then hiking"),
+        ];
+        semantic_service(emails).await
+    }
+
+    /// A service over `emails`, each indexed with its bag-of-words embedding.
+    async fn semantic_service(
+        emails: Vec<Email>,
+    ) -> (SearchService<ThreadStorage>, Arc<WordEmbeddings>) {
+        let stored = emails
+            .iter()
+            .map(|e| {
+                let text = format!("{} {}", e.subject.as_deref().unwrap_or(""), e.new_content());
+                (e.id.clone(), word_vector(&text))
+            })
+            .collect();
+        let engine = Arc::new(WordEmbeddings {
+            stored,
+            embed_calls: AtomicUsize::new(0),
+        });
+
+        let mut ai_settings = crate::services::AiSettings::default();
+        ai_settings.search_settings.min_relevance = 0.0;
+        let ai = Arc::new(AiService::new(ai_settings));
+        ai.set_embedding_engine(engine.clone()).await;
+
+        let service = SearchService::new(Arc::new(ThreadStorage { emails })).with_ai_service(ai);
+        (service, engine)
+    }
+
+    #[tokio::test]
+    async fn similar_to_ranks_closest_thread_first() {
+        let (service, _) = related_service().await;
+
+        let results = service.similar_to(&ThreadId::from("t1")).await.unwrap();
+
+        assert_eq!(results.hits[0].thread_id, ThreadId::from("t2"));
+        assert!(results
+            .hits
+            .iter()
+            .all(|h| h.thread_id != ThreadId::from("t1")));
+        assert!(results.hits[0].score > results.hits.last().unwrap().score);
+        assert_eq!(results.hits[0].source, SearchSource::Semantic);
+    }
+
+    #[tokio::test]
+    async fn similar_to_reuses_stored_embeddings() {
+        let (service, engine) = related_service().await;
+
+        service.similar_to(&ThreadId::from("t3")).await.unwrap();
+
+        assert_eq!(engine.embed_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn near_duplicates_are_found_above_the_threshold() {
+        let issue = "Quarterly budget forecast: the budget review moves to next week.";
+        let emails = vec![
+            thread_email("n1", "t1", "Newsletter", issue),
+            thread_email(
+                "n2",
+                "t2",
+                "Newsletter",
+                &format!("Forwarded on friday. {}", issue),
+            ),
+            thread_email("n3", "t3", "Newsletter", "Lunch on friday, then hiking?"),
+        ];
+        let (service, _) = semantic_service(emails.clone()).await;
+
+        let found = service.find_near_duplicates(&emails[0], 0.9).await.unwrap();
+        assert_eq!(found, vec![EmailId::from("n2")]);
+
+        // Nothing else comes close to the dissimilar message.
+        let found = service.find_near_duplicates(&emails[2], 0.9).await.unwrap();
+        assert!(found.is_empty());
+
+        let mut copy = thread_email("n4", "t4", "Newsletter", "Same issue, other list");
+        copy.message_id = emails[2].message_id.clone();
+        let mut latest = emails;
+        latest.push(copy);
+        let duplicates = service.duplicate_threads(&latest).await;
+        assert_eq!(
+            duplicates,
+            HashMap::from([
+                (ThreadId::from("t2"), ThreadId::from("t1")),
+                (ThreadId::from("t4"), ThreadId::from("t3")),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_threads_match_message_ids_when_ai_is_off() {
+        let ai = Arc::new(AiService::new(crate::services::AiSettings::default()));
+        let first = thread_email("n1", "t1", "Newsletter", "This week's issue");
+        let mut copy = thread_email("n2", "t2", "Newsletter", "This week's issue");
+        copy.message_id = first.message_id.clone();
+        let other = thread_email("n3", "t3", "Lunch", "Friday?");
+        let latest = vec![first, copy, other];
+        let service = SearchService::new(Arc::new(ThreadStorage {
+            emails: latest.clone(),
+        }))
+        .with_ai_service(ai);
+
+        assert_eq!(
+            service.duplicate_threads(&latest).await,
+            HashMap::from([(ThreadId::from("t2"), ThreadId::from("t1"))])
+        );
+    }
+
+    #[tokio::test]
+    async fn similar_to_without_ai_is_empty() {
+        let service = SearchService::new(Arc::new(MockStorage));
+
+        let results = service
+            .similar_to(&ThreadId::from("thread-1"))
+            .await
+            .unwrap();
+
+        assert!(results.hits.is_empty());
+        assert!(!results.used_semantic);
+    }
+
+    #[test]
+    fn query_to_criteria() {
+        let start = Utc::now();
+        let mut query = SearchQuery::new("  report ")
+            .with_from("alice@example.com")
+            .with_date_range(start, start)
+            .with_limit(20)
+            .with_offset(10);
+        query.is_unread = Some(true);
+
+        let criteria = query.to_criteria();
+        assert_eq!(criteria.text.as_deref(), Some("report"));
+        assert_eq!(criteria.from.as_deref(), Some("alice@example.com"));
+        assert_eq!(criteria.since, Some(start));
+        assert!(criteria.unseen);
+        assert_eq!(criteria.limit, Some(30));
+
+        assert_eq!(SearchQuery::new("").to_criteria().text, None);
+    }
 
     #[test]
     fn search_query_builder() {
@@ -5,14 +5,18 @@
 //! - Waiting For: sent emails awaiting replies
 //! - Newsletters: promotional/bulk mail
 //! - VIP: important contacts
+//! - Important: threads scoring high on importance
 //! - Follow Up: flagged for later action
+//!
+//! Inbox threads are also sorted into bundles (Primary, Promotions, Social,
+//! Updates, Forums), see [`BundleCategory::classify`].
 
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use std::collections::HashMap;
 use thiserror::Error;
 
-use crate::domain::{AccountId, ThreadId};
+use crate::domain::{AccountId, ThreadId, ThreadSummary};
 
 /// Types of smart views available.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -25,7 +29,9 @@ pub enum SmartViewType {
     Newsletters,
     /// VIP/important contacts.
     Vip,
-    /// Flagged for follow-up.
+    /// Threads whose importance is at least [`IMPORTANT_THRESHOLD`].
+    Important,
+    /// Flagged for follow-up, or sent with no reply before its reminder.
     FollowUp,
     /// Recently read but unactioned.
     RecentlyViewed,
@@ -41,6 +47,7 @@ impl SmartViewType {
             SmartViewType::WaitingFor,
             SmartViewType::Newsletters,
             SmartViewType::Vip,
+            SmartViewType::Important,
             SmartViewType::FollowUp,
             SmartViewType::RecentlyViewed,
             SmartViewType::Attachments,
@@ -48,6 +55,140 @@ impl SmartViewType {
     }
 }
 
+/// Importance, from 0 to 1, from which a thread belongs in the Important
+/// view.
+pub const IMPORTANT_THRESHOLD: f32 = 0.5;
+
+/// Sender address fragments of newsletters and other bulk mail.
+const NEWSLETTER_PATTERNS: &[&str] = &[
+    "noreply@",
+    "newsletter@",
+    "updates@",
+    "notifications@",
+    "digest@",
+    "weekly@",
+    "daily@",
+];
+
+/// Sender address fragments of transactional mail: notifications,
+/// receipts and alerts.
+const UPDATE_PATTERNS: &[&str] = &[
+    "notifications@",
+    "notification@",
+    "updates@",
+    "alerts@",
+    "receipts@",
+    "billing@",
+    "orders@",
+    "shipping@",
+];
+
+/// Sender domains of social networks.
+const SOCIAL_DOMAINS: &[&str] = &[
+    "facebookmail.com",
+    "linkedin.com",
+    "twitter.com",
+    "x.com",
+    "instagram.com",
+    "mastodon.social",
+    "reddit.com",
+];
+
+/// Sender address fragments of mailing lists and discussion groups.
+const FORUM_PATTERNS: &[&str] = &[
+    "googlegroups.com",
+    "groups.io",
+    "-list@",
+    "@lists.",
+    "discourse",
+];
+
+/// Inbox bundle a thread is grouped into, like Gmail's inbox tabs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BundleCategory {
+    /// Mail that fits no other bundle.
+    Primary,
+    /// Newsletters and marketing.
+    Promotions,
+    /// Social network notifications.
+    Social,
+    /// Notifications, receipts and alerts.
+    Updates,
+    /// Mailing lists and discussion groups.
+    Forums,
+}
+
+impl BundleCategory {
+    /// Returns all bundles, in display order.
+    pub fn all() -> &'static [BundleCategory] {
+        &[
+            BundleCategory::Primary,
+            BundleCategory::Promotions,
+            BundleCategory::Social,
+            BundleCategory::Updates,
+            BundleCategory::Forums,
+        ]
+    }
+
+    /// Returns the display name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BundleCategory::Primary => "Primary",
+            BundleCategory::Promotions => "Promotions",
+            BundleCategory::Social => "Social",
+            BundleCategory::Updates => "Updates",
+            BundleCategory::Forums => "Forums",
+        }
+    }
+
+    /// Returns the Gmail label of the matching inbox tab.
+    fn gmail_label(&self) -> Option<&'static str> {
+        match self {
+            BundleCategory::Primary => None,
+            BundleCategory::Promotions => Some("CATEGORY_PROMOTIONS"),
+            BundleCategory::Social => Some("CATEGORY_SOCIAL"),
+            BundleCategory::Updates => Some("CATEGORY_UPDATES"),
+            BundleCategory::Forums => Some("CATEGORY_FORUMS"),
+        }
+    }
+
+    /// Picks the bundle for a thread.
+    ///
+    /// A Gmail category label decides when present. Otherwise the sender
+    /// is matched against social networks, mailing lists and transactional
+    /// senders, in that order, and anything else the Newsletters view would
+    /// take goes to Promotions.
+    pub fn classify(input: &ClassificationInput) -> Self {
+        let from_gmail = Self::all().iter().copied().find(|bundle| {
+            bundle
+                .gmail_label()
+                .is_some_and(|label| input.labels.iter().any(|l| l == label))
+        });
+        if let Some(bundle) = from_gmail {
+            return bundle;
+        }
+
+        let email = input.sender_email.to_lowercase();
+        let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| email.contains(p));
+
+        if SOCIAL_DOMAINS
+            .iter()
+            .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+        {
+            BundleCategory::Social
+        } else if matches(FORUM_PATTERNS) {
+            BundleCategory::Forums
+        } else if matches(UPDATE_PATTERNS) {
+            BundleCategory::Updates
+        } else if matches(NEWSLETTER_PATTERNS) {
+            BundleCategory::Promotions
+        } else {
+            BundleCategory::Primary
+        }
+    }
+}
+
 /// Errors that can occur during smart view operations.
 #[derive(Debug, Error)]
 pub enum SmartViewError {
@@ -146,6 +287,26 @@ pub struct ClassificationInput {
     pub labels: Vec<String>,
 }
 
+impl ClassificationInput {
+    /// Builds the input from a thread summary. Summaries don't say who sent
+    /// last or whether there are attachments, so both are taken as false.
+    pub fn from_summary(summary: &ThreadSummary) -> Self {
+        Self {
+            thread_id: summary.id.clone(),
+            subject: summary.subject.clone(),
+            snippet: summary.snippet.clone(),
+            sender_email: summary.from.email.clone(),
+            sender_name: summary.from.name.clone(),
+            user_was_last_sender: false,
+            last_message_date: summary.last_message_date,
+            is_read: summary.unread_count == 0,
+            has_attachments: false,
+            message_count: summary.message_count,
+            labels: summary.labels.iter().map(|label| label.0.clone()).collect(),
+        }
+    }
+}
+
 /// Storage trait for smart view classifications.
 #[async_trait]
 pub trait SmartViewStorage: Send + Sync {
@@ -249,15 +410,7 @@ impl<S: SmartViewStorage> SmartViewService<S> {
             storage,
             account_id,
             vip_contacts: Vec::new(),
-            newsletter_patterns: vec![
-                "noreply@".to_string(),
-                "newsletter@".to_string(),
-                "updates@".to_string(),
-                "notifications@".to_string(),
-                "digest@".to_string(),
-                "weekly@".to_string(),
-                "daily@".to_string(),
-            ],
+            newsletter_patterns: NEWSLETTER_PATTERNS.iter().map(|p| p.to_string()).collect(),
         }
     }
 
@@ -361,6 +514,23 @@ impl<S: SmartViewStorage> SmartViewService<S> {
         classifications
     }
 
+    /// Classifies a thread into the Important view if its importance (see
+    /// `ThreadService::importance`) reaches [`IMPORTANT_THRESHOLD`].
+    pub fn classify_importance(
+        &self,
+        thread_id: &ThreadId,
+        importance: f32,
+    ) -> Option<Classification> {
+        (importance >= IMPORTANT_THRESHOLD).then(|| {
+            Classification::ai_classified(
+                thread_id.clone(),
+                SmartViewType::Important,
+                importance,
+                format!("Importance score {:.2}", importance),
+            )
+        })
+    }
+
     /// Gets threads for a smart view.
     pub async fn get_threads(
         &self,
@@ -617,4 +787,17 @@ mod tests {
             .iter()
             .any(|c| c.view_type == SmartViewType::Vip));
     }
+
+    #[test]
+    fn important_threads_reach_the_threshold() {
+        let service = SmartViewService::new(MockStorage::new(), AccountId::from("test"));
+        let thread_id = ThreadId::from("thread-1");
+
+        let classification = service
+            .classify_importance(&thread_id, IMPORTANT_THRESHOLD)
+            .unwrap();
+        assert_eq!(classification.view_type, SmartViewType::Important);
+        assert_eq!(classification.confidence, IMPORTANT_THRESHOLD);
+        assert!(service.classify_importance(&thread_id, 0.1).is_none());
+    }
 }
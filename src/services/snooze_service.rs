@@ -2,12 +2,14 @@
 //!
 //! Allows users to snooze emails until a specific time, hiding them from
 //! the inbox until the snooze period expires. Snoozed emails reappear
-//! automatically at the scheduled time.
+//! automatically at the scheduled time, or as soon as someone replies when
+//! snoozed until a reply.
 
 use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, Utc};
 use thiserror::Error;
 
-use crate::domain::{AccountId, ThreadId};
+use crate::domain::{AccountId, Email, ThreadId};
+use crate::services::sync_service::Change;
 
 /// Errors that can occur during snooze operations.
 #[derive(Debug, Error)]
@@ -28,6 +30,13 @@ pub enum SnoozeError {
 /// Result type for snooze operations.
 pub type Result<T> = std::result::Result<T, SnoozeError>;
 
+/// Event that wakes a snoozed thread before its wake time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeCondition {
+    /// Someone else sends a message in the thread.
+    Reply,
+}
+
 /// A snoozed email entry.
 #[derive(Debug, Clone)]
 pub struct SnoozedItem {
@@ -37,8 +46,11 @@ pub struct SnoozedItem {
     pub account_id: AccountId,
     /// When the snooze was created.
     pub snoozed_at: DateTime<Utc>,
-    /// When the email should reappear.
-    pub wake_at: DateTime<Utc>,
+    /// When the email should reappear, or `None` to wait only for the
+    /// condition.
+    pub wake_at: Option<DateTime<Utc>>,
+    /// Event that wakes the email early, whichever comes first.
+    pub condition: Option<WakeCondition>,
     /// Original folder before snoozing.
     pub original_folder: Option<String>,
 }
@@ -48,7 +60,7 @@ impl SnoozedItem {
     pub fn new(
         thread_id: ThreadId,
         account_id: AccountId,
-        wake_at: DateTime<Utc>,
+        wake_at: Option<DateTime<Utc>>,
         original_folder: Option<String>,
     ) -> Self {
         Self {
@@ -56,24 +68,46 @@ impl SnoozedItem {
             account_id,
             snoozed_at: Utc::now(),
             wake_at,
+            condition: None,
             original_folder,
         }
     }
 
+    /// Also wakes the item when `condition` occurs.
+    pub fn with_condition(mut self, condition: WakeCondition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
     /// Returns whether this item should wake up now.
     pub fn should_wake(&self) -> bool {
-        Utc::now() >= self.wake_at
+        self.wake_at.is_some_and(|wake_at| Utc::now() >= wake_at)
     }
 
-    /// Returns the duration until wake time.
-    pub fn time_until_wake(&self) -> Duration {
-        let now = Utc::now();
-        if now >= self.wake_at {
-            Duration::zero()
-        } else {
-            self.wake_at - now
+    /// Returns whether a newly arrived email wakes this item.
+    ///
+    /// Only messages from others count as a reply, not the user's own.
+    pub fn wakes_on(&self, email: &Email) -> bool {
+        match self.condition {
+            Some(WakeCondition::Reply) => {
+                email.thread_id == self.thread_id && !email.labels.iter().any(|l| l.0 == "SENT")
+            }
+            None => false,
         }
     }
+
+    /// Returns the duration until wake time, or `None` if the item only
+    /// wakes on its condition.
+    pub fn time_until_wake(&self) -> Option<Duration> {
+        let now = Utc::now();
+        self.wake_at.map(|wake_at| {
+            if now >= wake_at {
+                Duration::zero()
+            } else {
+                wake_at - now
+            }
+        })
+    }
 }
 
 /// Common snooze durations.
@@ -89,11 +123,14 @@ pub enum SnoozeDuration {
     NextWeek,
     /// Custom time.
     Custom(DateTime<Utc>),
+    /// No set time; wakes when someone replies.
+    UntilReply,
 }
 
 impl SnoozeDuration {
-    /// Calculates the wake time for this duration.
-    pub fn wake_time(&self) -> DateTime<Utc> {
+    /// Calculates the wake time for this duration, or `None` for
+    /// [`SnoozeDuration::UntilReply`].
+    pub fn wake_time(&self) -> Option<DateTime<Utc>> {
         let now = Local::now();
         let local_wake = match self {
             SnoozeDuration::LaterToday => {
@@ -142,10 +179,19 @@ impl SnoozeDuration {
                 let morning = monday.and_time(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
                 morning.and_local_timezone(now.timezone()).unwrap()
             }
-            SnoozeDuration::Custom(dt) => return *dt,
+            SnoozeDuration::Custom(dt) => return Some(*dt),
+            SnoozeDuration::UntilReply => return None,
         };
 
-        local_wake.with_timezone(&Utc)
+        Some(local_wake.with_timezone(&Utc))
+    }
+
+    /// Returns the condition that wakes a thread snoozed for this duration.
+    fn condition(&self) -> Option<WakeCondition> {
+        match self {
+            SnoozeDuration::UntilReply => Some(WakeCondition::Reply),
+            _ => None,
+        }
     }
 
     /// Returns a human-readable description.
@@ -159,6 +205,7 @@ impl SnoozeDuration {
                 let local = dt.with_timezone(&Local);
                 local.format("%a, %b %d at %I:%M %p").to_string()
             }
+            SnoozeDuration::UntilReply => "Someone replies".to_string(),
         }
     }
 }
@@ -203,15 +250,26 @@ impl<S: SnoozeStorage> SnoozeService<S> {
         duration: SnoozeDuration,
         original_folder: Option<String>,
     ) -> Result<SnoozedItem> {
-        let wake_at = duration.wake_time();
-
-        if wake_at <= Utc::now() {
-            return Err(SnoozeError::InvalidTime(
-                "Wake time must be in the future".to_string(),
-            ));
-        }
+        let wake_at = future_wake_time(duration)?;
+        let mut item = SnoozedItem::new(thread_id, account_id, wake_at, original_folder);
+        item.condition = duration.condition();
+        self.storage.store_snooze(&item)?;
+        Ok(item)
+    }
 
-        let item = SnoozedItem::new(thread_id, account_id, wake_at, original_folder);
+    /// Snoozes a thread until the specified time or until `condition`
+    /// occurs, whichever comes first.
+    pub fn snooze_or_until(
+        &self,
+        thread_id: ThreadId,
+        account_id: AccountId,
+        duration: SnoozeDuration,
+        condition: WakeCondition,
+        original_folder: Option<String>,
+    ) -> Result<SnoozedItem> {
+        let wake_at = future_wake_time(duration)?;
+        let item = SnoozedItem::new(thread_id, account_id, wake_at, original_folder)
+            .with_condition(condition);
         self.storage.store_snooze(&item)?;
         Ok(item)
     }
@@ -270,6 +328,27 @@ impl<S: SnoozeStorage> SnoozeService<S> {
         Ok(items)
     }
 
+    /// Wakes items whose condition is met by synced changes, returning them
+    /// and removing them from storage.
+    ///
+    /// Should run as soon as changes arrive, so a reply wakes its thread
+    /// without waiting for the next [`process_wakeups`](Self::process_wakeups).
+    pub fn process_changes(&self, changes: &[Change]) -> Result<Vec<SnoozedItem>> {
+        let mut woken = Vec::new();
+        for change in changes {
+            let Change::NewEmail(email) = change else {
+                continue;
+            };
+            if let Some(item) = self.storage.get_snooze(&email.thread_id)? {
+                if item.wakes_on(email) {
+                    self.storage.remove_snooze(&item.thread_id)?;
+                    woken.push(item);
+                }
+            }
+        }
+        Ok(woken)
+    }
+
     /// Gets count of snoozed items.
     pub fn snoozed_count(&self) -> Result<usize> {
         Ok(self.storage.get_all_snoozed()?.len())
@@ -286,15 +365,10 @@ impl<S: SnoozeStorage> SnoozeService<S> {
             .get_snooze(thread_id)?
             .ok_or_else(|| SnoozeError::NotSnoozed(thread_id.to_string()))?;
 
-        let wake_at = new_duration.wake_time();
-        if wake_at <= Utc::now() {
-            return Err(SnoozeError::InvalidTime(
-                "Wake time must be in the future".to_string(),
-            ));
-        }
-
+        let wake_at = future_wake_time(new_duration)?;
         let updated = SnoozedItem {
             wake_at,
+            condition: new_duration.condition().or(existing.condition),
             ..existing
         };
         self.storage.store_snooze(&updated)?;
@@ -302,6 +376,17 @@ impl<S: SnoozeStorage> SnoozeService<S> {
     }
 }
 
+/// Returns the wake time for a duration, which must be in the future.
+fn future_wake_time(duration: SnoozeDuration) -> Result<Option<DateTime<Utc>>> {
+    let wake_at = duration.wake_time();
+    if wake_at.is_some_and(|wake_at| wake_at <= Utc::now()) {
+        return Err(SnoozeError::InvalidTime(
+            "Wake time must be in the future".to_string(),
+        ));
+    }
+    Ok(wake_at)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,7 +447,7 @@ mod tests {
                 .read()
                 .unwrap()
                 .values()
-                .filter(|i| i.wake_at <= now)
+                .filter(|i| i.wake_at.is_some_and(|wake_at| wake_at <= now))
                 .cloned()
                 .collect())
         }
@@ -399,7 +484,7 @@ mod tests {
 
         let now = Utc::now();
         for duration in durations {
-            let wake = duration.wake_time();
+            let wake = duration.wake_time().unwrap();
             assert!(
                 wake > now,
                 "{:?} wake time should be in the future",
@@ -465,7 +550,8 @@ mod tests {
                 thread_id: make_thread_id("thread-past"),
                 account_id: make_account_id("account-1"),
                 snoozed_at: Utc::now() - Duration::hours(1),
-                wake_at: past_wake,
+                wake_at: Some(past_wake),
+                condition: None,
                 original_folder: None,
             };
             service.storage.store_snooze(&item).unwrap();
@@ -512,7 +598,7 @@ mod tests {
             .update_snooze(&thread_id, SnoozeDuration::Tomorrow)
             .unwrap();
 
-        assert!(updated.wake_at > wake_at);
+        assert!(updated.wake_at.unwrap() > wake_at);
     }
 
     #[test]
@@ -557,7 +643,8 @@ mod tests {
             thread_id: make_thread_id("t1"),
             account_id: make_account_id("a1"),
             snoozed_at: Utc::now() - Duration::hours(2),
-            wake_at: Utc::now() - Duration::hours(1),
+            wake_at: Some(Utc::now() - Duration::hours(1)),
+            condition: None,
             original_folder: None,
         };
 
@@ -565,7 +652,8 @@ mod tests {
             thread_id: make_thread_id("t2"),
             account_id: make_account_id("a1"),
             snoozed_at: Utc::now(),
-            wake_at: Utc::now() + Duration::hours(1),
+            wake_at: Some(Utc::now() + Duration::hours(1)),
+            condition: None,
             original_folder: None,
         };
 
@@ -579,12 +667,121 @@ mod tests {
             thread_id: make_thread_id("t1"),
             account_id: make_account_id("a1"),
             snoozed_at: Utc::now(),
-            wake_at: Utc::now() + Duration::hours(2),
+            wake_at: Some(Utc::now() + Duration::hours(2)),
+            condition: None,
             original_folder: None,
         };
 
-        let time_left = item.time_until_wake();
+        let time_left = item.time_until_wake().unwrap();
         assert!(time_left > Duration::hours(1));
         assert!(time_left <= Duration::hours(2));
     }
+
+    fn reply(thread_id: &str, labels: &[&str]) -> Change {
+        use crate::domain::{test_email, Address, LabelId};
+
+        Change::NewEmail(Box::new(Email {
+            from: Address::new("alice@example.com"),
+            subject: Some("Re: Plans".to_string()),
+            body_text: None,
+            snippet: String::new(),
+            labels: labels.iter().map(|l| LabelId::from(*l)).collect(),
+            ..test_email("reply", thread_id)
+        }))
+    }
+
+    #[test]
+    fn time_only_snooze_ignores_replies() {
+        let service = SnoozeService::new(MockStorage::new());
+        let thread_id = make_thread_id("thread-1");
+        let item = service
+            .snooze(
+                thread_id.clone(),
+                make_account_id("account-1"),
+                SnoozeDuration::Tomorrow,
+                None,
+            )
+            .unwrap();
+        assert_eq!(item.condition, None);
+
+        let woken = service
+            .process_changes(&[reply("thread-1", &["INBOX"])])
+            .unwrap();
+        assert!(woken.is_empty());
+        assert!(service.is_snoozed(&thread_id).unwrap());
+    }
+
+    #[test]
+    fn reply_only_snooze_wakes_on_reply() {
+        let service = SnoozeService::new(MockStorage::new());
+        let thread_id = make_thread_id("thread-1");
+        let item = service
+            .snooze(
+                thread_id.clone(),
+                make_account_id("account-1"),
+                SnoozeDuration::UntilReply,
+                None,
+            )
+            .unwrap();
+        assert_eq!(item.wake_at, None);
+        assert_eq!(item.condition, Some(WakeCondition::Reply));
+        assert!(service.process_wakeups().unwrap().is_empty());
+
+        // Neither the user's own message nor mail in another thread wakes it.
+        let woken = service
+            .process_changes(&[reply("thread-1", &["SENT"]), reply("thread-2", &["INBOX"])])
+            .unwrap();
+        assert!(woken.is_empty());
+
+        let woken = service
+            .process_changes(&[reply("thread-1", &["INBOX"])])
+            .unwrap();
+        assert_eq!(woken.len(), 1);
+        assert_eq!(woken[0].thread_id, thread_id);
+        assert!(!service.is_snoozed(&thread_id).unwrap());
+    }
+
+    #[test]
+    fn time_or_reply_snooze_wakes_on_whichever_comes_first() {
+        let service = SnoozeService::new(MockStorage::new());
+        let account_id = make_account_id("account-1");
+
+        // A reply arrives before the wake time.
+        service
+            .snooze_or_until(
+                make_thread_id("replied"),
+                account_id.clone(),
+                SnoozeDuration::Tomorrow,
+                WakeCondition::Reply,
+                None,
+            )
+            .unwrap();
+        let woken = service
+            .process_changes(&[reply("replied", &["INBOX"])])
+            .unwrap();
+        assert_eq!(woken.len(), 1);
+
+        // The wake time passes with no reply.
+        let item = SnoozedItem {
+            snoozed_at: Utc::now() - Duration::hours(2),
+            ..SnoozedItem::new(
+                make_thread_id("quiet"),
+                account_id,
+                Some(Utc::now() - Duration::seconds(1)),
+                None,
+            )
+            .with_condition(WakeCondition::Reply)
+        };
+        service.storage.store_snooze(&item).unwrap();
+        let woken = service.process_wakeups().unwrap();
+        assert_eq!(woken.len(), 1);
+        assert_eq!(woken[0].thread_id, make_thread_id("quiet"));
+        assert_eq!(service.snoozed_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn until_reply_has_no_wake_time() {
+        assert_eq!(SnoozeDuration::UntilReply.wake_time(), None);
+        assert_eq!(SnoozeDuration::UntilReply.description(), "Someone replies");
+    }
 }
@@ -0,0 +1,363 @@
+//! Spell checking for the composer.
+//!
+//! Words are checked against a bundled dictionary for the user's language.
+//! Dictionaries list one word per line, most frequent first, so suggestions
+//! at the same edit distance favour common words. Regular inflections
+//! ("meetings", "scheduled") are accepted when their stem is listed.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// Dictionaries bundled with the app, by ISO 639-1 code.
+const DICTIONARIES: &[(&str, &str)] =
+    &[("en", include_str!("../../resources/dictionaries/en.txt"))];
+
+/// Largest edit distance between a misspelling and its suggestions.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Most suggestions offered for a misspelling.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Suffixes stripped to find the stem of an inflected word, with what
+/// replaces them on the stem.
+const SUFFIXES: &[(&str, &[&str])] = &[
+    ("ies", &["y"]),
+    ("ied", &["y"]),
+    ("ily", &["y"]),
+    ("es", &[""]),
+    ("s", &[""]),
+    ("ed", &["", "e"]),
+    ("ing", &["", "e"]),
+    ("ly", &[""]),
+    ("er", &["", "e"]),
+    ("est", &["", "e"]),
+    ("ment", &[""]),
+    ("ness", &[""]),
+];
+
+/// A word not found in the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    /// Byte range of the word in the checked text.
+    pub range: Range<usize>,
+    /// The word as written.
+    pub word: String,
+}
+
+/// Checks text against a word list and suggests corrections.
+#[derive(Debug, Clone)]
+pub struct SpellChecker {
+    language: String,
+    /// Each word's position in the word list, lower is more frequent.
+    ranks: HashMap<String, usize>,
+    personal: HashSet<String>,
+}
+
+impl SpellChecker {
+    /// Creates a checker for `language` from a word list with one word per
+    /// line, most frequent first. Hunspell-style affix flags after a `/`
+    /// are ignored.
+    pub fn new(language: impl Into<String>, words: &str) -> Self {
+        let mut ranks = HashMap::new();
+        for word in words.lines() {
+            let word = word.split('/').next().unwrap_or_default().trim();
+            if !word.is_empty() {
+                let rank = ranks.len();
+                ranks.entry(normalize(word)).or_insert(rank);
+            }
+        }
+        Self {
+            language: language.into(),
+            ranks,
+            personal: HashSet::new(),
+        }
+    }
+
+    /// Creates a checker with the bundled dictionary for a locale such as
+    /// `en-US` or `en_GB`, or `None` when no dictionary covers it.
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        let language = locale
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        DICTIONARIES
+            .iter()
+            .find(|(code, _)| *code == language)
+            .map(|(code, words)| Self::new(*code, words))
+    }
+
+    /// Returns the checker's language code.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Accepts a word the dictionary doesn't know, such as a name.
+    pub fn add_word(&mut self, word: &str) {
+        self.personal.insert(normalize(word));
+    }
+
+    /// Returns the misspelled words in `text`, in order.
+    ///
+    /// Acronyms, mixed-case names, and anything in a URL, email address, or
+    /// token containing digits are not checked.
+    pub fn check(&self, text: &str) -> Vec<Misspelling> {
+        words(text)
+            .filter(|range| !self.is_correct(&text[range.clone()]))
+            .map(|range| Misspelling {
+                word: text[range.clone()].to_string(),
+                range,
+            })
+            .collect()
+    }
+
+    /// Suggests corrections for a word, closest and most common first.
+    ///
+    /// Suggestions follow the capitalization of `word`.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let target: Vec<char> = normalize(word).chars().collect();
+        let mut candidates: Vec<(usize, usize, &str)> = self
+            .ranks
+            .iter()
+            .filter(|(candidate, _)| {
+                candidate.chars().count().abs_diff(target.len()) <= MAX_SUGGESTION_DISTANCE
+            })
+            .filter_map(|(candidate, rank)| {
+                let distance = edit_distance(&target, &candidate.chars().collect::<Vec<_>>());
+                (distance > 0 && distance <= MAX_SUGGESTION_DISTANCE).then_some((
+                    distance,
+                    *rank,
+                    candidate.as_str(),
+                ))
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, _, candidate)| match_case(candidate, word))
+            .collect()
+    }
+
+    fn is_correct(&self, word: &str) -> bool {
+        // Acronyms and names like "iPhone" are left alone.
+        if word.chars().count() < 2 || word.chars().skip(1).any(char::is_uppercase) {
+            return true;
+        }
+        let word = normalize(word);
+        let word = word
+            .strip_suffix("'s")
+            .or_else(|| word.strip_suffix('\''))
+            .unwrap_or(&word);
+        self.is_known(word) || self.stems(word).iter().any(|stem| self.is_known(stem))
+    }
+
+    fn is_known(&self, word: &str) -> bool {
+        self.ranks.contains_key(word) || self.personal.contains(word)
+    }
+
+    /// Possible stems of an inflected word, e.g. "stop" for "stopped".
+    fn stems(&self, word: &str) -> Vec<String> {
+        let mut stems = vec![];
+        for (suffix, replacements) in SUFFIXES {
+            let Some(stem) = word.strip_suffix(suffix) else {
+                continue;
+            };
+            if stem.chars().count() < 2 {
+                continue;
+            }
+            for replacement in *replacements {
+                stems.push(format!("{}{}", stem, replacement));
+            }
+            // Doubled final consonants, as in "planned".
+            let mut chars = stem.chars().rev();
+            if let (Some(last), Some(previous)) = (chars.next(), chars.next()) {
+                if last == previous && !"aeiou".contains(last) {
+                    stems.push(stem[..stem.len() - last.len_utf8()].to_string());
+                }
+            }
+        }
+        stems
+    }
+}
+
+/// Returns the system locale from the environment, e.g. `en-US`.
+pub fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| {
+            let locale = value.split(['.', '@']).next().unwrap_or_default();
+            locale.replace('_', "-")
+        })
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+/// Byte ranges of the words in `text` worth checking.
+fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    tokens(text)
+        .filter(move |token| {
+            let token = &text[token.clone()];
+            !(token.contains('@')
+                || token.contains("://")
+                || token.starts_with("www.")
+                || token.chars().any(|c| c.is_ascii_digit()))
+        })
+        .flat_map(move |token| {
+            let start = token.start;
+            split_words(&text[token]).map(move |word| start + word.start..start + word.end)
+        })
+}
+
+/// Byte ranges of whitespace-separated tokens.
+fn tokens(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(i, c)| match (c.is_whitespace(), start) {
+            (false, None) => {
+                start = Some(i);
+                None
+            }
+            (true, Some(from)) => {
+                start = None;
+                Some(from..i)
+            }
+            _ => None,
+        })
+}
+
+/// Byte ranges of the runs of letters in a token, keeping apostrophes
+/// inside words like "don't".
+fn split_words(token: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let is_apostrophe = |c: char| c == '\'' || c == '\u{2019}';
+    let mut words = vec![];
+    let mut start = None;
+    for (i, c) in token
+        .char_indices()
+        .chain(std::iter::once((token.len(), ' ')))
+    {
+        let in_word = c.is_alphabetic() || (start.is_some() && is_apostrophe(c));
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                start = None;
+                let word = token[from..i].trim_end_matches(is_apostrophe);
+                words.push(from..from + word.len());
+            }
+            _ => {}
+        }
+    }
+    words.into_iter()
+}
+
+fn normalize(word: &str) -> String {
+    word.to_lowercase().replace('\u{2019}', "'")
+}
+
+/// Gives `suggestion` the capitalization of `original`.
+fn match_case(suggestion: &str, original: &str) -> String {
+    let mut chars = original.chars();
+    let first_upper = chars.next().is_some_and(char::is_uppercase);
+    if first_upper && original.chars().count() > 1 && chars.all(char::is_uppercase) {
+        suggestion.to_uppercase()
+    } else if first_upper {
+        let mut chars = suggestion.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        suggestion.to_string()
+    }
+}
+
+/// Edit distance counting insertions, deletions, substitutions and
+/// transpositions of adjacent letters.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous2: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(previous2[j - 2] + 1);
+            }
+        }
+        previous2 = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english() -> SpellChecker {
+        SpellChecker::for_locale("en-US").unwrap()
+    }
+
+    #[test]
+    fn flags_misspelled_words_with_their_ranges() {
+        let text = "Thanks, I will recieve the documents tomorow.";
+        let misspellings = english().check(text);
+
+        let words: Vec<&str> = misspellings.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(words, ["recieve", "tomorow"]);
+        for misspelling in &misspellings {
+            assert_eq!(&text[misspelling.range.clone()], misspelling.word);
+        }
+    }
+
+    #[test]
+    fn accepts_inflections_contractions_and_non_words() {
+        let text = "We're planning meetings; she scheduled calls and didn't \
+            reply. See https://example.com/xyzzy or mail bob@exmaple.com \
+            about ASAP items for iOS and order #A123B.";
+        assert!(english().check(text).is_empty());
+    }
+
+    #[test]
+    fn accepts_everyday_vocabulary() {
+        let text = "The quarterly invoice reconciliation was postponed pending \
+            the auditor's approval of the itinerary.";
+        assert!(english().check(text).is_empty());
+    }
+
+    #[test]
+    fn suggests_closest_and_most_common_words_first() {
+        let checker = english();
+
+        assert_eq!(checker.suggest("recieve").first().unwrap(), "receive");
+        let suggestions = checker.suggest("teh");
+        assert_eq!(suggestions[0], "the");
+        assert!(suggestions.contains(&"ten".to_string()));
+        assert!(suggestions.len() <= MAX_SUGGESTIONS);
+        assert_eq!(checker.suggest("Tomorow")[0], "Tomorrow");
+    }
+
+    #[test]
+    fn personal_words_are_accepted() {
+        let mut checker = english();
+        assert_eq!(checker.check("Blorf Zorblax").len(), 2);
+
+        checker.add_word("zorblax");
+        checker.add_word("blorf");
+        assert!(checker.check("Blorf Zorblax").is_empty());
+    }
+
+    #[test]
+    fn dictionaries_are_chosen_by_language() {
+        assert_eq!(english().language(), "en");
+        assert_eq!(
+            SpellChecker::for_locale("en_GB.UTF-8").unwrap().language(),
+            "en"
+        );
+        assert!(SpellChecker::for_locale("xx-YY").is_none());
+    }
+}
@@ -108,6 +108,16 @@ impl AiStats {
     pub fn estimate_cost(&mut self, cost_per_1k_tokens: f32) {
         self.estimated_cost_usd = (self.tokens_used as f32 / 1000.0) * cost_per_1k_tokens;
     }
+
+    /// Describes token usage and cost, e.g. `1.2M tokens (~$3.40)`.
+    pub fn usage_summary(&self) -> String {
+        let tokens = match self.tokens_used {
+            n if n >= 1_000_000 => format!("{:.1}M", n as f64 / 1_000_000.0),
+            n if n >= 1_000 => format!("{:.1}K", n as f64 / 1_000.0),
+            n => n.to_string(),
+        };
+        format!("{} tokens (~${:.2})", tokens, self.estimated_cost_usd)
+    }
 }
 
 /// Top correspondent entry.
@@ -553,6 +563,22 @@ mod tests {
         assert!((stats.estimated_cost_usd - 0.02).abs() < 0.001);
     }
 
+    #[test]
+    fn ai_stats_usage_summary() {
+        let stats = AiStats {
+            tokens_used: 1_234_567,
+            estimated_cost_usd: 3.4,
+            ..Default::default()
+        };
+        assert_eq!(stats.usage_summary(), "1.2M tokens (~$3.40)");
+
+        let small = AiStats {
+            tokens_used: 950,
+            ..Default::default()
+        };
+        assert_eq!(small.usage_summary(), "950 tokens (~$0.00)");
+    }
+
     #[test]
     fn csv_export() {
         let report = StatsReport {
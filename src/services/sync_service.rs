@@ -12,8 +12,13 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, RwLock};
+use tracing::Instrument;
 
-use crate::domain::{AccountId, Email, EmailId};
+use crate::domain::{AccountId, Address, Email, EmailId, LabelId, ThreadId};
+use crate::logging::provider_call;
+use crate::providers::email::{self, ProviderCapabilities};
+use crate::providers::http;
+use crate::services::{AuthStateReporter, BlockAction, Blocklist, ServiceBus, ServiceEvent};
 
 /// Change from a remote email provider.
 #[derive(Debug, Clone)]
@@ -60,6 +65,8 @@ pub enum PendingChangeType {
     Archive { thread_ids: Vec<String> },
     /// Trash threads.
     Trash { thread_ids: Vec<String> },
+    /// Report threads as spam.
+    ReportSpam { thread_ids: Vec<String> },
     /// Star/unstar a thread.
     Star { thread_id: String, starred: bool },
     /// Mark thread as read/unread.
@@ -72,8 +79,54 @@ pub enum PendingChangeType {
     SendEmail { draft_id: String },
 }
 
+impl PendingChangeType {
+    /// Converts the change to the form providers push to the server.
+    ///
+    /// Fails for [`SendEmail`](Self::SendEmail), which can't be sent without
+    /// its draft's content.
+    pub fn to_provider(&self) -> Result<email::PendingChangeType> {
+        let threads = |ids: &[String]| ids.iter().map(|id| ThreadId::from(id.as_str())).collect();
+        Ok(match self {
+            PendingChangeType::Archive { thread_ids } => email::PendingChangeType::Archive {
+                thread_ids: threads(thread_ids),
+            },
+            PendingChangeType::Trash { thread_ids } => email::PendingChangeType::Trash {
+                thread_ids: threads(thread_ids),
+            },
+            PendingChangeType::ReportSpam { thread_ids } => email::PendingChangeType::ReportSpam {
+                thread_ids: threads(thread_ids),
+            },
+            PendingChangeType::Star { thread_id, starred } => email::PendingChangeType::Star {
+                thread_id: ThreadId::from(thread_id.as_str()),
+                starred: *starred,
+            },
+            PendingChangeType::MarkRead { thread_id, read } => email::PendingChangeType::MarkRead {
+                thread_ids: vec![ThreadId::from(thread_id.as_str())],
+                read: *read,
+            },
+            PendingChangeType::ApplyLabel { thread_id, label } => {
+                email::PendingChangeType::ApplyLabel {
+                    thread_ids: vec![ThreadId::from(thread_id.as_str())],
+                    label_id: LabelId::from(label.as_str()),
+                }
+            }
+            PendingChangeType::RemoveLabel { thread_id, label } => {
+                email::PendingChangeType::RemoveLabel {
+                    thread_ids: vec![ThreadId::from(thread_id.as_str())],
+                    label_id: LabelId::from(label.as_str()),
+                }
+            }
+            PendingChangeType::SendEmail { draft_id } => {
+                anyhow::bail!("Can't send draft {} without its content", draft_id)
+            }
+        })
+    }
+}
+
 /// Sync state for an account.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The default is the state of an account that has never synced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SyncState {
     /// Last successful sync time.
     pub last_sync: Option<DateTime<Utc>>,
@@ -152,6 +205,43 @@ pub struct SyncSettings {
     pub sync_on_launch: bool,
     /// Maximum emails to fetch per sync.
     pub max_emails_per_sync: usize,
+    /// Days to keep message bodies and attachments locally before evicting
+    /// them, or `None` to keep them indefinitely. Evicted bodies are fetched
+    /// again from the provider when opened.
+    #[serde(default)]
+    pub body_retention_days: Option<u32>,
+    /// Days to keep threads in the trash before deleting them for good, or
+    /// `None` to keep them until emptied by hand.
+    #[serde(default = "default_purge_retention_days")]
+    pub trash_retention_days: Option<u32>,
+    /// Days to keep threads in spam before deleting them for good, or
+    /// `None` to keep them until emptied by hand.
+    #[serde(default = "default_purge_retention_days")]
+    pub spam_retention_days: Option<u32>,
+}
+
+impl SyncSettings {
+    /// Returns the age after which bodies are evicted, if eviction is on.
+    pub fn body_retention(&self) -> Option<chrono::Duration> {
+        self.body_retention_days
+            .map(|days| chrono::Duration::days(i64::from(days)))
+    }
+
+    /// Returns the age after which trashed threads are purged, if ever.
+    pub fn trash_retention(&self) -> Option<chrono::Duration> {
+        self.trash_retention_days
+            .map(|days| chrono::Duration::days(i64::from(days)))
+    }
+
+    /// Returns the age after which spam threads are purged, if ever.
+    pub fn spam_retention(&self) -> Option<chrono::Duration> {
+        self.spam_retention_days
+            .map(|days| chrono::Duration::days(i64::from(days)))
+    }
+}
+
+fn default_purge_retention_days() -> Option<u32> {
+    Some(30)
 }
 
 impl Default for SyncSettings {
@@ -163,6 +253,9 @@ impl Default for SyncSettings {
             retry_delay: Duration::from_secs(30),
             sync_on_launch: true,
             max_emails_per_sync: 500,
+            body_retention_days: None,
+            trash_retention_days: default_purge_retention_days(),
+            spam_retention_days: default_purge_retention_days(),
         }
     }
 }
@@ -178,6 +271,70 @@ pub trait SyncProvider: Send + Sync {
 
     /// Gets the current sync state from the server.
     async fn get_current_state(&self) -> Result<SyncState>;
+
+    /// Returns the features the provider supports.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+}
+
+/// Syncs against the fake mailbox, so the sync pipeline can be tested
+/// without a server.
+#[cfg(any(test, feature = "fake-provider"))]
+#[async_trait::async_trait]
+impl SyncProvider for email::FakeEmailProvider {
+    async fn fetch_changes_since(&self, state: &SyncState) -> Result<Vec<Change>> {
+        let labels = |ids: Vec<LabelId>| ids.into_iter().map(|id| id.0).collect();
+        Ok(self
+            .changes_since(state.last_sync)
+            .into_iter()
+            .map(|change| match change {
+                email::MailboxChange::Added(email) => Change::NewEmail(email),
+                email::MailboxChange::Updated {
+                    email_id,
+                    is_read,
+                    is_starred,
+                    added,
+                    removed,
+                } => Change::Updated(
+                    email_id,
+                    EmailUpdates {
+                        is_read,
+                        is_starred,
+                        add_labels: labels(added),
+                        remove_labels: labels(removed),
+                    },
+                ),
+                email::MailboxChange::Deleted(email_id) => Change::Deleted(email_id),
+            })
+            .collect())
+    }
+
+    async fn push_change(&self, change: &PendingChange) -> Result<()> {
+        email::EmailProvider::push_change(
+            self,
+            &email::PendingChange {
+                id: change.id.clone(),
+                change_type: change.change_type.to_provider()?,
+                created_at: change.created_at,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn get_current_state(&self) -> Result<SyncState> {
+        Ok(SyncState {
+            last_sync: Some(self.last_change().unwrap_or_else(Utc::now)),
+            last_history_id: None,
+            last_uid_validity: None,
+            last_uid: None,
+        })
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        email::EmailProvider::capabilities(self)
+    }
 }
 
 /// Storage trait for sync persistence.
@@ -203,6 +360,15 @@ pub trait SyncStorage: Send + Sync {
 
     /// Deletes an email.
     async fn delete_email(&self, email_id: &EmailId) -> Result<()>;
+
+    /// Returns whether a thread is muted.
+    async fn is_thread_muted(&self, thread_id: &ThreadId) -> Result<bool>;
+
+    /// Returns whether a thread is watched.
+    async fn is_thread_watched(&self, thread_id: &ThreadId) -> Result<bool>;
+
+    /// Returns the senders of the messages stored in a thread.
+    async fn thread_senders(&self, thread_id: &ThreadId) -> Result<Vec<Address>>;
 }
 
 /// Event emitted by the sync service.
@@ -216,6 +382,11 @@ pub enum SyncEvent {
         processed: usize,
         total: usize,
     },
+    /// A new email arrived in a thread that isn't muted.
+    ///
+    /// Mail for muted threads is archived on arrival and not reported, so
+    /// notifications should be driven from this event.
+    NewEmail(Box<Email>),
     /// Sync completed.
     Completed(AccountId, SyncResult),
     /// Sync failed.
@@ -257,6 +428,12 @@ pub struct SyncService<S: SyncStorage> {
     stop_flag: AtomicBool,
     /// Event sender for sync events.
     event_sender: broadcast::Sender<SyncEvent>,
+    /// Blocked senders, enforced as new mail is applied.
+    blocklist: RwLock<Blocklist>,
+    /// Bus that applied changes are published to for other services.
+    event_bus: Option<ServiceBus>,
+    /// Where provider failures are reported, so they reach the UI.
+    auth_states: Option<Arc<dyn AuthStateReporter>>,
 }
 
 impl<S: SyncStorage + 'static> SyncService<S> {
@@ -270,6 +447,34 @@ impl<S: SyncStorage + 'static> SyncService<S> {
             status: RwLock::new(HashMap::new()),
             stop_flag: AtomicBool::new(false),
             event_sender,
+            blocklist: RwLock::new(Blocklist::default()),
+            event_bus: None,
+            auth_states: None,
+        }
+    }
+
+    /// Publishes new mail, read state and label changes to `event_bus` as
+    /// they are applied.
+    pub fn with_event_bus(mut self, event_bus: ServiceBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Reports provider failures during sync to `auth_states`, e.g. the
+    /// [`AccountService`](crate::services::AccountService), so rejected
+    /// credentials badge the account. A successful fetch clears the state.
+    pub fn with_auth_states(mut self, auth_states: Arc<dyn AuthStateReporter>) -> Self {
+        self.auth_states = Some(auth_states);
+        self
+    }
+
+    /// Reports a failed provider call, if the error came from the provider.
+    fn report_provider_error(&self, account_id: &AccountId, error: &anyhow::Error) {
+        if let (Some(auth_states), Some(error)) = (
+            &self.auth_states,
+            error.downcast_ref::<email::ProviderError>(),
+        ) {
+            auth_states.report_provider_error(account_id, error);
         }
     }
 
@@ -297,6 +502,13 @@ impl<S: SyncStorage + 'static> SyncService<S> {
         *current = settings;
     }
 
+    /// Replaces the blocklist enforced on new mail.
+    ///
+    /// Call whenever the user blocks or unblocks a sender.
+    pub async fn set_blocklist(&self, blocklist: Blocklist) {
+        *self.blocklist.write().await = blocklist;
+    }
+
     /// Subscribes to sync events.
     pub fn subscribe(&self) -> broadcast::Receiver<SyncEvent> {
         self.event_sender.subscribe()
@@ -327,7 +539,19 @@ impl<S: SyncStorage + 'static> SyncService<S> {
             .event_sender
             .send(SyncEvent::Started(account_id.clone()));
 
-        let result = self.do_sync(account_id).await;
+        let span = tracing::info_span!("sync", account_id = %account_id);
+        let result = self.do_sync(account_id).instrument(span.clone()).await;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        span.in_scope(|| match &result {
+            Ok(sync_result) => tracing::info!(
+                duration_ms,
+                changes = sync_result.changes_applied,
+                pushed = sync_result.pending_synced,
+                errors = sync_result.errors.len(),
+                "sync finished"
+            ),
+            Err(e) => tracing::warn!(duration_ms, error = %e, "sync failed"),
+        });
 
         // Update status based on result
         {
@@ -344,7 +568,7 @@ impl<S: SyncStorage + 'static> SyncService<S> {
 
         match result {
             Ok(mut sync_result) => {
-                sync_result.duration_ms = start.elapsed().as_millis() as u64;
+                sync_result.duration_ms = duration_ms;
                 let _ = self.event_sender.send(SyncEvent::Completed(
                     account_id.clone(),
                     sync_result.clone(),
@@ -371,13 +595,31 @@ impl<S: SyncStorage + 'static> SyncService<S> {
         let local_state = self.storage.get_sync_state(account_id).await?;
 
         // Fetch changes from server
-        let changes = provider.fetch_changes_since(&local_state).await?;
+        let fetched = provider_call(
+            "fetch_changes",
+            account_id,
+            provider.fetch_changes_since(&local_state),
+        )
+        .await;
+        let changes = match fetched {
+            Ok(changes) => changes,
+            Err(e) => {
+                self.report_provider_error(account_id, &e);
+                return Err(e);
+            }
+        };
+        if let Some(auth_states) = &self.auth_states {
+            auth_states.report_provider_success(account_id);
+        }
         let changes_count = changes.len();
 
         // Apply changes locally
         let mut errors = Vec::new();
         for change in changes {
-            if let Err(e) = self.apply_change(&change).await {
+            if let Err(e) = self
+                .apply_change(account_id, provider.as_ref(), &change)
+                .await
+            {
                 errors.push(format!("Failed to apply change: {}", e));
             }
         }
@@ -388,20 +630,20 @@ impl<S: SyncStorage + 'static> SyncService<S> {
         let mut synced_count = 0;
 
         for change in pending {
-            match provider.push_change(&change).await {
+            match provider_call("push_change", account_id, provider.push_change(&change)).await {
                 Ok(()) => {
                     self.storage.mark_change_synced(&change.id).await?;
                     synced_count += 1;
                 }
                 Err(e) => {
+                    self.report_provider_error(account_id, &e);
                     errors.push(format!("Failed to push change: {}", e));
                 }
             }
         }
 
         // Update sync state
-        let new_state = provider
-            .get_current_state()
+        let new_state = provider_call("get_state", account_id, provider.get_current_state())
             .await
             .unwrap_or_else(|_| SyncState::now());
         self.storage
@@ -419,13 +661,85 @@ impl<S: SyncStorage + 'static> SyncService<S> {
     }
 
     /// Applies a change to local storage.
-    async fn apply_change(&self, change: &Change) -> Result<()> {
+    ///
+    /// New mail from a blocked sender is trashed or archived on arrival, on
+    /// the server as well, without being reported. New mail for a muted
+    /// thread is stored without the inbox label, unless the thread is also
+    /// watched; watching wins. With native labels the
+    /// archive is also pushed to the server; folder-based providers keep the
+    /// message where it is and mute stays local.
+    async fn apply_change(
+        &self,
+        account_id: &AccountId,
+        provider: &dyn SyncProvider,
+        change: &Change,
+    ) -> Result<()> {
         match change {
             Change::NewEmail(email) => {
-                self.storage.insert_email(email).await?;
+                if self.apply_blocked(account_id, provider, email).await? {
+                    return Ok(());
+                }
+
+                let muted = self.storage.is_thread_muted(&email.thread_id).await?;
+                let watched = self.storage.is_thread_watched(&email.thread_id).await?;
+                if muted && watched {
+                    tracing::warn!(
+                        thread_id = %email.thread_id,
+                        "Thread is both muted and watched; delivering as watched"
+                    );
+                }
+                if !muted || watched {
+                    self.storage.insert_email(email).await?;
+                    let _ = self.event_sender.send(SyncEvent::NewEmail(email.clone()));
+                    self.publish(ServiceEvent::NewEmail {
+                        email: email.clone(),
+                        watched,
+                    });
+                    return Ok(());
+                }
+
+                let inbox = LabelId::from("INBOX");
+                let mut archived = email.as_ref().clone();
+                archived.labels.retain(|l| *l != inbox);
+                self.storage.insert_email(&archived).await?;
+
+                if provider.capabilities().native_labels {
+                    let archive = PendingChange {
+                        id: format!("mute-{}", email.id),
+                        account_id: account_id.clone(),
+                        change_type: PendingChangeType::Archive {
+                            thread_ids: vec![email.thread_id.0.clone()],
+                        },
+                        created_at: Utc::now(),
+                    };
+                    provider.push_change(&archive).await?;
+                }
             }
             Change::Updated(email_id, updates) => {
                 self.storage.update_email(email_id, updates).await?;
+                if let Some(is_read) = updates.is_read {
+                    self.publish(ServiceEvent::ThreadRead {
+                        email_id: email_id.clone(),
+                        is_read,
+                    });
+                }
+                if !updates.add_labels.is_empty() || !updates.remove_labels.is_empty() {
+                    self.publish(ServiceEvent::LabelChanged {
+                        email_id: email_id.clone(),
+                        added: updates
+                            .add_labels
+                            .iter()
+                            .cloned()
+                            .map(LabelId::from)
+                            .collect(),
+                        removed: updates
+                            .remove_labels
+                            .iter()
+                            .cloned()
+                            .map(LabelId::from)
+                            .collect(),
+                    });
+                }
             }
             Change::Deleted(email_id) => {
                 self.storage.delete_email(email_id).await?;
@@ -434,6 +748,81 @@ impl<S: SyncStorage + 'static> SyncService<S> {
         Ok(())
     }
 
+    /// Publishes an event to the bus, if one is attached.
+    fn publish(&self, event: ServiceEvent) {
+        if let Some(bus) = &self.event_bus {
+            bus.publish(event);
+        }
+    }
+
+    /// Moves new mail from a blocked sender out of the inbox.
+    ///
+    /// Providers trash and archive whole threads, so the move is only pushed
+    /// when every message in the thread is from a blocked sender; in a
+    /// conversation with anyone else it stays local, leaving their messages
+    /// alone. Returns whether the sender was blocked.
+    async fn apply_blocked(
+        &self,
+        account_id: &AccountId,
+        provider: &dyn SyncProvider,
+        email: &Email,
+    ) -> Result<bool> {
+        let (action, report_spam) = match self.blocklist.read().await.matching(&email.from) {
+            Some(entry) => (entry.action, entry.report_spam),
+            None => return Ok(false),
+        };
+
+        let inbox = LabelId::from("INBOX");
+        let mut blocked = email.clone();
+        blocked.labels.retain(|l| *l != inbox);
+        if action == BlockAction::Trash {
+            blocked.labels.push(LabelId::from("TRASH"));
+        }
+        if report_spam {
+            blocked.labels.push(LabelId::from("SPAM"));
+        }
+        let senders = self.storage.thread_senders(&email.thread_id).await?;
+        self.storage.insert_email(&blocked).await?;
+
+        let blocklist = self.blocklist.read().await;
+        if let Some(other) = senders.iter().find(|s| !blocklist.is_blocked(s)) {
+            tracing::debug!(
+                thread_id = %email.thread_id,
+                sender = %other.email,
+                "Thread has mail from unblocked senders; moving the blocked message locally"
+            );
+            return Ok(true);
+        }
+        drop(blocklist);
+
+        let thread_id = email.thread_id.0.clone();
+        let mut changes = vec![match action {
+            BlockAction::Trash => PendingChangeType::Trash {
+                thread_ids: vec![thread_id.clone()],
+            },
+            BlockAction::Archive => PendingChangeType::Archive {
+                thread_ids: vec![thread_id.clone()],
+            },
+        }];
+        if report_spam {
+            changes.push(PendingChangeType::ApplyLabel {
+                thread_id,
+                label: "SPAM".to_string(),
+            });
+        }
+
+        for (i, change_type) in changes.into_iter().enumerate() {
+            let change = PendingChange {
+                id: format!("block-{}-{}", email.id, i),
+                account_id: account_id.clone(),
+                change_type,
+                created_at: Utc::now(),
+            };
+            provider.push_change(&change).await?;
+        }
+        Ok(true)
+    }
+
     /// Starts background synchronization.
     ///
     /// Spawns a task that periodically syncs all accounts.
@@ -465,19 +854,47 @@ impl<S: SyncStorage + 'static> SyncService<S> {
                 let account_ids: Vec<AccountId> = providers.keys().cloned().collect();
                 drop(providers);
 
-                // Sync each account
-                for account_id in &account_ids {
-                    if service.stop_flag.load(Ordering::SeqCst) {
-                        break;
-                    }
-                    let _ = service.sync_account(account_id).await;
-                }
+                let probe_online = http::is_online().await;
+                service.sync_round(&account_ids, probe_online).await;
 
                 tokio::time::sleep(interval).await;
             }
         });
     }
 
+    /// Syncs each account once.
+    ///
+    /// `probe_online` is the result of [`http::is_online`]. The probe host
+    /// is blocked on some networks, such as corporate networks and China,
+    /// so a failed probe alone doesn't skip the round: a provider that
+    /// answers counts as online. Only when nothing has answered and a
+    /// provider can't be reached are the accounts marked offline, rather
+    /// than letting every provider time out.
+    async fn sync_round(&self, account_ids: &[AccountId], probe_online: bool) {
+        let mut online = probe_online;
+        for account_id in account_ids {
+            if self.stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+            match self.sync_account(account_id).await {
+                Ok(_) => online = true,
+                Err(e) if !online && is_connection_error(&e) => {
+                    self.set_offline(account_ids).await;
+                    break;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Marks accounts as offline until their next sync.
+    async fn set_offline(&self, account_ids: &[AccountId]) {
+        let mut status = self.status.write().await;
+        for account_id in account_ids {
+            status.insert(account_id.clone(), SyncStatus::Offline);
+        }
+    }
+
     /// Stops background synchronization.
     pub fn stop_background_sync(&self) {
         self.stop_flag.store(true, Ordering::SeqCst);
@@ -514,6 +931,14 @@ impl<S: SyncStorage + 'static> SyncService<S> {
     }
 }
 
+/// Returns whether a sync failed because its provider couldn't be reached.
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<email::ProviderError>(),
+        Some(email::ProviderError::Connection(_))
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -555,6 +980,9 @@ mod tests {
         assert_eq!(settings.sync_interval, Duration::from_secs(300));
         assert_eq!(settings.max_retries, 3);
         assert!(settings.sync_on_launch);
+        assert!(settings.body_retention().is_none());
+        assert_eq!(settings.trash_retention(), Some(chrono::Duration::days(30)));
+        assert_eq!(settings.spam_retention(), Some(chrono::Duration::days(30)));
     }
 
     #[test]
@@ -589,6 +1017,606 @@ mod tests {
         }
     }
 
+    use crate::domain::MessageId;
+    use crate::services::BlockEntry;
+    use std::sync::Mutex;
+
+    struct MockStorage {
+        muted: Vec<ThreadId>,
+        watched: Vec<ThreadId>,
+        inserted: Mutex<Vec<Email>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SyncStorage for MockStorage {
+        async fn get_sync_state(&self, _account_id: &AccountId) -> Result<SyncState> {
+            Ok(SyncState::now())
+        }
+
+        async fn update_sync_state(
+            &self,
+            _account_id: &AccountId,
+            _state: SyncState,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_pending_changes(&self, _account_id: &AccountId) -> Result<Vec<PendingChange>> {
+            Ok(vec![])
+        }
+
+        async fn mark_change_synced(&self, _change_id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn insert_email(&self, email: &Email) -> Result<()> {
+            self.inserted.lock().unwrap().push(email.clone());
+            Ok(())
+        }
+
+        async fn update_email(&self, _email_id: &EmailId, _updates: &EmailUpdates) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete_email(&self, _email_id: &EmailId) -> Result<()> {
+            Ok(())
+        }
+
+        async fn is_thread_muted(&self, thread_id: &ThreadId) -> Result<bool> {
+            Ok(self.muted.contains(thread_id))
+        }
+
+        async fn is_thread_watched(&self, thread_id: &ThreadId) -> Result<bool> {
+            Ok(self.watched.contains(thread_id))
+        }
+
+        async fn thread_senders(&self, thread_id: &ThreadId) -> Result<Vec<Address>> {
+            Ok(self
+                .inserted
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.thread_id == *thread_id)
+                .map(|e| e.from.clone())
+                .collect())
+        }
+    }
+
+    struct MockProvider {
+        native_labels: bool,
+        pushed: Mutex<Vec<PendingChange>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SyncProvider for MockProvider {
+        async fn fetch_changes_since(&self, _state: &SyncState) -> Result<Vec<Change>> {
+            Ok(vec![Change::NewEmail(Box::new(reply()))])
+        }
+
+        async fn push_change(&self, change: &PendingChange) -> Result<()> {
+            self.pushed.lock().unwrap().push(change.clone());
+            Ok(())
+        }
+
+        async fn get_current_state(&self) -> Result<SyncState> {
+            Ok(SyncState::now())
+        }
+
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities {
+                native_labels: self.native_labels,
+                ..Default::default()
+            }
+        }
+    }
+
+    fn reply() -> Email {
+        Email {
+            message_id: MessageId::from("<msg-2@example.com>"),
+            in_reply_to: Some(MessageId::from("<msg-1@example.com>")),
+            from: Address::new("alice@example.com"),
+            to: vec![],
+            subject: Some("Re: Plans".to_string()),
+            body_text: None,
+            snippet: String::new(),
+            labels: vec![LabelId::from("INBOX")],
+            ..crate::domain::test_email("email-2", "thread-1")
+        }
+    }
+
+    async fn sync_reply(
+        muted: bool,
+        native_labels: bool,
+    ) -> (Arc<MockStorage>, Arc<MockProvider>, Vec<SyncEvent>) {
+        sync_reply_with_blocklist(muted, native_labels, Blocklist::default()).await
+    }
+
+    async fn sync_reply_with_blocklist(
+        muted: bool,
+        native_labels: bool,
+        blocklist: Blocklist,
+    ) -> (Arc<MockStorage>, Arc<MockProvider>, Vec<SyncEvent>) {
+        let storage = Arc::new(MockStorage {
+            muted: if muted {
+                vec![ThreadId::from("thread-1")]
+            } else {
+                vec![]
+            },
+            watched: vec![],
+            inserted: Mutex::new(vec![]),
+        });
+        let (provider, received) = sync_into(storage.clone(), native_labels, blocklist).await;
+        (storage, provider, received)
+    }
+
+    /// Syncs [`reply`] into `storage`, returning the provider and events.
+    async fn sync_into(
+        storage: Arc<MockStorage>,
+        native_labels: bool,
+        blocklist: Blocklist,
+    ) -> (Arc<MockProvider>, Vec<SyncEvent>) {
+        let provider = Arc::new(MockProvider {
+            native_labels,
+            pushed: Mutex::new(vec![]),
+        });
+        let account = AccountId::from("account-1");
+        let service = SyncService::new(storage.clone(), SyncSettings::default());
+        service
+            .register_provider(account.clone(), provider.clone())
+            .await;
+        service.set_blocklist(blocklist).await;
+        let mut events = service.subscribe();
+
+        service.sync_account(&account).await.unwrap();
+
+        let mut received = vec![];
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        (provider, received)
+    }
+
+    /// Rejects every fetch as the server does for revoked credentials.
+    struct RevokedProvider;
+
+    #[async_trait::async_trait]
+    impl SyncProvider for RevokedProvider {
+        async fn fetch_changes_since(&self, _state: &SyncState) -> Result<Vec<Change>> {
+            Err(email::ProviderError::Authentication("token revoked".into()).into())
+        }
+
+        async fn push_change(&self, _change: &PendingChange) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_current_state(&self) -> Result<SyncState> {
+            Ok(SyncState::now())
+        }
+    }
+
+    /// Provider whose server can't be reached.
+    struct UnreachableProvider;
+
+    #[async_trait::async_trait]
+    impl SyncProvider for UnreachableProvider {
+        async fn fetch_changes_since(&self, _state: &SyncState) -> Result<Vec<Change>> {
+            Err(email::ProviderError::Connection("timed out".into()).into())
+        }
+
+        async fn push_change(&self, _change: &PendingChange) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_current_state(&self) -> Result<SyncState> {
+            Ok(SyncState::now())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingAuthStates {
+        errors: Mutex<Vec<(AccountId, String)>>,
+        successes: Mutex<Vec<AccountId>>,
+    }
+
+    impl AuthStateReporter for RecordingAuthStates {
+        fn report_provider_error(&self, account_id: &AccountId, error: &email::ProviderError) {
+            let report = (account_id.clone(), error.to_string());
+            self.errors.lock().unwrap().push(report);
+        }
+
+        fn report_provider_success(&self, account_id: &AccountId) {
+            self.successes.lock().unwrap().push(account_id.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_failures_are_reported_to_the_account_auth_states() {
+        let storage = Arc::new(MockStorage {
+            muted: vec![],
+            watched: vec![],
+            inserted: Mutex::new(vec![]),
+        });
+        let auth_states = Arc::new(RecordingAuthStates::default());
+        let service = SyncService::new(storage, SyncSettings::default())
+            .with_auth_states(auth_states.clone());
+        let revoked = AccountId::from("revoked");
+        service
+            .register_provider(revoked.clone(), Arc::new(RevokedProvider))
+            .await;
+        let working = AccountId::from("working");
+        let provider = Arc::new(MockProvider {
+            native_labels: true,
+            pushed: Mutex::new(vec![]),
+        });
+        service.register_provider(working.clone(), provider).await;
+
+        assert!(service.sync_account(&revoked).await.is_err());
+        service.sync_account(&working).await.unwrap();
+
+        let errors = auth_states.errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, revoked);
+        assert!(errors[0].1.contains("token revoked"));
+        assert_eq!(*auth_states.successes.lock().unwrap(), vec![working]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_probe_is_overruled_by_a_provider_that_answers() {
+        let storage = Arc::new(MockStorage {
+            muted: vec![],
+            watched: vec![],
+            inserted: Mutex::new(vec![]),
+        });
+        let service = SyncService::new(storage, SyncSettings::default());
+        let working = AccountId::from("working");
+        let provider = Arc::new(MockProvider {
+            native_labels: true,
+            pushed: Mutex::new(vec![]),
+        });
+        service.register_provider(working.clone(), provider).await;
+        let unreachable = AccountId::from("unreachable");
+        service
+            .register_provider(unreachable.clone(), Arc::new(UnreachableProvider))
+            .await;
+
+        service
+            .sync_round(&[working.clone(), unreachable.clone()], false)
+            .await;
+        assert_eq!(service.get_sync_status(&working).await, SyncStatus::Success);
+        assert_eq!(
+            service.get_sync_status(&unreachable).await,
+            SyncStatus::Failed
+        );
+
+        // With nothing answering, the round stops and goes offline.
+        service
+            .sync_round(&[unreachable.clone(), working.clone()], false)
+            .await;
+        assert_eq!(
+            service.get_sync_status(&unreachable).await,
+            SyncStatus::Offline
+        );
+        assert_eq!(service.get_sync_status(&working).await, SyncStatus::Offline);
+    }
+
+    #[tokio::test]
+    async fn new_mail_on_unmuted_thread_lands_in_inbox_and_notifies() {
+        let (storage, provider, events) = sync_reply(false, true).await;
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(provider.pushed.lock().unwrap().is_empty());
+        assert!(events.iter().any(|e| matches!(e, SyncEvent::NewEmail(_))));
+    }
+
+    #[tokio::test]
+    async fn new_mail_is_published_on_the_event_bus() {
+        let storage = Arc::new(MockStorage {
+            muted: vec![],
+            watched: vec![],
+            inserted: Mutex::new(vec![]),
+        });
+        let provider = Arc::new(MockProvider {
+            native_labels: true,
+            pushed: Mutex::new(vec![]),
+        });
+        let account = AccountId::from("account-1");
+        let bus = ServiceBus::new();
+        let mut events = bus.subscribe();
+        let service =
+            SyncService::new(storage, SyncSettings::default()).with_event_bus(bus.clone());
+        service.register_provider(account.clone(), provider).await;
+
+        service.sync_account(&account).await.unwrap();
+
+        match events.try_recv().unwrap() {
+            ServiceEvent::NewEmail { email, watched } => {
+                assert_eq!(email.id, EmailId::from("email-2"));
+                assert!(!watched);
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn watching_a_muted_thread_delivers_new_mail() {
+        let thread = ThreadId::from("thread-1");
+        let storage = Arc::new(MockStorage {
+            muted: vec![thread.clone()],
+            watched: vec![thread],
+            inserted: Mutex::new(vec![]),
+        });
+        let provider = Arc::new(MockProvider {
+            native_labels: true,
+            pushed: Mutex::new(vec![]),
+        });
+        let account = AccountId::from("account-1");
+        let bus = ServiceBus::new();
+        let mut events = bus.subscribe();
+        let service =
+            SyncService::new(storage.clone(), SyncSettings::default()).with_event_bus(bus.clone());
+        service
+            .register_provider(account.clone(), provider.clone())
+            .await;
+
+        service.sync_account(&account).await.unwrap();
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(provider.pushed.lock().unwrap().is_empty());
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            ServiceEvent::NewEmail { watched: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn new_mail_on_muted_thread_is_archived_silently() {
+        let (storage, provider, events) = sync_reply(true, true).await;
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(!inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(!events.iter().any(|e| matches!(e, SyncEvent::NewEmail(_))));
+
+        let pushed = provider.pushed.lock().unwrap();
+        assert_eq!(pushed.len(), 1);
+        assert!(matches!(
+            &pushed[0].change_type,
+            PendingChangeType::Archive { thread_ids } if thread_ids == &["thread-1"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn muting_without_native_labels_stays_local() {
+        let (storage, provider, _) = sync_reply(true, false).await;
+
+        assert!(!storage.inserted.lock().unwrap()[0]
+            .labels
+            .contains(&LabelId::from("INBOX")));
+        assert!(provider.pushed.lock().unwrap().is_empty());
+    }
+
+    fn block(pattern: &str, action: BlockAction, report_spam: bool) -> Blocklist {
+        Blocklist::new(vec![BlockEntry {
+            pattern: pattern.to_string(),
+            action,
+            report_spam,
+            created_at: Utc::now(),
+        }])
+    }
+
+    #[tokio::test]
+    async fn new_mail_from_blocked_sender_is_trashed_silently() {
+        let blocklist = block("*@example.com", BlockAction::Trash, false);
+        let (storage, provider, events) = sync_reply_with_blocklist(false, false, blocklist).await;
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(!inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(inserted[0].labels.contains(&LabelId::from("TRASH")));
+        assert!(!events.iter().any(|e| matches!(e, SyncEvent::NewEmail(_))));
+
+        let pushed = provider.pushed.lock().unwrap();
+        assert_eq!(pushed.len(), 1);
+        assert!(matches!(
+            &pushed[0].change_type,
+            PendingChangeType::Trash { thread_ids } if thread_ids == &["thread-1"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn blocked_sender_can_be_archived_and_reported_as_spam() {
+        let blocklist = block("alice@example.com", BlockAction::Archive, true);
+        let (storage, provider, _) = sync_reply_with_blocklist(false, true, blocklist).await;
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(!inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(!inserted[0].labels.contains(&LabelId::from("TRASH")));
+
+        let pushed = provider.pushed.lock().unwrap();
+        assert_eq!(pushed.len(), 2);
+        assert!(matches!(
+            &pushed[0].change_type,
+            PendingChangeType::Archive { .. }
+        ));
+        assert!(matches!(
+            &pushed[1].change_type,
+            PendingChangeType::ApplyLabel { label, .. } if label == "SPAM"
+        ));
+    }
+
+    #[tokio::test]
+    async fn blocked_reply_in_a_mixed_thread_leaves_the_thread_alone() {
+        let mut original = reply();
+        original.id = EmailId::from("email-1");
+        original.from = Address::new("bob@example.org");
+        let storage = Arc::new(MockStorage {
+            muted: vec![],
+            watched: vec![],
+            inserted: Mutex::new(vec![original]),
+        });
+        let blocklist = block("alice@example.com", BlockAction::Trash, true);
+
+        let (provider, events) = sync_into(storage.clone(), true, blocklist).await;
+
+        let inserted = storage.inserted.lock().unwrap();
+        assert!(inserted[0].labels.contains(&LabelId::from("INBOX")));
+        assert!(!inserted[1].labels.contains(&LabelId::from("INBOX")));
+        assert!(inserted[1].labels.contains(&LabelId::from("TRASH")));
+        assert!(provider.pushed.lock().unwrap().is_empty());
+        assert!(!events.iter().any(|e| matches!(e, SyncEvent::NewEmail(_))));
+    }
+
+    #[tokio::test]
+    async fn unblocked_sender_is_unaffected() {
+        let blocklist = block("spammer.com", BlockAction::Trash, false);
+        let (storage, provider, events) = sync_reply_with_blocklist(false, true, blocklist).await;
+
+        assert!(storage.inserted.lock().unwrap()[0]
+            .labels
+            .contains(&LabelId::from("INBOX")));
+        assert!(provider.pushed.lock().unwrap().is_empty());
+        assert!(events.iter().any(|e| matches!(e, SyncEvent::NewEmail(_))));
+    }
+
+    /// Sync storage holding a local copy of the mailbox.
+    #[derive(Default)]
+    struct LocalMailbox {
+        state: Mutex<Option<SyncState>>,
+        emails: Mutex<HashMap<EmailId, Email>>,
+        pending: Mutex<Vec<PendingChange>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SyncStorage for LocalMailbox {
+        async fn get_sync_state(&self, _account_id: &AccountId) -> Result<SyncState> {
+            Ok(self.state.lock().unwrap().clone().unwrap_or(SyncState {
+                last_sync: None,
+                last_history_id: None,
+                last_uid_validity: None,
+                last_uid: None,
+            }))
+        }
+
+        async fn update_sync_state(&self, _account_id: &AccountId, state: SyncState) -> Result<()> {
+            *self.state.lock().unwrap() = Some(state);
+            Ok(())
+        }
+
+        async fn get_pending_changes(&self, _account_id: &AccountId) -> Result<Vec<PendingChange>> {
+            Ok(self.pending.lock().unwrap().clone())
+        }
+
+        async fn mark_change_synced(&self, change_id: &str) -> Result<()> {
+            self.pending.lock().unwrap().retain(|c| c.id != change_id);
+            Ok(())
+        }
+
+        async fn insert_email(&self, email: &Email) -> Result<()> {
+            self.emails
+                .lock()
+                .unwrap()
+                .insert(email.id.clone(), email.clone());
+            Ok(())
+        }
+
+        async fn update_email(&self, email_id: &EmailId, updates: &EmailUpdates) -> Result<()> {
+            if let Some(email) = self.emails.lock().unwrap().get_mut(email_id) {
+                if let Some(is_read) = updates.is_read {
+                    email.is_read = is_read;
+                }
+                if let Some(is_starred) = updates.is_starred {
+                    email.is_starred = is_starred;
+                }
+                email
+                    .labels
+                    .retain(|l| !updates.remove_labels.contains(&l.0));
+                email
+                    .labels
+                    .extend(updates.add_labels.iter().cloned().map(LabelId::from));
+            }
+            Ok(())
+        }
+
+        async fn delete_email(&self, email_id: &EmailId) -> Result<()> {
+            self.emails.lock().unwrap().remove(email_id);
+            Ok(())
+        }
+
+        async fn is_thread_muted(&self, _thread_id: &ThreadId) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn is_thread_watched(&self, _thread_id: &ThreadId) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn thread_senders(&self, thread_id: &ThreadId) -> Result<Vec<Address>> {
+            Ok(self
+                .emails
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|e| e.thread_id == *thread_id)
+                .map(|e| e.from.clone())
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_round_trip_with_fake_provider() {
+        use crate::providers::email::{EmailProvider, FakeEmailProvider};
+
+        let account = AccountId::from("account-1");
+        let local = Arc::new(LocalMailbox::default());
+        let server = Arc::new(FakeEmailProvider::new(account.clone(), "me@example.com"));
+        let service = SyncService::new(local.clone(), SyncSettings::default());
+        service
+            .register_provider(account.clone(), server.clone())
+            .await;
+        let email_id = EmailId::from("email-2");
+        let local_email = || local.emails.lock().unwrap().get(&email_id).cloned();
+
+        // Mail on the server is pulled down.
+        server.seed(reply());
+        let result = service.sync_account(&account).await.unwrap();
+        assert_eq!(result.emails_received, 1);
+        assert!(local_email()
+            .unwrap()
+            .labels
+            .contains(&LabelId::from("INBOX")));
+
+        // A local archive is pushed up, and not pulled down again.
+        local.pending.lock().unwrap().push(PendingChange {
+            id: "change-1".to_string(),
+            account_id: account.clone(),
+            change_type: PendingChangeType::Archive {
+                thread_ids: vec!["thread-1".to_string()],
+            },
+            created_at: Utc::now(),
+        });
+        let result = service.sync_account(&account).await.unwrap();
+        assert_eq!(result.pending_synced, 1);
+        assert!(local.pending.lock().unwrap().is_empty());
+        assert!(server.email(&email_id).unwrap().labels.is_empty());
+        assert_eq!(
+            service
+                .sync_account(&account)
+                .await
+                .unwrap()
+                .emails_received,
+            0
+        );
+
+        // A change made elsewhere, e.g. reading on the phone, comes back.
+        server.mark_read("thread-1", true).await.unwrap();
+        service.sync_account(&account).await.unwrap();
+        assert!(local_email().unwrap().is_read);
+
+        server.delete(&email_id);
+        service.sync_account(&account).await.unwrap();
+        assert!(local_email().is_none());
+    }
+
     #[test]
     fn email_updates_default() {
         let updates = EmailUpdates::default();
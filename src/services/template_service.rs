@@ -0,0 +1,359 @@
+//! Template service for canned responses.
+//!
+//! Provides template management including:
+//! - CRUD for named templates
+//! - Placeholder substitution (`{{first_name}}`, `{{date}}`, ...) from the
+//!   recipient contact
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use thiserror::Error;
+
+use crate::domain::{Contact, Template};
+
+/// Errors that can occur during template operations.
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Template not found: {0}")]
+    NotFound(String),
+
+    #[error("Template already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Invalid template name: {0}")]
+    InvalidName(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
+
+/// Result type for template operations.
+pub type Result<T> = std::result::Result<T, TemplateError>;
+
+/// Storage trait for template persistence.
+pub trait TemplateStorage: Send + Sync {
+    /// Gets a template by name.
+    fn get_by_name(&self, name: &str) -> Result<Option<Template>>;
+
+    /// Stores a new template.
+    fn insert(&self, template: &Template) -> Result<()>;
+
+    /// Deletes a template by name.
+    fn delete(&self, name: &str) -> Result<()>;
+
+    /// Gets all templates, ordered by name.
+    fn list(&self) -> Result<Vec<Template>>;
+}
+
+/// Values substituted into a template's placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    values: HashMap<String, String>,
+}
+
+impl TemplateContext {
+    /// Creates an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a context for replying to `contact` at `now`.
+    ///
+    /// Provides `first_name`, `last_name`, `name`, `email` and `date`. Name
+    /// placeholders are only set when the contact has a name.
+    pub fn from_contact(contact: &Contact, now: DateTime<Local>) -> Self {
+        let mut context = Self::new()
+            .set("email", &contact.email)
+            .set("date", now.format("%B %-d, %Y").to_string());
+
+        if let Some(name) = contact.name.as_deref().map(str::trim) {
+            let mut parts = name.split_whitespace();
+            if let Some(first) = parts.next() {
+                context = context.set("first_name", first).set("name", name);
+            }
+            if let Some(last) = parts.last() {
+                context = context.set("last_name", last);
+            }
+        }
+
+        context
+    }
+
+    /// Sets a placeholder value.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// Gets a placeholder value.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+}
+
+/// Service for managing canned responses.
+pub struct TemplateService<S: TemplateStorage> {
+    storage: S,
+}
+
+impl<S: TemplateStorage> TemplateService<S> {
+    /// Creates a new template service.
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Gets all templates, ordered by name.
+    pub fn list(&self) -> Result<Vec<Template>> {
+        self.storage.list()
+    }
+
+    /// Gets a template by name.
+    pub fn get(&self, name: &str) -> Result<Template> {
+        self.storage
+            .get_by_name(name)?
+            .ok_or_else(|| TemplateError::NotFound(name.to_string()))
+    }
+
+    /// Creates a new template.
+    pub fn create(&self, name: &str, body: &str) -> Result<Template> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(TemplateError::InvalidName(
+                "Name cannot be empty".to_string(),
+            ));
+        }
+
+        if self.storage.get_by_name(name)?.is_some() {
+            return Err(TemplateError::AlreadyExists(name.to_string()));
+        }
+
+        let template = Template::new(name, body);
+        self.storage.insert(&template)?;
+        Ok(template)
+    }
+
+    /// Deletes a template.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.get(name)?;
+        self.storage.delete(name)
+    }
+
+    /// Renders a template's body with `context`.
+    ///
+    /// Placeholders without a value in `context` are left as written so the
+    /// user can see and fill them in before sending.
+    pub fn render(&self, name: &str, context: &TemplateContext) -> Result<String> {
+        Ok(render_body(&self.get(name)?.body, context))
+    }
+
+    /// Returns the placeholders in a template that `context` has no value for.
+    pub fn missing_placeholders(
+        &self,
+        name: &str,
+        context: &TemplateContext,
+    ) -> Result<Vec<String>> {
+        let template = self.get(name)?;
+        let mut missing = Vec::new();
+        for (_, key) in placeholders(&template.body) {
+            if context.get(key).is_none() && !missing.iter().any(|m| m == key) {
+                missing.push(key.to_string());
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// Replaces each `{{key}}` in `body` with its value from `context`.
+fn render_body(body: &str, context: &TemplateContext) -> String {
+    let mut rendered = String::with_capacity(body.len());
+    let mut last = 0;
+    for (span, key) in placeholders(body) {
+        if let Some(value) = context.get(key) {
+            rendered.push_str(&body[last..span.start]);
+            rendered.push_str(value);
+            last = span.end;
+        }
+    }
+    rendered.push_str(&body[last..]);
+    rendered
+}
+
+/// Finds `{{ key }}` placeholders, returning each one's byte range and
+/// trimmed key.
+fn placeholders(body: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(open) = body[offset..].find("{{") {
+        let start = offset + open;
+        let Some(close) = body[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let key = body[start + 2..end - 2].trim();
+        if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            found.push((start..end, key));
+            offset = end;
+        } else {
+            offset = start + 2;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::RwLock;
+
+    struct MockStorage {
+        templates: RwLock<HashMap<String, Template>>,
+    }
+
+    impl MockStorage {
+        fn new() -> Self {
+            Self {
+                templates: RwLock::new(HashMap::new()),
+            }
+        }
+    }
+
+    impl TemplateStorage for MockStorage {
+        fn get_by_name(&self, name: &str) -> Result<Option<Template>> {
+            Ok(self.templates.read().unwrap().get(name).cloned())
+        }
+
+        fn insert(&self, template: &Template) -> Result<()> {
+            self.templates
+                .write()
+                .unwrap()
+                .insert(template.name.clone(), template.clone());
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<()> {
+            self.templates.write().unwrap().remove(name);
+            Ok(())
+        }
+
+        fn list(&self) -> Result<Vec<Template>> {
+            let mut templates: Vec<Template> =
+                self.templates.read().unwrap().values().cloned().collect();
+            templates.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            Ok(templates)
+        }
+    }
+
+    fn service() -> TemplateService<MockStorage> {
+        TemplateService::new(MockStorage::new())
+    }
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 13, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn create_and_list() {
+        let service = service();
+        service.create("thanks", "Thanks!").unwrap();
+        service.create("Decline", "No thanks.").unwrap();
+
+        let names: Vec<String> = service
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["Decline", "thanks"]);
+    }
+
+    #[test]
+    fn create_rejects_empty_and_duplicate_names() {
+        let service = service();
+        assert!(matches!(
+            service.create("  ", "Body"),
+            Err(TemplateError::InvalidName(_))
+        ));
+
+        service.create("thanks", "Thanks!").unwrap();
+        assert!(matches!(
+            service.create(" thanks ", "Again"),
+            Err(TemplateError::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn render_substitutes_contact_placeholders() {
+        let service = service();
+        service
+            .create(
+                "followup",
+                "Hi {{first_name}},\n\nFollowing up as of {{ date }}.",
+            )
+            .unwrap();
+
+        let contact = Contact::with_name("ada@example.com", "Ada Lovelace");
+        let context = TemplateContext::from_contact(&contact, now());
+
+        assert_eq!(
+            service.render("followup", &context).unwrap(),
+            "Hi Ada,\n\nFollowing up as of March 13, 2024."
+        );
+    }
+
+    #[test]
+    fn render_leaves_missing_placeholders() {
+        let service = service();
+        service
+            .create(
+                "intro",
+                "Hi {{first_name}}, meet {{colleague}}. {{}} {{not a key}}",
+            )
+            .unwrap();
+
+        // No name on the contact, so `first_name` has no value either.
+        let contact = Contact::new("someone@example.com");
+        let context = TemplateContext::from_contact(&contact, now());
+
+        assert_eq!(
+            service.render("intro", &context).unwrap(),
+            "Hi {{first_name}}, meet {{colleague}}. {{}} {{not a key}}"
+        );
+        assert_eq!(
+            service.missing_placeholders("intro", &context).unwrap(),
+            vec!["first_name".to_string(), "colleague".to_string()]
+        );
+    }
+
+    #[test]
+    fn render_unknown_template() {
+        let service = service();
+        assert!(matches!(
+            service.render("nope", &TemplateContext::new()),
+            Err(TemplateError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn context_from_contact_names() {
+        let contact = Contact::with_name("grace@example.com", "Grace Brewster Hopper");
+        let context = TemplateContext::from_contact(&contact, now());
+        assert_eq!(context.get("first_name"), Some("Grace"));
+        assert_eq!(context.get("last_name"), Some("Hopper"));
+        assert_eq!(context.get("name"), Some("Grace Brewster Hopper"));
+        assert_eq!(context.get("email"), Some("grace@example.com"));
+    }
+
+    #[test]
+    fn delete_template() {
+        let service = service();
+        service.create("thanks", "Thanks!").unwrap();
+        service.delete("thanks").unwrap();
+        assert!(service.list().unwrap().is_empty());
+        assert!(matches!(
+            service.delete("thanks"),
+            Err(TemplateError::NotFound(_))
+        ));
+    }
+}
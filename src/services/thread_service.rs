@@ -4,12 +4,31 @@
 //! - Retrieving threads with various filters
 //! - Updating thread metadata (starred, read status)
 //! - Thread archiving and deletion
+//! - Muting threads so new replies stay out of the inbox
+//! - Watching threads so every new reply notifies
+//! - Bulk actions over a selection of threads
+//! - Undoing label changes, archiving and trashing
 //! - Thread statistics
+//! - Scoring thread importance
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use thiserror::Error;
+use tokio::sync::Mutex;
+
+pub use crate::domain::ThreadSort;
 
-use crate::domain::{AccountId, LabelId, Thread, ThreadId, ThreadSummary};
+use crate::domain::{
+    AccountId, Contact, ImportanceSignals, ImportanceWeights, LabelId, Thread, ThreadId,
+    ThreadSummary,
+};
+use crate::services::email_service::ThreadMetadataUpdate;
+use crate::services::undo_service::{
+    ActionResult, ActionState, ActionType, UndoService, UndoableAction,
+};
+use crate::services::SmartViewType;
 
 /// Errors that can occur during thread operations.
 #[derive(Debug, Error)]
@@ -41,6 +60,8 @@ pub struct ThreadFilter {
     pub unread_only: bool,
     /// Only starred threads.
     pub starred_only: bool,
+    /// Filter by muted status (`None` includes both).
+    pub muted: Option<bool>,
     /// Maximum number of results.
     pub limit: Option<u32>,
     /// Offset for pagination.
@@ -68,6 +89,12 @@ impl ThreadFilter {
         self
     }
 
+    /// Filters by muted status.
+    pub fn with_muted(mut self, muted: bool) -> Self {
+        self.muted = Some(muted);
+        self
+    }
+
     /// Filters by label.
     pub fn with_label(mut self, label_id: LabelId) -> Self {
         self.label_id = Some(label_id);
@@ -87,18 +114,6 @@ impl ThreadFilter {
     }
 }
 
-/// Sorting options for thread lists.
-#[derive(Debug, Clone, Copy, Default)]
-pub enum ThreadSort {
-    /// Sort by date, newest first.
-    #[default]
-    DateDesc,
-    /// Sort by date, oldest first.
-    DateAsc,
-    /// Unread threads first, then by date.
-    UnreadFirst,
-}
-
 /// Storage abstraction for thread operations.
 #[async_trait]
 pub trait ThreadStorage: Send + Sync {
@@ -118,6 +133,12 @@ pub trait ThreadStorage: Send + Sync {
     /// Updates the starred status of a thread.
     async fn set_starred(&self, id: &ThreadId, starred: bool) -> ThreadResult<()>;
 
+    /// Updates the muted status of a thread.
+    async fn set_muted(&self, id: &ThreadId, muted: bool) -> ThreadResult<()>;
+
+    /// Updates the watched status of a thread.
+    async fn set_watched(&self, id: &ThreadId, watched: bool) -> ThreadResult<()>;
+
     /// Updates the unread count of a thread.
     async fn set_unread_count(&self, id: &ThreadId, count: u32) -> ThreadResult<()>;
 
@@ -144,6 +165,105 @@ pub trait ThreadStorage: Send + Sync {
 
     /// Counts threads matching the filter.
     async fn count_threads(&self, filter: &ThreadFilter) -> ThreadResult<u32>;
+
+    /// Archives several threads, returning one result per ID in order.
+    ///
+    /// The default archives each thread in turn. Provider-backed storage
+    /// should override the bulk methods to send one batched request.
+    async fn archive_many(&self, ids: &[ThreadId]) -> Vec<ThreadResult<()>> {
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            results.push(self.archive(id).await);
+        }
+        results
+    }
+
+    /// Moves several threads to trash, returning one result per ID in order.
+    async fn trash_many(&self, ids: &[ThreadId]) -> Vec<ThreadResult<()>> {
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            results.push(self.trash(id).await);
+        }
+        results
+    }
+
+    /// Marks several threads as read, returning one result per ID in order.
+    async fn mark_read_many(&self, ids: &[ThreadId]) -> Vec<ThreadResult<()>> {
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            results.push(self.mark_read(id).await);
+        }
+        results
+    }
+
+    /// Adds a label to several threads, returning one result per ID in order.
+    async fn add_label_many(&self, ids: &[ThreadId], label_id: &LabelId) -> Vec<ThreadResult<()>> {
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            results.push(self.add_label(id, label_id).await);
+        }
+        results
+    }
+}
+
+/// A bulk operation over a selection of threads.
+#[derive(Debug, Clone, Copy)]
+enum BulkOp<'a> {
+    Archive,
+    Trash,
+    MarkRead,
+    AddLabel(&'a LabelId),
+}
+
+impl BulkOp<'_> {
+    /// Builds the undoable action for one thread from its state before the
+    /// operation.
+    fn action(&self, id: &ThreadId, before: Option<&ThreadSummary>) -> UndoableAction {
+        let ids = vec![id.clone()];
+        // Undo only returns threads to the inbox if they were in it.
+        let inbox = LabelId::from("INBOX");
+        let was_in_inbox = before.map_or(true, |s| s.labels.contains(&inbox));
+        let original_folder = was_in_inbox.then_some(inbox);
+        match self {
+            BulkOp::Archive => UndoableAction::new(
+                ActionType::Archive,
+                ActionState {
+                    thread_ids: ids,
+                    original_folder,
+                    ..Default::default()
+                },
+            ),
+            BulkOp::Trash => UndoableAction::new(
+                ActionType::Delete,
+                ActionState {
+                    thread_ids: ids,
+                    original_folder,
+                    target_folder: Some(LabelId::from("TRASH")),
+                    ..Default::default()
+                },
+            ),
+            BulkOp::MarkRead => {
+                let was_read = before.is_some_and(|s| !s.has_unread());
+                UndoableAction::new(
+                    ActionType::MarkRead,
+                    ActionState::read_state(vec![(id.clone(), was_read)]),
+                )
+            }
+            BulkOp::AddLabel(label_id) => {
+                let original = before.map(|s| s.labels.clone()).unwrap_or_default();
+                // A label the thread already had stays on undo.
+                let added = if original.contains(label_id) {
+                    vec![]
+                } else {
+                    vec![(*label_id).clone()]
+                };
+                UndoableAction::new(
+                    ActionType::AddLabels,
+                    ActionState::labels(ids, original, added),
+                )
+            }
+        }
+    }
 }
 
 /// Statistics about threads for an account.
@@ -157,15 +277,119 @@ pub struct ThreadStats {
     pub starred_threads: u32,
 }
 
+/// Contacts exchanged with at least this many emails count as frequent.
+pub const FREQUENT_CONTACT_MIN: u32 = 5;
+
+/// What is known about threads beyond their summaries, for scoring their
+/// importance.
+#[derive(Debug, Clone, Default)]
+pub struct ImportanceContext {
+    /// How much each signal counts.
+    pub weights: ImportanceWeights,
+    /// Lowercased addresses of VIP contacts.
+    pub vip_senders: HashSet<String>,
+    /// Lowercased addresses of frequent contacts.
+    pub frequent_senders: HashSet<String>,
+    /// Threads with the user in the To of a message.
+    pub addressed_to_me: HashSet<ThreadId>,
+    /// Threads the user has replied in.
+    pub replied: HashSet<ThreadId>,
+    /// Smart view each thread was classified into.
+    pub categories: HashMap<ThreadId, SmartViewType>,
+}
+
+impl ImportanceContext {
+    /// Creates a context with the given weights.
+    pub fn new(weights: ImportanceWeights) -> Self {
+        Self {
+            weights,
+            ..Default::default()
+        }
+    }
+
+    /// Records VIP and frequent senders from contacts.
+    pub fn with_contacts<'a>(mut self, contacts: impl IntoIterator<Item = &'a Contact>) -> Self {
+        for contact in contacts {
+            let email = contact.email.to_lowercase();
+            if contact.frequency >= FREQUENT_CONTACT_MIN {
+                self.frequent_senders.insert(email.clone());
+            }
+            if contact.is_vip {
+                self.vip_senders.insert(email);
+            }
+        }
+        self
+    }
+
+    /// Collects the signals for a thread.
+    pub fn signals(&self, summary: &ThreadSummary) -> ImportanceSignals {
+        let sender = summary.from.email.to_lowercase();
+        let category = self.categories.get(&summary.id);
+        ImportanceSignals {
+            vip_sender: self.vip_senders.contains(&sender),
+            frequent_sender: self.frequent_senders.contains(&sender),
+            addressed_to_me: self.addressed_to_me.contains(&summary.id),
+            replied: self.replied.contains(&summary.id),
+            needs_reply: category == Some(&SmartViewType::NeedsReply),
+            newsletter: category == Some(&SmartViewType::Newsletters),
+            ..ImportanceSignals::of(summary)
+        }
+    }
+
+    /// Scores a thread's importance, from 0 to 1.
+    pub fn score(&self, summary: &ThreadSummary) -> f32 {
+        self.weights.score(&self.signals(summary))
+    }
+}
+
 /// Service for managing email threads.
 pub struct ThreadService<S: ThreadStorage> {
     storage: S,
+    importance: Option<ImportanceContext>,
+    undo: Option<Arc<Mutex<UndoService>>>,
 }
 
 impl<S: ThreadStorage> ThreadService<S> {
     /// Creates a new thread service.
     pub fn new(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            importance: None,
+            undo: None,
+        }
+    }
+
+    /// Records archiving, trashing and label changes in `undo`, so
+    /// [`undo`](Self::undo) can reverse them.
+    pub fn with_undo(mut self, undo: Arc<Mutex<UndoService>>) -> Self {
+        self.undo = Some(undo);
+        self
+    }
+
+    /// Scores thread importance with `context`, which then also ranks
+    /// [`ThreadSort::PriorityFirst`] lists.
+    pub fn with_importance(mut self, context: ImportanceContext) -> Self {
+        self.importance = Some(context);
+        self
+    }
+
+    /// Scores a thread's importance, from 0 (unimportant) to 1.
+    ///
+    /// Without an [`ImportanceContext`] only recency counts.
+    pub fn importance(&self, summary: &ThreadSummary) -> f32 {
+        match &self.importance {
+            Some(context) => context.score(summary),
+            None => ImportanceContext::default().score(summary),
+        }
+    }
+
+    /// Ranks a [`ThreadSort::PriorityFirst`] page from storage by
+    /// [`importance`](Self::importance).
+    fn rank(&self, mut threads: Vec<ThreadSummary>, sort: ThreadSort) -> Vec<ThreadSummary> {
+        if sort == ThreadSort::PriorityFirst {
+            sort.sort(&mut threads, |t| self.importance(t));
+        }
+        threads
     }
 
     /// Gets a thread by ID with all messages.
@@ -191,7 +415,7 @@ impl<S: ThreadStorage> ThreadService<S> {
         sort: ThreadSort,
     ) -> ThreadResult<Vec<ThreadSummary>> {
         let filter = ThreadFilter::for_account(account_id);
-        self.storage.list_threads(&filter, sort).await
+        self.list_threads_filtered(&filter, sort).await
     }
 
     /// Lists threads with a custom filter.
@@ -200,7 +424,8 @@ impl<S: ThreadStorage> ThreadService<S> {
         filter: &ThreadFilter,
         sort: ThreadSort,
     ) -> ThreadResult<Vec<ThreadSummary>> {
-        self.storage.list_threads(filter, sort).await
+        let threads = self.storage.list_threads(filter, sort).await?;
+        Ok(self.rank(threads, sort))
     }
 
     /// Lists unread threads for an account.
@@ -279,32 +504,93 @@ impl<S: ThreadStorage> ThreadService<S> {
         Ok(!is_read)
     }
 
-    /// Adds a label to a thread.
+    /// Adds a label to a thread. Undoing removes it again.
     pub async fn add_label(&self, id: &ThreadId, label_id: &LabelId) -> ThreadResult<()> {
-        // Verify thread exists
-        self.get_thread_summary(id).await?;
-        self.storage.add_label(id, label_id).await
+        let before = self.get_thread_summary(id).await?;
+        self.storage.add_label(id, label_id).await?;
+        self.record(BulkOp::AddLabel(label_id).action(id, Some(&before)))
+            .await;
+        Ok(())
     }
 
-    /// Removes a label from a thread.
+    /// Removes a label from a thread. Undoing adds it back.
     pub async fn remove_label(&self, id: &ThreadId, label_id: &LabelId) -> ThreadResult<()> {
-        // Verify thread exists
-        self.get_thread_summary(id).await?;
-        self.storage.remove_label(id, label_id).await
+        let before = self.get_thread_summary(id).await?;
+        self.storage.remove_label(id, label_id).await?;
+
+        let removed = if before.labels.contains(label_id) {
+            vec![label_id.clone()]
+        } else {
+            vec![]
+        };
+        let state = ActionState::labels(vec![id.clone()], before.labels, removed);
+        self.record(UndoableAction::new(ActionType::RemoveLabels, state))
+            .await;
+        Ok(())
     }
 
-    /// Archives a thread (removes from inbox, keeps in All Mail).
+    /// Archives a thread (removes from inbox, keeps in All Mail). Undoing
+    /// returns it to the inbox.
     pub async fn archive(&self, id: &ThreadId) -> ThreadResult<()> {
+        let before = self.get_thread_summary(id).await?;
+        self.storage.archive(id).await?;
+        self.record(BulkOp::Archive.action(id, Some(&before))).await;
+        Ok(())
+    }
+
+    /// Mutes a thread and archives it.
+    ///
+    /// New mail arriving for a muted thread is archived on arrival and does
+    /// not notify, so the thread stays out of the inbox.
+    pub async fn mute(&self, id: &ThreadId) -> ThreadResult<()> {
         // Verify thread exists
         self.get_thread_summary(id).await?;
+        self.storage.set_muted(id, true).await?;
         self.storage.archive(id).await
     }
 
-    /// Moves a thread to trash.
-    pub async fn trash(&self, id: &ThreadId) -> ThreadResult<()> {
+    /// Unmutes a thread. It returns to the inbox with the next new message.
+    pub async fn unmute(&self, id: &ThreadId) -> ThreadResult<()> {
+        // Verify thread exists
+        self.get_thread_summary(id).await?;
+        self.storage.set_muted(id, false).await
+    }
+
+    /// Watches a thread, so every new message in it notifies at high
+    /// priority, however unimportant the thread looks.
+    ///
+    /// Watching a muted thread overrides the mute: new mail lands in the
+    /// inbox and notifies.
+    pub async fn watch(&self, id: &ThreadId) -> ThreadResult<()> {
+        let thread = self.get_thread_summary(id).await?;
+        if thread.muted {
+            tracing::warn!(thread_id = %id, "Watching a muted thread; new mail will notify");
+        }
+        self.storage.set_watched(id, true).await
+    }
+
+    /// Stops watching a thread. New messages notify as usual.
+    pub async fn unwatch(&self, id: &ThreadId) -> ThreadResult<()> {
         // Verify thread exists
         self.get_thread_summary(id).await?;
-        self.storage.trash(id).await
+        self.storage.set_watched(id, false).await
+    }
+
+    /// Lists muted threads for an account.
+    pub async fn list_muted(&self, account_id: AccountId) -> ThreadResult<Vec<ThreadSummary>> {
+        let filter = ThreadFilter::for_account(account_id).with_muted(true);
+        self.storage
+            .list_threads(&filter, ThreadSort::DateDesc)
+            .await
+    }
+
+    /// Moves a thread to trash. Undoing takes it out of the trash and back
+    /// to the inbox if it was there.
+    pub async fn trash(&self, id: &ThreadId) -> ThreadResult<()> {
+        let before = self.get_thread_summary(id).await?;
+        self.storage.trash(id).await?;
+        self.record(BulkOp::Trash.action(id, Some(&before))).await;
+        Ok(())
     }
 
     /// Permanently deletes a thread.
@@ -314,6 +600,123 @@ impl<S: ThreadStorage> ThreadService<S> {
         self.storage.delete(id).await
     }
 
+    /// Archives the selected threads.
+    ///
+    /// Existing threads are archived in a single storage call. Returns one
+    /// [`ActionResult`] per ID, in order, so partial failures (including
+    /// unknown IDs) can be reported.
+    pub async fn archive_many(&self, ids: &[ThreadId]) -> Vec<ActionResult> {
+        self.bulk(ids, BulkOp::Archive).await
+    }
+
+    /// Moves the selected threads to trash. See [`archive_many`](Self::archive_many).
+    pub async fn trash_many(&self, ids: &[ThreadId]) -> Vec<ActionResult> {
+        self.bulk(ids, BulkOp::Trash).await
+    }
+
+    /// Marks the selected threads as read. See [`archive_many`](Self::archive_many).
+    pub async fn mark_read_many(&self, ids: &[ThreadId]) -> Vec<ActionResult> {
+        self.bulk(ids, BulkOp::MarkRead).await
+    }
+
+    /// Adds a label to the selected threads. See [`archive_many`](Self::archive_many).
+    pub async fn add_label_many(&self, ids: &[ThreadId], label_id: &LabelId) -> Vec<ActionResult> {
+        self.bulk(ids, BulkOp::AddLabel(label_id)).await
+    }
+
+    /// Runs a bulk operation, sending only existing threads to storage.
+    async fn bulk(&self, ids: &[ThreadId], op: BulkOp<'_>) -> Vec<ActionResult> {
+        let mut before = Vec::with_capacity(ids.len());
+        for id in ids {
+            before.push(self.storage.get_thread_summary(id).await);
+        }
+
+        let found: Vec<ThreadId> = ids
+            .iter()
+            .zip(&before)
+            .filter(|(_, summary)| matches!(summary, Ok(Some(_))))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut outcomes = if found.is_empty() {
+            Vec::new()
+        } else {
+            match op {
+                BulkOp::Archive => self.storage.archive_many(&found).await,
+                BulkOp::Trash => self.storage.trash_many(&found).await,
+                BulkOp::MarkRead => self.storage.mark_read_many(&found).await,
+                BulkOp::AddLabel(label_id) => self.storage.add_label_many(&found, label_id).await,
+            }
+        }
+        .into_iter();
+
+        ids.iter()
+            .zip(before)
+            .map(|(id, summary)| {
+                let action = op.action(id, summary.as_ref().ok().and_then(Option::as_ref));
+                let outcome = match summary {
+                    Ok(Some(_)) => outcomes.next().unwrap_or_else(|| {
+                        Err(ThreadError::Storage(format!("no result for thread {}", id)))
+                    }),
+                    Ok(None) => Err(ThreadError::NotFound(id.to_string())),
+                    Err(e) => Err(e),
+                };
+                match outcome {
+                    Ok(()) => ActionResult::success(action),
+                    Err(e) => ActionResult::failure(action, e.to_string()),
+                }
+            })
+            .collect()
+    }
+
+    /// Reverses the most recent recorded action by applying its inverse
+    /// through storage.
+    ///
+    /// Storage that syncs with a provider must push the inverse like any
+    /// other change, as [`ThreadStore`](crate::client::ThreadStore) does, or
+    /// the next sync brings the undone change back.
+    ///
+    /// Returns the undone action, or `None` when there is nothing to undo
+    /// within the undo window.
+    pub async fn undo(&self) -> ThreadResult<Option<UndoableAction>> {
+        let Some(undo) = &self.undo else {
+            return Ok(None);
+        };
+        let Some(action) = undo.lock().await.pop_undo() else {
+            return Ok(None);
+        };
+        for (id, update) in action.inverse() {
+            self.apply_update(&id, update).await?;
+        }
+        Ok(Some(action))
+    }
+
+    /// Records an action for undo, if undo is configured.
+    async fn record(&self, action: UndoableAction) {
+        if let Some(undo) = &self.undo {
+            undo.lock().await.record(action);
+        }
+    }
+
+    /// Applies a metadata update through the storage operations.
+    async fn apply_update(&self, id: &ThreadId, update: ThreadMetadataUpdate) -> ThreadResult<()> {
+        for label_id in &update.remove_labels {
+            self.storage.remove_label(id, label_id).await?;
+        }
+        for label_id in &update.add_labels {
+            self.storage.add_label(id, label_id).await?;
+        }
+        match update.is_read {
+            Some(true) => self.storage.mark_read(id).await?,
+            Some(false) => self.storage.mark_unread(id).await?,
+            None => {}
+        }
+        if let Some(starred) = update.is_starred {
+            self.storage.set_starred(id, starred).await?;
+        }
+        Ok(())
+    }
+
     /// Gets thread statistics for an account.
     pub async fn get_stats(&self, account_id: AccountId) -> ThreadResult<ThreadStats> {
         let total_filter = ThreadFilter::for_account(account_id.clone());
@@ -377,6 +780,8 @@ mod tests {
             unread_count: 1,
             is_starred: false,
             labels: vec![LabelId::from("INBOX")],
+            muted: false,
+            watched: false,
         }
     }
 
@@ -406,7 +811,7 @@ mod tests {
         async fn list_threads(
             &self,
             filter: &ThreadFilter,
-            _sort: ThreadSort,
+            sort: ThreadSort,
         ) -> ThreadResult<Vec<ThreadSummary>> {
             let threads = self.threads.lock().unwrap();
             let mut result: Vec<_> = threads
@@ -423,6 +828,9 @@ mod tests {
                     if filter.starred_only && !t.is_starred {
                         return false;
                     }
+                    if filter.muted.is_some_and(|muted| t.muted != muted) {
+                        return false;
+                    }
                     if let Some(ref label_id) = filter.label_id {
                         if !t.labels.contains(label_id) {
                             return false;
@@ -432,6 +840,7 @@ mod tests {
                 })
                 .cloned()
                 .collect();
+            sort.sort(&mut result, |t| ImportanceContext::default().score(t));
 
             // Apply pagination
             if let Some(offset) = filter.offset {
@@ -454,6 +863,26 @@ mod tests {
             }
         }
 
+        async fn set_muted(&self, id: &ThreadId, muted: bool) -> ThreadResult<()> {
+            let mut threads = self.threads.lock().unwrap();
+            if let Some(thread) = threads.get_mut(id) {
+                thread.muted = muted;
+                Ok(())
+            } else {
+                Err(ThreadError::NotFound(id.to_string()))
+            }
+        }
+
+        async fn set_watched(&self, id: &ThreadId, watched: bool) -> ThreadResult<()> {
+            let mut threads = self.threads.lock().unwrap();
+            if let Some(thread) = threads.get_mut(id) {
+                thread.watched = watched;
+                Ok(())
+            } else {
+                Err(ThreadError::NotFound(id.to_string()))
+            }
+        }
+
         async fn set_unread_count(&self, id: &ThreadId, count: u32) -> ThreadResult<()> {
             let mut threads = self.threads.lock().unwrap();
             if let Some(thread) = threads.get_mut(id) {
@@ -517,6 +946,17 @@ mod tests {
         }
     }
 
+    fn storage_with_threads(count: usize) -> (MockStorage, Vec<ThreadId>) {
+        let mut storage = MockStorage::new();
+        let mut ids = Vec::new();
+        for i in 0..count {
+            let id = format!("thread-{}", i);
+            storage = storage.with_thread(make_summary(&id, "account-1"));
+            ids.push(ThreadId::from(id));
+        }
+        (storage, ids)
+    }
+
     #[tokio::test]
     async fn get_thread_not_found() {
         let storage = MockStorage::new();
@@ -714,6 +1154,223 @@ mod tests {
         assert!(!thread.labels.contains(&LabelId::from("INBOX")));
     }
 
+    #[tokio::test]
+    async fn mute_archives_and_unmute_clears_flag() {
+        let storage = MockStorage::new()
+            .with_thread(make_summary("thread-1", "account-1"))
+            .with_thread(make_summary("thread-2", "account-1"));
+        let service = ThreadService::new(storage);
+
+        let id = ThreadId::from("thread-1");
+        service.mute(&id).await.unwrap();
+
+        let thread = service.get_thread_summary(&id).await.unwrap();
+        assert!(thread.muted);
+        assert!(!thread.labels.contains(&LabelId::from("INBOX")));
+
+        let muted = service
+            .list_muted(AccountId::from("account-1"))
+            .await
+            .unwrap();
+        assert_eq!(muted.len(), 1);
+        assert_eq!(muted[0].id, id);
+
+        let unmuted = ThreadFilter::for_account(AccountId::from("account-1")).with_muted(false);
+        let threads = service
+            .list_threads_filtered(&unmuted, ThreadSort::DateDesc)
+            .await
+            .unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].id, ThreadId::from("thread-2"));
+
+        service.unmute(&id).await.unwrap();
+        assert!(!service.get_thread_summary(&id).await.unwrap().muted);
+    }
+
+    #[tokio::test]
+    async fn watch_sets_and_unwatch_clears_flag() {
+        let storage = MockStorage::new().with_thread(make_summary("thread-1", "account-1"));
+        let service = ThreadService::new(storage);
+        let id = ThreadId::from("thread-1");
+
+        service.watch(&id).await.unwrap();
+        let thread = service.get_thread_summary(&id).await.unwrap();
+        assert!(thread.watched);
+        // Unlike muting, watching leaves the thread where it is.
+        assert!(thread.labels.contains(&LabelId::from("INBOX")));
+
+        service.unwatch(&id).await.unwrap();
+        assert!(!service.get_thread_summary(&id).await.unwrap().watched);
+
+        assert!(matches!(
+            service.watch(&ThreadId::from("missing")).await,
+            Err(ThreadError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn direct_mail_from_frequent_contact_outranks_cc_newsletter() {
+        let mut direct = make_summary("direct", "account-1");
+        direct.from = Address::new("Alice@example.com");
+        direct.last_message_date = Utc::now() - chrono::Duration::hours(6);
+        let mut newsletter = make_summary("newsletter", "account-1");
+        newsletter.from = Address::new("news@example.com");
+
+        let mut alice = Contact::new("alice@example.com");
+        alice.frequency = FREQUENT_CONTACT_MIN;
+        let mut context = ImportanceContext::default().with_contacts([&alice]);
+        context.addressed_to_me.insert(direct.id.clone());
+        context
+            .categories
+            .insert(newsletter.id.clone(), SmartViewType::Newsletters);
+        let service = ThreadService::new(MockStorage::new()).with_importance(context);
+
+        let signals = service.importance.as_ref().unwrap().signals(&direct);
+        assert!(signals.frequent_sender && signals.addressed_to_me && !signals.vip_sender);
+        assert!(service.importance(&direct) > service.importance(&newsletter));
+    }
+
+    #[tokio::test]
+    async fn priority_sort_ranks_by_importance() {
+        let mut vip = make_summary("vip", "account-1");
+        vip.from = Address::new("boss@example.com");
+        vip.last_message_date = Utc::now() - chrono::Duration::days(3);
+        let storage = MockStorage::new()
+            .with_thread(make_summary("recent", "account-1"))
+            .with_thread(vip);
+        let mut boss = Contact::new("boss@example.com");
+        boss.is_vip = true;
+        let service = ThreadService::new(storage)
+            .with_importance(ImportanceContext::default().with_contacts([&boss]));
+
+        let threads = service
+            .list_threads(AccountId::from("account-1"), ThreadSort::PriorityFirst)
+            .await
+            .unwrap();
+        let ids: Vec<_> = threads.into_iter().map(|t| t.id.0).collect();
+        assert_eq!(ids, ["vip", "recent"]);
+    }
+
+    #[tokio::test]
+    async fn archive_many_archives_every_thread() {
+        let (storage, ids) = storage_with_threads(5);
+        let service = ThreadService::new(storage);
+
+        let results = service.archive_many(&ids).await;
+
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.success));
+        for (result, id) in results.iter().zip(&ids) {
+            assert_eq!(result.action.action_type, ActionType::Archive);
+            assert_eq!(result.action.before_state.thread_ids, vec![id.clone()]);
+        }
+        for id in &ids {
+            let thread = service.get_thread_summary(id).await.unwrap();
+            assert!(!thread.labels.contains(&LabelId::from("INBOX")));
+        }
+    }
+
+    #[tokio::test]
+    async fn mark_read_and_label_many_update_every_thread() {
+        let (storage, ids) = storage_with_threads(3);
+        let service = ThreadService::new(storage);
+        let label = LabelId::from("Work");
+
+        let read = service.mark_read_many(&ids).await;
+        let labeled = service.add_label_many(&ids, &label).await;
+
+        assert!(read.iter().chain(&labeled).all(|r| r.success));
+        for id in &ids {
+            let thread = service.get_thread_summary(id).await.unwrap();
+            assert_eq!(thread.unread_count, 0);
+            assert!(thread.labels.contains(&label));
+        }
+    }
+
+    #[tokio::test]
+    async fn bulk_action_reports_partial_failures_in_order() {
+        let (storage, mut ids) = storage_with_threads(2);
+        ids.insert(1, ThreadId::from("missing"));
+        let service = ThreadService::new(storage);
+
+        let results = service.trash_many(&ids).await;
+
+        let outcomes: Vec<bool> = results.iter().map(|r| r.success).collect();
+        assert_eq!(outcomes, vec![true, false, true]);
+        assert!(results[1].error.as_ref().unwrap().contains("missing"));
+        assert_eq!(
+            results[1].action.before_state.thread_ids,
+            vec![ids[1].clone()]
+        );
+    }
+
+    fn undoable(storage: MockStorage) -> ThreadService<MockStorage> {
+        let undo = Arc::new(tokio::sync::Mutex::new(UndoService::new()));
+        ThreadService::new(storage).with_undo(undo)
+    }
+
+    /// Labels and flags an action may change, with labels sorted.
+    fn undo_state(summary: ThreadSummary) -> (Vec<LabelId>, u32, bool) {
+        let mut labels = summary.labels;
+        labels.sort_by(|a, b| a.0.cmp(&b.0));
+        (labels, summary.unread_count, summary.is_starred)
+    }
+
+    #[tokio::test]
+    async fn undoing_each_action_restores_the_thread() {
+        let mut summary = make_summary("thread-1", "account-1");
+        summary.labels.push(LabelId::from("Work"));
+        let service = undoable(MockStorage::new().with_thread(summary));
+        let id = ThreadId::from("thread-1");
+        let original = undo_state(service.get_thread_summary(&id).await.unwrap());
+
+        let work = LabelId::from("Work");
+        let receipts = LabelId::from("Receipts");
+        let actions = [
+            ActionType::Archive,
+            ActionType::Delete,
+            ActionType::AddLabels,
+            ActionType::RemoveLabels,
+        ];
+        for action_type in actions {
+            match action_type {
+                ActionType::Archive => service.archive(&id).await.unwrap(),
+                ActionType::Delete => service.trash(&id).await.unwrap(),
+                ActionType::AddLabels => service.add_label(&id, &receipts).await.unwrap(),
+                _ => service.remove_label(&id, &work).await.unwrap(),
+            }
+            let changed = undo_state(service.get_thread_summary(&id).await.unwrap());
+            assert_ne!(changed, original, "{:?} changed nothing", action_type);
+
+            let undone = service.undo().await.unwrap().unwrap();
+            assert_eq!(undone.action_type, action_type);
+            let restored = undo_state(service.get_thread_summary(&id).await.unwrap());
+            assert_eq!(restored, original, "undoing {:?}", action_type);
+        }
+        assert!(service.undo().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn undo_keeps_state_the_action_did_not_change() {
+        let mut summary = make_summary("thread-1", "account-1");
+        summary.labels = vec![LabelId::from("Work")];
+        let service = undoable(MockStorage::new().with_thread(summary));
+        let id = ThreadId::from("thread-1");
+
+        // Already archived and already labeled: undo must not add INBOX or
+        // strip the label.
+        service.archive(&id).await.unwrap();
+        service.undo().await.unwrap();
+        service
+            .add_label(&id, &LabelId::from("Work"))
+            .await
+            .unwrap();
+        service.undo().await.unwrap();
+
+        let thread = service.get_thread_summary(&id).await.unwrap();
+        assert_eq!(thread.labels, vec![LabelId::from("Work")]);
+    }
+
     #[tokio::test]
     async fn trash_thread() {
         let summary = make_summary("thread-1", "account-1");
@@ -789,6 +1446,7 @@ mod tests {
         let filter = ThreadFilter::for_account(AccountId::from("acc-1"))
             .unread()
             .starred()
+            .with_muted(false)
             .with_label(LabelId::from("INBOX"))
             .limit(10)
             .offset(5);
@@ -796,6 +1454,7 @@ mod tests {
         assert_eq!(filter.account_id, Some(AccountId::from("acc-1")));
         assert!(filter.unread_only);
         assert!(filter.starred_only);
+        assert_eq!(filter.muted, Some(false));
         assert_eq!(filter.label_id, Some(LabelId::from("INBOX")));
         assert_eq!(filter.limit, Some(10));
         assert_eq!(filter.offset, Some(5));
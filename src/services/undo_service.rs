@@ -15,6 +15,7 @@ use std::time::{Duration, Instant};
 use chrono::{DateTime, Utc};
 
 use crate::domain::{LabelId, ThreadId};
+use crate::services::email_service::ThreadMetadataUpdate;
 
 /// Maximum number of actions to keep in history.
 const MAX_HISTORY_SIZE: usize = 100;
@@ -220,6 +221,78 @@ impl UndoableAction {
         self.action_type.is_undoable() && self.is_within_window(DEFAULT_UNDO_WINDOW)
     }
 
+    /// Returns the metadata updates that reverse this action.
+    ///
+    /// Used both for user-initiated undo and for rolling back optimistic
+    /// changes the provider rejected. Actions that cannot be undone yield no
+    /// updates.
+    pub fn inverse(&self) -> Vec<(ThreadId, ThreadMetadataUpdate)> {
+        let state = &self.before_state;
+        match self.action_type {
+            ActionType::MarkRead | ActionType::MarkUnread => state
+                .read_states
+                .iter()
+                .map(|(id, was_read)| {
+                    let update = ThreadMetadataUpdate {
+                        is_read: Some(*was_read),
+                        ..Default::default()
+                    };
+                    (id.clone(), update)
+                })
+                .collect(),
+            ActionType::Star | ActionType::Unstar => state
+                .starred_states
+                .iter()
+                .map(|(id, was_starred)| {
+                    let update = ThreadMetadataUpdate {
+                        is_starred: Some(*was_starred),
+                        ..Default::default()
+                    };
+                    (id.clone(), update)
+                })
+                .collect(),
+            ActionType::AddLabels => self.for_each_thread(ThreadMetadataUpdate {
+                remove_labels: state.affected_labels.clone(),
+                ..Default::default()
+            }),
+            ActionType::RemoveLabels => self.for_each_thread(ThreadMetadataUpdate {
+                add_labels: state.affected_labels.clone(),
+                ..Default::default()
+            }),
+            ActionType::Archive
+            | ActionType::Delete
+            | ActionType::Move
+            | ActionType::ReportSpam
+            | ActionType::NotSpam => self.for_each_thread(ThreadMetadataUpdate {
+                add_labels: state.original_folder.iter().cloned().collect(),
+                remove_labels: state.target_folder.iter().cloned().collect(),
+                ..Default::default()
+            }),
+            ActionType::Snooze => self.for_each_thread(ThreadMetadataUpdate {
+                add_labels: vec![state
+                    .original_folder
+                    .clone()
+                    .unwrap_or_else(|| LabelId::from("INBOX"))],
+                remove_labels: vec![LabelId::from("heap/Snoozed")],
+                snooze_until: Some(None),
+                ..Default::default()
+            }),
+            ActionType::PermanentDelete | ActionType::Send => Vec::new(),
+        }
+    }
+
+    /// Applies the same update to every affected thread.
+    fn for_each_thread(
+        &self,
+        update: ThreadMetadataUpdate,
+    ) -> Vec<(ThreadId, ThreadMetadataUpdate)> {
+        self.before_state
+            .thread_ids
+            .iter()
+            .map(|id| (id.clone(), update.clone()))
+            .collect()
+    }
+
     /// Returns time remaining in the undo window.
     pub fn time_remaining(&self) -> Duration {
         let elapsed = self.performed_at.elapsed();
@@ -530,6 +603,35 @@ mod tests {
         assert!(action.description.contains("archived"));
     }
 
+    #[test]
+    fn inverse_of_star_restores_previous_state() {
+        let state = ActionState::starred_state(vec![(make_thread_id("t1"), false)]);
+        let action = UndoableAction::new(ActionType::Star, state);
+
+        let inverse = action.inverse();
+        assert_eq!(inverse.len(), 1);
+        assert_eq!(inverse[0].0, make_thread_id("t1"));
+        assert_eq!(inverse[0].1.is_starred, Some(false));
+    }
+
+    #[test]
+    fn inverse_of_delete_moves_back_to_original_folder() {
+        let state = ActionState::folder_move(
+            vec![make_thread_id("t1"), make_thread_id("t2")],
+            make_label_id("INBOX"),
+            make_label_id("TRASH"),
+        );
+        let action = UndoableAction::new(ActionType::Delete, state);
+
+        let inverse = action.inverse();
+        assert_eq!(inverse.len(), 2);
+        assert_eq!(inverse[1].1.add_labels, vec![make_label_id("INBOX")]);
+        assert_eq!(inverse[1].1.remove_labels, vec![make_label_id("TRASH")]);
+
+        let send = UndoableAction::new(ActionType::Send, ActionState::default());
+        assert!(send.inverse().is_empty());
+    }
+
     #[test]
     fn undo_service_basic() {
         let mut service = UndoService::new();
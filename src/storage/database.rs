@@ -3,12 +3,17 @@
 //! Provides a thread-safe wrapper around rusqlite for async operations.
 
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use rusqlite::Connection;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, MutexGuard};
 
+use super::queries::storage_stats::StorageStats;
 use super::schema;
 
 /// Errors that can occur during database operations.
@@ -25,18 +30,125 @@ pub enum DatabaseError {
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
 }
 
 /// Result type for database operations.
 pub type Result<T> = std::result::Result<T, DatabaseError>;
 
+/// How to sync writes to disk; see SQLite's `PRAGMA synchronous`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Synchronous {
+    /// Leave syncing to the OS; fastest, but a power loss can corrupt.
+    Off,
+    /// Sync at checkpoints; safe with WAL, may lose the latest commits.
+    Normal,
+    /// Sync on every commit.
+    Full,
+}
+
+impl Synchronous {
+    fn pragma_value(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Normal => "NORMAL",
+            Self::Full => "FULL",
+        }
+    }
+}
+
+/// Connection tuning for file databases.
+///
+/// The defaults suit the app; these are exposed for advanced users in
+/// the settings file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseOptions {
+    /// How long to wait for a lock held by another connection before
+    /// failing with "database is locked", in milliseconds.
+    pub busy_timeout_ms: u64,
+    /// Number of read-only connections kept alongside the writer.
+    pub readers: usize,
+    /// How to sync writes to disk.
+    pub synchronous: Synchronous,
+    /// Page cache size per connection, in KiB.
+    pub cache_size_kib: u32,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            busy_timeout_ms: 5_000,
+            readers: 4,
+            synchronous: Synchronous::Normal,
+            cache_size_kib: 8_192,
+        }
+    }
+}
+
+impl DatabaseOptions {
+    fn busy_timeout(&self) -> Duration {
+        Duration::from_millis(self.busy_timeout_ms)
+    }
+
+    /// Applies the pragmas shared by the writer and readers.
+    fn configure(&self, conn: &Connection) -> Result<()> {
+        conn.busy_timeout(self.busy_timeout())?;
+        conn.pragma_update(None, "cache_size", -i64::from(self.cache_size_kib))?;
+        Ok(())
+    }
+}
+
+/// Read-only connections to a WAL database, so reads don't queue behind
+/// writes.
+struct ReaderPool {
+    connections: Vec<Mutex<Connection>>,
+    next: AtomicUsize,
+}
+
+impl ReaderPool {
+    fn open(path: &Path, options: &DatabaseOptions) -> Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let connections = (0..options.readers)
+            .map(|_| {
+                let conn = Connection::open_with_flags(path, flags)?;
+                options.configure(&conn)?;
+                Ok(Mutex::new(conn))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            connections,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Takes an idle reader, or waits for the next in turn if all are busy.
+    fn acquire(&self) -> MutexGuard<'_, Connection> {
+        for conn in &self.connections {
+            if let Ok(guard) = conn.try_lock() {
+                return guard;
+            }
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        self.connections[index].blocking_lock()
+    }
+}
+
 /// Thread-safe database connection wrapper.
 ///
-/// Uses a Mutex to ensure only one operation accesses the connection at a time.
+/// Writes go through a single connection behind a Mutex. File databases run
+/// in WAL mode with a pool of read-only connections, so reads through
+/// [`with_reader`](Self::with_reader) see the last committed state without
+/// waiting on the writer.
 /// All operations are run via `spawn_blocking` to avoid blocking the async runtime.
 #[derive(Clone)]
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
+    readers: Option<Arc<ReaderPool>>,
 }
 
 impl Database {
@@ -44,27 +156,52 @@ impl Database {
     ///
     /// Runs migrations to ensure the schema is up to date.
     pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, DatabaseOptions::default()).await
+    }
+
+    /// Opens a database at the given path with tuned connection options.
+    pub async fn open_with_options(
+        path: impl AsRef<Path>,
+        options: DatabaseOptions,
+    ) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
 
-        let conn = tokio::task::spawn_blocking(move || -> Result<Connection> {
-            let conn = Connection::open(&path)?;
-            conn.execute_batch("PRAGMA foreign_keys = ON;")?;
-            conn.execute_batch("PRAGMA journal_mode = WAL;")?;
-            Ok(conn)
-        })
-        .await
-        .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))??;
+        let conn = {
+            let path = path.clone();
+            let options = options.clone();
+            tokio::task::spawn_blocking(move || -> Result<Connection> {
+                let conn = Connection::open(&path)?;
+                conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+                conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+                conn.pragma_update(None, "synchronous", options.synchronous.pragma_value())?;
+                options.configure(&conn)?;
+                Ok(conn)
+            })
+            .await
+            .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))??
+        };
 
-        let db = Self {
+        let mut db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers: None,
         };
 
         db.run_migrations().await?;
 
+        // Readers open read-only, so the file must exist with its schema first.
+        if options.readers > 0 {
+            let pool = tokio::task::spawn_blocking(move || ReaderPool::open(&path, &options))
+                .await
+                .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))??;
+            db.readers = Some(Arc::new(pool));
+        }
+
         Ok(db)
     }
 
     /// Opens an in-memory database for testing.
+    ///
+    /// An in-memory database has no readers; reads share the writer.
     pub async fn open_in_memory() -> Result<Self> {
         let conn = tokio::task::spawn_blocking(|| -> Result<Connection> {
             let conn = Connection::open_in_memory()?;
@@ -76,6 +213,7 @@ impl Database {
 
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers: None,
         };
 
         db.run_migrations().await?;
@@ -90,9 +228,13 @@ impl Database {
         tokio::task::spawn_blocking(move || -> Result<()> {
             let conn = conn.blocking_lock();
 
+            let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+            add_missing_columns(&conn, version)?;
+            rebuild_tables(&conn, version)?;
             for migration in schema::all_migrations() {
                 conn.execute_batch(migration)?;
             }
+            conn.pragma_update(None, "user_version", schema::SCHEMA_VERSION)?;
 
             Ok(())
         })
@@ -118,6 +260,27 @@ impl Database {
         .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))?
     }
 
+    /// Executes a read-only function on a reader connection.
+    ///
+    /// Falls back to the writer when the database has no readers. Writes
+    /// through a reader fail, so use [`with_conn`](Self::with_conn) for them.
+    pub async fn with_reader<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let Some(readers) = self.readers.clone() else {
+            return self.with_conn(f).await;
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let conn = readers.acquire();
+            f(&conn)
+        })
+        .await
+        .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))?
+    }
+
     /// Executes a function with mutable access to the database connection.
     ///
     /// Use this for transactions or operations that require mutable access.
@@ -156,6 +319,130 @@ impl Database {
         .await
         .map_err(|e| DatabaseError::MigrationFailed(e.to_string()))?
     }
+
+    /// Writes the write-ahead log back into the database file and truncates
+    /// it, so a copy of the file alone is complete.
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reports disk usage by account and thread, and of the vector store.
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        super::queries::storage_stats::get(self).await
+    }
+
+    /// Copies the database to a file at `path`, replacing its contents.
+    ///
+    /// Uses SQLite's online backup API from a reader, so the copy is a
+    /// consistent snapshot taken without holding up writes.
+    pub async fn backup_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+
+        self.with_reader(move |conn| {
+            let mut dest = Connection::open(&path)?;
+            Backup::new(conn, &mut dest)?.run_to_completion(-1, Duration::ZERO, None)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Replaces the database's contents with the backup at `path`.
+    ///
+    /// The backup must pass an integrity check and have the current schema
+    /// version. It's copied in a single step, so on failure the database is
+    /// left as it was.
+    pub async fn restore_from(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+
+        self.with_conn_mut(move |conn| {
+            if !path.exists() {
+                return Err(DatabaseError::InvalidBackup(format!(
+                    "{} does not exist",
+                    path.display()
+                )));
+            }
+            let source = Connection::open(&path)?;
+            check_backup(&source)?;
+            Backup::new(&source, conn)?.run_to_completion(-1, Duration::ZERO, None)?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Checks that a backup is intact and was made with the current schema.
+fn check_backup(conn: &Connection) -> Result<()> {
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| DatabaseError::InvalidBackup(e.to_string()))?;
+    if integrity != "ok" {
+        return Err(DatabaseError::InvalidBackup(integrity));
+    }
+
+    let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version != schema::SCHEMA_VERSION {
+        return Err(DatabaseError::InvalidBackup(format!(
+            "schema version {} does not match {}",
+            version,
+            schema::SCHEMA_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// Adds the columns introduced after `version` to the tables that already
+/// exist.
+///
+/// Runs before the creation statements, whose indexes and triggers may refer
+/// to the new columns. Columns already present are skipped, so a database
+/// whose version lags its tables is upgraded safely.
+fn add_missing_columns(conn: &Connection, version: i32) -> Result<()> {
+    for added in schema::ADDED_COLUMNS
+        .iter()
+        .filter(|added| added.version > version)
+    {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", added.table))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        // A missing table is created whole by its creation statement.
+        if columns.is_empty() || columns.iter().any(|c| c == added.column) {
+            continue;
+        }
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            added.table, added.column, added.definition
+        ))?;
+    }
+    Ok(())
+}
+
+/// Rebuilds the existing tables whose shape changed after `version`.
+///
+/// Like [`add_missing_columns`], runs before the creation statements, which
+/// restore the indexes dropped with the old tables.
+fn rebuild_tables(conn: &Connection, version: i32) -> Result<()> {
+    for rebuild in schema::TABLE_REBUILDS
+        .iter()
+        .filter(|rebuild| rebuild.version > version)
+    {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [rebuild.table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(rebuild.sql)?;
+            tx.commit()?;
+        }
+    }
+    Ok(())
 }
 
 impl std::fmt::Debug for Database {
@@ -295,4 +582,209 @@ mod tests {
 
         assert_eq!(value, "clone_value");
     }
+
+    #[tokio::test]
+    async fn open_adds_columns_missing_from_older_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heap.db");
+        {
+            // Create each changed table as it was before its added columns.
+            let conn = Connection::open(&path).unwrap();
+            for create in schema::all_migrations() {
+                let mut create = create.to_string();
+                for added in schema::ADDED_COLUMNS {
+                    if create.contains(&format!("EXISTS {} (", added.table)) {
+                        let line = format!("\n    {} {},", added.column, added.definition);
+                        create = create.replace(&line, "");
+                    }
+                }
+                if schema::ADDED_COLUMNS
+                    .iter()
+                    .any(|added| create.contains(&format!("EXISTS {} (", added.table)))
+                {
+                    conn.execute_batch(&create).unwrap();
+                }
+            }
+        }
+
+        let db = Database::open(&path).await.unwrap();
+
+        let (version, missing) = db
+            .with_conn(|conn| {
+                let version: i32 =
+                    conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+                let mut missing = Vec::new();
+                for added in schema::ADDED_COLUMNS {
+                    let sql = format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1",
+                        added.table
+                    );
+                    let count: i64 = conn.query_row(&sql, [added.column], |row| row.get(0))?;
+                    if count == 0 {
+                        missing.push(added.column);
+                    }
+                }
+                Ok((version, missing))
+            })
+            .await
+            .unwrap();
+        assert_eq!(version, schema::SCHEMA_VERSION);
+        assert!(missing.is_empty(), "missing columns: {:?}", missing);
+    }
+
+    #[tokio::test]
+    async fn open_rebuilds_snoozed_to_allow_snoozes_without_a_wake_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heap.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE snoozed (
+                    id TEXT PRIMARY KEY,
+                    thread_id TEXT NOT NULL,
+                    snooze_until TEXT NOT NULL,
+                    created_at TEXT NOT NULL
+                );
+                INSERT INTO snoozed VALUES ('s1', 't1', '2025-02-01', '2025-01-01');
+                PRAGMA user_version = 3;
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&path).await.unwrap();
+
+        let until: Vec<Option<String>> = db
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "PRAGMA foreign_keys = OFF;
+                     INSERT INTO snoozed VALUES ('s2', 't2', NULL, '2025-01-01');",
+                )?;
+                let mut stmt = conn.prepare("SELECT snooze_until FROM snoozed ORDER BY id")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(until, vec![Some("2025-02-01".to_string()), None]);
+    }
+
+    #[tokio::test]
+    async fn readers_see_committed_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("heap.db")).await.unwrap();
+
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)",
+                ["theme", "dark", "2025-01-01T00:00:00Z"],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let value: String = db
+            .with_reader(|conn| {
+                let value = conn.query_row(
+                    "SELECT value FROM settings WHERE key = 'theme'",
+                    [],
+                    |row| row.get(0),
+                )?;
+                Ok(value)
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, "dark");
+
+        let write = db
+            .with_reader(|conn| {
+                conn.execute("DELETE FROM settings", [])?;
+                Ok(())
+            })
+            .await;
+        assert!(write.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_readers_and_writers_do_not_hit_locks() {
+        const ITERATIONS: usize = 200;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heap.db");
+        let db = Database::open(&path).await.unwrap();
+        // A second handle on the same file contends for the lock the way
+        // another process would.
+        let other = Database::open(&path).await.unwrap();
+
+        let mut tasks = Vec::new();
+        for (writer, db) in [("a", db.clone()), ("b", other.clone())] {
+            tasks.push(tokio::spawn(async move {
+                for i in 0..ITERATIONS {
+                    let key = format!("{}-{}", writer, i);
+                    db.with_conn(move |conn| {
+                        conn.execute(
+                            "INSERT INTO settings (key, value, updated_at)
+                             VALUES (?, 'value', '2025-01-01T00:00:00Z')",
+                            [key],
+                        )?;
+                        Ok(())
+                    })
+                    .await?;
+                }
+                Ok::<_, DatabaseError>(())
+            }));
+        }
+        for _ in 0..4 {
+            let db = db.clone();
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..ITERATIONS {
+                    db.with_reader(|conn| {
+                        let count: i64 =
+                            conn.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))?;
+                        Ok(count)
+                    })
+                    .await?;
+                }
+                Ok(())
+            }));
+        }
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let count: i64 = db
+            .with_reader(|conn| {
+                let count =
+                    conn.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))?;
+                Ok(count)
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 2 * ITERATIONS as i64);
+    }
+
+    #[tokio::test]
+    async fn checkpoint_truncates_the_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heap.db");
+        let db = Database::open(&path).await.unwrap();
+
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)",
+                ["theme", "dark", "2025-01-01T00:00:00Z"],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        let wal = dir.path().join("heap.db-wal");
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+
+        db.checkpoint().await.unwrap();
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+    }
 }
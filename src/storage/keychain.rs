@@ -1,9 +1,31 @@
 //! Keychain access for secure credential storage.
 //!
 //! Wraps the keyring crate to provide OS-native credential storage.
+//!
+//! # Platform notes
+//!
+//! - **macOS**: credentials are generic password items in the login Keychain,
+//!   keyed by service and account. The first access from a new binary may
+//!   trigger a user prompt, and items survive app reinstalls.
+//! - **Linux**: credentials go through the Secret Service D-Bus API
+//!   (GNOME Keyring, KWallet). A running, unlocked secret-service daemon is
+//!   required; headless sessions typically fail with a platform error.
+//! - **Windows**: credentials live in the Credential Manager as generic
+//!   credentials targeting `{key}.{service}`.
+//!
+//! None of the backends expose enumeration through the keyring crate, so
+//! [`KeychainAccess`] keeps its own index of stored keys in a reserved entry
+//! under the same service. Entries written outside this type (or before the
+//! index existed) are invisible to [`KeychainAccess::list_keys`] and must be
+//! migrated explicitly with [`KeychainAccess::migrate_keys`].
 
+use std::sync::Mutex;
+
+use async_trait::async_trait;
 use thiserror::Error;
 
+use crate::domain::{AccountId, CredentialError, CredentialResult, CredentialStore};
+
 /// Errors that can occur during keychain operations.
 #[derive(Debug, Error)]
 pub enum KeychainError {
@@ -15,11 +37,20 @@ pub enum KeychainError {
 
     #[error("Failed to spawn blocking task: {0}")]
     TaskFailed(String),
+
+    #[error("Corrupt keychain index: {0}")]
+    CorruptIndex(String),
 }
 
 /// Result type for keychain operations.
 pub type Result<T> = std::result::Result<T, KeychainError>;
 
+/// Reserved key holding the JSON list of keys stored under a service.
+const INDEX_KEY: &str = "__heap_key_index__";
+
+/// Serializes read-modify-write cycles on the key index within this process.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
 /// Provides access to the OS keychain for credential storage.
 ///
 /// Credentials are stored using the service name as a namespace,
@@ -33,6 +64,10 @@ impl KeychainAccess {
     /// Default service name for The Heap credentials.
     pub const DEFAULT_SERVICE: &'static str = "com.panbanda.heap";
 
+    /// Service name used by early provider builds that wrote to the keyring
+    /// directly. Entries found here are migrated on first read.
+    pub const LEGACY_SERVICE: &'static str = "heap";
+
     /// Creates a new KeychainAccess with the default service name.
     pub fn new() -> Self {
         Self {
@@ -60,6 +95,13 @@ impl KeychainAccess {
         tokio::task::spawn_blocking(move || {
             let entry = keyring::Entry::new(&service, &key)?;
             entry.set_password(&value)?;
+
+            let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let mut keys = read_index(&service)?;
+            if !keys.contains(&key) {
+                keys.push(key);
+                write_index(&service, &keys)?;
+            }
             Ok(())
         })
         .await
@@ -94,11 +136,22 @@ impl KeychainAccess {
 
         tokio::task::spawn_blocking(move || {
             let entry = keyring::Entry::new(&service, &key)?;
-            match entry.delete_credential() {
+            let result = match entry.delete_credential() {
                 Ok(()) => Ok(()),
-                Err(keyring::Error::NoEntry) => Err(KeychainError::NotFound(key)),
-                Err(e) => Err(KeychainError::Keyring(e)),
+                Err(keyring::Error::NoEntry) => Err(KeychainError::NotFound(key.clone())),
+                Err(e) => return Err(KeychainError::Keyring(e)),
+            };
+
+            // Drop the key from the index even if the entry was already gone,
+            // so a stale index heals itself.
+            let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let mut keys = read_index(&service)?;
+            let before = keys.len();
+            keys.retain(|k| k != &key);
+            if keys.len() != before {
+                write_index(&service, &keys)?;
             }
+            result
         })
         .await
         .map_err(|e| KeychainError::TaskFailed(e.to_string()))?
@@ -109,11 +162,94 @@ impl KeychainAccess {
         Ok(self.retrieve(key).await?.is_some())
     }
 
+    /// Lists all keys stored under this service, in insertion order.
+    ///
+    /// Only keys written through [`KeychainAccess::store`] are tracked.
+    pub async fn list_keys(&self) -> Result<Vec<String>> {
+        let service = self.service_name.clone();
+
+        tokio::task::spawn_blocking(move || read_index(&service))
+            .await
+            .map_err(|e| KeychainError::TaskFailed(e.to_string()))?
+    }
+
+    /// Deletes every credential belonging to an account.
+    ///
+    /// A key belongs to an account when it ends with the account ID
+    /// separated by `.`, `-` or `:`, which covers all key helpers on this
+    /// type. Returns the number of credentials removed.
+    pub async fn delete_all_for_account(&self, account_id: &str) -> Result<usize> {
+        let mut deleted = 0;
+        for key in self.list_keys().await? {
+            if !key_belongs_to_account(&key, account_id) {
+                continue;
+            }
+            match self.delete(&key).await {
+                Ok(()) => deleted += 1,
+                Err(KeychainError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Moves every indexed credential from `old_service` to `new_service`.
+    ///
+    /// Existing values under the new service are overwritten. Returns the
+    /// number of credentials migrated.
+    pub async fn migrate_service(old_service: &str, new_service: &str) -> Result<usize> {
+        let keys = KeychainAccess::with_service(old_service)
+            .list_keys()
+            .await?;
+        Self::migrate_keys(old_service, new_service, &keys).await
+    }
+
+    /// Moves the given credentials from `old_service` to `new_service`.
+    ///
+    /// Use this for entries that predate the key index. Keys missing from
+    /// the old service are skipped. Returns the number of credentials
+    /// migrated.
+    pub async fn migrate_keys(
+        old_service: &str,
+        new_service: &str,
+        keys: &[String],
+    ) -> Result<usize> {
+        if old_service == new_service {
+            return Ok(0);
+        }
+
+        let old = KeychainAccess::with_service(old_service);
+        let new = KeychainAccess::with_service(new_service);
+        let mut migrated = 0;
+
+        for key in keys {
+            let Some(value) = old.retrieve(key).await? else {
+                continue;
+            };
+            new.store(key, &value).await?;
+            match old.delete(key).await {
+                Ok(()) | Err(KeychainError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+            migrated += 1;
+        }
+
+        Ok(migrated)
+    }
+
     /// Returns the service name used for this keychain access.
     pub fn service_name(&self) -> &str {
         &self.service_name
     }
 
+    /// Generates the keychain key holding a provider's credentials for an account.
+    ///
+    /// The value is a provider-specific JSON document, e.g. the OAuth client
+    /// and refresh token for Gmail or the username and password for IMAP.
+    pub fn account_credentials_key(provider: &str, account_id: &str) -> String {
+        format!("{}-{}", provider, account_id)
+    }
+
     /// Generates a keychain key for an account's OAuth access token.
     pub fn oauth_access_token_key(account_id: &str) -> String {
         format!("oauth.access_token.{}", account_id)
@@ -141,6 +277,95 @@ impl Default for KeychainAccess {
     }
 }
 
+#[async_trait]
+impl CredentialStore for KeychainAccess {
+    async fn store(&self, key: &str, value: &str) -> CredentialResult<()> {
+        KeychainAccess::store(self, key, value)
+            .await
+            .map_err(|e| CredentialError(e.to_string()))
+    }
+
+    async fn retrieve(&self, key: &str) -> CredentialResult<Option<String>> {
+        let to_err = |e: KeychainError| CredentialError(e.to_string());
+
+        if let Some(value) = KeychainAccess::retrieve(self, key).await.map_err(to_err)? {
+            return Ok(Some(value));
+        }
+
+        // Pick up credentials written by providers before the service name
+        // was unified.
+        if self.service_name() == KeychainAccess::DEFAULT_SERVICE {
+            let migrated = KeychainAccess::migrate_keys(
+                KeychainAccess::LEGACY_SERVICE,
+                self.service_name(),
+                &[key.to_string()],
+            )
+            .await
+            .map_err(to_err)?;
+            if migrated > 0 {
+                return KeychainAccess::retrieve(self, key).await.map_err(to_err);
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn delete(&self, key: &str) -> CredentialResult<()> {
+        match KeychainAccess::delete(self, key).await {
+            Ok(()) | Err(KeychainError::NotFound(_)) => Ok(()),
+            Err(e) => Err(CredentialError(e.to_string())),
+        }
+    }
+
+    async fn delete_all_for_account(&self, account_id: &AccountId) -> CredentialResult<()> {
+        KeychainAccess::delete_all_for_account(self, &account_id.0)
+            .await
+            .map(|_| ())
+            .map_err(|e| CredentialError(e.to_string()))
+    }
+}
+
+/// Reads the key index for a service. Must run on a blocking thread.
+fn read_index(service: &str) -> Result<Vec<String>> {
+    let entry = keyring::Entry::new(service, INDEX_KEY)?;
+    match entry.get_password() {
+        Ok(raw) => parse_index(&raw),
+        Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+        Err(e) => Err(KeychainError::Keyring(e)),
+    }
+}
+
+/// Writes the key index for a service, removing it when empty.
+/// Must run on a blocking thread.
+fn write_index(service: &str, keys: &[String]) -> Result<()> {
+    let entry = keyring::Entry::new(service, INDEX_KEY)?;
+    if keys.is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(KeychainError::Keyring(e)),
+        };
+    }
+    let raw =
+        serde_json::to_string(keys).map_err(|e| KeychainError::CorruptIndex(e.to_string()))?;
+    entry.set_password(&raw)?;
+    Ok(())
+}
+
+/// Parses the serialized key index.
+fn parse_index(raw: &str) -> Result<Vec<String>> {
+    serde_json::from_str(raw).map_err(|e| KeychainError::CorruptIndex(e.to_string()))
+}
+
+/// Returns whether a key is scoped to the given account.
+fn key_belongs_to_account(key: &str, account_id: &str) -> bool {
+    if account_id.is_empty() {
+        return false;
+    }
+    key.strip_suffix(account_id)
+        .and_then(|prefix| prefix.chars().last())
+        .is_some_and(|sep| matches!(sep, '.' | '-' | ':'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,6 +382,13 @@ mod tests {
         assert_eq!(keychain.service_name(), "test.service");
     }
 
+    #[test]
+    fn account_credentials_key_format() {
+        let key = KeychainAccess::account_credentials_key("gmail", "account-123");
+        assert_eq!(key, "gmail-account-123");
+        assert!(key_belongs_to_account(&key, "account-123"));
+    }
+
     #[test]
     fn oauth_access_token_key_format() {
         let key = KeychainAccess::oauth_access_token_key("account-123");
@@ -181,6 +413,48 @@ mod tests {
         assert_eq!(key, "ai.api_key.anthropic");
     }
 
+    #[test]
+    fn key_belongs_to_account_matches_helpers() {
+        let id = "account-123";
+        assert!(key_belongs_to_account(
+            &KeychainAccess::oauth_access_token_key(id),
+            id
+        ));
+        assert!(key_belongs_to_account(
+            &KeychainAccess::imap_password_key(id),
+            id
+        ));
+        assert!(key_belongs_to_account("gmail-account-123", id));
+        assert!(key_belongs_to_account("password:account-123", id));
+    }
+
+    #[test]
+    fn key_belongs_to_account_rejects_other_accounts() {
+        assert!(!key_belongs_to_account(
+            "imap.password.account-1234",
+            "account-123"
+        ));
+        assert!(!key_belongs_to_account(
+            "imap.password.xaccount-123",
+            "account-123"
+        ));
+        assert!(!key_belongs_to_account("account-123", "account-123"));
+        assert!(!key_belongs_to_account("ai.api_key.anthropic", ""));
+    }
+
+    #[test]
+    fn parse_index_roundtrip() {
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let raw = serde_json::to_string(&keys).unwrap();
+        assert_eq!(parse_index(&raw).unwrap(), keys);
+    }
+
+    #[test]
+    fn parse_index_rejects_garbage() {
+        let err = parse_index("not json").unwrap_err();
+        assert!(matches!(err, KeychainError::CorruptIndex(_)));
+    }
+
     #[test]
     fn keychain_is_clone() {
         let keychain1 = KeychainAccess::new();
@@ -212,5 +486,58 @@ mod tests {
             let after_delete = keychain.retrieve(key).await.unwrap();
             assert_eq!(after_delete, None);
         }
+
+        #[tokio::test]
+        #[ignore = "requires OS keychain access"]
+        async fn list_and_delete_all_for_account() {
+            let keychain = KeychainAccess::with_service("com.panbanda.heap.test.batch");
+            let access = KeychainAccess::oauth_access_token_key("acct-1");
+            let password = KeychainAccess::imap_password_key("acct-2");
+
+            keychain.store(&access, "token").await.unwrap();
+            keychain.store(&password, "secret").await.unwrap();
+
+            let keys = keychain.list_keys().await.unwrap();
+            assert!(keys.contains(&access));
+            assert!(keys.contains(&password));
+
+            let deleted = keychain.delete_all_for_account("acct-1").await.unwrap();
+            assert_eq!(deleted, 1);
+            assert_eq!(keychain.retrieve(&access).await.unwrap(), None);
+            assert_eq!(keychain.list_keys().await.unwrap(), vec![password.clone()]);
+
+            keychain.delete(&password).await.unwrap();
+        }
+
+        #[tokio::test]
+        #[ignore = "requires OS keychain access"]
+        async fn migrate_service_moves_credentials() {
+            let old = "com.panbanda.heap.test.old";
+            let new = "com.panbanda.heap.test.new";
+            let key = "imap-acct-3";
+
+            KeychainAccess::with_service(old)
+                .store(key, "secret")
+                .await
+                .unwrap();
+
+            let migrated = KeychainAccess::migrate_service(old, new).await.unwrap();
+            assert_eq!(migrated, 1);
+
+            let new_keychain = KeychainAccess::with_service(new);
+            assert_eq!(
+                new_keychain.retrieve(key).await.unwrap(),
+                Some("secret".to_string())
+            );
+            assert_eq!(
+                KeychainAccess::with_service(old)
+                    .retrieve(key)
+                    .await
+                    .unwrap(),
+                None
+            );
+
+            new_keychain.delete(key).await.unwrap();
+        }
     }
 }
@@ -11,11 +11,16 @@ mod keychain;
 pub mod queries;
 mod schema;
 
-pub use database::{Database, DatabaseError, Result};
+pub use database::{Database, DatabaseError, DatabaseOptions, Result, Synchronous};
 pub use keychain::{KeychainAccess, KeychainError};
+pub use queries::blobs::BlobWriter;
+pub use queries::storage_stats::{AccountStorageStats, StorageStats, ThreadStorageStats};
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::domain::{AccountId, Email, EmailId};
+
 /// Combined storage layer with database and keychain access.
 ///
 /// This is the main entry point for storage operations.
@@ -34,6 +39,17 @@ impl StorageLayer {
         Ok(Self { db, keychain })
     }
 
+    /// Creates a storage layer with tuned database connection options.
+    pub async fn with_options(
+        db_path: impl AsRef<std::path::Path>,
+        options: DatabaseOptions,
+    ) -> Result<Self> {
+        let db = Database::open_with_options(db_path, options).await?;
+        let keychain = KeychainAccess::new();
+
+        Ok(Self { db, keychain })
+    }
+
     /// Creates a storage layer with an in-memory database for testing.
     pub async fn in_memory() -> Result<Self> {
         let db = Database::open_in_memory().await?;
@@ -52,6 +68,89 @@ impl StorageLayer {
         &self.keychain
     }
 
+    /// Stores attachment content, returning its SHA-256 hash.
+    ///
+    /// Identical content is stored once; reference it from an attachment's
+    /// `content_hash` to keep it alive.
+    pub async fn store_blob(&self, bytes: Vec<u8>) -> Result<String> {
+        queries::blobs::store(&self.db, bytes).await
+    }
+
+    /// Stores attachment content streamed into a [`BlobWriter`], such as a
+    /// large download, returning its SHA-256 hash.
+    pub async fn store_blob_from(&self, writer: BlobWriter) -> Result<String> {
+        queries::blobs::store_written(&self.db, writer).await
+    }
+
+    /// Loads attachment content by its hash.
+    pub async fn load_blob(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        queries::blobs::load(&self.db, hash).await
+    }
+
+    /// Loads the downloaded content of an email's parts that have a
+    /// Content-ID, keyed by it, for showing inline images.
+    ///
+    /// Parts that haven't been downloaded are left out.
+    pub async fn load_inline_parts(&self, email: &Email) -> Result<HashMap<String, Vec<u8>>> {
+        let mut parts = HashMap::new();
+        for attachment in &email.attachments {
+            let (Some(content_id), Some(hash)) = (&attachment.content_id, &attachment.content_hash)
+            else {
+                continue;
+            };
+            if let Some(bytes) = self.load_blob(hash).await? {
+                parts.insert(content_id.clone(), bytes);
+            }
+        }
+        Ok(parts)
+    }
+
+    /// Evicts the bodies and downloaded attachments of emails older than
+    /// `older_than`, returning the number of emails evicted.
+    ///
+    /// Headers and snippets are kept for search; evicted bodies are fetched
+    /// again from the provider when opened. Drafts and starred mail are kept.
+    pub async fn evict_bodies(&self, older_than: chrono::Duration) -> Result<usize> {
+        queries::emails::evict_bodies(&self.db, chrono::Utc::now() - older_than).await
+    }
+
+    /// Deletes an account with all its stored data and keychain
+    /// credentials, returning the IDs of the deleted emails.
+    ///
+    /// Credentials that can't be removed, e.g. because the keychain is
+    /// locked, are logged and left behind rather than failing the deletion.
+    /// Drop the returned emails from the vector store with
+    /// [`VectorStore::remove_all`](crate::embedding::VectorStore::remove_all).
+    pub async fn delete_account(&self, account_id: &AccountId) -> Result<Vec<EmailId>> {
+        let email_ids = queries::accounts::delete_cascade(&self.db, account_id).await?;
+        if let Err(e) = self.keychain.delete_all_for_account(&account_id.0).await {
+            tracing::warn!(account = %account_id, "Failed to delete credentials: {}", e);
+        }
+        Ok(email_ids)
+    }
+
+    /// Checkpoints the database's write-ahead log, e.g. before exiting.
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.db.checkpoint().await
+    }
+
+    /// Backs up the database to a file at `path`, safely while in use.
+    ///
+    /// The backup holds all mail, settings and stored embeddings. Account
+    /// passwords and tokens live in the keychain and aren't included.
+    pub async fn backup_to(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.db.backup_to(path).await
+    }
+
+    /// Replaces the database's contents with a backup made by
+    /// [`backup_to`](Self::backup_to).
+    ///
+    /// Fails without changing anything if the backup is damaged or from a
+    /// different schema version.
+    pub async fn restore_from(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.db.restore_from(path).await
+    }
+
     /// Wraps the storage layer in an Arc for shared ownership.
     pub fn into_arc(self) -> Arc<Self> {
         Arc::new(self)
@@ -103,4 +202,74 @@ mod tests {
 
         assert_eq!(count, 0);
     }
+
+    async fn set_setting(storage: &StorageLayer, key: &'static str, value: &'static str) {
+        storage
+            .db()
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO settings (key, value, updated_at)
+                     VALUES (?, ?, '2025-01-01T00:00:00Z')",
+                    [key, value],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn settings(storage: &StorageLayer) -> Vec<(String, String)> {
+        storage
+            .db()
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn restore_returns_backed_up_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("backup.db");
+        let storage = StorageLayer::new(dir.path().join("heap.db")).await.unwrap();
+        set_setting(&storage, "theme", "dark").await;
+        let original = settings(&storage).await;
+
+        storage.backup_to(&backup).await.unwrap();
+        set_setting(&storage, "theme", "light").await;
+        set_setting(&storage, "density", "compact").await;
+        assert_ne!(settings(&storage).await, original);
+
+        storage.restore_from(&backup).await.unwrap();
+        assert_eq!(settings(&storage).await, original);
+    }
+
+    #[tokio::test]
+    async fn restore_rejects_invalid_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageLayer::in_memory().await.unwrap();
+        set_setting(&storage, "theme", "dark").await;
+
+        let garbage = dir.path().join("garbage.db");
+        std::fs::write(&garbage, b"not a database").unwrap();
+        let other_version = dir.path().join("other.db");
+        rusqlite::Connection::open(&other_version)
+            .unwrap()
+            .pragma_update(None, "user_version", 99)
+            .unwrap();
+
+        for path in [garbage, other_version, dir.path().join("missing.db")] {
+            assert!(matches!(
+                storage.restore_from(&path).await,
+                Err(DatabaseError::InvalidBackup(_))
+            ));
+        }
+        assert_eq!(
+            settings(&storage).await,
+            vec![("theme".to_string(), "dark".to_string())]
+        );
+    }
 }
@@ -5,7 +5,9 @@
 use chrono::Utc;
 use rusqlite::{params, OptionalExtension, Row};
 
-use crate::domain::{Account, AccountId, ProviderConfig, ProviderType};
+use crate::domain::{
+    Account, AccountId, Address, EmailId, FolderMapping, ProviderConfig, ProviderType,
+};
 use crate::storage::database::{Database, Result};
 
 use std::time::Duration;
@@ -21,15 +23,17 @@ pub async fn insert(db: &Database, account: &Account) -> Result<()> {
             ProviderType::Imap => "imap",
         };
         let provider_config = serde_json::to_string(&account.provider_config).unwrap_or_default();
+        let folder_mapping = serde_json::to_string(&account.folder_mapping).unwrap_or_default();
+        let aliases = serde_json::to_string(&account.aliases).unwrap_or_default();
 
         conn.execute(
             r#"
             INSERT INTO accounts (
                 id, email, display_name, provider_type, provider_config,
-                sync_enabled, sync_interval_seconds, signature,
-                created_at, updated_at
+                sync_enabled, sync_interval_seconds, signature, signature_html,
+                folder_mapping, aliases, created_at, updated_at
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
             )
             "#,
             params![
@@ -41,6 +45,9 @@ pub async fn insert(db: &Database, account: &Account) -> Result<()> {
                 account.sync_enabled as i32,
                 account.sync_interval.as_secs() as i32,
                 account.signature,
+                account.signature_html,
+                folder_mapping,
+                aliases,
                 now,
                 now,
             ],
@@ -55,12 +62,13 @@ pub async fn insert(db: &Database, account: &Account) -> Result<()> {
 pub async fn get_by_id(db: &Database, account_id: &AccountId) -> Result<Option<Account>> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, email, display_name, provider_type, provider_config,
-                sync_enabled, sync_interval_seconds, signature
+                sync_enabled, sync_interval_seconds, signature, signature_html,
+                folder_mapping, aliases
             FROM accounts
             WHERE id = ?1
             "#,
@@ -76,12 +84,13 @@ pub async fn get_by_id(db: &Database, account_id: &AccountId) -> Result<Option<A
 pub async fn get_by_email(db: &Database, email: &str) -> Result<Option<Account>> {
     let email = email.to_string();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, email, display_name, provider_type, provider_config,
-                sync_enabled, sync_interval_seconds, signature
+                sync_enabled, sync_interval_seconds, signature, signature_html,
+                folder_mapping, aliases
             FROM accounts
             WHERE email = ?1
             "#,
@@ -95,12 +104,13 @@ pub async fn get_by_email(db: &Database, email: &str) -> Result<Option<Account>>
 
 /// Retrieves all accounts.
 pub async fn get_all(db: &Database) -> Result<Vec<Account>> {
-    db.with_conn(|conn| {
+    db.with_reader(|conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, email, display_name, provider_type, provider_config,
-                sync_enabled, sync_interval_seconds, signature
+                sync_enabled, sync_interval_seconds, signature, signature_html,
+                folder_mapping, aliases
             FROM accounts
             ORDER BY email
             "#,
@@ -173,20 +183,162 @@ pub async fn set_signature(
     .await
 }
 
+/// Updates an account's HTML signature.
+pub async fn set_signature_html(
+    db: &Database,
+    account_id: &AccountId,
+    signature_html: Option<&str>,
+) -> Result<()> {
+    let account_id = account_id.clone();
+    let signature_html = signature_html.map(|s| s.to_string());
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE accounts SET signature_html = ?1, updated_at = ?2 WHERE id = ?3",
+            params![signature_html, now, account_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Updates where an account's special folders are on the server.
+pub async fn set_folder_mapping(
+    db: &Database,
+    account_id: &AccountId,
+    folder_mapping: &FolderMapping,
+) -> Result<()> {
+    let account_id = account_id.clone();
+    let folder_mapping = serde_json::to_string(folder_mapping).unwrap_or_default();
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE accounts SET folder_mapping = ?1, updated_at = ?2 WHERE id = ?3",
+            params![folder_mapping, now, account_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Updates the addresses an account can send as, besides its own.
+pub async fn set_aliases(db: &Database, account_id: &AccountId, aliases: &[Address]) -> Result<()> {
+    let account_id = account_id.clone();
+    let aliases = serde_json::to_string(aliases).unwrap_or_default();
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE accounts SET aliases = ?1, updated_at = ?2 WHERE id = ?3",
+            params![aliases, now, account_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
 /// Deletes an account and all associated data.
+///
+/// See [`delete_cascade`].
 pub async fn delete(db: &Database, account_id: &AccountId) -> Result<()> {
+    delete_cascade(db, account_id).await.map(|_| ())
+}
+
+/// Deletes an account and everything stored for it in one transaction,
+/// returning the IDs of the deleted emails.
+///
+/// Removes the account's threads, emails (with their attachments, stored
+/// embeddings and search index entries), labels, snoozes, follow-ups,
+/// cached summaries, drafts, pending changes, sync state, unsubscribes and
+/// stats, and the contacts that only appear in its mail. Screener entries
+/// are shared across accounts and lose their link to the deleted first
+/// email.
+///
+/// Callers should drop the returned emails from the in-memory vector store.
+pub async fn delete_cascade(db: &Database, account_id: &AccountId) -> Result<Vec<EmailId>> {
     let account_id = account_id.clone();
 
     db.transaction(move |tx| {
+        let email_ids = {
+            let mut stmt = tx.prepare("SELECT id FROM emails WHERE account_id = ?1")?;
+            let rows = stmt.query_map([&account_id.0], |row| Ok(EmailId(row.get(0)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        // Contacts are shared; remove those no other account has mail with,
+        // their merged addresses first.
+        tx.execute(
+            r#"
+            WITH seen(account_id, email) AS (
+                SELECT account_id, lower(from_address) FROM emails
+                UNION
+                SELECT e.account_id, lower(json_extract(r.value, '$.email'))
+                FROM emails e, json_each(e.to_addresses) r
+                UNION
+                SELECT e.account_id, lower(json_extract(r.value, '$.email'))
+                FROM emails e, json_each(e.cc_addresses) r
+            )
+            DELETE FROM contact_addresses
+            WHERE contact_id IN (
+                SELECT id FROM contacts
+                WHERE lower(email) IN (SELECT email FROM seen WHERE account_id = ?1)
+                    AND lower(email) NOT IN (SELECT email FROM seen WHERE account_id != ?1)
+            )
+            "#,
+            [&account_id.0],
+        )?;
+        tx.execute(
+            r#"
+            WITH seen(account_id, email) AS (
+                SELECT account_id, lower(from_address) FROM emails
+                UNION
+                SELECT e.account_id, lower(json_extract(r.value, '$.email'))
+                FROM emails e, json_each(e.to_addresses) r
+                UNION
+                SELECT e.account_id, lower(json_extract(r.value, '$.email'))
+                FROM emails e, json_each(e.cc_addresses) r
+            )
+            DELETE FROM contacts
+            WHERE lower(email) IN (SELECT email FROM seen WHERE account_id = ?1)
+                AND lower(email) NOT IN (SELECT email FROM seen WHERE account_id != ?1)
+            "#,
+            [&account_id.0],
+        )?;
+
         // Delete in order to respect foreign key constraints
         tx.execute(
-            "DELETE FROM embeddings WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            "DELETE FROM embeddings
+             WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
             [&account_id.0],
         )?;
         tx.execute(
-            "DELETE FROM attachments WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            "DELETE FROM attachments
+             WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
             [&account_id.0],
         )?;
+        tx.execute(
+            "UPDATE screener_entries SET first_email_id = NULL
+             WHERE first_email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM snoozed
+             WHERE thread_id IN (SELECT id FROM threads WHERE account_id = ?1)",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM summaries
+             WHERE thread_id IN (SELECT id FROM threads WHERE account_id = ?1)",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM followups
+             WHERE thread_id IN (SELECT id FROM threads WHERE account_id = ?1)",
+            [&account_id.0],
+        )?;
+        // The emails_ad trigger removes the search index entries.
         tx.execute("DELETE FROM emails WHERE account_id = ?1", [&account_id.0])?;
         tx.execute("DELETE FROM threads WHERE account_id = ?1", [&account_id.0])?;
         tx.execute("DELETE FROM labels WHERE account_id = ?1", [&account_id.0])?;
@@ -195,17 +347,36 @@ pub async fn delete(db: &Database, account_id: &AccountId) -> Result<()> {
             "DELETE FROM pending_changes WHERE account_id = ?1",
             [&account_id.0],
         )?;
-        tx.execute("DELETE FROM sync_state WHERE account_id = ?1", [&account_id.0])?;
+        tx.execute(
+            "DELETE FROM sync_state WHERE account_id = ?1",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM imap_locations WHERE account_id = ?1",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM imap_folders WHERE account_id = ?1",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM unsubscribes WHERE account_id = ?1",
+            [&account_id.0],
+        )?;
+        tx.execute(
+            "DELETE FROM daily_stats WHERE account_id = ?1",
+            [&account_id.0],
+        )?;
         tx.execute("DELETE FROM accounts WHERE id = ?1", [&account_id.0])?;
 
-        Ok(())
+        Ok(email_ids)
     })
     .await
 }
 
 /// Counts total accounts.
 pub async fn count(db: &Database) -> Result<u32> {
-    db.with_conn(|conn| {
+    db.with_reader(|conn| {
         let count: u32 = conn.query_row("SELECT COUNT(*) FROM accounts", [], |row| row.get(0))?;
         Ok(count)
     })
@@ -216,7 +387,7 @@ pub async fn count(db: &Database) -> Result<u32> {
 pub async fn exists_by_email(db: &Database, email: &str) -> Result<bool> {
     let email = email.to_string();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM accounts WHERE email = ?1",
             [&email],
@@ -240,6 +411,14 @@ fn row_to_account(row: &Row<'_>) -> std::result::Result<Account, rusqlite::Error
 
     let provider_config: ProviderConfig =
         serde_json::from_str(&provider_config_json).unwrap_or(ProviderConfig::Gmail {});
+    let folder_mapping_json: Option<String> = row.get(9)?;
+    let folder_mapping = folder_mapping_json
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let aliases_json: Option<String> = row.get(10)?;
+    let aliases = aliases_json
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
 
     Ok(Account {
         id: AccountId(row.get(0)?),
@@ -250,12 +429,16 @@ fn row_to_account(row: &Row<'_>) -> std::result::Result<Account, rusqlite::Error
         sync_enabled: row.get::<_, i32>(5)? != 0,
         sync_interval: Duration::from_secs(sync_interval_secs as u64),
         signature: row.get(7)?,
+        signature_html: row.get(8)?,
+        folder_mapping,
+        aliases,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::SpecialFolder;
 
     fn make_test_account() -> Account {
         Account {
@@ -267,6 +450,9 @@ mod tests {
             sync_enabled: true,
             sync_interval: Duration::from_secs(300),
             signature: Some("-- \nTest User".to_string()),
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+            aliases: vec![],
         }
     }
 
@@ -282,10 +468,16 @@ mod tests {
                 smtp_host: "smtp.example.com".to_string(),
                 smtp_port: 587,
                 use_tls: true,
+                allow_plaintext: false,
+                tls_root_cert: None,
+                accept_invalid_certs: false,
             },
             sync_enabled: true,
             sync_interval: Duration::from_secs(600),
             signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+            aliases: vec![],
         }
     }
 
@@ -402,6 +594,63 @@ mod tests {
         assert_eq!(retrieved.signature, Some("New Signature".to_string()));
     }
 
+    #[tokio::test]
+    async fn update_signature_html() {
+        let db = Database::open_in_memory().await.unwrap();
+        let account = make_test_account();
+
+        insert(&db, &account).await.unwrap();
+
+        set_signature_html(&db, &account.id, Some("<b>Test User</b>"))
+            .await
+            .unwrap();
+
+        let retrieved = get_by_id(&db, &account.id).await.unwrap().unwrap();
+        assert_eq!(
+            retrieved.signature_html,
+            Some("<b>Test User</b>".to_string())
+        );
+        assert_eq!(retrieved.signature, account.signature);
+    }
+
+    #[tokio::test]
+    async fn update_folder_mapping() {
+        let db = Database::open_in_memory().await.unwrap();
+        let account = make_imap_account();
+        insert(&db, &account).await.unwrap();
+        assert!(get_by_id(&db, &account.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .folder_mapping
+            .is_empty());
+
+        let mut mapping = FolderMapping::new();
+        mapping.set_override(SpecialFolder::Sent, Some("INBOX.Sent".to_string()));
+        set_folder_mapping(&db, &account.id, &mapping)
+            .await
+            .unwrap();
+
+        let retrieved = get_by_id(&db, &account.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.folder_mapping, mapping);
+    }
+
+    #[tokio::test]
+    async fn update_aliases() {
+        let db = Database::open_in_memory().await.unwrap();
+        let account = make_test_account();
+        insert(&db, &account).await.unwrap();
+
+        let aliases = vec![Address::with_name("support@example.com", "Support")];
+        set_aliases(&db, &account.id, &aliases).await.unwrap();
+
+        let retrieved = get_by_email(&db, "test@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retrieved.aliases, aliases);
+    }
+
     #[tokio::test]
     async fn delete_account() {
         let db = Database::open_in_memory().await.unwrap();
@@ -439,4 +688,165 @@ mod tests {
         assert!(exists_by_email(&db, "test@example.com").await.unwrap());
         assert!(!exists_by_email(&db, "other@example.com").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn delete_removes_only_that_accounts_data() {
+        use crate::storage::queries::test_support::{self, account_id, Dataset};
+
+        let db = Database::open_in_memory().await.unwrap();
+        let dataset = Dataset::generate(2, 4);
+        test_support::seed(&db, &dataset).await.unwrap();
+        db.with_conn(|conn| {
+            conn.execute_batch(
+                r#"
+                INSERT INTO snoozed (id, thread_id, snooze_until, created_at)
+                VALUES ('snooze-1', 'account-1-thread-00', '2025-02-01', '2025-01-01');
+                INSERT INTO summaries (thread_id, content_hash, summary, created_at)
+                VALUES ('account-1-thread-01', 'hash', 'Summary', '2025-01-01');
+                INSERT INTO screener_entries (id, sender_email, first_email_id, status, created_at)
+                VALUES ('entry-1', 'bob@example.com', 'account-1-thread-01-email-0', 'pending',
+                        '2025-01-01');
+                "#,
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        delete(&db, &account_id(1)).await.unwrap();
+
+        let counts = |account: &'static str| {
+            db.with_conn(move |conn| {
+                let count = |sql: &str| -> rusqlite::Result<i64> {
+                    conn.query_row(sql, [account], |row| row.get(0))
+                };
+                Ok([
+                    count("SELECT COUNT(*) FROM accounts WHERE id = ?1")?,
+                    count("SELECT COUNT(*) FROM threads WHERE account_id = ?1")?,
+                    count("SELECT COUNT(*) FROM emails WHERE account_id = ?1")?,
+                    count("SELECT COUNT(*) FROM labels WHERE account_id = ?1")?,
+                    count(
+                        "SELECT COUNT(*) FROM snoozed s JOIN threads t ON t.id = s.thread_id
+                         WHERE t.account_id = ?1",
+                    )?,
+                ])
+            })
+        };
+        assert_eq!(counts("account-1").await.unwrap(), [0, 0, 0, 0, 0]);
+        let other = account_id(2);
+        assert_eq!(
+            counts("account-2").await.unwrap(),
+            [
+                1,
+                dataset.threads_of(&other).len() as i64,
+                dataset.emails_of(&other).len() as i64,
+                2,
+                0
+            ]
+        );
+
+        let (snoozed, summaries, first_email, contacts): (i64, i64, Option<String>, i64) = db
+            .with_conn(|conn| {
+                Ok(conn.query_row(
+                    "SELECT (SELECT COUNT(*) FROM snoozed), (SELECT COUNT(*) FROM summaries),
+                         (SELECT first_email_id FROM screener_entries WHERE id = 'entry-1'),
+                         (SELECT COUNT(*) FROM contacts)",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )?)
+            })
+            .await
+            .unwrap();
+        assert_eq!((snoozed, summaries, first_email), (0, 0, None));
+        assert_eq!(contacts, dataset.contacts.len() as i64);
+    }
+
+    #[tokio::test]
+    async fn delete_cascade_leaves_no_orphans() {
+        use crate::domain::{Address, Contact};
+        use crate::storage::queries::test_support::{self, account_id, Dataset};
+
+        let db = Database::open_in_memory().await.unwrap();
+        let mut dataset = Dataset::generate(2, 3);
+        dataset.emails[0].cc = vec![Address::new("only-account-1@example.com")];
+        dataset
+            .contacts
+            .push(Contact::new("only-account-1@example.com"));
+        test_support::seed(&db, &dataset).await.unwrap();
+        db.with_conn(|conn| {
+            for n in 1..=2 {
+                conn.execute_batch(&format!(
+                    r#"
+                    INSERT INTO embeddings (email_id, embedding, created_at)
+                    VALUES ('account-{n}-thread-00-email-0', x'00', '2025-01-01');
+                    INSERT INTO attachments (id, email_id, filename, created_at)
+                    VALUES ('attachment-{n}', 'account-{n}-thread-00-email-0', 'a.pdf',
+                            '2025-01-01');
+                    INSERT INTO drafts (id, account_id, created_at, updated_at)
+                    VALUES ('draft-{n}', 'account-{n}', '2025-01-01', '2025-01-01');
+                    INSERT INTO pending_changes (id, account_id, change_type, payload, created_at)
+                    VALUES ('change-{n}', 'account-{n}', 'archive', '{{}}', '2025-01-01');
+                    INSERT INTO sync_state (account_id) VALUES ('account-{n}');
+                    INSERT INTO daily_stats (date, account_id) VALUES ('2025-01-01', 'account-{n}');
+                    INSERT INTO snoozed (id, thread_id, snooze_until, created_at)
+                    VALUES ('snooze-{n}', 'account-{n}-thread-00', '2025-02-01', '2025-01-01');
+                    "#
+                ))?;
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let deleted = delete_cascade(&db, &account_id(1)).await.unwrap();
+        let expected: Vec<EmailId> = dataset
+            .emails_of(&account_id(1))
+            .into_iter()
+            .map(|email| email.id.clone())
+            .collect();
+        assert_eq!(deleted.len(), expected.len());
+        assert!(expected.iter().all(|id| deleted.contains(id)));
+
+        let counts = |n: usize| {
+            db.with_conn(move |conn| {
+                let account = format!("account-{}", n);
+                let mut counts = vec![];
+                for sql in [
+                    "SELECT COUNT(*) FROM accounts WHERE id = ?1",
+                    "SELECT COUNT(*) FROM threads WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM emails WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM labels WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM drafts WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM pending_changes WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM sync_state WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM daily_stats WHERE account_id = ?1",
+                    "SELECT COUNT(*) FROM embeddings WHERE email_id LIKE ?1 || '-%'",
+                    "SELECT COUNT(*) FROM attachments WHERE email_id LIKE ?1 || '-%'",
+                    "SELECT COUNT(*) FROM snoozed WHERE thread_id LIKE ?1 || '-%'",
+                ] {
+                    let count: i64 = conn.query_row(sql, [&account], |row| row.get(0))?;
+                    counts.push(count.min(1));
+                }
+                let search: i64 = conn.query_row(
+                    "SELECT COUNT(*) FROM emails_fts WHERE emails_fts MATCH ?1",
+                    [format!("\"me{}\"", n)],
+                    |row| row.get(0),
+                )?;
+                counts.push(search.min(1));
+                Ok(counts)
+            })
+        };
+        assert_eq!(counts(1).await.unwrap(), vec![0; 12]);
+        assert_eq!(counts(2).await.unwrap(), vec![1; 12]);
+
+        let contacts: Vec<String> = db
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare("SELECT email FROM contacts ORDER BY email")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(contacts, test_support::SENDERS);
+    }
 }
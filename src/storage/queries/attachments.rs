@@ -0,0 +1,147 @@
+//! Attachment metadata queries.
+//!
+//! Attachment bodies live in the blob store; rows here reference them by
+//! content hash once downloaded.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, Row};
+
+use crate::domain::{Attachment, EmailId};
+use crate::storage::database::{Database, Result};
+
+/// Inserts an attachment of an email.
+pub async fn insert(db: &Database, email_id: &EmailId, attachment: &Attachment) -> Result<()> {
+    let email_id = email_id.clone();
+    let attachment = attachment.clone();
+
+    db.with_conn(move |conn| Ok(insert_row(conn, &email_id, &attachment)?))
+        .await
+}
+
+/// Inserts an attachment of an email on an open connection, such as within
+/// the transaction inserting the email.
+pub(crate) fn insert_row(
+    conn: &Connection,
+    email_id: &EmailId,
+    attachment: &Attachment,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO attachments (
+            id, email_id, filename, content_type, size_bytes, is_inline, content_hash,
+            content_id, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            attachment.id,
+            email_id.0,
+            attachment.filename,
+            attachment.content_type,
+            attachment.size_bytes as i64,
+            attachment.is_inline as i32,
+            attachment.content_hash,
+            attachment.content_id,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Gets the attachments of an email.
+pub async fn get_for_email(db: &Database, email_id: &EmailId) -> Result<Vec<Attachment>> {
+    let email_id = email_id.clone();
+
+    db.with_reader(move |conn| Ok(load_for_email(conn, &email_id)?))
+        .await
+}
+
+/// Loads the attachments of an email on an open connection.
+pub(crate) fn load_for_email(
+    conn: &Connection,
+    email_id: &EmailId,
+) -> rusqlite::Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, filename, content_type, size_bytes, is_inline, content_hash, content_id
+         FROM attachments WHERE email_id = ?1 ORDER BY created_at, id",
+    )?;
+    let attachments = stmt.query_map([&email_id.0], row_to_attachment)?;
+    attachments.collect()
+}
+
+/// Records that an attachment's content was downloaded into the blob store.
+pub async fn set_content_hash(
+    db: &Database,
+    attachment_id: &str,
+    content_hash: &str,
+) -> Result<()> {
+    let attachment_id = attachment_id.to_string();
+    let content_hash = content_hash.to_string();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "UPDATE attachments SET content_hash = ?1 WHERE id = ?2",
+            params![content_hash, attachment_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+fn row_to_attachment(row: &Row<'_>) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        filename: row.get(1)?,
+        content_type: row
+            .get::<_, Option<String>>(2)?
+            .unwrap_or_else(|| "application/octet-stream".to_string()),
+        size_bytes: row.get::<_, Option<i64>>(3)?.unwrap_or_default() as u64,
+        is_inline: row.get::<_, i32>(4)? != 0,
+        content_hash: row.get(5)?,
+        content_id: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::blobs;
+
+    async fn setup_db_with_email() -> Database {
+        let db = Database::open_in_memory().await.unwrap();
+        db.with_conn(|conn| {
+            conn.execute_batch(
+                "INSERT INTO accounts (id, email, provider_type, provider_config, created_at, updated_at)
+                 VALUES ('account-1', 'me@example.com', 'imap', '{}', '2025-01-01', '2025-01-01');
+                 INSERT INTO emails (id, account_id, thread_id, message_id, from_address, to_addresses, date, created_at, updated_at)
+                 VALUES ('email-1', 'account-1', 'thread-1', '<1@example.com>', 'a@example.com', '[]', '2025-01-01', '2025-01-01', '2025-01-01');",
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn download_links_attachment_to_blob() {
+        let db = setup_db_with_email().await;
+        let email_id = EmailId::from("email-1");
+        let attachment = Attachment {
+            id: "att-1".to_string(),
+            filename: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 4,
+            is_inline: false,
+            content_hash: None,
+            content_id: None,
+        };
+        insert(&db, &email_id, &attachment).await.unwrap();
+
+        let hash = blobs::store(&db, b"%PDF".to_vec()).await.unwrap();
+        set_content_hash(&db, "att-1", &hash).await.unwrap();
+
+        let stored = get_for_email(&db, &email_id).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].filename, "report.pdf");
+        assert_eq!(stored[0].content_hash.as_deref(), Some(hash.as_str()));
+        assert_eq!(blobs::ref_count(&db, &hash).await.unwrap(), Some(1));
+    }
+}
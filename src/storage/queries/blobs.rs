@@ -0,0 +1,347 @@
+//! Content-addressed blob queries.
+//!
+//! Attachment bodies are stored once per distinct content, keyed by their
+//! SHA-256 hash. Reference counts are maintained by triggers on the
+//! attachments table, so a blob is deleted with its last attachment.
+//!
+//! Large downloads are written to a [`BlobWriter`] as they arrive and
+//! copied into the store in chunks, so they are never held in memory whole.
+//!
+//! Thumbnails of a blob are cached alongside it, keyed by its hash and the
+//! thumbnail size, and deleted with it.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use chrono::Utc;
+use rusqlite::{params, DatabaseName, OptionalExtension};
+
+use crate::storage::database::{Database, Result};
+
+/// Returns the hex SHA-256 hash identifying `bytes` in the blob store.
+pub fn hash(bytes: &[u8]) -> String {
+    hex(ring::digest::digest(&ring::digest::SHA256, bytes))
+}
+
+fn hex(digest: ring::digest::Digest) -> String {
+    digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Spools content to a temporary file while hashing it, for storing with
+/// [`store_written`].
+///
+/// The file is removed when the writer is dropped.
+pub struct BlobWriter {
+    file: BufWriter<File>,
+    path: PathBuf,
+    digest: ring::digest::Context,
+    size: u64,
+}
+
+impl BlobWriter {
+    /// Creates a writer spooling to the system's temporary directory.
+    pub fn new() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("heap-blob-{}", uuid::Uuid::new_v4()));
+        let file = File::create(&path)?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            path,
+            digest: ring::digest::Context::new(&ring::digest::SHA256),
+            size: 0,
+        })
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Write for BlobWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.digest.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Stores `bytes` unless identical content is already stored, returning
+/// its hash.
+///
+/// The blob is unreferenced until an attachment with its hash is stored.
+pub async fn store(db: &Database, bytes: Vec<u8>) -> Result<String> {
+    let hash = hash(&bytes);
+    let key = hash.clone();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "INSERT OR IGNORE INTO blobs (hash, data, size_bytes, ref_count, created_at)
+             VALUES (?1, ?2, ?3, 0, ?4)",
+            params![key, bytes, bytes.len() as i64, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(hash)
+}
+
+/// Stores the content written to `writer` unless identical content is
+/// already stored, returning its hash.
+///
+/// The content is copied from the spool file into the database in chunks.
+pub async fn store_written(db: &Database, mut writer: BlobWriter) -> Result<String> {
+    writer.flush()?;
+    let hash = hex(writer.digest.clone().finish());
+    let key = hash.clone();
+
+    db.with_conn_mut(move |conn| {
+        let tx = conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO blobs (hash, data, size_bytes, ref_count, created_at)
+             VALUES (?1, zeroblob(?2), ?2, 0, ?3)",
+            params![key, writer.size as i64, Utc::now().to_rfc3339()],
+        )?;
+        if inserted > 0 {
+            let mut blob = tx.blob_open(
+                DatabaseName::Main,
+                "blobs",
+                "data",
+                tx.last_insert_rowid(),
+                false,
+            )?;
+            io::copy(&mut File::open(&writer.path)?, &mut blob)?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(hash)
+}
+
+/// Loads the content stored under `hash`.
+pub async fn load(db: &Database, hash: &str) -> Result<Option<Vec<u8>>> {
+    let hash = hash.to_string();
+
+    db.with_reader(move |conn| {
+        let data = conn
+            .query_row("SELECT data FROM blobs WHERE hash = ?1", [&hash], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(data)
+    })
+    .await
+}
+
+/// Returns the number of attachments referencing a blob, or `None` if it
+/// isn't stored.
+pub async fn ref_count(db: &Database, hash: &str) -> Result<Option<u32>> {
+    let hash = hash.to_string();
+
+    db.with_reader(move |conn| {
+        let count = conn
+            .query_row(
+                "SELECT ref_count FROM blobs WHERE hash = ?1",
+                [&hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(count)
+    })
+    .await
+}
+
+/// Caches a thumbnail of the blob stored under `hash`, replacing any of the
+/// same size.
+pub async fn store_thumbnail(db: &Database, hash: &str, size: u32, data: Vec<u8>) -> Result<()> {
+    let hash = hash.to_string();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO thumbnails (content_hash, size, data, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![hash, size, data, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Loads a cached thumbnail of the blob stored under `hash`.
+pub async fn load_thumbnail(db: &Database, hash: &str, size: u32) -> Result<Option<Vec<u8>>> {
+    let hash = hash.to_string();
+
+    db.with_reader(move |conn| {
+        let data = conn
+            .query_row(
+                "SELECT data FROM thumbnails WHERE content_hash = ?1 AND size = ?2",
+                params![hash, size],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data)
+    })
+    .await
+}
+
+/// Deletes blobs no attachment references, e.g. downloads whose
+/// attachment was never stored. Returns the number deleted.
+pub async fn delete_unreferenced(db: &Database) -> Result<usize> {
+    db.with_conn(|conn| Ok(conn.execute("DELETE FROM blobs WHERE ref_count <= 0", [])?))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{test_email, Address, Attachment, Email, EmailId};
+    use crate::storage::queries::{attachments, emails};
+
+    fn make_email(id: &str) -> Email {
+        Email {
+            from: Address::new("news@example.com"),
+            subject: Some("Newsletter".to_string()),
+            body_text: Some("This week's news".to_string()),
+            snippet: String::new(),
+            ..test_email(id, "thread-1")
+        }
+    }
+
+    fn logo(id: &str, content_hash: &str) -> Attachment {
+        Attachment {
+            id: id.to_string(),
+            filename: "logo.png".to_string(),
+            content_type: "image/png".to_string(),
+            size_bytes: 5,
+            is_inline: true,
+            content_hash: Some(content_hash.to_string()),
+            content_id: None,
+        }
+    }
+
+    async fn setup_db() -> Database {
+        let db = Database::open_in_memory().await.unwrap();
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO accounts (id, email, provider_type, provider_config, created_at, updated_at)
+                 VALUES ('account-1', 'me@example.com', 'imap', '{}', '2025-01-01', '2025-01-01')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        db
+    }
+
+    async fn blob_rows(db: &Database) -> i64 {
+        db.with_conn(|conn| {
+            let count = conn.query_row("SELECT COUNT(*) FROM blobs", [], |row| row.get(0))?;
+            Ok(count)
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn identical_attachments_share_one_blob() {
+        let db = setup_db().await;
+        let first_hash = store(&db, b"\x89PNG!".to_vec()).await.unwrap();
+        let second_hash = store(&db, b"\x89PNG!".to_vec()).await.unwrap();
+        assert_eq!(first_hash, second_hash);
+        assert_eq!(first_hash, hash(b"\x89PNG!"));
+
+        for (email_id, attachment_id) in [("email-1", "att-1"), ("email-2", "att-2")] {
+            emails::insert(&db, &make_email(email_id)).await.unwrap();
+            attachments::insert(
+                &db,
+                &EmailId::from(email_id),
+                &logo(attachment_id, &first_hash),
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(blob_rows(&db).await, 1);
+        assert_eq!(ref_count(&db, &first_hash).await.unwrap(), Some(2));
+
+        emails::delete(&db, &EmailId::from("email-1"))
+            .await
+            .unwrap();
+        assert_eq!(ref_count(&db, &first_hash).await.unwrap(), Some(1));
+        assert_eq!(
+            load(&db, &first_hash).await.unwrap().as_deref(),
+            Some(&b"\x89PNG!"[..])
+        );
+
+        emails::delete(&db, &EmailId::from("email-2"))
+            .await
+            .unwrap();
+        assert_eq!(ref_count(&db, &first_hash).await.unwrap(), None);
+        assert_eq!(blob_rows(&db).await, 0);
+    }
+
+    #[tokio::test]
+    async fn written_blobs_are_stored_once() {
+        let db = setup_db().await;
+        let content: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 253) as u8).collect();
+
+        let mut writer = BlobWriter::new().unwrap();
+        for chunk in content.chunks(64 * 1024) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.size(), content.len() as u64);
+        let written_hash = store_written(&db, writer).await.unwrap();
+        assert_eq!(written_hash, hash(&content));
+        assert!(load(&db, &written_hash).await.unwrap() == Some(content.clone()));
+
+        let mut writer = BlobWriter::new().unwrap();
+        writer.write_all(&content).unwrap();
+        assert_eq!(store_written(&db, writer).await.unwrap(), written_hash);
+        assert_eq!(blob_rows(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn unreferenced_blobs_are_cleaned_up() {
+        let db = setup_db().await;
+        let hash = store(&db, b"orphan".to_vec()).await.unwrap();
+        assert_eq!(ref_count(&db, &hash).await.unwrap(), Some(0));
+
+        assert_eq!(delete_unreferenced(&db).await.unwrap(), 1);
+        assert!(load(&db, &hash).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn thumbnails_are_deleted_with_their_blob() {
+        let db = setup_db().await;
+        let hash = store(&db, b"\x89PNG!".to_vec()).await.unwrap();
+        store_thumbnail(&db, &hash, 64, b"small".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            load_thumbnail(&db, &hash, 64).await.unwrap().as_deref(),
+            Some(&b"small"[..])
+        );
+        assert!(load_thumbnail(&db, &hash, 128).await.unwrap().is_none());
+
+        delete_unreferenced(&db).await.unwrap();
+        assert!(load_thumbnail(&db, &hash, 64).await.unwrap().is_none());
+    }
+}
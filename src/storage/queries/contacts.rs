@@ -1,11 +1,14 @@
 //! Contact database queries.
 //!
 //! CRUD operations for contacts extracted from email interactions.
+//!
+//! Addresses merged into a contact are kept in `contact_addresses`, so a
+//! contact can be found by any of them.
 
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension, Result};
 
-use crate::domain::Contact;
+use crate::domain::{AccountId, Contact};
 
 /// Inserts or updates a contact.
 pub fn upsert(conn: &Connection, contact: &Contact) -> Result<()> {
@@ -30,10 +33,48 @@ pub fn upsert(conn: &Connection, contact: &Contact) -> Result<()> {
     Ok(())
 }
 
+/// Stores a contact as given, replacing its fields and merged addresses.
+pub fn save(conn: &Connection, contact: &Contact) -> Result<()> {
+    conn.execute(
+        "INSERT INTO contacts (id, email, name, frequency, last_contacted, is_vip, notes, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'), datetime('now'))
+         ON CONFLICT(id) DO UPDATE SET
+             email = ?2,
+             name = ?3,
+             frequency = ?4,
+             last_contacted = ?5,
+             is_vip = ?6,
+             notes = ?7,
+             updated_at = datetime('now')",
+        params![
+            contact.id,
+            contact.email,
+            contact.name,
+            contact.frequency,
+            contact.last_contacted.map(|dt| dt.to_rfc3339()),
+            contact.is_vip,
+            contact.notes,
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM contact_addresses WHERE contact_id = ?1",
+        params![contact.id],
+    )?;
+    for alias in &contact.aliases {
+        conn.execute(
+            "INSERT OR REPLACE INTO contact_addresses (address, contact_id) VALUES (?1, ?2)",
+            params![alias, contact.id],
+        )?;
+    }
+    Ok(())
+}
+
 /// Gets a contact by ID.
 pub fn get_by_id(conn: &Connection, id: &str) -> Result<Option<Contact>> {
     conn.query_row(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts WHERE id = ?1",
         params![id],
         row_to_contact,
@@ -44,7 +85,9 @@ pub fn get_by_id(conn: &Connection, id: &str) -> Result<Option<Contact>> {
 /// Gets a contact by email.
 pub fn get_by_email(conn: &Connection, email: &str) -> Result<Option<Contact>> {
     conn.query_row(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts WHERE email = ?1",
         params![email],
         row_to_contact,
@@ -52,10 +95,28 @@ pub fn get_by_email(conn: &Connection, email: &str) -> Result<Option<Contact>> {
     .optional()
 }
 
+/// Gets the contact with an address, either as its email or as an address
+/// merged into it.
+pub fn get_by_address(conn: &Connection, address: &str) -> Result<Option<Contact>> {
+    conn.query_row(
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
+         FROM contacts
+         WHERE email = ?1
+            OR id = (SELECT contact_id FROM contact_addresses WHERE address = ?1)",
+        params![address],
+        row_to_contact,
+    )
+    .optional()
+}
+
 /// Gets all contacts ordered by frequency.
 pub fn get_all_by_frequency(conn: &Connection) -> Result<Vec<Contact>> {
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts ORDER BY frequency DESC",
     )?;
 
@@ -66,7 +127,9 @@ pub fn get_all_by_frequency(conn: &Connection) -> Result<Vec<Contact>> {
 /// Gets all contacts ordered by name.
 pub fn get_all_by_name(conn: &Connection) -> Result<Vec<Contact>> {
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts ORDER BY COALESCE(name, email)",
     )?;
 
@@ -74,10 +137,35 @@ pub fn get_all_by_name(conn: &Connection) -> Result<Vec<Contact>> {
     contacts.collect()
 }
 
+/// Gets the contacts an account has received mail from or sent mail to,
+/// ordered by name.
+pub fn get_by_account(conn: &Connection, account_id: &AccountId) -> Result<Vec<Contact>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = c.id)
+         FROM contacts c
+         WHERE EXISTS (
+             SELECT 1 FROM emails e
+             WHERE e.account_id = ?1
+               AND (e.from_address = c.email
+                    OR e.to_addresses LIKE '%"' || c.email || '"%'
+                    OR e.cc_addresses LIKE '%"' || c.email || '"%'
+                    OR e.bcc_addresses LIKE '%"' || c.email || '"%')
+         )
+         ORDER BY COALESCE(name, email)"#,
+    )?;
+
+    let contacts = stmt.query_map(params![account_id.0], row_to_contact)?;
+    contacts.collect()
+}
+
 /// Gets VIP contacts.
 pub fn get_vip(conn: &Connection) -> Result<Vec<Contact>> {
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts WHERE is_vip = 1 ORDER BY COALESCE(name, email)",
     )?;
 
@@ -88,7 +176,9 @@ pub fn get_vip(conn: &Connection) -> Result<Vec<Contact>> {
 /// Gets recently contacted contacts.
 pub fn get_recent(conn: &Connection, limit: u32) -> Result<Vec<Contact>> {
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts WHERE last_contacted IS NOT NULL
          ORDER BY last_contacted DESC LIMIT ?1",
     )?;
@@ -100,7 +190,9 @@ pub fn get_recent(conn: &Connection, limit: u32) -> Result<Vec<Contact>> {
 /// Gets frequently contacted contacts.
 pub fn get_frequent(conn: &Connection, limit: u32) -> Result<Vec<Contact>> {
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts ORDER BY frequency DESC LIMIT ?1",
     )?;
 
@@ -112,7 +204,9 @@ pub fn get_frequent(conn: &Connection, limit: u32) -> Result<Vec<Contact>> {
 pub fn search(conn: &Connection, query: &str, limit: u32) -> Result<Vec<Contact>> {
     let pattern = format!("%{}%", query);
     let mut stmt = conn.prepare(
-        "SELECT id, email, name, frequency, last_contacted, is_vip, notes
+        "SELECT id, email, name, frequency, last_contacted, is_vip, notes,
+             (SELECT json_group_array(address) FROM contact_addresses
+              WHERE contact_id = contacts.id)
          FROM contacts
          WHERE email LIKE ?1 OR name LIKE ?1
          ORDER BY frequency DESC LIMIT ?2",
@@ -161,12 +255,21 @@ pub fn record_interaction(conn: &Connection, email: &str) -> Result<()> {
 
 /// Deletes a contact.
 pub fn delete(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM contact_addresses WHERE contact_id = ?1",
+        params![id],
+    )?;
     conn.execute("DELETE FROM contacts WHERE id = ?1", params![id])?;
     Ok(())
 }
 
 /// Deletes a contact by email.
 pub fn delete_by_email(conn: &Connection, email: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM contact_addresses
+         WHERE contact_id IN (SELECT id FROM contacts WHERE email = ?1)",
+        params![email],
+    )?;
     conn.execute("DELETE FROM contacts WHERE email = ?1", params![email])?;
     Ok(())
 }
@@ -196,6 +299,7 @@ pub fn exists_by_email(conn: &Connection, email: &str) -> Result<bool> {
 
 fn row_to_contact(row: &rusqlite::Row) -> Result<Contact> {
     let last_contacted: Option<String> = row.get(4)?;
+    let aliases: Option<String> = row.get(7)?;
     Ok(Contact {
         id: row.get(0)?,
         email: row.get(1)?,
@@ -208,6 +312,9 @@ fn row_to_contact(row: &rusqlite::Row) -> Result<Contact> {
         }),
         is_vip: row.get(5)?,
         notes: row.get(6)?,
+        aliases: aliases
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
     })
 }
 
@@ -232,6 +339,7 @@ mod tests {
             last_contacted: None,
             is_vip: false,
             notes: None,
+            aliases: vec![],
         }
     }
 
@@ -358,4 +466,68 @@ mod tests {
         assert_eq!(frequent.len(), 1);
         assert_eq!(frequent[0].email, "frequent@example.com");
     }
+
+    #[test]
+    fn contacts_are_scoped_to_the_accounts_that_mailed_them() {
+        let conn = setup();
+        conn.execute_batch(
+            r#"INSERT INTO accounts (id, email, provider_type, provider_config, created_at, updated_at)
+               VALUES ('account-1', 'me@example.com', 'imap', '{}', '2025-01-01', '2025-01-01'),
+                      ('account-2', 'me@work.example', 'imap', '{}', '2025-01-01', '2025-01-01');
+               INSERT INTO emails (id, account_id, thread_id, message_id, from_address, to_addresses, date, created_at, updated_at)
+               VALUES ('email-1', 'account-1', 'thread-1', '<1@example.com>', 'alice@example.com',
+                       '[{"email":"me@example.com"}]', '2025-01-01', '2025-01-01', '2025-01-01'),
+                      ('email-2', 'account-2', 'thread-2', '<2@example.com>', 'me@work.example',
+                       '[{"email":"bob@example.com","name":"Bob"}]', '2025-01-01', '2025-01-01', '2025-01-01');"#,
+        )
+        .unwrap();
+        upsert(&conn, &make_contact("c1", "alice@example.com")).unwrap();
+        upsert(&conn, &make_contact("c2", "bob@example.com")).unwrap();
+        upsert(&conn, &make_contact("c3", "carol@example.com")).unwrap();
+
+        let emails = |account: &str| -> Vec<String> {
+            get_by_account(&conn, &AccountId::from(account))
+                .unwrap()
+                .into_iter()
+                .map(|contact| contact.email)
+                .collect()
+        };
+        assert_eq!(emails("account-1"), ["alice@example.com"]);
+        assert_eq!(emails("account-2"), ["bob@example.com"]);
+    }
+
+    #[test]
+    fn merged_addresses_are_saved_and_found() {
+        let conn = setup();
+        let mut contact = make_contact("c1", "alice@example.com");
+        contact.aliases = vec![
+            "alice@work.example".to_string(),
+            "a@example.org".to_string(),
+        ];
+        save(&conn, &contact).unwrap();
+
+        let found = get_by_address(&conn, "alice@work.example")
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, "c1");
+        assert_eq!(found.aliases, contact.aliases);
+        assert_eq!(
+            get_by_address(&conn, "alice@example.com")
+                .unwrap()
+                .unwrap()
+                .id,
+            "c1"
+        );
+        assert!(get_by_address(&conn, "bob@example.com").unwrap().is_none());
+
+        // Saving again replaces the addresses.
+        contact.aliases.pop();
+        save(&conn, &contact).unwrap();
+        assert!(get_by_address(&conn, "a@example.org").unwrap().is_none());
+
+        delete(&conn, "c1").unwrap();
+        assert!(get_by_address(&conn, "alice@work.example")
+            .unwrap()
+            .is_none());
+    }
 }
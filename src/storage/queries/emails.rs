@@ -7,12 +7,13 @@ use rusqlite::{params, OptionalExtension, Row};
 
 use crate::domain::{AccountId, Address, Email, EmailId, LabelId, MessageId, ThreadId};
 use crate::storage::database::{Database, Result};
+use crate::storage::queries::attachments;
 
-/// Inserts a new email into the database.
+/// Inserts a new email and its attachments into the database.
 pub async fn insert(db: &Database, email: &Email) -> Result<()> {
     let email = email.clone();
 
-    db.with_conn(move |conn| {
+    db.transaction(move |conn| {
         let now = Utc::now().to_rfc3339();
         let references_json = serde_json::to_string(&email.references).unwrap_or_default();
         let to_json = serde_json::to_string(&email.to).unwrap_or_default();
@@ -59,17 +60,20 @@ pub async fn insert(db: &Database, email: &Email) -> Result<()> {
                 now,
             ],
         )?;
+        for attachment in &email.attachments {
+            attachments::insert_row(conn, &email.id, attachment)?;
+        }
 
         Ok(())
     })
     .await
 }
 
-/// Retrieves an email by its ID.
+/// Retrieves an email and its attachments by its ID.
 pub async fn get_by_id(db: &Database, email_id: &EmailId) -> Result<Option<Email>> {
     let email_id = email_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
@@ -82,17 +86,20 @@ pub async fn get_by_id(db: &Database, email_id: &EmailId) -> Result<Option<Email
             "#,
         )?;
 
-        let result = stmt.query_row([&email_id.0], row_to_email).optional()?;
+        let mut result = stmt.query_row([&email_id.0], row_to_email).optional()?;
+        if let Some(email) = &mut result {
+            email.attachments = attachments::load_for_email(conn, &email.id)?;
+        }
         Ok(result)
     })
     .await
 }
 
-/// Retrieves all emails in a thread.
+/// Retrieves all emails in a thread, with their attachments.
 pub async fn get_by_thread(db: &Database, thread_id: &ThreadId) -> Result<Vec<Email>> {
     let thread_id = thread_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
@@ -107,8 +114,33 @@ pub async fn get_by_thread(db: &Database, thread_id: &ThreadId) -> Result<Vec<Em
         )?;
 
         let rows = stmt.query_map([&thread_id.0], row_to_email)?;
-        let emails: std::result::Result<Vec<_>, _> = rows.collect();
-        Ok(emails?)
+        let mut emails = rows.collect::<std::result::Result<Vec<_>, _>>()?;
+        for email in &mut emails {
+            email.attachments = attachments::load_for_email(conn, &email.id)?;
+        }
+        Ok(emails)
+    })
+    .await
+}
+
+/// Returns the thread holding an account's message with `message_id`.
+pub async fn thread_for_message(
+    db: &Database,
+    account_id: &AccountId,
+    message_id: &MessageId,
+) -> Result<Option<ThreadId>> {
+    let account_id = account_id.clone();
+    let message_id = message_id.clone();
+
+    db.with_reader(move |conn| {
+        let thread_id = conn
+            .query_row(
+                "SELECT thread_id FROM emails WHERE account_id = ?1 AND message_id = ?2 LIMIT 1",
+                params![account_id.0, message_id.0],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(thread_id.map(ThreadId))
     })
     .await
 }
@@ -122,7 +154,7 @@ pub async fn get_by_account(
 ) -> Result<Vec<Email>> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
@@ -185,11 +217,66 @@ pub async fn delete(db: &Database, email_id: &EmailId) -> Result<()> {
     .await
 }
 
+/// Drops the bodies and downloaded attachments of emails dated before
+/// `older_than`, keeping headers and snippets so they stay searchable.
+///
+/// Drafts, starred emails and emails in starred threads are never evicted.
+/// Returns the number of emails evicted.
+pub async fn evict_bodies(db: &Database, older_than: DateTime<Utc>) -> Result<usize> {
+    let cutoff = older_than.to_rfc3339();
+
+    db.transaction(move |tx| {
+        const EVICTABLE: &str = "SELECT id FROM emails
+             WHERE date < ?1 AND is_draft = 0 AND is_starred = 0
+               AND (body_text IS NOT NULL OR body_html IS NOT NULL)
+               AND thread_id NOT IN (SELECT id FROM threads WHERE is_starred = 1)";
+
+        // Clearing the hash releases the blob through the attachment triggers.
+        tx.execute(
+            &format!(
+                "UPDATE attachments SET content_hash = NULL, local_path = NULL
+                 WHERE email_id IN ({})",
+                EVICTABLE
+            ),
+            [&cutoff],
+        )?;
+        let evicted = tx.execute(
+            &format!(
+                "UPDATE emails SET body_text = NULL, body_html = NULL, updated_at = ?2
+                 WHERE id IN ({})",
+                EVICTABLE
+            ),
+            params![cutoff, Utc::now().to_rfc3339()],
+        )?;
+        Ok(evicted)
+    })
+    .await
+}
+
+/// Stores a body fetched again from the provider after eviction.
+pub async fn restore_body(
+    db: &Database,
+    email_id: &EmailId,
+    body_text: Option<String>,
+    body_html: Option<String>,
+) -> Result<()> {
+    let email_id = email_id.clone();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "UPDATE emails SET body_text = ?1, body_html = ?2, updated_at = ?3 WHERE id = ?4",
+            params![body_text, body_html, Utc::now().to_rfc3339(), email_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
 /// Counts emails in a thread.
 pub async fn count_in_thread(db: &Database, thread_id: &ThreadId) -> Result<u32> {
     let thread_id = thread_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM emails WHERE thread_id = ?1",
             [&thread_id.0],
@@ -204,7 +291,7 @@ pub async fn count_in_thread(db: &Database, thread_id: &ThreadId) -> Result<u32>
 pub async fn count_unread_in_thread(db: &Database, thread_id: &ThreadId) -> Result<u32> {
     let thread_id = thread_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM emails WHERE thread_id = ?1 AND is_read = 0",
             [&thread_id.0],
@@ -259,13 +346,16 @@ fn row_to_email(row: &Row<'_>) -> std::result::Result<Email, rusqlite::Error> {
         is_starred: row.get::<_, i32>(17)? != 0,
         is_draft: row.get::<_, i32>(18)? != 0,
         labels,
-        attachments: vec![], // Loaded separately if needed
+        attachments: vec![], // Loaded by get_by_id and get_by_thread
+        unsubscribe: None,
+        read_receipt_to: None,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::Attachment;
 
     fn make_test_email() -> Email {
         Email {
@@ -289,6 +379,8 @@ mod tests {
             is_draft: false,
             labels: vec![LabelId::from("INBOX")],
             attachments: vec![],
+            unsubscribe: None,
+            read_receipt_to: None,
         }
     }
 
@@ -354,6 +446,24 @@ mod tests {
         assert_eq!(emails.len(), 2);
     }
 
+    #[tokio::test]
+    async fn finds_the_thread_of_a_message_id() {
+        let db = setup_db_with_account().await;
+        let email = make_test_email();
+        insert(&db, &email).await.unwrap();
+
+        let found = thread_for_message(&db, &email.account_id, &email.message_id)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(email.thread_id));
+
+        let other = AccountId::from("account-2");
+        let missing = thread_for_message(&db, &other, &email.message_id)
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
     #[tokio::test]
     async fn set_read_status() {
         let db = setup_db_with_account().await;
@@ -429,4 +539,84 @@ mod tests {
             .unwrap();
         assert_eq!(count, 1);
     }
+
+    #[tokio::test]
+    async fn evict_old_bodies() {
+        let db = setup_db_with_account().await;
+        let old = Utc::now() - chrono::Duration::days(120);
+
+        let mut plain = make_test_email();
+        plain.id = EmailId::from("old");
+        plain.date = old;
+        let mut recent = make_test_email();
+        recent.id = EmailId::from("recent");
+        let mut draft = make_test_email();
+        draft.id = EmailId::from("draft");
+        draft.date = old;
+        draft.is_draft = true;
+        let mut starred = make_test_email();
+        starred.id = EmailId::from("starred");
+        starred.date = old;
+        starred.is_starred = true;
+        let mut in_starred_thread = make_test_email();
+        in_starred_thread.id = EmailId::from("in-starred-thread");
+        in_starred_thread.thread_id = ThreadId::from("thread-2");
+        in_starred_thread.date = old;
+        for email in [&plain, &recent, &draft, &starred, &in_starred_thread] {
+            insert(&db, email).await.unwrap();
+        }
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO threads (id, account_id, participant_emails, last_message_date,
+                     is_starred, created_at, updated_at)
+                 VALUES ('thread-2', 'account-1', '[]', '2025-01-01', 1, '2025-01-01', '2025-01-01')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let cutoff = Utc::now() - chrono::Duration::days(90);
+        assert_eq!(evict_bodies(&db, cutoff).await.unwrap(), 1);
+        assert_eq!(evict_bodies(&db, cutoff).await.unwrap(), 0);
+
+        let evicted = get_by_id(&db, &plain.id).await.unwrap().unwrap();
+        assert!(!evicted.has_body());
+        assert_eq!(evicted.snippet, "Test body");
+        assert_eq!(evicted.subject.as_deref(), Some("Test Subject"));
+        for id in ["recent", "draft", "starred", "in-starred-thread"] {
+            let kept = get_by_id(&db, &EmailId::from(id)).await.unwrap().unwrap();
+            assert!(kept.has_body(), "{} was evicted", id);
+        }
+
+        restore_body(&db, &plain.id, Some("Test body".to_string()), None)
+            .await
+            .unwrap();
+        let restored = get_by_id(&db, &plain.id).await.unwrap().unwrap();
+        assert_eq!(restored.body_text.as_deref(), Some("Test body"));
+    }
+
+    #[tokio::test]
+    async fn attachments_are_stored_and_loaded_with_their_email() {
+        let db = setup_db_with_account().await;
+        let mut email = make_test_email();
+        email.attachments = vec![Attachment {
+            id: "att-1".to_string(),
+            filename: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 4,
+            is_inline: false,
+            content_hash: None,
+            content_id: None,
+        }];
+        insert(&db, &email).await.unwrap();
+
+        let stored = get_by_id(&db, &email.id).await.unwrap().unwrap();
+        assert_eq!(stored.attachments.len(), 1);
+        assert_eq!(stored.attachments[0].filename, "report.pdf");
+        assert_eq!(stored.attachments[0].content_type, "application/pdf");
+        let thread = get_by_thread(&db, &email.thread_id).await.unwrap();
+        assert_eq!(thread[0].attachments[0].id, "att-1");
+    }
 }
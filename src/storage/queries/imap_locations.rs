@@ -0,0 +1,204 @@
+//! IMAP message location queries.
+//!
+//! IMAP addresses messages by folder and UID, both of which change when a
+//! message moves. The IMAP provider gives each message a stable ID and keeps
+//! where it was last seen here, so stored thread IDs still resolve after a
+//! restart. Each folder's UIDVALIDITY is kept too, so UIDs renumbered while
+//! the app was closed are noticed.
+
+use rusqlite::{params, Row};
+
+use crate::domain::AccountId;
+use crate::storage::database::{Database, Result};
+
+/// Where an IMAP message was last seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImapLocation {
+    /// Stable ID of the message.
+    pub id: String,
+    /// Folder holding the message.
+    pub folder: String,
+    /// UID within the folder, unknown after the message was moved.
+    pub uid: Option<u32>,
+    /// Message-ID without angle brackets.
+    pub message_id: Option<String>,
+}
+
+/// Retrieves every location saved for an account.
+pub async fn get_by_account(db: &Database, account_id: &AccountId) -> Result<Vec<ImapLocation>> {
+    let account_id = account_id.clone();
+
+    db.with_reader(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, folder, uid, message_id FROM imap_locations WHERE account_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![account_id.0], row_to_location)?;
+        let locations: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(locations?)
+    })
+    .await
+}
+
+/// Saves locations for an account, replacing any with the same IDs.
+pub async fn upsert(
+    db: &Database,
+    account_id: &AccountId,
+    locations: &[ImapLocation],
+) -> Result<()> {
+    let account_id = account_id.clone();
+    let locations = locations.to_vec();
+
+    db.transaction(move |tx| {
+        let mut stmt = tx.prepare(
+            "INSERT INTO imap_locations (account_id, id, folder, uid, message_id)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(account_id, id) DO UPDATE SET
+                folder = excluded.folder,
+                uid = excluded.uid,
+                message_id = excluded.message_id",
+        )?;
+        for location in &locations {
+            stmt.execute(params![
+                account_id.0,
+                location.id,
+                location.folder,
+                location.uid,
+                location.message_id,
+            ])?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes the locations of an account with the given IDs.
+pub async fn delete(db: &Database, account_id: &AccountId, ids: &[String]) -> Result<()> {
+    let account_id = account_id.clone();
+    let ids = ids.to_vec();
+
+    db.transaction(move |tx| {
+        let mut stmt =
+            tx.prepare("DELETE FROM imap_locations WHERE account_id = ?1 AND id = ?2")?;
+        for id in &ids {
+            stmt.execute(params![account_id.0, id])?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Retrieves the UIDVALIDITY saved for each of an account's folders.
+pub async fn get_uid_validities(
+    db: &Database,
+    account_id: &AccountId,
+) -> Result<Vec<(String, u32)>> {
+    let account_id = account_id.clone();
+
+    db.with_reader(move |conn| {
+        let mut stmt =
+            conn.prepare("SELECT folder, uid_validity FROM imap_folders WHERE account_id = ?1")?;
+        let rows = stmt.query_map(params![account_id.0], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let folders: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(folders?)
+    })
+    .await
+}
+
+/// Saves a folder's UIDVALIDITY, replacing the one saved before.
+pub async fn set_uid_validity(
+    db: &Database,
+    account_id: &AccountId,
+    folder: &str,
+    uid_validity: u32,
+) -> Result<()> {
+    let account_id = account_id.clone();
+    let folder = folder.to_string();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "INSERT INTO imap_folders (account_id, folder, uid_validity) VALUES (?1, ?2, ?3)
+             ON CONFLICT(account_id, folder) DO UPDATE SET uid_validity = excluded.uid_validity",
+            params![account_id.0, folder, uid_validity],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+fn row_to_location(row: &Row<'_>) -> rusqlite::Result<ImapLocation> {
+    Ok(ImapLocation {
+        id: row.get(0)?,
+        folder: row.get(1)?,
+        uid: row.get(2)?,
+        message_id: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup() -> (Database, AccountId) {
+        let db = Database::open_in_memory().await.unwrap();
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO accounts (id, email, provider_type, provider_config, created_at, updated_at)
+                 VALUES ('account-1', 'me@example.com', 'imap', '{}', '2025-01-01', '2025-01-01')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        (db, AccountId::from("account-1"))
+    }
+
+    fn location(id: &str, folder: &str, uid: Option<u32>) -> ImapLocation {
+        ImapLocation {
+            id: id.to_string(),
+            folder: folder.to_string(),
+            uid,
+            message_id: Some(format!("{}@example.com", id)),
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_locations_are_replaced_and_deleted() {
+        let (db, account) = setup().await;
+        upsert(
+            &db,
+            &account,
+            &[
+                location("a", "INBOX", Some(1)),
+                location("b", "INBOX", Some(2)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        upsert(&db, &account, &[location("a", "Archive", None)])
+            .await
+            .unwrap();
+        delete(&db, &account, &["b".to_string()]).await.unwrap();
+
+        assert_eq!(
+            get_by_account(&db, &account).await.unwrap(),
+            vec![location("a", "Archive", None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn uid_validity_is_kept_per_folder() {
+        let (db, account) = setup().await;
+        set_uid_validity(&db, &account, "INBOX", 1).await.unwrap();
+        set_uid_validity(&db, &account, "Archive", 7).await.unwrap();
+        set_uid_validity(&db, &account, "INBOX", 2).await.unwrap();
+
+        let mut saved = get_uid_validities(&db, &account).await.unwrap();
+        saved.sort();
+        assert_eq!(
+            saved,
+            vec![("Archive".to_string(), 7), ("INBOX".to_string(), 2)]
+        );
+    }
+}
@@ -3,8 +3,16 @@
 //! Each module provides async functions that operate on the database.
 
 pub mod accounts;
+pub mod attachments;
+pub mod blobs;
 pub mod contacts;
 pub mod emails;
+pub mod imap_locations;
 pub mod labels;
 pub mod screener;
+pub mod storage_stats;
+pub mod summaries;
+pub mod templates;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod threads;
@@ -0,0 +1,286 @@
+//! Disk usage queries.
+//!
+//! Reports how much of the database each account, thread and the vector
+//! store take up, so the settings UI can show usage and offer cleanup.
+
+use rusqlite::Connection;
+
+use crate::domain::{AccountId, ThreadId};
+use crate::storage::database::{Database, Result};
+
+/// Number of threads reported in [`StorageStats::largest_threads`].
+const LARGEST_THREADS: usize = 10;
+
+/// Bytes of message content stored for a row of the emails table `e`.
+const EMAIL_BYTES: &str = "COALESCE(LENGTH(CAST(e.subject AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.body_text AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.body_html AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.snippet AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.raw_headers AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.to_addresses AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.cc_addresses AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.bcc_addresses AS BLOB)), 0)
+    + COALESCE(LENGTH(CAST(e.references_json AS BLOB)), 0)";
+
+/// Disk usage of the local database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Size of the database, in bytes.
+    pub total_bytes: u64,
+    /// Usage per account, largest first.
+    pub accounts: Vec<AccountStorageStats>,
+    /// The threads using the most space, largest first.
+    pub largest_threads: Vec<ThreadStorageStats>,
+    /// Bytes used by stored embeddings.
+    pub vector_store_bytes: u64,
+}
+
+impl StorageStats {
+    /// Returns the usage of one account.
+    pub fn account(&self, account_id: &AccountId) -> Option<&AccountStorageStats> {
+        self.accounts.iter().find(|a| &a.account_id == account_id)
+    }
+}
+
+/// Disk usage of one account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStorageStats {
+    /// The account.
+    pub account_id: AccountId,
+    /// Number of stored emails.
+    pub email_count: u64,
+    /// Bytes of stored message content (headers and bodies).
+    pub email_bytes: u64,
+    /// Bytes of downloaded attachments. Content shared between emails is
+    /// counted for each of them.
+    pub attachment_bytes: u64,
+}
+
+impl AccountStorageStats {
+    /// Returns the account's total usage.
+    pub fn total_bytes(&self) -> u64 {
+        self.email_bytes + self.attachment_bytes
+    }
+}
+
+/// Disk usage of one thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadStorageStats {
+    /// The thread.
+    pub thread_id: ThreadId,
+    /// Account the thread belongs to.
+    pub account_id: AccountId,
+    /// Subject of the thread.
+    pub subject: Option<String>,
+    /// Number of stored emails in the thread.
+    pub email_count: u64,
+    /// Bytes of stored message content and downloaded attachments.
+    pub bytes: u64,
+}
+
+/// Computes disk usage for the whole database.
+pub async fn get(db: &Database) -> Result<StorageStats> {
+    db.with_reader(|conn| {
+        Ok(StorageStats {
+            total_bytes: total_bytes(conn)?,
+            accounts: accounts(conn)?,
+            largest_threads: largest_threads(conn)?,
+            vector_store_bytes: vector_store_bytes(conn)?,
+        })
+    })
+    .await
+}
+
+fn total_bytes(conn: &Connection) -> rusqlite::Result<u64> {
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((page_count * page_size) as u64)
+}
+
+fn accounts(conn: &Connection) -> rusqlite::Result<Vec<AccountStorageStats>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.account_id, COUNT(*), SUM({}),
+             (SELECT COALESCE(SUM(a.size_bytes), 0)
+              FROM attachments a JOIN emails ae ON ae.id = a.email_id
+              WHERE ae.account_id = e.account_id
+                AND (a.local_path IS NOT NULL OR a.content_hash IS NOT NULL)) AS attachment_bytes
+         FROM emails e
+         GROUP BY e.account_id",
+        EMAIL_BYTES
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AccountStorageStats {
+            account_id: AccountId(row.get(0)?),
+            email_count: row.get::<_, i64>(1)? as u64,
+            email_bytes: row.get::<_, i64>(2)? as u64,
+            attachment_bytes: row.get::<_, i64>(3)? as u64,
+        })
+    })?;
+    let mut accounts = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    accounts.sort_by_key(|a| std::cmp::Reverse(a.total_bytes()));
+    Ok(accounts)
+}
+
+fn largest_threads(conn: &Connection) -> rusqlite::Result<Vec<ThreadStorageStats>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.thread_id, e.account_id, t.subject, COUNT(*),
+             SUM({}) + COALESCE(SUM(
+                 (SELECT SUM(a.size_bytes) FROM attachments a
+                  WHERE a.email_id = e.id
+                    AND (a.local_path IS NOT NULL OR a.content_hash IS NOT NULL))), 0) AS bytes
+         FROM emails e LEFT JOIN threads t ON t.id = e.thread_id
+         GROUP BY e.thread_id
+         ORDER BY bytes DESC
+         LIMIT ?1",
+        EMAIL_BYTES
+    ))?;
+    let rows = stmt.query_map([LARGEST_THREADS as i64], |row| {
+        Ok(ThreadStorageStats {
+            thread_id: ThreadId(row.get(0)?),
+            account_id: AccountId(row.get(1)?),
+            subject: row.get(2)?,
+            email_count: row.get::<_, i64>(3)? as u64,
+            bytes: row.get::<_, i64>(4)? as u64,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Measures the embeddings table with the `dbstat` virtual table, falling
+/// back to summing vector sizes when SQLite is built without it.
+fn vector_store_bytes(conn: &Connection) -> rusqlite::Result<u64> {
+    let pages: Option<i64> = conn
+        .query_row(
+            "SELECT SUM(pgsize) FROM dbstat
+             WHERE name IN ('embeddings', 'sqlite_autoindex_embeddings_1')",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+    if let Some(bytes) = pages {
+        return Ok(bytes as u64);
+    }
+
+    let bytes: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(LENGTH(embedding)), 0) FROM embeddings",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{test_email, Address, Email, MessageId};
+    use crate::storage::queries::emails;
+
+    fn make_email(account_id: &str, n: usize) -> Email {
+        Email {
+            account_id: AccountId::from(account_id),
+            message_id: MessageId::from(format!("<{}-{}@example.com>", account_id, n)),
+            to: vec![Address::new("recipient@example.com")],
+            subject: Some("Quarterly report".to_string()),
+            body_text: Some("The numbers are in. ".repeat(20)),
+            snippet: "The numbers are in.".to_string(),
+            ..test_email(
+                &format!("{}-email-{}", account_id, n),
+                &format!("{}-thread-{}", account_id, n % 3),
+            )
+        }
+    }
+
+    async fn setup_db() -> Database {
+        let db = Database::open_in_memory().await.unwrap();
+        db.with_conn(|conn| {
+            for id in ["account-1", "account-2"] {
+                conn.execute(
+                    "INSERT INTO accounts (id, email, provider_type, provider_config, created_at, updated_at)
+                     VALUES (?1, ?1, 'imap', '{}', '2025-01-01', '2025-01-01')",
+                    [id],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn account_usage_grows_with_emails() {
+        let db = setup_db().await;
+        emails::insert(&db, &make_email("account-2", 0))
+            .await
+            .unwrap();
+        for n in 0..5 {
+            emails::insert(&db, &make_email("account-1", n))
+                .await
+                .unwrap();
+        }
+        let before = db.storage_stats().await.unwrap();
+        let first = before
+            .account(&AccountId::from("account-1"))
+            .unwrap()
+            .clone();
+        assert_eq!(first.email_count, 5);
+        assert!(first.email_bytes > 0);
+        assert!(before.total_bytes > 0);
+
+        for n in 5..15 {
+            emails::insert(&db, &make_email("account-1", n))
+                .await
+                .unwrap();
+        }
+        let after = db.storage_stats().await.unwrap();
+        let account = after.account(&AccountId::from("account-1")).unwrap();
+        assert_eq!(account.email_count, 15);
+        assert_eq!(account.email_bytes, first.email_bytes * 3);
+        assert_eq!(account.attachment_bytes, 0);
+        assert_eq!(after.accounts[0].account_id, AccountId::from("account-1"));
+        assert_eq!(
+            after
+                .account(&AccountId::from("account-2"))
+                .unwrap()
+                .email_count,
+            1
+        );
+        assert!(after.total_bytes >= before.total_bytes);
+
+        // Emails are spread over three threads of five.
+        let largest = &after.largest_threads[0];
+        assert_eq!(largest.email_count, 5);
+        assert_eq!(largest.bytes, first.email_bytes);
+    }
+
+    #[tokio::test]
+    async fn downloaded_attachments_and_embeddings_are_counted() {
+        let db = setup_db().await;
+        emails::insert(&db, &make_email("account-1", 0))
+            .await
+            .unwrap();
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO attachments (id, email_id, filename, size_bytes, local_path, created_at)
+                 VALUES ('a1', 'account-1-email-0', 'report.pdf', 4096, '/tmp/report.pdf', '2025-01-01'),
+                        ('a2', 'account-1-email-0', 'big.zip', 90000, NULL, '2025-01-01')",
+                [],
+            )?;
+            conn.execute(
+                "INSERT INTO embeddings (email_id, embedding, created_at)
+                 VALUES ('account-1-email-0', zeroblob(1536), '2025-01-01')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let stats = db.storage_stats().await.unwrap();
+        let account = stats.account(&AccountId::from("account-1")).unwrap();
+        assert_eq!(account.attachment_bytes, 4096);
+        assert_eq!(stats.largest_threads[0].bytes, account.total_bytes());
+        assert!(stats.vector_store_bytes >= 1536);
+    }
+}
@@ -0,0 +1,88 @@
+//! Summary cache database queries.
+//!
+//! Stores one AI summary per thread, serialized as JSON, alongside the hash
+//! of the thread content it was generated from.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+use crate::domain::ThreadId;
+
+/// Gets the cached summary JSON for a thread if it was generated from
+/// content with `content_hash`.
+pub fn get(conn: &Connection, thread_id: &ThreadId, content_hash: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT summary FROM summaries WHERE thread_id = ?1 AND content_hash = ?2",
+        params![thread_id.0, content_hash],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Stores a thread's summary JSON, replacing any previous one.
+pub fn upsert(
+    conn: &Connection,
+    thread_id: &ThreadId,
+    content_hash: &str,
+    summary: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO summaries (thread_id, content_hash, summary, created_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(thread_id) DO UPDATE SET
+            content_hash = excluded.content_hash,
+            summary = excluded.summary,
+            created_at = excluded.created_at",
+        params![thread_id.0, content_hash, summary, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Deletes a thread's cached summary.
+pub fn delete(conn: &Connection, thread_id: &ThreadId) -> Result<()> {
+    conn.execute(
+        "DELETE FROM summaries WHERE thread_id = ?1",
+        params![thread_id.0],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in super::super::super::schema::all_migrations() {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn get_requires_matching_hash() {
+        let conn = setup();
+        let thread = ThreadId::from("thread-1");
+        upsert(&conn, &thread, "abc", r#"{"text":"One"}"#).unwrap();
+
+        assert_eq!(
+            get(&conn, &thread, "abc").unwrap().as_deref(),
+            Some(r#"{"text":"One"}"#)
+        );
+        assert!(get(&conn, &thread, "def").unwrap().is_none());
+    }
+
+    #[test]
+    fn upsert_replaces_and_delete_removes() {
+        let conn = setup();
+        let thread = ThreadId::from("thread-1");
+        upsert(&conn, &thread, "abc", "one").unwrap();
+        upsert(&conn, &thread, "def", "two").unwrap();
+
+        assert!(get(&conn, &thread, "abc").unwrap().is_none());
+        assert_eq!(get(&conn, &thread, "def").unwrap().as_deref(), Some("two"));
+
+        delete(&conn, &thread).unwrap();
+        assert!(get(&conn, &thread, "def").unwrap().is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Template database queries.
+//!
+//! CRUD operations for canned responses.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+
+use crate::domain::Template;
+
+/// Inserts a new template.
+pub fn insert(conn: &Connection, template: &Template) -> Result<()> {
+    conn.execute(
+        "INSERT INTO templates (id, name, body, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            template.id,
+            template.name,
+            template.body,
+            template.created_at.to_rfc3339(),
+            template.updated_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Gets a template by name.
+pub fn get_by_name(conn: &Connection, name: &str) -> Result<Option<Template>> {
+    conn.query_row(
+        "SELECT id, name, body, created_at, updated_at FROM templates WHERE name = ?1",
+        params![name],
+        row_to_template,
+    )
+    .optional()
+}
+
+/// Gets all templates, ordered by name.
+pub fn list(conn: &Connection) -> Result<Vec<Template>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, body, created_at, updated_at FROM templates ORDER BY name COLLATE NOCASE",
+    )?;
+    let templates = stmt.query_map([], row_to_template)?;
+    templates.collect()
+}
+
+/// Updates a template's body.
+pub fn set_body(conn: &Connection, name: &str, body: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE templates SET body = ?1, updated_at = ?2 WHERE name = ?3",
+        params![body, Utc::now().to_rfc3339(), name],
+    )?;
+    Ok(())
+}
+
+/// Deletes a template by name.
+pub fn delete(conn: &Connection, name: &str) -> Result<()> {
+    conn.execute("DELETE FROM templates WHERE name = ?1", params![name])?;
+    Ok(())
+}
+
+fn row_to_template(row: &Row<'_>) -> Result<Template> {
+    Ok(Template {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        body: row.get(2)?,
+        created_at: parse_timestamp(&row.get::<_, String>(3)?),
+        updated_at: parse_timestamp(&row.get::<_, String>(4)?),
+    })
+}
+
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in super::super::super::schema::all_migrations() {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn insert_get_and_list() {
+        let conn = setup();
+        insert(&conn, &Template::new("thanks", "Thanks {{first_name}}!")).unwrap();
+        insert(&conn, &Template::new("Decline", "Not this time.")).unwrap();
+
+        let fetched = get_by_name(&conn, "thanks").unwrap().unwrap();
+        assert_eq!(fetched.body, "Thanks {{first_name}}!");
+
+        let names: Vec<String> = list(&conn).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Decline", "thanks"]);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let conn = setup();
+        insert(&conn, &Template::new("thanks", "One")).unwrap();
+        assert!(insert(&conn, &Template::new("thanks", "Two")).is_err());
+    }
+
+    #[test]
+    fn update_and_delete() {
+        let conn = setup();
+        insert(&conn, &Template::new("thanks", "Thanks")).unwrap();
+
+        set_body(&conn, "thanks", "Thank you").unwrap();
+        assert_eq!(
+            get_by_name(&conn, "thanks").unwrap().unwrap().body,
+            "Thank you"
+        );
+
+        delete(&conn, "thanks").unwrap();
+        assert!(get_by_name(&conn, "thanks").unwrap().is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! Shared fixtures for query tests.
+//!
+//! [`Dataset::generate`] builds a realistic, deterministic mailbox and
+//! [`seed`] writes it to a database, so each query module's tests run
+//! against the same data.
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::domain::{
+    test_email, Account, AccountId, Address, Contact, Email, FolderMapping, Label, LabelId,
+    ProviderConfig, ProviderType, ThreadId, ThreadSummary,
+};
+use crate::storage::database::{Database, Result};
+use crate::storage::queries::{accounts, contacts, emails, labels, threads};
+
+/// Senders of generated threads, in turn. The first is a VIP.
+pub const SENDERS: [&str; 4] = [
+    "alice@example.com",
+    "bob@example.com",
+    "carol@example.com",
+    "dave@example.com",
+];
+
+/// Messages in each generated thread.
+pub const EMAILS_PER_THREAD: usize = 2;
+
+/// A mailbox to seed a database with.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    pub accounts: Vec<Account>,
+    pub labels: Vec<Label>,
+    pub threads: Vec<ThreadSummary>,
+    pub emails: Vec<Email>,
+    pub contacts: Vec<Contact>,
+}
+
+impl Dataset {
+    /// Generates `accounts` accounts with `threads_per_account` threads each.
+    ///
+    /// Thread `i` of an account is `i` hours older than thread 0 and:
+    /// - is in the inbox unless `i % 5 == 4` (archived),
+    /// - has the account's work label when `i` is even,
+    /// - has the account's receipts label when `i % 3 == 0`,
+    /// - is read when `i % 3 == 0`,
+    /// - comes from `SENDERS[i % 4]`.
+    pub fn generate(accounts: usize, threads_per_account: usize) -> Self {
+        let mut dataset = Self::default();
+
+        for a in 1..=accounts {
+            let account = account(a);
+            dataset.labels.extend([
+                user_label(&account.id, "work"),
+                user_label(&account.id, "receipts"),
+            ]);
+            for i in 0..threads_per_account {
+                let (summary, thread_emails) = thread(&account, i);
+                dataset.threads.push(summary);
+                dataset.emails.extend(thread_emails);
+            }
+            dataset.accounts.push(account);
+        }
+
+        for (i, sender) in SENDERS.iter().enumerate() {
+            let mut contact = Contact::new(*sender);
+            contact.id = format!("contact-{}", i);
+            contact.frequency = dataset
+                .emails
+                .iter()
+                .filter(|email| email.from.email == *sender)
+                .count() as u32;
+            contact.is_vip = i == 0;
+            dataset.contacts.push(contact);
+        }
+
+        dataset
+    }
+
+    /// Returns the threads of an account, newest first.
+    pub fn threads_of(&self, account_id: &AccountId) -> Vec<&ThreadSummary> {
+        self.threads
+            .iter()
+            .filter(|thread| &thread.account_id == account_id)
+            .collect()
+    }
+
+    /// Returns the emails of an account.
+    pub fn emails_of(&self, account_id: &AccountId) -> Vec<&Email> {
+        self.emails
+            .iter()
+            .filter(|email| &email.account_id == account_id)
+            .collect()
+    }
+}
+
+/// Id of the `n`th generated account, counting from 1.
+pub fn account_id(n: usize) -> AccountId {
+    AccountId(format!("account-{}", n))
+}
+
+/// Id of an account's user label.
+pub fn label_id(account_id: &AccountId, name: &str) -> LabelId {
+    LabelId(format!("{}/{}", account_id.0, name))
+}
+
+/// Writes a dataset to the database.
+pub async fn seed(db: &Database, dataset: &Dataset) -> Result<()> {
+    for account in &dataset.accounts {
+        accounts::insert(db, account).await?;
+    }
+    for thread in &dataset.threads {
+        threads::upsert(db, thread).await?;
+    }
+    for email in &dataset.emails {
+        emails::insert(db, email).await?;
+    }
+
+    let dataset_labels = dataset.labels.clone();
+    let dataset_contacts = dataset.contacts.clone();
+    db.with_conn(move |conn| {
+        for label in &dataset_labels {
+            labels::insert(conn, label)?;
+        }
+        for contact in &dataset_contacts {
+            contacts::upsert(conn, contact)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+fn base_date() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap()
+}
+
+fn account(n: usize) -> Account {
+    Account {
+        id: account_id(n),
+        email: format!("me{}@example.com", n),
+        display_name: Some(format!("Account {}", n)),
+        provider_type: ProviderType::Gmail,
+        provider_config: ProviderConfig::Gmail {},
+        sync_enabled: true,
+        sync_interval: Duration::from_secs(300),
+        signature: None,
+        signature_html: None,
+        folder_mapping: FolderMapping::default(),
+        aliases: vec![],
+    }
+}
+
+fn user_label(account_id: &AccountId, name: &str) -> Label {
+    Label {
+        id: label_id(account_id, name),
+        account_id: account_id.clone(),
+        name: name.to_string(),
+        color: None,
+        is_system: false,
+        provider_id: None,
+    }
+}
+
+fn thread(account: &Account, i: usize) -> (ThreadSummary, Vec<Email>) {
+    let thread_id = ThreadId(format!("{}-thread-{:02}", account.id.0, i));
+    let from = Address::new(SENDERS[i % SENDERS.len()]);
+    let subject = if i % 2 == 0 {
+        format!("Project update {}", i)
+    } else {
+        format!("Order confirmation {}", i)
+    };
+    let is_read = i % 3 == 0;
+
+    let mut thread_labels = vec![];
+    if i % 5 != 4 {
+        thread_labels.push(LabelId::from("INBOX"));
+    }
+    if i % 2 == 0 {
+        thread_labels.push(label_id(&account.id, "work"));
+    }
+    if i % 3 == 0 {
+        thread_labels.push(label_id(&account.id, "receipts"));
+    }
+
+    let last_message_date = base_date() - chrono::Duration::hours(i as i64);
+    let thread_emails: Vec<Email> = (0..EMAILS_PER_THREAD)
+        .map(|n| {
+            let id = format!("{}-email-{}", thread_id.0, n);
+            let age = (EMAILS_PER_THREAD - 1 - n) as i64;
+            Email {
+                account_id: account.id.clone(),
+                from: from.clone(),
+                to: vec![Address::new(&account.email)],
+                subject: Some(subject.clone()),
+                body_text: Some(format!("Message {} of {}", n + 1, subject)),
+                snippet: format!("Message {} of {}", n + 1, subject),
+                date: last_message_date - chrono::Duration::minutes(age),
+                is_read,
+                labels: thread_labels.clone(),
+                ..test_email(&id, &thread_id.0)
+            }
+        })
+        .collect();
+
+    let summary = ThreadSummary {
+        id: thread_id,
+        account_id: account.id.clone(),
+        subject: Some(subject),
+        snippet: thread_emails[EMAILS_PER_THREAD - 1].snippet.clone(),
+        from,
+        last_message_date,
+        message_count: EMAILS_PER_THREAD as u32,
+        unread_count: if is_read { 0 } else { EMAILS_PER_THREAD as u32 },
+        is_starred: false,
+        labels: thread_labels,
+        muted: false,
+        watched: false,
+    };
+
+    (summary, thread_emails)
+}
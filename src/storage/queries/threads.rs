@@ -2,13 +2,19 @@
 //!
 //! Provides database operations for thread entities.
 
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use rusqlite::{params, OptionalExtension, Row};
 
-use crate::domain::{AccountId, Address, LabelId, ThreadId, ThreadSummary};
+use crate::domain::{AccountId, Address, LabelId, ThreadId, ThreadSort, ThreadSummary};
 use crate::storage::database::{Database, Result};
 
 /// Inserts or updates a thread in the database.
+///
+/// The muted and watched flags are only written on insert, so re-syncing a
+/// thread from the provider never resets them; use [`set_muted`] and
+/// [`set_watched`] to change them.
 pub async fn upsert(db: &Database, summary: &ThreadSummary) -> Result<()> {
     let summary = summary.clone();
 
@@ -23,9 +29,9 @@ pub async fn upsert(db: &Database, summary: &ThreadSummary) -> Result<()> {
             INSERT INTO threads (
                 id, account_id, subject, snippet, participant_emails, participant_names,
                 last_message_date, message_count, unread_count, is_starred, labels,
-                created_at, updated_at
+                is_muted, is_watched, created_at, updated_at
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15
             )
             ON CONFLICT(id) DO UPDATE SET
                 subject = excluded.subject,
@@ -51,6 +57,8 @@ pub async fn upsert(db: &Database, summary: &ThreadSummary) -> Result<()> {
                 summary.unread_count,
                 summary.is_starred as i32,
                 labels_json,
+                summary.muted as i32,
+                summary.watched as i32,
                 now,
                 now,
             ],
@@ -65,12 +73,13 @@ pub async fn upsert(db: &Database, summary: &ThreadSummary) -> Result<()> {
 pub async fn get_by_id(db: &Database, thread_id: &ThreadId) -> Result<Option<ThreadSummary>> {
     let thread_id = thread_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, account_id, subject, snippet, participant_emails, participant_names,
-                last_message_date, message_count, unread_count, is_starred, labels
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
             FROM threads
             WHERE id = ?1
             "#,
@@ -91,12 +100,13 @@ pub async fn get_by_account(
 ) -> Result<Vec<ThreadSummary>> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, account_id, subject, snippet, participant_emails, participant_names,
-                last_message_date, message_count, unread_count, is_starred, labels
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
             FROM threads
             WHERE account_id = ?1
             ORDER BY last_message_date DESC
@@ -119,12 +129,13 @@ pub async fn get_unread(
 ) -> Result<Vec<ThreadSummary>> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, account_id, subject, snippet, participant_emails, participant_names,
-                last_message_date, message_count, unread_count, is_starred, labels
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
             FROM threads
             WHERE account_id = ?1 AND unread_count > 0
             ORDER BY last_message_date DESC
@@ -147,12 +158,13 @@ pub async fn get_starred(
 ) -> Result<Vec<ThreadSummary>> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, account_id, subject, snippet, participant_emails, participant_names,
-                last_message_date, message_count, unread_count, is_starred, labels
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
             FROM threads
             WHERE account_id = ?1 AND is_starred = 1
             ORDER BY last_message_date DESC
@@ -177,12 +189,13 @@ pub async fn get_by_label(
     let account_id = account_id.clone();
     let label_pattern = format!("%\"{}\"%%", label_id.0);
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let mut stmt = conn.prepare(
             r#"
             SELECT
                 id, account_id, subject, snippet, participant_emails, participant_names,
-                last_message_date, message_count, unread_count, is_starred, labels
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
             FROM threads
             WHERE account_id = ?1 AND labels LIKE ?2
             ORDER BY last_message_date DESC
@@ -197,6 +210,133 @@ pub async fn get_by_label(
     .await
 }
 
+/// Retrieves the threads of every account in the trash or spam `label_id`
+/// that were moved there before `before`, longest there first.
+///
+/// Threads trashed before their move was recorded count from their last
+/// update.
+pub async fn get_trashed_before(
+    db: &Database,
+    label_id: &LabelId,
+    before: DateTime<Utc>,
+) -> Result<Vec<ThreadSummary>> {
+    let label_pattern = format!("%\"{}\"%%", label_id.0);
+    let before = before.to_rfc3339();
+
+    db.with_reader(move |conn| {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, account_id, subject, snippet, participant_emails, participant_names,
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
+            FROM threads
+            WHERE labels LIKE ?1 AND COALESCE(trashed_at, updated_at) < ?2
+            ORDER BY COALESCE(trashed_at, updated_at) ASC
+            "#,
+        )?;
+
+        let rows = stmt.query_map(params![label_pattern, before], row_to_summary)?;
+        let threads: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(threads?)
+    })
+    .await
+}
+
+/// Retrieves thread summaries for an account in the given order, optionally
+/// only those with `label_id`.
+pub async fn list(
+    db: &Database,
+    account_id: &AccountId,
+    label_id: Option<&LabelId>,
+    sort: ThreadSort,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ThreadSummary>> {
+    let account_id = account_id.clone();
+    let label_pattern = label_id.map(|label_id| format!("%\"{}\"%", label_id.0));
+
+    db.with_reader(move |conn| {
+        let sql = format!(
+            r#"
+            SELECT
+                id, account_id, subject, snippet, participant_emails, participant_names,
+                last_message_date, message_count, unread_count, is_starred, labels, is_muted,
+                is_watched
+            FROM threads t
+            WHERE account_id = ?1 AND (?2 IS NULL OR labels LIKE ?2)
+            ORDER BY {}
+            LIMIT ?3 OFFSET ?4
+            "#,
+            order_by(sort)
+        );
+        let mut stmt = conn.prepare(&sql)?;
+
+        let rows = stmt.query_map(
+            params![account_id.0, label_pattern, limit, offset],
+            row_to_summary,
+        )?;
+        let threads: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(threads?)
+    })
+    .await
+}
+
+/// Returns the ORDER BY clause for a sort over `threads t`.
+///
+/// Importance depends on contacts and classifications scored outside the
+/// database, so [`ThreadSort::PriorityFirst`] lists newest first for the
+/// caller to rank.
+fn order_by(sort: ThreadSort) -> &'static str {
+    match sort {
+        ThreadSort::DateDesc | ThreadSort::PriorityFirst => "t.last_message_date DESC",
+        ThreadSort::DateAsc => "t.last_message_date ASC",
+        ThreadSort::UnreadFirst => "(t.unread_count > 0) DESC, t.last_message_date DESC",
+    }
+}
+
+/// Returns the account's threads with a message that has the account's
+/// address in To.
+pub async fn addressed_to_owner(db: &Database, account_id: &AccountId) -> Result<Vec<ThreadId>> {
+    owner_threads(
+        db,
+        account_id,
+        "EXISTS (
+             SELECT 1 FROM json_each(e.to_addresses) r
+             WHERE lower(json_extract(r.value, '$.email')) = lower(a.email)
+         )",
+    )
+    .await
+}
+
+/// Returns the account's threads with a message sent from the account's
+/// address.
+pub async fn replied_by_owner(db: &Database, account_id: &AccountId) -> Result<Vec<ThreadId>> {
+    owner_threads(db, account_id, "lower(e.from_address) = lower(a.email)").await
+}
+
+/// Returns the distinct threads of the account's emails matching
+/// `condition`, which can refer to the email as `e` and the account as `a`.
+async fn owner_threads(
+    db: &Database,
+    account_id: &AccountId,
+    condition: &'static str,
+) -> Result<Vec<ThreadId>> {
+    let account_id = account_id.clone();
+    db.with_reader(move |conn| {
+        let sql = format!(
+            "SELECT DISTINCT e.thread_id FROM emails e JOIN accounts a ON a.id = e.account_id
+             WHERE e.account_id = ?1 AND {}",
+            condition
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![account_id.0], |row| Ok(ThreadId(row.get(0)?)))?;
+        let ids: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(ids?)
+    })
+    .await
+}
+
 /// Updates the starred status of a thread.
 pub async fn set_starred(db: &Database, thread_id: &ThreadId, is_starred: bool) -> Result<()> {
     let thread_id = thread_id.clone();
@@ -212,6 +352,52 @@ pub async fn set_starred(db: &Database, thread_id: &ThreadId, is_starred: bool)
     .await
 }
 
+/// Updates the muted status of a thread.
+pub async fn set_muted(db: &Database, thread_id: &ThreadId, muted: bool) -> Result<()> {
+    let thread_id = thread_id.clone();
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE threads SET is_muted = ?1, updated_at = ?2 WHERE id = ?3",
+            params![muted as i32, now, thread_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Replaces the labels of a thread.
+pub async fn set_labels(db: &Database, thread_id: &ThreadId, labels: &[LabelId]) -> Result<()> {
+    let thread_id = thread_id.clone();
+    let labels_json = serde_json::to_string(labels).unwrap_or_default();
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE threads SET labels = ?1, updated_at = ?2 WHERE id = ?3",
+            params![labels_json, now, thread_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Updates the watched status of a thread.
+pub async fn set_watched(db: &Database, thread_id: &ThreadId, watched: bool) -> Result<()> {
+    let thread_id = thread_id.clone();
+
+    db.with_conn(move |conn| {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE threads SET is_watched = ?1, updated_at = ?2 WHERE id = ?3",
+            params![watched as i32, now, thread_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
 /// Updates the unread count of a thread.
 pub async fn set_unread_count(db: &Database, thread_id: &ThreadId, count: u32) -> Result<()> {
     let thread_id = thread_id.clone();
@@ -227,11 +413,58 @@ pub async fn set_unread_count(db: &Database, thread_id: &ThreadId, count: u32) -
     .await
 }
 
+/// Recomputes a thread's preview from its stored messages: the snippet of
+/// the newest unread message, or of the newest message when all are read,
+/// as [`Thread::preview_snippet`](crate::domain::Thread::preview_snippet)
+/// picks it. Threads without stored messages keep their snippet.
+pub async fn refresh_snippet(db: &Database, thread_id: &ThreadId) -> Result<()> {
+    let thread_id = thread_id.clone();
+
+    db.with_conn(move |conn| {
+        conn.execute(
+            "UPDATE threads SET snippet = COALESCE(
+                 (SELECT snippet FROM emails WHERE thread_id = ?1
+                  ORDER BY is_read ASC, date DESC LIMIT 1),
+                 snippet)
+             WHERE id = ?1",
+            [&thread_id.0],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Gets the stored previews of threads by id. Threads that aren't stored
+/// are left out.
+pub async fn snippets(db: &Database, thread_ids: &[ThreadId]) -> Result<HashMap<ThreadId, String>> {
+    if thread_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ids: Vec<String> = thread_ids.iter().map(|id| id.0.clone()).collect();
+
+    db.with_reader(move |conn| {
+        let sql = format!(
+            "SELECT id, snippet FROM threads WHERE id IN ({})",
+            vec!["?"; ids.len()].join(", ")
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(&ids), |row| {
+            Ok((
+                ThreadId(row.get(0)?),
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<HashMap<_, _>>>()?)
+    })
+    .await
+}
+
 /// Deletes a thread and all its emails.
 pub async fn delete(db: &Database, thread_id: &ThreadId) -> Result<()> {
     let thread_id = thread_id.clone();
 
     db.transaction(move |tx| {
+        tx.execute("DELETE FROM followups WHERE thread_id = ?1", [&thread_id.0])?;
         tx.execute("DELETE FROM emails WHERE thread_id = ?1", [&thread_id.0])?;
         tx.execute("DELETE FROM threads WHERE id = ?1", [&thread_id.0])?;
         Ok(())
@@ -243,7 +476,7 @@ pub async fn delete(db: &Database, thread_id: &ThreadId) -> Result<()> {
 pub async fn count_by_account(db: &Database, account_id: &AccountId) -> Result<u32> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM threads WHERE account_id = ?1",
             [&account_id.0],
@@ -258,7 +491,7 @@ pub async fn count_by_account(db: &Database, account_id: &AccountId) -> Result<u
 pub async fn count_unread(db: &Database, account_id: &AccountId) -> Result<u32> {
     let account_id = account_id.clone();
 
-    db.with_conn(move |conn| {
+    db.with_reader(move |conn| {
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM threads WHERE account_id = ?1 AND unread_count > 0",
             [&account_id.0],
@@ -269,6 +502,43 @@ pub async fn count_unread(db: &Database, account_id: &AccountId) -> Result<u32>
     .await
 }
 
+/// Aggregates unread messages and threads per label for an account.
+///
+/// Returns `(label, unread_count, thread_count)` rows from a single query.
+/// Starred threads are reported under `STARRED` even when the provider does
+/// not model starring as a label.
+pub async fn label_counts(
+    db: &Database,
+    account_id: &AccountId,
+) -> Result<Vec<(LabelId, u32, u32)>> {
+    let account_id = account_id.clone();
+
+    db.with_reader(move |conn| {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT label, COALESCE(SUM(unread_count), 0), COUNT(*)
+            FROM (
+                SELECT j.value AS label, t.unread_count
+                FROM threads t, json_each(t.labels) j
+                WHERE t.account_id = ?1
+                UNION ALL
+                SELECT 'STARRED', unread_count
+                FROM threads
+                WHERE account_id = ?1 AND is_starred = 1 AND labels NOT LIKE '%"STARRED"%'
+            )
+            GROUP BY label
+            "#,
+        )?;
+
+        let rows = stmt.query_map([&account_id.0], |row| {
+            Ok((LabelId(row.get(0)?), row.get(1)?, row.get(2)?))
+        })?;
+        let counts: std::result::Result<Vec<_>, _> = rows.collect();
+        Ok(counts?)
+    })
+    .await
+}
+
 fn row_to_summary(row: &Row<'_>) -> std::result::Result<ThreadSummary, rusqlite::Error> {
     let participant_emails_json: String = row.get(4)?;
     let participant_names_json: String = row.get(5)?;
@@ -306,12 +576,15 @@ fn row_to_summary(row: &Row<'_>) -> std::result::Result<ThreadSummary, rusqlite:
         unread_count: row.get(8)?,
         is_starred: row.get::<_, i32>(9)? != 0,
         labels,
+        muted: row.get::<_, i32>(11)? != 0,
+        watched: row.get::<_, i32>(12)? != 0,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::IMPORTANT_LABEL;
 
     fn make_test_summary() -> ThreadSummary {
         ThreadSummary {
@@ -325,6 +598,8 @@ mod tests {
             unread_count: 1,
             is_starred: false,
             labels: vec![LabelId::from("INBOX")],
+            muted: false,
+            watched: false,
         }
     }
 
@@ -476,6 +751,51 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn set_labels_replaces_labels() {
+        let db = setup_db_with_account().await;
+        let summary = make_test_summary();
+        upsert(&db, &summary).await.unwrap();
+
+        set_labels(&db, &summary.id, &[LabelId::from("work")])
+            .await
+            .unwrap();
+
+        let inbox = get_by_label(&db, &summary.account_id, &LabelId::from("INBOX"), 10)
+            .await
+            .unwrap();
+        assert!(inbox.is_empty());
+        let retrieved = get_by_id(&db, &summary.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.labels, vec![LabelId::from("work")]);
+    }
+
+    #[tokio::test]
+    async fn muted_status_survives_upsert() {
+        let db = setup_db_with_account().await;
+        let summary = make_test_summary();
+
+        upsert(&db, &summary).await.unwrap();
+        set_muted(&db, &summary.id, true).await.unwrap();
+        upsert(&db, &summary).await.unwrap();
+
+        let retrieved = get_by_id(&db, &summary.id).await.unwrap().unwrap();
+        assert!(retrieved.muted);
+    }
+
+    #[tokio::test]
+    async fn watched_status_survives_upsert() {
+        let db = setup_db_with_account().await;
+        let summary = make_test_summary();
+
+        upsert(&db, &summary).await.unwrap();
+        set_watched(&db, &summary.id, true).await.unwrap();
+        upsert(&db, &summary).await.unwrap();
+
+        let retrieved = get_by_id(&db, &summary.id).await.unwrap().unwrap();
+        assert!(retrieved.watched);
+        assert!(!retrieved.muted);
+    }
+
     #[tokio::test]
     async fn delete_thread() {
         let db = setup_db_with_account().await;
@@ -506,4 +826,290 @@ mod tests {
             .unwrap();
         assert_eq!(count, 2);
     }
+
+    /// Threads numbered by age, thread-1 being the newest and the even ones
+    /// unread. thread-4 is from a VIP, thread-3 is addressed to the account
+    /// and important, and thread-2 is important.
+    async fn setup_sortable_threads() -> Database {
+        let db = setup_db_with_account().await;
+        let now = Utc::now();
+        for i in 1..=4 {
+            let mut summary = make_test_summary();
+            summary.id = ThreadId::from(format!("thread-{}", i));
+            summary.from = Address::new(format!("sender{}@example.com", i));
+            summary.last_message_date = now - chrono::Duration::hours(i);
+            summary.unread_count = (i % 2 == 0) as u32;
+            if i == 2 || i == 3 {
+                summary.labels.push(LabelId::from(IMPORTANT_LABEL));
+            }
+            upsert(&db, &summary).await.unwrap();
+        }
+
+        db.with_conn(|conn| {
+            conn.execute(
+                r#"
+                INSERT INTO emails (
+                    id, account_id, thread_id, message_id, from_address, to_addresses,
+                    date, created_at, updated_at
+                ) VALUES (
+                    'email-3', 'account-1', 'thread-3', '<3@example.com>',
+                    'sender3@example.com', '[{"email":"TEST@example.com","name":null}]',
+                    '2025-01-01', '2025-01-01', '2025-01-01'
+                )
+                "#,
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        db
+    }
+
+    async fn sorted_ids(
+        db: &Database,
+        label_id: Option<&LabelId>,
+        sort: ThreadSort,
+    ) -> Vec<String> {
+        list(db, &AccountId::from("account-1"), label_id, sort, 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id.0)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn list_orders_by_each_sort() {
+        let db = setup_sortable_threads().await;
+
+        assert_eq!(
+            sorted_ids(&db, None, ThreadSort::DateDesc).await,
+            ["thread-1", "thread-2", "thread-3", "thread-4"]
+        );
+        assert_eq!(
+            sorted_ids(&db, None, ThreadSort::DateAsc).await,
+            ["thread-4", "thread-3", "thread-2", "thread-1"]
+        );
+        assert_eq!(
+            sorted_ids(&db, None, ThreadSort::UnreadFirst).await,
+            ["thread-2", "thread-4", "thread-1", "thread-3"]
+        );
+        // Ranked by the caller.
+        assert_eq!(
+            sorted_ids(&db, None, ThreadSort::PriorityFirst).await,
+            ["thread-1", "thread-2", "thread-3", "thread-4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn owner_threads_match_the_account_address() {
+        let db = setup_sortable_threads().await;
+        db.with_conn(|conn| {
+            conn.execute(
+                r#"
+                INSERT INTO emails (
+                    id, account_id, thread_id, message_id, from_address, to_addresses,
+                    date, created_at, updated_at
+                ) VALUES (
+                    'email-1', 'account-1', 'thread-1', '<1@example.com>',
+                    'Test@example.com', '[{"email":"sender1@example.com","name":null}]',
+                    '2025-01-01', '2025-01-01', '2025-01-01'
+                )
+                "#,
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        let account_id = AccountId::from("account-1");
+
+        assert_eq!(
+            addressed_to_owner(&db, &account_id).await.unwrap(),
+            [ThreadId::from("thread-3")]
+        );
+        assert_eq!(
+            replied_by_owner(&db, &account_id).await.unwrap(),
+            [ThreadId::from("thread-1")]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_label_and_paginates() {
+        let db = setup_sortable_threads().await;
+        let important = LabelId::from(IMPORTANT_LABEL);
+
+        assert_eq!(
+            sorted_ids(&db, Some(&important), ThreadSort::DateAsc).await,
+            ["thread-3", "thread-2"]
+        );
+
+        let page = list(
+            &db,
+            &AccountId::from("account-1"),
+            None,
+            ThreadSort::UnreadFirst,
+            2,
+            1,
+        )
+        .await
+        .unwrap();
+        let ids: Vec<_> = page.into_iter().map(|t| t.id.0).collect();
+        assert_eq!(ids, ["thread-4", "thread-1"]);
+    }
+
+    #[tokio::test]
+    async fn label_counts_aggregate_unread_and_threads() {
+        let db = setup_db_with_account().await;
+
+        let mut summary1 = make_test_summary();
+        summary1.id = ThreadId::from("thread-1");
+        summary1.unread_count = 2;
+        summary1.labels = vec![LabelId::from("INBOX"), LabelId::from("work")];
+
+        let mut summary2 = make_test_summary();
+        summary2.id = ThreadId::from("thread-2");
+        summary2.unread_count = 1;
+        summary2.is_starred = true;
+
+        upsert(&db, &summary1).await.unwrap();
+        upsert(&db, &summary2).await.unwrap();
+
+        let mut counts = label_counts(&db, &AccountId::from("account-1"))
+            .await
+            .unwrap();
+        counts.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+
+        assert_eq!(
+            counts,
+            vec![
+                (LabelId::from("INBOX"), 3, 2),
+                (LabelId::from("STARRED"), 1, 1),
+                (LabelId::from("work"), 2, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_pages_cover_every_thread_once() {
+        use crate::storage::queries::test_support::{self, account_id, label_id, Dataset};
+
+        let db = Database::open_in_memory().await.unwrap();
+        let dataset = Dataset::generate(2, 12);
+        test_support::seed(&db, &dataset).await.unwrap();
+        let account = account_id(1);
+        let work = label_id(&account, "work");
+
+        for (label, page_sizes) in [(None, vec![5, 5, 2]), (Some(&work), vec![5, 1])] {
+            let mut seen = vec![];
+            let mut sizes = vec![];
+            for offset in (0..).step_by(5) {
+                let page = list(&db, &account, label, ThreadSort::DateDesc, 5, offset)
+                    .await
+                    .unwrap();
+                if page.is_empty() {
+                    break;
+                }
+                sizes.push(page.len());
+                seen.extend(page.into_iter().map(|thread| thread.id));
+            }
+
+            let expected: Vec<ThreadId> = dataset
+                .threads_of(&account)
+                .into_iter()
+                .filter(|thread| label.map_or(true, |label| thread.labels.contains(label)))
+                .map(|thread| thread.id.clone())
+                .collect();
+            assert_eq!(seen, expected);
+            assert_eq!(sizes, page_sizes);
+        }
+    }
+
+    #[tokio::test]
+    async fn trashed_threads_are_aged_from_when_they_were_trashed() {
+        let db = setup_db_with_account().await;
+        let mut summary = make_test_summary();
+        summary.last_message_date = Utc::now() - chrono::Duration::days(90);
+        upsert(&db, &summary).await.unwrap();
+        let trash = LabelId::from("TRASH");
+        let spam = LabelId::from("SPAM");
+
+        set_labels(&db, &summary.id, &[trash.clone()])
+            .await
+            .unwrap();
+        let week_ago = Utc::now() - chrono::Duration::days(7);
+        assert!(get_trashed_before(&db, &trash, week_ago)
+            .await
+            .unwrap()
+            .is_empty());
+        let trashed = get_trashed_before(&db, &trash, Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(trashed.len(), 1);
+
+        // Moving to spam and syncing keep the original time
+        let db = &db;
+        let trashed_at = || async move {
+            db.with_reader(|conn| {
+                Ok(conn.query_row("SELECT trashed_at FROM threads", [], |row| {
+                    row.get::<_, Option<String>>(0)
+                })?)
+            })
+            .await
+            .unwrap()
+        };
+        let first = trashed_at().await;
+        assert!(first.is_some());
+        set_labels(&db, &summary.id, &[spam.clone()]).await.unwrap();
+        summary.labels = vec![spam];
+        upsert(&db, &summary).await.unwrap();
+        assert_eq!(trashed_at().await, first);
+
+        set_labels(&db, &summary.id, &[LabelId::from("INBOX")])
+            .await
+            .unwrap();
+        assert_eq!(trashed_at().await, None);
+    }
+
+    #[tokio::test]
+    async fn snippet_follows_the_newest_unread_message() {
+        use crate::domain::{test_email, Email, EmailId};
+        use crate::storage::queries::emails;
+
+        let db = setup_db_with_account().await;
+        let summary = make_test_summary();
+        upsert(&db, &summary).await.unwrap();
+        let message = |id: &str, minutes_ago: i64, is_read: bool| Email {
+            snippet: format!("Snippet of {}", id),
+            date: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            is_read,
+            ..test_email(id, "thread-1")
+        };
+        for email in [
+            message("email-1", 30, false),
+            message("email-2", 20, false),
+            message("email-3", 10, true),
+        ] {
+            emails::insert(&db, &email).await.unwrap();
+        }
+
+        refresh_snippet(&db, &summary.id).await.unwrap();
+        let stored = snippets(&db, &[summary.id.clone(), ThreadId::from("missing")])
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[&summary.id], "Snippet of email-2");
+
+        emails::set_read(&db, &EmailId::from("email-2"), true)
+            .await
+            .unwrap();
+        emails::set_read(&db, &EmailId::from("email-1"), true)
+            .await
+            .unwrap();
+        refresh_snippet(&db, &summary.id).await.unwrap();
+        let stored = get_by_id(&db, &summary.id).await.unwrap().unwrap();
+        assert_eq!(stored.snippet, "Snippet of email-3");
+    }
 }
@@ -13,6 +13,9 @@ CREATE TABLE IF NOT EXISTS accounts (
     sync_enabled INTEGER DEFAULT 1,
     sync_interval_seconds INTEGER DEFAULT 300,
     signature TEXT,
+    signature_html TEXT,
+    folder_mapping TEXT,
+    aliases TEXT,
     created_at TEXT NOT NULL,
     updated_at TEXT NOT NULL
 )
@@ -52,7 +55,8 @@ pub const CREATE_EMAIL_INDEXES: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_emails_account ON emails(account_id);
 CREATE INDEX IF NOT EXISTS idx_emails_thread ON emails(thread_id);
 CREATE INDEX IF NOT EXISTS idx_emails_date ON emails(date DESC);
-CREATE INDEX IF NOT EXISTS idx_emails_from ON emails(from_address)
+CREATE INDEX IF NOT EXISTS idx_emails_from ON emails(from_address);
+CREATE INDEX IF NOT EXISTS idx_emails_message_id ON emails(account_id, message_id)
 "#;
 
 /// SQL to create the threads table.
@@ -69,8 +73,11 @@ CREATE TABLE IF NOT EXISTS threads (
     unread_count INTEGER DEFAULT 0,
     is_starred INTEGER DEFAULT 0,
     labels TEXT,
+    is_muted INTEGER DEFAULT 0,
+    is_watched INTEGER DEFAULT 0,
     created_at TEXT NOT NULL,
-    updated_at TEXT NOT NULL
+    updated_at TEXT NOT NULL,
+    trashed_at TEXT
 )
 "#;
 
@@ -80,6 +87,25 @@ CREATE INDEX IF NOT EXISTS idx_threads_account ON threads(account_id);
 CREATE INDEX IF NOT EXISTS idx_threads_date ON threads(last_message_date DESC)
 "#;
 
+/// SQL to create triggers recording when a thread entered the trash or
+/// spam in `trashed_at`, clearing it once the thread leaves both.
+///
+/// Moving a thread between trash and spam keeps the original time.
+pub const CREATE_THREAD_TRASH_TRIGGERS: &str = r#"
+CREATE TRIGGER IF NOT EXISTS threads_trashed_ai AFTER INSERT ON threads
+WHEN NEW.labels LIKE '%"TRASH"%' OR NEW.labels LIKE '%"SPAM"%' BEGIN
+    UPDATE threads SET trashed_at = NEW.updated_at WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS threads_trashed_au AFTER UPDATE OF labels ON threads BEGIN
+    UPDATE threads SET trashed_at = CASE
+        WHEN NEW.labels LIKE '%"TRASH"%' OR NEW.labels LIKE '%"SPAM"%'
+        THEN COALESCE(OLD.trashed_at, NEW.updated_at)
+    END
+    WHERE id = NEW.id;
+END
+"#;
+
 /// SQL to create the labels table.
 pub const CREATE_LABELS: &str = r#"
 CREATE TABLE IF NOT EXISTS labels (
@@ -104,15 +130,79 @@ CREATE TABLE IF NOT EXISTS attachments (
     content_id TEXT,
     is_inline INTEGER DEFAULT 0,
     local_path TEXT,
+    content_hash TEXT,
     created_at TEXT NOT NULL
 )
 "#;
 
+/// SQL to create the content-addressed blob table for attachment bodies.
+///
+/// Blobs are keyed by the SHA-256 of their content, so identical attachments
+/// are stored once. `ref_count` counts the attachments referencing a blob.
+pub const CREATE_BLOBS: &str = r#"
+CREATE TABLE IF NOT EXISTS blobs (
+    hash TEXT PRIMARY KEY,
+    data BLOB NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_email ON attachments(email_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_content_hash ON attachments(content_hash)
+"#;
+
+/// SQL to create triggers keeping blob reference counts in sync with
+/// attachments, deleting a blob when its last reference goes away.
+///
+/// Deleting an email deletes its attachments first.
+pub const CREATE_BLOB_TRIGGERS: &str = r#"
+CREATE TRIGGER IF NOT EXISTS attachments_blob_ai AFTER INSERT ON attachments
+WHEN NEW.content_hash IS NOT NULL BEGIN
+    UPDATE blobs SET ref_count = ref_count + 1 WHERE hash = NEW.content_hash;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attachments_blob_ad AFTER DELETE ON attachments
+WHEN OLD.content_hash IS NOT NULL BEGIN
+    UPDATE blobs SET ref_count = ref_count - 1 WHERE hash = OLD.content_hash;
+    DELETE FROM blobs WHERE hash = OLD.content_hash AND ref_count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attachments_blob_au AFTER UPDATE OF content_hash ON attachments
+WHEN OLD.content_hash IS NOT NEW.content_hash BEGIN
+    UPDATE blobs SET ref_count = ref_count + 1 WHERE hash = NEW.content_hash;
+    UPDATE blobs SET ref_count = ref_count - 1 WHERE hash = OLD.content_hash;
+    DELETE FROM blobs WHERE hash = OLD.content_hash AND ref_count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS emails_attachments_bd BEFORE DELETE ON emails BEGIN
+    DELETE FROM attachments WHERE email_id = OLD.id;
+END
+"#;
+
+/// SQL to create the table of attachment thumbnails, keyed by the hash of
+/// the blob they preview and their size, with a trigger dropping them with
+/// their blob.
+pub const CREATE_THUMBNAILS: &str = r#"
+CREATE TABLE IF NOT EXISTS thumbnails (
+    content_hash TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (content_hash, size)
+);
+
+CREATE TRIGGER IF NOT EXISTS blobs_thumbnails_ad AFTER DELETE ON blobs BEGIN
+    DELETE FROM thumbnails WHERE content_hash = OLD.hash;
+END
+"#;
+
 /// SQL to create the drafts table.
 pub const CREATE_DRAFTS: &str = r#"
 CREATE TABLE IF NOT EXISTS drafts (
     id TEXT PRIMARY KEY,
     account_id TEXT NOT NULL REFERENCES accounts(id),
+    from_address TEXT,
     reply_to_thread_id TEXT,
     reply_to_message_id TEXT,
     to_addresses TEXT,
@@ -121,7 +211,11 @@ CREATE TABLE IF NOT EXISTS drafts (
     subject TEXT,
     body_markdown TEXT,
     body_html TEXT,
+    signature_html TEXT,
     attachments TEXT,
+    references_json TEXT,
+    idempotency_key TEXT,
+    request_read_receipt INTEGER DEFAULT 0,
     created_at TEXT NOT NULL,
     updated_at TEXT NOT NULL
 )
@@ -147,6 +241,15 @@ pub const CREATE_CONTACTS_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_contacts_email ON contacts(email)
 "#;
 
+/// SQL to create the table of addresses merged into contacts, each
+/// belonging to one contact.
+pub const CREATE_CONTACT_ADDRESSES: &str = r#"
+CREATE TABLE IF NOT EXISTS contact_addresses (
+    address TEXT PRIMARY KEY,
+    contact_id TEXT NOT NULL REFERENCES contacts(id)
+)
+"#;
+
 /// SQL to create the screener_entries table.
 pub const CREATE_SCREENER_ENTRIES: &str = r#"
 CREATE TABLE IF NOT EXISTS screener_entries (
@@ -177,7 +280,7 @@ pub const CREATE_SNOOZED: &str = r#"
 CREATE TABLE IF NOT EXISTS snoozed (
     id TEXT PRIMARY KEY,
     thread_id TEXT NOT NULL REFERENCES threads(id),
-    snooze_until TEXT NOT NULL,
+    snooze_until TEXT,
     created_at TEXT NOT NULL
 )
 "#;
@@ -187,6 +290,26 @@ pub const CREATE_SNOOZED_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_snoozed_until ON snoozed(snooze_until)
 "#;
 
+/// SQL to create the table of follow-up reminders, one per thread.
+pub const CREATE_FOLLOWUPS: &str = r#"
+CREATE TABLE IF NOT EXISTS followups (
+    thread_id TEXT PRIMARY KEY REFERENCES threads(id),
+    set_at TEXT NOT NULL,
+    due_at TEXT NOT NULL
+)
+"#;
+
+/// SQL to create the table of blocked sender patterns, whose new mail is
+/// moved out of the inbox as it syncs.
+pub const CREATE_BLOCKED_SENDERS: &str = r#"
+CREATE TABLE IF NOT EXISTS blocked_senders (
+    pattern TEXT PRIMARY KEY,
+    action TEXT NOT NULL,
+    report_spam INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+)
+"#;
+
 /// SQL to create the sync_state table.
 pub const CREATE_SYNC_STATE: &str = r#"
 CREATE TABLE IF NOT EXISTS sync_state (
@@ -198,6 +321,52 @@ CREATE TABLE IF NOT EXISTS sync_state (
 )
 "#;
 
+/// SQL to create the table of where IMAP messages were last seen, by the
+/// stable ID the provider gives them.
+pub const CREATE_IMAP_LOCATIONS: &str = r#"
+CREATE TABLE IF NOT EXISTS imap_locations (
+    account_id TEXT NOT NULL REFERENCES accounts(id),
+    id TEXT NOT NULL,
+    folder TEXT NOT NULL,
+    uid INTEGER,
+    message_id TEXT,
+    PRIMARY KEY (account_id, id)
+)
+"#;
+
+/// SQL to create the table of the UIDVALIDITY last seen per IMAP folder, so
+/// a folder renumbered while the app was closed is still noticed.
+pub const CREATE_IMAP_FOLDERS: &str = r#"
+CREATE TABLE IF NOT EXISTS imap_folders (
+    account_id TEXT NOT NULL REFERENCES accounts(id),
+    folder TEXT NOT NULL,
+    uid_validity INTEGER NOT NULL,
+    PRIMARY KEY (account_id, folder)
+)
+"#;
+
+/// SQL to create the table of sends, keyed by their draft's idempotency
+/// key, so a send retried after a restart isn't delivered twice.
+pub const CREATE_SEND_STATES: &str = r#"
+CREATE TABLE IF NOT EXISTS send_states (
+    idempotency_key TEXT PRIMARY KEY,
+    state TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+)
+"#;
+
+/// SQL to create the table of unsubscribe outcomes, by account and the
+/// lowercased address of the list's sender.
+pub const CREATE_UNSUBSCRIBES: &str = r#"
+CREATE TABLE IF NOT EXISTS unsubscribes (
+    account_id TEXT NOT NULL REFERENCES accounts(id),
+    sender TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (account_id, sender)
+)
+"#;
+
 /// SQL to create the pending_changes table.
 pub const CREATE_PENDING_CHANGES: &str = r#"
 CREATE TABLE IF NOT EXISTS pending_changes (
@@ -261,6 +430,27 @@ CREATE TABLE IF NOT EXISTS settings (
 )
 "#;
 
+/// SQL to create the templates table for canned responses.
+pub const CREATE_TEMPLATES: &str = r#"
+CREATE TABLE IF NOT EXISTS templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+)
+"#;
+
+/// SQL to create the summaries table caching AI thread summaries.
+pub const CREATE_SUMMARIES: &str = r#"
+CREATE TABLE IF NOT EXISTS summaries (
+    thread_id TEXT PRIMARY KEY,
+    content_hash TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    created_at TEXT NOT NULL
+)
+"#;
+
 /// SQL to create the FTS5 virtual table for email search.
 pub const CREATE_EMAILS_FTS: &str = r#"
 CREATE VIRTUAL TABLE IF NOT EXISTS emails_fts USING fts5(
@@ -294,6 +484,142 @@ CREATE TRIGGER IF NOT EXISTS emails_au AFTER UPDATE ON emails BEGIN
 END
 "#;
 
+/// Version of the schema created by [`all_migrations`], stored in the
+/// database's `user_version`.
+///
+/// Bump it when a migration changes the shape of existing tables, and list
+/// any new columns of existing tables in [`ADDED_COLUMNS`].
+pub const SCHEMA_VERSION: i32 = 10;
+
+/// A column added to a table after the table was first released.
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves existing tables alone, so databases
+/// created before `version` get the column through `ALTER TABLE`.
+#[derive(Debug, Clone, Copy)]
+pub struct AddedColumn {
+    /// Schema version that added the column.
+    pub version: i32,
+    /// Table the column belongs to.
+    pub table: &'static str,
+    /// Column name.
+    pub column: &'static str,
+    /// Type and constraints, as written after the name in `ADD COLUMN`.
+    pub definition: &'static str,
+}
+
+/// Columns added to existing tables, oldest first.
+pub const ADDED_COLUMNS: &[AddedColumn] = &[
+    AddedColumn {
+        version: 1,
+        table: "threads",
+        column: "is_muted",
+        definition: "INTEGER DEFAULT 0",
+    },
+    AddedColumn {
+        version: 2,
+        table: "accounts",
+        column: "signature_html",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 3,
+        table: "attachments",
+        column: "content_hash",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 5,
+        table: "accounts",
+        column: "folder_mapping",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 6,
+        table: "drafts",
+        column: "references_json",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 6,
+        table: "drafts",
+        column: "idempotency_key",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 6,
+        table: "drafts",
+        column: "request_read_receipt",
+        definition: "INTEGER DEFAULT 0",
+    },
+    AddedColumn {
+        version: 7,
+        table: "threads",
+        column: "is_watched",
+        definition: "INTEGER DEFAULT 0",
+    },
+    AddedColumn {
+        version: 8,
+        table: "accounts",
+        column: "aliases",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 8,
+        table: "drafts",
+        column: "from_address",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 9,
+        table: "threads",
+        column: "trashed_at",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 11,
+        table: "drafts",
+        column: "signature_html",
+        definition: "TEXT",
+    },
+    AddedColumn {
+        version: 10,
+        table: "drafts",
+        column: "signature_html",
+        definition: "TEXT",
+    },
+];
+
+/// A table recreated to make a change `ALTER TABLE` can't, such as
+/// dropping a constraint.
+#[derive(Debug, Clone, Copy)]
+pub struct TableRebuild {
+    /// Schema version that changed the table.
+    pub version: i32,
+    /// Table being rebuilt.
+    pub table: &'static str,
+    /// Statements copying the table into its new shape under the same name.
+    pub sql: &'static str,
+}
+
+/// Table rebuilds, oldest first.
+pub const TABLE_REBUILDS: &[TableRebuild] = &[TableRebuild {
+    version: 4,
+    table: "snoozed",
+    // Snoozes that wake on a reply have no wake time.
+    sql: r#"
+CREATE TABLE snoozed_rebuild (
+    id TEXT PRIMARY KEY,
+    thread_id TEXT NOT NULL REFERENCES threads(id),
+    snooze_until TEXT,
+    created_at TEXT NOT NULL
+);
+INSERT INTO snoozed_rebuild (id, thread_id, snooze_until, created_at)
+    SELECT id, thread_id, snooze_until, created_at FROM snoozed;
+DROP TABLE snoozed;
+ALTER TABLE snoozed_rebuild RENAME TO snoozed
+"#,
+}];
+
 /// Returns all schema creation statements in order.
 pub fn all_migrations() -> Vec<&'static str> {
     vec![
@@ -302,22 +628,35 @@ pub fn all_migrations() -> Vec<&'static str> {
         CREATE_EMAIL_INDEXES,
         CREATE_THREADS,
         CREATE_THREAD_INDEXES,
+        CREATE_THREAD_TRASH_TRIGGERS,
         CREATE_LABELS,
         CREATE_ATTACHMENTS,
+        CREATE_BLOBS,
+        CREATE_BLOB_TRIGGERS,
+        CREATE_THUMBNAILS,
         CREATE_DRAFTS,
         CREATE_CONTACTS,
         CREATE_CONTACTS_INDEX,
+        CREATE_CONTACT_ADDRESSES,
         CREATE_SCREENER_ENTRIES,
         CREATE_SCREENER_RULES,
         CREATE_SNOOZED,
         CREATE_SNOOZED_INDEX,
+        CREATE_FOLLOWUPS,
+        CREATE_BLOCKED_SENDERS,
         CREATE_SYNC_STATE,
+        CREATE_IMAP_LOCATIONS,
+        CREATE_IMAP_FOLDERS,
+        CREATE_SEND_STATES,
+        CREATE_UNSUBSCRIBES,
         CREATE_PENDING_CHANGES,
         CREATE_EMBEDDINGS,
         CREATE_TELEMETRY_EVENTS,
         CREATE_TELEMETRY_INDEX,
         CREATE_DAILY_STATS,
         CREATE_SETTINGS,
+        CREATE_TEMPLATES,
+        CREATE_SUMMARIES,
         CREATE_EMAILS_FTS,
         CREATE_EMAILS_FTS_TRIGGERS,
     ]
@@ -346,6 +685,18 @@ mod tests {
         assert!(CREATE_EMAILS.contains("REFERENCES accounts(id)"));
     }
 
+    #[test]
+    fn added_columns_are_in_their_create_statements() {
+        for added in ADDED_COLUMNS {
+            assert!(added.version <= SCHEMA_VERSION);
+            let create = all_migrations()
+                .into_iter()
+                .find(|sql| sql.contains(&format!("EXISTS {} (", added.table)))
+                .unwrap();
+            assert!(create.contains(&format!("{} {}", added.column, added.definition)));
+        }
+    }
+
     #[test]
     fn indexes_use_if_not_exists() {
         assert!(CREATE_EMAIL_INDEXES.contains("IF NOT EXISTS"));
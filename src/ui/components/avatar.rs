@@ -7,7 +7,7 @@ use gpui::{
     Styled,
 };
 
-use crate::ui::theme::ThemeColors;
+use crate::ui::theme::{color_seed, ThemeColors};
 
 /// Avatar size options.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -81,13 +81,12 @@ impl Avatar {
     /// Create avatar from a full name, extracting initials.
     pub fn from_name(id: impl Into<ElementId>, name: &str) -> Self {
         let initials = extract_initials(name);
-        let color_seed = name.bytes().fold(0u32, |acc, b| acc.wrapping_add(b as u32));
         Self {
             id: id.into(),
             initials: initials.into(),
             size: AvatarSize::Medium,
             shape: AvatarShape::Circle,
-            color_seed,
+            color_seed: color_seed(name),
         }
     }
 
@@ -103,7 +102,8 @@ impl Avatar {
         self
     }
 
-    /// Set a seed for consistent background color generation.
+    /// Set the seed picking the background from the theme palette, e.g. a
+    /// contact's avatar seed.
     pub fn color_seed(mut self, seed: u32) -> Self {
         self.color_seed = seed;
         self
@@ -127,18 +127,12 @@ fn extract_initials(name: &str) -> String {
     }
 }
 
-fn generate_color(seed: u32) -> gpui::Hsla {
-    // Generate a consistent hue based on the seed
-    let hue = ((seed * 137) % 360) as f32;
-    gpui::hsla(hue / 360.0, 0.5, 0.35, 1.0)
-}
-
 impl RenderOnce for Avatar {
     fn render(self, _window: &mut gpui::Window, _cx: &mut gpui::App) -> impl IntoElement {
         let colors = ThemeColors::dark();
         let size = self.size.px();
         let font_size = self.size.font_size();
-        let bg_color = generate_color(self.color_seed);
+        let bg_color = colors.palette_color(self.color_seed);
 
         let radius = match self.shape {
             AvatarShape::Circle => size / 2.0,
@@ -153,7 +147,7 @@ impl RenderOnce for Avatar {
             .justify_center()
             .rounded(px(radius))
             .bg(bg_color)
-            .text_color(colors.text_primary)
+            .text_color(colors.background)
             .text_size(px(font_size))
             .font_weight(gpui::FontWeight::MEDIUM)
             .child(self.initials)
@@ -182,8 +176,7 @@ impl AvatarGroup {
     /// Add an avatar by name.
     pub fn with_avatar(mut self, name: &str) -> Self {
         let initials = extract_initials(name);
-        let seed = name.bytes().fold(0u32, |acc, b| acc.wrapping_add(b as u32));
-        self.avatars.push((initials.into(), seed));
+        self.avatars.push((initials.into(), color_seed(name)));
         self
     }
 
@@ -213,7 +206,7 @@ impl RenderOnce for AvatarGroup {
         let mut container = div().id(self.id).flex().items_center();
 
         for (i, (initials, seed)) in self.avatars.iter().take(visible_count).enumerate() {
-            let bg_color = generate_color(*seed);
+            let bg_color = colors.palette_color(*seed);
             let margin = if i > 0 { -overlap } else { 0.0 };
 
             container = container.child(
@@ -227,7 +220,7 @@ impl RenderOnce for AvatarGroup {
                     .bg(bg_color)
                     .border_2()
                     .border_color(colors.background)
-                    .text_color(colors.text_primary)
+                    .text_color(colors.background)
                     .text_size(px(font_size))
                     .font_weight(gpui::FontWeight::MEDIUM)
                     .child(initials.clone()),
@@ -315,9 +308,25 @@ mod tests {
     }
 
     #[test]
-    fn color_generation_consistent() {
-        let color1 = generate_color(42);
-        let color2 = generate_color(42);
-        assert_eq!(color1.h, color2.h);
+    fn avatar_color_comes_from_name() {
+        let colors = ThemeColors::dark();
+        let alice = Avatar::from_name("a", "Alice Smith");
+        assert_eq!(
+            alice.color_seed,
+            Avatar::from_name("b", "Alice Smith").color_seed
+        );
+        assert_eq!(
+            colors.palette_color(alice.color_seed),
+            colors.color_for("Alice Smith")
+        );
+    }
+
+    #[test]
+    fn palette_color_accepts_large_seeds() {
+        let colors = ThemeColors::light();
+        assert_eq!(
+            colors.palette_color(u32::MAX),
+            colors.palette_color(u32::MAX % colors.palette.len() as u32)
+        );
     }
 }
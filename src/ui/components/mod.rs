@@ -13,6 +13,7 @@ pub mod input;
 pub mod list;
 mod notifications;
 pub mod tooltip;
+pub mod which_key;
 
 pub use avatar::{Avatar, AvatarGroup, AvatarShape, AvatarSize};
 pub use badge::{Badge, BadgeSize, BadgeVariant, CountBadge, DotIndicator};
@@ -26,3 +27,4 @@ pub use notifications::{
     StatusMessage,
 };
 pub use tooltip::{HelpTooltip, KeyboardHint, Tooltip, TooltipBox, TooltipPosition};
+pub use which_key::{should_show_which_key, WhichKey, WHICH_KEY_DELAY};
@@ -0,0 +1,157 @@
+//! Which-key hint popup.
+//!
+//! Shown while a multi-key sequence such as `g` is pending, listing the keys
+//! that complete it and where each one leads.
+
+use std::time::Duration;
+
+use gpui::{
+    div, px, ElementId, InteractiveElement, IntoElement, ParentElement, RenderOnce, SharedString,
+    Styled,
+};
+
+use crate::ui::keybindings::Keystroke;
+use crate::ui::theme::ThemeColors;
+
+/// How long a sequence must be pending before the popup appears, so fast
+/// typists never see it flash.
+pub const WHICH_KEY_DELAY: Duration = Duration::from_millis(400);
+
+/// Returns whether the popup should be visible for a sequence that has been
+/// pending for `pending_for` (see `KeybindingManager::pending_for`).
+pub fn should_show_which_key(pending_for: Option<Duration>) -> bool {
+    pending_for.is_some_and(|elapsed| elapsed >= WHICH_KEY_DELAY)
+}
+
+/// Popup listing the completions of a pending key sequence.
+pub struct WhichKey {
+    id: ElementId,
+    prefix: SharedString,
+    entries: Vec<(SharedString, SharedString)>,
+}
+
+impl WhichKey {
+    /// Create a popup for the keys typed so far.
+    pub fn new(id: impl Into<ElementId>, pending: &[Keystroke]) -> Self {
+        let prefix = pending
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            id: id.into(),
+            prefix: prefix.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Set the completions, as returned by
+    /// `KeybindingManager::pending_completions`.
+    pub fn completions(mut self, completions: &[(Keystroke, String)]) -> Self {
+        self.entries = completions
+            .iter()
+            .map(|(key, command)| (key.to_string().into(), command_label(command).into()))
+            .collect();
+        self
+    }
+}
+
+/// Turns a command id such as `go_inbox` into a label such as `Inbox`.
+fn command_label(command: &str) -> String {
+    let words = command
+        .strip_prefix("go_")
+        .unwrap_or(command)
+        .replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl RenderOnce for WhichKey {
+    fn render(self, _window: &mut gpui::Window, _cx: &mut gpui::App) -> impl IntoElement {
+        let colors = ThemeColors::dark();
+
+        let mut container = div()
+            .id(self.id)
+            .flex()
+            .flex_col()
+            .gap(px(4.0))
+            .min_w(px(160.0))
+            .px(px(12.0))
+            .py(px(8.0))
+            .rounded(px(6.0))
+            .bg(colors.surface_elevated)
+            .border_1()
+            .border_color(colors.border)
+            .shadow_md()
+            .child(
+                div()
+                    .text_color(colors.text_muted)
+                    .text_size(px(11.0))
+                    .child(self.prefix),
+            );
+
+        for (key, label) in self.entries {
+            container = container.child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(8.0))
+                    .child(
+                        div()
+                            .px(px(4.0))
+                            .py(px(2.0))
+                            .rounded(px(3.0))
+                            .border_1()
+                            .border_color(colors.border)
+                            .text_color(colors.text_secondary)
+                            .text_size(px(11.0))
+                            .font_weight(gpui::FontWeight::MEDIUM)
+                            .child(key),
+                    )
+                    .child(
+                        div()
+                            .text_color(colors.text_primary)
+                            .text_size(px(12.0))
+                            .child(label),
+                    ),
+            );
+        }
+
+        container
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::keybindings::Key;
+
+    #[test]
+    fn which_key_entries() {
+        let popup = WhichKey::new("which-key", &[Keystroke::key(Key::G)]).completions(&[
+            (Keystroke::key(Key::I), "go_inbox".to_string()),
+            (Keystroke::key(Key::D), "go_drafts".to_string()),
+        ]);
+
+        assert_eq!(popup.prefix.as_ref(), "G");
+        assert_eq!(popup.entries.len(), 2);
+        assert_eq!(popup.entries[0].1.as_ref(), "Inbox");
+    }
+
+    #[test]
+    fn command_labels() {
+        assert_eq!(command_label("go_inbox"), "Inbox");
+        assert_eq!(command_label("reply_all"), "Reply all");
+        assert_eq!(command_label(""), "");
+    }
+
+    #[test]
+    fn shows_only_after_delay() {
+        assert!(!should_show_which_key(None));
+        assert!(!should_show_which_key(Some(Duration::from_millis(100))));
+        assert!(should_show_which_key(Some(WHICH_KEY_DELAY)));
+    }
+}
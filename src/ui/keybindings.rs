@@ -6,12 +6,17 @@
 //! - Context-aware bindings
 //! - Conflict detection
 //! - User customization
+//! - Recorded command macros
 
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use crate::config::{KeyboardMacro, Preset};
 
 /// A keyboard key.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -423,6 +428,12 @@ pub enum KeyResult {
     Matched(String),
 }
 
+/// Default time allowed between the keys of a sequence.
+pub const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Source of the current time for timing key sequences.
+pub type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
 /// Manages keyboard bindings and input processing.
 pub struct KeybindingManager {
     /// Bindings organized by context.
@@ -435,6 +446,12 @@ pub struct KeybindingManager {
     sequence_timeout: Duration,
     /// Current context.
     current_context: KeyContext,
+    /// Contexts covered by pushed overlays, innermost last.
+    context_stack: Vec<KeyContext>,
+    /// Source of the current time.
+    clock: Clock,
+    /// Preset the bindings started from.
+    preset: Preset,
 }
 
 impl Default for KeybindingManager {
@@ -450,206 +467,73 @@ impl KeybindingManager {
             bindings: HashMap::new(),
             pending_sequence: Vec::new(),
             last_keystroke: None,
-            sequence_timeout: Duration::from_millis(1000),
+            sequence_timeout: DEFAULT_SEQUENCE_TIMEOUT,
             current_context: KeyContext::Global,
+            context_stack: Vec::new(),
+            clock: Arc::new(Instant::now),
+            preset: Preset::Default,
         };
-        manager.register_defaults();
+        manager.load_preset(Preset::Default);
         manager
     }
 
-    /// Registers the default keybindings.
-    fn register_defaults(&mut self) {
-        // Global bindings
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::single(Keystroke::key(Key::C)),
-            "compose",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::single(Keystroke::key(Key::Slash)),
-            "search",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::single(Keystroke::cmd(Key::K)),
-            "command_palette",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::single(Keystroke::cmd(Key::Comma)),
-            "settings",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::single(Keystroke::key(Key::Escape)),
-            "cancel",
-        );
-
-        // Gmail-style navigation sequences
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::sequence(vec![Keystroke::key(Key::G), Keystroke::key(Key::I)]),
-            "go_inbox",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::sequence(vec![Keystroke::key(Key::G), Keystroke::key(Key::S)]),
-            "go_starred",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::sequence(vec![Keystroke::key(Key::G), Keystroke::key(Key::D)]),
-            "go_drafts",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::sequence(vec![Keystroke::key(Key::G), Keystroke::key(Key::T)]),
-            "go_sent",
-        );
-        self.bind(
-            KeyContext::Global,
-            KeyBinding::sequence(vec![Keystroke::key(Key::G), Keystroke::key(Key::A)]),
-            "go_archive",
-        );
+    /// Creates a manager with a preset's bindings.
+    pub fn with_preset(preset: Preset) -> Self {
+        let mut manager = Self::new();
+        manager.load_preset(preset);
+        manager
+    }
 
-        // Message list bindings
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::J)),
-            "next_message",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::K)),
-            "prev_message",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::Enter)),
-            "open_message",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::X)),
-            "select_message",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::E)),
-            "archive",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::S)),
-            "star",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::shift(Key::Num3)),
-            "trash",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::key(Key::U)),
-            "mark_unread",
-        );
-        self.bind(
-            KeyContext::MessageList,
-            KeyBinding::single(Keystroke::shift(Key::U)),
-            "mark_read",
-        );
+    /// Replaces all bindings with a preset's, dropping user overrides.
+    pub fn load_preset(&mut self, preset: Preset) {
+        self.bindings.clear();
+        for (context, binding, command) in preset.bindings() {
+            self.bind(context, binding, command);
+        }
+        self.preset = preset;
+        self.pending_sequence.clear();
+    }
 
-        // Reading pane bindings
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::key(Key::R)),
-            "reply",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::shift(Key::R)),
-            "reply_all",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::key(Key::F)),
-            "forward",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::key(Key::J)),
-            "next_in_thread",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::key(Key::K)),
-            "prev_in_thread",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::cmd(Key::S)),
-            "summarize",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::key(Key::N)),
-            "expand_all",
-        );
-        self.bind(
-            KeyContext::ReadingPane,
-            KeyBinding::single(Keystroke::shift(Key::N)),
-            "collapse_all",
-        );
+    /// Switches to another preset, optionally re-applying the user's
+    /// overrides of the current one on top.
+    pub fn switch_preset(&mut self, preset: Preset, keep_overrides: bool) {
+        let overrides = self.overrides();
+        self.load_preset(preset);
+        if keep_overrides {
+            for entry in overrides {
+                self.bind(entry.context, entry.binding, &entry.command);
+            }
+        }
+    }
 
-        // Composer bindings
-        self.bind(
-            KeyContext::Composer,
-            KeyBinding::single(Keystroke::cmd(Key::Enter)),
-            "send",
-        );
-        self.bind(
-            KeyContext::Composer,
-            KeyBinding::single(Keystroke::cmd(Key::S)),
-            "save_draft",
-        );
-        self.bind(
-            KeyContext::Composer,
-            KeyBinding::single(Keystroke::cmd(Key::R)),
-            "ai_suggest",
-        );
-        self.bind(
-            KeyContext::Composer,
-            KeyBinding::single(Keystroke::cmd(Key::D)),
-            "discard",
-        );
-        self.bind(
-            KeyContext::Composer,
-            KeyBinding::single(Keystroke::cmd(Key::Slash)),
-            "toggle_markdown",
-        );
+    /// Returns the preset the bindings started from.
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
 
-        // Command palette bindings
-        self.bind(
-            KeyContext::CommandPalette,
-            KeyBinding::single(Keystroke::key(Key::Up)),
-            "prev_item",
-        );
-        self.bind(
-            KeyContext::CommandPalette,
-            KeyBinding::single(Keystroke::key(Key::Down)),
-            "next_item",
-        );
-        self.bind(
-            KeyContext::CommandPalette,
-            KeyBinding::single(Keystroke::key(Key::Enter)),
-            "execute",
-        );
-        self.bind(
-            KeyContext::CommandPalette,
-            KeyBinding::single(Keystroke::key(Key::Escape)),
-            "close",
-        );
+    /// Returns the bindings that differ from the preset's.
+    ///
+    /// Bindings removed from the preset with [`unbind`](Self::unbind) aren't
+    /// included.
+    pub fn overrides(&self) -> Vec<KeybindingEntry> {
+        let preset = Self::with_preset(self.preset);
+        let mut overrides = Vec::new();
+        for (context, context_bindings) in &self.bindings {
+            for (binding, command) in context_bindings {
+                let original = preset
+                    .bindings
+                    .get(context)
+                    .and_then(|bindings| bindings.get(binding));
+                if original != Some(command) {
+                    overrides.push(KeybindingEntry {
+                        context: *context,
+                        binding: binding.clone(),
+                        command: command.clone(),
+                    });
+                }
+            }
+        }
+        overrides
     }
 
     /// Binds a key sequence to a command.
@@ -672,33 +556,59 @@ impl KeybindingManager {
         self.pending_sequence.clear();
     }
 
+    /// Enters a context for an overlay, remembering the one it covers.
+    ///
+    /// Keys resolve against the pushed context and then Global; contexts
+    /// further down the stack are not consulted until popped back to.
+    pub fn push_context(&mut self, context: KeyContext) {
+        self.context_stack.push(self.current_context);
+        self.set_context(context);
+    }
+
+    /// Leaves the innermost pushed context and restores the one it covered.
+    ///
+    /// Returns the context that was left, or `None` if nothing was pushed.
+    pub fn pop_context(&mut self) -> Option<KeyContext> {
+        let previous = self.context_stack.pop()?;
+        let left = self.current_context;
+        self.set_context(previous);
+        Some(left)
+    }
+
     /// Returns the current context.
     pub fn context(&self) -> KeyContext {
         self.current_context
     }
 
+    /// Sets how long to wait for the next key of a sequence before starting
+    /// over.
+    pub fn set_sequence_timeout(&mut self, timeout: Duration) {
+        self.sequence_timeout = timeout;
+    }
+
+    /// Returns how long to wait for the next key of a sequence.
+    pub fn sequence_timeout(&self) -> Duration {
+        self.sequence_timeout
+    }
+
+    /// Replaces the clock used to time key sequences.
+    pub fn set_clock(&mut self, clock: impl Fn() -> Instant + Send + Sync + 'static) {
+        self.clock = Arc::new(clock);
+    }
+
     /// Processes a keystroke and returns the result.
     pub fn process(&mut self, keystroke: Keystroke) -> KeyResult {
-        let now = Instant::now();
+        let now = (self.clock)();
 
         // Check if sequence timed out
-        if let Some(last) = self.last_keystroke {
-            if now.duration_since(last) > self.sequence_timeout {
-                self.pending_sequence.clear();
-            }
+        if self.sequence_timed_out(now) {
+            self.pending_sequence.clear();
         }
 
         self.last_keystroke = Some(now);
         self.pending_sequence.push(keystroke);
 
-        // Try to match in current context first, then global
-        let contexts = if self.current_context == KeyContext::Global {
-            vec![KeyContext::Global]
-        } else {
-            vec![self.current_context, KeyContext::Global]
-        };
-
-        for context in contexts {
+        for context in self.active_contexts() {
             if let Some(context_bindings) = self.bindings.get(&context) {
                 // Check for exact match
                 let current_binding = KeyBinding::sequence(self.pending_sequence.clone());
@@ -729,6 +639,73 @@ impl KeybindingManager {
         self.pending_sequence.clear();
     }
 
+    /// Returns the keys of the sequence typed so far, or an empty slice if
+    /// none is pending or it has timed out.
+    pub fn pending_sequence(&self) -> &[Keystroke] {
+        if self.sequence_timed_out((self.clock)()) {
+            &[]
+        } else {
+            &self.pending_sequence
+        }
+    }
+
+    /// Returns how long the pending sequence has been waiting for its next
+    /// key, or `None` if no sequence is pending.
+    pub fn pending_for(&self) -> Option<Duration> {
+        let last = self.last_keystroke?;
+        if self.pending_sequence().is_empty() {
+            return None;
+        }
+        Some((self.clock)().duration_since(last))
+    }
+
+    /// Lists the keys that can follow the pending sequence, with the command
+    /// each one leads to.
+    ///
+    /// Bindings in the current context shadow Global ones on the same key.
+    /// Returns an empty list when no sequence is pending.
+    pub fn pending_completions(&self) -> Vec<(Keystroke, String)> {
+        let pending = self.pending_sequence();
+        if pending.is_empty() {
+            return Vec::new();
+        }
+
+        let mut completions: Vec<(Keystroke, String)> = Vec::new();
+        for context in self.active_contexts() {
+            let Some(context_bindings) = self.bindings.get(&context) else {
+                continue;
+            };
+            for (binding, command) in context_bindings {
+                if binding.sequence.len() <= pending.len() || !binding.sequence.starts_with(pending)
+                {
+                    continue;
+                }
+                let next = binding.sequence[pending.len()];
+                if !completions.iter().any(|(key, _)| *key == next) {
+                    completions.push((next, command.clone()));
+                }
+            }
+        }
+
+        completions.sort_by_key(|(key, _)| key.to_string());
+        completions
+    }
+
+    /// Contexts to resolve keys against: the current one, then Global.
+    fn active_contexts(&self) -> Vec<KeyContext> {
+        if self.current_context == KeyContext::Global {
+            vec![KeyContext::Global]
+        } else {
+            vec![self.current_context, KeyContext::Global]
+        }
+    }
+
+    /// Returns whether the gap since the last keystroke exceeds the timeout.
+    fn sequence_timed_out(&self, now: Instant) -> bool {
+        self.last_keystroke
+            .is_some_and(|last| now.duration_since(last) > self.sequence_timeout)
+    }
+
     /// Returns all bindings for a context.
     pub fn bindings_for_context(&self, context: KeyContext) -> Vec<(KeyBinding, String)> {
         self.bindings
@@ -751,10 +728,32 @@ impl KeybindingManager {
     }
 
     /// Detects conflicts between bindings.
+    ///
+    /// Besides keys bound to several commands, a binding conflicts with a
+    /// longer sequence it starts, in its own context or in Global, since the
+    /// shorter one always matches first and the sequence can't be typed.
     pub fn detect_conflicts(&self) -> Vec<Conflict> {
         let mut conflicts = Vec::new();
 
         for (context, bindings) in &self.bindings {
+            let mut reachable = vec![bindings];
+            if *context != KeyContext::Global {
+                reachable.extend(self.bindings.get(&KeyContext::Global));
+            }
+            for (binding, command) in bindings {
+                for (longer, shadowed) in reachable.iter().flat_map(|b| b.iter()) {
+                    if longer.sequence.len() > binding.sequence.len()
+                        && longer.sequence.starts_with(&binding.sequence)
+                    {
+                        conflicts.push(Conflict {
+                            binding: binding.clone(),
+                            commands: vec![command.clone(), shadowed.clone()],
+                            context: *context,
+                        });
+                    }
+                }
+            }
+
             // Group commands by binding
             let mut by_binding: HashMap<&KeyBinding, Vec<&String>> = HashMap::new();
             for (binding, command) in bindings {
@@ -792,35 +791,150 @@ impl KeybindingManager {
         result
     }
 
-    /// Exports bindings as a serializable config.
+    /// Exports the active preset and the user's overrides as a
+    /// serializable config.
     pub fn export_config(&self) -> KeybindingConfig {
-        let mut bindings = Vec::new();
-
-        for (context, context_bindings) in &self.bindings {
-            for (binding, command) in context_bindings {
-                bindings.push(KeybindingEntry {
-                    context: *context,
-                    binding: binding.clone(),
-                    command: command.clone(),
-                });
-            }
+        KeybindingConfig {
+            preset: self.preset,
+            bindings: self.overrides(),
         }
-
-        KeybindingConfig { bindings }
     }
 
-    /// Imports bindings from config.
+    /// Loads the config's preset and applies its overrides on top.
     pub fn import_config(&mut self, config: &KeybindingConfig) {
+        self.load_preset(config.preset);
         for entry in &config.bindings {
             self.bind(entry.context, entry.binding.clone(), &entry.command);
         }
     }
 }
 
-/// Serializable keybinding configuration.
+impl Preset {
+    /// Returns the preset's bindings. Every preset binds every command.
+    pub fn bindings(&self) -> Vec<(KeyContext, KeyBinding, &'static str)> {
+        use KeyContext::{CommandPalette, Composer, Global, MessageList, ReadingPane};
+
+        let key = |key| KeyBinding::single(Keystroke::key(key));
+        let shift = |key| KeyBinding::single(Keystroke::shift(key));
+        let cmd = |key| KeyBinding::single(Keystroke::cmd(key));
+        let ctrl = |key| KeyBinding::single(Keystroke::ctrl(key));
+        let seq = |first, second| {
+            KeyBinding::sequence(vec![Keystroke::key(first), Keystroke::key(second)])
+        };
+
+        // Shared by every preset.
+        let mut bindings = vec![
+            (Global, key(Key::Slash), "search"),
+            (Global, cmd(Key::K), "command_palette"),
+            (Global, cmd(Key::Comma), "settings"),
+            (Global, key(Key::Escape), "cancel"),
+            (MessageList, shift(Key::J), "select_next"),
+            (MessageList, shift(Key::K), "select_previous"),
+            (MessageList, cmd(Key::A), "select_all"),
+            (MessageList, key(Key::Enter), "open_message"),
+            (MessageList, key(Key::Q), RECORD_MACRO),
+            (MessageList, shift(Key::Num2), REPLAY_MACRO),
+            (ReadingPane, cmd(Key::S), "summarize"),
+            (Composer, cmd(Key::Enter), "send"),
+            (Composer, cmd(Key::S), "save_draft"),
+            (Composer, cmd(Key::R), "ai_suggest"),
+            (Composer, cmd(Key::D), "discard"),
+            (Composer, cmd(Key::Slash), "toggle_markdown"),
+            (CommandPalette, key(Key::Up), "prev_item"),
+            (CommandPalette, key(Key::Down), "next_item"),
+            (CommandPalette, key(Key::Enter), "execute"),
+            (CommandPalette, key(Key::Escape), "close"),
+        ];
+
+        match self {
+            Preset::Default => bindings.extend([
+                (Global, key(Key::C), "compose"),
+                (Global, seq(Key::G, Key::I), "go_inbox"),
+                (Global, seq(Key::G, Key::S), "go_starred"),
+                (Global, seq(Key::G, Key::D), "go_drafts"),
+                (Global, seq(Key::G, Key::T), "go_sent"),
+                (Global, seq(Key::G, Key::A), "go_archive"),
+                (MessageList, key(Key::J), "next_message"),
+                (MessageList, key(Key::K), "prev_message"),
+                (MessageList, key(Key::X), "select_message"),
+                (MessageList, key(Key::E), "archive"),
+                (MessageList, key(Key::S), "star"),
+                (MessageList, shift(Key::Num3), "trash"),
+                (MessageList, shift(Key::Num1), "report_spam"),
+                (MessageList, key(Key::U), "mark_unread"),
+                (MessageList, shift(Key::U), "mark_read"),
+                (ReadingPane, key(Key::R), "reply"),
+                (ReadingPane, shift(Key::R), "reply_all"),
+                (ReadingPane, key(Key::F), "forward"),
+                (ReadingPane, key(Key::J), "next_in_thread"),
+                (ReadingPane, key(Key::K), "prev_in_thread"),
+                (ReadingPane, key(Key::N), "expand_all"),
+                (ReadingPane, shift(Key::N), "collapse_all"),
+            ]),
+            Preset::Gmail => bindings.extend([
+                (Global, key(Key::C), "compose"),
+                (Global, seq(Key::G, Key::I), "go_inbox"),
+                (Global, seq(Key::G, Key::S), "go_starred"),
+                (Global, seq(Key::G, Key::D), "go_drafts"),
+                (Global, seq(Key::G, Key::T), "go_sent"),
+                (Global, seq(Key::G, Key::A), "go_archive"),
+                (MessageList, key(Key::J), "next_message"),
+                (MessageList, key(Key::K), "prev_message"),
+                (MessageList, key(Key::O), "open_message"),
+                (MessageList, key(Key::X), "select_message"),
+                (MessageList, key(Key::E), "archive"),
+                (MessageList, key(Key::S), "star"),
+                (MessageList, shift(Key::Num3), "trash"),
+                (MessageList, shift(Key::Num1), "report_spam"),
+                (MessageList, shift(Key::U), "mark_unread"),
+                (MessageList, shift(Key::I), "mark_read"),
+                (ReadingPane, key(Key::R), "reply"),
+                (ReadingPane, key(Key::A), "reply_all"),
+                (ReadingPane, key(Key::F), "forward"),
+                (ReadingPane, key(Key::N), "next_in_thread"),
+                (ReadingPane, key(Key::P), "prev_in_thread"),
+                (ReadingPane, key(Key::Semicolon), "expand_all"),
+                (ReadingPane, shift(Key::Semicolon), "collapse_all"),
+            ]),
+            Preset::Mutt => bindings.extend([
+                (Global, key(Key::M), "compose"),
+                (Global, seq(Key::C, Key::I), "go_inbox"),
+                (Global, seq(Key::C, Key::S), "go_starred"),
+                (Global, seq(Key::C, Key::D), "go_drafts"),
+                (Global, seq(Key::C, Key::T), "go_sent"),
+                (Global, seq(Key::C, Key::A), "go_archive"),
+                (MessageList, key(Key::J), "next_message"),
+                (MessageList, key(Key::K), "prev_message"),
+                (MessageList, key(Key::T), "select_message"),
+                (MessageList, key(Key::S), "archive"),
+                (MessageList, shift(Key::F), "star"),
+                (MessageList, key(Key::D), "trash"),
+                (MessageList, shift(Key::S), "report_spam"),
+                (MessageList, shift(Key::N), "mark_unread"),
+                (MessageList, shift(Key::R), "mark_read"),
+                (ReadingPane, key(Key::R), "reply"),
+                (ReadingPane, key(Key::G), "reply_all"),
+                (ReadingPane, key(Key::F), "forward"),
+                (ReadingPane, key(Key::J), "next_in_thread"),
+                (ReadingPane, key(Key::K), "prev_in_thread"),
+                (ReadingPane, seq(Key::Z, Key::O), "expand_all"),
+                (ReadingPane, seq(Key::Z, Key::C), "collapse_all"),
+                (CommandPalette, ctrl(Key::P), "prev_item"),
+                (CommandPalette, ctrl(Key::N), "next_item"),
+            ]),
+        }
+        bindings
+    }
+}
+
+/// Serializable keybinding configuration: a preset and the user's
+/// overrides layered on top of it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeybindingConfig {
-    /// List of keybinding entries.
+    /// Preset the overrides apply to.
+    #[serde(default)]
+    pub preset: Preset,
+    /// Bindings that differ from the preset's.
     pub bindings: Vec<KeybindingEntry>,
 }
 
@@ -835,6 +949,193 @@ pub struct KeybindingEntry {
     pub command: String,
 }
 
+/// Command that starts and stops recording a macro.
+pub const RECORD_MACRO: &str = "record_macro";
+
+/// Command that replays the last recorded macro.
+pub const REPLAY_MACRO: &str = "replay_macro";
+
+/// Commands that act on the focused or selected thread.
+const SELECTION_COMMANDS: &[&str] = &[
+    "open_message",
+    "select_message",
+    "archive",
+    "star",
+    "trash",
+    "report_spam",
+    "mark_read",
+    "mark_unread",
+    "reply",
+    "reply_all",
+    "forward",
+    "summarize",
+    "next_in_thread",
+    "prev_in_thread",
+    "expand_all",
+    "collapse_all",
+];
+
+impl KeyboardMacro {
+    /// Returns true if any command acts on the selected thread.
+    pub fn needs_selection(&self) -> bool {
+        self.commands
+            .iter()
+            .any(|command| SELECTION_COMMANDS.contains(&command.as_str()))
+    }
+}
+
+/// Errors from recording and replaying macros.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MacroError {
+    /// Nothing has been recorded yet.
+    #[error("no macro has been recorded")]
+    NothingRecorded,
+
+    /// No macro is saved under the name.
+    #[error("no macro named {0}")]
+    NotFound(String),
+
+    /// A macro can't be saved or replayed while one is being recorded.
+    #[error("a macro is being recorded")]
+    Recording,
+
+    /// The macro acts on a thread but none is focused.
+    #[error("macro {0} needs a focused thread")]
+    NoThreadFocused(String),
+}
+
+/// Records the commands matched by a [`KeybindingManager`] and replays
+/// them.
+///
+/// The caller feeds each matched command id to [`record`](Self::record)
+/// and dispatches the ids [`replay`](Self::replay) returns as if they had
+/// been typed. The record and replay commands themselves are never
+/// recorded.
+#[derive(Debug, Clone, Default)]
+pub struct MacroRecorder {
+    /// Commands recorded so far, while recording.
+    recording: Option<Vec<String>>,
+    /// The most recent recording.
+    last: Option<Vec<String>>,
+    /// Saved macros, in the order they were saved.
+    saved: Vec<KeyboardMacro>,
+}
+
+impl MacroRecorder {
+    /// Creates a recorder with no saved macros.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a recorder with previously saved macros.
+    pub fn with_macros(macros: Vec<KeyboardMacro>) -> Self {
+        Self {
+            saved: macros,
+            ..Self::default()
+        }
+    }
+
+    /// Returns true while recording.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts recording, discarding any unfinished recording.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// Stops recording and keeps the commands as the last macro. Returns
+    /// the commands, or `None` if nothing was recorded.
+    pub fn stop_recording(&mut self) -> Option<&[String]> {
+        let commands = self.recording.take().filter(|c| !c.is_empty())?;
+        self.last = Some(commands);
+        self.last.as_deref()
+    }
+
+    /// Starts recording, or stops if already recording. Bound to
+    /// [`RECORD_MACRO`].
+    pub fn toggle_recording(&mut self) {
+        if self.is_recording() {
+            self.stop_recording();
+        } else {
+            self.start_recording();
+        }
+    }
+
+    /// Records a matched command, if recording.
+    pub fn record(&mut self, command: &str) {
+        if command == RECORD_MACRO || command == REPLAY_MACRO {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(command.to_string());
+        }
+    }
+
+    /// Saves the last recording under `name`, replacing any macro with
+    /// that name.
+    pub fn save(&mut self, name: &str) -> Result<&KeyboardMacro, MacroError> {
+        if self.is_recording() {
+            return Err(MacroError::Recording);
+        }
+        let commands = self.last.clone().ok_or(MacroError::NothingRecorded)?;
+        self.saved.retain(|m| m.name != name);
+        self.saved.push(KeyboardMacro {
+            name: name.to_string(),
+            commands,
+        });
+        Ok(&self.saved[self.saved.len() - 1])
+    }
+
+    /// Removes a saved macro. Returns false if none had the name.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.saved.len();
+        self.saved.retain(|m| m.name != name);
+        self.saved.len() != before
+    }
+
+    /// Returns a saved macro.
+    pub fn get(&self, name: &str) -> Option<&KeyboardMacro> {
+        self.saved.iter().find(|m| m.name == name)
+    }
+
+    /// Returns the saved macros, for writing to settings.
+    pub fn macros(&self) -> &[KeyboardMacro] {
+        &self.saved
+    }
+
+    /// Returns the commands to run for the named macro, or for the last
+    /// recording when `name` is `None`.
+    ///
+    /// Fails with [`MacroError::NoThreadFocused`] if the macro acts on the
+    /// selection and `thread_focused` is false, so nothing runs against
+    /// the wrong thread.
+    pub fn replay(
+        &self,
+        name: Option<&str>,
+        thread_focused: bool,
+    ) -> Result<Vec<String>, MacroError> {
+        if self.is_recording() {
+            return Err(MacroError::Recording);
+        }
+        let found = match name {
+            Some(name) => self
+                .get(name)
+                .cloned()
+                .ok_or_else(|| MacroError::NotFound(name.to_string()))?,
+            None => KeyboardMacro {
+                name: "last".to_string(),
+                commands: self.last.clone().ok_or(MacroError::NothingRecorded)?,
+            },
+        };
+        if !thread_focused && found.needs_selection() {
+            return Err(MacroError::NoThreadFocused(found.name));
+        }
+        Ok(found.commands)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -883,6 +1184,124 @@ mod tests {
         assert_eq!(result2, KeyResult::Matched("go_inbox".to_string()));
     }
 
+    /// Returns a clock that only moves when the returned handle is advanced.
+    fn fake_clock() -> (
+        impl Fn() -> Instant + Send + Sync + 'static,
+        Arc<std::sync::Mutex<Instant>>,
+    ) {
+        let now = Arc::new(std::sync::Mutex::new(Instant::now()));
+        let handle = now.clone();
+        (move || *now.lock().unwrap(), handle)
+    }
+
+    #[test]
+    fn manager_sequence_times_out() {
+        let mut manager = KeybindingManager::new();
+        let (clock, now) = fake_clock();
+        manager.set_clock(clock);
+
+        assert_eq!(manager.process(Keystroke::key(Key::G)), KeyResult::Pending);
+        *now.lock().unwrap() += Duration::from_millis(1001);
+
+        // `i` now starts a new sequence on its own instead of completing `g i`.
+        assert_eq!(manager.process(Keystroke::key(Key::I)), KeyResult::Ignored);
+    }
+
+    #[test]
+    fn manager_sequence_timeout_is_configurable() {
+        let mut manager = KeybindingManager::new();
+        let (clock, now) = fake_clock();
+        manager.set_clock(clock);
+        manager.set_sequence_timeout(Duration::from_millis(2500));
+        assert_eq!(manager.sequence_timeout(), Duration::from_millis(2500));
+
+        manager.process(Keystroke::key(Key::G));
+        *now.lock().unwrap() += Duration::from_millis(2000);
+        assert_eq!(
+            manager.process(Keystroke::key(Key::I)),
+            KeyResult::Matched("go_inbox".to_string())
+        );
+
+        manager.set_sequence_timeout(Duration::from_millis(500));
+        manager.process(Keystroke::key(Key::G));
+        *now.lock().unwrap() += Duration::from_millis(501);
+        assert_eq!(manager.process(Keystroke::key(Key::I)), KeyResult::Ignored);
+    }
+
+    #[test]
+    fn manager_pop_context_restores_previous() {
+        let mut manager = KeybindingManager::new();
+        manager.set_context(KeyContext::ReadingPane);
+
+        manager.push_context(KeyContext::CommandPalette);
+        assert_eq!(manager.context(), KeyContext::CommandPalette);
+
+        assert_eq!(manager.pop_context(), Some(KeyContext::CommandPalette));
+        assert_eq!(manager.context(), KeyContext::ReadingPane);
+        assert_eq!(
+            manager.process(Keystroke::key(Key::R)),
+            KeyResult::Matched("reply".to_string())
+        );
+
+        // Nothing left to pop; the context is unchanged.
+        assert_eq!(manager.pop_context(), None);
+        assert_eq!(manager.context(), KeyContext::ReadingPane);
+    }
+
+    #[test]
+    fn manager_pushed_context_falls_through_to_global_only() {
+        let mut manager = KeybindingManager::new();
+        manager.set_context(KeyContext::ReadingPane);
+        manager.push_context(KeyContext::CommandPalette);
+
+        assert_eq!(
+            manager.process(Keystroke::key(Key::Down)),
+            KeyResult::Matched("next_item".to_string())
+        );
+        assert_eq!(
+            manager.process(Keystroke::key(Key::C)),
+            KeyResult::Matched("compose".to_string())
+        );
+        // The covered reading pane's bindings are not reachable.
+        assert_eq!(manager.process(Keystroke::key(Key::R)), KeyResult::Ignored);
+    }
+
+    #[test]
+    fn manager_pending_completions() {
+        let mut manager = KeybindingManager::new();
+        let (clock, now) = fake_clock();
+        manager.set_clock(clock);
+        assert!(manager.pending_completions().is_empty());
+        assert_eq!(manager.pending_for(), None);
+
+        manager.process(Keystroke::key(Key::G));
+        *now.lock().unwrap() += Duration::from_millis(300);
+
+        let completions = manager.pending_completions();
+        assert_eq!(manager.pending_sequence(), &[Keystroke::key(Key::G)]);
+        assert_eq!(manager.pending_for(), Some(Duration::from_millis(300)));
+        assert!(completions.contains(&(Keystroke::key(Key::I), "go_inbox".to_string())));
+        assert!(completions.contains(&(Keystroke::key(Key::S), "go_starred".to_string())));
+
+        // Timing out hides the completions even before the next key.
+        *now.lock().unwrap() += Duration::from_millis(1000);
+        assert!(manager.pending_completions().is_empty());
+        assert_eq!(manager.pending_for(), None);
+    }
+
+    #[test]
+    fn manager_completions_clear_on_match_and_cancel() {
+        let mut manager = KeybindingManager::new();
+
+        manager.process(Keystroke::key(Key::G));
+        manager.process(Keystroke::key(Key::I));
+        assert!(manager.pending_completions().is_empty());
+
+        manager.process(Keystroke::key(Key::G));
+        manager.cancel_sequence();
+        assert!(manager.pending_completions().is_empty());
+    }
+
     #[test]
     fn manager_unknown_keystroke() {
         let mut manager = KeybindingManager::new();
@@ -939,10 +1358,159 @@ mod tests {
             bindings: HashMap::new(),
             pending_sequence: Vec::new(),
             last_keystroke: None,
-            sequence_timeout: Duration::from_millis(1000),
+            sequence_timeout: DEFAULT_SEQUENCE_TIMEOUT,
             current_context: KeyContext::Global,
+            context_stack: Vec::new(),
+            clock: Arc::new(Instant::now),
+            preset: Preset::Default,
         };
         new_manager.import_config(&config);
         assert!(!new_manager.bindings.is_empty());
     }
+
+    fn commands(manager: &KeybindingManager) -> Vec<String> {
+        let mut commands: Vec<String> = manager.all_bindings().into_keys().collect();
+        commands.sort();
+        commands
+    }
+
+    #[test]
+    fn presets_are_complete_and_free_of_conflicts() {
+        let default_commands = commands(&KeybindingManager::new());
+        for preset in Preset::ALL {
+            let manager = KeybindingManager::with_preset(preset);
+            let conflicts = manager.detect_conflicts();
+            assert!(conflicts.is_empty(), "{:?}: {:?}", preset, conflicts);
+            assert_eq!(commands(&manager), default_commands, "{:?}", preset);
+
+            // No binding in the table is silently replaced by a later one.
+            let bound: usize = manager.bindings.values().map(HashMap::len).sum();
+            assert_eq!(bound, preset.bindings().len(), "{:?}", preset);
+        }
+    }
+
+    #[test]
+    fn shadowed_sequences_are_conflicts() {
+        let mut manager = KeybindingManager::new();
+        manager.bind(
+            KeyContext::MessageList,
+            KeyBinding::single(Keystroke::key(Key::G)),
+            "go_top",
+        );
+        let conflicts = manager.detect_conflicts();
+        assert_eq!(conflicts.len(), 5);
+        assert!(conflicts.iter().all(|conflict| {
+            conflict.context == KeyContext::MessageList && conflict.commands[0] == "go_top"
+        }));
+    }
+
+    #[test]
+    fn switching_presets_resets_or_keeps_overrides() {
+        let mut manager = KeybindingManager::new();
+        let archive = KeyBinding::single(Keystroke::key(Key::Y));
+        manager.bind(KeyContext::MessageList, archive.clone(), "archive");
+
+        manager.switch_preset(Preset::Mutt, true);
+        assert_eq!(manager.preset(), Preset::Mutt);
+        manager.set_context(KeyContext::MessageList);
+        assert_eq!(
+            manager.process(Keystroke::key(Key::Y)),
+            KeyResult::Matched("archive".to_string())
+        );
+        assert_eq!(
+            manager.process(Keystroke::key(Key::D)),
+            KeyResult::Matched("trash".to_string())
+        );
+
+        let config = manager.export_config();
+        assert_eq!(config.preset, Preset::Mutt);
+        assert_eq!(config.bindings.len(), 1);
+        let mut restored = KeybindingManager::new();
+        restored.import_config(&config);
+        assert_eq!(restored.preset(), Preset::Mutt);
+        assert_eq!(
+            restored.bindings_for_command("archive").len(),
+            manager.bindings_for_command("archive").len()
+        );
+
+        manager.load_preset(Preset::Gmail);
+        assert!(manager.overrides().is_empty());
+        assert_eq!(manager.process(Keystroke::key(Key::Y)), KeyResult::Ignored);
+    }
+
+    fn record_keys(
+        manager: &mut KeybindingManager,
+        recorder: &mut MacroRecorder,
+        keys: &[Keystroke],
+    ) {
+        for &keystroke in keys {
+            if let KeyResult::Matched(command) = manager.process(keystroke) {
+                if command == RECORD_MACRO {
+                    recorder.toggle_recording();
+                } else {
+                    recorder.record(&command);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn recorded_macros_replay_the_same_commands() {
+        let mut manager = KeybindingManager::new();
+        manager.set_context(KeyContext::MessageList);
+        let mut recorder = MacroRecorder::new();
+        assert_eq!(
+            recorder.replay(None, true),
+            Err(MacroError::NothingRecorded)
+        );
+
+        record_keys(
+            &mut manager,
+            &mut recorder,
+            &[
+                Keystroke::key(Key::Q),
+                Keystroke::shift(Key::U),
+                Keystroke::key(Key::E),
+                Keystroke::key(Key::J),
+                Keystroke::key(Key::Q),
+            ],
+        );
+        assert!(!recorder.is_recording());
+        let recorded = vec!["mark_read", "archive", "next_message"];
+        assert_eq!(recorder.replay(None, true).unwrap(), recorded);
+
+        recorder.save("triage").unwrap();
+        let restored = MacroRecorder::with_macros(recorder.macros().to_vec());
+        assert_eq!(restored.replay(Some("triage"), true).unwrap(), recorded);
+        assert_eq!(
+            restored.replay(Some("other"), true),
+            Err(MacroError::NotFound("other".to_string()))
+        );
+    }
+
+    #[test]
+    fn macros_acting_on_the_selection_need_a_focused_thread() {
+        let mut recorder = MacroRecorder::new();
+        recorder.start_recording();
+        recorder.record("go_inbox");
+        recorder.record(REPLAY_MACRO);
+        assert_eq!(recorder.replay(None, false), Err(MacroError::Recording));
+        recorder.stop_recording();
+        recorder.save("inbox").unwrap();
+        assert_eq!(recorder.replay(Some("inbox"), false).unwrap(), ["go_inbox"]);
+
+        recorder.start_recording();
+        recorder.record("star");
+        recorder.stop_recording();
+        recorder.save("star").unwrap();
+        assert_eq!(
+            recorder.replay(Some("star"), false),
+            Err(MacroError::NoThreadFocused("star".to_string()))
+        );
+        assert_eq!(recorder.replay(Some("star"), true).unwrap(), ["star"]);
+
+        assert!(recorder.remove("star"));
+        assert!(!recorder.remove("star"));
+        assert_eq!(recorder.macros().len(), 1);
+    }
 }
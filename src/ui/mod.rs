@@ -19,8 +19,8 @@ pub use accessibility::{
     AccessibleElement, AccessibleState, Role,
 };
 pub use keybindings::{
-    Key, KeyBinding, KeyContext, KeyResult, KeybindingConfig, KeybindingManager, Keystroke,
-    Modifiers,
+    Key, KeyBinding, KeyContext, KeyResult, KeybindingConfig, KeybindingManager, KeyboardMacro,
+    Keystroke, MacroError, MacroRecorder, Modifiers, Preset,
 };
 pub use theme::{Theme, ThemeColors, ThemeMode};
 pub use views::MainWindow;
@@ -31,8 +31,46 @@ pub struct ThemeColors {
     // Email-specific
     pub unread: Hsla,
     pub starred: Hsla,
+
+    /// Colors handed out to labels and avatars by [`ThemeColors::color_for`].
+    pub palette: &'static [u32],
 }
 
+/// Palette for the dark theme. Every color has at least 3:1 contrast with
+/// the background in both directions, so it works for dots and as an
+/// avatar background behind background-colored initials.
+const DARK_PALETTE: &[u32] = &[
+    0x60a5fa, // Blue
+    0x34d399, // Emerald
+    0xfbbf24, // Amber
+    0xf87171, // Red
+    0xa78bfa, // Violet
+    0xf472b6, // Pink
+    0x22d3ee, // Cyan
+    0xa3e635, // Lime
+    0xfb923c, // Orange
+    0x2dd4bf, // Teal
+    0x818cf8, // Indigo
+    0xe879f9, // Fuchsia
+];
+
+/// Palette for the light theme, the same hues darkened for contrast with
+/// a white background.
+const LIGHT_PALETTE: &[u32] = &[
+    0x2563eb, // Blue
+    0x047857, // Emerald
+    0xb45309, // Amber
+    0xdc2626, // Red
+    0x7c3aed, // Violet
+    0xdb2777, // Pink
+    0x0e7490, // Cyan
+    0x4d7c0f, // Lime
+    0xc2410c, // Orange
+    0x0f766e, // Teal
+    0x4f46e5, // Indigo
+    0xa21caf, // Fuchsia
+];
+
 impl ThemeColors {
     /// Dark theme colors
     pub fn dark() -> Self {
@@ -63,6 +101,8 @@ impl ThemeColors {
             // Email-specific
             unread: rgb(0x4a9eff).into(),
             starred: rgb(0xffc107).into(),
+
+            palette: DARK_PALETTE,
         }
     }
 
@@ -95,8 +135,42 @@ impl ThemeColors {
             // Email-specific
             unread: rgb(0x1a73e8).into(),
             starred: rgb(0xfbbc04).into(),
+
+            palette: LIGHT_PALETTE,
         }
     }
+
+    /// Color for a key such as a label id or contact address. The same key
+    /// always gets the same palette color.
+    pub fn color_for(&self, key: &str) -> Hsla {
+        self.palette_color(color_seed(key))
+    }
+
+    /// Palette color for a seed from [`color_seed`] or a contact's avatar.
+    pub fn palette_color(&self, seed: u32) -> Hsla {
+        rgb(self.palette[seed as usize % self.palette.len()]).into()
+    }
+}
+
+/// Stable hash of a key for picking a palette color (32-bit FNV-1a).
+///
+/// Unlike `std`'s randomly keyed hasher, it is the same across runs, and
+/// similar keys like "work" and "works" still spread across the palette.
+pub fn color_seed(key: &str) -> u32 {
+    key.bytes().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Parses a `#rrggbb` color, as providers report label colors.
+pub fn parse_hex_color(value: &str) -> Option<Hsla> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .map(|color| rgb(color).into())
 }
 
 /// Theme mode
@@ -145,3 +219,63 @@ impl Theme {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn luminance(color: u32) -> f64 {
+        let channel = |shift: u32| {
+            let c = f64::from((color >> shift) & 0xff) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(16) + 0.7152 * channel(8) + 0.0722 * channel(0)
+    }
+
+    fn contrast(a: u32, b: u32) -> f64 {
+        let (a, b) = (luminance(a), luminance(b));
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    #[test]
+    fn same_key_gets_same_palette_color() {
+        for colors in [ThemeColors::dark(), ThemeColors::light()] {
+            let palette: Vec<Hsla> = colors.palette.iter().map(|&c| rgb(c).into()).collect();
+            for key in ["work", "works", "alice@example.com", ""] {
+                let color = colors.color_for(key);
+                assert_eq!(color, colors.color_for(key));
+                assert!(palette.contains(&color), "{} is off the palette", key);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_spread_across_the_palette() {
+        let colors = ThemeColors::dark();
+        let used: std::collections::HashSet<u32> = (0..100)
+            .map(|i| color_seed(&format!("label-{}", i)) % colors.palette.len() as u32)
+            .collect();
+        assert_eq!(used.len(), colors.palette.len());
+    }
+
+    #[test]
+    fn palettes_contrast_with_their_background() {
+        for (palette, background) in [(DARK_PALETTE, 0x1a1a1a), (LIGHT_PALETTE, 0xffffff)] {
+            for &color in palette {
+                assert!(contrast(color, background) >= 3.0, "{:06x}", color);
+            }
+        }
+        assert_eq!(DARK_PALETTE.len(), LIGHT_PALETTE.len());
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex_color("#3b82f6"), Some(rgb(0x3b82f6).into()));
+        assert_eq!(parse_hex_color("3b82f6"), None);
+        assert_eq!(parse_hex_color("#fff"), None);
+    }
+}
@@ -134,6 +134,12 @@ impl CommandPalette {
                 shortcut: Some("#".to_string()),
                 category: CommandCategory::Email,
             },
+            Command {
+                id: "report-spam".to_string(),
+                label: "Report Spam".to_string(),
+                shortcut: Some("!".to_string()),
+                category: CommandCategory::Email,
+            },
             Command {
                 id: "star".to_string(),
                 label: "Star/Unstar".to_string(),
@@ -2,18 +2,24 @@
 //!
 //! Email composition window for new messages, replies, and forwards.
 
+use std::sync::Arc;
+
 use gpui::{
-    div, prelude::FluentBuilder, px, Context, FontWeight, InteractiveElement, IntoElement,
-    ParentElement, Render, SharedString, Styled, Window,
+    div, prelude::FluentBuilder, px, ClickEvent, Context, FontWeight, HighlightStyle,
+    InteractiveElement, InteractiveText, IntoElement, ParentElement, Render, SharedString,
+    StatefulInteractiveElement, Styled, StyledText, UnderlineStyle, Window,
 };
 
-use crate::app::ComposerMode;
+use crate::app::{ComposerMode, CorrectSpelling, NextMisspelling};
+use crate::domain::Address;
+use crate::services::{system_locale, Misspelling, SpellChecker};
 use crate::ui::theme::ThemeColors;
 
 /// Composer view component.
 pub struct Composer {
     colors: ThemeColors,
     mode: ComposerMode,
+    from: FromAddresses,
     to: Vec<String>,
     cc: Vec<String>,
     bcc: Vec<String>,
@@ -25,6 +31,67 @@ pub struct Composer {
     ai_suggestion: Option<String>,
     show_cc: bool,
     show_bcc: bool,
+    spell_checker: Option<Arc<SpellChecker>>,
+    misspellings: Vec<Misspelling>,
+    /// Misspelling whose suggestions are shown.
+    active_misspelling: Option<usize>,
+}
+
+/// The addresses a message can be sent from and which one is chosen.
+#[derive(Debug, Clone, Default)]
+pub struct FromAddresses {
+    /// Addresses the account can send from, its own first.
+    addresses: Vec<Address>,
+    /// Index into `addresses` of the address to send from.
+    index: usize,
+}
+
+impl FromAddresses {
+    /// Sets the addresses, as given by `Account::from_addresses`, keeping
+    /// the chosen one if it is still there.
+    pub fn set(&mut self, addresses: Vec<Address>) {
+        let chosen = self.current().map(|address| address.email.clone());
+        self.addresses = addresses;
+        self.index = chosen
+            .and_then(|email| {
+                self.addresses
+                    .iter()
+                    .position(|address| address.email.eq_ignore_ascii_case(&email))
+            })
+            .unwrap_or(0);
+    }
+
+    /// Returns the address the message will be sent from.
+    pub fn current(&self) -> Option<&Address> {
+        self.addresses.get(self.index)
+    }
+
+    /// Whether there is more than one address to choose from.
+    pub fn has_choice(&self) -> bool {
+        self.addresses.len() > 1
+    }
+
+    /// Chooses the address at `index`, returning whether the choice
+    /// changed.
+    pub fn select(&mut self, index: usize) -> bool {
+        let changed = index < self.addresses.len() && index != self.index;
+        if changed {
+            self.index = index;
+        }
+        changed
+    }
+
+    /// Chooses the next address, wrapping around, returning whether the
+    /// choice changed.
+    pub fn cycle(&mut self) -> bool {
+        !self.addresses.is_empty() && self.select((self.index + 1) % self.addresses.len())
+    }
+
+    /// Returns the alias to put on the draft: `None` for the account's own
+    /// address.
+    pub fn alias(&self) -> Option<Address> {
+        (self.index > 0).then(|| self.current().cloned()).flatten()
+    }
 }
 
 /// Attachment in composer.
@@ -41,6 +108,7 @@ impl Composer {
         Self {
             colors: ThemeColors::dark(),
             mode: ComposerMode::New,
+            from: FromAddresses::default(),
             to: Vec::new(),
             cc: Vec::new(),
             bcc: Vec::new(),
@@ -52,6 +120,9 @@ impl Composer {
             ai_suggestion: None,
             show_cc: false,
             show_bcc: false,
+            spell_checker: default_spell_checker(),
+            misspellings: Vec::new(),
+            active_misspelling: None,
         }
     }
 
@@ -66,6 +137,7 @@ impl Composer {
         Self {
             colors: ThemeColors::dark(),
             mode: ComposerMode::Reply,
+            from: FromAddresses::default(),
             to: vec![to],
             cc: Vec::new(),
             bcc: Vec::new(),
@@ -77,9 +149,44 @@ impl Composer {
             ai_suggestion: None,
             show_cc: false,
             show_bcc: false,
+            spell_checker: default_spell_checker(),
+            misspellings: Vec::new(),
+            active_misspelling: None,
+        }
+    }
+
+    /// Sets the addresses the message can be sent from, as given by
+    /// `Account::from_addresses`, keeping the chosen one if it is still
+    /// there.
+    pub fn set_from_addresses(&mut self, addresses: Vec<Address>) {
+        self.from.set(addresses);
+    }
+
+    /// Returns the address the message will be sent from.
+    pub fn from(&self) -> Option<&Address> {
+        self.from.current()
+    }
+
+    /// Chooses the address at `index` of the from addresses.
+    pub fn select_from(&mut self, index: usize) {
+        if self.from.select(index) {
+            self.is_dirty = true;
+        }
+    }
+
+    /// Chooses the next from address, wrapping around.
+    pub fn cycle_from(&mut self) {
+        if self.from.cycle() {
+            self.is_dirty = true;
         }
     }
 
+    /// Returns the alias to put on the draft: `None` for the account's own
+    /// address.
+    pub fn from_alias(&self) -> Option<Address> {
+        self.from.alias()
+    }
+
     /// Set the AI suggestion.
     pub fn set_ai_suggestion(&mut self, suggestion: String) {
         self.ai_suggestion = Some(suggestion);
@@ -88,8 +195,7 @@ impl Composer {
     /// Accept AI suggestion.
     pub fn accept_ai_suggestion(&mut self) {
         if let Some(suggestion) = self.ai_suggestion.take() {
-            self.body = suggestion;
-            self.is_dirty = true;
+            self.set_body(suggestion);
         }
     }
 
@@ -98,6 +204,81 @@ impl Composer {
         self.ai_suggestion = None;
     }
 
+    /// Replace the body and check its spelling.
+    pub fn set_body(&mut self, body: String) {
+        self.body = body;
+        self.is_dirty = true;
+        self.check_spelling();
+    }
+
+    /// Set the spell checker, e.g. for another language. `None` turns
+    /// spell checking off.
+    pub fn set_spell_checker(&mut self, spell_checker: Option<Arc<SpellChecker>>) {
+        self.spell_checker = spell_checker;
+        self.check_spelling();
+    }
+
+    /// Misspelled words in the body.
+    pub fn misspellings(&self) -> &[Misspelling] {
+        &self.misspellings
+    }
+
+    /// Show the suggestions for a misspelling.
+    pub fn show_suggestions(&mut self, index: usize) {
+        if index < self.misspellings.len() {
+            self.active_misspelling = Some(index);
+        }
+    }
+
+    /// Show the suggestions for the next misspelling, wrapping around.
+    pub fn next_misspelling(&mut self) {
+        self.active_misspelling = match self.active_misspelling {
+            _ if self.misspellings.is_empty() => None,
+            Some(index) => Some((index + 1) % self.misspellings.len()),
+            None => Some(0),
+        };
+    }
+
+    /// Hide spelling suggestions.
+    pub fn dismiss_suggestions(&mut self) {
+        self.active_misspelling = None;
+    }
+
+    /// Corrections for the misspelling whose suggestions are shown.
+    pub fn suggestions(&self) -> Vec<String> {
+        let (Some(checker), Some(index)) = (&self.spell_checker, self.active_misspelling) else {
+            return Vec::new();
+        };
+        checker.suggest(&self.misspellings[index].word)
+    }
+
+    /// Replace the misspelling whose suggestions are shown.
+    pub fn apply_suggestion(&mut self, suggestion: &str) {
+        if let Some(index) = self.active_misspelling.take() {
+            let range = self.misspellings[index].range.clone();
+            self.body.replace_range(range, suggestion);
+            self.is_dirty = true;
+            self.check_spelling();
+        }
+    }
+
+    /// Replace the misspelling whose suggestions are shown with the best
+    /// suggestion.
+    pub fn correct_spelling(&mut self) {
+        if let Some(suggestion) = self.suggestions().into_iter().next() {
+            self.apply_suggestion(&suggestion);
+        }
+    }
+
+    fn check_spelling(&mut self) {
+        self.misspellings = self
+            .spell_checker
+            .as_ref()
+            .map(|checker| checker.check(&self.body))
+            .unwrap_or_default();
+        self.active_misspelling = None;
+    }
+
     /// Add attachment.
     pub fn add_attachment(&mut self, attachment: ComposerAttachment) {
         self.attachments.push(attachment);
@@ -225,26 +406,107 @@ impl Composer {
             )
     }
 
-    fn render_body_area(&self) -> impl IntoElement {
+    /// Renders the From row, which switches to the next address on click.
+    fn render_from(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let display = self.from().map(Address::display).unwrap_or_default();
+        div()
+            .id("composer-from")
+            .cursor_pointer()
+            .on_click(cx.listener(|this, _: &ClickEvent, _, cx| {
+                this.cycle_from();
+                cx.notify();
+            }))
+            .child(self.render_field("From", &display, "Sender"))
+    }
+
+    fn render_body_area(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let text_primary = self.colors.text_primary;
         let text_muted = self.colors.text_muted;
 
-        let display = if self.body.is_empty() {
-            "Compose your message..."
-        } else {
-            &self.body
-        };
-        let color = if self.body.is_empty() {
-            text_muted
-        } else {
-            text_primary
+        if self.body.is_empty() {
+            return div()
+                .flex_1()
+                .p(px(16.0))
+                .text_color(text_muted)
+                .child(SharedString::from("Compose your message..."));
+        }
+
+        let underline = HighlightStyle {
+            underline: Some(UnderlineStyle {
+                thickness: px(1.0),
+                color: Some(self.colors.error),
+                wavy: true,
+            }),
+            ..Default::default()
         };
+        let ranges: Vec<_> = self
+            .misspellings
+            .iter()
+            .map(|misspelling| misspelling.range.clone())
+            .collect();
+        let text = StyledText::new(SharedString::from(self.body.clone()))
+            .with_highlights(ranges.iter().map(|range| (range.clone(), underline)));
+        let composer = cx.entity().downgrade();
 
         div()
             .flex_1()
             .p(px(16.0))
-            .text_color(color)
-            .child(SharedString::from(display.to_string()))
+            .text_color(text_primary)
+            .child(InteractiveText::new("composer-body", text).on_click(
+                ranges,
+                move |index, _, cx| {
+                    composer
+                        .update(cx, |this, cx| {
+                            this.show_suggestions(index);
+                            cx.notify();
+                        })
+                        .ok();
+                },
+            ))
+            .when(self.active_misspelling.is_some(), |this| {
+                this.child(self.render_spelling_suggestions(cx))
+            })
+    }
+
+    fn render_spelling_suggestions(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let suggestions = self.suggestions();
+        let text_primary = self.colors.text_primary;
+        let text_muted = self.colors.text_muted;
+
+        div()
+            .mt(px(8.0))
+            .p(px(8.0))
+            .flex()
+            .flex_wrap()
+            .gap(px(8.0))
+            .rounded(px(8.0))
+            .bg(self.colors.surface_elevated)
+            .border_1()
+            .border_color(self.colors.border)
+            .when(suggestions.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(text_muted)
+                        .child(SharedString::from("No suggestions")),
+                )
+            })
+            .children(suggestions.into_iter().enumerate().map(|(i, suggestion)| {
+                let label = SharedString::from(suggestion.clone());
+                div()
+                    .id(("spelling-suggestion", i))
+                    .px(px(8.0))
+                    .py(px(4.0))
+                    .rounded(px(4.0))
+                    .text_sm()
+                    .text_color(text_primary)
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |this, _: &ClickEvent, _, cx| {
+                        this.apply_suggestion(&suggestion);
+                        cx.notify();
+                    }))
+                    .child(label)
+            }))
     }
 
     fn render_ai_suggestion(&self, suggestion: &str) -> impl IntoElement {
@@ -355,15 +617,33 @@ impl Composer {
     }
 }
 
+/// Spell checker for the user's locale, when a dictionary covers it.
+fn default_spell_checker() -> Option<Arc<SpellChecker>> {
+    let locale = system_locale().unwrap_or_else(|| "en".to_string());
+    SpellChecker::for_locale(&locale).map(Arc::new)
+}
+
 impl Render for Composer {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .id("composer")
+            .key_context("Composer")
+            .on_action(cx.listener(|this, _: &NextMisspelling, _, cx| {
+                this.next_misspelling();
+                cx.notify();
+            }))
+            .on_action(cx.listener(|this, _: &CorrectSpelling, _, cx| {
+                this.correct_spelling();
+                cx.notify();
+            }))
             .size_full()
             .flex()
             .flex_col()
             .bg(self.colors.background)
             .child(self.render_header())
+            .when(self.from.has_choice(), |this| {
+                this.child(self.render_from(cx))
+            })
             .child(self.render_field("To", &self.to.join(", "), "Recipients"))
             .when(self.show_cc, |this| {
                 this.child(self.render_field("Cc", &self.cc.join(", "), "Cc recipients"))
@@ -375,7 +655,7 @@ impl Render for Composer {
             .when_some(self.ai_suggestion.clone(), |this, suggestion| {
                 this.child(self.render_ai_suggestion(&suggestion))
             })
-            .child(self.render_body_area())
+            .child(self.render_body_area(cx))
             .when(!self.attachments.is_empty(), |this| {
                 this.child(self.render_attachments())
             })
@@ -391,6 +671,7 @@ mod tests {
         let mut composer = Composer {
             colors: ThemeColors::dark(),
             mode: ComposerMode::New,
+            from: FromAddresses::default(),
             to: Vec::new(),
             cc: Vec::new(),
             bcc: Vec::new(),
@@ -402,6 +683,9 @@ mod tests {
             ai_suggestion: None,
             show_cc: false,
             show_bcc: false,
+            spell_checker: None,
+            misspellings: Vec::new(),
+            active_misspelling: None,
         };
 
         assert!(!composer.can_send());
@@ -418,6 +702,7 @@ mod tests {
         let mut composer = Composer {
             colors: ThemeColors::dark(),
             mode: ComposerMode::Reply,
+            from: FromAddresses::default(),
             to: vec!["test@example.com".to_string()],
             cc: Vec::new(),
             bcc: Vec::new(),
@@ -429,6 +714,9 @@ mod tests {
             ai_suggestion: None,
             show_cc: false,
             show_bcc: false,
+            spell_checker: None,
+            misspellings: Vec::new(),
+            active_misspelling: None,
         };
 
         composer.set_ai_suggestion("AI drafted reply".to_string());
@@ -445,6 +733,7 @@ mod tests {
         let mut composer = Composer {
             colors: ThemeColors::dark(),
             mode: ComposerMode::New,
+            from: FromAddresses::default(),
             to: Vec::new(),
             cc: Vec::new(),
             bcc: Vec::new(),
@@ -456,6 +745,9 @@ mod tests {
             ai_suggestion: None,
             show_cc: false,
             show_bcc: false,
+            spell_checker: None,
+            misspellings: Vec::new(),
+            active_misspelling: None,
         };
 
         composer.add_attachment(ComposerAttachment {
@@ -470,4 +762,98 @@ mod tests {
         composer.remove_attachment(0);
         assert!(composer.attachments.is_empty());
     }
+
+    #[test]
+    fn spelling_corrections_replace_misspelled_words() {
+        let mut composer = Composer {
+            colors: ThemeColors::dark(),
+            mode: ComposerMode::New,
+            from: FromAddresses::default(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: String::new(),
+            body: String::new(),
+            attachments: Vec::new(),
+            is_dirty: false,
+            is_sending: false,
+            ai_suggestion: None,
+            show_cc: false,
+            show_bcc: false,
+            spell_checker: None,
+            misspellings: Vec::new(),
+            active_misspelling: None,
+        };
+
+        composer.set_body("I will recieve it tomorow".to_string());
+        assert!(composer.misspellings().is_empty());
+
+        composer.set_spell_checker(SpellChecker::for_locale("en").map(Arc::new));
+        assert_eq!(composer.misspellings().len(), 2);
+
+        composer.next_misspelling();
+        composer.next_misspelling();
+        assert_eq!(composer.suggestions()[0], "tomorrow");
+        composer.correct_spelling();
+        assert_eq!(composer.body, "I will recieve it tomorrow");
+
+        composer.show_suggestions(0);
+        composer.apply_suggestion("receive");
+        assert_eq!(composer.body, "I will receive it tomorrow");
+        assert!(composer.misspellings().is_empty());
+        assert!(composer.suggestions().is_empty());
+    }
+
+    #[test]
+    fn from_alias_selection() {
+        let mut composer = Composer {
+            colors: ThemeColors::dark(),
+            mode: ComposerMode::New,
+            from: FromAddresses::default(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: String::new(),
+            body: String::new(),
+            attachments: Vec::new(),
+            is_dirty: false,
+            is_sending: false,
+            ai_suggestion: None,
+            show_cc: false,
+            show_bcc: false,
+            spell_checker: None,
+            misspellings: Vec::new(),
+            active_misspelling: None,
+        };
+        assert!(composer.from().is_none());
+
+        composer.set_from_addresses(vec![
+            Address::with_name("me@example.com", "Me"),
+            Address::new("billing@example.com"),
+            Address::new("support@example.com"),
+        ]);
+        assert_eq!(composer.from().unwrap().email, "me@example.com");
+        assert_eq!(composer.from_alias(), None);
+
+        composer.cycle_from();
+        composer.cycle_from();
+        assert_eq!(
+            composer.from_alias(),
+            Some(Address::new("support@example.com"))
+        );
+        assert!(composer.is_dirty);
+
+        // Refreshed aliases keep the choice; a removed alias falls back to
+        // the account's own address.
+        composer.set_from_addresses(vec![
+            Address::new("me@example.com"),
+            Address::new("support@example.com"),
+        ]);
+        assert_eq!(composer.from().unwrap().email, "support@example.com");
+        composer.set_from_addresses(vec![Address::new("me@example.com")]);
+        assert_eq!(composer.from_alias(), None);
+
+        composer.cycle_from();
+        assert_eq!(composer.from().unwrap().email, "me@example.com");
+    }
 }
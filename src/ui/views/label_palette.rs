@@ -0,0 +1,395 @@
+//! Label palette state.
+//!
+//! Keyboard-driven picker behind the apply label overlay. Typing filters
+//! labels with a fuzzy match, space toggles the highlighted label, and enter
+//! applies the selection. Labels the threads already have start selected,
+//! and deselecting one removes it. When no label has the typed name, the
+//! palette offers to create it.
+
+use crate::ui::components::{KeyInputResult, TextBuffer};
+
+/// A label the palette can apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PaletteLabel {
+    /// Existing label ID, or `None` for a label created in the palette.
+    id: Option<String>,
+    name: String,
+}
+
+/// A row in the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelPaletteEntry {
+    /// A known label.
+    Label {
+        id: Option<String>,
+        name: String,
+        selected: bool,
+    },
+    /// Creates a label with the typed name.
+    Create(String),
+}
+
+/// What a key press did to the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelPaletteKey {
+    /// The key changed the query, highlight or selection.
+    Changed,
+    /// The user confirmed the selection.
+    Apply(LabelSelection),
+    /// The user dismissed the palette.
+    Cancel,
+    /// The palette did not handle the key.
+    Ignored,
+}
+
+/// Labels chosen in the palette.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelection {
+    /// IDs of existing labels, in the order they were selected.
+    pub label_ids: Vec<String>,
+    /// Names of labels to create, in the order they were selected.
+    pub new_labels: Vec<String>,
+    /// IDs of labels the threads had that were deselected.
+    pub removed_label_ids: Vec<String>,
+}
+
+/// Filter and selection state of the label palette.
+#[derive(Debug, Clone, Default)]
+pub struct LabelPalette {
+    labels: Vec<PaletteLabel>,
+    query: TextBuffer,
+    /// Indices into `labels` matching the query, best match first.
+    matches: Vec<usize>,
+    /// Index into [`entries`](Self::entries).
+    highlighted: usize,
+    /// Indices into `labels`, in the order they were selected.
+    selected: Vec<usize>,
+    /// Indices into `labels` of the labels the threads already have.
+    applied: Vec<usize>,
+}
+
+impl LabelPalette {
+    /// Creates a palette over `(id, name)` pairs, in display order.
+    pub fn new(labels: impl IntoIterator<Item = (String, String)>) -> Self {
+        let labels: Vec<PaletteLabel> = labels
+            .into_iter()
+            .map(|(id, name)| PaletteLabel { id: Some(id), name })
+            .collect();
+        Self {
+            matches: (0..labels.len()).collect(),
+            labels,
+            ..Default::default()
+        }
+    }
+
+    /// Starts with the labels in `label_ids`, which the threads already
+    /// have, selected.
+    pub fn with_applied(mut self, label_ids: &[String]) -> Self {
+        self.applied = self
+            .labels
+            .iter()
+            .enumerate()
+            .filter(|(_, label)| label.id.as_ref().is_some_and(|id| label_ids.contains(id)))
+            .map(|(i, _)| i)
+            .collect();
+        self.selected = self.applied.clone();
+        self
+    }
+
+    /// Returns the filter text.
+    pub fn query(&self) -> &str {
+        self.query.text()
+    }
+
+    /// Returns the highlighted row.
+    pub fn highlighted(&self) -> usize {
+        self.highlighted
+    }
+
+    /// Returns how many labels are selected.
+    pub fn selected_count(&self) -> usize {
+        self.selected.len()
+    }
+
+    /// Returns the rows to show: matching labels, then the option to create
+    /// a label when none is named exactly like the query.
+    pub fn entries(&self) -> Vec<LabelPaletteEntry> {
+        let mut entries: Vec<LabelPaletteEntry> = self
+            .matches
+            .iter()
+            .map(|&i| LabelPaletteEntry::Label {
+                id: self.labels[i].id.clone(),
+                name: self.labels[i].name.clone(),
+                selected: self.selected.contains(&i),
+            })
+            .collect();
+        if let Some(name) = self.new_label_name() {
+            entries.push(LabelPaletteEntry::Create(name));
+        }
+        entries
+    }
+
+    /// Handles a key: arrows move the highlight, space toggles the
+    /// highlighted row, enter applies, escape cancels, and anything else
+    /// edits the query.
+    pub fn handle_key(&mut self, key: &str, shift: bool, ctrl: bool, cmd: bool) -> LabelPaletteKey {
+        match key {
+            "up" => {
+                self.highlighted = self.highlighted.saturating_sub(1);
+                LabelPaletteKey::Changed
+            }
+            "down" => {
+                if self.highlighted + 1 < self.entry_count() {
+                    self.highlighted += 1;
+                }
+                LabelPaletteKey::Changed
+            }
+            "space" => {
+                self.toggle(self.highlighted);
+                LabelPaletteKey::Changed
+            }
+            "enter" => self.confirm(),
+            _ => match self.query.process_key(key, shift, ctrl, cmd) {
+                KeyInputResult::TextChanged => {
+                    self.refilter();
+                    LabelPaletteKey::Changed
+                }
+                KeyInputResult::Consumed => LabelPaletteKey::Changed,
+                KeyInputResult::Submit => self.confirm(),
+                KeyInputResult::Cancel => LabelPaletteKey::Cancel,
+                KeyInputResult::Ignored => LabelPaletteKey::Ignored,
+            },
+        }
+    }
+
+    /// Toggles the row at `index` of [`entries`](Self::entries). Toggling
+    /// the create row adds the new label, selected.
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(&label) = self.matches.get(index) {
+            if let Some(position) = self.selected.iter().position(|&i| i == label) {
+                self.selected.remove(position);
+            } else {
+                self.selected.push(label);
+            }
+        } else if index == self.matches.len() {
+            if let Some(name) = self.new_label_name() {
+                self.labels.push(PaletteLabel { id: None, name });
+                self.selected.push(self.labels.len() - 1);
+                self.refilter();
+            }
+        }
+    }
+
+    /// Applies the changes to the selection, or the highlighted row when
+    /// nothing was toggled yet. A highlighted create row is always included.
+    pub fn confirm(&mut self) -> LabelPaletteKey {
+        if self.selected == self.applied || self.highlighted == self.matches.len() {
+            self.toggle(self.highlighted);
+        }
+
+        let mut selection = LabelSelection::default();
+        for &i in self.selected.iter().filter(|i| !self.applied.contains(i)) {
+            let label = &self.labels[i];
+            match &label.id {
+                Some(id) => selection.label_ids.push(id.clone()),
+                None => selection.new_labels.push(label.name.clone()),
+            }
+        }
+        for &i in self.applied.iter().filter(|i| !self.selected.contains(i)) {
+            selection
+                .removed_label_ids
+                .extend(self.labels[i].id.iter().cloned());
+        }
+        if selection == LabelSelection::default() {
+            return LabelPaletteKey::Ignored;
+        }
+        LabelPaletteKey::Apply(selection)
+    }
+
+    fn entry_count(&self) -> usize {
+        self.matches.len() + usize::from(self.new_label_name().is_some())
+    }
+
+    /// The trimmed query, if no label already has that name.
+    fn new_label_name(&self) -> Option<String> {
+        let name = self.query.text().trim();
+        let taken = self
+            .labels
+            .iter()
+            .any(|label| label.name.eq_ignore_ascii_case(name));
+        (!name.is_empty() && !taken).then(|| name.to_string())
+    }
+
+    fn refilter(&mut self) {
+        let query = self.query.text().trim().to_lowercase();
+        let mut scored: Vec<(usize, usize)> = self
+            .labels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| fuzzy_score(&query, &label.name).map(|score| (score, i)))
+            .collect();
+        scored.sort_unstable();
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.highlighted = 0;
+    }
+}
+
+/// Scores `name` against a lowercase query whose characters must appear in
+/// order. Lower is better: a prefix match scores 0, and otherwise the score
+/// grows with how late the match starts and how spread out it is.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let name = name.to_lowercase();
+    if name.starts_with(query) {
+        return Some(0);
+    }
+
+    let mut wanted = query.chars().peekable();
+    let mut first = None;
+    let mut last = 0;
+    for (i, c) in name.chars().enumerate() {
+        if wanted.peek() == Some(&c) {
+            wanted.next();
+            first.get_or_insert(i);
+            last = i;
+        }
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    let first = first.unwrap_or_default();
+    Some(1 + first + (last - first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> LabelPalette {
+        LabelPalette::new(
+            [
+                ("label-1", "Work"),
+                ("label-2", "Personal"),
+                ("label-3", "Follow Up"),
+                ("label-4", "Waiting"),
+            ]
+            .map(|(id, name)| (id.to_string(), name.to_string())),
+        )
+    }
+
+    fn type_text(palette: &mut LabelPalette, text: &str) {
+        for c in text.chars() {
+            palette.handle_key(&c.to_string(), false, false, false);
+        }
+    }
+
+    fn names(palette: &LabelPalette) -> Vec<String> {
+        palette
+            .entries()
+            .into_iter()
+            .map(|entry| match entry {
+                LabelPaletteEntry::Label { name, .. } => name,
+                LabelPaletteEntry::Create(name) => format!("+{}", name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn label_palette_filters_selects_and_creates() {
+        let mut palette = palette();
+        assert_eq!(
+            names(&palette),
+            ["Work", "Personal", "Follow Up", "Waiting"]
+        );
+
+        // Prefix matches rank ahead of scattered ones.
+        type_text(&mut palette, "w");
+        assert_eq!(names(&palette), ["Work", "Waiting", "Follow Up", "+w"]);
+        type_text(&mut palette, "ai");
+        assert_eq!(names(&palette), ["Waiting", "+wai"]);
+
+        // Space toggles the highlighted label without applying.
+        assert_eq!(
+            palette.handle_key("space", false, false, false),
+            LabelPaletteKey::Changed
+        );
+        assert_eq!(palette.selected_count(), 1);
+
+        // Clearing the filter keeps the selection.
+        for _ in 0..3 {
+            palette.handle_key("backspace", false, false, false);
+        }
+        type_text(&mut palette, "flup");
+        assert_eq!(names(&palette), ["Follow Up", "+flup"]);
+        palette.handle_key("space", false, false, false);
+
+        // Nothing matches, so enter creates the label and applies.
+        for _ in 0..4 {
+            palette.handle_key("backspace", false, false, false);
+        }
+        type_text(&mut palette, "Travel");
+        assert_eq!(names(&palette), ["+Travel"]);
+        assert_eq!(
+            palette.handle_key("enter", false, false, false),
+            LabelPaletteKey::Apply(LabelSelection {
+                label_ids: vec!["label-4".into(), "label-3".into()],
+                new_labels: vec!["Travel".into()],
+                removed_label_ids: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn enter_applies_the_highlighted_label_when_nothing_is_selected() {
+        let mut palette = palette();
+        palette.handle_key("down", false, false, false);
+        assert_eq!(palette.highlighted(), 1);
+
+        assert_eq!(
+            palette.handle_key("enter", false, false, false),
+            LabelPaletteKey::Apply(LabelSelection {
+                label_ids: vec!["label-2".into()],
+                new_labels: vec![],
+                removed_label_ids: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn create_is_not_offered_for_an_existing_name() {
+        let mut palette = palette();
+        type_text(&mut palette, "work");
+        assert_eq!(names(&palette), ["Work"]);
+
+        palette.handle_key("down", false, false, false);
+        assert_eq!(palette.highlighted(), 0);
+        assert_eq!(
+            palette.handle_key("escape", false, false, false),
+            LabelPaletteKey::Cancel
+        );
+    }
+
+    #[test]
+    fn deselecting_an_applied_label_removes_it() {
+        let mut palette = palette().with_applied(&["label-2".into(), "label-3".into()]);
+        assert_eq!(palette.selected_count(), 2);
+
+        // Deselect Personal and select Waiting.
+        palette.handle_key("down", false, false, false);
+        palette.handle_key("space", false, false, false);
+        for _ in 0..2 {
+            palette.handle_key("down", false, false, false);
+        }
+        palette.handle_key("space", false, false, false);
+        assert_eq!(
+            palette.handle_key("enter", false, false, false),
+            LabelPaletteKey::Apply(LabelSelection {
+                label_ids: vec!["label-4".into()],
+                new_labels: vec![],
+                removed_label_ids: vec!["label-2".into()],
+            })
+        );
+    }
+}
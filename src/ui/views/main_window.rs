@@ -3,16 +3,27 @@
 //! Integrates sidebar, message list, and reading pane with full interactivity.
 
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
 
 use gpui::{
-    div, prelude::FluentBuilder, px, AnyElement, ClickEvent, Context, CursorStyle, FocusHandle,
-    Focusable, FontWeight, InteractiveElement, IntoElement, KeyDownEvent, MouseButton,
-    MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Render, SharedString,
-    StatefulInteractiveElement, Styled, Window,
+    div, img, prelude::FluentBuilder, px, AnyElement, ClickEvent, Context, CursorStyle,
+    FocusHandle, Focusable, FontWeight, Image, InteractiveElement, IntoElement, KeyDownEvent,
+    MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Render, ScrollHandle,
+    SharedString, StatefulInteractiveElement, Styled, Window,
 };
 
 use crate::ui::components::{KeyInputResult, TextBuffer};
 
+/// Height of a message list row without snippet lines, in pixels.
+const THREAD_ITEM_BASE_HEIGHT: f32 = 68.0;
+
+/// Height of each snippet line in a message list row, in pixels.
+const PREVIEW_LINE_HEIGHT: f32 = 20.0;
+
+/// How long a follow-up reminder waits for a reply.
+const FOLLOW_UP_AFTER: std::time::Duration = std::time::Duration::from_secs(3 * 24 * 60 * 60);
+
 /// Command palette commands (label, shortcut).
 const COMMANDS: &[(&str, &str)] = &[
     ("Go to Inbox", "g i"),
@@ -30,26 +41,44 @@ const COMMANDS: &[(&str, &str)] = &[
     ("Forward", "f"),
     ("Archive", "e"),
     ("Trash", "#"),
+    ("Report Spam", "!"),
     ("Star", "s"),
     ("Snooze", "h"),
+    ("Follow Up", "H"),
     ("Apply Labels", "l"),
     ("Mark Read", "u"),
     ("Mark Unread", "U"),
+    ("Expand All Messages", "n"),
+    ("Collapse All Messages", "N"),
+    ("View Source", "V"),
     ("Undo", "z"),
     ("Settings", "Cmd+,"),
     ("Search", "/"),
 ];
 
 use crate::app::{
-    ApplyLabel, Archive, Compose, Dismiss, Forward, GoToArchive, GoToDrafts, GoToInbox,
-    GoToScreener, GoToSent, GoToStarred, GoToStats, MarkRead, MarkUnread, NextMessage,
-    OpenCommandPalette, OpenSettings, PreviousMessage, Reply, ReplyAll, ScreenerApprove,
-    ScreenerReject, Search, Snooze, Star, Trash, Undo, ViewType,
+    ApplyLabel, Archive, ClientHandle, CollapseAllMessages, Compose, Dismiss, ExpandAllMessages,
+    FollowUp, Forward, GoToArchive, GoToDrafts, GoToInbox, GoToScreener, GoToSent, GoToStarred,
+    GoToStats, MarkRead, MarkReadSchedule, MarkReadTimer, MarkUnread, NextMessage,
+    OpenCommandPalette, OpenSettings, PreviousMessage, Reply, ReplyAll, ReportSpam,
+    ScreenerApprove, ScreenerReject, Search, SelectAll, SelectNext, SelectPrevious, Snooze, Star,
+    ToggleSelection, Trash, Undo, ViewSource, ViewType,
+};
+use crate::config::{AfterDone, Density, DoneAction, ReadingSettings, SendingSettings};
+use crate::domain::{
+    truncate_chars, Account, AccountId, Address, EmailId, LabelId, ScreenerAction, SenderType,
+    ThreadId,
+};
+use crate::services::{
+    BundleCategory, FolderCount, FolderCounts, SendHandle, SendOptions, SendStatus, SnoozeDuration,
+    ViewType as FolderView,
+};
+use crate::ui::theme::{parse_hex_color, Theme};
+use crate::ui::views::reading_pane::{self, render_attachments, ATTACHMENT_THUMBNAIL_SIZE};
+use crate::ui::views::{
+    AttachmentInfo, FromAddresses, LabelPalette, LabelPaletteEntry, LabelPaletteKey,
+    LabelSelection, ScreenerEntry, StatsTimeRange,
 };
-use crate::domain::{EmailId, LabelId, ScreenerAction, SenderType, ThreadId};
-use crate::services::SnoozeDuration;
-use crate::ui::theme::Theme;
-use crate::ui::views::{ScreenerEntry, StatsTimeRange};
 
 /// Active overlay state
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -63,6 +92,7 @@ pub enum ActiveOverlay {
     AccountSetup,
     SnoozePicker,
     LabelPicker,
+    MessageSource,
 }
 
 /// Account setup mode
@@ -135,6 +165,76 @@ pub enum DisplayDensity {
     Spacious,
 }
 
+impl From<Density> for DisplayDensity {
+    fn from(density: Density) -> Self {
+        match density {
+            Density::Compact => Self::Compact,
+            Density::Default => Self::Comfortable,
+            Density::Relaxed => Self::Spacious,
+        }
+    }
+}
+
+impl From<DisplayDensity> for Density {
+    fn from(density: DisplayDensity) -> Self {
+        match density {
+            DisplayDensity::Compact => Self::Compact,
+            DisplayDensity::Comfortable => Self::Default,
+            DisplayDensity::Spacious => Self::Relaxed,
+        }
+    }
+}
+
+/// How rows of the message list are laid out at a display density.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadRowLayout {
+    /// Lines of snippet shown under the subject.
+    pub preview_lines: u8,
+    /// Whether the sender's email address is shown after their name.
+    pub show_sender_email: bool,
+}
+
+impl ThreadRowLayout {
+    /// Returns the row layout for a density: compact rows show sender and
+    /// subject only, relaxed rows add a second snippet line and the
+    /// sender's address.
+    pub fn for_density(density: Density) -> Self {
+        match density {
+            Density::Compact => Self {
+                preview_lines: 0,
+                show_sender_email: false,
+            },
+            Density::Default => Self {
+                preview_lines: 1,
+                show_sender_email: false,
+            },
+            Density::Relaxed => Self {
+                preview_lines: 2,
+                show_sender_email: true,
+            },
+        }
+    }
+
+    /// Returns whether the snippet is shown at all.
+    pub fn show_snippet(&self) -> bool {
+        self.preview_lines > 0
+    }
+
+    /// Returns the height of a row, in pixels.
+    pub fn row_height(&self) -> f32 {
+        THREAD_ITEM_BASE_HEIGHT + f32::from(self.preview_lines) * PREVIEW_LINE_HEIGHT
+    }
+
+    /// Returns how the sender of a row is shown.
+    pub fn sender_label(&self, name: &str, email: &str) -> String {
+        if self.show_sender_email && !email.is_empty() && name != email {
+            format!("{} <{}>", name, email)
+        } else {
+            name.to_string()
+        }
+    }
+}
+
 /// AI provider selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AiProvider {
@@ -155,6 +255,9 @@ pub enum GeneralToggle {
     AutoArchiveAfterReply,
     MarkAsReadWhenOpened,
     ShowConversationView,
+    OpenNextAfterArchive,
+    InboxBundles,
+    CollapseDuplicates,
 }
 
 /// AI toggle setting identifiers
@@ -180,6 +283,11 @@ pub enum UndoableAction {
         thread_id: ThreadId,
         from_view: ViewType,
     },
+    /// Thread was reported as spam (contains thread id, previous view)
+    ReportSpam {
+        thread_id: ThreadId,
+        from_view: ViewType,
+    },
     /// Thread was starred (contains thread id)
     Star { thread_id: ThreadId },
     /// Thread was unstarred (contains thread id)
@@ -190,6 +298,12 @@ pub enum UndoableAction {
     MarkUnread { thread_id: ThreadId },
     /// Thread was snoozed (contains thread id)
     Snooze { thread_id: ThreadId },
+    /// Email was sent and is waiting out the undo window (contains subject
+    /// and the send to cancel)
+    Send { subject: String, handle: SendHandle },
+    /// Labels were added or removed (contains how many thread changes the
+    /// client recorded)
+    Labels { changes: usize },
 }
 
 impl UndoableAction {
@@ -197,11 +311,14 @@ impl UndoableAction {
         match self {
             UndoableAction::Archive { .. } => "Archived",
             UndoableAction::Trash { .. } => "Moved to Trash",
+            UndoableAction::ReportSpam { .. } => "Reported as spam",
             UndoableAction::Star { .. } => "Starred",
             UndoableAction::Unstar { .. } => "Unstarred",
             UndoableAction::MarkRead { .. } => "Marked as read",
             UndoableAction::MarkUnread { .. } => "Marked as unread",
             UndoableAction::Snooze { .. } => "Snoozed",
+            UndoableAction::Send { .. } => "Sending...",
+            UndoableAction::Labels { .. } => "Labels changed",
         }
     }
 
@@ -209,11 +326,14 @@ impl UndoableAction {
         match self {
             UndoableAction::Archive { .. } => "Unarchived",
             UndoableAction::Trash { .. } => "Restored from Trash",
+            UndoableAction::ReportSpam { .. } => "Moved back to Inbox",
             UndoableAction::Star { .. } => "Unstarred",
             UndoableAction::Unstar { .. } => "Re-starred",
             UndoableAction::MarkRead { .. } => "Marked as unread",
             UndoableAction::MarkUnread { .. } => "Marked as read",
             UndoableAction::Snooze { .. } => "Unsnooze",
+            UndoableAction::Send { .. } => "Send canceled",
+            UndoableAction::Labels { .. } => "Labels restored",
         }
     }
 }
@@ -223,6 +343,8 @@ impl UndoableAction {
 pub struct Toast {
     pub message: String,
     pub can_undo: bool,
+    /// Senders of mail just reported as spam, offered for blocking.
+    pub block: Vec<Address>,
     pub created_at: std::time::Instant,
 }
 
@@ -231,6 +353,7 @@ impl Toast {
         Self {
             message: message.into(),
             can_undo,
+            block: Vec::new(),
             created_at: std::time::Instant::now(),
         }
     }
@@ -263,6 +386,13 @@ pub struct MainWindow {
     composer_active_field: ComposerField,
     composer_show_cc: bool,
     composer_show_bcc: bool,
+    /// Addresses the composed message can be sent from.
+    composer_from: FromAddresses,
+    /// Account the composed message is sent from: the replied-to thread's,
+    /// or else the selected one. The first account when `None`.
+    composer_account: Option<AccountId>,
+    /// Undo window for sends.
+    sending: SendingSettings,
 
     // Settings state
     settings_active_tab: SettingsTab,
@@ -275,7 +405,12 @@ pub struct MainWindow {
     settings_show_in_menu_bar: bool,
     settings_auto_archive_after_reply: bool,
     settings_mark_as_read_when_opened: bool,
+    settings_mark_read_delay_ms: u64,
     settings_show_conversation_view: bool,
+    settings_inbox_bundles: bool,
+    settings_collapse_duplicates: bool,
+    settings_done_action: DoneAction,
+    settings_after_done: AfterDone,
 
     // AI settings
     settings_smart_compose: bool,
@@ -301,17 +436,29 @@ pub struct MainWindow {
 
     // Sidebar state
     sidebar_accounts: Vec<SidebarAccount>,
+    /// Account new mail is sent from, chosen in the sidebar.
+    selected_account: Option<AccountId>,
     sidebar_labels: Vec<SidebarLabel>,
     sidebar_collapsed_sections: HashSet<String>,
+    folder_counts: FolderCounts,
 
     // Message list state
     threads: Vec<ThreadListItem>,
     selected_thread_id: Option<ThreadId>,
+    selection: ThreadSelection,
     focused_index: usize,
+    /// Inbox bundles showing only their header.
+    collapsed_bundles: HashSet<BundleCategory>,
+    message_list_scroll: ScrollHandle,
+    /// Distance the message list is scrolled down, in pixels.
+    message_list_scroll_offset: f32,
 
     // Reading pane state
     current_thread: Option<ThreadDetail>,
     expanded_messages: HashSet<EmailId>,
+    mark_read_timer: MarkReadTimer,
+    /// Source shown by the view source overlay.
+    message_source: Option<MessageSource>,
 
     // Status bar state
     is_syncing: bool,
@@ -347,7 +494,7 @@ pub struct MainWindow {
     snooze_selected_index: usize,
 
     // Label picker state
-    label_picker_selected: HashSet<String>,
+    label_palette: LabelPalette,
     available_labels: Vec<(String, String)>, // (id, name)
 
     // Pane widths (resizable)
@@ -383,6 +530,8 @@ pub struct SidebarAccount {
     pub display_name: Option<String>,
     pub unread_count: u32,
     pub is_expanded: bool,
+    /// Whether the account must sign in again before it can sync.
+    pub needs_attention: bool,
 }
 
 /// Thread item for the message list
@@ -398,6 +547,118 @@ pub struct ThreadListItem {
     pub is_unread: bool,
     pub is_starred: bool,
     pub message_count: u32,
+    pub bundle: BundleCategory,
+    /// Thread this one repeats, such as the same newsletter from another
+    /// list.
+    pub duplicate_of: Option<ThreadId>,
+}
+
+/// A row of the message list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageListRow {
+    /// Header of an inbox bundle, with its thread count.
+    Bundle(BundleCategory, usize),
+    /// The thread at this index of the list.
+    Thread(usize),
+}
+
+/// Lays out the message list. Bundled, threads are grouped under a header
+/// per bundle and collapsed bundles show only the header. Collapsing
+/// duplicates leaves out threads that repeat another.
+fn message_list_rows(
+    threads: &[ThreadListItem],
+    bundled: bool,
+    collapse_duplicates: bool,
+    collapsed: &HashSet<BundleCategory>,
+) -> Vec<MessageListRow> {
+    let shown: Vec<usize> = (0..threads.len())
+        .filter(|&i| !collapse_duplicates || threads[i].duplicate_of.is_none())
+        .collect();
+    if !bundled {
+        return shown.into_iter().map(MessageListRow::Thread).collect();
+    }
+    let mut rows = Vec::new();
+    for &category in BundleCategory::all() {
+        let indices: Vec<usize> = shown
+            .iter()
+            .copied()
+            .filter(|&i| threads[i].bundle == category)
+            .collect();
+        if indices.is_empty() {
+            continue;
+        }
+        rows.push(MessageListRow::Bundle(category, indices.len()));
+        if !collapsed.contains(&category) {
+            rows.extend(indices.into_iter().map(MessageListRow::Thread));
+        }
+    }
+    rows
+}
+
+/// Threads picked in the message list for bulk actions.
+#[derive(Debug, Default)]
+struct ThreadSelection {
+    selected_ids: HashSet<ThreadId>,
+    /// Index that range selections extend from.
+    anchor: Option<usize>,
+}
+
+impl ThreadSelection {
+    fn is_empty(&self) -> bool {
+        self.selected_ids.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.selected_ids.len()
+    }
+
+    fn contains(&self, thread_id: &ThreadId) -> bool {
+        self.selected_ids.contains(thread_id)
+    }
+
+    /// Adds or removes the thread at `index`, which becomes the anchor.
+    fn toggle(&mut self, threads: &[ThreadListItem], index: usize) {
+        let Some(thread) = threads.get(index) else {
+            return;
+        };
+        if !self.selected_ids.remove(&thread.id) {
+            self.selected_ids.insert(thread.id.clone());
+        }
+        self.anchor = Some(index);
+    }
+
+    /// Adds the threads between the anchor and `to`, inclusive. Without an
+    /// anchor the range starts at `from`, which becomes the anchor.
+    fn select_range(&mut self, threads: &[ThreadListItem], from: usize, to: usize) {
+        let anchor = *self.anchor.get_or_insert(from);
+        let (start, end) = (anchor.min(to), anchor.max(to));
+        self.selected_ids.extend(
+            threads
+                .iter()
+                .skip(start)
+                .take(end + 1 - start)
+                .map(|t| t.id.clone()),
+        );
+    }
+
+    fn select_all(&mut self, threads: &[ThreadListItem]) {
+        self.selected_ids
+            .extend(threads.iter().map(|t| t.id.clone()));
+    }
+
+    fn clear(&mut self) {
+        self.selected_ids.clear();
+        self.anchor = None;
+    }
+
+    /// Returns the selected threads in list order.
+    fn ordered_ids(&self, threads: &[ThreadListItem]) -> Vec<ThreadId> {
+        threads
+            .iter()
+            .filter(|t| self.selected_ids.contains(&t.id))
+            .map(|t| t.id.clone())
+            .collect()
+    }
 }
 
 /// Detailed thread data for reading pane
@@ -405,11 +666,30 @@ pub struct ThreadListItem {
 #[allow(dead_code)]
 pub struct ThreadDetail {
     pub id: ThreadId,
+    /// Account the thread belongs to; `None` for the sample threads shown
+    /// without a client.
+    pub account_id: Option<AccountId>,
     pub subject: String,
     pub messages: Vec<MessageDetail>,
     pub labels: Vec<String>,
 }
 
+impl ThreadDetail {
+    /// Returns the expanded set with every message expanded.
+    fn all_expanded(&self) -> HashSet<EmailId> {
+        self.messages.iter().map(|m| m.id.clone()).collect()
+    }
+
+    /// Returns the expanded set with only the most recent message expanded.
+    fn latest_expanded(&self) -> HashSet<EmailId> {
+        self.messages
+            .last()
+            .map(|m| m.id.clone())
+            .into_iter()
+            .collect()
+    }
+}
+
 /// Individual message in a thread
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -421,11 +701,38 @@ pub struct MessageDetail {
     pub timestamp: String,
     pub body_text: String,
     pub is_unread: bool,
+    pub attachments: Vec<AttachmentInfo>,
+    /// Inline images referenced from the HTML body, in order.
+    pub inline_images: Vec<Arc<Image>>,
+}
+
+/// Original source of a message, fetched for the view source overlay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MessageSource {
+    /// The header block, up to the first blank line.
+    headers: String,
+    /// The whole message as received.
+    raw: String,
+}
+
+impl MessageSource {
+    fn new(raw: String) -> Self {
+        let end = [raw.find("\r\n\r\n"), raw.find("\n\n")]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(raw.len());
+        Self {
+            headers: raw[..end].to_string(),
+            raw,
+        }
+    }
 }
 
 impl MainWindow {
     pub fn new(_window: &mut Window, cx: &mut Context<Self>) -> Self {
         let focus_handle = cx.focus_handle();
+        let reading = ReadingSettings::default();
 
         let mut this = Self {
             theme: Theme::dark(),
@@ -443,6 +750,9 @@ impl MainWindow {
             composer_active_field: ComposerField::To,
             composer_show_cc: false,
             composer_show_bcc: false,
+            composer_from: FromAddresses::default(),
+            composer_account: None,
+            sending: SendingSettings::default(),
             settings_active_tab: SettingsTab::General,
 
             // General settings defaults
@@ -452,8 +762,13 @@ impl MainWindow {
             settings_launch_at_startup: false,
             settings_show_in_menu_bar: true,
             settings_auto_archive_after_reply: false,
-            settings_mark_as_read_when_opened: true,
+            settings_mark_as_read_when_opened: reading.mark_read_on_open,
+            settings_mark_read_delay_ms: reading.mark_read_delay_ms,
             settings_show_conversation_view: true,
+            settings_inbox_bundles: false,
+            settings_collapse_duplicates: false,
+            settings_done_action: reading.done_action,
+            settings_after_done: reading.after_done,
 
             // AI settings defaults
             settings_smart_compose: true,
@@ -476,13 +791,21 @@ impl MainWindow {
             smtp_server: TextBuffer::new(),
             smtp_port: TextBuffer::with_text("587"),
             sidebar_accounts: Vec::new(),
+            selected_account: None,
             sidebar_labels: Vec::new(),
             sidebar_collapsed_sections: HashSet::new(),
+            folder_counts: FolderCounts::default(),
             threads: Vec::new(),
             selected_thread_id: None,
+            selection: ThreadSelection::default(),
             focused_index: 0,
+            collapsed_bundles: HashSet::new(),
+            message_list_scroll: ScrollHandle::new(),
+            message_list_scroll_offset: 0.0,
             current_thread: None,
             expanded_messages: HashSet::new(),
+            message_source: None,
+            mark_read_timer: MarkReadTimer::new(reading.mark_read_delay()),
             is_syncing: false,
             sync_progress: 0,
             is_offline: false,
@@ -506,7 +829,7 @@ impl MainWindow {
             stats_ai_tokens: 156_000,
             stats_time_range: StatsTimeRange::Week,
             snooze_selected_index: 0,
-            label_picker_selected: HashSet::new(),
+            label_palette: LabelPalette::default(),
             available_labels: vec![
                 ("label-1".to_string(), "Work".to_string()),
                 ("label-2".to_string(), "Personal".to_string()),
@@ -539,6 +862,8 @@ impl MainWindow {
                 is_unread: true,
                 is_starred: true,
                 message_count: 1,
+                bundle: BundleCategory::Updates,
+                duplicate_of: None,
             },
             ThreadListItem {
                 id: ThreadId::from("thread-2"),
@@ -550,6 +875,8 @@ impl MainWindow {
                 is_unread: true,
                 is_starred: false,
                 message_count: 5,
+                bundle: BundleCategory::Primary,
+                duplicate_of: None,
             },
             ThreadListItem {
                 id: ThreadId::from("thread-3"),
@@ -561,6 +888,8 @@ impl MainWindow {
                 is_unread: false,
                 is_starred: false,
                 message_count: 3,
+                bundle: BundleCategory::Primary,
+                duplicate_of: None,
             },
             ThreadListItem {
                 id: ThreadId::from("thread-4"),
@@ -572,6 +901,8 @@ impl MainWindow {
                 is_unread: false,
                 is_starred: true,
                 message_count: 1,
+                bundle: BundleCategory::Primary,
+                duplicate_of: None,
             },
             ThreadListItem {
                 id: ThreadId::from("thread-5"),
@@ -583,6 +914,8 @@ impl MainWindow {
                 is_unread: false,
                 is_starred: false,
                 message_count: 8,
+                bundle: BundleCategory::Primary,
+                duplicate_of: None,
             },
         ];
 
@@ -593,6 +926,7 @@ impl MainWindow {
                 display_name: Some("Personal".to_string()),
                 unread_count: 3,
                 is_expanded: true,
+                needs_attention: false,
             },
             SidebarAccount {
                 id: "account-2".to_string(),
@@ -600,6 +934,7 @@ impl MainWindow {
                 display_name: Some("Work".to_string()),
                 unread_count: 12,
                 is_expanded: false,
+                needs_attention: false,
             },
         ];
 
@@ -621,6 +956,30 @@ impl MainWindow {
             },
         ];
 
+        self.folder_counts = FolderCounts::from_labels(vec![
+            (
+                LabelId::from("INBOX"),
+                FolderCount {
+                    unread: 2,
+                    total: 5,
+                },
+            ),
+            (
+                LabelId::from("DRAFT"),
+                FolderCount {
+                    unread: 0,
+                    total: 1,
+                },
+            ),
+            (
+                LabelId::from("work"),
+                FolderCount {
+                    unread: 1,
+                    total: 2,
+                },
+            ),
+        ]);
+
         self.screener_entries = vec![
             ScreenerEntry::new("screener-1", "newsletter@techweekly.io")
                 .with_name("Tech Weekly")
@@ -673,7 +1032,12 @@ impl MainWindow {
         self.current_view = view;
         self.selected_thread_id = None;
         self.current_thread = None;
+        self.mark_read_timer.cancel();
+        self.selection.clear();
         self.focused_index = 0;
+        self.message_list_scroll_offset = 0.0;
+        self.message_list_scroll
+            .set_offset(gpui::point(px(0.0), px(0.0)));
         cx.notify();
     }
 
@@ -685,28 +1049,173 @@ impl MainWindow {
             self.focused_index = idx;
         }
 
-        // Load thread detail
-        self.current_thread = Some(self.get_thread_detail(&thread_id));
-        self.expanded_messages.clear();
+        self.load_thread_detail(thread_id, cx);
+        cx.notify();
+    }
 
-        // Expand last message
-        if let Some(ref thread) = self.current_thread {
-            if let Some(last) = thread.messages.last() {
-                self.expanded_messages.insert(last.id.clone());
+    /// Shows a thread in the reading pane with its latest message expanded,
+    /// and starts the wait before marking it read.
+    fn open_thread_detail(&mut self, thread: ThreadDetail, cx: &mut Context<Self>) {
+        let thread_id = thread.id.clone();
+        self.expanded_messages = thread.latest_expanded();
+        self.current_thread = Some(thread);
+        self.schedule_mark_read(thread_id, cx);
+    }
+
+    /// Expands every message of the open thread.
+    fn expand_all_messages(&mut self, cx: &mut Context<Self>) {
+        if let Some(thread) = &self.current_thread {
+            self.expanded_messages = thread.all_expanded();
+            cx.notify();
+        }
+    }
+
+    /// Collapses the open thread down to its most recent message.
+    fn collapse_all_messages(&mut self, cx: &mut Context<Self>) {
+        if let Some(thread) = &self.current_thread {
+            self.expanded_messages = thread.latest_expanded();
+            cx.notify();
+        }
+    }
+
+    /// Shows the source of the open thread's most recent expanded message.
+    fn view_source(&mut self, cx: &mut Context<Self>) {
+        let Some(thread) = &self.current_thread else {
+            return;
+        };
+        let Some(message) = thread
+            .messages
+            .iter()
+            .rev()
+            .find(|m| self.expanded_messages.contains(&m.id))
+        else {
+            return;
+        };
+        let (Some(handle), Some(account_id)) =
+            (cx.try_global::<ClientHandle>(), thread.account_id.clone())
+        else {
+            self.show_toast("Source unavailable: no account is connected", false);
+            cx.notify();
+            return;
+        };
+
+        // The source isn't stored, so it's fetched from the server each time.
+        let client = handle.client.clone();
+        let email_id = message.id.clone();
+        let fetched = handle.spawn(async move {
+            client
+                .email_service()
+                .fetch_raw(&account_id, &email_id)
+                .await
+        });
+        let email_id = message.id.clone();
+        cx.spawn(move |this, mut cx| async move {
+            let fetched = fetched.await;
+            this.update(&mut cx, |this, cx| match fetched {
+                Ok(raw) => {
+                    let raw = String::from_utf8_lossy(&raw).into_owned();
+                    this.message_source = Some(MessageSource::new(raw));
+                    this.show_overlay(ActiveOverlay::MessageSource, cx);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to fetch the source of {}: {}", email_id, e);
+                    this.show_toast(format!("Couldn't load the source: {}", e), false);
+                    cx.notify();
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Start the dwell timer for a newly opened thread. Opening another
+    /// thread before it fires leaves this one unread.
+    fn schedule_mark_read(&mut self, thread_id: ThreadId, cx: &mut Context<Self>) {
+        match self.mark_read_timer.open(thread_id, Instant::now()) {
+            MarkReadSchedule::Never => {}
+            MarkReadSchedule::Now(thread_id) => self.mark_opened_thread_read(thread_id, cx),
+            MarkReadSchedule::After(delay) => {
+                cx.spawn(move |this, mut cx| async move {
+                    cx.background_executor().timer(delay).await;
+                    this.update(&mut cx, |this, cx| {
+                        if let Some(thread_id) = this.mark_read_timer.poll(Instant::now()) {
+                            this.mark_opened_thread_read(thread_id, cx);
+                            cx.notify();
+                        }
+                    })
+                    .ok();
+                })
+                .detach();
             }
         }
+    }
 
-        cx.notify();
+    /// Marks the opened thread read with the client, then shows it read
+    /// once that succeeded. Without a client only the display changes.
+    fn mark_opened_thread_read(&mut self, thread_id: ThreadId, cx: &mut Context<Self>) {
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            self.show_thread_read(&thread_id);
+            return;
+        };
+
+        let client = handle.client.clone();
+        let target = thread_id.clone();
+        let marked =
+            handle.spawn(async move { client.email_service().mark_read(&target, true).await });
+        cx.spawn(move |this, mut cx| async move {
+            let marked = marked.await;
+            this.update(&mut cx, |this, cx| {
+                match marked {
+                    Ok(()) => this.show_thread_read(&thread_id),
+                    Err(e) => tracing::warn!("Failed to mark thread {} read: {}", thread_id, e),
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Shows a thread and its messages as read in the list and the reading
+    /// pane.
+    fn show_thread_read(&mut self, thread_id: &ThreadId) {
+        if let Some(thread) = self.threads.iter_mut().find(|t| t.id == *thread_id) {
+            thread.is_unread = false;
+        }
+        if let Some(detail) = self.current_thread.as_mut() {
+            if detail.id == *thread_id {
+                for message in &mut detail.messages {
+                    message.is_unread = false;
+                }
+            }
+        }
+    }
+
+    fn mark_read_delay(&self) -> Option<std::time::Duration> {
+        ReadingSettings {
+            mark_read_on_open: self.settings_mark_as_read_when_opened,
+            mark_read_delay_ms: self.settings_mark_read_delay_ms,
+            ..Default::default()
+        }
+        .mark_read_delay()
     }
 
     fn focus_next(&mut self, cx: &mut Context<Self>) {
         if self.current_view == ViewType::Screener {
             self.screener_select_next();
             cx.notify();
-        } else if self.focused_index + 1 < self.threads.len() {
-            self.focused_index += 1;
-            let thread_id = self.threads[self.focused_index].id.clone();
-            self.select_thread(thread_id, cx);
+        } else {
+            let order = self.visible_thread_order();
+            let next = match order.iter().position(|&i| i == self.focused_index) {
+                Some(position) => order.get(position + 1),
+                None => order.first(),
+            };
+            if let Some(&index) = next {
+                self.focused_index = index;
+                self.scroll_focused_into_view();
+                let thread_id = self.threads[index].id.clone();
+                self.select_thread(thread_id, cx);
+            }
         }
     }
 
@@ -714,19 +1223,153 @@ impl MainWindow {
         if self.current_view == ViewType::Screener {
             self.screener_select_previous();
             cx.notify();
-        } else if self.focused_index > 0 {
-            self.focused_index -= 1;
-            let thread_id = self.threads[self.focused_index].id.clone();
-            self.select_thread(thread_id, cx);
+        } else {
+            let order = self.visible_thread_order();
+            let previous = order
+                .iter()
+                .position(|&i| i == self.focused_index)
+                .and_then(|position| position.checked_sub(1))
+                .map(|position| order[position]);
+            if let Some(index) = previous {
+                self.focused_index = index;
+                self.scroll_focused_into_view();
+                let thread_id = self.threads[index].id.clone();
+                self.select_thread(thread_id, cx);
+            }
         }
     }
 
+    /// Whether the message list groups threads into bundles.
+    fn bundles_shown(&self) -> bool {
+        self.settings_inbox_bundles && self.current_view == ViewType::Inbox
+    }
+
+    /// Whether the message list shows near-duplicate threads as one.
+    fn duplicates_collapsed(&self) -> bool {
+        self.settings_collapse_duplicates && self.current_view == ViewType::Inbox
+    }
+
+    fn message_list_rows(&self) -> Vec<MessageListRow> {
+        message_list_rows(
+            &self.threads,
+            self.bundles_shown(),
+            self.duplicates_collapsed(),
+            &self.collapsed_bundles,
+        )
+    }
+
+    /// Indices of the threads in the message list, top to bottom, leaving
+    /// out those in collapsed bundles.
+    fn visible_thread_order(&self) -> Vec<usize> {
+        self.message_list_rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                MessageListRow::Thread(index) => Some(index),
+                MessageListRow::Bundle(..) => None,
+            })
+            .collect()
+    }
+
+    /// Toggles whether the focused thread is part of the multi-selection.
+    fn toggle_focused_selection(&mut self, cx: &mut Context<Self>) {
+        self.selection.toggle(&self.threads, self.focused_index);
+        cx.notify();
+    }
+
+    /// Moves focus by one thread, adding the threads passed over to the
+    /// multi-selection.
+    fn extend_selection(&mut self, forward: bool, cx: &mut Context<Self>) {
+        let from = self.focused_index;
+        let to = if forward {
+            from + 1
+        } else {
+            match from.checked_sub(1) {
+                Some(to) => to,
+                None => return,
+            }
+        };
+        if to >= self.threads.len() {
+            return;
+        }
+        self.selection.select_range(&self.threads, from, to);
+        self.focused_index = to;
+        self.scroll_focused_into_view();
+        cx.notify();
+    }
+
+    fn select_all_threads(&mut self, cx: &mut Context<Self>) {
+        self.selection.select_all(&self.threads);
+        cx.notify();
+    }
+
+    /// Scrolls the message list just enough to show the focused thread.
+    fn scroll_focused_into_view(&mut self) {
+        let viewport_height = f32::from(self.message_list_scroll.bounds().size.height);
+        if viewport_height <= 0.0 {
+            // Not laid out yet.
+            return;
+        }
+        // Pick up scrolling done with the mouse since the last adjustment.
+        self.message_list_scroll_offset = -f32::from(self.message_list_scroll.offset().y);
+        // Bundle headers take a row each.
+        let row = self
+            .message_list_rows()
+            .iter()
+            .position(|row| *row == MessageListRow::Thread(self.focused_index))
+            .unwrap_or(self.focused_index);
+        self.message_list_scroll_offset = scroll_offset_to_reveal(
+            row,
+            self.message_list_scroll_offset,
+            viewport_height,
+            self.thread_row_layout().row_height(),
+        );
+        self.message_list_scroll
+            .set_offset(gpui::point(px(0.0), px(-self.message_list_scroll_offset)));
+    }
+
     // Overlay management
     fn show_overlay(&mut self, overlay: ActiveOverlay, cx: &mut Context<Self>) {
+        if overlay == ActiveOverlay::Composer {
+            self.load_composer_from(cx);
+        }
         self.active_overlay = overlay;
         cx.notify();
     }
 
+    /// Opens the composer for new mail, sent from the selected account.
+    fn compose_new(&mut self, cx: &mut Context<Self>) {
+        self.composer_account = self.selected_account.clone();
+        self.show_overlay(ActiveOverlay::Composer, cx);
+    }
+
+    /// Opens the composer for a reply or forward, sent from the open
+    /// thread's account.
+    fn compose_in_thread(&mut self, cx: &mut Context<Self>) {
+        self.composer_account = self
+            .current_thread
+            .as_ref()
+            .and_then(|thread| thread.account_id.clone())
+            .or_else(|| self.selected_account.clone());
+        self.show_overlay(ActiveOverlay::Composer, cx);
+    }
+
+    /// Offers the sending account's own address and its aliases in the
+    /// composer.
+    fn load_composer_from(&mut self, cx: &mut Context<Self>) {
+        let Some(client) = cx.try_global::<ClientHandle>() else {
+            return;
+        };
+        match client.runtime.block_on(client.client.accounts()) {
+            Ok(accounts) => {
+                let addresses = sending_account(accounts, self.composer_account.as_ref())
+                    .map(|account| account.from_addresses())
+                    .unwrap_or_default();
+                self.composer_from.set(addresses);
+            }
+            Err(e) => tracing::warn!("Failed to load the accounts to send from: {}", e),
+        }
+    }
+
     fn dismiss_overlay(&mut self, cx: &mut Context<Self>) {
         self.active_overlay = ActiveOverlay::None;
         self.command_palette_buffer.clear();
@@ -740,6 +1383,8 @@ impl MainWindow {
         self.composer_active_field = ComposerField::To;
         self.composer_show_cc = false;
         self.composer_show_bcc = false;
+        self.composer_from = FromAddresses::default();
+        self.composer_account = None;
         self.settings_active_tab = SettingsTab::General;
         self.account_setup_mode = AccountSetupMode::Selection;
         self.imap_active_field = ImapField::ImapServer;
@@ -749,6 +1394,7 @@ impl MainWindow {
         self.imap_password.clear();
         self.smtp_server.clear();
         self.smtp_port.set_text("587");
+        self.message_source = None;
         cx.notify();
     }
 
@@ -791,6 +1437,24 @@ impl MainWindow {
             return self.handle_account_setup_key(key, shift, cx);
         }
 
+        if self.active_overlay == ActiveOverlay::LabelPicker {
+            return match self.label_palette.handle_key(key, shift, ctrl, cmd) {
+                LabelPaletteKey::Changed => {
+                    cx.notify();
+                    true
+                }
+                LabelPaletteKey::Apply(selection) => {
+                    self.apply_labels(selection, cx);
+                    true
+                }
+                LabelPaletteKey::Cancel => {
+                    self.dismiss_overlay(cx);
+                    true
+                }
+                LabelPaletteKey::Ignored => false,
+            };
+        }
+
         // Get the active buffer
         let buffer = match self.active_overlay {
             ActiveOverlay::CommandPalette => &mut self.command_palette_buffer,
@@ -952,7 +1616,7 @@ impl MainWindow {
         let cc = self.composer_cc.text().to_string();
         let bcc = self.composer_bcc.text().to_string();
         let subject = self.composer_subject.text().to_string();
-        let _body = self.composer_body.text().to_string();
+        let body = self.composer_body.text().to_string();
 
         if to.is_empty() {
             tracing::warn!("Cannot send: no recipients");
@@ -967,8 +1631,62 @@ impl MainWindow {
             subject
         );
 
-        // TODO: Actually send via email service
+        let Some(client) = cx.try_global::<ClientHandle>() else {
+            tracing::warn!("Cannot send: no client");
+            self.show_toast("Not sent: no account is connected", false);
+            return;
+        };
+        let draft_subject = subject.clone();
+        let from = self.composer_from.alias();
+        let account_id = self.composer_account.clone();
+        let options = SendOptions {
+            delay: self.sending.undo_send_delay(),
+            schedule_at: None,
+        };
+        let sent = client.runtime.block_on(async {
+            let accounts = client.client.accounts().await?;
+            let account = sending_account(accounts, account_id.as_ref())
+                .ok_or_else(|| anyhow::anyhow!("No account to send from"))?;
+            let mut draft = client.client.email_service().build_new(&account, false);
+            draft.from = from;
+            draft.to = parse_recipients(&to);
+            draft.cc = parse_recipients(&cc);
+            draft.bcc = parse_recipients(&bcc);
+            draft.subject = draft_subject;
+            draft.body_markdown = body;
+            client.client.send_with_options(draft, options).await
+        });
+        let handle = match sent {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::error!("Failed to send: {}", e);
+                self.show_toast(format!("Not sent: {}", e), false);
+                return;
+            }
+        };
+
         self.dismiss_overlay(cx);
+        self.push_undo_action(UndoableAction::Send {
+            subject,
+            handle: handle.clone(),
+        });
+
+        // Replace "Sending..." with the outcome once the undo window ends.
+        cx.spawn(move |this, mut cx| async move {
+            let status = handle.finished().await;
+            this.update(&mut cx, |this, cx| {
+                match status {
+                    SendStatus::Sent(_) => this.show_toast("Sent", false),
+                    SendStatus::Failed(e) => {
+                        this.show_toast(format!("Failed to send: {}", e), false)
+                    }
+                    _ => return,
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
     }
 
     /// Handle keyboard input for account setup overlay.
@@ -1134,12 +1852,12 @@ impl MainWindow {
             }
             "Compose" => {
                 self.dismiss_overlay(cx);
-                self.show_overlay(ActiveOverlay::Composer, cx);
+                self.compose_new(cx);
             }
             "Reply" | "Reply All" | "Forward" => {
                 if self.selected_thread_id.is_some() {
                     self.dismiss_overlay(cx);
-                    self.show_overlay(ActiveOverlay::Composer, cx);
+                    self.compose_in_thread(cx);
                 }
             }
             "Archive" => {
@@ -1150,6 +1868,10 @@ impl MainWindow {
                 self.dismiss_overlay(cx);
                 self.trash_selected(cx);
             }
+            "Report Spam" => {
+                self.dismiss_overlay(cx);
+                self.report_spam_selected(cx);
+            }
             "Star" => {
                 self.dismiss_overlay(cx);
                 self.star_selected(cx);
@@ -1158,6 +1880,22 @@ impl MainWindow {
                 self.dismiss_overlay(cx);
                 self.snooze_selected(cx);
             }
+            "Follow Up" => {
+                self.dismiss_overlay(cx);
+                self.follow_up_selected(cx);
+            }
+            "Expand All Messages" => {
+                self.dismiss_overlay(cx);
+                self.expand_all_messages(cx);
+            }
+            "Collapse All Messages" => {
+                self.dismiss_overlay(cx);
+                self.collapse_all_messages(cx);
+            }
+            "View Source" => {
+                self.dismiss_overlay(cx);
+                self.view_source(cx);
+            }
             "Mark Read" => {
                 self.dismiss_overlay(cx);
                 self.mark_read_selected(cx);
@@ -1214,14 +1952,21 @@ impl MainWindow {
                     from_view: _,
                 } => {
                     tracing::info!("Undo archive: {:?}", thread_id);
-                    // TODO: Actually unarchive via service
+                    self.undo_thread_change(cx);
                 }
                 UndoableAction::Trash {
                     thread_id,
                     from_view: _,
                 } => {
                     tracing::info!("Undo trash: {:?}", thread_id);
-                    // TODO: Actually restore from trash via service
+                    self.undo_thread_change(cx);
+                }
+                UndoableAction::ReportSpam {
+                    thread_id,
+                    from_view: _,
+                } => {
+                    tracing::info!("Undo report spam: {:?}", thread_id);
+                    self.move_out_of_spam(thread_id, cx);
                 }
                 UndoableAction::Star { thread_id } => {
                     tracing::info!("Undo star: {:?}", thread_id);
@@ -1251,52 +1996,268 @@ impl MainWindow {
                     tracing::info!("Undo snooze: {:?}", thread_id);
                     // TODO: Actually unsnooze via service
                 }
+                UndoableAction::Labels { changes } => {
+                    tracing::info!("Undo {} label changes", changes);
+                    for _ in 0..changes {
+                        self.undo_thread_change(cx);
+                    }
+                }
+                UndoableAction::Send { subject, handle } => {
+                    tracing::info!("Undo send: {}", subject);
+                    if !handle.cancel() {
+                        self.show_toast("Already sent", false);
+                        cx.notify();
+                        return;
+                    }
+                }
             }
             self.show_toast(description, false);
             cx.notify();
         }
     }
 
-    // Email actions on selected thread
+    /// Reverses the client's latest thread change, locally and at the
+    /// provider.
+    fn undo_thread_change(&self, cx: &Context<Self>) {
+        let Some(client) = cx.try_global::<ClientHandle>() else {
+            return;
+        };
+        if let Err(e) = client.runtime.block_on(client.client.undo()) {
+            tracing::warn!("Failed to undo thread change: {}", e);
+        }
+    }
+
+    /// Threads the bulk actions apply to: the multi-selection in list order,
+    /// or else the open thread.
+    fn action_targets(&self) -> Vec<ThreadId> {
+        if self.selection.is_empty() {
+            self.selected_thread_id.iter().cloned().collect()
+        } else {
+            self.selection.ordered_ids(&self.threads)
+        }
+    }
+
+    /// Handles the archive key, which archives, trashes or skips the open
+    /// thread depending on the configured done action.
+    fn done_selected(&mut self, cx: &mut Context<Self>) {
+        match self.settings_done_action {
+            DoneAction::Archive => self.archive_selected(cx),
+            DoneAction::Trash => self.trash_selected(cx),
+            DoneAction::Skip => self.focus_next(cx),
+        }
+    }
+
+    /// Takes threads that were archived, trashed or reported out of the
+    /// list and moves focus on as configured.
+    fn remove_done_threads(&mut self, thread_ids: &[ThreadId], cx: &mut Context<Self>) {
+        if !self.threads.iter().any(|t| thread_ids.contains(&t.id)) {
+            return;
+        }
+        let was_open = self
+            .selected_thread_id
+            .as_ref()
+            .is_some_and(|id| thread_ids.contains(id));
+        // Position of the focused thread, or the one taking its place,
+        // once the done threads are gone.
+        let focused = self.focused_index.min(self.threads.len() - 1);
+        let index = self.threads[..focused]
+            .iter()
+            .filter(|t| !thread_ids.contains(&t.id))
+            .count();
+        self.threads.retain(|t| !thread_ids.contains(&t.id));
+
+        match focus_after_done(index, self.threads.len(), self.settings_after_done) {
+            Some((index, open)) => {
+                self.focused_index = index;
+                self.scroll_focused_into_view();
+                if was_open && open {
+                    let thread_id = self.threads[index].id.clone();
+                    self.select_thread(thread_id, cx);
+                    return;
+                }
+            }
+            None => self.focused_index = 0,
+        }
+        if was_open {
+            self.selected_thread_id = None;
+            self.current_thread = None;
+            self.mark_read_timer.cancel();
+        }
+    }
+
+    // Email actions on selected threads
     fn archive_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
+        let mut archived = Vec::new();
+        for thread_id in self.action_targets() {
             tracing::info!("Archive thread: {:?}", thread_id);
+            if let Some(client) = cx.try_global::<ClientHandle>() {
+                let archiving = client.client.threads().archive(&thread_id);
+                if let Err(e) = client.runtime.block_on(archiving) {
+                    tracing::warn!("Failed to archive thread {}: {}", thread_id, e);
+                    self.show_toast(format!("Couldn't archive: {}", e), false);
+                    continue;
+                }
+            }
             self.push_undo_action(UndoableAction::Archive {
                 thread_id: thread_id.clone(),
                 from_view: self.current_view.clone(),
             });
-            // TODO: Actually archive via service
+            archived.push(thread_id);
         }
+        self.selection.clear();
+        self.remove_done_threads(&archived, cx);
         cx.notify();
     }
 
     fn trash_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
+        let mut trashed = Vec::new();
+        for thread_id in self.action_targets() {
             tracing::info!("Trash thread: {:?}", thread_id);
+            if let Some(client) = cx.try_global::<ClientHandle>() {
+                let trashing = client.client.threads().trash(&thread_id);
+                if let Err(e) = client.runtime.block_on(trashing) {
+                    tracing::warn!("Failed to trash thread {}: {}", thread_id, e);
+                    self.show_toast(format!("Couldn't move to Trash: {}", e), false);
+                    continue;
+                }
+            }
             self.push_undo_action(UndoableAction::Trash {
                 thread_id: thread_id.clone(),
                 from_view: self.current_view.clone(),
             });
-            // TODO: Actually trash via service
+            trashed.push(thread_id);
         }
+        self.selection.clear();
+        self.remove_done_threads(&trashed, cx);
         cx.notify();
     }
 
+    /// Reports the targets as spam, then offers to block their senders in
+    /// the toast.
+    fn report_spam_selected(&mut self, cx: &mut Context<Self>) {
+        let targets = self.action_targets();
+        if targets.is_empty() {
+            return;
+        }
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            self.show_toast("Can't report spam: no account is connected", false);
+            cx.notify();
+            return;
+        };
+
+        tracing::info!("Report spam: {:?}", targets);
+        let client = handle.client.clone();
+        let thread_ids = targets.clone();
+        let reported =
+            handle.spawn(async move { client.email_service().report_spam(&thread_ids).await });
+        let from_view = self.current_view.clone();
+        cx.spawn(move |this, mut cx| async move {
+            let reported = reported.await;
+            this.update(&mut cx, |this, cx| {
+                match reported {
+                    Ok(senders) => {
+                        for thread_id in targets.iter().cloned() {
+                            this.push_undo_action(UndoableAction::ReportSpam {
+                                thread_id,
+                                from_view: from_view.clone(),
+                            });
+                        }
+                        if let Some(toast) = this.toast.as_mut() {
+                            toast.block = senders;
+                        }
+                        this.selection.clear();
+                        this.remove_done_threads(&targets, cx);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to report spam: {}", e);
+                        this.show_toast(format!("Couldn't report spam: {}", e), false);
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Moves a thread reported as spam back to the inbox.
+    fn move_out_of_spam(&mut self, thread_id: ThreadId, cx: &mut Context<Self>) {
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            return;
+        };
+        let client = handle.client.clone();
+        let target = thread_id.clone();
+        let moved = handle.spawn(async move { client.email_service().not_spam(&[target]).await });
+        cx.spawn(move |this, mut cx| async move {
+            if let Err(e) = moved.await {
+                tracing::warn!("Failed to move {} out of spam: {}", thread_id, e);
+                this.update(&mut cx, |this, cx| {
+                    this.show_toast(format!("Couldn't move it back to Inbox: {}", e), false);
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Blocks the senders the toast offers after reporting spam.
+    fn block_offered_senders(&mut self, cx: &mut Context<Self>) {
+        let senders = match self.toast.as_mut() {
+            Some(toast) => std::mem::take(&mut toast.block),
+            None => return,
+        };
+        if senders.is_empty() {
+            return;
+        }
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            return;
+        };
+
+        let client = handle.client.clone();
+        let blocking = senders.clone();
+        let blocked = handle.spawn(async move { client.block_senders(&blocking).await });
+        cx.spawn(move |this, mut cx| async move {
+            let blocked = blocked.await;
+            this.update(&mut cx, |this, cx| {
+                match blocked {
+                    Ok(()) if senders.len() == 1 => {
+                        this.show_toast(format!("Blocked {}", senders[0].email), false);
+                    }
+                    Ok(()) => {
+                        this.show_toast(format!("Blocked {} senders", senders.len()), false);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to block senders: {}", e);
+                        this.show_toast(format!("Couldn't block: {}", e), false);
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
     fn star_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
+        let targets = self.action_targets();
+        // Star them all unless they're all starred already.
+        let star = self
+            .threads
+            .iter()
+            .any(|t| targets.contains(&t.id) && !t.is_starred);
+        for thread_id in targets {
             tracing::info!("Star thread: {:?}", thread_id);
             // Toggle star in local state for now
-            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == *thread_id) {
-                let was_starred = thread.is_starred;
-                thread.is_starred = !thread.is_starred;
-                if was_starred {
-                    self.push_undo_action(UndoableAction::Unstar {
-                        thread_id: thread_id.clone(),
-                    });
+            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == thread_id) {
+                if thread.is_starred == star {
+                    continue;
+                }
+                thread.is_starred = star;
+                if star {
+                    self.push_undo_action(UndoableAction::Star { thread_id });
                 } else {
-                    self.push_undo_action(UndoableAction::Star {
-                        thread_id: thread_id.clone(),
-                    });
+                    self.push_undo_action(UndoableAction::Unstar { thread_id });
                 }
             }
         }
@@ -1304,24 +2265,64 @@ impl MainWindow {
     }
 
     fn snooze_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
+        for thread_id in self.action_targets() {
             tracing::info!("Snooze thread: {:?}", thread_id);
-            self.push_undo_action(UndoableAction::Snooze {
-                thread_id: thread_id.clone(),
-            });
+            self.push_undo_action(UndoableAction::Snooze { thread_id });
             // TODO: Show snooze picker
         }
+        self.selection.clear();
         cx.notify();
     }
 
+    /// Reminds the user to follow up on the open thread if nobody replies
+    /// within [`FOLLOW_UP_AFTER`]. A reply cancels the reminder.
+    fn follow_up_selected(&mut self, cx: &mut Context<Self>) {
+        let Some(thread_id) = self.selected_thread_id.clone() else {
+            return;
+        };
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            self.show_toast("Can't set a reminder: no account is connected", false);
+            cx.notify();
+            return;
+        };
+
+        let client = handle.client.clone();
+        let target = thread_id.clone();
+        let set = handle.spawn(async move {
+            client
+                .email_service()
+                .set_followup(&target, FOLLOW_UP_AFTER)
+                .await
+        });
+        cx.spawn(move |this, mut cx| async move {
+            let set = set.await;
+            this.update(&mut cx, |this, cx| {
+                match set {
+                    Ok(due_at) => {
+                        let due_at = due_at.with_timezone(&chrono::Local).format("%a %b %-d");
+                        this.show_toast(
+                            format!("Reminder set for {} if nobody replies", due_at),
+                            false,
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to set a follow-up on {}: {}", thread_id, e);
+                        this.show_toast(format!("Couldn't set the reminder: {}", e), false);
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
     fn mark_read_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
-            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == *thread_id) {
+        for thread_id in self.action_targets() {
+            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == thread_id) {
                 if thread.is_unread {
                     thread.is_unread = false;
-                    self.push_undo_action(UndoableAction::MarkRead {
-                        thread_id: thread_id.clone(),
-                    });
+                    self.push_undo_action(UndoableAction::MarkRead { thread_id });
                 }
             }
         }
@@ -1329,23 +2330,97 @@ impl MainWindow {
     }
 
     fn mark_unread_selected(&mut self, cx: &mut Context<Self>) {
-        if let Some(ref thread_id) = self.selected_thread_id {
-            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == *thread_id) {
+        for thread_id in self.action_targets() {
+            if let Some(thread) = self.threads.iter_mut().find(|t| t.id == thread_id) {
                 if !thread.is_unread {
                     thread.is_unread = true;
-                    self.push_undo_action(UndoableAction::MarkUnread {
-                        thread_id: thread_id.clone(),
-                    });
+                    self.push_undo_action(UndoableAction::MarkUnread { thread_id });
                 }
             }
         }
         cx.notify();
     }
 
+    /// Loads a thread from the client in the background, with thumbnails of
+    /// its attachments, and opens it if it's still selected. Opens the
+    /// sample thread instead without a client or when the thread can't be
+    /// found.
+    fn load_thread_detail(&mut self, thread_id: ThreadId, cx: &mut Context<Self>) {
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            let thread = self.get_thread_detail(&thread_id);
+            self.open_thread_detail(thread, cx);
+            return;
+        };
+        // Until it loads, the reading pane shows nothing rather than the
+        // previous thread, which is no longer marked read.
+        self.current_thread = None;
+        self.mark_read_timer.cancel();
+
+        let client = handle.client.clone();
+        let target = thread_id.clone();
+        let loaded = handle.spawn(async move {
+            let thread = client.get_thread(&target).await?;
+            let now = chrono::Local::now();
+            let mut messages = Vec::with_capacity(thread.messages.len());
+            for email in &thread.messages {
+                let thumbnails = client.thumbnails(email, ATTACHMENT_THUMBNAIL_SIZE).await;
+                let inline_parts = match client.storage().load_inline_parts(email).await {
+                    Ok(parts) => parts,
+                    Err(e) => {
+                        tracing::warn!("Failed to load inline images of {}: {}", email.id, e);
+                        Default::default()
+                    }
+                };
+                // Only the inline images are shown, so remote ones never load.
+                let message = reading_pane::MessageDetail::from_email(email, now)
+                    .with_thumbnails(&thumbnails)
+                    .with_inline_parts(email, &inline_parts, false);
+                messages.push(MessageDetail {
+                    id: message.id,
+                    sender_name: message.sender_name,
+                    sender_email: message.sender_email,
+                    recipients: message.recipients,
+                    timestamp: message.timestamp,
+                    body_text: message.body_text,
+                    is_unread: message.is_unread,
+                    attachments: message.attachments,
+                    inline_images: message.inline_images,
+                });
+            }
+            anyhow::Ok(ThreadDetail {
+                id: thread.id,
+                account_id: Some(thread.account_id),
+                subject: thread.subject.unwrap_or_default(),
+                messages,
+                labels: thread.labels.into_iter().map(|label| label.0).collect(),
+            })
+        });
+        cx.spawn(move |this, mut cx| async move {
+            let loaded = loaded.await;
+            this.update(&mut cx, |this, cx| {
+                if this.selected_thread_id.as_ref() != Some(&thread_id) {
+                    return;
+                }
+                let thread = match loaded {
+                    Ok(thread) => thread,
+                    Err(e) => {
+                        tracing::debug!("Failed to load thread {}: {}", thread_id, e);
+                        this.get_thread_detail(&thread_id)
+                    }
+                };
+                this.open_thread_detail(thread, cx);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
     fn get_thread_detail(&self, thread_id: &ThreadId) -> ThreadDetail {
         match thread_id.0.as_str() {
             "thread-1" => ThreadDetail {
                 id: thread_id.clone(),
+                account_id: None,
                 subject: "Welcome to The Heap".to_string(),
                 messages: vec![MessageDetail {
                     id: EmailId::from("msg-1-1"),
@@ -1355,11 +2430,14 @@ impl MainWindow {
                     timestamp: "Today at 9:00 AM".to_string(),
                     body_text: "Welcome to The Heap!\n\nWe're excited to have you on board. Here are some tips to get started:\n\n1. Use 'j' and 'k' to navigate through your messages\n2. Press 'e' to archive, 's' to star\n3. Press 'c' to compose a new email\n4. Press '/' to search\n\nEnjoy your new email experience!".to_string(),
                     is_unread: true,
+                    attachments: vec![],
+                    inline_images: vec![],
                 }],
                 labels: vec!["Getting Started".to_string()],
             },
             "thread-2" => ThreadDetail {
                 id: thread_id.clone(),
+                account_id: None,
                 subject: "Project Update: Q1 Planning".to_string(),
                 messages: vec![
                     MessageDetail {
@@ -1370,6 +2448,8 @@ impl MainWindow {
                         timestamp: "Today at 10:30 AM".to_string(),
                         body_text: "Hey team,\n\nI wanted to share the latest updates on our Q1 planning. We've made great progress on the roadmap.\n\nKey highlights:\n- Feature A is on track for release next week\n- Feature B needs some additional work\n- We'll be hiring two new engineers\n\nLet me know if you have any questions!".to_string(),
                         is_unread: false,
+                        attachments: vec![],
+                        inline_images: vec![],
                     },
                     MessageDetail {
                         id: EmailId::from("msg-2-2"),
@@ -1379,6 +2459,8 @@ impl MainWindow {
                         timestamp: "Today at 10:45 AM".to_string(),
                         body_text: "Thanks for the update, Alice! This looks great.\n\nQuick question - what's the timeline for Feature B?".to_string(),
                         is_unread: false,
+                        attachments: vec![],
+                        inline_images: vec![],
                     },
                     MessageDetail {
                         id: EmailId::from("msg-2-3"),
@@ -1388,12 +2470,15 @@ impl MainWindow {
                         timestamp: "Today at 11:00 AM".to_string(),
                         body_text: "Good question! We're aiming for end of February, but I'll have a more concrete timeline by next week.".to_string(),
                         is_unread: true,
+                        attachments: vec![],
+                        inline_images: vec![],
                     },
                 ],
                 labels: vec!["Work".to_string(), "Planning".to_string()],
             },
             _ => ThreadDetail {
                 id: thread_id.clone(),
+                account_id: None,
                 subject: self.threads.iter().find(|t| t.id == *thread_id).map(|t| t.subject.clone()).unwrap_or_else(|| "Message".to_string()),
                 messages: vec![MessageDetail {
                     id: EmailId::from("msg-default"),
@@ -1403,6 +2488,8 @@ impl MainWindow {
                     timestamp: "Recently".to_string(),
                     body_text: self.threads.iter().find(|t| t.id == *thread_id).map(|t| t.snippet.clone()).unwrap_or_else(|| "This is a sample message.".to_string()),
                     is_unread: false,
+                    attachments: vec![],
+                    inline_images: vec![],
                 }],
                 labels: vec![],
             },
@@ -1536,49 +2623,49 @@ impl MainWindow {
                                 "inbox",
                                 "Inbox",
                                 ViewType::Inbox,
-                                Some(2),
+                                self.sidebar_count(&ViewType::Inbox),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "starred",
                                 "Starred",
                                 ViewType::Starred,
-                                None,
+                                self.sidebar_count(&ViewType::Starred),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "snoozed",
                                 "Snoozed",
                                 ViewType::Snoozed,
-                                None,
+                                self.sidebar_count(&ViewType::Snoozed),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "sent",
                                 "Sent",
                                 ViewType::Sent,
-                                None,
+                                self.sidebar_count(&ViewType::Sent),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "drafts",
                                 "Drafts",
                                 ViewType::Drafts,
-                                Some(1),
+                                self.sidebar_count(&ViewType::Drafts),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "archive",
                                 "Archive",
                                 ViewType::Archive,
-                                None,
+                                self.sidebar_count(&ViewType::Archive),
                                 cx,
                             ))
                             .child(self.render_sidebar_item(
                                 "trash",
                                 "Trash",
                                 ViewType::Trash,
-                                None,
+                                self.sidebar_count(&ViewType::Trash),
                                 cx,
                             ))
                         },
@@ -1780,12 +2867,13 @@ impl MainWindow {
         &self,
         idx: usize,
         account: &SidebarAccount,
-        _cx: &mut Context<Self>,
+        cx: &mut Context<Self>,
     ) -> impl IntoElement {
         let colors = &self.theme.colors;
         let text_color = colors.text_primary;
         let muted_color = colors.text_muted;
         let accent = colors.accent;
+        let warning = colors.warning;
         let hover_bg = colors.surface_elevated;
 
         let display = account
@@ -1793,6 +2881,8 @@ impl MainWindow {
             .as_ref()
             .unwrap_or(&account.email)
             .clone();
+        let account_id = AccountId::from(account.id.as_str());
+        let selected = self.selected_account.as_ref() == Some(&account_id);
 
         div()
             .id(SharedString::from(format!("account-{}", idx)))
@@ -1801,7 +2891,12 @@ impl MainWindow {
             .mx(px(8.0))
             .rounded(px(6.0))
             .cursor_pointer()
+            .when(selected, |this| this.bg(hover_bg))
             .hover(move |style| style.bg(hover_bg))
+            .on_click(cx.listener(move |this, _: &ClickEvent, _, cx| {
+                this.selected_account = Some(account_id.clone());
+                cx.notify();
+            }))
             .child(
                 div()
                     .flex()
@@ -1843,23 +2938,91 @@ impl MainWindow {
                                     .text_xs()
                                     .text_color(muted_color)
                                     .child(SharedString::from(account.email.clone())),
-                            ),
+                            )
+                            .when(account.needs_attention, |this| {
+                                this.child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(warning)
+                                        .child(SharedString::from("Sign in again to sync")),
+                                )
+                            }),
                     )
-                    .when(account.unread_count > 0, |this| {
+                    .when(account.needs_attention, |this| {
                         this.child(
                             div()
                                 .px(px(6.0))
                                 .py(px(2.0))
                                 .rounded(px(10.0))
-                                .bg(accent)
+                                .bg(warning)
                                 .text_xs()
                                 .text_color(colors.background)
-                                .child(SharedString::from(account.unread_count.to_string())),
+                                .child(SharedString::from("!")),
                         )
-                    }),
+                    })
+                    .when(
+                        account.unread_count > 0 && !account.needs_attention,
+                        |this| {
+                            this.child(
+                                div()
+                                    .px(px(6.0))
+                                    .py(px(2.0))
+                                    .rounded(px(10.0))
+                                    .bg(accent)
+                                    .text_xs()
+                                    .text_color(colors.background)
+                                    .child(SharedString::from(account.unread_count.to_string())),
+                            )
+                        },
+                    ),
             )
     }
 
+    /// Badges the sidebar accounts whose credentials need attention.
+    pub fn set_accounts_needing_attention(&mut self, ids: &[AccountId], cx: &mut Context<Self>) {
+        for account in &mut self.sidebar_accounts {
+            account.needs_attention = ids.iter().any(|id| id.0 == account.id);
+        }
+        cx.notify();
+    }
+
+    /// Sets the display density, e.g. from the appearance settings.
+    pub fn set_density(&mut self, density: Density, cx: &mut Context<Self>) {
+        self.settings_display_density = density.into();
+        cx.notify();
+    }
+
+    /// Returns how message list rows are laid out at the current density.
+    fn thread_row_layout(&self) -> ThreadRowLayout {
+        ThreadRowLayout::for_density(self.settings_display_density.into())
+    }
+
+    /// Replaces the folder counts shown in the sidebar.
+    pub fn set_folder_counts(&mut self, counts: FolderCounts, cx: &mut Context<Self>) {
+        self.folder_counts = counts;
+        cx.notify();
+    }
+
+    /// Returns the badge count for a sidebar entry.
+    ///
+    /// Drafts show how many exist; other folders show unread messages.
+    fn sidebar_count(&self, view: &ViewType) -> Option<u32> {
+        let folder = match view {
+            ViewType::Inbox => FolderView::Inbox,
+            ViewType::Starred => FolderView::Starred,
+            ViewType::Sent => FolderView::Sent,
+            ViewType::Drafts => {
+                return Some(self.folder_counts.get(&FolderView::Drafts).total).filter(|&n| n > 0)
+            }
+            ViewType::Archive => FolderView::Archive,
+            ViewType::Trash => FolderView::Trash,
+            ViewType::Snoozed => FolderView::Snoozed,
+            ViewType::Label(id) => FolderView::Label(id.clone()),
+            _ => return None,
+        };
+        self.folder_counts.unread_badge(&folder)
+    }
+
     fn render_sidebar_item(
         &self,
         id: &str,
@@ -1925,6 +3088,12 @@ impl MainWindow {
         };
         let hover_bg = colors.surface_elevated;
 
+        let dot_color = label
+            .color
+            .as_deref()
+            .and_then(parse_hex_color)
+            .unwrap_or_else(|| colors.color_for(&label.id.0));
+
         let label_id = label.id.clone();
         let click_handler = cx.listener(move |this, _: &ClickEvent, _, cx| {
             this.navigate_to(ViewType::Label(label_id.clone()), cx);
@@ -1945,11 +3114,23 @@ impl MainWindow {
                     .flex()
                     .items_center()
                     .gap(px(8.0))
-                    .child(div().size(px(8.0)).rounded_full().bg(colors.accent))
+                    .child(div().size(px(8.0)).rounded_full().bg(dot_color))
                     .child(
                         div()
+                            .flex_1()
                             .text_color(colors.text_primary)
                             .child(SharedString::from(label.name.clone())),
+                    )
+                    .when_some(
+                        self.sidebar_count(&ViewType::Label(label.id.clone())),
+                        |this, c| {
+                            this.child(
+                                div()
+                                    .text_xs()
+                                    .text_color(colors.text_muted)
+                                    .child(SharedString::from(c.to_string())),
+                            )
+                        },
                     ),
             )
     }
@@ -1958,11 +3139,17 @@ impl MainWindow {
         let colors = &self.theme.colors;
         let view_title = self.view_title();
 
-        let thread_items: Vec<_> = self
-            .threads
-            .iter()
-            .enumerate()
-            .map(|(idx, thread)| self.render_thread_item(thread, idx, cx))
+        let rows: Vec<AnyElement> = self
+            .message_list_rows()
+            .into_iter()
+            .map(|row| match row {
+                MessageListRow::Bundle(category, count) => self
+                    .render_bundle_header(category, count, cx)
+                    .into_any_element(),
+                MessageListRow::Thread(idx) => self
+                    .render_thread_item(&self.threads[idx], idx, cx)
+                    .into_any_element(),
+            })
             .collect();
 
         div()
@@ -2001,7 +3188,60 @@ impl MainWindow {
                     .id("message-list-scroll")
                     .flex_1()
                     .overflow_y_scroll()
-                    .children(thread_items),
+                    .track_scroll(&self.message_list_scroll)
+                    .children(rows),
+            )
+    }
+
+    fn render_bundle_header(
+        &self,
+        category: BundleCategory,
+        count: usize,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let colors = &self.theme.colors;
+        let is_collapsed = self.collapsed_bundles.contains(&category);
+        let hover_bg = colors.surface;
+
+        let click_handler = cx.listener(move |this, _: &ClickEvent, _, cx| {
+            if !this.collapsed_bundles.remove(&category) {
+                this.collapsed_bundles.insert(category);
+            }
+            cx.notify();
+        });
+
+        div()
+            .id(SharedString::from(format!("bundle-{:?}", category)))
+            .h(px(self.thread_row_layout().row_height()))
+            .flex_none()
+            .px(px(16.0))
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .bg(colors.surface_elevated)
+            .border_b_1()
+            .border_color(colors.border)
+            .cursor_pointer()
+            .hover(move |style| style.bg(hover_bg))
+            .on_click(click_handler)
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(colors.text_muted)
+                    .child(SharedString::from(if is_collapsed { "+" } else { "-" })),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .font_weight(FontWeight::SEMIBOLD)
+                    .text_color(colors.text_primary)
+                    .child(SharedString::from(category.name())),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(colors.text_muted)
+                    .child(SharedString::from(count.to_string())),
             )
     }
 
@@ -2012,7 +3252,9 @@ impl MainWindow {
         cx: &mut Context<Self>,
     ) -> impl IntoElement {
         let colors = &self.theme.colors;
-        let is_selected = self.selected_thread_id.as_ref() == Some(&thread.id);
+        let layout = self.thread_row_layout();
+        let is_selected = self.selected_thread_id.as_ref() == Some(&thread.id)
+            || self.selection.contains(&thread.id);
         let is_focused = index == self.focused_index;
 
         let bg = if is_selected {
@@ -2037,12 +3279,23 @@ impl MainWindow {
         let starred_color = colors.starred;
 
         let thread_id = thread.id.clone();
-        let click_handler = cx.listener(move |this, _: &ClickEvent, _, cx| {
-            this.select_thread(thread_id.clone(), cx);
+        let click_handler = cx.listener(move |this, _: &ClickEvent, window, cx| {
+            if window.modifiers().shift {
+                this.selection
+                    .select_range(&this.threads, this.focused_index, index);
+                this.focused_index = index;
+                cx.notify();
+            } else {
+                this.selection.clear();
+                this.select_thread(thread_id.clone(), cx);
+            }
         });
 
         div()
             .id(SharedString::from(format!("thread-{}", index)))
+            .h(px(layout.row_height()))
+            .flex_none()
+            .overflow_hidden()
             .px(px(16.0))
             .py(px(12.0))
             .bg(bg)
@@ -2065,7 +3318,12 @@ impl MainWindow {
                                 div()
                                     .font_weight(text_weight)
                                     .text_color(text_primary)
-                                    .child(SharedString::from(thread.sender_name.clone())),
+                                    .child(SharedString::from(
+                                        layout.sender_label(
+                                            &thread.sender_name,
+                                            &thread.sender_email,
+                                        ),
+                                    )),
                             )
                             .when(thread.message_count > 1, |this| {
                                 this.child(div().text_xs().text_color(text_muted).child(
@@ -2101,13 +3359,18 @@ impl MainWindow {
                     .truncate()
                     .child(SharedString::from(thread.subject.clone())),
             )
-            .child(
-                div()
-                    .text_sm()
-                    .text_color(text_secondary)
-                    .truncate()
-                    .child(SharedString::from(thread.snippet.clone())),
-            )
+            .when(layout.show_snippet(), |this| {
+                let lines = layout.preview_lines;
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(text_secondary)
+                        .max_h(px(f32::from(lines) * PREVIEW_LINE_HEIGHT))
+                        .overflow_hidden()
+                        .when(lines == 1, |this| this.truncate())
+                        .child(SharedString::from(thread.snippet.clone())),
+                )
+            })
     }
 
     fn render_reading_pane(&self, cx: &mut Context<Self>) -> impl IntoElement {
@@ -2120,7 +3383,7 @@ impl MainWindow {
             .flex()
             .flex_col()
             .bg(colors.background)
-            .when(self.current_thread.is_none(), |this| {
+            .when(self.selected_thread_id.is_none(), |this| {
                 this.child(
                     div().flex_1().flex().items_center().justify_center().child(
                         div()
@@ -2261,6 +3524,15 @@ impl MainWindow {
                         .text_color(colors.text_primary)
                         .child(SharedString::from(message.body_text.clone())),
                 )
+                .children(
+                    message
+                        .inline_images
+                        .iter()
+                        .map(|image| img(image.clone()).max_w_full().mt(px(12.0))),
+                )
+                .when(!message.attachments.is_empty(), |this| {
+                    this.child(render_attachments(&message.attachments, colors))
+                })
         } else {
             div()
                 .id(SharedString::from(format!("msg-{}", message.id.0)))
@@ -3209,6 +4481,8 @@ impl MainWindow {
         // Center section: selection count or AI status
         let center_text = if let Some(ref ai_status) = self.ai_status {
             ai_status.clone()
+        } else if !self.selection.is_empty() {
+            format!("{} selected", self.selection.len())
         } else if self.selected_thread_id.is_some() {
             "1 selected".to_string()
         } else {
@@ -3314,6 +4588,99 @@ impl MainWindow {
         }
     }
 
+    fn render_message_source(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let colors = &self.theme.colors;
+        let (headers, raw) = self
+            .message_source
+            .as_ref()
+            .map(|source| (source.headers.clone(), source.raw.clone()))
+            .unwrap_or_default();
+
+        let backdrop_handler = cx.listener(|this, _: &ClickEvent, _, cx| {
+            this.dismiss_overlay(cx);
+        });
+
+        let section = |title: &'static str| {
+            div()
+                .px(px(16.0))
+                .pt(px(12.0))
+                .pb(px(4.0))
+                .text_xs()
+                .font_weight(FontWeight::SEMIBOLD)
+                .text_color(colors.text_muted)
+                .child(SharedString::from(title))
+        };
+
+        div()
+            .id("message-source-backdrop")
+            .absolute()
+            .inset_0()
+            .bg(gpui::Hsla {
+                h: 0.0,
+                s: 0.0,
+                l: 0.0,
+                a: 0.5,
+            })
+            .flex()
+            .items_center()
+            .justify_center()
+            .on_click(backdrop_handler)
+            .child(
+                div()
+                    .id("message-source")
+                    .w(px(720.0))
+                    .max_h(px(560.0))
+                    .flex()
+                    .flex_col()
+                    .bg(colors.surface_elevated)
+                    .rounded(px(12.0))
+                    .border_1()
+                    .border_color(colors.border)
+                    .shadow_lg()
+                    .overflow_hidden()
+                    .on_click(cx.listener(|_, _: &ClickEvent, _, cx| {
+                        cx.stop_propagation();
+                    }))
+                    .child(
+                        div()
+                            .px(px(16.0))
+                            .py(px(12.0))
+                            .border_b_1()
+                            .border_color(colors.border)
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .text_color(colors.text_primary)
+                                    .child(SharedString::from("Message source")),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .id("message-source-scroll")
+                            .flex_1()
+                            .overflow_y_scroll()
+                            .pb(px(12.0))
+                            .child(section("HEADERS"))
+                            .child(
+                                div()
+                                    .px(px(16.0))
+                                    .text_xs()
+                                    .text_color(colors.text_secondary)
+                                    .child(SharedString::from(headers)),
+                            )
+                            .child(section("ORIGINAL MESSAGE"))
+                            .child(
+                                div()
+                                    .px(px(16.0))
+                                    .text_xs()
+                                    .text_color(colors.text_primary)
+                                    .child(SharedString::from(raw)),
+                            ),
+                    ),
+            )
+    }
+
     fn render_snooze_picker(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let colors = &self.theme.colors;
         let snooze_options = [
@@ -3321,6 +4688,11 @@ impl MainWindow {
             (SnoozeDuration::Tomorrow, "Tomorrow", "8 AM tomorrow"),
             (SnoozeDuration::ThisWeekend, "This Weekend", "Saturday 9 AM"),
             (SnoozeDuration::NextWeek, "Next Week", "Monday 8 AM"),
+            (
+                SnoozeDuration::UntilReply,
+                "Until Reply",
+                "When someone replies",
+            ),
         ];
 
         let backdrop_handler = cx.listener(|this, _: &ClickEvent, _, cx| {
@@ -3451,6 +4823,9 @@ impl MainWindow {
 
     fn render_label_picker(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let colors = &self.theme.colors;
+        let query = self.label_palette.query();
+        let entries = self.label_palette.entries();
+        let highlighted = self.label_palette.highlighted();
 
         let backdrop_handler = cx.listener(|this, _: &ClickEvent, _, cx| {
             this.dismiss_overlay(cx);
@@ -3484,7 +4859,7 @@ impl MainWindow {
                         cx.stop_propagation();
                     }))
                     .child(
-                        // Header
+                        // Filter input
                         div()
                             .px(px(16.0))
                             .py(px(12.0))
@@ -3493,31 +4868,45 @@ impl MainWindow {
                             .child(
                                 div()
                                     .text_sm()
-                                    .font_weight(FontWeight::SEMIBOLD)
-                                    .text_color(colors.text_primary)
-                                    .child(SharedString::from("Apply Labels")),
+                                    .text_color(if query.is_empty() {
+                                        colors.text_muted
+                                    } else {
+                                        colors.text_primary
+                                    })
+                                    .child(SharedString::from(if query.is_empty() {
+                                        "Apply labels...".to_string()
+                                    } else {
+                                        query.to_string()
+                                    })),
                             ),
                     )
                     .child(
                         // Labels list
-                        div().py(px(8.0)).children(self.available_labels.iter().map(
-                            |(id, name)| {
-                                let is_selected = self.label_picker_selected.contains(id);
-                                let checkbox = if is_selected { "[x]" } else { "[ ]" };
+                        div()
+                            .py(px(8.0))
+                            .children(entries.into_iter().enumerate().map(|(index, entry)| {
+                                let (text, checkbox) = match entry {
+                                    LabelPaletteEntry::Label { name, selected, .. } => {
+                                        (name, if selected { "[x]" } else { "[ ]" })
+                                    }
+                                    LabelPaletteEntry::Create(name) => {
+                                        (format!("Create \"{}\"", name), "[+]")
+                                    }
+                                };
+                                let is_highlighted = index == highlighted;
                                 let text_primary = colors.text_primary;
                                 let text_muted = colors.text_muted;
                                 let hover_bg = colors.surface;
-                                let name_str = name.clone();
-                                let id_str = id.clone();
 
                                 div()
-                                    .id(SharedString::from(format!("label-{}", id)))
+                                    .id(SharedString::from(format!("label-{}", index)))
                                     .px(px(16.0))
                                     .py(px(8.0))
                                     .cursor_pointer()
+                                    .when(is_highlighted, |d| d.bg(hover_bg))
                                     .hover(move |s| s.bg(hover_bg))
                                     .on_click(cx.listener(move |this, _: &ClickEvent, _, cx| {
-                                        this.toggle_label(&id_str);
+                                        this.label_palette.toggle(index);
                                         cx.notify();
                                     }))
                                     .child(
@@ -3535,11 +4924,10 @@ impl MainWindow {
                                                 div()
                                                     .text_sm()
                                                     .text_color(text_primary)
-                                                    .child(SharedString::from(name_str)),
+                                                    .child(SharedString::from(text)),
                                             ),
                                     )
-                            },
-                        )),
+                            })),
                     )
                     .child(
                         // Footer with apply button
@@ -3554,7 +4942,7 @@ impl MainWindow {
                             .child(div().text_xs().text_color(colors.text_muted).child(
                                 SharedString::from(format!(
                                     "{} selected",
-                                    self.label_picker_selected.len()
+                                    self.label_palette.selected_count()
                                 )),
                             ))
                             .child(
@@ -3567,7 +4955,11 @@ impl MainWindow {
                                     .cursor_pointer()
                                     .hover(move |s| s.bg(colors.accent_hover))
                                     .on_click(cx.listener(|this, _: &ClickEvent, _, cx| {
-                                        this.apply_labels(cx);
+                                        if let LabelPaletteKey::Apply(selection) =
+                                            this.label_palette.confirm()
+                                        {
+                                            this.apply_labels(selection, cx);
+                                        }
                                     }))
                                     .child(
                                         div()
@@ -3580,22 +4972,174 @@ impl MainWindow {
             )
     }
 
-    fn toggle_label(&mut self, id: &str) {
-        if self.label_picker_selected.contains(id) {
-            self.label_picker_selected.remove(id);
-        } else {
-            self.label_picker_selected.insert(id.to_string());
-        }
+    /// Opens the label palette over the labels of the first target's
+    /// account, loaded in the background, with the labels every target has
+    /// selected.
+    fn open_label_palette(&mut self, cx: &mut Context<Self>) {
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            self.show_label_palette(&[], cx);
+            return;
+        };
+
+        let client = handle.client.clone();
+        let targets = self.action_targets();
+        let loaded = handle.spawn(async move {
+            let mut summaries = Vec::with_capacity(targets.len());
+            for thread_id in &targets {
+                summaries.push(client.threads().get_thread_summary(thread_id).await?);
+            }
+            let labels = match summaries.first() {
+                Some(summary) => client.labels(&summary.account_id).await?,
+                None => Vec::new(),
+            };
+            anyhow::Ok((summaries, labels))
+        });
+        cx.spawn(move |this, mut cx| async move {
+            let loaded = loaded.await;
+            this.update(&mut cx, |this, cx| {
+                let mut applied = Vec::new();
+                match loaded {
+                    Ok((summaries, labels)) => {
+                        this.available_labels = labels
+                            .into_iter()
+                            .map(|label| (label.id.0, label.name))
+                            .collect();
+                        applied = this
+                            .available_labels
+                            .iter()
+                            .map(|(id, _)| id.clone())
+                            .filter(|id| {
+                                let id = LabelId::from(id.as_str());
+                                summaries.iter().all(|s| s.labels.contains(&id))
+                            })
+                            .collect();
+                    }
+                    Err(e) => tracing::warn!("Failed to load the threads' labels: {}", e),
+                }
+                this.show_label_palette(&applied, cx);
+            })
+            .ok();
+        })
+        .detach();
     }
 
-    fn apply_labels(&mut self, cx: &mut Context<Self>) {
-        if !self.label_picker_selected.is_empty() {
-            let count = self.label_picker_selected.len();
-            let label_text = if count == 1 { "label" } else { "labels" };
-            self.show_toast(format!("Applied {} {}", count, label_text), false);
-            self.label_picker_selected.clear();
-        }
+    /// Shows the label palette over the available labels, with `applied`
+    /// selected.
+    fn show_label_palette(&mut self, applied: &[String], cx: &mut Context<Self>) {
+        self.label_palette =
+            LabelPalette::new(self.available_labels.iter().cloned()).with_applied(applied);
+        self.show_overlay(ActiveOverlay::LabelPicker, cx);
+    }
+
+    /// Applies the palette's labels to the selected threads, or the open
+    /// thread, creating labels named in the palette first.
+    ///
+    /// Labels are matched by name in each thread's account, so threads from
+    /// different accounts get their own account's label.
+    fn apply_labels(&mut self, selection: LabelSelection, cx: &mut Context<Self>) {
+        let targets = self.action_targets();
+        let name_of = |id: &String| {
+            self.available_labels
+                .iter()
+                .find(|(label_id, _)| label_id == id)
+                .map(|(_, name)| name.clone())
+        };
+        let mut added: Vec<String> = selection.label_ids.iter().filter_map(name_of).collect();
+        added.extend(selection.new_labels.iter().cloned());
+        let removed: Vec<String> = selection
+            .removed_label_ids
+            .iter()
+            .filter_map(name_of)
+            .collect();
+
+        let count = added.len() + removed.len();
+        let labels = if count == 1 { "label" } else { "labels" };
+        let threads = if targets.len() == 1 {
+            "thread"
+        } else {
+            "threads"
+        };
+        let (verb, preposition) = if removed.is_empty() {
+            ("Applied", "to")
+        } else if added.is_empty() {
+            ("Removed", "from")
+        } else {
+            ("Changed", "on")
+        };
+        let message = format!(
+            "{} {} {} {} {} {}",
+            verb,
+            count,
+            labels,
+            preposition,
+            targets.len(),
+            threads
+        );
+        self.selection.clear();
         self.dismiss_overlay(cx);
+
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            for name in &selection.new_labels {
+                let id = format!("label-{}", uuid::Uuid::new_v4());
+                self.available_labels.push((id, name.clone()));
+            }
+            self.show_toast(message, false);
+            return;
+        };
+
+        let client = handle.client.clone();
+        let changed = handle.spawn(async move {
+            let threads = client.threads();
+            let mut changes = 0;
+            let result = async {
+                for thread_id in &targets {
+                    let account_id = threads.get_thread_summary(thread_id).await?.account_id;
+                    for name in &added {
+                        let label = client.ensure_label(&account_id, name).await?;
+                        threads.add_label(thread_id, &label.id).await?;
+                        changes += 1;
+                    }
+                    let labels = client.labels(&account_id).await?;
+                    for label in labels.iter().filter(|label| removed.contains(&label.name)) {
+                        threads.remove_label(thread_id, &label.id).await?;
+                        changes += 1;
+                    }
+                }
+                anyhow::Ok(())
+            }
+            .await;
+            if let Err(e) = result {
+                // Reverse the changes made before the failure, which would
+                // otherwise be left without an undo entry.
+                for _ in 0..changes {
+                    if let Err(e) = client.undo().await {
+                        tracing::warn!("Failed to undo a label change: {}", e);
+                    }
+                }
+                return Err(e);
+            }
+            Ok(changes)
+        });
+        cx.spawn(move |this, mut cx| async move {
+            let changed = changed.await;
+            this.update(&mut cx, |this, cx| {
+                match changed {
+                    Ok(changes) => {
+                        if changes > 0 {
+                            this.push_undo_action(UndoableAction::Labels { changes });
+                        }
+                        this.show_toast(message, changes > 0);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to change the threads' labels: {}", e);
+                        this.show_toast(format!("Couldn't change labels: {}", e), false);
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
     }
 
     fn render_toast(&self, cx: &mut Context<Self>) -> impl IntoElement {
@@ -3610,6 +5154,15 @@ impl MainWindow {
                 this.dismiss_toast();
                 cx.notify();
             });
+            let block_handler = cx.listener(|this, _: &ClickEvent, _, cx| {
+                this.block_offered_senders(cx);
+                cx.notify();
+            });
+            let block_label = if toast.block.len() == 1 {
+                "Block sender"
+            } else {
+                "Block senders"
+            };
 
             div()
                 .id("toast-container")
@@ -3647,6 +5200,18 @@ impl MainWindow {
                                     .child(SharedString::from("Z")),
                             )
                         })
+                        .when(!toast.block.is_empty(), |this| {
+                            this.child(
+                                div()
+                                    .id("toast-block")
+                                    .text_sm()
+                                    .text_color(colors.accent)
+                                    .font_weight(FontWeight::MEDIUM)
+                                    .cursor_pointer()
+                                    .on_click(block_handler)
+                                    .child(SharedString::from(block_label)),
+                            )
+                        })
                         .child(
                             div()
                                 .id("toast-dismiss")
@@ -4280,6 +5845,24 @@ impl MainWindow {
                         GeneralToggle::ShowConversationView,
                         self.settings_show_conversation_view,
                         cx,
+                    ))
+                    .child(self.render_general_toggle(
+                        "Open next thread after archiving",
+                        GeneralToggle::OpenNextAfterArchive,
+                        self.settings_after_done == AfterDone::Advance,
+                        cx,
+                    ))
+                    .child(self.render_general_toggle(
+                        "Group inbox into bundles",
+                        GeneralToggle::InboxBundles,
+                        self.settings_inbox_bundles,
+                        cx,
+                    ))
+                    .child(self.render_general_toggle(
+                        "Collapse near-duplicate messages",
+                        GeneralToggle::CollapseDuplicates,
+                        self.settings_collapse_duplicates,
+                        cx,
                     )),
             )
     }
@@ -4321,10 +5904,24 @@ impl MainWindow {
                 GeneralToggle::MarkAsReadWhenOpened => {
                     this.settings_mark_as_read_when_opened =
                         !this.settings_mark_as_read_when_opened;
+                    let delay = this.mark_read_delay();
+                    this.mark_read_timer.set_delay(delay);
                 }
                 GeneralToggle::ShowConversationView => {
                     this.settings_show_conversation_view = !this.settings_show_conversation_view;
                 }
+                GeneralToggle::OpenNextAfterArchive => {
+                    this.settings_after_done = match this.settings_after_done {
+                        AfterDone::Advance => AfterDone::Stay,
+                        AfterDone::Stay => AfterDone::Advance,
+                    };
+                }
+                GeneralToggle::InboxBundles => {
+                    this.settings_inbox_bundles = !this.settings_inbox_bundles;
+                }
+                GeneralToggle::CollapseDuplicates => {
+                    this.settings_collapse_duplicates = !this.settings_collapse_duplicates;
+                }
             }
             cx.notify();
         });
@@ -4744,6 +6341,7 @@ impl MainWindow {
                     ("#", "Move to trash"),
                     ("s", "Star / Unstar"),
                     ("h", "Snooze"),
+                    ("H", "Remind me if nobody replies"),
                     ("u / U", "Mark read / unread"),
                 ],
             ),
@@ -5124,6 +6722,11 @@ impl MainWindow {
             this.dismiss_overlay(cx);
         });
 
+        let cycle_from = cx.listener(|this, _: &ClickEvent, _, cx| {
+            this.composer_from.cycle();
+            cx.notify();
+        });
+
         let toggle_cc = cx.listener(|this, _: &ClickEvent, _, cx| {
             this.composer_show_cc = !this.composer_show_cc;
             if this.composer_show_cc {
@@ -5253,6 +6856,43 @@ impl MainWindow {
                                     ),
                             ),
                     )
+                    // From field, when the account has aliases
+                    .when(self.composer_from.has_choice(), |this| {
+                        let from = self
+                            .composer_from
+                            .current()
+                            .map(Address::display)
+                            .unwrap_or_default();
+                        this.child(
+                            div()
+                                .id("from-field")
+                                .px(px(16.0))
+                                .py(px(10.0))
+                                .border_b_1()
+                                .border_color(colors.border)
+                                .cursor_pointer()
+                                .on_click(cycle_from)
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap(px(8.0))
+                                        .child(
+                                            div()
+                                                .w(px(60.0))
+                                                .text_sm()
+                                                .text_color(colors.text_muted)
+                                                .child(SharedString::from("From:")),
+                                        )
+                                        .child(
+                                            div()
+                                                .flex_1()
+                                                .text_color(colors.text_primary)
+                                                .child(SharedString::from(from)),
+                                        ),
+                                ),
+                        )
+                    })
                     // To field
                     .child(
                         div()
@@ -6202,15 +7842,54 @@ impl MainWindow {
     }
 }
 
-fn truncate_text(text: &str, max_len: usize) -> String {
-    let first_line = text.lines().next().unwrap_or(text);
-    if first_line.len() <= max_len {
-        first_line.to_string()
+/// Returns the scroll offset that brings the row at `index` fully into view,
+/// moving as little as possible from `offset`.
+fn scroll_offset_to_reveal(
+    index: usize,
+    offset: f32,
+    viewport_height: f32,
+    item_height: f32,
+) -> f32 {
+    let top = index as f32 * item_height;
+    let bottom = top + item_height;
+    if top < offset {
+        top
+    } else if bottom > offset + viewport_height {
+        // Rows taller than the viewport show their top.
+        (bottom - viewport_height).min(top)
     } else {
-        format!("{}...", &first_line[..max_len])
+        offset
     }
 }
 
+/// Returns where focus goes once the thread at `index` has left the list,
+/// which now holds `remaining` threads: the thread that took its place (or
+/// the new last thread), and whether to open it.
+fn focus_after_done(index: usize, remaining: usize, after: AfterDone) -> Option<(usize, bool)> {
+    let last = remaining.checked_sub(1)?;
+    Some((index.min(last), after == AfterDone::Advance))
+}
+
+/// Returns the account to send from: the one with `account_id`, or else
+/// the first.
+fn sending_account(accounts: Vec<Account>, account_id: Option<&AccountId>) -> Option<Account> {
+    let index = account_id
+        .and_then(|id| accounts.iter().position(|account| account.id == *id))
+        .unwrap_or(0);
+    accounts.into_iter().nth(index)
+}
+
+/// Parses a comma-separated recipient field, skipping entries that aren't
+/// addresses.
+fn parse_recipients(field: &str) -> Vec<Address> {
+    field.split(',').filter_map(Address::parse).collect()
+}
+
+fn truncate_text(text: &str, max_len: usize) -> String {
+    let first_line = text.lines().next().unwrap_or(text);
+    truncate_chars(first_line, max_len)
+}
+
 impl Focusable for MainWindow {
     fn focus_handle(&self, _cx: &gpui::App) -> FocusHandle {
         self.focus_handle.clone()
@@ -6226,7 +7905,14 @@ impl Render for MainWindow {
             .id("main-window")
             .key_context("MainWindow")
             // Only enable single-letter keybindings when no overlay is active
-            .when(!has_overlay, |div| div.key_context("EmailActions"))
+            .when(!has_overlay, |div| {
+                // The screener takes over keys like `x` from the message list.
+                div.key_context(if self.current_view == ViewType::Screener {
+                    "EmailActions Screener"
+                } else {
+                    "EmailActions"
+                })
+            })
             .track_focus(&self.focus_handle)
             // Handle text input for overlays
             .on_key_down(cx.listener(|this, event: &KeyDownEvent, _window, cx| {
@@ -6236,6 +7922,7 @@ impl Render for MainWindow {
                         | ActiveOverlay::Search
                         | ActiveOverlay::Composer
                         | ActiveOverlay::AccountSetup
+                        | ActiveOverlay::LabelPicker
                 ) {
                     this.handle_overlay_key(event, cx);
                 }
@@ -6244,6 +7931,9 @@ impl Render for MainWindow {
             .on_action(cx.listener(|this, _: &Dismiss, _, cx| {
                 if this.active_overlay != ActiveOverlay::None {
                     this.dismiss_overlay(cx);
+                } else if !this.selection.is_empty() {
+                    this.selection.clear();
+                    cx.notify();
                 }
             }))
             // Undo
@@ -6263,6 +7953,27 @@ impl Render for MainWindow {
                     this.focus_previous(cx);
                 }
             }))
+            // Multi-selection
+            .on_action(cx.listener(|this, _: &ToggleSelection, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.toggle_focused_selection(cx);
+                }
+            }))
+            .on_action(cx.listener(|this, _: &SelectNext, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.extend_selection(true, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, _: &SelectPrevious, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.extend_selection(false, cx);
+                }
+            }))
+            .on_action(cx.listener(|this, _: &SelectAll, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.select_all_threads(cx);
+                }
+            }))
             // Overlays (Cmd+key works always, single-letter only when no overlay)
             .on_action(cx.listener(|this, _: &OpenCommandPalette, _, cx| {
                 this.toggle_overlay(ActiveOverlay::CommandPalette, cx);
@@ -6277,13 +7988,13 @@ impl Render for MainWindow {
             }))
             .on_action(cx.listener(|this, _: &Compose, _, cx| {
                 if this.active_overlay == ActiveOverlay::None {
-                    this.show_overlay(ActiveOverlay::Composer, cx);
+                    this.compose_new(cx);
                 }
             }))
             // Email actions
             .on_action(cx.listener(|this, _: &Archive, _, cx| {
                 if this.active_overlay == ActiveOverlay::None {
-                    this.archive_selected(cx);
+                    this.done_selected(cx);
                 }
             }))
             .on_action(cx.listener(|this, _: &Trash, _, cx| {
@@ -6291,6 +8002,11 @@ impl Render for MainWindow {
                     this.trash_selected(cx);
                 }
             }))
+            .on_action(cx.listener(|this, _: &ReportSpam, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.report_spam_selected(cx);
+                }
+            }))
             .on_action(cx.listener(|this, _: &Star, _, cx| {
                 if this.active_overlay == ActiveOverlay::None {
                     this.star_selected(cx);
@@ -6302,10 +8018,19 @@ impl Render for MainWindow {
                     this.show_overlay(ActiveOverlay::SnoozePicker, cx);
                 }
             }))
+            .on_action(cx.listener(|this, _: &FollowUp, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.follow_up_selected(cx);
+                }
+            }))
             .on_action(cx.listener(|this, _: &ApplyLabel, _, cx| {
-                if this.active_overlay == ActiveOverlay::None && this.selected_thread_id.is_some() {
-                    this.label_picker_selected.clear();
-                    this.show_overlay(ActiveOverlay::LabelPicker, cx);
+                if this.active_overlay == ActiveOverlay::None && !this.action_targets().is_empty() {
+                    this.open_label_palette(cx);
+                }
+            }))
+            .on_action(cx.listener(|this, _: &ViewSource, _, cx| {
+                if this.active_overlay == ActiveOverlay::None {
+                    this.view_source(cx);
                 }
             }))
             .on_action(cx.listener(|this, _: &MarkRead, _, cx| {
@@ -6380,19 +8105,25 @@ impl Render for MainWindow {
             // Reply/Forward (show composer with context, only when no overlay)
             .on_action(cx.listener(|this, _: &Reply, _, cx| {
                 if this.active_overlay == ActiveOverlay::None && this.selected_thread_id.is_some() {
-                    this.show_overlay(ActiveOverlay::Composer, cx);
+                    this.compose_in_thread(cx);
                 }
             }))
             .on_action(cx.listener(|this, _: &ReplyAll, _, cx| {
                 if this.active_overlay == ActiveOverlay::None && this.selected_thread_id.is_some() {
-                    this.show_overlay(ActiveOverlay::Composer, cx);
+                    this.compose_in_thread(cx);
                 }
             }))
             .on_action(cx.listener(|this, _: &Forward, _, cx| {
                 if this.active_overlay == ActiveOverlay::None && this.selected_thread_id.is_some() {
-                    this.show_overlay(ActiveOverlay::Composer, cx);
+                    this.compose_in_thread(cx);
                 }
             }))
+            .on_action(cx.listener(|this, _: &ExpandAllMessages, _, cx| {
+                this.expand_all_messages(cx);
+            }))
+            .on_action(cx.listener(|this, _: &CollapseAllMessages, _, cx| {
+                this.collapse_all_messages(cx);
+            }))
             .size_full()
             // Handle resize drag - mouse move
             .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, _window, cx| {
@@ -6460,7 +8191,298 @@ impl Render for MainWindow {
                 ActiveOverlay::AccountSetup => this.child(self.render_account_setup_overlay(cx)),
                 ActiveOverlay::SnoozePicker => this.child(self.render_snooze_picker(cx)),
                 ActiveOverlay::LabelPicker => this.child(self.render_label_picker(cx)),
+                ActiveOverlay::MessageSource => this.child(self.render_message_source(cx)),
                 ActiveOverlay::None => this,
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn density_sets_the_message_row_layout() {
+        let rows: Vec<(u8, bool, bool)> = [Density::Compact, Density::Default, Density::Relaxed]
+            .into_iter()
+            .map(ThreadRowLayout::for_density)
+            .map(|layout| {
+                (
+                    layout.preview_lines,
+                    layout.show_snippet(),
+                    layout.show_sender_email,
+                )
+            })
+            .collect();
+        assert_eq!(rows, [(0, false, false), (1, true, false), (2, true, true)]);
+
+        let default = ThreadRowLayout::for_density(Density::Default);
+        assert_eq!(default.row_height(), 88.0);
+        assert!(ThreadRowLayout::for_density(Density::Compact).row_height() < default.row_height());
+        assert_eq!(default.sender_label("Alice", "alice@example.com"), "Alice");
+        assert_eq!(
+            ThreadRowLayout::for_density(Density::Relaxed)
+                .sender_label("Alice", "alice@example.com"),
+            "Alice <alice@example.com>"
+        );
+        assert_eq!(
+            DisplayDensity::from(Density::from(DisplayDensity::Spacious)),
+            DisplayDensity::Spacious
+        );
+    }
+
+    fn thread(ids: &[&str]) -> ThreadDetail {
+        ThreadDetail {
+            id: ThreadId::from("thread-1"),
+            account_id: None,
+            subject: "Plans".to_string(),
+            messages: ids
+                .iter()
+                .map(|id| MessageDetail {
+                    id: EmailId::from(*id),
+                    sender_name: "Alice".to_string(),
+                    sender_email: "alice@example.com".to_string(),
+                    recipients: vec![],
+                    timestamp: String::new(),
+                    body_text: String::new(),
+                    is_unread: false,
+                    attachments: vec![],
+                    inline_images: vec![],
+                })
+                .collect(),
+            labels: vec![],
+        }
+    }
+
+    #[test]
+    fn expand_and_collapse_all_messages() {
+        let thread = thread(&["m1", "m2", "m3"]);
+
+        let expanded = thread.all_expanded();
+        assert_eq!(expanded.len(), 3);
+        assert!(["m1", "m2", "m3"]
+            .iter()
+            .all(|id| expanded.contains(&EmailId::from(*id))));
+
+        let collapsed = thread.latest_expanded();
+        assert_eq!(collapsed, HashSet::from([EmailId::from("m3")]));
+    }
+
+    fn thread_list(count: usize) -> Vec<ThreadListItem> {
+        (0..count)
+            .map(|i| ThreadListItem {
+                id: ThreadId::from(format!("thread-{}", i)),
+                subject: String::new(),
+                sender_name: String::new(),
+                sender_email: String::new(),
+                snippet: String::new(),
+                timestamp: String::new(),
+                is_unread: false,
+                is_starred: false,
+                message_count: 1,
+                bundle: BundleCategory::Primary,
+                duplicate_of: None,
+            })
+            .collect()
+    }
+
+    fn ids(indices: &[usize]) -> Vec<ThreadId> {
+        indices
+            .iter()
+            .map(|i| ThreadId::from(format!("thread-{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn toggle_selection() {
+        let threads = thread_list(5);
+        let mut selection = ThreadSelection::default();
+
+        selection.toggle(&threads, 3);
+        selection.toggle(&threads, 1);
+        assert_eq!(selection.ordered_ids(&threads), ids(&[1, 3]));
+
+        selection.toggle(&threads, 3);
+        assert_eq!(selection.ordered_ids(&threads), ids(&[1]));
+        assert_eq!(selection.len(), 1);
+    }
+
+    #[test]
+    fn bundled_rows_group_threads_under_headers() {
+        let mut threads = thread_list(4);
+        threads[1].bundle = BundleCategory::Promotions;
+        threads[3].bundle = BundleCategory::Promotions;
+        let mut collapsed = HashSet::new();
+
+        assert_eq!(
+            message_list_rows(&threads, false, false, &collapsed),
+            (0..4).map(MessageListRow::Thread).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            message_list_rows(&threads, true, false, &collapsed),
+            [
+                MessageListRow::Bundle(BundleCategory::Primary, 2),
+                MessageListRow::Thread(0),
+                MessageListRow::Thread(2),
+                MessageListRow::Bundle(BundleCategory::Promotions, 2),
+                MessageListRow::Thread(1),
+                MessageListRow::Thread(3),
+            ]
+        );
+
+        collapsed.insert(BundleCategory::Primary);
+        assert_eq!(
+            message_list_rows(&threads, true, false, &collapsed),
+            [
+                MessageListRow::Bundle(BundleCategory::Primary, 2),
+                MessageListRow::Bundle(BundleCategory::Promotions, 2),
+                MessageListRow::Thread(1),
+                MessageListRow::Thread(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn collapsed_duplicates_leave_one_row() {
+        let mut threads = thread_list(4);
+        threads[2].duplicate_of = Some(ThreadId::from("thread-0"));
+        threads[3].bundle = BundleCategory::Promotions;
+        let collapsed = HashSet::new();
+
+        assert_eq!(
+            message_list_rows(&threads, false, true, &collapsed),
+            [0, 1, 3].map(MessageListRow::Thread)
+        );
+        assert_eq!(
+            message_list_rows(&threads, true, true, &collapsed),
+            [
+                MessageListRow::Bundle(BundleCategory::Primary, 2),
+                MessageListRow::Thread(0),
+                MessageListRow::Thread(1),
+                MessageListRow::Bundle(BundleCategory::Promotions, 1),
+                MessageListRow::Thread(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn range_selection_extends_from_anchor() {
+        let threads = thread_list(6);
+        let mut selection = ThreadSelection::default();
+
+        // Shift+j from the focused thread starts a range there.
+        selection.select_range(&threads, 1, 2);
+        selection.select_range(&threads, 2, 3);
+        assert_eq!(selection.ordered_ids(&threads), ids(&[1, 2, 3]));
+
+        // Shift+click above the anchor selects back to it.
+        selection.toggle(&threads, 5);
+        selection.select_range(&threads, 5, 4);
+        assert_eq!(selection.ordered_ids(&threads), ids(&[1, 2, 3, 4, 5]));
+
+        selection.select_all(&threads);
+        assert_eq!(selection.len(), 6);
+    }
+
+    #[test]
+    fn clearing_selection_resets_anchor() {
+        let threads = thread_list(4);
+        let mut selection = ThreadSelection::default();
+        selection.toggle(&threads, 0);
+        selection.select_range(&threads, 0, 1);
+
+        // Opening a thread with a plain click clears the selection.
+        selection.clear();
+        assert!(selection.is_empty());
+
+        selection.select_range(&threads, 2, 3);
+        assert_eq!(selection.ordered_ids(&threads), ids(&[2, 3]));
+    }
+
+    #[test]
+    fn scroll_offset_reveals_focused_row() {
+        // 400px viewport shows rows 0-4 of 80px each.
+        assert_eq!(scroll_offset_to_reveal(2, 0.0, 400.0, 80.0), 0.0);
+        assert_eq!(scroll_offset_to_reveal(4, 0.0, 400.0, 80.0), 0.0);
+        // Moving down past the bottom aligns the row with the bottom edge.
+        assert_eq!(scroll_offset_to_reveal(5, 0.0, 400.0, 80.0), 80.0);
+        assert_eq!(scroll_offset_to_reveal(9, 80.0, 400.0, 80.0), 400.0);
+        // Moving up past the top aligns the row with the top edge.
+        assert_eq!(scroll_offset_to_reveal(3, 400.0, 400.0, 80.0), 240.0);
+        // A partially visible row is scrolled fully into view.
+        assert_eq!(scroll_offset_to_reveal(1, 100.0, 400.0, 80.0), 80.0);
+        // Rows taller than the viewport show their top.
+        assert_eq!(scroll_offset_to_reveal(0, 0.0, 50.0, 80.0), 0.0);
+    }
+
+    #[test]
+    fn focus_after_done_advances_or_stays() {
+        // Archiving the second of five threads leaves four.
+        assert_eq!(focus_after_done(1, 4, AfterDone::Advance), Some((1, true)));
+        assert_eq!(focus_after_done(1, 4, AfterDone::Stay), Some((1, false)));
+        // Archiving the last thread moves up to the new last one.
+        assert_eq!(focus_after_done(4, 4, AfterDone::Advance), Some((3, true)));
+        assert_eq!(focus_after_done(4, 4, AfterDone::Stay), Some((3, false)));
+        // Nothing is left to focus once the list is empty.
+        assert_eq!(focus_after_done(0, 0, AfterDone::Advance), None);
+        assert_eq!(focus_after_done(0, 0, AfterDone::Stay), None);
+    }
+
+    #[test]
+    fn collapse_all_of_empty_thread() {
+        assert!(thread(&[]).latest_expanded().is_empty());
+    }
+
+    #[test]
+    fn message_source_splits_headers_at_the_first_blank_line() {
+        let raw = "Subject: Hi\r\nX-Folded: a\r\n b\r\n\r\nBody\r\n\r\nMore\r\n";
+        let source = MessageSource::new(raw.to_string());
+        assert_eq!(source.headers, "Subject: Hi\r\nX-Folded: a\r\n b");
+        assert_eq!(source.raw, raw);
+
+        let bare = MessageSource::new("Subject: Hi\n\nBody\n".to_string());
+        assert_eq!(bare.headers, "Subject: Hi");
+
+        let headers_only = MessageSource::new("Subject: Hi\r\n".to_string());
+        assert_eq!(headers_only.headers, "Subject: Hi\r\n");
+    }
+
+    #[test]
+    fn sending_account_is_the_chosen_one_or_else_the_first() {
+        use crate::domain::{FolderMapping, ProviderConfig, ProviderType};
+
+        let account = |id: &str| Account {
+            id: AccountId::from(id),
+            email: format!("{}@example.com", id),
+            display_name: None,
+            provider_type: ProviderType::Gmail,
+            provider_config: ProviderConfig::Gmail {},
+            sync_enabled: true,
+            sync_interval: std::time::Duration::from_secs(300),
+            signature: None,
+            signature_html: None,
+            folder_mapping: FolderMapping::default(),
+            aliases: vec![],
+        };
+        let accounts = || vec![account("home"), account("work")];
+        let sender = |id: Option<&str>| {
+            sending_account(accounts(), id.map(AccountId::from).as_ref()).map(|a| a.email)
+        };
+
+        assert_eq!(sender(Some("work")).as_deref(), Some("work@example.com"));
+        assert_eq!(sender(None).as_deref(), Some("home@example.com"));
+        assert_eq!(sender(Some("removed")).as_deref(), Some("home@example.com"));
+        assert!(sending_account(Vec::new(), None).is_none());
+    }
+
+    #[test]
+    fn recipients_are_split_on_commas() {
+        assert_eq!(
+            parse_recipients("alice@example.com, Bob <bob@example.com>,, nobody"),
+            vec![
+                Address::new("alice@example.com"),
+                Address::with_name("bob@example.com", "Bob"),
+            ]
+        );
+    }
+}
@@ -2,13 +2,14 @@
 //!
 //! Displays a virtualized list of email threads for the current view.
 
+use chrono::{DateTime, Local};
 use gpui::{
     div, prelude::FluentBuilder, px, ClickEvent, Context, FontWeight, InteractiveElement,
     IntoElement, ParentElement, Render, SharedString, StatefulInteractiveElement, Styled, Window,
 };
 
 use crate::app::ViewType;
-use crate::domain::ThreadId;
+use crate::domain::{format_relative, ThreadId, ThreadSummary};
 use crate::ui::components::VirtualizedListState;
 use crate::ui::theme::ThemeColors;
 
@@ -42,6 +43,30 @@ pub struct ThreadListItem {
     pub has_attachments: bool,
 }
 
+impl ThreadListItem {
+    /// Build a list item from a thread summary, with its date formatted
+    /// relative to `now`.
+    pub fn from_summary(summary: &ThreadSummary, now: DateTime<Local>) -> Self {
+        Self {
+            id: summary.id.clone(),
+            subject: summary.subject.clone().unwrap_or_default(),
+            sender_name: summary
+                .from
+                .name
+                .clone()
+                .unwrap_or_else(|| summary.from.email.clone()),
+            sender_email: summary.from.email.clone(),
+            snippet: summary.snippet.clone(),
+            timestamp: format_relative(summary.last_message_date, now),
+            is_unread: summary.unread_count > 0,
+            is_starred: summary.is_starred,
+            message_count: summary.message_count,
+            // Summaries don't track attachments.
+            has_attachments: false,
+        }
+    }
+}
+
 impl MessageList {
     /// Create a new message list.
     pub fn new(_cx: &mut Context<Self>) -> Self {
@@ -410,6 +435,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn thread_list_item_from_summary() {
+        use crate::domain::{AccountId, Address};
+        use chrono::{TimeZone, Utc};
+
+        let summary = ThreadSummary {
+            id: ThreadId::from("thread-1"),
+            account_id: AccountId::from("account-1"),
+            subject: Some("Quarterly report".to_string()),
+            snippet: "Numbers attached".to_string(),
+            from: Address::with_name("alice@example.com", "Alice"),
+            last_message_date: Utc.with_ymd_and_hms(2020, 6, 15, 12, 0, 0).unwrap(),
+            message_count: 3,
+            unread_count: 1,
+            is_starred: false,
+            labels: vec![],
+            muted: false,
+            watched: false,
+        };
+
+        let item = ThreadListItem::from_summary(&summary, Local::now());
+        assert_eq!(item.sender_name, "Alice");
+        assert!(item.is_unread);
+        assert!(item.timestamp.ends_with(", 2020"));
+    }
+
     #[test]
     fn thread_list_item() {
         let thread = make_thread("thread-1", "Test Subject", true);
@@ -6,6 +6,7 @@
 
 mod command_palette;
 mod composer;
+mod label_palette;
 mod main_window;
 mod message_list;
 mod reading_pane;
@@ -19,14 +20,15 @@ mod stats;
 mod stats_dashboard;
 
 pub use command_palette::{Command, CommandCategory, CommandPalette};
-pub use composer::{Composer, ComposerAttachment};
+pub use composer::{Composer, ComposerAttachment, FromAddresses};
+pub use label_palette::{LabelPalette, LabelPaletteEntry, LabelPaletteKey, LabelSelection};
 pub use main_window::MainWindow;
 pub use message_list::{MessageList, ThreadListItem};
 pub use reading_pane::{AttachmentInfo, MessageDetail, ReadingPane, ThreadDetail};
 pub use screener_queue::{ScreenerEntry, ScreenerQueue};
 pub use search_bar::{SearchBar, SearchOperator, SearchSuggestion};
 pub use settings::{SettingsSection, SettingsView};
-pub use settings_panel::{SettingsPanel, SettingsTab};
+pub use settings_panel::{FocusTarget, SettingKey, SettingsPanel, SettingsPanelEvent, SettingsTab};
 pub use sidebar::{Sidebar, SidebarAccount, SidebarLabel};
 pub use smart_views::{
     SmartViewCriteria, SmartViewEntry, SmartViewManager, SmartViewMatch, SmartViewType,
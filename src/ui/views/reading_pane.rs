@@ -2,16 +2,31 @@
 //!
 //! Displays the selected email thread with messages and actions.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+use chrono::{DateTime, Local};
 use gpui::{
-    div, prelude::FluentBuilder, px, ClickEvent, Context, FontWeight, InteractiveElement,
-    IntoElement, ParentElement, Render, SharedString, StatefulInteractiveElement, Styled, Window,
+    div, img, prelude::FluentBuilder, px, ClickEvent, Context, FontWeight, Image, ImageFormat,
+    InteractiveElement, IntoElement, ParentElement, Render, SharedString,
+    StatefulInteractiveElement, Styled, Window,
 };
 
-use crate::domain::{EmailId, ThreadId};
+use crate::app::ClientHandle;
+use crate::domain::{
+    data_url, detect_language, format_relative, language_name, referenced_content_ids,
+    resolve_image_sources, truncate_chars, Email, EmailId, ThreadId,
+};
+use crate::services::ImageBytes;
 use crate::ui::theme::ThemeColors;
 
+/// Size in pixels of the attachment thumbnails to request from
+/// [`AttachmentService::thumbnail`](crate::services::AttachmentService::thumbnail).
+pub const ATTACHMENT_THUMBNAIL_SIZE: u32 = 96;
+
+/// Size at which attachment thumbnails are shown.
+const THUMBNAIL_DISPLAY_SIZE: f32 = 48.0;
+
 /// Reading pane view component.
 pub struct ReadingPane {
     colors: ThemeColors,
@@ -19,6 +34,11 @@ pub struct ReadingPane {
     expanded_messages: HashSet<EmailId>,
     inline_composer_visible: bool,
     scroll_offset: f32,
+    /// Language translations are offered into, or `None` while AI
+    /// translation is turned off.
+    translate_to: Option<String>,
+    /// Translated bodies shown in place of the original, by message.
+    translations: HashMap<EmailId, String>,
 }
 
 /// Detailed thread data for display.
@@ -42,6 +62,116 @@ pub struct MessageDetail {
     pub body_html: Option<String>,
     pub attachments: Vec<AttachmentInfo>,
     pub is_unread: bool,
+    /// Detected ISO 639-1 language of the body, if known.
+    pub language: Option<String>,
+    /// Inline images referenced from the HTML body, in order.
+    pub inline_images: Vec<Arc<Image>>,
+}
+
+impl MessageDetail {
+    /// Build a message for display, with its date formatted relative to
+    /// `now`. Inline attachments are rendered in the body and not listed.
+    pub fn from_email(email: &Email, now: DateTime<Local>) -> Self {
+        Self {
+            id: email.id.clone(),
+            sender_name: email
+                .from
+                .name
+                .clone()
+                .unwrap_or_else(|| email.from.email.clone()),
+            sender_email: email.from.email.clone(),
+            recipients: email
+                .to
+                .iter()
+                .chain(&email.cc)
+                .map(|a| a.display())
+                .collect(),
+            timestamp: format_relative(email.date, now),
+            // An evicted body that couldn't be fetched again shows its snippet.
+            body_text: match &email.body_text {
+                Some(body) => body.clone(),
+                None if !email.has_body() => email.snippet.clone(),
+                None => String::new(),
+            },
+            body_html: email.body_html.clone(),
+            attachments: email
+                .attachments
+                .iter()
+                .filter(|a| !a.is_inline)
+                .map(|a| AttachmentInfo {
+                    id: a.id.clone(),
+                    filename: a.filename.clone(),
+                    size_bytes: a.size_bytes,
+                    content_type: a.content_type.clone(),
+                    thumbnail: None,
+                })
+                .collect(),
+            is_unread: !email.is_read,
+            language: detect_language(&email.new_content()).map(str::to_string),
+            inline_images: vec![],
+        }
+    }
+
+    /// Resolves the HTML body's `cid:` images to the downloaded content of
+    /// the email's inline parts, keyed by Content-ID.
+    ///
+    /// Inline parts are local and always shown. Remote images stay blocked
+    /// unless `allow_remote_images` mirrors the external images setting.
+    pub fn with_inline_parts(
+        mut self,
+        email: &Email,
+        parts: &HashMap<String, Vec<u8>>,
+        allow_remote_images: bool,
+    ) -> Self {
+        let Some(html) = &self.body_html else {
+            return self;
+        };
+        let by_content_id = email.attachments_by_content_id();
+
+        let mut data_urls = HashMap::new();
+        for content_id in referenced_content_ids(html) {
+            let (Some(attachment), Some(bytes)) = (
+                by_content_id.get(content_id.as_str()),
+                parts.get(&content_id),
+            ) else {
+                continue;
+            };
+            data_urls.insert(content_id, data_url(&attachment.content_type, bytes));
+            if let Some(format) = ImageFormat::from_mime_type(&attachment.content_type) {
+                self.inline_images
+                    .push(Arc::new(Image::from_bytes(format, bytes.clone())));
+            }
+        }
+
+        self.body_html = Some(resolve_image_sources(html, &data_urls, allow_remote_images));
+        self
+    }
+
+    /// Shows PNG thumbnails in place of the attachments' icons, keyed by
+    /// attachment ID. Attachments without one keep their icon.
+    pub fn with_thumbnails(mut self, thumbnails: &HashMap<String, ImageBytes>) -> Self {
+        for attachment in &mut self.attachments {
+            if let Some(png) = thumbnails.get(&attachment.id) {
+                attachment.thumbnail =
+                    Some(Arc::new(Image::from_bytes(ImageFormat::Png, png.clone())));
+            }
+        }
+        self
+    }
+
+    /// Returns the label for offering a translation into `user_language`,
+    /// e.g. "Translate to English", when the message is in another language.
+    pub fn translation_label(&self, user_language: &str) -> Option<String> {
+        let language = self.language.as_deref()?;
+        let user_primary = user_language.split(['-', '_']).next()?;
+        if language.eq_ignore_ascii_case(user_primary) {
+            return None;
+        }
+        Some(format!(
+            "Translate to {}",
+            language_name(user_language).unwrap_or(user_language)
+        ))
+    }
 }
 
 /// Attachment information.
@@ -51,6 +181,8 @@ pub struct AttachmentInfo {
     pub filename: String,
     pub size_bytes: u64,
     pub content_type: String,
+    /// Preview shown instead of the type icon, if one could be made.
+    pub thumbnail: Option<Arc<Image>>,
 }
 
 impl ReadingPane {
@@ -62,12 +194,15 @@ impl ReadingPane {
             expanded_messages: HashSet::new(),
             inline_composer_visible: false,
             scroll_offset: 0.0,
+            translate_to: None,
+            translations: HashMap::new(),
         }
     }
 
     /// Set the displayed thread.
     pub fn set_thread(&mut self, thread: Option<ThreadDetail>) {
         self.expanded_messages.clear();
+        self.translations.clear();
         self.inline_composer_visible = false;
         self.scroll_offset = 0.0;
 
@@ -104,6 +239,56 @@ impl ReadingPane {
         self.expanded_messages.clear();
     }
 
+    /// Set the language translations are offered into, e.g. the user's
+    /// locale. `None` hides the offer, for when AI translation is off.
+    pub fn set_translate_to(&mut self, language: Option<String>) {
+        self.translate_to = language;
+        if self.translate_to.is_none() {
+            self.translations.clear();
+        }
+    }
+
+    /// Show a message's translation in place of its body.
+    pub fn set_translation(&mut self, message_id: EmailId, translation: String) {
+        self.translations.insert(message_id, translation);
+    }
+
+    /// Show a message's original body again.
+    pub fn show_original(&mut self, message_id: &EmailId) {
+        self.translations.remove(message_id);
+    }
+
+    /// Translates a message with the client's AI service in the background
+    /// and shows the translation in place of its body.
+    fn translate(&mut self, message_id: &EmailId, cx: &mut Context<Self>) {
+        let (Some(thread), Some(language)) = (&self.thread, &self.translate_to) else {
+            return;
+        };
+        let Some(handle) = cx.try_global::<ClientHandle>() else {
+            return;
+        };
+
+        let client = handle.client.clone();
+        let thread_id = thread.id.clone();
+        let target = message_id.clone();
+        let language = language.clone();
+        let translated =
+            handle.spawn(async move { client.translate(&thread_id, &target, &language).await });
+        let message_id = message_id.clone();
+        cx.spawn(move |this, mut cx| async move {
+            let translated = translated.await;
+            this.update(&mut cx, |this, cx| {
+                match translated {
+                    Ok(translation) => this.set_translation(message_id, translation),
+                    Err(e) => tracing::warn!("Failed to translate message {}: {}", message_id, e),
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
     /// Show inline composer for reply.
     pub fn show_composer(&mut self) {
         self.inline_composer_visible = true;
@@ -348,13 +533,26 @@ impl ReadingPane {
                                 ),
                         ),
                 )
+                .when_some(
+                    self.render_translation_toggle(message, index, cx),
+                    |this, toggle| this.child(toggle),
+                )
                 .child(
-                    div()
-                        .text_color(text_primary)
-                        .child(SharedString::from(message.body_text.clone())),
+                    div().text_color(text_primary).child(SharedString::from(
+                        self.translations
+                            .get(&message.id)
+                            .unwrap_or(&message.body_text)
+                            .clone(),
+                    )),
+                )
+                .children(
+                    message
+                        .inline_images
+                        .iter()
+                        .map(|image| img(image.clone()).max_w_full().mt(px(12.0))),
                 )
                 .when(!message.attachments.is_empty(), |this| {
-                    this.child(self.render_attachments(&message.attachments))
+                    this.child(render_attachments(&message.attachments, &self.colors))
                 })
         } else {
             div()
@@ -433,83 +631,43 @@ impl ReadingPane {
         }
     }
 
-    fn render_attachments(&self, attachments: &[AttachmentInfo]) -> impl IntoElement {
-        let border_color = self.colors.border;
-        let surface = self.colors.surface;
-        let text_primary = self.colors.text_primary;
-        let text_muted = self.colors.text_muted;
-        let accent = self.colors.accent;
+    fn render_translation_toggle(
+        &self,
+        message: &MessageDetail,
+        index: usize,
+        cx: &mut Context<Self>,
+    ) -> Option<impl IntoElement> {
+        let label = if self.translations.contains_key(&message.id) {
+            "Show original".to_string()
+        } else {
+            message.translation_label(self.translate_to.as_deref()?)?
+        };
 
-        div()
-            .mt(px(16.0))
-            .pt(px(12.0))
-            .border_t_1()
-            .border_color(border_color)
-            .child(
-                div().flex().items_center().gap(px(8.0)).mb(px(8.0)).child(
-                    div()
-                        .text_sm()
-                        .text_color(text_muted)
-                        .child(SharedString::from(format!(
-                            "{} attachment{}",
-                            attachments.len(),
-                            if attachments.len() == 1 { "" } else { "s" }
-                        ))),
-                ),
-            )
-            .child(
-                div()
-                    .flex()
-                    .flex_wrap()
-                    .gap(px(8.0))
-                    .children(attachments.iter().map(|att| {
-                        let icon = match att.content_type.as_str() {
-                            t if t.starts_with("image/") => "[IMG]",
-                            t if t.starts_with("video/") => "[VID]",
-                            t if t.starts_with("audio/") => "[AUD]",
-                            "application/pdf" => "[PDF]",
-                            t if t.contains("zip") || t.contains("tar") || t.contains("rar") => {
-                                "[ZIP]"
-                            }
-                            t if t.contains("word") || t.contains("document") => "[DOC]",
-                            t if t.contains("sheet") || t.contains("excel") => "[XLS]",
-                            _ => "[FILE]",
-                        };
+        let message_id = message.id.clone();
+        let click_handler = cx.listener(move |this, _: &ClickEvent, _, cx| {
+            if this.translations.contains_key(&message_id) {
+                this.show_original(&message_id);
+            } else {
+                this.translate(&message_id, cx);
+            }
+            cx.notify();
+        });
+        let hover_bg = self.colors.surface_elevated;
 
-                        div()
-                            .flex()
-                            .items_center()
-                            .gap(px(8.0))
-                            .px(px(12.0))
-                            .py(px(8.0))
-                            .rounded(px(6.0))
-                            .bg(surface)
-                            .cursor_pointer()
-                            .hover(move |style| style.bg(accent.opacity(0.1)))
-                            .child(
-                                div()
-                                    .text_xs()
-                                    .text_color(accent)
-                                    .font_weight(FontWeight::BOLD)
-                                    .child(SharedString::from(icon.to_string())),
-                            )
-                            .child(
-                                div()
-                                    .child(
-                                        div()
-                                            .text_sm()
-                                            .text_color(text_primary)
-                                            .child(SharedString::from(att.filename.clone())),
-                                    )
-                                    .child(
-                                        div()
-                                            .text_xs()
-                                            .text_color(text_muted)
-                                            .child(SharedString::from(format_size(att.size_bytes))),
-                                    ),
-                            )
-                    })),
-            )
+        Some(
+            div()
+                .id(SharedString::from(format!("msg-translate-{}", index)))
+                .mb(px(8.0))
+                .px(px(8.0))
+                .py(px(4.0))
+                .rounded(px(4.0))
+                .text_sm()
+                .text_color(self.colors.accent)
+                .cursor_pointer()
+                .hover(move |style| style.bg(hover_bg))
+                .on_click(click_handler)
+                .child(SharedString::from(label)),
+        )
     }
 
     fn render_inline_composer(&self) -> impl IntoElement {
@@ -534,6 +692,94 @@ impl ReadingPane {
     }
 }
 
+/// Renders the list of a message's attachments, each with its thumbnail or
+/// a type label.
+pub(crate) fn render_attachments(
+    attachments: &[AttachmentInfo],
+    colors: &ThemeColors,
+) -> impl IntoElement {
+    let border_color = colors.border;
+    let surface = colors.surface;
+    let text_primary = colors.text_primary;
+    let text_muted = colors.text_muted;
+    let accent = colors.accent;
+
+    div()
+        .mt(px(16.0))
+        .pt(px(12.0))
+        .border_t_1()
+        .border_color(border_color)
+        .child(
+            div().flex().items_center().gap(px(8.0)).mb(px(8.0)).child(
+                div()
+                    .text_sm()
+                    .text_color(text_muted)
+                    .child(SharedString::from(format!(
+                        "{} attachment{}",
+                        attachments.len(),
+                        if attachments.len() == 1 { "" } else { "s" }
+                    ))),
+            ),
+        )
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .gap(px(8.0))
+                .children(attachments.iter().map(|att| {
+                    let label = match att.content_type.as_str() {
+                        t if t.starts_with("image/") => "[IMG]",
+                        t if t.starts_with("video/") => "[VID]",
+                        t if t.starts_with("audio/") => "[AUD]",
+                        "application/pdf" => "[PDF]",
+                        t if t.contains("zip") || t.contains("tar") || t.contains("rar") => "[ZIP]",
+                        t if t.contains("word") || t.contains("document") => "[DOC]",
+                        t if t.contains("sheet") || t.contains("excel") => "[XLS]",
+                        _ => "[FILE]",
+                    };
+
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap(px(8.0))
+                        .px(px(12.0))
+                        .py(px(8.0))
+                        .rounded(px(6.0))
+                        .bg(surface)
+                        .cursor_pointer()
+                        .hover(move |style| style.bg(accent.opacity(0.1)))
+                        .child(match &att.thumbnail {
+                            Some(thumbnail) => img(thumbnail.clone())
+                                .w(px(THUMBNAIL_DISPLAY_SIZE))
+                                .h(px(THUMBNAIL_DISPLAY_SIZE))
+                                .rounded(px(4.0))
+                                .into_any_element(),
+                            None => div()
+                                .text_xs()
+                                .text_color(accent)
+                                .font_weight(FontWeight::BOLD)
+                                .child(SharedString::from(label.to_string()))
+                                .into_any_element(),
+                        })
+                        .child(
+                            div()
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(text_primary)
+                                        .child(SharedString::from(att.filename.clone())),
+                                )
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(text_muted)
+                                        .child(SharedString::from(format_size(att.size_bytes))),
+                                ),
+                        )
+                })),
+        )
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -548,11 +794,7 @@ fn format_size(bytes: u64) -> String {
 }
 
 fn truncate_text(text: &str, max_len: usize) -> String {
-    if text.len() <= max_len {
-        text.to_string()
-    } else {
-        format!("{}...", &text[..max_len])
-    }
+    truncate_chars(text, max_len)
 }
 
 impl Render for ReadingPane {
@@ -621,6 +863,118 @@ mod tests {
         assert_eq!(truncate_text("this is a long text", 10), "this is a ...");
     }
 
+    #[test]
+    fn truncate_text_multibyte() {
+        assert_eq!(truncate_text("Résumé ✅ attached", 8), "Résumé ✅...");
+    }
+
+    #[test]
+    fn message_detail_from_email() {
+        use crate::domain::{test_email, Address, Attachment};
+
+        let attachment = |id: &str, is_inline| Attachment {
+            id: id.to_string(),
+            filename: format!("{id}.png"),
+            content_type: "image/png".to_string(),
+            size_bytes: 10,
+            is_inline,
+            content_hash: None,
+            content_id: None,
+        };
+        let email = Email {
+            from: Address::new("bob@example.com"),
+            to: vec![Address::with_name("alice@example.com", "Alice")],
+            cc: vec![Address::new("carol@example.com")],
+            body_text: Some("Hi there".to_string()),
+            snippet: "Hi there".to_string(),
+            is_read: true,
+            attachments: vec![attachment("logo", true), attachment("chart", false)],
+            ..test_email("email-1", "thread-1")
+        };
+
+        let detail = MessageDetail::from_email(&email, Local::now());
+        assert_eq!(detail.sender_name, "bob@example.com");
+        assert_eq!(
+            detail.recipients,
+            vec!["Alice <alice@example.com>", "carol@example.com"]
+        );
+        assert!(detail.timestamp.ends_with('M'));
+        assert_eq!(detail.attachments.len(), 1);
+        assert_eq!(detail.attachments[0].id, "chart");
+        assert!(!detail.is_unread);
+        assert_eq!(detail.language, None);
+        assert!(detail.attachments[0].thumbnail.is_none());
+
+        let thumbnails = HashMap::from([("chart".to_string(), b"\x89PNG".to_vec())]);
+        let detail = detail.with_thumbnails(&thumbnails);
+        assert!(detail.attachments[0].thumbnail.is_some());
+    }
+
+    #[test]
+    fn inline_parts_resolve_cid_images() {
+        use crate::domain::{test_email, Address, Attachment};
+
+        let email = Email {
+            from: Address::new("news@example.com"),
+            to: vec![],
+            subject: None,
+            body_text: None,
+            body_html: Some(
+                r#"<img src="cid:logo@example.com"><img src="https://example.com/t.gif">"#
+                    .to_string(),
+            ),
+            snippet: String::new(),
+            is_read: true,
+            attachments: vec![Attachment {
+                id: "logo".to_string(),
+                filename: "logo.png".to_string(),
+                content_type: "image/png".to_string(),
+                size_bytes: 3,
+                is_inline: true,
+                content_hash: Some("hash".to_string()),
+                content_id: Some("logo@example.com".to_string()),
+            }],
+            ..test_email("email-1", "thread-1")
+        };
+        let parts = HashMap::from([("logo@example.com".to_string(), b"png".to_vec())]);
+
+        let detail = MessageDetail::from_email(&email, Local::now())
+            .with_inline_parts(&email, &parts, false);
+
+        assert_eq!(
+            detail.body_html.as_deref(),
+            Some(r#"<img src="data:image/png;base64,cG5n"><img src="">"#)
+        );
+        assert_eq!(detail.inline_images.len(), 1);
+        assert!(detail.attachments.is_empty());
+    }
+
+    #[test]
+    fn translation_label_for_other_languages() {
+        let mut detail = MessageDetail {
+            id: EmailId::from("email-1"),
+            sender_name: "Ana".to_string(),
+            sender_email: "ana@example.com".to_string(),
+            recipients: vec![],
+            timestamp: String::new(),
+            body_text: String::new(),
+            body_html: None,
+            attachments: vec![],
+            is_unread: false,
+            language: Some("es".to_string()),
+            inline_images: vec![],
+        };
+
+        assert_eq!(
+            detail.translation_label("en-US").as_deref(),
+            Some("Translate to English")
+        );
+        assert_eq!(detail.translation_label("es"), None);
+
+        detail.language = None;
+        assert_eq!(detail.translation_label("en"), None);
+    }
+
     #[test]
     fn attachment_info() {
         let attachment = AttachmentInfo {
@@ -628,6 +982,7 @@ mod tests {
             filename: "document.pdf".to_string(),
             size_bytes: 1024 * 100,
             content_type: "application/pdf".to_string(),
+            thumbnail: None,
         };
 
         assert_eq!(attachment.filename, "document.pdf");
@@ -641,6 +996,8 @@ mod tests {
             expanded_messages: HashSet::new(),
             inline_composer_visible: false,
             scroll_offset: 0.0,
+            translate_to: None,
+            translations: HashMap::new(),
         };
 
         let msg_id = EmailId::from("msg-1");
@@ -6,10 +6,20 @@
 //! - Keyboard shortcuts
 //! - Theme and appearance
 //! - Sync preferences
+//!
+//! While open, the panel holds keyboard focus: Tab and Shift-Tab cycle
+//! through its controls, Enter or Space activates the focused one, and
+//! Escape closes it, asking first when there are unsaved changes.
+//!
+//! The search box filters settings across all tabs by label and description,
+//! switching to the tab of the first match.
+
+use crate::ui::components::{KeyInputResult, TextBuffer};
+use crate::ui::keybindings::{KeybindingManager, Preset};
 
 use gpui::{
-    div, prelude::*, px, rgba, Context, InteractiveElement, IntoElement, ParentElement, Render,
-    SharedString, Styled, Window,
+    div, prelude::*, px, rgba, ClickEvent, Context, EventEmitter, FocusHandle, InteractiveElement,
+    IntoElement, KeyDownEvent, ParentElement, Render, SharedString, Styled, Window,
 };
 
 /// Settings tab categories.
@@ -68,6 +78,9 @@ impl SettingsTab {
     }
 }
 
+/// Key identifying a setting, e.g. `block_tracking`.
+pub type SettingKey = String;
+
 /// A toggle setting.
 #[derive(Debug, Clone)]
 pub struct ToggleSetting {
@@ -148,6 +161,40 @@ impl SelectOption {
     }
 }
 
+/// A control that can hold keyboard focus in the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FocusTarget {
+    /// The close button.
+    Close,
+    /// The search box.
+    Search,
+    /// A tab in the sidebar.
+    Tab(SettingsTab),
+    /// A toggle setting, by key.
+    Toggle(String),
+    /// A select setting, by key. Activating it picks the next option.
+    Select(String),
+    /// The Cancel button, discarding changes.
+    Cancel,
+    /// The Save Changes button.
+    Save,
+    /// Confirms discarding unsaved changes on close.
+    Discard,
+    /// Goes back to editing instead of discarding changes.
+    KeepEditing,
+}
+
+/// Events emitted by the settings panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsPanelEvent {
+    /// Changes were saved and the panel closed. The new values are read
+    /// with [`SettingsPanel::toggle_value`] and
+    /// [`SettingsPanel::select_value`].
+    Saved,
+    /// The panel closed without saving.
+    Closed,
+}
+
 /// The settings panel view component.
 pub struct SettingsPanel {
     /// Whether the panel is visible.
@@ -158,19 +205,53 @@ pub struct SettingsPanel {
     toggles: Vec<(SettingsTab, Vec<ToggleSetting>)>,
     /// Select settings by category.
     selects: Vec<(SettingsTab, Vec<SelectSetting>)>,
+    /// Toggle values as last saved, restored when changes are discarded.
+    saved_toggles: Vec<(SettingsTab, Vec<ToggleSetting>)>,
+    /// Select values as last saved.
+    saved_selects: Vec<(SettingsTab, Vec<SelectSetting>)>,
     /// Whether there are unsaved changes.
     has_changes: bool,
+    /// Settings search query.
+    search: TextBuffer,
+    /// Settings matching the search query.
+    search_results: Vec<(SettingsTab, SettingKey)>,
+    /// Whether closing waits for the user to confirm discarding changes.
+    confirming_discard: bool,
+    /// Index of the focused control in [`Self::focus_targets`].
+    focused: usize,
+    focus_handle: Option<FocusHandle>,
+    /// Whether to take keyboard focus on the next render.
+    needs_focus: bool,
 }
 
+impl EventEmitter<SettingsPanelEvent> for SettingsPanel {}
+
 impl SettingsPanel {
     /// Creates a new settings panel.
-    pub fn new(_window: &mut Window, _cx: &mut Context<Self>) -> Self {
+    pub fn new(_window: &mut Window, cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: Some(cx.focus_handle()),
+            ..Self::with_defaults()
+        }
+    }
+
+    fn with_defaults() -> Self {
+        let toggles = Self::default_toggles();
+        let selects = Self::default_selects();
         Self {
             visible: false,
             current_tab: SettingsTab::General,
-            toggles: Self::default_toggles(),
-            selects: Self::default_selects(),
+            saved_toggles: toggles.clone(),
+            saved_selects: selects.clone(),
+            toggles,
+            selects,
             has_changes: false,
+            search: TextBuffer::new(),
+            search_results: vec![],
+            confirming_discard: false,
+            focused: 0,
+            focus_handle: None,
+            needs_focus: false,
         }
     }
 
@@ -196,7 +277,9 @@ impl SettingsPanel {
                     ToggleSetting::new("ai_enabled", "Enable AI Features", true)
                         .with_description("Use AI for summaries, drafts, and search"),
                     ToggleSetting::new("auto_summarize", "Auto-Summarize Threads", true)
-                        .with_description("Automatically generate summaries for long threads"),
+                        .with_description(
+                            "Summarize threads above the message and length thresholds",
+                        ),
                     ToggleSetting::new("smart_compose", "Smart Compose", true)
                         .with_description("AI-powered writing suggestions"),
                     ToggleSetting::new("semantic_search", "Semantic Search", true)
@@ -270,6 +353,27 @@ impl SettingsPanel {
                         ],
                         "casual",
                     ),
+                    SelectSetting::new(
+                        "auto_summarize_min_messages",
+                        "Auto-Summarize After",
+                        vec![
+                            SelectOption::new("2", "2 messages"),
+                            SelectOption::new("3", "3 messages"),
+                            SelectOption::new("5", "5 messages"),
+                            SelectOption::new("10", "10 messages"),
+                        ],
+                        "3",
+                    ),
+                    SelectSetting::new(
+                        "auto_summarize_min_chars",
+                        "Auto-Summarize Length",
+                        vec![
+                            SelectOption::new("500", "500 characters"),
+                            SelectOption::new("1500", "1,500 characters"),
+                            SelectOption::new("5000", "5,000 characters"),
+                        ],
+                        "1500",
+                    ),
                 ],
             ),
             (
@@ -307,6 +411,18 @@ impl SettingsPanel {
                     ),
                 ],
             ),
+            (
+                SettingsTab::Keybindings,
+                vec![SelectSetting::new(
+                    "keybinding_preset",
+                    "Shortcut Preset",
+                    Preset::ALL
+                        .iter()
+                        .map(|preset| SelectOption::new(preset.key(), preset.name()))
+                        .collect(),
+                    Preset::default().key(),
+                )],
+            ),
             (
                 SettingsTab::Sync,
                 vec![SelectSetting::new(
@@ -324,16 +440,87 @@ impl SettingsPanel {
         ]
     }
 
-    /// Opens the settings panel.
+    /// Opens the settings panel and takes keyboard focus.
     pub fn open(&mut self) {
         self.visible = true;
         self.current_tab = SettingsTab::General;
+        self.saved_toggles = self.toggles.clone();
+        self.saved_selects = self.selects.clone();
         self.has_changes = false;
+        self.confirming_discard = false;
+        self.clear_search();
+        self.focused = 1;
+        self.needs_focus = true;
     }
 
-    /// Closes the settings panel.
+    /// Closes the settings panel, keeping any unsaved changes.
     pub fn close(&mut self) {
         self.visible = false;
+        self.confirming_discard = false;
+    }
+
+    /// Closes the panel unless there are unsaved changes, in which case it
+    /// asks to confirm discarding them. Returns whether the panel closed.
+    pub fn request_close(&mut self) -> bool {
+        if self.has_changes {
+            self.confirming_discard = true;
+            self.focused = 0;
+            false
+        } else {
+            self.close();
+            true
+        }
+    }
+
+    /// Returns whether closing is waiting for confirmation to discard
+    /// unsaved changes.
+    pub fn is_confirming_discard(&self) -> bool {
+        self.confirming_discard
+    }
+
+    /// Returns whether there are unsaved changes.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.has_changes
+    }
+
+    /// Restores the settings as last saved and closes the panel.
+    pub fn discard_changes(&mut self) {
+        self.toggles = self.saved_toggles.clone();
+        self.selects = self.saved_selects.clone();
+        self.has_changes = false;
+        self.close();
+    }
+
+    /// Goes back to editing instead of discarding changes.
+    pub fn keep_editing(&mut self) {
+        self.confirming_discard = false;
+        self.focused = 0;
+    }
+
+    /// Keeps the changes and closes the panel.
+    pub fn save(&mut self) {
+        self.saved_toggles = self.toggles.clone();
+        self.saved_selects = self.selects.clone();
+        self.has_changes = false;
+        self.close();
+    }
+
+    /// Returns the value of a toggle setting.
+    pub fn toggle_value(&self, key: &str) -> Option<bool> {
+        self.toggles
+            .iter()
+            .flat_map(|(_, settings)| settings)
+            .find(|setting| setting.key == key)
+            .map(|setting| setting.enabled)
+    }
+
+    /// Returns the selected value of a select setting.
+    pub fn select_value(&self, key: &str) -> Option<&str> {
+        self.selects
+            .iter()
+            .flat_map(|(_, settings)| settings)
+            .find(|setting| setting.key == key)
+            .map(|setting| setting.selected.as_str())
     }
 
     /// Returns whether the panel is visible.
@@ -372,6 +559,256 @@ impl SettingsPanel {
         }
     }
 
+    /// Returns the settings whose label or description contains `query`,
+    /// ignoring case, in tab order.
+    pub fn search(&self, query: &str) -> Vec<(SettingsTab, SettingKey)> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+        let matches = |text: &str| text.to_lowercase().contains(&query);
+
+        let mut results = vec![];
+        for tab in SettingsTab::all() {
+            let toggles = self.toggles.iter().filter(|(t, _)| t == tab);
+            for (_, settings) in toggles {
+                results.extend(
+                    settings
+                        .iter()
+                        .filter(|s| {
+                            matches(&s.label) || s.description.as_deref().is_some_and(matches)
+                        })
+                        .map(|s| (*tab, s.key.clone())),
+                );
+            }
+            let selects = self.selects.iter().filter(|(t, _)| t == tab);
+            for (_, settings) in selects {
+                results.extend(
+                    settings
+                        .iter()
+                        .filter(|s| matches(&s.label))
+                        .map(|s| (*tab, s.key.clone())),
+                );
+            }
+        }
+        results
+    }
+
+    /// Returns the search query.
+    pub fn search_query(&self) -> &str {
+        self.search.text()
+    }
+
+    /// Sets the search query and switches to the tab of the first match.
+    pub fn set_search_query(&mut self, query: &str) {
+        self.search.set_text(query);
+        self.update_search();
+    }
+
+    /// Clears the search query.
+    pub fn clear_search(&mut self) {
+        self.search.clear();
+        self.search_results.clear();
+    }
+
+    /// Returns the settings matching the search query.
+    pub fn search_results(&self) -> &[(SettingsTab, SettingKey)] {
+        &self.search_results
+    }
+
+    fn update_search(&mut self) {
+        self.search_results = self.search(self.search.text());
+        if let Some((tab, _)) = self.search_results.first() {
+            self.current_tab = *tab;
+        }
+    }
+
+    fn is_search_match(&self, key: &str) -> bool {
+        self.search_results.iter().any(|(_, k)| k == key)
+    }
+
+    /// Handles a key typed into the search box. Returns whether it was
+    /// consumed.
+    fn handle_search_key(&mut self, key: &str, shift: bool) -> bool {
+        match self.search.process_key(key, shift, false, false) {
+            KeyInputResult::TextChanged => {
+                self.update_search();
+                true
+            }
+            KeyInputResult::Consumed => true,
+            KeyInputResult::Cancel if !self.search.is_empty() => {
+                self.clear_search();
+                true
+            }
+            KeyInputResult::Submit => {
+                // Move to the first match.
+                if let Some((_, key)) = self.search_results.first() {
+                    let target = if self.current_toggles().iter().any(|s| &s.key == key) {
+                        FocusTarget::Toggle(key.clone())
+                    } else {
+                        FocusTarget::Select(key.clone())
+                    };
+                    if let Some(index) = self.focus_targets().iter().position(|t| *t == target) {
+                        self.focused = index;
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Selects the option after the current one, wrapping around.
+    fn cycle_select(&mut self, key: &str) {
+        let next = self
+            .selects
+            .iter()
+            .flat_map(|(_, settings)| settings)
+            .find(|setting| setting.key == key)
+            .filter(|setting| !setting.options.is_empty())
+            .and_then(|setting| {
+                let current = setting
+                    .options
+                    .iter()
+                    .position(|option| option.value == setting.selected)
+                    .unwrap_or(setting.options.len() - 1);
+                setting
+                    .options
+                    .get((current + 1) % setting.options.len())
+                    .map(|option| option.value.clone())
+            });
+        if let Some(value) = next {
+            self.set_select(key, value);
+        }
+    }
+
+    /// Controls that can take focus, in Tab order. Only the confirmation
+    /// buttons can while it is shown.
+    pub fn focus_targets(&self) -> Vec<FocusTarget> {
+        if self.confirming_discard {
+            return vec![FocusTarget::KeepEditing, FocusTarget::Discard];
+        }
+
+        let mut targets = vec![FocusTarget::Close, FocusTarget::Search];
+        targets.extend(SettingsTab::all().iter().copied().map(FocusTarget::Tab));
+        targets.extend(
+            self.current_toggles()
+                .iter()
+                .map(|setting| FocusTarget::Toggle(setting.key.clone())),
+        );
+        targets.extend(
+            self.current_selects()
+                .iter()
+                .map(|setting| FocusTarget::Select(setting.key.clone())),
+        );
+        if self.has_changes {
+            targets.extend([FocusTarget::Cancel, FocusTarget::Save]);
+        }
+        targets
+    }
+
+    /// Returns the focused control.
+    pub fn focused_target(&self) -> Option<FocusTarget> {
+        let targets = self.focus_targets();
+        targets.get(self.focused % targets.len()).cloned()
+    }
+
+    /// Moves focus to the next control, wrapping around.
+    pub fn focus_next(&mut self) {
+        let len = self.focus_targets().len();
+        self.focused = (self.focused % len + 1) % len;
+    }
+
+    /// Moves focus to the previous control, wrapping around.
+    pub fn focus_previous(&mut self) {
+        let len = self.focus_targets().len();
+        self.focused = (self.focused % len + len - 1) % len;
+    }
+
+    /// Activates a control, as a click or Enter would, and focuses it.
+    /// Returns the event to emit, if any.
+    pub fn activate(&mut self, target: &FocusTarget) -> Option<SettingsPanelEvent> {
+        let event = match target {
+            FocusTarget::Close => self.request_close().then_some(SettingsPanelEvent::Closed),
+            FocusTarget::Search => None,
+            FocusTarget::Tab(tab) => {
+                self.set_tab(*tab);
+                None
+            }
+            FocusTarget::Toggle(key) => {
+                self.toggle_setting(key);
+                None
+            }
+            FocusTarget::Select(key) => {
+                self.cycle_select(key);
+                None
+            }
+            FocusTarget::Cancel | FocusTarget::Discard => {
+                self.discard_changes();
+                Some(SettingsPanelEvent::Closed)
+            }
+            FocusTarget::Save => {
+                self.save();
+                Some(SettingsPanelEvent::Saved)
+            }
+            FocusTarget::KeepEditing => {
+                self.keep_editing();
+                None
+            }
+        };
+        if let Some(index) = self.focus_targets().iter().position(|t| t == target) {
+            self.focused = index;
+        }
+        event
+    }
+
+    /// Handles a key press while the panel has focus. Returns the event to
+    /// emit, if any.
+    pub fn handle_key(&mut self, key: &str, shift: bool) -> Option<SettingsPanelEvent> {
+        if self.focused_target() == Some(FocusTarget::Search) && self.handle_search_key(key, shift)
+        {
+            return None;
+        }
+
+        match key {
+            "escape" if self.confirming_discard => {
+                self.keep_editing();
+                None
+            }
+            "escape" => self.activate(&FocusTarget::Close),
+            "tab" if shift => {
+                self.focus_previous();
+                None
+            }
+            "tab" => {
+                self.focus_next();
+                None
+            }
+            "enter" | "space" => {
+                let target = self.focused_target()?;
+                self.activate(&target)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_focused(&self, target: &FocusTarget) -> bool {
+        self.focused_target().as_ref() == Some(target)
+    }
+
+    /// Click handler activating a control.
+    fn on_activate(
+        target: FocusTarget,
+        cx: &mut Context<Self>,
+    ) -> impl Fn(&ClickEvent, &mut Window, &mut gpui::App) + 'static {
+        cx.listener(move |this, _: &ClickEvent, _, cx| {
+            if let Some(event) = this.activate(&target) {
+                cx.emit(event);
+            }
+            cx.notify();
+        })
+    }
+
     /// Returns toggle settings for the current tab.
     fn current_toggles(&self) -> &[ToggleSetting] {
         self.toggles
@@ -390,8 +827,14 @@ impl SettingsPanel {
             .unwrap_or(&[])
     }
 
-    fn render_tab(&self, tab: SettingsTab, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_tab(&self, tab: SettingsTab, cx: &mut Context<Self>) -> impl IntoElement {
         let is_selected = tab == self.current_tab;
+        let target = FocusTarget::Tab(tab);
+        let matches = self
+            .search_results
+            .iter()
+            .filter(|(t, _)| *t == tab)
+            .count();
 
         div()
             .id(SharedString::from(format!("tab-{:?}", tab)))
@@ -404,6 +847,9 @@ impl SettingsPanel {
             .rounded(px(6.0))
             .when(is_selected, |d| d.bg(rgba(0x3B82F620)))
             .when(!is_selected, |d| d.hover(|d| d.bg(rgba(0xFFFFFF08))))
+            .border_1()
+            .border_color(focus_ring(self.is_focused(&target)))
+            .on_click(Self::on_activate(target, cx))
             .child(
                 div()
                     .text_sm()
@@ -418,12 +864,57 @@ impl SettingsPanel {
                     .when(!is_selected, |d| d.text_color(rgba(0xA1A1AAFF)))
                     .child(tab.name()),
             )
+            .when(matches > 0, |d| {
+                d.child(
+                    div()
+                        .ml_auto()
+                        .text_xs()
+                        .text_color(rgba(0x3B82F6FF))
+                        .child(matches.to_string()),
+                )
+            })
     }
 
-    fn render_toggle(&self, setting: &ToggleSetting, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_search(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let query = self.search.text();
+        let is_focused = self.is_focused(&FocusTarget::Search);
+
+        div()
+            .id("settings-search")
+            .mx(px(8.0))
+            .mt(px(8.0))
+            .h(px(32.0))
+            .px(px(10.0))
+            .flex()
+            .items_center()
+            .rounded(px(6.0))
+            .bg(rgba(0x27272AFF))
+            .border_1()
+            .border_color(focus_ring(is_focused))
+            .cursor_text()
+            .on_click(Self::on_activate(FocusTarget::Search, cx))
+            .child(
+                div()
+                    .text_sm()
+                    .when(query.is_empty(), |d| {
+                        d.text_color(rgba(0x71717AFF)).child("Search settings")
+                    })
+                    .when(!query.is_empty(), |d| {
+                        d.text_color(rgba(0xE4E4E7FF)).child(query.to_string())
+                    })
+                    .when(is_focused, |d| d.child("|")),
+            )
+    }
+
+    fn render_toggle(&self, setting: &ToggleSetting, cx: &mut Context<Self>) -> impl IntoElement {
+        let target = FocusTarget::Toggle(setting.key.clone());
+
         div()
             .id(SharedString::from(format!("toggle-{}", setting.key)))
             .py(px(12.0))
+            .when(self.is_search_match(&setting.key), |d| {
+                d.px(px(8.0)).rounded(px(6.0)).bg(rgba(0x3B82F615))
+            })
             .flex()
             .items_center()
             .gap(px(12.0))
@@ -453,6 +944,9 @@ impl SettingsPanel {
                     .h(px(24.0))
                     .rounded_full()
                     .cursor_pointer()
+                    .border_1()
+                    .border_color(focus_ring(self.is_focused(&target)))
+                    .on_click(Self::on_activate(target, cx))
                     .when(setting.enabled, |d| d.bg(rgba(0x3B82F6FF)))
                     .when(!setting.enabled, |d| d.bg(rgba(0x3F3F46FF)))
                     .child(
@@ -467,7 +961,8 @@ impl SettingsPanel {
             )
     }
 
-    fn render_select(&self, setting: &SelectSetting, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_select(&self, setting: &SelectSetting, cx: &mut Context<Self>) -> impl IntoElement {
+        let target = FocusTarget::Select(setting.key.clone());
         let selected_label = setting
             .options
             .iter()
@@ -478,6 +973,9 @@ impl SettingsPanel {
         div()
             .id(SharedString::from(format!("select-{}", setting.key)))
             .py(px(12.0))
+            .when(self.is_search_match(&setting.key), |d| {
+                d.px(px(8.0)).rounded(px(6.0)).bg(rgba(0x3B82F615))
+            })
             .flex()
             .items_center()
             .gap(px(12.0))
@@ -503,6 +1001,9 @@ impl SettingsPanel {
                     .justify_between()
                     .cursor_pointer()
                     .hover(|d| d.bg(rgba(0x3F3F46FF)))
+                    .border_1()
+                    .border_color(focus_ring(self.is_focused(&target)))
+                    .on_click(Self::on_activate(target, cx))
                     .child(
                         div()
                             .text_sm()
@@ -514,21 +1015,32 @@ impl SettingsPanel {
     }
 
     fn render_keybindings_content(&self, _cx: &mut Context<Self>) -> impl IntoElement {
-        let bindings = vec![
-            ("Compose", "C"),
-            ("Reply", "R"),
-            ("Reply All", "Shift+R"),
-            ("Forward", "F"),
-            ("Archive", "E"),
-            ("Delete", "#"),
-            ("Star", "S"),
-            ("Mark Read", "Shift+U"),
-            ("Go to Inbox", "G I"),
-            ("Go to Starred", "G S"),
-            ("Search", "/"),
-            ("Command Palette", "Cmd+K"),
-            ("Settings", "Cmd+,"),
-        ];
+        let preset = self
+            .select_value("keybinding_preset")
+            .and_then(Preset::from_key)
+            .unwrap_or_default();
+        let manager = KeybindingManager::with_preset(preset);
+        let bindings: Vec<(&str, String)> = [
+            ("Compose", "compose"),
+            ("Reply", "reply"),
+            ("Reply All", "reply_all"),
+            ("Forward", "forward"),
+            ("Archive", "archive"),
+            ("Delete", "trash"),
+            ("Star", "star"),
+            ("Mark Read", "mark_read"),
+            ("Go to Inbox", "go_inbox"),
+            ("Go to Starred", "go_starred"),
+            ("Search", "search"),
+            ("Command Palette", "command_palette"),
+            ("Settings", "settings"),
+        ]
+        .into_iter()
+        .filter_map(|(name, command)| {
+            let (_, binding) = manager.bindings_for_command(command).into_iter().next()?;
+            Some((name, binding.display()))
+        })
+        .collect();
 
         div()
             .flex()
@@ -556,16 +1068,64 @@ impl SettingsPanel {
                             .rounded(px(4.0))
                             .text_xs()
                             .text_color(rgba(0xA1A1AAFF))
-                            .child(*shortcut),
+                            .child(shortcut.clone()),
                     )
             }))
     }
 
+    fn render_discard_confirmation(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let button = |id: &'static str, label: &'static str, target: FocusTarget| {
+            div()
+                .id(id)
+                .px(px(16.0))
+                .h(px(36.0))
+                .flex()
+                .items_center()
+                .rounded(px(6.0))
+                .cursor_pointer()
+                .bg(rgba(0x27272AFF))
+                .hover(|d| d.bg(rgba(0x3F3F46FF)))
+                .border_1()
+                .border_color(focus_ring(self.is_focused(&target)))
+                .on_click(Self::on_activate(target, cx))
+                .child(div().text_sm().text_color(rgba(0xE4E4E7FF)).child(label))
+        };
+
+        div()
+            .h(px(64.0))
+            .px(px(24.0))
+            .flex()
+            .items_center()
+            .gap(px(12.0))
+            .border_t_1()
+            .border_color(rgba(0x27272AFF))
+            .child(
+                div()
+                    .flex_1()
+                    .text_sm()
+                    .text_color(rgba(0xE4E4E7FF))
+                    .child("Discard unsaved changes?"),
+            )
+            .child(button(
+                "keep-editing",
+                "Keep Editing",
+                FocusTarget::KeepEditing,
+            ))
+            .child(button("discard-changes", "Discard", FocusTarget::Discard))
+    }
+
     fn render_content(&self, cx: &mut Context<Self>) -> impl IntoElement {
         match self.current_tab {
             SettingsTab::Keybindings => div()
                 .flex_1()
                 .overflow_hidden()
+                .flex()
+                .flex_col()
+                .children(
+                    self.current_selects()
+                        .iter()
+                        .map(|s| self.render_select(s, cx)),
+                )
                 .child(self.render_keybindings_content(cx)),
             _ => {
                 let toggles = self.current_toggles();
@@ -583,18 +1143,37 @@ impl SettingsPanel {
     }
 }
 
+/// Border color marking the control with keyboard focus.
+fn focus_ring(focused: bool) -> gpui::Rgba {
+    if focused {
+        rgba(0x3B82F6FF)
+    } else {
+        rgba(0x00000000)
+    }
+}
+
 impl Render for SettingsPanel {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if !self.visible {
             return div().id("settings-hidden");
         }
 
+        let focus_handle = self
+            .focus_handle
+            .get_or_insert_with(|| cx.focus_handle())
+            .clone();
+        if self.needs_focus {
+            window.focus(&focus_handle);
+            self.needs_focus = false;
+        }
+
         // Backdrop
         div()
             .id("settings-backdrop")
             .absolute()
             .inset_0()
             .bg(rgba(0x00000080))
+            .occlude()
             .flex()
             .items_center()
             .justify_center()
@@ -602,6 +1181,18 @@ impl Render for SettingsPanel {
                 // Panel container
                 div()
                     .id("settings-panel")
+                    .track_focus(&focus_handle)
+                    // Keep keys inside the panel while it is open.
+                    .on_key_down(cx.listener(|this, event: &KeyDownEvent, _, cx| {
+                        let keystroke = &event.keystroke;
+                        if let Some(event) =
+                            this.handle_key(&keystroke.key, keystroke.modifiers.shift)
+                        {
+                            cx.emit(event);
+                        }
+                        cx.stop_propagation();
+                        cx.notify();
+                    }))
                     .w(px(720.0))
                     .h(px(560.0))
                     .bg(rgba(0x18181BFF))
@@ -638,6 +1229,7 @@ impl Render for SettingsPanel {
                                             .child("Settings"),
                                     ),
                             )
+                            .child(self.render_search(cx))
                             .child(
                                 // Tab list
                                 div()
@@ -689,6 +1281,11 @@ impl Render for SettingsPanel {
                                             .rounded(px(6.0))
                                             .cursor_pointer()
                                             .hover(|d| d.bg(rgba(0x27272AFF)))
+                                            .border_1()
+                                            .border_color(focus_ring(
+                                                self.is_focused(&FocusTarget::Close),
+                                            ))
+                                            .on_click(Self::on_activate(FocusTarget::Close, cx))
                                             .child(
                                                 div()
                                                     .text_lg()
@@ -705,8 +1302,11 @@ impl Render for SettingsPanel {
                                     .overflow_hidden()
                                     .child(self.render_content(cx)),
                             )
+                            .when(self.confirming_discard, |d| {
+                                d.child(self.render_discard_confirmation(cx))
+                            })
                             // Footer with save button
-                            .when(self.has_changes, |d| {
+                            .when(self.has_changes && !self.confirming_discard, |d| {
                                 d.child(
                                     div()
                                         .h(px(64.0))
@@ -728,6 +1328,14 @@ impl Render for SettingsPanel {
                                                 .cursor_pointer()
                                                 .bg(rgba(0x27272AFF))
                                                 .hover(|d| d.bg(rgba(0x3F3F46FF)))
+                                                .border_1()
+                                                .border_color(focus_ring(
+                                                    self.is_focused(&FocusTarget::Cancel),
+                                                ))
+                                                .on_click(Self::on_activate(
+                                                    FocusTarget::Cancel,
+                                                    cx,
+                                                ))
                                                 .child(
                                                     div()
                                                         .text_sm()
@@ -746,6 +1354,11 @@ impl Render for SettingsPanel {
                                                 .cursor_pointer()
                                                 .bg(rgba(0x3B82F6FF))
                                                 .hover(|d| d.bg(rgba(0x2563EBFF)))
+                                                .border_1()
+                                                .border_color(focus_ring(
+                                                    self.is_focused(&FocusTarget::Save),
+                                                ))
+                                                .on_click(Self::on_activate(FocusTarget::Save, cx))
                                                 .child(
                                                     div()
                                                         .text_sm()
@@ -807,4 +1420,148 @@ mod tests {
         assert_eq!(select.options.len(), 2);
         assert_eq!(select.selected, "dark");
     }
+
+    #[test]
+    fn closing_with_unsaved_changes_asks_first() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+        assert_eq!(panel.toggle_value("sounds"), Some(false));
+
+        panel.toggle_setting("sounds");
+        assert_eq!(panel.handle_key("escape", false), None);
+        assert!(panel.is_visible());
+        assert!(panel.is_confirming_discard());
+
+        // Escape again backs out of the confirmation.
+        panel.handle_key("escape", false);
+        assert!(panel.is_visible());
+        assert!(!panel.is_confirming_discard());
+        assert_eq!(panel.toggle_value("sounds"), Some(true));
+
+        assert!(!panel.request_close());
+        assert_eq!(
+            panel.activate(&FocusTarget::Discard),
+            Some(SettingsPanelEvent::Closed)
+        );
+        assert!(!panel.is_visible());
+        assert_eq!(panel.toggle_value("sounds"), Some(false));
+    }
+
+    #[test]
+    fn closing_without_changes_closes_immediately() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+
+        assert_eq!(
+            panel.handle_key("escape", false),
+            Some(SettingsPanelEvent::Closed)
+        );
+        assert!(!panel.is_visible());
+    }
+
+    #[test]
+    fn saved_changes_survive_closing() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+        panel.set_select("theme", "light".to_string());
+
+        assert_eq!(
+            panel.activate(&FocusTarget::Save),
+            Some(SettingsPanelEvent::Saved)
+        );
+        assert!(!panel.is_visible());
+
+        panel.open();
+        assert!(panel.request_close());
+        assert_eq!(panel.select_value("theme"), Some("light"));
+    }
+
+    #[test]
+    fn tab_cycles_focus_through_controls() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+        let targets = panel.focus_targets();
+        assert_eq!(targets[0], FocusTarget::Close);
+        assert_eq!(targets[2], FocusTarget::Tab(SettingsTab::General));
+        assert_eq!(panel.focused_target(), Some(FocusTarget::Search));
+
+        panel.handle_key("tab", true);
+        panel.handle_key("tab", true);
+        assert_eq!(panel.focused_target().as_ref(), targets.last());
+        panel.handle_key("tab", false);
+        assert_eq!(panel.focused_target(), Some(FocusTarget::Close));
+
+        // Enter on a select picks its next option.
+        let default_view = FocusTarget::Select("default_view".to_string());
+        while panel.focused_target().as_ref() != Some(&default_view) {
+            panel.handle_key("tab", false);
+        }
+        panel.handle_key("enter", false);
+        assert_eq!(panel.select_value("default_view"), Some("all"));
+        assert!(panel.focus_targets().contains(&FocusTarget::Save));
+    }
+
+    #[test]
+    fn search_finds_settings_across_tabs() {
+        let panel = SettingsPanel::with_defaults();
+
+        assert_eq!(
+            panel.search("tracking"),
+            [(SettingsTab::Privacy, "block_tracking".to_string())]
+        );
+        // Descriptions match too, ignoring case.
+        let results = panel.search("OFFLINE");
+        assert!(results.contains(&(SettingsTab::Sync, "offline_mode".to_string())));
+        assert!(results.contains(&(SettingsTab::Sync, "sync_attachments".to_string())));
+        assert!(panel.search("  ").is_empty());
+    }
+
+    #[test]
+    fn typing_a_search_jumps_to_the_first_match() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+
+        for key in ["t", "h", "e", "m", "e"] {
+            panel.handle_key(key, false);
+        }
+        assert_eq!(panel.search_query(), "theme");
+        assert_eq!(panel.current_tab, SettingsTab::Appearance);
+
+        panel.handle_key("enter", false);
+        assert_eq!(
+            panel.focused_target(),
+            Some(FocusTarget::Select("theme".to_string()))
+        );
+
+        // Escape clears a search before it closes the panel.
+        panel.set_search_query("theme");
+        panel.focused = 1;
+        panel.handle_key("escape", false);
+        assert!(panel.search_query().is_empty());
+        assert!(panel.is_visible());
+    }
+
+    #[test]
+    fn shortcut_preset_is_chosen_on_the_keybindings_tab() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+        panel.set_tab(SettingsTab::Keybindings);
+
+        let preset = FocusTarget::Select("keybinding_preset".to_string());
+        assert!(panel.focus_targets().contains(&preset));
+        panel.activate(&preset);
+        assert_eq!(panel.select_value("keybinding_preset"), Some("gmail"));
+    }
+
+    #[test]
+    fn activating_a_select_without_options_does_nothing() {
+        let mut panel = SettingsPanel::with_defaults();
+        panel.open();
+        panel.selects[0]
+            .1
+            .push(SelectSetting::new("empty", "Empty", Vec::new(), ""));
+
+        panel.activate(&FocusTarget::Select("empty".to_string()));
+        assert_eq!(panel.select_value("empty"), Some(""));
+    }
 }
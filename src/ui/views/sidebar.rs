@@ -9,7 +9,7 @@ use gpui::{
 
 use crate::app::ViewType;
 use crate::domain::{AccountId, LabelId};
-use crate::ui::theme::ThemeColors;
+use crate::ui::theme::{parse_hex_color, ThemeColors};
 
 /// Callback type for navigation.
 type OnNavigateCallback = Box<dyn Fn(ViewType) + 'static>;
@@ -169,6 +169,12 @@ impl Sidebar {
         };
         let hover_bg = self.colors.surface_elevated;
 
+        let dot_color = label
+            .color
+            .as_deref()
+            .and_then(parse_hex_color)
+            .unwrap_or_else(|| self.colors.color_for(&label.id.0));
+
         let label_id = label.id.clone();
         let click_handler = cx.listener(move |this, _event: &ClickEvent, _window, _cx| {
             let view = ViewType::Label(label_id.clone());
@@ -193,7 +199,7 @@ impl Sidebar {
                     .flex()
                     .items_center()
                     .gap(px(8.0))
-                    .child(div().size(px(8.0)).rounded_full().bg(self.colors.accent))
+                    .child(div().size(px(8.0)).rounded_full().bg(dot_color))
                     .child(
                         div()
                             .text_color(self.colors.text_primary)
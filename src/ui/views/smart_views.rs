@@ -6,7 +6,7 @@
 //! - Waiting For: sent emails awaiting replies
 //! - Newsletters: promotional/bulk mail
 //! - VIP: important contacts
-//! - Follow Up: flagged for later action
+//! - Needs Follow-up: flagged for later action, or sent with no reply in time
 
 use chrono::{DateTime, Duration, Utc};
 use gpui::{
@@ -27,7 +27,7 @@ pub enum SmartViewType {
     Newsletters,
     /// VIP/important contacts.
     Vip,
-    /// Flagged for follow-up.
+    /// Flagged for follow-up, or sent with no reply before its reminder.
     FollowUp,
     /// Recently read but unactioned.
     RecentlyViewed,
@@ -56,7 +56,7 @@ impl SmartViewType {
             SmartViewType::WaitingFor => "Waiting For",
             SmartViewType::Newsletters => "Newsletters",
             SmartViewType::Vip => "VIP",
-            SmartViewType::FollowUp => "Follow Up",
+            SmartViewType::FollowUp => "Needs Follow-up",
             SmartViewType::RecentlyViewed => "Recently Viewed",
             SmartViewType::Attachments => "Attachments",
         }
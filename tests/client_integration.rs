@@ -0,0 +1,1022 @@
+//! End-to-end tests for the headless client.
+//!
+//! These drive [`MarginClient`] over an in-memory database, without a window,
+//! the way a script would.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use heap::client::LocalEmbeddings;
+use heap::config::PrivacySettings;
+use heap::domain::{
+    Account, AccountId, Address, Attachment, Contact, Email, EmailId, FolderMapping, Label,
+    LabelId, MessageId, ProviderConfig, ProviderType, Thread, ThreadId, ThreadSort, ThreadSummary,
+    UnsubscribeInfo,
+};
+use heap::embedding::{self, Embedding, VectorStore};
+use heap::providers::email::SearchCriteria;
+use heap::services::{
+    AiService, AiSettings, Draft, EmailProvider, EmbeddingEngine, ExportFormat, OutgoingEmail,
+    Pagination, PendingChangeType, SearchFolder, SearchQuery, SearchSource, UnsubscribeOutcome,
+    ViewType,
+};
+use heap::storage::queries::{accounts, blobs, contacts, emails, labels, threads};
+use heap::{MarginClient, ShutdownReport};
+
+fn account() -> Account {
+    Account {
+        id: AccountId::from("account-1"),
+        email: "me@example.com".to_string(),
+        display_name: None,
+        provider_type: ProviderType::Gmail,
+        provider_config: ProviderConfig::Gmail {},
+        sync_enabled: false,
+        sync_interval: Duration::from_secs(300),
+        signature: None,
+        signature_html: None,
+        folder_mapping: FolderMapping::default(),
+        aliases: vec![],
+    }
+}
+
+fn email(thread: &str, from: &str, subject: &str, body: &str) -> Email {
+    Email {
+        id: EmailId::from(format!("{}-email", thread)),
+        account_id: AccountId::from("account-1"),
+        thread_id: ThreadId::from(thread),
+        message_id: MessageId::from(format!("<{}@example.com>", thread)),
+        in_reply_to: None,
+        references: vec![],
+        from: Address::new(from),
+        to: vec![Address::new("me@example.com")],
+        cc: vec![],
+        bcc: vec![],
+        subject: Some(subject.to_string()),
+        body_text: Some(body.to_string()),
+        body_html: None,
+        snippet: body.to_string(),
+        date: Utc::now(),
+        is_read: false,
+        is_starred: false,
+        is_draft: false,
+        labels: vec![LabelId::from("INBOX")],
+        attachments: vec![],
+        unsubscribe: None,
+        read_receipt_to: None,
+    }
+}
+
+/// Summary of a single-message inbox thread.
+fn summary(email: &Email) -> ThreadSummary {
+    ThreadSummary {
+        id: email.thread_id.clone(),
+        account_id: email.account_id.clone(),
+        subject: email.subject.clone(),
+        snippet: email.snippet.clone(),
+        from: email.from.clone(),
+        last_message_date: email.date,
+        message_count: 1,
+        unread_count: 1,
+        is_starred: false,
+        labels: vec![LabelId::from("INBOX")],
+        muted: false,
+        watched: false,
+    }
+}
+
+async fn insert_thread(client: &MarginClient, email: Email) {
+    let db = client.storage().db();
+    threads::upsert(db, &summary(&email)).await.unwrap();
+    emails::insert(db, &email).await.unwrap();
+}
+
+/// Provider that records the changes pushed to it.
+#[derive(Default)]
+struct RecordingProvider {
+    summaries: Mutex<Vec<ThreadSummary>>,
+    threads: Mutex<Vec<Thread>>,
+    archived: Mutex<Vec<String>>,
+    deleted: Mutex<Vec<String>>,
+    disconnected: Mutex<bool>,
+    /// Attachment content by attachment ID.
+    attachments: Mutex<Vec<(String, Vec<u8>)>>,
+    downloads: Mutex<Vec<String>>,
+    /// Labels applied, as (thread ID, label).
+    labeled: Mutex<Vec<(String, String)>>,
+}
+
+#[async_trait::async_trait]
+impl EmailProvider for RecordingProvider {
+    fn provider_type(&self) -> &str {
+        "recording"
+    }
+
+    async fn fetch_threads(
+        &self,
+        _folder: &str,
+        _pagination: Pagination,
+    ) -> anyhow::Result<Vec<ThreadSummary>> {
+        Ok(self.summaries.lock().unwrap().clone())
+    }
+
+    async fn search(
+        &self,
+        _folder: &str,
+        criteria: &SearchCriteria,
+    ) -> anyhow::Result<Vec<ThreadSummary>> {
+        let text = criteria.text.clone().unwrap_or_default();
+        let summaries = self.summaries.lock().unwrap();
+        Ok(summaries
+            .iter()
+            .filter(|summary| summary.subject.as_deref().unwrap_or("").contains(&text))
+            .cloned()
+            .collect())
+    }
+
+    async fn fetch_thread(&self, thread_id: &str) -> anyhow::Result<Thread> {
+        let threads = self.threads.lock().unwrap();
+        match threads.iter().find(|t| t.id.0 == thread_id) {
+            Some(thread) => Ok(thread.clone()),
+            None => anyhow::bail!("Thread not found: {}", thread_id),
+        }
+    }
+
+    async fn fetch_attachment(
+        &self,
+        _email_id: &str,
+        attachment_id: &str,
+        out: &mut (dyn std::io::Write + Send),
+    ) -> anyhow::Result<u64> {
+        self.downloads
+            .lock()
+            .unwrap()
+            .push(attachment_id.to_string());
+        let attachments = self.attachments.lock().unwrap();
+        match attachments.iter().find(|(id, _)| id == attachment_id) {
+            Some((_, content)) => {
+                out.write_all(content)?;
+                Ok(content.len() as u64)
+            }
+            None => anyhow::bail!("Attachment not found: {}", attachment_id),
+        }
+    }
+
+    async fn send_email(&self, _email: &OutgoingEmail) -> anyhow::Result<String> {
+        anyhow::bail!("Sending is not supported")
+    }
+
+    async fn archive(&self, thread_ids: &[String]) -> anyhow::Result<()> {
+        self.archived.lock().unwrap().extend_from_slice(thread_ids);
+        Ok(())
+    }
+
+    async fn trash(&self, _thread_ids: &[String]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn delete_permanently(&self, thread_ids: &[String]) -> anyhow::Result<()> {
+        self.deleted.lock().unwrap().extend_from_slice(thread_ids);
+        Ok(())
+    }
+
+    async fn report_spam(&self, _thread_ids: &[String]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn star(&self, _thread_id: &str, _starred: bool) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn mark_read(&self, _thread_id: &str, _read: bool) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn apply_label(&self, thread_id: &str, label: &str) -> anyhow::Result<()> {
+        self.labeled
+            .lock()
+            .unwrap()
+            .push((thread_id.to_string(), label.to_string()));
+        Ok(())
+    }
+
+    async fn remove_label(&self, _thread_id: &str, _label: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        *self.disconnected.lock().unwrap() = true;
+        Ok(())
+    }
+}
+
+fn draft() -> Draft {
+    Draft {
+        id: None,
+        account_id: AccountId::from("account-1"),
+        from: None,
+        reply_to_thread_id: None,
+        reply_to_message_id: None,
+        references: vec![],
+        to: vec![Address::new("alice@example.com")],
+        cc: vec![],
+        bcc: vec![],
+        subject: "Friday".to_string(),
+        body_markdown: "Lunch at **noon**?".to_string(),
+        body_html: None,
+        signature_html: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        idempotency_key: "send-1".to_string(),
+        request_read_receipt: true,
+    }
+}
+
+async fn inbox_ids(client: &MarginClient) -> Vec<ThreadId> {
+    client
+        .list_threads(
+            &AccountId::from("account-1"),
+            ViewType::Inbox,
+            Pagination::default(),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|thread| thread.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn search_and_archive_newsletters() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    insert_thread(
+        &client,
+        email(
+            "newsletter",
+            "news@example.com",
+            "Weekly digest",
+            "This week's top stories",
+        ),
+    )
+    .await;
+    insert_thread(
+        &client,
+        email(
+            "lunch",
+            "alice@example.com",
+            "Lunch?",
+            "Are you free on Friday?",
+        ),
+    )
+    .await;
+    assert_eq!(inbox_ids(&client).await.len(), 2);
+
+    let query = SearchQuery::new("digest").with_folder(SearchFolder::Inbox);
+    let results = client.search(query.clone()).await.unwrap();
+    let found: Vec<ThreadId> = results.hits.into_iter().map(|hit| hit.thread_id).collect();
+    assert_eq!(found, vec![ThreadId::from("newsletter")]);
+
+    client.archive(&found).await.unwrap();
+
+    assert_eq!(inbox_ids(&client).await, vec![ThreadId::from("lunch")]);
+    let archived = client
+        .list_threads(
+            &AccountId::from("account-1"),
+            ViewType::Archive,
+            Pagination::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].id, ThreadId::from("newsletter"));
+    assert!(client.search(query).await.unwrap().hits.is_empty());
+}
+
+#[tokio::test]
+async fn list_threads_in_priority_order() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let mut newsletter = email("newsletter", "news@example.com", "Digest", "Top stories");
+    newsletter.to = vec![Address::new("list@example.com")];
+    insert_thread(&client, newsletter).await;
+    let mut direct = email("direct", "alice@example.com", "Lunch?", "Free on Friday?");
+    direct.date = Utc::now() - chrono::Duration::days(1);
+    insert_thread(&client, direct).await;
+
+    let ids = |threads: Vec<ThreadSummary>| -> Vec<ThreadId> {
+        threads.into_iter().map(|thread| thread.id).collect()
+    };
+    let account_id = AccountId::from("account-1");
+    let list = |sort| {
+        client.list_threads_sorted(&account_id, ViewType::Inbox, sort, Pagination::default())
+    };
+    assert_eq!(
+        ids(list(ThreadSort::DateDesc).await.unwrap()),
+        vec![ThreadId::from("newsletter"), ThreadId::from("direct")]
+    );
+    assert_eq!(
+        ids(list(ThreadSort::PriorityFirst).await.unwrap()),
+        vec![ThreadId::from("direct"), ThreadId::from("newsletter")]
+    );
+}
+
+#[tokio::test]
+async fn summarize_requires_an_ai_service() {
+    let client = MarginClient::in_memory().await.unwrap();
+    assert!(client
+        .summarize(&ThreadId::from("newsletter"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn translate_requires_an_ai_service() {
+    let client = MarginClient::in_memory().await.unwrap();
+    assert!(client
+        .translate(
+            &ThreadId::from("newsletter"),
+            &EmailId::from("newsletter-email"),
+            "en"
+        )
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn shutdown_saves_drafts_and_flushes_queued_changes() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let account_id = AccountId::from("account-1");
+
+    // Changes queued while offline wait for a provider.
+    client
+        .queue_change(
+            account_id.clone(),
+            PendingChangeType::Archive {
+                thread_ids: vec!["newsletter".to_string()],
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(client.flush_pending_changes().await.unwrap(), 0);
+    assert_eq!(client.pending_changes().await.unwrap().len(), 1);
+
+    let draft_id = client.edit_draft(draft()).await;
+    let provider = Arc::new(RecordingProvider::default());
+    client
+        .register_provider(account_id.clone(), provider.clone())
+        .await;
+
+    let report = client.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(
+        report,
+        ShutdownReport {
+            drafts_saved: 1,
+            changes_flushed: 1,
+            changes_remaining: 0,
+            timed_out: false,
+        }
+    );
+    assert_eq!(*provider.archived.lock().unwrap(), vec!["newsletter"]);
+    assert!(*provider.disconnected.lock().unwrap());
+    assert!(client.pending_changes().await.unwrap().is_empty());
+
+    // Once it is no longer open, the draft is read back from the database.
+    client.close_draft(&draft_id).await;
+    let saved = client.load_draft(&draft_id).await.unwrap().unwrap();
+    assert_eq!(saved.account_id, account_id);
+    assert_eq!(saved.subject, "Friday");
+    assert_eq!(saved.body_markdown, "Lunch at **noon**?");
+    assert_eq!(saved.to, vec![Address::new("alice@example.com")]);
+    assert_eq!(saved.idempotency_key, "send-1");
+    assert!(saved.request_read_receipt);
+}
+
+#[tokio::test]
+async fn removing_an_account_disconnects_its_provider() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let account_id = AccountId::from("account-1");
+    let provider = Arc::new(RecordingProvider::default());
+    client
+        .register_provider(account_id.clone(), provider.clone())
+        .await;
+
+    client.remove_account(&account_id).await.unwrap();
+
+    assert!(*provider.disconnected.lock().unwrap());
+    assert!(client.accounts().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn removing_an_account_deletes_its_mail_and_embeddings() {
+    let mut vectors = VectorStore::new();
+    vectors
+        .insert(&EmailId::from("t1-email"), Embedding::new(vec![1.0, 0.0]))
+        .unwrap();
+    let engine = embedding::EmbeddingEngine::with_defaults(vectors);
+    let embeddings = Arc::new(LocalEmbeddings::new(engine));
+    let ai = Arc::new(AiService::new(AiSettings::default()));
+    ai.set_embedding_engine(embeddings.clone()).await;
+    let client = MarginClient::in_memory()
+        .await
+        .unwrap()
+        .with_ai_service(ai)
+        .await;
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    insert_thread(&client, email("t1", "alice@example.com", "Lunch", "Noon?")).await;
+
+    client
+        .remove_account(&AccountId::from("account-1"))
+        .await
+        .unwrap();
+
+    assert!(inbox_ids(&client).await.is_empty());
+    assert!(embeddings
+        .stored_embedding(&EmailId::from("t1-email"))
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn evicted_bodies_are_stored_again_when_opened() {
+    let client = MarginClient::in_memory().await.unwrap();
+    let db = client.storage().db();
+    accounts::insert(db, &account()).await.unwrap();
+    let mut old = email("t1", "alice@example.com", "Lunch?", "Are you free?");
+    old.date = Utc::now() - chrono::Duration::days(200);
+    insert_thread(&client, old.clone()).await;
+    let evicted = client
+        .storage()
+        .evict_bodies(chrono::Duration::days(90))
+        .await
+        .unwrap();
+    assert_eq!(evicted, 1);
+    let provider = Arc::new(RecordingProvider::default());
+    provider.threads.lock().unwrap().push(Thread {
+        id: old.thread_id.clone(),
+        account_id: old.account_id.clone(),
+        subject: old.subject.clone(),
+        snippet: old.snippet.clone(),
+        participants: vec![old.from.clone()],
+        last_message_date: old.date,
+        unread_count: 1,
+        is_starred: false,
+        labels: old.labels.clone(),
+        messages: vec![old.clone()],
+    });
+    client
+        .register_provider(AccountId::from("account-1"), provider)
+        .await;
+
+    client
+        .email_service()
+        .get_thread(&old.thread_id)
+        .await
+        .unwrap();
+
+    let stored = emails::get_by_id(db, &old.id).await.unwrap().unwrap();
+    assert_eq!(stored.body_text.as_deref(), Some("Are you free?"));
+}
+
+#[tokio::test]
+async fn expired_trash_is_purged_locally_and_at_the_provider() {
+    let client = MarginClient::in_memory().await.unwrap();
+    let db = client.storage().db();
+    accounts::insert(db, &account()).await.unwrap();
+    // Every message is old; only how long ago each was trashed differs
+    for (thread, trashed_days, starred) in
+        [("old", 40, false), ("recent", 5, false), ("kept", 40, true)]
+    {
+        let mut trashed = email(thread, "alice@example.com", "Lunch?", "Are you free?");
+        trashed.date = Utc::now() - chrono::Duration::days(90);
+        insert_thread(&client, trashed).await;
+        let thread_id = ThreadId::from(thread);
+        threads::set_labels(db, &thread_id, &[LabelId::from("TRASH")])
+            .await
+            .unwrap();
+        threads::set_starred(db, &thread_id, starred).await.unwrap();
+        let trashed_at = (Utc::now() - chrono::Duration::days(trashed_days)).to_rfc3339();
+        db.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE threads SET trashed_at = ?1 WHERE id = ?2",
+                [trashed_at.as_str(), thread],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
+    let provider = Arc::new(RecordingProvider::default());
+    client
+        .register_provider(AccountId::from("account-1"), provider.clone())
+        .await;
+
+    let report = client
+        .email_service()
+        .purge_expired(Utc::now())
+        .await
+        .unwrap();
+
+    assert_eq!(report.purged, vec![ThreadId::from("old")]);
+    assert_eq!(report.kept_starred, vec![ThreadId::from("kept")]);
+    assert_eq!(*provider.deleted.lock().unwrap(), vec!["old"]);
+    assert!(threads::get_by_id(db, &ThreadId::from("old"))
+        .await
+        .unwrap()
+        .is_none());
+    assert!(emails::get_by_thread(db, &ThreadId::from("old"))
+        .await
+        .unwrap()
+        .is_empty());
+    for survivor in ["recent", "kept"] {
+        assert!(threads::get_by_id(db, &ThreadId::from(survivor))
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
+
+#[tokio::test]
+async fn importing_the_same_mbox_twice_skips_stored_messages() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("archive.mbox");
+    std::fs::write(
+        &path,
+        "From alice@example.com Mon Jan  6 09:00:00 2025\n\
+         From: alice@example.com\n\
+         To: me@example.com\n\
+         Subject: Lunch\n\
+         Message-ID: <lunch@example.com>\n\
+         Date: Mon, 6 Jan 2025 09:00:00 +0000\n\
+         \n\
+         Noon?\n",
+    )
+    .unwrap();
+    let account_id = AccountId::from("account-1");
+
+    let first = client
+        .email_service()
+        .import_mbox(&account_id, &path, |_| {})
+        .await
+        .unwrap();
+    let second = client
+        .email_service()
+        .import_mbox(&account_id, &path, |_| {})
+        .await
+        .unwrap();
+
+    assert_eq!((first.imported, first.duplicates), (1, 0));
+    assert_eq!((second.imported, second.duplicates), (0, 1));
+}
+
+/// An mbox message from a newsletter with a `logo.png` attachment.
+fn newsletter(id: &str) -> String {
+    format!(
+        "From news@example.com Mon Jan  6 09:00:00 2025\n\
+         From: news@example.com\n\
+         To: me@example.com\n\
+         Subject: Issue {id}\n\
+         Message-ID: <{id}@example.com>\n\
+         Content-Type: multipart/mixed; boundary=\"b\"\n\
+         \n\
+         --b\n\
+         Content-Type: text/plain\n\
+         \n\
+         News\n\
+         --b\n\
+         Content-Type: image/png\n\
+         Content-Disposition: attachment; filename=\"logo.png\"\n\
+         \n\
+         LOGO\n\
+         --b--\n"
+    )
+}
+
+#[tokio::test]
+async fn imported_attachments_are_stored_once_per_content() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("newsletters.mbox");
+    std::fs::write(&path, newsletter("1") + &newsletter("2")).unwrap();
+
+    client
+        .email_service()
+        .import_mbox(&AccountId::from("account-1"), &path, |_| {})
+        .await
+        .unwrap();
+
+    let hash = blobs::hash(b"LOGO");
+    let db = client.storage().db();
+    assert_eq!(blobs::ref_count(db, &hash).await.unwrap(), Some(2));
+    assert_eq!(
+        client.storage().load_blob(&hash).await.unwrap().as_deref(),
+        Some(&b"LOGO"[..])
+    );
+    let stored = emails::get_by_account(db, &AccountId::from("account-1"), 10, 0)
+        .await
+        .unwrap();
+    let thread = emails::get_by_thread(db, &stored[0].thread_id)
+        .await
+        .unwrap();
+    assert_eq!(thread[0].attachments[0].filename, "logo.png");
+    assert_eq!(
+        thread[0].attachments[0].content_hash.as_deref(),
+        Some(hash.as_str())
+    );
+}
+
+#[tokio::test]
+async fn downloaded_images_are_previewed_as_thumbnails() {
+    use base64::Engine;
+
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let photo = image::RgbaImage::from_pixel(400, 200, image::Rgba([40, 120, 200, 255]));
+    let mut png = Vec::new();
+    photo
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let message = format!(
+        "From alice@example.com Mon Jan  6 09:00:00 2025\n\
+         From: alice@example.com\n\
+         To: me@example.com\n\
+         Subject: Holiday\n\
+         Message-ID: <holiday@example.com>\n\
+         Content-Type: multipart/mixed; boundary=\"b\"\n\
+         \n\
+         --b\n\
+         Content-Type: text/plain\n\
+         \n\
+         Photo attached\n\
+         --b\n\
+         Content-Type: image/png\n\
+         Content-Disposition: attachment; filename=\"beach.png\"\n\
+         Content-Transfer-Encoding: base64\n\
+         \n\
+         {}\n\
+         --b--\n",
+        base64::engine::general_purpose::STANDARD.encode(&png)
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("holiday.mbox");
+    std::fs::write(&path, message).unwrap();
+    client
+        .email_service()
+        .import_mbox(&AccountId::from("account-1"), &path, |_| {})
+        .await
+        .unwrap();
+
+    let db = client.storage().db();
+    let stored = emails::get_by_account(db, &AccountId::from("account-1"), 10, 0)
+        .await
+        .unwrap();
+    let thread = client.get_thread(&stored[0].thread_id).await.unwrap();
+    let email = &thread.messages[0];
+    let thumbnails = client.thumbnails(email, 96).await;
+
+    let thumbnail = image::load_from_memory(&thumbnails[&email.attachments[0].id]).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (96, 48));
+}
+
+#[tokio::test]
+async fn attachments_are_downloaded_once_to_preview_them() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let mut holiday = email("holiday", "alice@example.com", "Holiday", "Photo attached");
+    holiday.attachments = vec![Attachment {
+        id: "holiday:2".to_string(),
+        filename: "beach.png".to_string(),
+        content_type: "image/png".to_string(),
+        size_bytes: 0,
+        is_inline: false,
+        content_hash: None,
+        content_id: None,
+    }];
+    insert_thread(&client, holiday).await;
+    let photo = image::RgbaImage::from_pixel(400, 200, image::Rgba([40, 120, 200, 255]));
+    let mut png = Vec::new();
+    photo
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let provider = Arc::new(RecordingProvider::default());
+    provider
+        .attachments
+        .lock()
+        .unwrap()
+        .push(("holiday:2".to_string(), png));
+    client
+        .register_provider(AccountId::from("account-1"), provider.clone())
+        .await;
+
+    for _ in 0..2 {
+        let thread = client.get_thread(&ThreadId::from("holiday")).await.unwrap();
+        let thumbnails = client.thumbnails(&thread.messages[0], 96).await;
+        let thumbnail = image::load_from_memory(&thumbnails["holiday:2"]).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (96, 48));
+    }
+    assert_eq!(*provider.downloads.lock().unwrap(), vec!["holiday:2"]);
+}
+
+#[tokio::test]
+async fn exports_include_the_accounts_labels_contacts_and_attachments() {
+    let client = MarginClient::in_memory().await.unwrap();
+    let db = client.storage().db();
+    accounts::insert(db, &account()).await.unwrap();
+    db.with_conn(|conn| {
+        labels::insert(
+            conn,
+            &Label {
+                id: LabelId::from("account-1/work"),
+                account_id: AccountId::from("account-1"),
+                name: "Work".to_string(),
+                color: None,
+                is_system: false,
+                provider_id: None,
+            },
+        )?;
+        contacts::upsert(conn, &Contact::new("news@example.com"))?;
+        contacts::upsert(conn, &Contact::new("stranger@example.com"))?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mbox = dir.path().join("newsletters.mbox");
+    std::fs::write(&mbox, newsletter("1")).unwrap();
+    let account_id = AccountId::from("account-1");
+    client
+        .email_service()
+        .import_mbox(&account_id, &mbox, |_| {})
+        .await
+        .unwrap();
+
+    let out = dir.path().join("export");
+    let summary = client
+        .email_service()
+        .export_account(&account_id, ExportFormat::Json, &out)
+        .await
+        .unwrap();
+
+    assert_eq!(summary.attachments, 1);
+    let export: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.join("account.json")).unwrap()).unwrap();
+    assert_eq!(export["labels"][0]["name"], "Work");
+    let contacts = export["contacts"].as_array().unwrap();
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0]["email"], "news@example.com");
+}
+
+#[tokio::test]
+async fn inbox_previews_follow_the_newest_unread_message() {
+    let client = MarginClient::in_memory().await.unwrap();
+    let db = client.storage().db();
+    accounts::insert(db, &account()).await.unwrap();
+    let mut question = email("lunch", "alice@example.com", "Lunch", "Noon?");
+    question.date = Utc::now() - chrono::Duration::hours(1);
+    insert_thread(&client, question).await;
+    let mut reply = email("lunch", "me@example.com", "Re: Lunch", "Sounds good");
+    reply.id = EmailId::from("lunch-reply");
+    reply.message_id = MessageId::from("<lunch-reply@example.com>");
+    reply.is_read = true;
+    emails::insert(db, &reply).await.unwrap();
+    threads::refresh_snippet(db, &reply.thread_id)
+        .await
+        .unwrap();
+
+    // The provider previews the thread with its newest message.
+    let account_id = AccountId::from("account-1");
+    let mut summary = threads::get_by_id(db, &reply.thread_id)
+        .await
+        .unwrap()
+        .unwrap();
+    summary.snippet = "Sounds good".to_string();
+    let provider = Arc::new(RecordingProvider::default());
+    provider.summaries.lock().unwrap().push(summary);
+    client
+        .register_provider(account_id.clone(), provider.clone())
+        .await;
+    let (client, account_id) = (&client, &account_id);
+    let preview = || async move {
+        client
+            .email_service()
+            .fetch_threads(account_id, ViewType::Inbox, Pagination::default())
+            .await
+            .unwrap()[0]
+            .snippet
+            .clone()
+    };
+
+    assert_eq!(preview().await, "Noon?");
+
+    client
+        .email_service()
+        .mark_read(&reply.thread_id, true)
+        .await
+        .unwrap();
+    assert_eq!(preview().await, "Sounds good");
+}
+
+#[tokio::test]
+async fn followup_reminders_survive_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.db");
+    let thread_id = ThreadId::from("lunch");
+    let due_at = {
+        let client = MarginClient::open(&path).await.unwrap();
+        accounts::insert(client.storage().db(), &account())
+            .await
+            .unwrap();
+        insert_thread(
+            &client,
+            email("lunch", "me@example.com", "Lunch?", "Are you free?"),
+        )
+        .await;
+        client
+            .email_service()
+            .set_followup(&thread_id, Duration::from_secs(3600))
+            .await
+            .unwrap()
+    };
+
+    let client = MarginClient::open(&path).await.unwrap();
+    assert_eq!(
+        client.email_service().followup_due_at(&thread_id).await,
+        None
+    );
+    client.start_followup_checks().await;
+
+    let email_service = client.email_service();
+    assert_eq!(
+        email_service.followup_due_at(&thread_id).await,
+        Some(due_at)
+    );
+    let due = email_service.check_followups(due_at).await;
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].thread_id, thread_id);
+    // Reported reminders are forgotten in storage too.
+    assert_eq!(email_service.load_followups().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn privacy_settings_and_unsubscribes_reach_the_email_service() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    let mut newsletter = email("news", "news@example.com", "Weekly", "This week...");
+    newsletter.read_receipt_to = Some(Address::new("news@example.com"));
+    newsletter.unsubscribe = UnsubscribeInfo::parse(
+        "<https://example.com/u/1>",
+        Some("List-Unsubscribe=One-Click"),
+    );
+    let email_service = client.email_service();
+    assert!(!email_service.should_prompt_read_receipt(&newsletter));
+
+    client.apply_privacy(&PrivacySettings {
+        read_receipts_enabled: true,
+        ..PrivacySettings::default()
+    });
+
+    assert!(email_service.should_prompt_read_receipt(&newsletter));
+    // External requests are still off, so the user is sent to the page.
+    let page = UnsubscribeOutcome::OpenUrl("https://example.com/u/1".to_string());
+    assert_eq!(email_service.unsubscribe(&newsletter).await.unwrap(), page);
+    assert_eq!(
+        email_service
+            .unsubscribe_outcome(&newsletter)
+            .await
+            .unwrap(),
+        Some(page)
+    );
+}
+
+#[tokio::test]
+async fn connected_accounts_are_searched_on_the_server() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    insert_thread(
+        &client,
+        email("lunch", "alice@example.com", "Lunch?", "Are you free?"),
+    )
+    .await;
+
+    // The provider has an older thread that was never downloaded.
+    let account_id = AccountId::from("account-1");
+    let provider = Arc::new(RecordingProvider::default());
+    provider.summaries.lock().unwrap().push(summary(&email(
+        "invoice",
+        "billing@example.com",
+        "Invoice 2019",
+        "Attached.",
+    )));
+    client
+        .register_provider(account_id.clone(), provider.clone())
+        .await;
+
+    let query = SearchQuery::new("Invoice").with_accounts(vec![account_id]);
+    let results = client.search(query).await.unwrap();
+
+    assert_eq!(results.hits.len(), 1);
+    assert_eq!(results.hits[0].thread_id, ThreadId::from("invoice"));
+    assert_eq!(results.hits[0].source, SearchSource::Server);
+}
+
+#[tokio::test]
+async fn undoing_an_archive_returns_the_thread_to_the_inbox_at_the_provider() {
+    let client = MarginClient::in_memory().await.unwrap();
+    accounts::insert(client.storage().db(), &account())
+        .await
+        .unwrap();
+    insert_thread(
+        &client,
+        email("lunch", "alice@example.com", "Lunch?", "Are you free?"),
+    )
+    .await;
+    let provider = Arc::new(RecordingProvider::default());
+    client
+        .register_provider(AccountId::from("account-1"), provider.clone())
+        .await;
+
+    let thread_id = ThreadId::from("lunch");
+    let inbox = LabelId::from("INBOX");
+    let labels = || async {
+        client
+            .threads()
+            .get_thread_summary(&thread_id)
+            .await
+            .unwrap()
+            .labels
+    };
+    client.threads().archive(&thread_id).await.unwrap();
+    assert!(!labels().await.contains(&inbox));
+    assert_eq!(*provider.archived.lock().unwrap(), vec!["lunch"]);
+
+    assert!(client.undo().await.unwrap().is_some());
+    assert!(labels().await.contains(&inbox));
+    assert_eq!(
+        *provider.labeled.lock().unwrap(),
+        vec![("lunch".to_string(), "INBOX".to_string())]
+    );
+    assert!(client.undo().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn labels_created_by_name_belong_to_their_account() {
+    let client = MarginClient::in_memory().await.unwrap();
+    let db = client.storage().db();
+    let other = Account {
+        id: AccountId::from("account-2"),
+        email: "me@work.example.com".to_string(),
+        ..account()
+    };
+    accounts::insert(db, &account()).await.unwrap();
+    accounts::insert(db, &other).await.unwrap();
+
+    let work = client
+        .ensure_label(&AccountId::from("account-1"), "Work")
+        .await
+        .unwrap();
+    let other_work = client.ensure_label(&other.id, "Work").await.unwrap();
+    assert_ne!(work.id, other_work.id);
+    assert_eq!(other_work.account_id, other.id);
+
+    // Asking again, in any case, finds the label instead of adding one.
+    let again = client
+        .ensure_label(&AccountId::from("account-1"), "work")
+        .await
+        .unwrap();
+    assert_eq!(again.id, work.id);
+    let names: Vec<String> = client
+        .labels(&other.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|label| label.name)
+        .collect();
+    assert_eq!(names, vec!["Work"]);
+}
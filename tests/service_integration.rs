@@ -76,7 +76,7 @@ fn system_labels_are_defined() {
 #[test]
 fn snooze_duration_later_today() {
     let now = chrono::Utc::now();
-    let wake_time = SnoozeDuration::LaterToday.wake_time();
+    let wake_time = SnoozeDuration::LaterToday.wake_time().unwrap();
 
     assert!(wake_time > now);
 }
@@ -84,7 +84,7 @@ fn snooze_duration_later_today() {
 #[test]
 fn snooze_duration_tomorrow_is_future() {
     let now = chrono::Utc::now();
-    let wake_time = SnoozeDuration::Tomorrow.wake_time();
+    let wake_time = SnoozeDuration::Tomorrow.wake_time().unwrap();
 
     assert!(wake_time > now);
 }
@@ -92,7 +92,7 @@ fn snooze_duration_tomorrow_is_future() {
 #[test]
 fn snooze_duration_next_week_is_future() {
     let now = chrono::Utc::now();
-    let wake_time = SnoozeDuration::NextWeek.wake_time();
+    let wake_time = SnoozeDuration::NextWeek.wake_time().unwrap();
 
     assert!(wake_time > now);
     let days_diff = (wake_time.date_naive() - now.date_naive()).num_days();
@@ -103,7 +103,7 @@ fn snooze_duration_next_week_is_future() {
 #[test]
 fn snooze_duration_weekend_is_future() {
     let now = chrono::Utc::now();
-    let wake_time = SnoozeDuration::ThisWeekend.wake_time();
+    let wake_time = SnoozeDuration::ThisWeekend.wake_time().unwrap();
 
     assert!(wake_time > now);
 }
@@ -111,7 +111,7 @@ fn snooze_duration_weekend_is_future() {
 #[test]
 fn snooze_duration_custom() {
     let future = chrono::Utc::now() + chrono::Duration::hours(5);
-    let wake_time = SnoozeDuration::Custom(future).wake_time();
+    let wake_time = SnoozeDuration::Custom(future).wake_time().unwrap();
 
     assert_eq!(wake_time, future);
 }